@@ -0,0 +1,138 @@
+//! Producer Channels
+//!
+//! Sends from the block producer to the rest of the node, with their counters.
+//! Submitted transactions enter the pool directly, so the transaction
+//! broadcast only announces them and having no listener yet, as during
+//! startup, is not an error. A block send that finds the consumer's channel
+//! full is retried `BLOCK_SEND_ATTEMPTS` times, waiting twice as long before
+//! each retry starting from `BLOCK_SEND_INITIAL_BACKOFF_MS`: production is
+//! held back for at most 150 ms, after which the block is dropped and counted
+//! rather than wedging the producer behind a consumer that stopped reading.
+
+use runtime::channel_metrics::ChannelMetrics;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::{broadcast, mpsc};
+use tokio::time::{self, Duration};
+
+/// Tries a block send makes before dropping the block
+pub const BLOCK_SEND_ATTEMPTS: u32 = 5;
+
+/// Wait before the first retry of a block send; each later retry waits twice as long
+pub const BLOCK_SEND_INITIAL_BACKOFF_MS: u64 = 10;
+
+/// Counters behind `ChannelMetrics`
+#[derive(Debug, Default)]
+pub struct ChannelCounters {
+    transactions_announced: AtomicU64,
+    transactions_unannounced: AtomicU64,
+    blocks_delivered: AtomicU64,
+    block_send_retries: AtomicU64,
+    blocks_dropped: AtomicU64,
+}
+
+impl ChannelCounters {
+    /// Gets the counters' current values
+    pub fn metrics(&self) -> ChannelMetrics {
+        ChannelMetrics {
+            transactions_announced: self.transactions_announced.load(Ordering::Relaxed),
+            transactions_unannounced: self.transactions_unannounced.load(Ordering::Relaxed),
+            blocks_delivered: self.blocks_delivered.load(Ordering::Relaxed),
+            block_send_retries: self.block_send_retries.load(Ordering::Relaxed),
+            blocks_dropped: self.blocks_dropped.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Announces a pooled transaction to the listeners, if there are any
+    ///
+    /// # Returns
+    /// Whether at least one listener received the announcement
+    pub fn announce<T>(&self, sender: &broadcast::Sender<T>, item: T) -> bool {
+        let announced = sender.send(item).is_ok();
+        let counter = if announced { &self.transactions_announced } else { &self.transactions_unannounced };
+        counter.fetch_add(1, Ordering::Relaxed);
+        announced
+    }
+
+    /// Sends a block to the consumer, retrying with backoff while its channel is full
+    ///
+    /// # Returns
+    /// An error describing why the block was dropped, if it was not delivered
+    pub async fn send_block<T>(&self, sender: &mpsc::Sender<T>, block: T) -> Result<(), String> {
+        let mut block = block;
+        let mut backoff = Duration::from_millis(BLOCK_SEND_INITIAL_BACKOFF_MS);
+        for attempt in 1..=BLOCK_SEND_ATTEMPTS {
+            match sender.try_send(block) {
+                Ok(()) => {
+                    self.blocks_delivered.fetch_add(1, Ordering::Relaxed);
+                    return Ok(());
+                },
+                Err(mpsc::error::TrySendError::Full(returned)) if attempt < BLOCK_SEND_ATTEMPTS => {
+                    self.block_send_retries.fetch_add(1, Ordering::Relaxed);
+                    time::sleep(backoff).await;
+                    backoff *= 2;
+                    block = returned;
+                },
+                Err(mpsc::error::TrySendError::Full(_)) => break,
+                Err(mpsc::error::TrySendError::Closed(_)) => {
+                    self.blocks_dropped.fetch_add(1, Ordering::Relaxed);
+                    return Err("block channel closed".to_string());
+                },
+            }
+        }
+        self.blocks_dropped.fetch_add(1, Ordering::Relaxed);
+        Err(format!("block consumer lagging, channel still full after {} attempts", BLOCK_SEND_ATTEMPTS))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_announcements_without_listeners_are_counted() {
+        let counters = ChannelCounters::default();
+        let (sender, _) = broadcast::channel(1);
+        assert!(!counters.announce(&sender, 1));
+
+        let mut receiver = sender.subscribe();
+        assert!(counters.announce(&sender, 2));
+        assert_eq!(receiver.recv().await.unwrap(), 2);
+
+        let metrics = counters.metrics();
+        assert_eq!((metrics.transactions_announced, metrics.transactions_unannounced), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn test_block_sends_retry_while_full_then_drop() {
+        let counters = ChannelCounters::default();
+        let (sender, mut receiver) = mpsc::channel(1);
+        counters.send_block(&sender, 1).await.unwrap();
+
+        // The consumer makes room during the backoff, so the send goes through
+        let reader = tokio::spawn(async move {
+            time::sleep(Duration::from_millis(BLOCK_SEND_INITIAL_BACKOFF_MS * 2)).await;
+            assert_eq!(receiver.recv().await, Some(1));
+            receiver
+        });
+        counters.send_block(&sender, 2).await.unwrap();
+        let mut receiver = reader.await.unwrap();
+        let metrics = counters.metrics();
+        assert_eq!((metrics.blocks_delivered, metrics.blocks_dropped), (2, 0));
+        assert!(metrics.block_send_retries >= 1);
+
+        // A consumer that never reads gets the block dropped after the last attempt
+        let retries = metrics.block_send_retries;
+        let error = counters.send_block(&sender, 3).await.unwrap_err();
+        assert!(error.contains("lagging"));
+        let metrics = counters.metrics();
+        assert_eq!(metrics.block_send_retries, retries + u64::from(BLOCK_SEND_ATTEMPTS) - 1);
+        assert_eq!(metrics.blocks_dropped, 1);
+
+        // A closed channel is not retried
+        assert_eq!(receiver.recv().await, Some(2));
+        drop(receiver);
+        assert!(counters.send_block(&sender, 4).await.unwrap_err().contains("closed"));
+        assert_eq!(counters.metrics().block_send_retries, retries + u64::from(BLOCK_SEND_ATTEMPTS) - 1);
+        assert_eq!(counters.metrics().blocks_dropped, 2);
+    }
+}
@@ -0,0 +1,93 @@
+//! Periodic Checkpoints
+//!
+//! The node writes a state checkpoint at a fixed interval, as the "checkpoint"
+//! job of the RPC handler's scheduler. A full disk or a permissions problem
+//! must not take the node down, and retrying at full speed only adds load to a
+//! disk that is already in trouble, so the scheduler doubles the delay after
+//! each failed write, up to `rpc::scheduler::MAX_BACKOFF_SECS`. Once
+//! `FAILURE_ALERT_THRESHOLD` writes in a row have failed every further failure
+//! is logged at error level with `alert = "checkpoint_write_failing"`. The
+//! failure count is also reported by `getNetworkStatus`.
+
+use log::{error, info, warn};
+use runtime::Runtime;
+
+/// Default number of seconds between checkpoints
+pub const DEFAULT_CHECKPOINT_INTERVAL_SECS: u64 = 300;
+
+/// Consecutive failures after which failures are raised as alerts
+pub const FAILURE_ALERT_THRESHOLD: u32 = rpc::scheduler::FAILURE_ALERT_THRESHOLD;
+
+/// Writes one checkpoint; the body of the scheduled "checkpoint" job
+///
+/// # Arguments
+/// * `runtime` - The runtime to checkpoint
+///
+/// # Returns
+/// The path written, or why the write failed
+pub async fn write_checkpoint(runtime: Runtime) -> Result<String, String> {
+    match runtime.create_checkpoint(false) {
+        Ok(checkpoint) => {
+            info!("Wrote checkpoint {}", checkpoint.file_path);
+            Ok(format!("wrote checkpoint {}", checkpoint.file_path))
+        },
+        Err(e) => {
+            let failures = runtime.checkpoint_health().consecutive_failures;
+            if failures >= FAILURE_ALERT_THRESHOLD {
+                error!(alert = "checkpoint_write_failing", consecutive_failures = failures;
+                       "Checkpoint write failed {} times in a row: {}", failures, e);
+            } else {
+                warn!(consecutive_failures = failures; "Checkpoint write failed: {}", e);
+            }
+            Err(e.to_string())
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rpc::scheduler::{Schedule, Scheduler};
+    use std::time::Duration;
+
+    const ACCOUNT: &str = "0x1111111111111111111111111111111111111111";
+
+    #[tokio::test]
+    async fn test_scheduled_checkpoints_are_written_and_pruned() {
+        let dir = format!("./test_checkpointing_{}", std::process::id());
+        let _ = std::fs::remove_dir_all(&dir);
+        let runtime = Runtime::with_checkpoint_config(2, &dir).unwrap();
+        runtime.create_account(ACCOUNT).unwrap();
+
+        // Files are named by the second, so runs are just over a second apart
+        let scheduler = Scheduler::default();
+        let job_runtime = runtime.clone();
+        scheduler.register("checkpoint", Schedule::Every(Duration::from_millis(1100)), move || {
+            write_checkpoint(job_runtime.clone())
+        });
+        scheduler.start();
+
+        // Unchanged state is not checkpointed again, so every run sees a new balance
+        let mut credited = 0;
+        while scheduler.job("checkpoint").unwrap().runs < 4 {
+            runtime.credit_balance(ACCOUNT, 1).unwrap();
+            credited += 1;
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        let status = scheduler.job("checkpoint").unwrap();
+        assert_eq!(status.failures, 0);
+        let files: Vec<_> = std::fs::read_dir(&dir).unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .filter(|name| name.ends_with(".dat"))
+            .collect();
+        assert_eq!(files.len(), 2, "pruning keeps the newest 2 of {:?}", files);
+        let checkpoints = runtime.list_checkpoints();
+        assert_eq!(checkpoints.len(), 2);
+        assert!(checkpoints[0].timestamp < checkpoints[1].timestamp);
+        assert!(checkpoints[1].total_supply <= credited);
+        assert_eq!(runtime.checkpoint_health().consecutive_failures, 0);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,218 @@
+//! End-to-end scenarios against a whole node in one process
+//!
+//! Each scenario wires a Runtime, a BlockProducer, an RpcHandler and the
+//! Ethereum-compatible servers together the way `main` does, with the servers
+//! bound to ephemeral ports. The native API is driven through the same request
+//! dispatcher the native RPC server uses, the Ethereum API over HTTP, and blocks
+//! are produced on demand. Block timestamps come from a mock clock and hashes
+//! from a seeded generator, so runs are repeatable.
+//!
+//! Whenever the native and Ethereum APIs describe the same data, both are read
+//! and must agree. Scenarios for disagreements that are known and not yet fixed
+//! are ignored, with the disagreement as the reason; run them with
+//! `cargo test -p ubi-chain-node e2e -- --ignored`.
+
+use crate::testkit::{MockClock, GENESIS_TIMESTAMP};
+use crate::{handle_rpc_request, BlockProducer, Block, BLOCK_TIME_MS};
+use runtime::Runtime;
+use serde_json::{json, Value};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::Arc;
+use tokio::sync::{broadcast, mpsc};
+
+/// Chain ID the Ethereum servers are started with
+const CHAIN_ID: u64 = 2030;
+
+/// Address of the node, which funds the faucet
+const NODE_ADDRESS: &str = "0x1111111111111111111111111111111111111111";
+
+/// Wallet that receives faucet grants and sends transfers
+const USER: &str = "0x2222222222222222222222222222222222222222";
+
+/// Recipient of the user's transfers
+const RECIPIENT: &str = "0x3333333333333333333333333333333333333333";
+
+/// Wei in one token
+const WEI_PER_TOKEN: u128 = 1_000_000_000_000_000_000;
+
+/// A node with its block producer, RPC handler and Ethereum servers running in this process
+struct InProcessNode {
+    handler: rpc::RpcHandler,
+    producer: Arc<BlockProducer>,
+    eth_server: Option<rpc::CombinedServer>,
+    tokio: tokio::runtime::Runtime,
+    _block_receiver: mpsc::Receiver<Block>,
+}
+
+impl InProcessNode {
+    /// Starts a node whose hashes are drawn from the given seed
+    fn start(seed: u64) -> Self {
+        let runtime = Runtime::new();
+        runtime.create_account(NODE_ADDRESS).unwrap();
+        runtime.credit_balance(NODE_ADDRESS, 1_000_000).unwrap();
+
+        let clock = MockClock::new(GENESIS_TIMESTAMP);
+        let (tx_sender, _) = broadcast::channel(100);
+        let (block_sender, block_receiver) = mpsc::channel(100);
+        let producer = Arc::new(BlockProducer::new(
+            runtime.clone(),
+            BLOCK_TIME_MS,
+            "e2e-node".to_string(),
+            NODE_ADDRESS.to_string(),
+            tx_sender,
+            block_sender,
+        ).with_clock(clock.as_clock()));
+        runtime.set_block_producer(producer.clone());
+
+        let mut handler = rpc::RpcHandler::new(runtime);
+        handler.set_node_address(NODE_ADDRESS.to_string());
+        handler.set_random_source(rpc::RandomSource::seeded(seed));
+
+        // The servers run their own event loops, so they are started outside the tokio runtime
+        let eth_server = jsonrpc_core::futures::executor::block_on(
+            handler.start_combined_server("127.0.0.1:0", "127.0.0.1:0", CHAIN_ID)
+        ).unwrap();
+
+        InProcessNode {
+            handler,
+            producer,
+            eth_server: Some(eth_server),
+            tokio: tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap(),
+            _block_receiver: block_receiver,
+        }
+    }
+
+    /// Calls a method of the native API
+    fn native(&self, method: &str, params: Value) -> Value {
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let peer_addr: SocketAddr = "127.0.0.1:40000".parse().unwrap();
+        let response = self.tokio.block_on(handle_rpc_request(&self.handler, &request.to_string(), peer_addr));
+        serde_json::from_str(&response).unwrap()
+    }
+
+    /// Calls a method of the Ethereum API over HTTP, failing the test on an error response
+    fn eth(&self, method: &str, params: Value) -> Value {
+        let addr = self.eth_server.as_ref().unwrap().http_addr;
+        let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }).to_string();
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "POST / HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+               addr, body.len(), body).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        let response: Value = serde_json::from_str(body).unwrap();
+        assert!(response.get("error").is_none(), "{} failed: {}", method, response);
+        response["result"].clone()
+    }
+
+    /// Produces the next block
+    fn produce_block(&self) -> Block {
+        self.tokio.block_on(self.producer.produce_block()).unwrap()
+    }
+
+    /// Reads a balance through both APIs, failing the test if they disagree
+    fn balance(&self, address: &str) -> u64 {
+        let native = self.native("getAccountInfo", json!([address]))["balance"].as_u64().unwrap();
+        let eth = self.eth("eth_getBalance", json!([address, "latest"]));
+        let wei = u128::from_str_radix(eth.as_str().unwrap().trim_start_matches("0x"), 16).unwrap();
+        assert_eq!(wei, native as u128 * WEI_PER_TOKEN, "native and eth balances of {} disagree", address);
+        native
+    }
+}
+
+impl Drop for InProcessNode {
+    fn drop(&mut self) {
+        if let Some(server) = self.eth_server.take() {
+            server.close();
+        }
+    }
+}
+
+#[test]
+fn test_faucet_grant_is_visible_through_both_apis() {
+    let node = InProcessNode::start(1);
+    assert_eq!(node.balance(USER), 0);
+
+    let grant = node.native("requestFromFaucet", json!([USER, 75]));
+    assert_eq!(grant["success"], json!(true), "{}", grant);
+    assert_eq!(grant["new_balance"], json!(75));
+    assert_eq!(node.balance(USER), 75);
+}
+
+#[test]
+fn test_transfer_is_included_and_reflected_in_history_and_stats() {
+    let node = InProcessNode::start(2);
+    assert_eq!(node.native("createAccount", json!([RECIPIENT]))["success"], json!(true));
+    for _ in 0..2 {
+        assert_eq!(node.native("requestFromFaucet", json!([USER, 100]))["success"], json!(true));
+    }
+    assert_eq!(node.balance(USER), 200);
+
+    let submitted = node.native("submitTransaction", json!([{ "from": USER, "to": RECIPIENT, "amount": 100 }]));
+    let hash = submitted["transaction_hash"].as_str().unwrap().to_string();
+    assert_eq!(node.native("getTransactionStatus", json!([hash]))["state"]["status"], json!("pending"));
+    assert_eq!(node.balance(USER), 200);
+
+    let block = node.produce_block();
+    assert_eq!(block.number, 1);
+    let fee = node.handler.runtime.chain_params().transfer_fee(100);
+    assert_eq!(node.balance(USER), 100 - fee);
+    assert_eq!(node.balance(RECIPIENT), 100);
+
+    // The status, the block and the chain height agree on where the transfer went
+    let status = node.native("getTransactionStatus", json!([hash]));
+    assert_eq!(status["state"], json!({ "status": "included", "block_number": 1 }));
+    let stored = node.native("getBlockByNumber", json!([1]));
+    assert_eq!(stored["transactions"][0]["hash"], json!(hash));
+    assert_eq!(stored["fees_collected"], json!(fee));
+    assert_eq!(node.native("getLatestBlocks", json!([1]))[0]["number"], json!(1));
+    assert_eq!(node.native("getChainInfo", json!([]))["block_height"], json!(1));
+
+    // The fee ledger names the transfer that paid into the pool
+    let ledger = node.native("getFeeLedger", json!([]));
+    assert!(ledger["items"].as_array().unwrap().iter().any(|entry| entry["tx_hash"] == json!(hash)), "{}", ledger);
+}
+
+#[test]
+#[ignore = "eth_sendTransaction executes at once and seals an eth-only block instead of going through the block producer"]
+fn test_eth_transfer_is_included_in_the_native_chain() {
+    let node = InProcessNode::start(3);
+    assert_eq!(node.native("requestFromFaucet", json!([USER, 100]))["success"], json!(true));
+
+    let value = format!("0x{:x}", 10 * WEI_PER_TOKEN);
+    let hash = node.eth("eth_sendTransaction", json!([{ "from": USER, "to": RECIPIENT, "value": value }]));
+    node.produce_block();
+
+    let receipt = node.eth("eth_getTransactionReceipt", json!([hash]));
+    let receipt_block = u64::from_str_radix(receipt["blockNumber"].as_str().unwrap().trim_start_matches("0x"), 16).unwrap();
+    let status = node.native("getTransactionStatus", json!([hash]));
+    assert_eq!(status["state"], json!({ "status": "included", "block_number": receipt_block }));
+    assert_eq!(node.native("getBlockByNumber", json!([receipt_block]))["transactions"][0]["hash"], hash);
+}
+
+#[test]
+#[ignore = "eth blocks are numbered separately from the blocks the block producer seals"]
+fn test_block_heights_agree() {
+    let node = InProcessNode::start(4);
+    for _ in 0..3 {
+        node.produce_block();
+    }
+
+    let native_height = node.native("getChainInfo", json!([]))["block_height"].as_u64().unwrap();
+    assert_eq!(native_height, 3);
+    assert_eq!(node.eth("eth_blockNumber", json!([])), json!(format!("0x{:x}", native_height)));
+    assert_eq!(node.eth("eth_getBlockByNumber", json!(["latest", false]))["number"], json!("0x3"));
+}
+
+#[test]
+#[ignore = "faucet grants transfer directly and return a random hash that is never stored"]
+fn test_faucet_hash_can_be_looked_up() {
+    let node = InProcessNode::start(5);
+    let grant = node.native("requestFromFaucet", json!([USER, 10]));
+    let hash = grant["transaction_hash"].clone();
+
+    assert_ne!(node.eth("eth_getTransactionByHash", json!([hash])), Value::Null);
+    assert_ne!(node.native("getTransactionStatus", json!([hash])), Value::Null);
+}
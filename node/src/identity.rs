@@ -0,0 +1,182 @@
+//! Persistent Node Identity
+//!
+//! Each node keeps a random 20-byte identity in its data directory. The
+//! identity's address receives block rewards and funds the faucet, and a short
+//! form of it names the node on the P2P network, so they survive restarts and
+//! port changes and never collide between nodes.
+//!
+//! Nodes used to derive their address from the P2P port. `migrate_port_account`
+//! moves the balance accumulated under such an address to the identity.
+
+use log::info;
+use runtime::{AccountError, Runtime};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Name of the identity file inside the data directory
+pub const IDENTITY_FILE: &str = "node_identity.json";
+
+/// Persistent identity of a node
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeIdentity {
+    /// 0x-prefixed 20-byte address
+    pub address: String,
+}
+
+impl NodeIdentity {
+    /// Loads the identity from the data directory, generating and saving one if none exists
+    ///
+    /// # Arguments
+    /// * `data_dir` - Directory holding the node's persistent files
+    ///
+    /// # Returns
+    /// The node identity, or an error if the file cannot be read, parsed or written
+    pub fn load_or_create(data_dir: &str) -> io::Result<Self> {
+        let path = identity_path(data_dir);
+
+        if path.exists() {
+            let contents = fs::read_to_string(&path)?;
+            let identity: NodeIdentity = serde_json::from_str(&contents)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            if !is_valid_address(&identity.address) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid address in {}: {}", path.display(), identity.address),
+                ));
+            }
+            info!("Loaded node identity from {}", path.display());
+            return Ok(identity);
+        }
+
+        let mut bytes = [0u8; 20];
+        rpc::RandomSource::thread().fill_bytes(&mut bytes);
+        let identity = NodeIdentity {
+            address: format!("0x{}", hex::encode(bytes)),
+        };
+
+        fs::create_dir_all(data_dir)?;
+        let contents = serde_json::to_string_pretty(&identity)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(&path, contents)?;
+        info!("Generated new node identity in {}", path.display());
+
+        Ok(identity)
+    }
+
+    /// Gets the node id announced on the P2P network and recorded in produced blocks
+    pub fn node_id(&self) -> String {
+        format!("node-{}", &self.address[2..10])
+    }
+}
+
+/// Gets the path of the identity file in a data directory
+fn identity_path(data_dir: &str) -> PathBuf {
+    Path::new(data_dir).join(IDENTITY_FILE)
+}
+
+/// Checks that an address is 0x followed by 40 hex digits
+fn is_valid_address(address: &str) -> bool {
+    address.len() == 42
+        && address.starts_with("0x")
+        && address[2..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Gets the address older nodes derived from their P2P port
+pub fn port_derived_address(port: u16) -> String {
+    format!("0x{:040x}", port)
+}
+
+/// Moves the balance of the old port-derived account to the node identity
+///
+/// Only needed once per node. Does nothing if the port-derived account does not
+/// exist in local state.
+///
+/// # Arguments
+/// * `runtime` - Runtime holding the local state
+/// * `port` - P2P port the old address was derived from
+/// * `identity` - The node identity receiving the balance
+///
+/// # Returns
+/// The amount moved
+pub fn migrate_port_account(runtime: &Runtime, port: u16, identity: &NodeIdentity) -> Result<u64, AccountError> {
+    let old_address = port_derived_address(port);
+    if !runtime.get_account_addresses().contains(&old_address) {
+        info!("No port-derived account {} to migrate", old_address);
+        return Ok(0);
+    }
+
+    match runtime.create_account(&identity.address) {
+        Ok(()) | Err(AccountError::AlreadyExists) => {},
+        Err(e) => return Err(e),
+    }
+
+    let amount = runtime.move_balance(&old_address, &identity.address)?;
+    info!(from = old_address.as_str(), to = identity.address.as_str(), amount = amount;
+        "Migrated {} UBI from port-derived account", amount);
+    Ok(amount)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> String {
+        format!("./test_identity_{}_{}", name, std::process::id())
+    }
+
+    #[test]
+    fn test_identity_persists_across_restarts() {
+        let dir = test_dir("persist");
+        let _ = fs::remove_dir_all(&dir);
+
+        let identity = NodeIdentity::load_or_create(&dir).unwrap();
+        assert!(is_valid_address(&identity.address));
+        assert_ne!(identity.address, port_derived_address(30333));
+        assert_eq!(NodeIdentity::load_or_create(&dir).unwrap(), identity);
+        assert_eq!(identity.node_id(), format!("node-{}", &identity.address[2..10]));
+
+        // Another node gets its own identity
+        let other_dir = test_dir("other");
+        let _ = fs::remove_dir_all(&other_dir);
+        assert_ne!(NodeIdentity::load_or_create(&other_dir).unwrap(), identity);
+
+        let _ = fs::remove_dir_all(&dir);
+        let _ = fs::remove_dir_all(&other_dir);
+    }
+
+    #[test]
+    fn test_corrupt_identity_is_rejected() {
+        let dir = test_dir("corrupt");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(identity_path(&dir), r#"{"address":"0x1234"}"#).unwrap();
+
+        assert!(NodeIdentity::load_or_create(&dir).is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_migrate_port_account() {
+        let runtime = Runtime::new();
+        let identity = NodeIdentity {
+            address: "0x00112233445566778899aabbccddeeff00112233".to_string(),
+        };
+
+        // Nothing to migrate on a fresh node
+        assert_eq!(migrate_port_account(&runtime, 30333, &identity).unwrap(), 0);
+
+        let old_address = port_derived_address(30333);
+        runtime.create_account(&old_address).unwrap();
+        runtime.credit_balance(&old_address, 300).unwrap();
+
+        assert_eq!(migrate_port_account(&runtime, 30333, &identity).unwrap(), 300);
+        assert_eq!(runtime.get_balance(&identity.address), 300);
+        assert_eq!(runtime.get_balance(&old_address), 0);
+
+        // Running it again moves nothing
+        assert_eq!(migrate_port_account(&runtime, 30333, &identity).unwrap(), 0);
+    }
+}
@@ -0,0 +1,227 @@
+//! Single Instance Lock
+//!
+//! Two node processes pointed at the same directories would both write
+//! checkpoints, block stores and the node identity, corrupting files and
+//! interleaving block numbers. At startup the node takes an advisory lock on a
+//! lock file in each of its data, checkpoint and chain store directories and
+//! holds it until it exits. The lock file records the holder's PID so the
+//! error names the process in the way.
+//!
+//! The operating system releases the locks when a process dies, so a crash
+//! normally leaves nothing to clean up. Where it does not (some network
+//! filesystems), `--force-unlock` replaces the lock file, but only if the
+//! recorded PID is no longer running.
+
+use log::{info, warn};
+use std::fmt;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{self, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+/// Name of the lock file inside each locked directory
+pub const LOCK_FILE: &str = "ubi-node.lock";
+
+/// Reason the node could not lock its directories
+#[derive(Debug)]
+pub enum LockError {
+    /// Another process holds the lock
+    Held {
+        /// The locked directory
+        dir: PathBuf,
+        /// PID recorded by the holder, if readable
+        pid: Option<u32>,
+    },
+    /// `--force-unlock` was given, but the recorded holder is still running
+    HolderAlive {
+        /// The locked directory
+        dir: PathBuf,
+        /// PID recorded by the holder
+        pid: u32,
+    },
+    /// The directory or lock file could not be used
+    Io {
+        /// The directory
+        dir: PathBuf,
+        /// The underlying error
+        source: io::Error,
+    },
+}
+
+impl fmt::Display for LockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LockError::Held { dir, pid: Some(pid) } => write!(
+                f,
+                "Another node instance (PID {}) is using {}. Stop it first; if it crashed and left a stale lock, restart with --force-unlock",
+                pid, dir.display()
+            ),
+            LockError::Held { dir, pid: None } => write!(
+                f,
+                "Another node instance is using {}. Stop it first; if it crashed and left a stale lock, restart with --force-unlock",
+                dir.display()
+            ),
+            LockError::HolderAlive { dir, pid } => write!(
+                f,
+                "Refusing to force unlock {}: the lock holder (PID {}) is still running",
+                dir.display(), pid
+            ),
+            LockError::Io { dir, source } => write!(f, "Could not lock {}: {}", dir.display(), source),
+        }
+    }
+}
+
+impl std::error::Error for LockError {}
+
+/// Locks held on the node's directories, released when dropped
+#[derive(Debug)]
+pub struct InstanceLock {
+    /// Lock files and their open, locked handles
+    files: Vec<(PathBuf, File)>,
+}
+
+impl InstanceLock {
+    /// Locks every given directory, creating it if needed
+    ///
+    /// Directories that resolve to the same path are locked once. If any lock
+    /// cannot be taken, the locks already taken are released.
+    ///
+    /// # Arguments
+    /// * `dirs` - The directories to lock
+    /// * `force_unlock` - Whether to replace locks whose recorded holder is no longer running
+    ///
+    /// # Returns
+    /// The held locks, or the first directory that could not be locked
+    pub fn acquire(dirs: &[&str], force_unlock: bool) -> Result<Self, LockError> {
+        let mut lock = InstanceLock { files: Vec::new() };
+        let mut locked_dirs = Vec::new();
+
+        for dir in dirs {
+            let dir = Path::new(dir);
+            let canonical = fs::create_dir_all(dir)
+                .and_then(|_| dir.canonicalize())
+                .map_err(|source| LockError::Io { dir: dir.to_path_buf(), source })?;
+            if locked_dirs.contains(&canonical) {
+                continue;
+            }
+
+            lock.files.push(lock_dir(&canonical, force_unlock)?);
+            locked_dirs.push(canonical);
+        }
+
+        Ok(lock)
+    }
+
+    /// Gets the lock files held
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.files.iter().map(|(path, _)| path.as_path())
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        for (path, file) in &mut self.files {
+            // The file itself stays: removing it could let two later processes lock different files
+            let _ = file.set_len(0);
+            if let Err(e) = file.unlock() {
+                warn!("Failed to release {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+/// Locks the lock file of one directory and records this process's PID in it
+fn lock_dir(dir: &Path, force_unlock: bool) -> Result<(PathBuf, File), LockError> {
+    let path = dir.join(LOCK_FILE);
+    let io_error = |source| LockError::Io { dir: dir.to_path_buf(), source };
+
+    let mut file = open_lock_file(&path).map_err(io_error)?;
+    match file.try_lock() {
+        Ok(()) => {},
+        Err(TryLockError::WouldBlock) => {
+            let pid = read_pid(&mut file);
+            if !force_unlock {
+                return Err(LockError::Held { dir: dir.to_path_buf(), pid });
+            }
+            // Without a readable PID there is no way to tell the holder is gone
+            let pid = pid.ok_or_else(|| LockError::Held { dir: dir.to_path_buf(), pid: None })?;
+            if is_process_alive(pid) {
+                return Err(LockError::HolderAlive { dir: dir.to_path_buf(), pid });
+            }
+
+            warn!("Replacing stale lock {} left by PID {}", path.display(), pid);
+            fs::remove_file(&path).map_err(io_error)?;
+            file = open_lock_file(&path).map_err(io_error)?;
+            match file.try_lock() {
+                Ok(()) => {},
+                Err(TryLockError::WouldBlock) => {
+                    return Err(LockError::Held { dir: dir.to_path_buf(), pid: read_pid(&mut file) });
+                },
+                Err(TryLockError::Error(e)) => return Err(io_error(e)),
+            }
+        },
+        Err(TryLockError::Error(e)) => return Err(io_error(e)),
+    }
+
+    file.set_len(0)
+        .and_then(|_| file.rewind())
+        .and_then(|_| write!(file, "{}", std::process::id()))
+        .and_then(|_| file.sync_all())
+        .map_err(io_error)?;
+    info!("Locked {}", path.display());
+
+    Ok((path, file))
+}
+
+/// Opens a lock file without truncating it, so the holder's PID stays readable
+fn open_lock_file(path: &Path) -> io::Result<File> {
+    OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)
+}
+
+/// Reads the PID recorded in a lock file
+fn read_pid(file: &mut File) -> Option<u32> {
+    let mut contents = String::new();
+    file.rewind().ok()?;
+    file.read_to_string(&mut contents).ok()?;
+    contents.trim().parse().ok()
+}
+
+/// Checks whether a process is running
+///
+/// Only Linux can tell; elsewhere every process is assumed to be alive, so
+/// locks are never forced.
+fn is_process_alive(pid: u32) -> bool {
+    if cfg!(target_os = "linux") {
+        Path::new("/proc").join(pid.to_string()).exists()
+    } else {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_second_instance_is_refused() {
+        let dir = format!("./test_instance_lock_{}", std::process::id());
+        let checkpoints = format!("{}/checkpoints", dir);
+
+        let lock = InstanceLock::acquire(&[&dir, &checkpoints, &dir], false).unwrap();
+        assert_eq!(lock.paths().count(), 2);
+
+        // The lock is per open file, so a second acquisition in this process conflicts too
+        match InstanceLock::acquire(&[&checkpoints], false) {
+            Err(LockError::Held { pid, .. }) => assert_eq!(pid, Some(std::process::id())),
+            other => panic!("unexpected result {:?}", other),
+        }
+        match InstanceLock::acquire(&[&checkpoints], true) {
+            Err(LockError::HolderAlive { pid, .. }) => assert_eq!(pid, std::process::id()),
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        drop(lock);
+        assert!(InstanceLock::acquire(&[&dir, &checkpoints], false).is_ok());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
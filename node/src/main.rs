@@ -10,18 +10,85 @@ use tokio::net::TcpListener;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use log::{info, error, trace, debug, warn};
 use std::net::SocketAddr;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use tokio::sync::{mpsc, broadcast};
 use tokio::time::{self, Duration, Instant};
 use std::sync::Arc;
-use std::collections::VecDeque;
-use std::sync::atomic::{AtomicU64, Ordering};
-use runtime::{Runtime, BlockProducer as BlockProducerTrait};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use runtime::{AccountError, Runtime, BlockProducer as BlockProducerTrait};
+use runtime::tx_status::{TransactionStatus, TransactionStatusStore};
+use runtime::tx_trace::{LifecycleEvent, LifecycleStage, TransactionTraceStore};
+use runtime::sync_status::SyncStatus;
+use runtime::liveness::{LivenessStatus, NodeHealthEvent};
+use runtime::ubi_mint::{self, UbiMint};
+use runtime::maintenance::MaintenanceBlocks;
+use runtime::transfer_mode::TransferMode;
+use runtime::block_extras::BlockExtras;
+use rpc::chain_store::TieredStore;
 use std::time::{SystemTime, UNIX_EPOCH};
 use serde::{Serialize, Deserialize};
 
 mod p2p;
-use p2p::P2PNetwork;
+use p2p::{P2PNetwork, PeerTracker};
+
+mod wire;
+
+mod identity;
+use identity::NodeIdentity;
+
+mod network_time;
+use network_time::NetworkTime;
+
+mod checkpoint_migration;
+
+mod state_export;
+
+mod checkpointing;
+
+mod instance_lock;
+use instance_lock::InstanceLock;
+
+mod watchdog;
+use watchdog::Watchdog;
+
+mod channels;
+use channels::ChannelCounters;
+
+mod production;
+use production::{HaltAction, ProductionBackend, ProductionError, ProductionHalt};
+
+#[cfg(test)]
+mod testkit;
+
+#[cfg(test)]
+mod e2e;
+
+/// Target time between blocks in milliseconds
+const BLOCK_TIME_MS: u64 = 1000;
+
+/// Hash of the genesis block, which is the parent of block #1
+///
+/// Block hashes are currently derived from the block number.
+const GENESIS_HASH: &str = "0x0";
+
+/// Directory of the state store inside the data directory
+const STATE_STORE_DIR: &str = "state";
+
+/// Source of the current UNIX time in seconds
+///
+/// Injectable so that tests can drive block timestamps from a mock clock.
+pub type Clock = Arc<dyn Fn() -> u64 + Send + Sync>;
+
+/// Returns a clock backed by the system time
+fn system_clock() -> Clock {
+    Arc::new(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+    })
+}
 
 /// Command line arguments for the node
 #[derive(Parser, Debug)]
@@ -63,6 +130,22 @@ struct Args {
     #[arg(long, default_value = "8545")]
     eth_rpc_port: u16,
     
+    /// Ethereum WebSocket JSON-RPC port, for eth_subscribe
+    /// Default: 8546 (standard Ethereum WebSocket port)
+    #[arg(long, default_value = "8546")]
+    eth_ws_port: u16,
+    
+    /// Address to bind the Ethereum JSON-RPC server to; repeat to listen on several
+    /// Overrides --eth-rpc-host and --eth-rpc-port; port 0 picks a free port
+    /// Example: --eth-rpc-addr 0.0.0.0:8545 --eth-rpc-addr [::]:8545
+    #[arg(long = "eth-rpc-addr", value_parser = rpc::parse_bind_addr)]
+    eth_rpc_addrs: Vec<SocketAddr>,
+    
+    /// Address to bind the Ethereum WebSocket server to; repeat to listen on several
+    /// Overrides --eth-rpc-host and --eth-ws-port; port 0 picks a free port
+    #[arg(long = "eth-ws-addr", value_parser = rpc::parse_bind_addr)]
+    eth_ws_addrs: Vec<SocketAddr>,
+    
     /// Chain ID for Ethereum compatibility (EIP-155)
     /// Default: 2030 (UBI Chain network)
     #[arg(long, default_value = "2030")]
@@ -71,9 +154,245 @@ struct Args {
     /// Disable Ethereum JSON-RPC server
     #[arg(long)]
     disable_eth_rpc: bool,
+    
+    /// Dev mode: draw transaction hashes, block hashes and subscription ids
+    /// from a seeded generator so runs can be replayed
+    #[arg(long)]
+    deterministic: bool,
+    
+    /// Seed for deterministic mode (defaults to UBI_RANDOM_SEED or the current time)
+    #[arg(long, requires = "deterministic")]
+    seed: Option<u64>,
+    
+    /// Number of most recent blocks the Ethereum RPC and the native block store keep in memory
+    /// Older blocks are spilled to --chain-store-dir
+    #[arg(long, default_value = "10000")]
+    max_cached_blocks: usize,
+    
+    /// Number of most recent transactions the Ethereum RPC keeps in memory
+    /// Older transactions are spilled to --chain-store-dir
+    #[arg(long, default_value = "100000")]
+    max_cached_transactions: usize,
+    
+    /// Directory for blocks and transactions evicted from memory
+    #[arg(long, default_value = "./chain_store")]
+    chain_store_dir: String,
+    
+    /// Log output format: text or json
+    /// JSON lines carry contextual fields and the request correlation id
+    #[arg(long, default_value = "text")]
+    log_format: rpc::logging::LogFormat,
+    
+    /// File holding RUST_LOG-style log levels, read at startup in place of RUST_LOG
+    /// ubi_setLogLevel saves the levels here when asked to persist them
+    #[arg(long)]
+    log_levels_file: Option<std::path::PathBuf>,
+    
+    /// Directory for persistent node files such as the node identity and the state store
+    #[arg(long, default_value = "./data")]
+    data_dir: String,
+    
+    /// JSON file of operator-maintained address labels shown by explorers
+    /// Defaults to labels.json in the data directory; ubi_setLabel and ubi_removeLabel save to it
+    #[arg(long)]
+    labels_file: Option<std::path::PathBuf>,
+    
+    /// Move the balance of the old port-derived node address to the node identity
+    #[arg(long)]
+    migrate_port_account: bool,
+    
+    /// Replace directory locks left by a node that is no longer running
+    /// Refused if the PID recorded in a lock is still alive
+    #[arg(long)]
+    force_unlock: bool,
+    
+    /// Seconds a block received from a peer may be ahead of the local clock
+    #[arg(long, default_value_t = network_time::DEFAULT_MAX_CLOCK_DRIFT_SECS)]
+    max_clock_drift: u64,
+    
+    /// Directory to write state checkpoints to
+    #[arg(long, default_value = "./checkpoints")]
+    checkpoint_dir: String,
+    
+    /// Seconds between state checkpoints
+    #[arg(long, default_value_t = checkpointing::DEFAULT_CHECKPOINT_INTERVAL_SECS)]
+    checkpoint_interval_secs: u64,
+    
+    /// Run without writing state checkpoints
+    /// Use when the checkpoint directory is unavailable; state is lost on restart
+    #[arg(long)]
+    no_checkpoints: bool,
+    
+    /// Gzip the accounts of new checkpoints; compressed and uncompressed checkpoints both load
+    #[arg(long)]
+    compress_checkpoints: bool,
+    
+    /// UBI tokens accrued per hour by verified accounts; 0 turns accrual off
+    /// Overrides the genesis file's ubi.tokens_per_hour on this node only, for local testing:
+    /// nodes accruing at different rates disagree on every balance
+    #[arg(long)]
+    ubi_rate: Option<u64>,
+    
+    /// Most hours of UBI a single claim credits; an account idle for longer forfeits the rest
+    /// Overrides the genesis file's ubi.max_accrual_hours on this node only, for local testing
+    #[arg(long)]
+    max_ubi_accrual_hours: Option<u64>,
+    
+    /// Address whose transfers are burned instead of credited
+    #[arg(long, default_value = runtime::DEFAULT_BURN_ADDRESS)]
+    burn_address: String,
+    
+    /// Most accounts a checkpoint may declare; larger files are refused before they are read
+    #[arg(long, default_value_t = runtime::checkpoint::DEFAULT_MAX_CHECKPOINT_ACCOUNTS)]
+    max_checkpoint_accounts: u64,
+    
+    /// What the block producer does in maintenance mode: heartbeat seals empty blocks, pause produces none
+    #[arg(long, default_value_t = MaintenanceBlocks::Heartbeat)]
+    maintenance_blocks: MaintenanceBlocks,
+    
+    /// What the node does when block production stops after repeated or unrecoverable failures:
+    /// maintenance keeps serving reads in maintenance mode, shutdown exits
+    #[arg(long, default_value_t = HaltAction::Maintenance)]
+    on_production_halt: HaltAction,
+    
+    /// Maximum number of RPC requests handled at once across all methods and servers
+    #[arg(long, default_value_t = rpc::concurrency::DEFAULT_MAX_IN_FLIGHT)]
+    rpc_max_in_flight: usize,
+    
+    /// Per-method concurrency limit as method=limit; repeat for several methods
+    /// Example: --rpc-method-limit ubi_listAccounts=2
+    #[arg(long = "rpc-method-limit", value_parser = rpc::concurrency::parse_method_limit)]
+    rpc_method_limits: Vec<(String, usize)>,
+    
+    /// Milliseconds an RPC request waits for a free slot before it is rejected as busy
+    #[arg(long, default_value_t = rpc::concurrency::DEFAULT_QUEUE_TIMEOUT_MS)]
+    rpc_queue_timeout_ms: u64,
+    
+    /// Milliseconds a cached response to a hot polling method (eth_blockNumber and similar) is served
+    /// The cache is also cleared whenever a block is sealed; 0 disables it
+    #[arg(long, default_value_t = rpc::response_cache::DEFAULT_RESPONSE_CACHE_TTL_MS)]
+    rpc_cache_ttl_ms: u64,
+    
+    /// Notifications queued for a WebSocket subscriber that is not keeping up
+    #[arg(long, default_value_t = rpc::eth_pubsub::DEFAULT_NOTIFICATION_QUEUE)]
+    ws_notification_queue: usize,
+    
+    /// What happens when a subscriber's queue is full: drop-oldest marks it lagged, disconnect closes the client
+    #[arg(long, default_value_t = rpc::eth_pubsub::SlowConsumerPolicy::DropOldest)]
+    ws_slow_consumer_policy: rpc::eth_pubsub::SlowConsumerPolicy,
+    
+    /// Seconds an idempotency key is remembered; repeats within this window return the first result
+    #[arg(long, default_value_t = rpc::idempotency::DEFAULT_IDEMPOTENCY_TTL_SECS)]
+    idempotency_ttl_secs: u64,
+    
+    /// Endpoint that requires a solved proof-of-work challenge: faucet or create-account; repeat for both
+    /// Challenges are fetched with ubi_getPowChallenge; without this flag no proof of work is asked for
+    #[arg(long = "pow-endpoint")]
+    pow_endpoints: Vec<rpc::pow::PowEndpoint>,
+    
+    /// Proof-of-work difficulty in leading zero bits while request volume is at or below the target
+    #[arg(long, default_value_t = rpc::pow::DEFAULT_MIN_POW_DIFFICULTY)]
+    pow_min_difficulty: u32,
+    
+    /// Highest proof-of-work difficulty in leading zero bits
+    #[arg(long, default_value_t = rpc::pow::DEFAULT_MAX_POW_DIFFICULTY)]
+    pow_max_difficulty: u32,
+    
+    /// Proof-of-work challenges per window issued at the minimum difficulty; each doubling adds a bit
+    #[arg(long, default_value_t = rpc::pow::DEFAULT_POW_TARGET_REQUESTS)]
+    pow_target_requests: u64,
+    
+    /// Seconds over which proof-of-work challenge volume is measured
+    #[arg(long, default_value_t = rpc::pow::DEFAULT_POW_WINDOW_SECS)]
+    pow_window_secs: u64,
+    
+    /// Seconds a proof-of-work challenge stays valid
+    #[arg(long, default_value_t = rpc::pow::DEFAULT_POW_CHALLENGE_TTL_SECS)]
+    pow_challenge_ttl_secs: u64,
+    
+    /// Address allowed to schedule chain parameter changes over RPC while the network bootstraps
+    /// Requests must carry this address's signature; without it changes come only from governance
+    #[arg(long)]
+    param_admin: Option<String>,
+    
+    /// Address allowed to export and import accounts over RPC
+    /// Exports burn the account's balance here; imports mint it
+    #[arg(long)]
+    account_admin: Option<String>,
+    
+    /// Address allowed to run housekeeping actions over RPC
+    /// Covers on-demand fee distribution, the supply audit and state root recomputation
+    #[arg(long)]
+    ops_admin: Option<String>,
+    
+    /// JSON file listing the faucet's grant tiers, replacing the default "starter" and "developer" tiers
+    /// Each entry has name, amount, cooldown_secs (omit for once ever), requires_code, codes and requires_challenge
+    #[arg(long)]
+    faucet_tiers: Option<String>,
+    
+    /// Comma-separated faucet source accounts, tried in order until one can pay a grant
+    /// Defaults to the genesis faucet, or else this node's account
+    /// Example: --faucet-sources 0x1111111111111111111111111111111111111111,0x2222222222222222222222222222222222222222
+    #[arg(long)]
+    faucet_sources: Option<String>,
+    
+    /// JSON genesis file of the network: a funded faucet, the UBI, fee and fee distribution settings and the bootstrap window
+    /// Every node of a network must use the same file; the faucet address may be "node" for this
+    /// node's address on a single-node network. See the README for the format
+    #[arg(long)]
+    genesis: Option<String>,
+    
+    /// Maintenance command to run instead of starting the node
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// Maintenance commands
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Rewrite every checkpoint in a directory in the current format
+    MigrateCheckpoints {
+        /// Directory holding the checkpoints to migrate
+        #[arg(long)]
+        from_dir: String,
+        
+        /// Directory to write migrated checkpoints and the migration report to
+        #[arg(long)]
+        to_dir: String,
+    },
+    
+    /// Write the newest checkpoint as a sorted, pretty-printed JSON state snapshot
+    ExportState {
+        /// Directory holding the checkpoints
+        #[arg(long, default_value = "./checkpoints")]
+        checkpoint_dir: String,
+        
+        /// File to write the snapshot to
+        #[arg(long)]
+        out: String,
+    },
+    
+    /// Bootstrap a fresh node from a JSON state snapshot
+    /// Writes the snapshot as the first checkpoint of an empty checkpoint directory
+    ImportState {
+        /// Snapshot written by export-state
+        #[arg(long)]
+        state: String,
+        
+        /// Checkpoint directory of the node to bootstrap
+        #[arg(long, default_value = "./checkpoints")]
+        checkpoint_dir: String,
+    },
 }
 
 /// Block structure for the UBI Chain
+///
+/// Signed transactions come first, in canonical order (see
+/// `Transaction::canonical_cmp`): highest fee first, then oldest timestamp,
+/// then lowest hash. The order does not depend on when a node received each
+/// transaction, so every node executing the block reaches the same state.
+/// System transactions such as UBI mints follow in settlement order. Imported
+/// blocks whose signed transactions are out of canonical order are rejected.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Block {
     /// Block number/height
@@ -96,6 +415,21 @@ pub struct Block {
     
     /// Block producer identifier
     pub producer: String,
+    
+    /// Address credited with the block reward
+    pub producer_address: String,
+}
+
+/// A block with the UBI-specific data the Ethereum-shaped block leaves out
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockRecord {
+    /// The block, including failed transactions
+    #[serde(flatten)]
+    pub block: Block,
+    
+    /// Reward, fee, UBI mint and verification figures recorded at seal time
+    #[serde(flatten)]
+    pub extras: BlockExtras,
 }
 
 /// Transaction structure for the UBI Chain
@@ -118,6 +452,74 @@ pub struct Transaction {
     
     /// Timestamp when the transaction was created
     pub timestamp: u64,
+    
+    /// Whether execution failed; failed transactions are recorded in the block but change no state
+    #[serde(default)]
+    pub failed: bool,
+    
+    /// Whether the amount is what the sender sent or what the recipient must receive
+    #[serde(default, skip_serializing_if = "TransferMode::is_send")]
+    pub mode: TransferMode,
+    
+    /// Sender's nonce; pooled transactions without one are given the next nonce when included
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<u64>,
+}
+
+impl Transaction {
+    /// Checks whether this is a system transaction, which no account signed
+    pub fn is_system(&self) -> bool {
+        ubi_mint::is_ubi_mint_address(&self.from)
+    }
+    
+    /// Lowercases the hash and addresses, the form blocks store transactions in
+    pub fn normalized(mut self) -> Self {
+        self.hash = self.hash.to_lowercase();
+        self.from = self.from.to_lowercase();
+        self.to = self.to.to_lowercase();
+        self
+    }
+    
+    /// Compares transactions by their canonical position within a block
+    ///
+    /// Higher fees come first, then older timestamps, then lower hashes, so
+    /// the order is total and the same on every node.
+    pub fn canonical_cmp(&self, other: &Transaction) -> std::cmp::Ordering {
+        other.fee.cmp(&self.fee)
+            .then_with(|| self.timestamp.cmp(&other.timestamp))
+            .then_with(|| self.hash.cmp(&other.hash))
+    }
+}
+
+impl From<&UbiMint> for Transaction {
+    fn from(mint: &UbiMint) -> Self {
+        Transaction {
+            hash: mint.hash.clone(),
+            from: ubi_mint::UBI_MINT_ADDRESS.to_string(),
+            to: mint.to.clone(),
+            amount: mint.amount,
+            fee: 0,
+            timestamp: mint.settled_at,
+            failed: false,
+            mode: TransferMode::Send,
+            nonce: None,
+        }
+    }
+}
+
+impl From<&Transaction> for runtime::Transaction {
+    fn from(tx: &Transaction) -> Self {
+        runtime::Transaction {
+            hash: tx.hash.clone(),
+            from: tx.from.clone(),
+            to: tx.to.clone(),
+            amount: tx.amount,
+            fee: tx.fee,
+            timestamp: tx.timestamp,
+            mode: tx.mode,
+            nonce: tx.nonce,
+        }
+    }
 }
 
 /// Transaction pool for pending transactions
@@ -128,6 +530,9 @@ pub struct TransactionPool {
     
     /// Maximum number of transactions per block
     max_txs_per_block: usize,
+    
+    /// Where admissions are recorded in each transaction's lifecycle trace
+    traces: TransactionTraceStore,
 }
 
 impl TransactionPool {
@@ -136,28 +541,36 @@ impl TransactionPool {
         TransactionPool {
             transactions: Arc::new(std::sync::Mutex::new(VecDeque::new())),
             max_txs_per_block,
+            traces: TransactionTraceStore::default(),
         }
     }
     
-    /// Adds a transaction to the pool
+    /// Records admissions in the given trace store
+    pub fn with_traces(mut self, traces: TransactionTraceStore) -> Self {
+        self.traces = traces;
+        self
+    }
+    
+    /// Adds a transaction to the pool, with its hash and addresses lowercased
     pub fn add_transaction(&self, tx: Transaction) {
+        let tx = tx.normalized();
+        self.traces.record(&tx.hash, LifecycleStage::AdmittedToPool);
         let mut transactions = self.transactions.lock().unwrap();
         transactions.push_back(tx);
     }
     
     /// Gets transactions for the next block
+    ///
+    /// The whole pool is put in canonical order first, so nodes holding the
+    /// same transactions select and order the same ones whatever order they
+    /// arrived in.
     pub fn get_transactions_for_block(&self) -> Vec<Transaction> {
         let mut transactions = self.transactions.lock().unwrap();
-        let mut block_txs = Vec::new();
+        transactions.make_contiguous().sort_by(Transaction::canonical_cmp);
         
         // Take up to max_txs_per_block transactions
-        while !transactions.is_empty() && block_txs.len() < self.max_txs_per_block {
-            if let Some(tx) = transactions.pop_front() {
-                block_txs.push(tx);
-            }
-        }
-        
-        block_txs
+        let count = transactions.len().min(self.max_txs_per_block);
+        transactions.drain(..count).collect()
     }
     
     /// Gets the number of pending transactions
@@ -187,11 +600,50 @@ pub struct BlockProducer {
     /// Node address (for receiving block rewards)
     node_address: String,
     
-    /// Channel for submitting transactions
+    /// Channel announcing transactions added to the pool
     tx_sender: broadcast::Sender<Transaction>,
     
     /// Channel for receiving new blocks
     block_sender: mpsc::Sender<Block>,
+    
+    /// What happened to transaction announcements and block sends
+    channel_counters: ChannelCounters,
+    
+    /// Source of block timestamps and estimate of the network clock offset
+    network_time: NetworkTime,
+    
+    /// Sync progress of each peer, fed by block imports and the P2P network
+    peers: Arc<PeerTracker>,
+    
+    /// Timestamp of the latest produced or imported block
+    last_timestamp: AtomicU64,
+    
+    /// What happened to each submitted transaction
+    tx_statuses: TransactionStatusStore,
+    
+    /// Produced and imported blocks with their reward and fee data, by number
+    block_store: Arc<std::sync::Mutex<TieredStore<BlockRecord>>>,
+    
+    /// Runtime's total distributed fees when the previous block was recorded
+    fees_distributed_seen: AtomicU64,
+    
+    /// Local time the last block was produced, 0 if none was
+    last_produced_at: AtomicU64,
+    
+    /// Local time the last imported block was applied, 0 if none was
+    last_imported_at: AtomicU64,
+    
+    /// Flags the producer as stalled when neither happens for too long
+    watchdog: Watchdog,
+    
+    /// Whether to seal empty blocks or pause while in maintenance mode
+    maintenance_blocks: MaintenanceBlocks,
+    
+    /// Runtime operations a block depends on that can fail
+    backend: Arc<dyn ProductionBackend>,
+    
+    /// Set once the production loop should return
+    stopping: AtomicBool,
 }
 
 impl BlockProducer {
@@ -210,45 +662,232 @@ impl BlockProducer {
             Err(_) => debug!("Node account already exists: {}", node_address),
         }
         
+        let fees_distributed_seen = runtime.get_total_fees_distributed();
+        
+        let tx_pool = TransactionPool::new(50) // Allow up to 50 transactions per block
+            .with_traces(runtime.transaction_traces().clone());
+        
         BlockProducer {
+            backend: Arc::new(runtime.clone()),
+            stopping: AtomicBool::new(false),
             runtime,
-            tx_pool: TransactionPool::new(50), // Allow up to 50 transactions per block
+            tx_pool,
             current_block: Arc::new(AtomicU64::new(0)),
             block_time_ms,
             node_id,
             node_address,
             tx_sender,
             block_sender,
+            channel_counters: ChannelCounters::default(),
+            network_time: NetworkTime::new(system_clock()),
+            peers: Arc::new(PeerTracker::new(system_clock())),
+            last_timestamp: AtomicU64::new(0),
+            tx_statuses: TransactionStatusStore::default(),
+            block_store: Arc::new(std::sync::Mutex::new(TieredStore::new(
+                "native_blocks",
+                rpc::chain_store::DEFAULT_MAX_BLOCKS,
+                rpc::chain_store::DEFAULT_SPILL_DIR,
+            ))),
+            fees_distributed_seen: AtomicU64::new(fees_distributed_seen),
+            last_produced_at: AtomicU64::new(0),
+            last_imported_at: AtomicU64::new(0),
+            watchdog: Watchdog::new(block_time_ms, system_clock()()),
+            maintenance_blocks: MaintenanceBlocks::default(),
         }
     }
     
-    /// Starts the block production loop
-    pub async fn start(&self) {
-        info!("Starting block production with {}ms block time", self.block_time_ms);
+    /// Sets whether to seal empty blocks or pause while in maintenance mode
+    pub fn with_maintenance_blocks(mut self, maintenance_blocks: MaintenanceBlocks) -> Self {
+        self.maintenance_blocks = maintenance_blocks;
+        self
+    }
+    
+    /// Replaces the clock used to timestamp produced blocks
+    #[allow(dead_code)]
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        let max_drift_secs = self.network_time.max_drift_secs();
+        self.network_time = NetworkTime::new(clock.clone()).with_max_drift(max_drift_secs);
+        self.watchdog = Watchdog::new(self.block_time_ms, clock());
+        self.peers = Arc::new(PeerTracker::new(clock));
+        self
+    }
+    
+    /// Sets how many blocks are kept in memory and where older ones are spilled
+    pub fn with_block_retention(self, config: &rpc::RetentionConfig) -> Self {
+        self.block_store.lock().unwrap().set_retention(config.max_blocks, &config.spill_dir);
+        self
+    }
+    
+    /// Gets a produced or imported block with its reward and fee data
+    pub fn get_block_record(&self, number: u64) -> Option<BlockRecord> {
+        self.block_store.lock().unwrap().get(&number.to_string())
+    }
+    
+    /// Stores a block with its reward, fee, UBI mint and verification data
+    ///
+    /// The verification changes since the previous block are taken into this one.
+    fn record_block(&self, block: &Block, reward_paid: u64, fees_collected: u64) -> Result<(), ProductionError> {
+        let total_distributed = self.runtime.get_total_fees_distributed();
+        let previous = self.fees_distributed_seen.swap(total_distributed, Ordering::SeqCst);
         
-        // Clone necessary fields for the transaction receiver task
-        let tx_pool = self.tx_pool.clone();
-        let mut tx_receiver = self.tx_sender.subscribe();
+        // Imported blocks may carry addresses in any casing; the store keeps them lowercase
+        let mut stored = block.clone();
+        stored.transactions = stored.transactions.into_iter().map(Transaction::normalized).collect();
         
-        // Spawn a task to receive transactions and add them to the pool
-        tokio::spawn(async move {
-            while let Ok(tx) = tx_receiver.recv().await {
-                debug!("Received transaction: {:?}", tx);
-                tx_pool.add_transaction(tx);
-            }
-        });
+        let record = BlockRecord {
+            block: stored,
+            extras: BlockExtras {
+                reward_paid,
+                fees_collected,
+                fees_distributed: total_distributed.saturating_sub(previous),
+                ubi_minted_in_block: block.transactions.iter().filter(|tx| tx.is_system()).map(|tx| tx.amount).sum(),
+                verification_events: self.runtime.take_verification_events(),
+            },
+        };
+        self.block_store.lock()
+            .map_err(|_| ProductionError::Storage("the block store lock is poisoned".to_string()))?
+            .insert(block.number.to_string(), record);
+        Ok(())
+    }
+    
+    /// Pays the reward of a stored block to its producer
+    ///
+    /// The reward is recorded against the block hash, so `discard_blocks_above`
+    /// can take it back if the block leaves the chain.
+    fn pay_block_reward(&self, block: &Block, block_reward: u64) -> Result<(), ProductionError> {
+        if let Err(e) = self.backend.credit_block_reward(&block.producer_address, block_reward, &block.hash) {
+            error!(block_number = block.number; "Failed to credit block reward: {:?}", e);
+            return Err(ProductionError::Transient(format!("Failed to credit the reward of block #{}: {}", block.number, e)));
+        }
+        info!(block_number = block.number, address = block.producer_address.as_str();
+              "Block #{} reward: {} UBI tokens to {}, new balance: {}",
+              block.number, block_reward, block.producer_address, self.runtime.get_balance(&block.producer_address));
+        
+        let mut store = self.block_store.lock().unwrap();
+        if let Some(mut record) = store.get(&block.number.to_string()) {
+            record.extras.reward_paid = block_reward;
+            store.insert(block.number.to_string(), record);
+        }
+        Ok(())
+    }
+    
+    /// Discards the blocks above `height` after a reorg and takes back their rewards
+    ///
+    /// Rewards are reconciled through the supply events recorded against each
+    /// block's hash. Only the blocks and their rewards are discarded; transfers
+    /// they executed stay applied.
+    ///
+    /// # Arguments
+    /// * `height` - The last block that stays on the chain
+    ///
+    /// # Returns
+    /// The tokens taken back from producers
+    #[allow(dead_code)]
+    pub fn discard_blocks_above(&self, height: u64) -> u64 {
+        let mut reclaimed = 0;
+        let mut store = self.block_store.lock().unwrap();
+        for number in (height + 1..=self.current_block()).rev() {
+            let record = match store.remove(&number.to_string()) {
+                Some(record) => record,
+                None => continue,
+            };
+            let taken = self.runtime.revert_block_reward(&record.block.hash).map_or(0, |event| event.amount);
+            warn!(block_number = number; "Discarded block #{}, took back {} of its {} token reward", number, taken, record.extras.reward_paid);
+            reclaimed += taken;
+        }
+        let parent_timestamp = store.get(&height.to_string()).map_or(0, |record| record.block.timestamp);
+        drop(store);
+        
+        self.current_block.store(height, Ordering::SeqCst);
+        self.last_timestamp.store(parent_timestamp, Ordering::SeqCst);
+        reclaimed
+    }
+    
+    /// Sets how many seconds an imported block may be ahead of the local clock
+    pub fn with_max_clock_drift(mut self, max_drift_secs: u64) -> Self {
+        self.network_time = self.network_time.with_max_drift(max_drift_secs);
+        self
+    }
+    
+    /// Gets the network time estimate, which peer handshakes report their time to
+    #[allow(dead_code)]
+    pub fn network_time(&self) -> &NetworkTime {
+        &self.network_time
+    }
+    
+    /// Gets the tracker that peer handshakes and received blocks report to
+    pub fn peer_tracker(&self) -> Arc<PeerTracker> {
+        self.peers.clone()
+    }
+    
+    /// Gets the target time between blocks in milliseconds
+    pub fn block_time_ms(&self) -> u64 {
+        self.block_time_ms
+    }
+    
+    /// Checks whether the producer stalled or recovered since the last check
+    pub fn check_liveness(&self) -> Option<NodeHealthEvent> {
+        self.watchdog.check(self.network_time.local_now(), &self.liveness())
+    }
+    
+    /// Makes the runtime operations a block depends on go through the given backend
+    #[cfg(test)]
+    pub fn with_backend(mut self, backend: Arc<dyn ProductionBackend>) -> Self {
+        self.backend = backend;
+        self
+    }
+    
+    /// Makes the production loop return after the block in progress
+    pub fn stop(&self) {
+        self.stopping.store(true, Ordering::SeqCst);
+    }
+    
+    /// Flags the producer as stalled because production stopped for good
+    pub fn mark_halted(&self, halt: &ProductionHalt) {
+        self.watchdog.halt(self.network_time.local_now(), &self.liveness(), halt.to_string());
+    }
+    
+    /// Runs the block production loop until stopped or until production fails for good
+    ///
+    /// A transient failure is retried with a backoff that doubles with each
+    /// consecutive failure, up to `MAX_RETRY_BLOCK_TIMES` block times. Production
+    /// stops after `MAX_CONSECUTIVE_FAILURES` in a row, or at once on a state or
+    /// storage failure, flagging the producer as stalled.
+    ///
+    /// # Returns
+    /// Ok once `stop` was called, or why production stopped
+    pub async fn start(&self) -> Result<(), ProductionHalt> {
+        info!("Starting block production with {}ms block time", self.block_time_ms);
+        let mut consecutive_failures = 0;
         
         // Main block production loop
-        loop {
+        while !self.stopping.load(Ordering::SeqCst) {
             let start_time = Instant::now();
             
-            // Produce a block
+            // Produce a block, unless maintenance mode pauses production
+            if self.maintenance_blocks == MaintenanceBlocks::Pause && self.runtime.maintenance_status().enabled {
+                debug!("Block production paused for maintenance");
+                time::sleep(Duration::from_millis(self.block_time_ms)).await;
+                continue;
+            }
             match self.produce_block().await {
                 Ok(block) => {
-                    info!("Produced block #{} with {} transactions", block.number, block.transactions.len());
+                    consecutive_failures = 0;
+                    info!(block_number = block.number; "Produced block #{} with {} transactions", block.number, block.transactions.len());
                 },
-                Err(e) => {
-                    error!("Failed to produce block: {}", e);
+                Err(error) => {
+                    consecutive_failures += 1;
+                    if !error.is_transient() || consecutive_failures >= production::MAX_CONSECUTIVE_FAILURES {
+                        let halt = ProductionHalt { consecutive_failures, error };
+                        error!("Stopping block production: {}", halt);
+                        self.mark_halted(&halt);
+                        return Err(halt);
+                    }
+                    let backoff_blocks = (1u64 << consecutive_failures).min(production::MAX_RETRY_BLOCK_TIMES);
+                    warn!("Failed to produce block ({} in a row), retrying in {} block times: {}",
+                          consecutive_failures, backoff_blocks, error);
+                    time::sleep(Duration::from_millis(self.block_time_ms * backoff_blocks)).await;
+                    continue;
                 }
             }
             
@@ -266,53 +905,83 @@ impl BlockProducer {
                       elapsed.as_millis(), self.block_time_ms);
             }
         }
+        info!("Block production stopped");
+        Ok(())
     }
     
     /// Produces a new block with pending transactions
-    async fn produce_block(&self) -> Result<Block, String> {
+    ///
+    /// A block whose reward cannot be credited stays on the chain, unrewarded,
+    /// but still counts as a failure.
+    async fn produce_block(&self) -> Result<Block, ProductionError> {
+        self.backend.check_state()?;
+        let block_number = self.current_block() + 1;
+        
+        // Parameter changes due at this block apply to its transactions and reward, and a due
+        // distribution pays out the fees collected before it
+        self.runtime.apply_param_changes(block_number);
+        self.runtime.distribute_fees_if_due(block_number);
+        let block_reward = self.runtime.chain_params().block_reward;
+        
+        // In maintenance mode the block is an empty heartbeat: the pool is left alone and nothing is paid
+        let maintenance = self.runtime.maintenance_status().enabled;
+        
         // Get transactions from the pool
-        let pending_transactions = self.tx_pool.get_transactions_for_block();
-        let mut successful_transactions = Vec::new();
-        
-        // Process each transaction
-        for tx in pending_transactions {
-            match self.runtime.transfer_with_fee(&tx.from, &tx.to, tx.amount) {
-                Ok(_) => {
-                    info!("Successfully processed transaction: {} -> {}, amount: {}", tx.from, tx.to, tx.amount);
-                    successful_transactions.push(tx);
+        let pending_transactions = if maintenance { Vec::new() } else { self.tx_pool.get_transactions_for_block() };
+        let mut block_transactions = Vec::new();
+        let mut fees_collected = 0;
+        
+        // Process each transaction; failed ones stay in the block, flagged, so the chain records them
+        let traces = self.runtime.transaction_traces();
+        for mut tx in pending_transactions {
+            traces.record_event(&tx.hash, LifecycleEvent::now(LifecycleStage::SelectedForBlock).in_block(block_number));
+            // A transfer breaking the consensus rules is recorded as failed, or peers would reject the block
+            let result = self.runtime.validate_transaction(&tx.from, &tx.to, tx.amount, tx.mode)
+                .map_err(|e| AccountError::Other(e.to_string()))
+                .and_then(|_| self.runtime.execute_transaction(&(&tx).into()));
+            match result {
+                Ok(nonce) => {
+                    // The block records the nonce used, so importers execute the transaction in the same place
+                    tx.nonce = Some(nonce);
+                    info!(tx_hash = tx.hash.as_str(), address = tx.from.as_str();
+                          "Successfully processed transaction: {} -> {}, amount: {}", tx.from, tx.to, tx.amount);
+                    self.tx_statuses.record_included(&(&tx).into(), block_number);
+                    traces.record_event(&tx.hash, LifecycleEvent::now(LifecycleStage::Executed).in_block(block_number));
+                    fees_collected += self.runtime.transfer_fee_for(&tx.from, tx.amount);
                 },
                 Err(e) => {
-                    error!("Failed to process transaction: {} -> {}, amount: {}, error: {:?}", 
+                    error!(tx_hash = tx.hash.as_str(), address = tx.from.as_str();
+                           "Failed to process transaction: {} -> {}, amount: {}, error: {:?}", 
                            tx.from, tx.to, tx.amount, e);
+                    self.tx_statuses.record_failure(&(&tx).into(), &e, block_number);
+                    traces.record_event(&tx.hash, LifecycleEvent::now(LifecycleStage::ExecutionFailed)
+                        .in_block(block_number)
+                        .with_detail(e.to_string()));
+                    tx.failed = true;
                 }
             }
+            block_transactions.push(tx);
         }
         
-        // Get current block number
-        let block_number = self.current_block.fetch_add(1, Ordering::SeqCst) + 1;
-        
-        // Get parent block hash (use a simple hash of the block number for now)
-        let parent_hash = format!("0x{:x}", block_number - 1);
+        // Create block hash (simple concatenation for now)
+        let block_hash = format!("0x{:x}", block_number);
         
-        // Credit block reward to producer
-        match self.runtime.credit_balance(&self.node_address, 100) {
-            Ok(new_balance) => {
-                info!("Block #{} reward: 100 UBI tokens to {}, new balance: {}", 
-                      block_number, self.node_address, new_balance);
-            },
-            Err(e) => {
-                error!("Failed to credit block reward: {:?}", e);
-            }
+        // Settled UBI accruals follow as system transactions, capped per block
+        if !maintenance {
+            let mints = self.runtime.include_ubi_mints(block_number, &block_hash, ubi_mint::MAX_UBI_MINTS_PER_BLOCK);
+            block_transactions.extend(mints.iter().map(Transaction::from));
         }
         
-        // Create block hash (simple concatenation for now)
-        let block_hash = format!("0x{:x}", block_number);
+        // Advance the block number
+        self.current_block.store(block_number, Ordering::SeqCst);
         
-        // Get current timestamp
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
+        // Get parent block hash (use a simple hash of the block number for now)
+        let parent_hash = format!("0x{:x}", block_number - 1);
+        
+        // Use the local clock, but never go below the parent's timestamp
+        let timestamp = self.network_time.block_timestamp(self.last_timestamp.load(Ordering::SeqCst));
+        self.last_timestamp.store(timestamp, Ordering::SeqCst);
+        self.last_produced_at.store(self.network_time.local_now(), Ordering::SeqCst);
         
         // Create the block
         let block = Block {
@@ -320,23 +989,145 @@ impl BlockProducer {
             hash: block_hash,
             parent_hash,
             timestamp,
-            transactions: successful_transactions.clone(),
+            transactions: block_transactions,
             state_root: "0x0".to_string(), // Simplified for now
             producer: self.node_id.clone(),
+            producer_address: self.node_address.clone(),
         };
+        self.record_block(&block, 0, fees_collected)?;
+        for tx in block.transactions.iter().filter(|tx| !tx.is_system()) {
+            traces.record_event(&tx.hash, LifecycleEvent::now(LifecycleStage::ReceiptWritten).in_block(block_number));
+        }
         
-        // Send block to subscribers
-        if let Err(e) = self.block_sender.send(block.clone()).await {
-            error!("Failed to broadcast block: {}", e);
+        // Send block to subscribers; the reward is paid only for a block that was stored and sent
+        match self.channel_counters.send_block(&self.block_sender, block.clone()).await {
+            Err(e) => error!(block_number = block.number; "Failed to broadcast block, withholding its reward: {}", e),
+            Ok(()) if maintenance => {},
+            Ok(()) => self.pay_block_reward(&block, block_reward)?,
         }
 
         Ok(block)
     }
     
-    /// Submits a transaction to the pool
+    /// Imports a block produced by another node
+    ///
+    /// Re-executes the block's transactions against the local runtime, credits the
+    /// block reward to the producer and advances the local block height. The block
+    /// runs on a detached copy of the state first, so one that fails anywhere,
+    /// such as on an unfunded transfer after valid ones, changes nothing.
+    ///
+    /// # Arguments
+    /// * `block` - The block to import; must directly follow the current block
+    pub fn import_block(&self, block: &Block) -> Result<(), String> {
+        let expected_number = self.current_block() + 1;
+        if block.number != expected_number {
+            return Err(format!("Unexpected block number: expected {}, got {}", expected_number, block.number));
+        }
+        
+        self.network_time.validate_block_timestamp(block.timestamp, self.last_timestamp.load(Ordering::SeqCst))?;
+        
+        // Signed transactions must be in canonical order, or nodes could execute them differently
+        let signed: Vec<&Transaction> = block.transactions.iter().filter(|tx| !tx.is_system()).collect();
+        if let Some(pair) = signed.windows(2).find(|pair| pair[0].canonical_cmp(pair[1]).is_gt()) {
+            return Err(format!("Transactions out of canonical order: {} before {}", pair[0].hash, pair[1].hash));
+        }
+        
+        // Nothing touches the local state until the whole block has run on the copy
+        Self::execute_block(&self.runtime.detached_copy(), block)?;
+        let fees_collected = Self::execute_block(&self.runtime, block)?;
+        
+        self.record_block(block, 0, fees_collected).map_err(|e| e.to_string())?;
+        let _ = self.runtime.create_account(&block.producer_address);
+        // The block is valid whether or not the local credit of its reward succeeds; a failure is logged
+        let _ = self.pay_block_reward(block, self.runtime.chain_params().block_reward);
+        
+        self.current_block.store(block.number, Ordering::SeqCst);
+        self.last_timestamp.store(block.timestamp, Ordering::SeqCst);
+        self.last_imported_at.store(self.network_time.local_now(), Ordering::SeqCst);
+        debug!("Imported block #{} from {}", block.number, block.producer);
+        
+        Ok(())
+    }
+    
+    /// Runs a block's transfers against a runtime, after the parameter changes
+    /// and fee distribution the producer applied before them
+    ///
+    /// # Arguments
+    /// * `runtime` - The runtime to run the block on
+    /// * `block` - The block
+    ///
+    /// # Returns
+    /// The fees the block's transfers collected, or why the block is invalid
+    fn execute_block(runtime: &Runtime, block: &Block) -> Result<u64, String> {
+        runtime.apply_param_changes(block.number);
+        runtime.distribute_fees_if_due(block.number);
+        
+        // UBI mints are settled by each node from the same verification times, so they are not replayed.
+        // Every transfer is checked against the consensus rules before any of them runs
+        let transfers: Vec<Transaction> = block.transactions.iter()
+            .filter(|tx| !tx.failed && !tx.is_system())
+            .map(|tx| tx.clone().normalized())
+            .collect();
+        let mut next_nonces: HashMap<&str, u64> = HashMap::new();
+        for tx in &transfers {
+            runtime.validate_transaction(&tx.from, &tx.to, tx.amount, tx.mode)
+                .map_err(|e| format!("Transaction {} breaks consensus rules: {}", tx.hash, e))?;
+            
+            // Each sender's transactions must use its nonces in order, from the next one
+            let next = next_nonces.entry(tx.from.as_str()).or_insert_with(|| runtime.get_nonce(&tx.from));
+            if let Some(nonce) = tx.nonce.filter(|nonce| nonce != next) {
+                return Err(format!("Transaction {} has nonce {}, expected {}", tx.hash, nonce, next));
+            }
+            *next += 1;
+        }
+        
+        let mut fees_collected = 0;
+        for tx in &transfers {
+            // The producer only includes transfers to existing accounts, so mirror them locally
+            let _ = runtime.create_account(&tx.to);
+            runtime.execute_transaction(&tx.into())
+                .map_err(|e| format!("Failed to apply transaction {}: {}", tx.hash, e))?;
+            fees_collected += runtime.transfer_fee_for(&tx.from, tx.amount);
+        }
+        Ok(fees_collected)
+    }
+    
+    /// Imports a block received from a peer, recording it in the peer's sync progress
+    ///
+    /// # Arguments
+    /// * `peer_id` - The peer that sent the block
+    /// * `block` - The block to import
+    #[allow(dead_code)]
+    pub fn import_block_from(&self, peer_id: &str, block: &Block) -> Result<(), String> {
+        let bytes = serde_json::to_vec(block).map(|encoded| encoded.len() as u64).unwrap_or(0);
+        self.peers.record_local_height(self.current_block());
+        
+        let result = self.import_block(block);
+        if let Err(e) = &result {
+            warn!(block_number = block.number, peer_id = peer_id; "Invalid block from {}: {}", peer_id, e);
+        }
+        self.peers.record_block(peer_id, block.number, bytes, result.is_ok());
+        self.peers.record_local_height(self.current_block());
+        
+        result
+    }
+    
+    /// Submits a transaction to the pool and announces it to the transaction listeners
+    ///
+    /// The transaction is pooled whether or not anything listens yet, so submissions
+    /// arriving before the node's tasks have subscribed are not lost.
     pub async fn submit_transaction(&self, tx: Transaction) -> Result<(), String> {
-        if let Err(e) = self.tx_sender.send(tx) {
-            return Err(format!("Failed to submit transaction: {}", e));
+        let maintenance = self.runtime.maintenance_status();
+        if maintenance.enabled {
+            return Err(maintenance.refusal());
+        }
+        let pending = runtime::Transaction::from(&tx);
+        self.tx_statuses.record_pending(&pending);
+        self.tx_pool.add_transaction(tx.clone());
+        if self.channel_counters.announce(&self.tx_sender, tx) {
+            self.runtime.transaction_traces().record(&pending.hash, LifecycleStage::Gossiped);
+        } else {
+            debug!(tx_hash = pending.hash.as_str(); "No listener for the announcement of pooled transaction {}", pending.hash);
         }
         Ok(())
     }
@@ -349,6 +1140,11 @@ impl BlockProducer {
 
 impl BlockProducerTrait for BlockProducer {
     fn submit_transaction(&self, tx: runtime::Transaction) -> Result<(), String> {
+        let maintenance = self.runtime.maintenance_status();
+        if maintenance.enabled {
+            return Err(maintenance.refusal());
+        }
+        self.tx_statuses.record_pending(&tx);
         let node_tx = Transaction {
             hash: tx.hash,
             from: tx.from,
@@ -356,6 +1152,9 @@ impl BlockProducerTrait for BlockProducer {
             amount: tx.amount,
             fee: tx.fee,
             timestamp: tx.timestamp,
+            failed: false,
+            mode: tx.mode,
+            nonce: tx.nonce,
         };
 
         // Directly add transaction to the pool
@@ -366,6 +1165,44 @@ impl BlockProducerTrait for BlockProducer {
     fn current_block(&self) -> u64 {
         self.current_block.load(Ordering::SeqCst)
     }
+    
+    fn clock_offset(&self) -> i64 {
+        self.network_time.offset()
+    }
+    
+    fn transaction_status(&self, hash: &str) -> Option<TransactionStatus> {
+        self.tx_statuses.get(hash)
+    }
+    
+    fn block_record(&self, number: u64) -> Option<serde_json::Value> {
+        self.get_block_record(number)
+            .and_then(|record| serde_json::to_value(record).ok())
+    }
+    
+    fn block_extras(&self, number: u64) -> Option<BlockExtras> {
+        self.get_block_record(number).map(|record| record.extras)
+    }
+    
+    fn sync_status(&self) -> SyncStatus {
+        self.peers.status(self.current_block())
+    }
+    
+    fn channel_metrics(&self) -> runtime::channel_metrics::ChannelMetrics {
+        self.channel_counters.metrics()
+    }
+    
+    fn liveness(&self) -> LivenessStatus {
+        let recorded = |at: &AtomicU64| Some(at.load(Ordering::SeqCst)).filter(|at| *at > 0);
+        let stalled_since = self.watchdog.stalled_since();
+        LivenessStatus {
+            stalled: stalled_since.is_some(),
+            stalled_since,
+            last_block: self.current_block(),
+            last_produced_at: recorded(&self.last_produced_at),
+            last_imported_at: recorded(&self.last_imported_at),
+            halted: self.watchdog.halted(),
+        }
+    }
 }
 
 /// Main entry point for the UBI Chain node
@@ -405,38 +1242,151 @@ impl BlockProducerTrait for BlockProducer {
 /// ```
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize logging
-    env_logger::init_from_env(env_logger::Env::default().default_filter_or("info"));
-    
     // Parse command line arguments
     let args = Args::parse();
     
-    // Calculate RPC port if not specified
-    let rpc_port = args.rpc_port.unwrap_or(args.port - 20400);
+    // Initialize logging
+    let log_handle = rpc::logging::init_logger(args.log_format, log::LevelFilter::Info, args.log_levels_file.clone());
     
-    // Create P2P network address
-    let p2p_addr = format!("{}:{}", args.p2p_host, args.port);
-    let p2p_socket_addr = p2p_addr.parse::<SocketAddr>().expect("Invalid P2P address");
-    info!("Starting P2P network on {}", p2p_addr);
+    if let Some(Command::MigrateCheckpoints { from_dir, to_dir }) = &args.command {
+        let report = checkpoint_migration::migrate_checkpoints(from_dir, to_dir)?;
+        info!("Migrated {} of {} checkpoints, report written to {}/{}",
+              report.entries.len() - report.failure_count(), report.entries.len(),
+              to_dir, checkpoint_migration::MIGRATION_REPORT_FILE);
+        if report.failure_count() > 0 {
+            return Err(format!("{} checkpoints could not be migrated", report.failure_count()).into());
+        }
+        return Ok(());
+    }
+    
+    if let Some(Command::ExportState { checkpoint_dir, out }) = &args.command {
+        let snapshot = state_export::export_state(checkpoint_dir, out)?;
+        info!("Exported {} accounts with state root {} to {}", snapshot.accounts.len(), snapshot.state_root, out);
+        return Ok(());
+    }
+    
+    if let Some(Command::ImportState { state, checkpoint_dir }) = &args.command {
+        let path = state_export::import_state(state, checkpoint_dir)?;
+        info!("Imported {} as checkpoint {}", state, path.display());
+        return Ok(());
+    }
+    
+    // Make sure no other node instance uses the same directories
+    let instance_lock = InstanceLock::acquire(
+        &[&args.data_dir, &args.checkpoint_dir, &args.chain_store_dir],
+        args.force_unlock,
+    )?;
+    for path in instance_lock.paths() {
+        info!("Holding instance lock {}", path.display());
+    }
+    
+    // Calculate RPC port if not specified
+    let rpc_port = args.rpc_port.unwrap_or(args.port - 20400);
+    
+    // Create P2P network address
+    let p2p_addr = format!("{}:{}", args.p2p_host, args.port);
+    let p2p_socket_addr = p2p_addr.parse::<SocketAddr>().expect("Invalid P2P address");
+    info!("Starting P2P network on {}", p2p_addr);
     
     // Create RPC server address
     let rpc_addr = format!("{}:{}", args.rpc_host, rpc_port);
     info!("Starting RPC server on {}", rpc_addr);
     
-    // Create Ethereum RPC server address
-    let eth_rpc_addr = format!("{}:{}", args.eth_rpc_host, args.eth_rpc_port);
+    // Create Ethereum RPC server addresses, from the host and ports unless given explicitly
+    let eth_rpc_addrs = if args.eth_rpc_addrs.is_empty() {
+        vec![format!("{}:{}", args.eth_rpc_host, args.eth_rpc_port)]
+    } else {
+        args.eth_rpc_addrs.iter().map(|addr| addr.to_string()).collect()
+    };
+    let eth_ws_addrs = if args.eth_ws_addrs.is_empty() {
+        vec![format!("{}:{}", args.eth_rpc_host, args.eth_ws_port)]
+    } else {
+        args.eth_ws_addrs.iter().map(|addr| addr.to_string()).collect()
+    };
     
-    // Generate a node address based on the port (for simplicity)
-    // In a production environment, this would be a proper Ethereum address
-    let node_address = format!("0x{:040x}", args.port);
+    // Load the persistent node identity, generating one on first start
+    let identity = NodeIdentity::load_or_create(&args.data_dir)?;
+    let node_address = identity.address.clone();
     info!("Node address: {}", node_address);
     
+    if !rpc::is_valid_eth_address(&args.burn_address.to_lowercase()) {
+        return Err(format!("Invalid --burn-address {}", args.burn_address).into());
+    }
+    
+    // The genesis file, not this node's flags, sets the chain settings every node must share
+    let genesis = args.genesis.as_ref().map(runtime::genesis::GenesisConfig::load).transpose()?;
+    
     // Initialize blockchain runtime with custom checkpoint configuration
-    let runtime = Runtime::with_checkpoint_config(
-        20, // Keep up to 20 checkpoints
-        "./checkpoints" // Use the checkpoints directory in the current working directory
-    );
-    info!("Initialized blockchain runtime");
+    let mut runtime_config = genesis.as_ref().map(runtime::genesis::GenesisConfig::runtime_config).unwrap_or_default();
+    if let Some(ubi_rate) = args.ubi_rate {
+        warn!("--ubi-rate {} overrides the genesis UBI rate on this node only; it diverges from nodes without it", ubi_rate);
+        runtime_config.ubi_rate_per_hour = ubi_rate;
+    }
+    if let Some(max_hours) = args.max_ubi_accrual_hours {
+        warn!("--max-ubi-accrual-hours {} overrides the genesis accrual cap on this node only; it diverges from nodes without it", max_hours);
+        runtime_config.max_ubi_accrual_hours = max_hours;
+    }
+    let runtime = if args.no_checkpoints {
+        warn!("Checkpoints are disabled; only the state store keeps the state");
+        Runtime::with_config(runtime_config).with_burn_address(&args.burn_address)
+    } else {
+        Runtime::with_checkpoint_config(
+            20, // Keep up to 20 checkpoints
+            &args.checkpoint_dir,
+        ).map_err(|e| format!("{}. Fix the directory, choose another with --checkpoint-dir, or start with --no-checkpoints", e))?
+        .with_runtime_config(runtime_config)
+        .with_max_checkpoint_accounts(args.max_checkpoint_accounts)
+        .with_checkpoint_compression(args.compress_checkpoints)
+        .with_burn_address(&args.burn_address)
+    };
+    
+    // Start from the newest checkpoint; the state store, if it holds state, is newer still
+    if !args.no_checkpoints {
+        match runtime.restore_latest() {
+            Ok(Some(checkpoint)) => info!("Restored checkpoint {} with {} accounts", checkpoint.file_path, checkpoint.account_count),
+            Ok(None) => info!("No checkpoint to restore in {}", args.checkpoint_dir),
+            Err(e) => warn!("Failed to read checkpoints in {}: {}", args.checkpoint_dir, e),
+        }
+    }
+    
+    // Account state lives in the data directory, so balances survive a restart
+    let state_dir = std::path::Path::new(&args.data_dir).join(STATE_STORE_DIR);
+    let runtime = runtime.with_storage(&state_dir)
+        .map_err(|e| format!("Failed to open the state store in {}: {}", state_dir.display(), e))?;
+    info!("Initialized blockchain runtime from {} (total supply {})", state_dir.display(), runtime.get_total_supply());
+    
+    // Fund the faucet and open the bootstrap window of a brand-new network
+    if let Some(genesis) = &genesis {
+        runtime.apply_genesis(genesis, &node_address)?;
+        if let Some(faucet) = runtime.genesis_faucet() {
+            info!(address = faucet.as_str(); "Genesis faucet funded: {}", faucet);
+        }
+        if let Some(bootstrap) = runtime.bootstrap_status() {
+            info!("Bootstrap window open until {} (auto-verify: {})", bootstrap.ends_at, bootstrap.auto_verify);
+        }
+    }
+    
+    if args.migrate_port_account {
+        identity::migrate_port_account(&runtime, args.port, &identity)?;
+    }
+    
+    // Replace the default faucet tiers with the operator's
+    if let Some(path) = &args.faucet_tiers {
+        let tiers: Vec<runtime::faucet::FaucetTier> = serde_json::from_str(&std::fs::read_to_string(path)?)
+            .map_err(|e| format!("Invalid faucet tiers file {}: {}", path, e))?;
+        info!("Faucet tiers: {:?}", tiers.iter().map(|tier| tier.name.as_str()).collect::<Vec<_>>());
+        runtime.set_faucet_tiers(tiers);
+    }
+    
+    // Pay faucet grants from the operator's sources, in order
+    if let Some(sources) = &args.faucet_sources {
+        let sources: Vec<String> = sources.split(',')
+            .filter(|source| !source.trim().is_empty())
+            .map(str::to_string)
+            .collect();
+        runtime.set_faucet_sources(sources)?;
+        info!("Faucet sources: {:?}", runtime.faucet_sources());
+    }
     
     // Create RPC handler
     let mut rpc_handler = rpc::RpcHandler::new(runtime.clone());
@@ -445,6 +1395,101 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     rpc_handler.set_node_address(node_address.clone());
     info!("Set node address as faucet address: {}", node_address);
     
+    // Share the block producer settings with the RPC layer
+    rpc_handler.set_node_info(rpc::NodeInfo {
+        chain_id: args.chain_id,
+        genesis_hash: GENESIS_HASH.to_string(),
+        block_time_ms: BLOCK_TIME_MS,
+        version: env!("CARGO_PKG_VERSION").to_string(),
+    });
+    
+    // Let the bootstrap admin schedule chain parameter changes
+    if let Some(param_admin) = &args.param_admin {
+        rpc_handler.set_param_admin(param_admin.clone());
+        info!("Chain parameter admin: {}", param_admin);
+    }
+    
+    // Let the account admin move accounts between deployments
+    if let Some(account_admin) = &args.account_admin {
+        rpc_handler.set_account_admin(account_admin.clone());
+        info!("Account admin: {}", account_admin);
+    }
+    
+    // Let the operations admin force housekeeping
+    if let Some(ops_admin) = &args.ops_admin {
+        rpc_handler.set_ops_admin(ops_admin.clone());
+        info!("Operations admin: {}", ops_admin);
+    }
+    rpc_handler.set_log_handle(log_handle);
+    
+    // Labels are node-local display metadata, kept in the data directory rather than in checkpoints
+    let labels_file = args.labels_file.clone()
+        .unwrap_or_else(|| std::path::Path::new(&args.data_dir).join(rpc::labels::LABELS_FILE));
+    let labels = rpc::labels::LabelRegistry::load(&labels_file)?;
+    info!("Loaded {} address labels from {}", labels.all().len(), labels_file.display());
+    rpc_handler.set_label_registry(labels);
+    
+    // Cap concurrent RPC requests so they cannot starve block production
+    let mut concurrency_config = rpc::ConcurrencyConfig {
+        max_in_flight: args.rpc_max_in_flight,
+        queue_timeout: Duration::from_millis(args.rpc_queue_timeout_ms),
+        ..Default::default()
+    };
+    concurrency_config.method_limits.extend(args.rpc_method_limits.iter().cloned());
+    info!("RPC concurrency limits: {} in flight, per method {:?}",
+          concurrency_config.max_in_flight, concurrency_config.method_limits);
+    rpc_handler.set_concurrency_config(concurrency_config);
+    
+    // Bound what a slow WebSocket client can make the node buffer
+    rpc_handler.set_subscription_config(args.ws_notification_queue, args.ws_slow_consumer_policy);
+    
+    // Serve wallet polling from a cache that is cleared on every sealed block
+    rpc_handler.set_response_cache_ttl(Duration::from_millis(args.rpc_cache_ttl_ms));
+    
+    // Remember idempotency keys so retried faucet requests and transfers run once
+    rpc_handler.set_idempotency_config(rpc::IdempotencyConfig {
+        ttl: Duration::from_secs(args.idempotency_ttl_secs),
+        ..Default::default()
+    });
+    
+    // Make bots pay for faucet grants and new accounts with a little work
+    if !args.pow_endpoints.is_empty() {
+        let pow_config = rpc::pow::PowConfig {
+            endpoints: args.pow_endpoints.iter().copied().collect(),
+            min_difficulty: args.pow_min_difficulty,
+            max_difficulty: args.pow_max_difficulty.max(args.pow_min_difficulty),
+            target_requests: args.pow_target_requests,
+            window_secs: args.pow_window_secs,
+            challenge_ttl_secs: args.pow_challenge_ttl_secs,
+        };
+        info!("Proof of work required for {:?}, difficulty {} to {} bits",
+              pow_config.endpoints, pow_config.min_difficulty, pow_config.max_difficulty);
+        rpc_handler.set_pow_config(pow_config);
+    }
+    
+    // Use a seeded random source in deterministic dev mode
+    if args.deterministic {
+        let seed = args.seed
+            .or_else(|| rpc_handler.random.seed())
+            .unwrap_or_else(|| {
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_nanos() as u64
+            });
+        rpc_handler.set_random_source(rpc::RandomSource::seeded(seed));
+        info!("Deterministic mode enabled, random seed: {}", seed);
+    } else if let Some(seed) = rpc_handler.random.seed() {
+        info!("Random seed from {}: {}", rpc::random::RANDOM_SEED_ENV_VAR, seed);
+    }
+    
+    // Blocks and transactions beyond these limits are spilled to the chain store directory
+    let retention = rpc::RetentionConfig {
+        max_blocks: args.max_cached_blocks,
+        max_transactions: args.max_cached_transactions,
+        spill_dir: args.chain_store_dir.clone(),
+    };
+    
     // Create channels for transactions and blocks
     let (tx_sender, _) = broadcast::channel(100);
     let (block_sender, mut block_receiver) = mpsc::channel(100);
@@ -452,21 +1497,43 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Create block producer
     let block_producer = Arc::new(BlockProducer::new(
         runtime.clone(),
-        1000, // 1 second block time
-        format!("node-{}", args.port),
+        BLOCK_TIME_MS,
+        identity.node_id(),
         node_address.clone(),
         tx_sender,
         block_sender,
-    ));
+    ).with_max_clock_drift(args.max_clock_drift)
+        .with_block_retention(&retention)
+        .with_maintenance_blocks(args.maintenance_blocks));
     
     // Set the block producer reference in the runtime
     runtime.set_block_producer(block_producer.clone());
     
-    // Start block production
+    // Start block production, and shut down or enter maintenance mode if it stops for good
     let block_producer_clone = block_producer.clone();
-    tokio::spawn(async move {
-        block_producer_clone.start().await;
+    let production_task = tokio::spawn(async move {
+        block_producer_clone.start().await
     });
+    let mut production_watch = Box::pin(production::watch_production(
+        production_task,
+        block_producer.clone(),
+        runtime.clone(),
+        args.on_production_halt,
+    ));
+    
+    // Flag the node as stalled when it stops producing and importing blocks
+    tokio::spawn(watchdog::run_watchdog(block_producer.clone(), rpc_handler.eth_subscriptions.clone()));
+    
+    // Run recurring work as scheduled jobs. Checkpoints back off while the disk refuses them
+    if !args.no_checkpoints {
+        let checkpoint_runtime = runtime.clone();
+        rpc_handler.scheduler.register(
+            "checkpoint",
+            rpc::scheduler::Schedule::Every(Duration::from_secs(args.checkpoint_interval_secs.max(1))),
+            move || checkpointing::write_checkpoint(checkpoint_runtime.clone()),
+        );
+    }
+    rpc_handler.scheduler.start();
     
     // Spawn a task to consume blocks from the channel
     tokio::spawn(async move {
@@ -476,26 +1543,51 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     });
     
-    // Start Ethereum-compatible JSON-RPC server if not disabled
-    let _eth_server = if !args.disable_eth_rpc {
-        info!("Starting Ethereum-compatible JSON-RPC server on {}", eth_rpc_addr);
-        match rpc_handler.start_eth_rpc_server(&eth_rpc_addr, args.chain_id) {
-            Ok(server) => {
-                info!("Ethereum-compatible JSON-RPC server started successfully");
-                Some(server)
-            },
-            Err(e) => {
-                error!("Failed to start Ethereum-compatible JSON-RPC server: {}", e);
-                None
+    // Start Ethereum-compatible JSON-RPC servers if not disabled. Each HTTP address is paired with
+    // a WebSocket address into a combined server that shuts down gracefully; unpaired addresses get a
+    // server of their own. The WebSocket servers share the HTTP servers' subscriptions, so transfers
+    // sent over HTTP reach subscribers.
+    let mut eth_servers = Vec::new();
+    let mut eth_http_servers = Vec::new();
+    let mut eth_ws_servers = Vec::new();
+    if !args.disable_eth_rpc {
+        rpc::eth_compat::configure_retention(&retention);
+        for (eth_rpc_addr, eth_ws_addr) in eth_rpc_addrs.iter().zip(&eth_ws_addrs) {
+            info!("Starting Ethereum-compatible servers on {} (HTTP) and {} (WebSocket)", eth_rpc_addr, eth_ws_addr);
+            match rpc_handler.start_combined_server(eth_rpc_addr, eth_ws_addr, args.chain_id).await {
+                Ok(server) => {
+                    info!("Ethereum-compatible servers started successfully on {} and {}", server.http_addr, server.ws_addr);
+                    eth_servers.push(server);
+                },
+                Err(e) => error!("Failed to start Ethereum-compatible servers on {} and {}: {}", eth_rpc_addr, eth_ws_addr, e),
+            }
+        }
+        for eth_rpc_addr in eth_rpc_addrs.iter().skip(eth_ws_addrs.len()) {
+            info!("Starting Ethereum-compatible JSON-RPC server on {}", eth_rpc_addr);
+            match rpc_handler.start_eth_rpc_server(eth_rpc_addr, args.chain_id) {
+                Ok((server, bound_addr)) => {
+                    info!("Ethereum-compatible JSON-RPC server started successfully on {}", bound_addr);
+                    eth_http_servers.push(server);
+                },
+                Err(e) => error!("Failed to start Ethereum-compatible JSON-RPC server on {}: {}", eth_rpc_addr, e),
+            }
+        }
+        for eth_ws_addr in eth_ws_addrs.iter().skip(eth_rpc_addrs.len()) {
+            info!("Starting Ethereum-compatible WebSocket server on {}", eth_ws_addr);
+            match rpc_handler.start_eth_ws_server(eth_ws_addr, args.chain_id).await {
+                Ok((server, bound_addr)) => {
+                    info!("Ethereum-compatible WebSocket server started successfully on {}", bound_addr);
+                    eth_ws_servers.push(server);
+                },
+                Err(e) => error!("Failed to start Ethereum-compatible WebSocket server on {}: {}", eth_ws_addr, e),
             }
         }
     } else {
         info!("Ethereum-compatible JSON-RPC server disabled");
-        None
-    };
+    }
     
     // Start P2P network
-    let _p2p_network = P2PNetwork::new(p2p_socket_addr);
+    let _p2p_network = P2PNetwork::new(p2p_socket_addr, identity.node_id(), block_producer.peer_tracker());
     
     // Connect to peers if specified
     if let Some(peers) = args.peers {
@@ -521,13 +1613,33 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     info!("UBI Chain testnet node started successfully");
     info!("Faucet service available via RPC endpoint");
     
-    // Keep the main task running indefinitely
-    // This approach avoids the issue with dropping the runtime in an async context
-    std::future::pending::<()>().await;
+    // Run until interrupted or until block production stops and asks for a shutdown, then release the directory locks
+    tokio::select! {
+        signal = tokio::signal::ctrl_c() => if let Err(e) = signal {
+            error!("Failed to listen for the shutdown signal: {}", e);
+            production_watch.await;
+        },
+        _ = &mut production_watch => {},
+    }
+    info!("Shutting down");
+    block_producer.stop();
+    
+    // Stop accepting Ethereum RPC connections and let requests already running finish
+    for server in eth_servers {
+        let drained = tokio::task::spawn_blocking(move || server.close()).await.unwrap_or(false);
+        if !drained {
+            warn!("Ethereum-compatible servers did not drain before the timeout");
+        }
+    }
+    eth_http_servers.into_iter().for_each(|server| server.close());
+    eth_ws_servers.into_iter().for_each(|server| server.close());
+    if let Err(e) = runtime.flush_state_store() {
+        error!("Failed to flush the state store: {}", e);
+    }
+    drop(instance_lock);
     
-    // This line will never be reached, but we keep it for type correctness
-    #[allow(unreachable_code)]
-    Ok(())
+    // Exit directly; dropping the runtime in an async context with tasks still running panics
+    std::process::exit(0)
 }
 
 /// RPC server implementation
@@ -559,75 +1671,12 @@ async fn run_rpc_server(addr: &str, rpc_handler: rpc::RpcHandler) -> Result<(),
                             if let Ok(request_str) = String::from_utf8(buf[..n].to_vec()) {
                                 debug!("RPC request from {}: {}", peer_addr, request_str);
                                 
-                                // Parse the JSON-RPC request
-                                let response = if let Ok(request) = serde_json::from_str::<serde_json::Value>(&request_str) {
-                                    if let Some(method) = request.get("method").and_then(|m| m.as_str()) {
-                                        match method {
-                                            "getAccountInfo" => {
-                                                trace!("Processing getAccountInfo request");
-                                                if let Some(params) = request.get("params").and_then(|p| p.as_array()) {
-                                                    if let Some(address) = params.first().and_then(|a| a.as_str()) {
-                                                        let response = handler.get_account_info(address.to_string());
-                                                        serde_json::to_string(&response).unwrap_or_default()
-                                                    } else {
-                                                        r#"{"error": "Missing address parameter"}"#.to_string()
-                                                    }
-                                                } else {
-                                                    r#"{"error": "Invalid parameters"}"#.to_string()
-                                                }
-                                            },
-                                            "createAccount" => {
-                                                trace!("Processing createAccount request");
-                                                if let Some(params) = request.get("params").and_then(|p| p.as_array()) {
-                                                    if let Some(address) = params.first().and_then(|a| a.as_str()) {
-                                                        let response = handler.create_account(address.to_string());
-                                                        serde_json::to_string(&response).unwrap_or_default()
-                                                    } else {
-                                                        r#"{"error": "Missing address parameter"}"#.to_string()
-                                                    }
-                                                } else {
-                                                    r#"{"error": "Invalid parameters"}"#.to_string()
-                                                }
-                                            },
-                                            "requestFromFaucet" => {
-                                                trace!("Processing requestFromFaucet request");
-                                                if let Some(params) = request.get("params").and_then(|p| p.as_array()) {
-                                                    if let Some(address) = params.first().and_then(|a| a.as_str()) {
-                                                        // Get optional amount parameter
-                                                        let amount = params.get(1)
-                                                            .and_then(|a| a.as_u64());
-                                                        
-                                                        info!("Faucet request from {}: address={}, amount={:?}", 
-                                                             peer_addr, address, amount);
-                                                        
-                                                        let response = handler.request_from_faucet(address.to_string(), amount).await;
-                                                        
-                                                        if response.success {
-                                                            info!("Faucet request successful: sent {} tokens to {}, new balance: {}",
-                                                                 response.amount.unwrap_or(0), address, response.new_balance.unwrap_or(0));
-                                                        } else {
-                                                            warn!("Faucet request failed: {}", response.error.as_ref().unwrap_or(&String::new()));
-                                                        }
-                                                        
-                                                        serde_json::to_string(&response).unwrap_or_default()
-                                                    } else {
-                                                        r#"{"error": "Missing address parameter"}"#.to_string()
-                                                    }
-                                                } else {
-                                                    r#"{"error": "Invalid parameters"}"#.to_string()
-                                                }
-                                            },
-                                            _ => {
-                                                debug!("Unhandled RPC method: {}", method);
-                                                r#"{"error": "Method not found"}"#.to_string()
-                                            }
-                                        }
-                                    } else {
-                                        r#"{"error": "Invalid request, missing method"}"#.to_string()
-                                    }
-                                } else {
-                                    r#"{"error": "Invalid JSON-RPC request"}"#.to_string()
-                                };
+                                // Every log line for this request carries the same correlation id
+                                let correlation_id = rpc::logging::new_correlation_id(&handler.random);
+                                let response = rpc::logging::with_correlation_id(
+                                    correlation_id,
+                                    handle_rpc_request(&handler, &request_str, peer_addr),
+                                ).await;
                                 
                                 debug!("Sending response to {}: {}", peer_addr, response);
                                 if let Err(e) = socket.write_all(response.as_bytes()).await {
@@ -646,4 +1695,1618 @@ async fn run_rpc_server(addr: &str, rpc_handler: rpc::RpcHandler) -> Result<(),
             }
         }
     }
-}
\ No newline at end of file
+}
+
+/// Builds a native RPC error response body
+///
+/// Includes the request's correlation id, if any, so users can quote it in bug reports.
+fn rpc_error(message: &str) -> String {
+    match rpc::logging::current_correlation_id() {
+        Some(correlation_id) => serde_json::json!({
+            "error": message,
+            (rpc::logging::CORRELATION_ID_FIELD): correlation_id,
+        }).to_string(),
+        None => serde_json::json!({ "error": message }).to_string(),
+    }
+}
+
+/// Reads an optional idempotency key parameter
+fn idempotency_key_param(value: Option<&serde_json::Value>) -> Result<Option<String>, String> {
+    match value {
+        None | Some(serde_json::Value::Null) => Ok(None),
+        Some(serde_json::Value::String(key)) => Ok(Some(key.clone())),
+        Some(_) => Err("Invalid idempotency key parameter".to_string()),
+    }
+}
+
+/// Builds a native RPC response body for a request rejected as busy
+fn rpc_busy_error(busy: &rpc::concurrency::ServerBusy) -> String {
+    let mut body = serde_json::json!({
+        "error": "server busy",
+        "retry_after_ms": busy.retry_after_ms,
+    });
+    if let Some(correlation_id) = rpc::logging::current_correlation_id() {
+        body[rpc::logging::CORRELATION_ID_FIELD] = serde_json::json!(correlation_id);
+    }
+    body.to_string()
+}
+
+/// Dispatches a single native JSON-RPC request and returns the response body
+///
+/// # Arguments
+/// * `handler` - The RPC handler to serve the request with
+/// * `request_str` - The raw request body
+/// * `peer_addr` - Address of the client, used for logging
+async fn handle_rpc_request(handler: &rpc::RpcHandler, request_str: &str, peer_addr: SocketAddr) -> String {
+    // Parse the JSON-RPC request
+    let request = match serde_json::from_str::<serde_json::Value>(request_str) {
+        Ok(request) => request,
+        Err(_) => return rpc_error("Invalid JSON-RPC request"),
+    };
+    
+    let method = match request.get("method").and_then(|m| m.as_str()) {
+        Some(method) => method,
+        None => return rpc_error("Invalid request, missing method"),
+    };
+    
+    // Hold a concurrency slot, shared with the Ethereum servers, while handling the request
+    let _permit = match handler.concurrency.acquire(method).await {
+        Ok(permit) => permit,
+        Err(busy) => {
+            warn!(peer_addr = peer_addr.to_string().as_str(), method = method; "{}", busy);
+            return rpc_busy_error(&busy);
+        }
+    };
+    
+    let params: &[serde_json::Value] = request.get("params")
+        .and_then(|p| p.as_array())
+        .map(|p| p.as_slice())
+        .unwrap_or(&[]);
+    
+    match method {
+        "getAccountInfo" | "getAccountDetail" => {
+            trace!("Processing getAccountInfo request");
+            if let Some(address) = params.first().and_then(|a| a.as_str()) {
+                let response = handler.get_account_info(address.to_string());
+                serde_json::to_string(&response).unwrap_or_default()
+            } else {
+                rpc_error("Missing address parameter")
+            }
+        },
+        "createAccount" => {
+            trace!("Processing createAccount request");
+            if let Some(address) = params.first().and_then(|a| a.as_str()) {
+                let pow_solution = match rpc::parse_pow_solution(params.get(1)) {
+                    Ok(solution) => solution,
+                    Err(e) => return rpc_error(&e),
+                };
+                let response = handler.create_account_with_pow(address.to_string(), pow_solution.as_ref());
+                serde_json::to_string(&response).unwrap_or_default()
+            } else {
+                rpc_error("Missing address parameter")
+            }
+        },
+        "getPowChallenge" => {
+            trace!("Processing getPowChallenge request");
+            if let Some(endpoint) = params.first().and_then(|e| e.as_str()) {
+                match handler.get_pow_challenge(endpoint) {
+                    Ok(challenge) => serde_json::to_string(&challenge).unwrap_or_default(),
+                    Err(e) => rpc_error(&e),
+                }
+            } else {
+                rpc_error("Missing endpoint parameter")
+            }
+        },
+        "getChainInfo" => {
+            trace!("Processing getChainInfo request");
+            serde_json::to_string(&handler.get_chain_info()).unwrap_or_default()
+        },
+        "getNetworkStatus" => {
+            trace!("Processing getNetworkStatus request");
+            serde_json::to_string(&handler.get_network_status()).unwrap_or_default()
+        },
+        "getSyncStatus" => {
+            trace!("Processing getSyncStatus request");
+            serde_json::to_string(&handler.get_sync_status()).unwrap_or_default()
+        },
+        "submitTransaction" => {
+            trace!("Processing submitTransaction request");
+            let transfer = match params.first() {
+                Some(transfer) if transfer.is_object() => transfer,
+                _ => return rpc_error("Missing transaction parameter"),
+            };
+            let (from, to) = match (transfer.get("from").and_then(|v| v.as_str()), transfer.get("to").and_then(|v| v.as_str())) {
+                (Some(from), Some(to)) => (from, to),
+                _ => return rpc_error("Transaction must have from and to addresses"),
+            };
+            let amount = match transfer.get("amount").map(rpc::parse_amount) {
+                Some(Ok(amount)) => amount,
+                Some(Err(e)) => return rpc_error(&format!("Invalid amount parameter: {}", e)),
+                None => return rpc_error("Missing amount parameter"),
+            };
+            let mode = match rpc::parse_transfer_mode(transfer.get("mode")) {
+                Ok(mode) => mode,
+                Err(e) => return rpc_error(&e),
+            };
+            let idempotency_key = match idempotency_key_param(params.get(1)) {
+                Ok(key) => key,
+                Err(e) => return rpc_error(&e),
+            };
+            
+            let response = handler.submit_transaction(from.to_string(), to.to_string(), amount, mode, idempotency_key).await;
+            serde_json::to_string(&response).unwrap_or_default()
+        },
+        "getSubscriptionStats" => {
+            trace!("Processing getSubscriptionStats request");
+            serde_json::to_string(&handler.get_subscription_stats()).unwrap_or_default()
+        },
+        "getRpcMetrics" => {
+            trace!("Processing getRpcMetrics request");
+            serde_json::to_string(&handler.get_rpc_metrics()).unwrap_or_default()
+        },
+        "getAccountLimits" => {
+            trace!("Processing getAccountLimits request");
+            if let Some(address) = params.first().and_then(|a| a.as_str()) {
+                serde_json::to_string(&handler.get_account_limits(address.to_string())).unwrap_or_default()
+            } else {
+                rpc_error("Missing address parameter")
+            }
+        },
+        "setAccountLimits" => {
+            trace!("Processing setAccountLimits request");
+            let address = match params.first().and_then(|a| a.as_str()) {
+                Some(address) => address,
+                None => return rpc_error("Missing address parameter"),
+            };
+            let limits = match params.get(1).map(rpc::parse_account_limits) {
+                Some(Ok(limits)) => limits,
+                Some(Err(e)) => return rpc_error(&e),
+                None => return rpc_error("Missing limits parameter"),
+            };
+            let signature = match params.get(2).and_then(|s| s.as_str()) {
+                Some(signature) => signature,
+                None => return rpc_error("Missing signature parameter"),
+            };
+            
+            let response = handler.set_account_limits(address.to_string(), limits, signature);
+            if !response.success {
+                warn!(address = address; "Account limits change rejected: {}", response.error.as_ref().unwrap_or(&String::new()));
+            }
+            serde_json::to_string(&response).unwrap_or_default()
+        },
+        "getTransactionStatus" => {
+            trace!("Processing getTransactionStatus request");
+            if let Some(hash) = params.first().and_then(|h| h.as_str()) {
+                serde_json::to_string(&handler.get_transaction_status(hash)).unwrap_or_default()
+            } else {
+                rpc_error("Missing transaction hash parameter")
+            }
+        },
+        "traceTransactionLifecycle" => {
+            trace!("Processing traceTransactionLifecycle request");
+            if let Some(hash) = params.first().and_then(|h| h.as_str()) {
+                serde_json::to_string(&handler.trace_transaction_lifecycle(hash)).unwrap_or_default()
+            } else {
+                rpc_error("Missing transaction hash parameter")
+            }
+        },
+        "getBlockByNumber" => {
+            trace!("Processing getBlockByNumber request");
+            let number = match params.first() {
+                Some(serde_json::Value::String(tag)) if tag == "latest" => handler.get_chain_info().block_height,
+                Some(number) => match rpc::parse_amount(number) {
+                    Ok(number) => number,
+                    Err(e) => return rpc_error(&format!("Invalid block number parameter: {}", e)),
+                },
+                None => return rpc_error("Missing block number parameter"),
+            };
+            serde_json::to_string(&handler.get_block_by_number(number)).unwrap_or_default()
+        },
+        "getBlockExtras" => {
+            trace!("Processing getBlockExtras request");
+            let number = match params.first() {
+                Some(serde_json::Value::String(tag)) if tag == "latest" => handler.get_chain_info().block_height,
+                Some(number) => match rpc::parse_amount(number) {
+                    Ok(number) => number,
+                    Err(e) => return rpc_error(&format!("Invalid block number parameter: {}", e)),
+                },
+                None => return rpc_error("Missing block number parameter"),
+            };
+            serde_json::to_string(&handler.get_block_extras(number)).unwrap_or_default()
+        },
+        "getLatestBlocks" => {
+            trace!("Processing getLatestBlocks request");
+            let count = match rpc::parse_optional_amount(params.first()) {
+                Ok(count) => count.unwrap_or(rpc::DEFAULT_LATEST_BLOCKS as u64),
+                Err(e) => return rpc_error(&format!("Invalid count parameter: {}", e)),
+            };
+            serde_json::to_string(&handler.get_latest_blocks(count as usize)).unwrap_or_default()
+        },
+        "getFeeSchedule" => {
+            trace!("Processing getFeeSchedule request");
+            serde_json::to_string(&handler.get_fee_schedule()).unwrap_or_default()
+        },
+        "scheduleParamChange" => {
+            trace!("Processing scheduleParamChange request");
+            let param = match params.first().and_then(|p| p.as_str()) {
+                Some(param) => param,
+                None => return rpc_error("Missing param parameter"),
+            };
+            let new_value = match params.get(1).map(rpc::parse_amount) {
+                Some(Ok(value)) => value,
+                Some(Err(e)) => return rpc_error(&format!("Invalid new value parameter: {}", e)),
+                None => return rpc_error("Missing new value parameter"),
+            };
+            let activates_at_block = match params.get(2).map(rpc::parse_amount) {
+                Some(Ok(block)) => block,
+                Some(Err(e)) => return rpc_error(&format!("Invalid activation block parameter: {}", e)),
+                None => return rpc_error("Missing activation block parameter"),
+            };
+            let signature = match params.get(3).and_then(|s| s.as_str()) {
+                Some(signature) => signature,
+                None => return rpc_error("Missing signature parameter"),
+            };
+            
+            let response = handler.schedule_param_change(param, new_value, activates_at_block, signature);
+            if !response.success {
+                warn!("Chain parameter change rejected: {}", response.error.as_ref().unwrap_or(&String::new()));
+            }
+            serde_json::to_string(&response).unwrap_or_default()
+        },
+        "burn" => {
+            trace!("Processing burn request");
+            let address = match params.first().and_then(|a| a.as_str()) {
+                Some(address) => address,
+                None => return rpc_error("Missing address parameter"),
+            };
+            let amount = match params.get(1).map(rpc::parse_amount) {
+                Some(Ok(amount)) => amount,
+                Some(Err(e)) => return rpc_error(&format!("Invalid amount: {}", e)),
+                None => return rpc_error("Missing amount parameter"),
+            };
+            let signature = match params.get(2).and_then(|s| s.as_str()) {
+                Some(signature) => signature,
+                None => return rpc_error("Missing signature parameter"),
+            };
+            
+            let response = handler.burn(address, amount, signature);
+            if !response.success {
+                warn!(address = address; "Burn rejected: {}", response.error.as_ref().unwrap_or(&String::new()));
+            }
+            serde_json::to_string(&response).unwrap_or_default()
+        },
+        "batchTransfer" => {
+            trace!("Processing batchTransfer request");
+            let from = match params.first().and_then(|v| v.as_str()) {
+                Some(from) => from,
+                None => return rpc_error("Missing sender parameter"),
+            };
+            let legs = match params.get(1).map(rpc::parse_batch_legs) {
+                Some(Ok(legs)) => legs,
+                Some(Err(e)) => return rpc_error(&e),
+                None => return rpc_error("Missing transfers parameter"),
+            };
+            let signature = match params.get(2).and_then(|s| s.as_str()) {
+                Some(signature) => signature,
+                None => return rpc_error("Missing signature parameter"),
+            };
+            serde_json::to_string(&handler.batch_transfer(from, legs, signature)).unwrap_or_default()
+        },
+        "approve" => {
+            trace!("Processing approve request");
+            let (owner, spender) = match (params.first().and_then(|v| v.as_str()), params.get(1).and_then(|v| v.as_str())) {
+                (Some(owner), Some(spender)) => (owner, spender),
+                _ => return rpc_error("Missing owner or spender parameter"),
+            };
+            let amount = match params.get(2).map(rpc::parse_amount) {
+                Some(Ok(amount)) => amount,
+                Some(Err(e)) => return rpc_error(&format!("Invalid amount: {}", e)),
+                None => return rpc_error("Missing amount parameter"),
+            };
+            let signature = match params.get(3).and_then(|s| s.as_str()) {
+                Some(signature) => signature,
+                None => return rpc_error("Missing signature parameter"),
+            };
+            serde_json::to_string(&handler.approve(owner, spender, amount, signature)).unwrap_or_default()
+        },
+        "setAutoCompound" => {
+            trace!("Processing setAutoCompound request");
+            let address = match params.first().and_then(|a| a.as_str()) {
+                Some(address) => address,
+                None => return rpc_error("Missing address parameter"),
+            };
+            let enabled = match params.get(1).and_then(|v| v.as_bool()) {
+                Some(enabled) => enabled,
+                None => return rpc_error("Missing enabled parameter"),
+            };
+            let signature = match params.get(2).and_then(|s| s.as_str()) {
+                Some(signature) => signature,
+                None => return rpc_error("Missing signature parameter"),
+            };
+            serde_json::to_string(&handler.set_auto_compound(address, enabled, signature)).unwrap_or_default()
+        },
+        "transferFrom" => {
+            trace!("Processing transferFrom request");
+            let addresses: Vec<&str> = params.iter().take(3).filter_map(|v| v.as_str()).collect();
+            let [spender, owner, recipient] = addresses[..] else {
+                return rpc_error("Missing spender, owner or recipient parameter");
+            };
+            let amount = match params.get(3).map(rpc::parse_amount) {
+                Some(Ok(amount)) => amount,
+                Some(Err(e)) => return rpc_error(&format!("Invalid amount: {}", e)),
+                None => return rpc_error("Missing amount parameter"),
+            };
+            let signature = match params.get(4).and_then(|s| s.as_str()) {
+                Some(signature) => signature,
+                None => return rpc_error("Missing signature parameter"),
+            };
+            
+            let response = handler.transfer_from(spender, owner, recipient, amount, signature);
+            if !response.success {
+                warn!(spender = spender; "Transfer from rejected: {}", response.error.as_ref().unwrap_or(&String::new()));
+            }
+            serde_json::to_string(&response).unwrap_or_default()
+        },
+        "openStream" => {
+            trace!("Processing openStream request");
+            let (from, to) = match (params.first().and_then(|v| v.as_str()), params.get(1).and_then(|v| v.as_str())) {
+                (Some(from), Some(to)) => (from, to),
+                _ => return rpc_error("Missing from or to parameter"),
+            };
+            let rate_per_hour = match params.get(2).map(rpc::parse_amount) {
+                Some(Ok(rate)) => rate,
+                Some(Err(e)) => return rpc_error(&format!("Invalid rate: {}", e)),
+                None => return rpc_error("Missing rate parameter"),
+            };
+            let signature = match params.get(3).and_then(|s| s.as_str()) {
+                Some(signature) => signature,
+                None => return rpc_error("Missing signature parameter"),
+            };
+            
+            let response = handler.open_stream(from, to, rate_per_hour, signature);
+            if !response.success {
+                warn!(from = from; "Stream open rejected: {}", response.error.as_ref().unwrap_or(&String::new()));
+            }
+            serde_json::to_string(&response).unwrap_or_default()
+        },
+        "closeStream" => {
+            trace!("Processing closeStream request");
+            let (stream_id, signature) = match (params.first().and_then(|v| v.as_u64()), params.get(1).and_then(|v| v.as_str())) {
+                (Some(stream_id), Some(signature)) => (stream_id, signature),
+                _ => return rpc_error("Expected stream id and signature parameters"),
+            };
+            serde_json::to_string(&handler.close_stream(stream_id, signature)).unwrap_or_default()
+        },
+        "getStreams" => {
+            trace!("Processing getStreams request");
+            let address = match params.first().and_then(|v| v.as_str()) {
+                Some(address) => address,
+                None => return rpc_error("Missing address parameter"),
+            };
+            match handler.get_streams(address) {
+                Ok(streams) => serde_json::to_string(&streams).unwrap_or_default(),
+                Err(e) => rpc_error(&e),
+            }
+        },
+        "getStats" => {
+            trace!("Processing getStats request");
+            match handler.get_stats(params.first().and_then(|v| v.as_str())) {
+                Ok(stats) => serde_json::to_string(&stats).unwrap_or_default(),
+                Err(e) => rpc_error(&e),
+            }
+        },
+        "claimUbi" => {
+            trace!("Processing claimUbi request");
+            let address = match params.first().and_then(|a| a.as_str()) {
+                Some(address) => address,
+                None => return rpc_error("Missing address parameter"),
+            };
+            
+            let response = handler.claim_ubi(address);
+            if !response.success {
+                warn!(address = address; "UBI claim rejected: {}", response.error.as_ref().unwrap_or(&String::new()));
+            }
+            serde_json::to_string(&response).unwrap_or_default()
+        },
+        "exportAccount" => {
+            trace!("Processing exportAccount request");
+            let (address, signature) = match (params.first().and_then(|a| a.as_str()), params.get(1).and_then(|s| s.as_str())) {
+                (Some(address), Some(signature)) => (address, signature),
+                _ => return rpc_error("Expected address and signature parameters"),
+            };
+            
+            let response = handler.export_account(address, signature);
+            if !response.success {
+                warn!("Account export rejected: {}", response.error.as_ref().unwrap_or(&String::new()));
+            }
+            serde_json::to_string(&response).unwrap_or_default()
+        },
+        "importAccount" => {
+            trace!("Processing importAccount request");
+            let export: runtime::account_export::AccountExport = match params.first().map(|e| serde_json::from_value(e.clone())) {
+                Some(Ok(export)) => export,
+                Some(Err(e)) => return rpc_error(&format!("Invalid export parameter: {}", e)),
+                None => return rpc_error("Missing export parameter"),
+            };
+            let signature = match params.get(1).and_then(|s| s.as_str()) {
+                Some(signature) => signature,
+                None => return rpc_error("Missing signature parameter"),
+            };
+            
+            let response = handler.import_account(&export, signature);
+            if !response.success {
+                warn!("Account import rejected: {}", response.error.as_ref().unwrap_or(&String::new()));
+            }
+            serde_json::to_string(&response).unwrap_or_default()
+        },
+        "getSupplyAudit" => {
+            trace!("Processing getSupplyAudit request");
+            let page = match rpc::PageRequest::from_param(params.first()) {
+                Ok(page) => page,
+                Err(e) => return rpc_error(&e),
+            };
+            match handler.get_supply_audit(page) {
+                Ok(audit) => serde_json::to_string(&audit).unwrap_or_default(),
+                Err(e) => rpc_error(&e),
+            }
+        },
+        "distributeFeesNow" | "audit" | "recomputeStateRoot" => {
+            trace!("Processing {} request", method);
+            let signature = match params.first().and_then(|s| s.as_str()) {
+                Some(signature) => signature,
+                None => return rpc_error("Missing signature parameter"),
+            };
+            
+            let (success, error, response) = match method {
+                "distributeFeesNow" => {
+                    let response = handler.distribute_fees_now(signature);
+                    (response.success, response.error.clone(), serde_json::to_string(&response))
+                },
+                "audit" => {
+                    let response = handler.audit(signature);
+                    (response.success, response.error.clone(), serde_json::to_string(&response))
+                },
+                _ => {
+                    let response = handler.recompute_state_root(signature);
+                    (response.success, response.error.clone(), serde_json::to_string(&response))
+                },
+            };
+            if !success {
+                warn!("Admin action {} rejected: {}", method, error.unwrap_or_default());
+            }
+            response.unwrap_or_default()
+        },
+        "setMaintenanceMode" => {
+            trace!("Processing setMaintenanceMode request");
+            let enabled = match params.first().and_then(|e| e.as_bool()) {
+                Some(enabled) => enabled,
+                None => return rpc_error("Missing enabled parameter"),
+            };
+            let reason = params.get(1).and_then(|r| r.as_str()).map(str::to_string);
+            let signature = match params.get(2).and_then(|s| s.as_str()) {
+                Some(signature) => signature,
+                None => return rpc_error("Missing signature parameter"),
+            };
+            
+            let response = handler.set_maintenance_mode(enabled, reason, signature);
+            if !response.success {
+                warn!("Maintenance mode change rejected: {}", response.error.as_ref().unwrap_or(&String::new()));
+            }
+            serde_json::to_string(&response).unwrap_or_default()
+        },
+        "setLogLevel" => {
+            trace!("Processing setLogLevel request");
+            let (target, level) = match (params.first().and_then(|t| t.as_str()), params.get(1).and_then(|l| l.as_str())) {
+                (Some(target), Some(level)) => (target, level),
+                _ => return rpc_error("Missing target or level parameter"),
+            };
+            let persist = params.get(2).and_then(|p| p.as_bool()).unwrap_or(false);
+            let signature = match params.get(3).and_then(|s| s.as_str()) {
+                Some(signature) => signature,
+                None => return rpc_error("Missing signature parameter"),
+            };
+            
+            let response = handler.set_log_level(target, level, persist, signature);
+            if !response.success {
+                warn!("Log level change rejected: {}", response.error.as_ref().unwrap_or(&String::new()));
+            }
+            serde_json::to_string(&response).unwrap_or_default()
+        },
+        "setLabel" => {
+            trace!("Processing setLabel request");
+            let (address, label, category) = match (
+                params.first().and_then(|a| a.as_str()),
+                params.get(1).and_then(|l| l.as_str()),
+                params.get(2).and_then(|c| c.as_str()),
+            ) {
+                (Some(address), Some(label), Some(category)) => (address, label, category),
+                _ => return rpc_error("Missing address, label or category parameter"),
+            };
+            let signature = match params.get(3).and_then(|s| s.as_str()) {
+                Some(signature) => signature,
+                None => return rpc_error("Missing signature parameter"),
+            };
+            
+            let response = handler.set_label(address, label, category, signature);
+            if !response.success {
+                warn!("Label change rejected: {}", response.error.as_ref().unwrap_or(&String::new()));
+            }
+            serde_json::to_string(&response).unwrap_or_default()
+        },
+        "removeLabel" => {
+            trace!("Processing removeLabel request");
+            let (address, signature) = match (params.first().and_then(|a| a.as_str()), params.get(1).and_then(|s| s.as_str())) {
+                (Some(address), Some(signature)) => (address, signature),
+                _ => return rpc_error("Missing address or signature parameter"),
+            };
+            
+            let response = handler.remove_label(address, signature);
+            if !response.success {
+                warn!("Label removal rejected: {}", response.error.as_ref().unwrap_or(&String::new()));
+            }
+            serde_json::to_string(&response).unwrap_or_default()
+        },
+        "freezeAccount" => {
+            trace!("Processing freezeAccount request");
+            let address = match params.first().and_then(|a| a.as_str()) {
+                Some(address) => address,
+                None => return rpc_error("Missing address parameter"),
+            };
+            let reason = params.get(1).and_then(|r| r.as_str());
+            let signature = match params.get(2).and_then(|s| s.as_str()) {
+                Some(signature) => signature,
+                None => return rpc_error("Missing signature parameter"),
+            };
+            
+            let response = handler.freeze_account(address, reason, signature);
+            if !response.success {
+                warn!("Account freeze rejected: {}", response.error.as_ref().unwrap_or(&String::new()));
+            }
+            serde_json::to_string(&response).unwrap_or_default()
+        },
+        "unfreezeAccount" => {
+            trace!("Processing unfreezeAccount request");
+            let (address, signature) = match (params.first().and_then(|a| a.as_str()), params.get(1).and_then(|s| s.as_str())) {
+                (Some(address), Some(signature)) => (address, signature),
+                _ => return rpc_error("Missing address or signature parameter"),
+            };
+            
+            let response = handler.unfreeze_account(address, signature);
+            if !response.success {
+                warn!("Account unfreeze rejected: {}", response.error.as_ref().unwrap_or(&String::new()));
+            }
+            serde_json::to_string(&response).unwrap_or_default()
+        },
+        "getScheduledJobs" => {
+            trace!("Processing getScheduledJobs request");
+            serde_json::to_string(&handler.get_scheduled_jobs()).unwrap_or_default()
+        },
+        "runJobNow" => {
+            trace!("Processing runJobNow request");
+            let (name, signature) = match (params.first().and_then(|n| n.as_str()), params.get(1).and_then(|s| s.as_str())) {
+                (Some(name), Some(signature)) => (name, signature),
+                _ => return rpc_error("Missing job name or signature parameter"),
+            };
+            
+            let response = handler.run_job_now(name, signature);
+            if !response.success {
+                warn!("Job run rejected: {}", response.error.as_ref().unwrap_or(&String::new()));
+            }
+            serde_json::to_string(&response).unwrap_or_default()
+        },
+        "getLogLevels" => {
+            trace!("Processing getLogLevels request");
+            match handler.get_log_levels() {
+                Ok(levels) => serde_json::to_string(&levels).unwrap_or_default(),
+                Err(e) => rpc_error(&e),
+            }
+        },
+        "getAdminEvents" => {
+            trace!("Processing getAdminEvents request");
+            let page = match rpc::PageRequest::from_param(params.first()) {
+                Ok(page) => page,
+                Err(e) => return rpc_error(&e),
+            };
+            match handler.get_admin_events(page) {
+                Ok(events) => serde_json::to_string(&events).unwrap_or_default(),
+                Err(e) => rpc_error(&e),
+            }
+        },
+        "getFeeLedger" => {
+            trace!("Processing getFeeLedger request");
+            let page = match rpc::PageRequest::from_param(params.first()) {
+                Ok(page) => page,
+                Err(e) => return rpc_error(&e),
+            };
+            let from_block = params.get(1).and_then(|v| v.as_u64());
+            let to_block = params.get(2).and_then(|v| v.as_u64());
+            match handler.get_fee_ledger(page, from_block, to_block) {
+                Ok(entries) => serde_json::to_string(&entries).unwrap_or_default(),
+                Err(e) => rpc_error(&e),
+            }
+        },
+        "exportStatement" => {
+            trace!("Processing exportStatement request");
+            let address = match params.first().and_then(|v| v.as_str()) {
+                Some(address) => address,
+                None => return rpc_error("Missing address parameter"),
+            };
+            let from_timestamp = params.get(1).and_then(|v| v.as_u64());
+            let to_timestamp = params.get(2).and_then(|v| v.as_u64());
+            let format = match params.get(3).and_then(|v| v.as_str()).map(str::parse::<runtime::statement::StatementFormat>).transpose() {
+                Ok(format) => format.unwrap_or_default(),
+                Err(e) => return rpc_error(&e),
+            };
+            match handler.export_statement(address, from_timestamp, to_timestamp, format) {
+                Ok(statement) => serde_json::to_string(&statement).unwrap_or_default(),
+                Err(e) => rpc_error(&e),
+            }
+        },
+        "getStateRoot" => {
+            trace!("Processing getStateRoot request");
+            serde_json::to_string(&handler.get_state_root()).unwrap_or_default()
+        },
+        "verifyProof" => {
+            trace!("Processing verifyProof request");
+            let proof = match params.first().cloned().map(serde_json::from_value::<runtime::state_proof::AccountProof>) {
+                Some(Ok(proof)) => proof,
+                Some(Err(e)) => return rpc_error(&format!("Invalid proof: {}", e)),
+                None => return rpc_error("Missing proof parameter"),
+            };
+            match handler.verify_proof(&proof) {
+                Ok(check) => serde_json::to_string(&check).unwrap_or_default(),
+                Err(e) => rpc_error(&e),
+            }
+        },
+        "getAccountProof" => {
+            trace!("Processing getAccountProof request");
+            let address = match params.first().and_then(|v| v.as_str()) {
+                Some(address) => address,
+                None => return rpc_error("Missing address parameter"),
+            };
+            match handler.get_account_proof(address) {
+                Ok(proof) => serde_json::to_string(&proof).unwrap_or_default(),
+                Err(e) => rpc_error(&e),
+            }
+        },
+        "verifyAccountProof" => {
+            trace!("Processing verifyAccountProof request");
+            let proof = match params.first().cloned().map(serde_json::from_value::<runtime::state_proof::AccountProof>) {
+                Some(Ok(proof)) => proof,
+                Some(Err(e)) => return rpc_error(&format!("Invalid proof: {}", e)),
+                None => return rpc_error("Missing proof parameter"),
+            };
+            match handler.verify_account_proof(&proof) {
+                Ok(valid) => valid.to_string(),
+                Err(e) => rpc_error(&e),
+            }
+        },
+        "diffCheckpoints" => {
+            trace!("Processing diffCheckpoints request");
+            let (from_timestamp, to_timestamp) = match (params.first().and_then(|v| v.as_u64()), params.get(1).and_then(|v| v.as_u64())) {
+                (Some(from_timestamp), Some(to_timestamp)) => (from_timestamp, to_timestamp),
+                _ => return rpc_error("Expected two checkpoint timestamps"),
+            };
+            match handler.diff_checkpoints(from_timestamp, to_timestamp) {
+                Ok(diff) => serde_json::to_string(&diff).unwrap_or_default(),
+                Err(e) => rpc_error(&e),
+            }
+        },
+        "getUbiMints" => {
+            trace!("Processing getUbiMints request");
+            let address = match params.first().and_then(|v| v.as_str()) {
+                Some(address) => address,
+                None => return rpc_error("Missing address parameter"),
+            };
+            let page = match rpc::PageRequest::from_param(params.get(1)) {
+                Ok(page) => page,
+                Err(e) => return rpc_error(&e),
+            };
+            match handler.get_ubi_mints(address, page) {
+                Ok(mints) => serde_json::to_string(&mints).unwrap_or_default(),
+                Err(e) => rpc_error(&e),
+            }
+        },
+        "listAccounts" => {
+            trace!("Processing listAccounts request");
+            let page = match rpc::PageRequest::from_param(params.first()) {
+                Ok(page) => page,
+                Err(e) => return rpc_error(&e),
+            };
+            serde_json::to_string(&handler.list_accounts(page)).unwrap_or_default()
+        },
+        "getFaucetChallenge" => {
+            trace!("Processing getFaucetChallenge request");
+            if let Some(address) = params.first().and_then(|a| a.as_str()) {
+                match handler.get_faucet_challenge(address) {
+                    Ok(challenge) => serde_json::to_string(&challenge).unwrap_or_default(),
+                    Err(e) => rpc_error(&e),
+                }
+            } else {
+                rpc_error("Missing address parameter")
+            }
+        },
+        "getFaucetStats" => {
+            trace!("Processing getFaucetStats request");
+            serde_json::to_string(&handler.get_faucet_stats()).unwrap_or_default()
+        },
+        "requestFromFaucet" => {
+            trace!("Processing requestFromFaucet request");
+            if let Some(address) = params.first().and_then(|a| a.as_str()) {
+                // Get optional amount parameter
+                let amount = match rpc::parse_optional_amount(params.get(1)) {
+                    Ok(amount) => amount,
+                    Err(e) => return rpc_error(&format!("Invalid amount parameter: {}", e)),
+                };
+                
+                // Get optional idempotency key
+                let idempotency_key = match idempotency_key_param(params.get(2)) {
+                    Ok(key) => key,
+                    Err(e) => return rpc_error(&e),
+                };
+                
+                // Get optional tier request
+                let tier = match params.get(3) {
+                    None | Some(serde_json::Value::Null) => None,
+                    Some(tier) => match serde_json::from_value::<rpc::FaucetTierRequest>(tier.clone()) {
+                        Ok(tier) => Some(tier),
+                        Err(e) => return rpc_error(&format!("Invalid tier parameter: {}", e)),
+                    },
+                };
+                
+                // Get optional proof-of-work solution
+                let pow_solution = match rpc::parse_pow_solution(params.get(4)) {
+                    Ok(solution) => solution,
+                    Err(e) => return rpc_error(&e),
+                };
+                
+                info!(peer_addr = peer_addr.to_string().as_str(), address = address;
+                      "Faucet request from {}: address={}, amount={:?}", peer_addr, address, amount);
+                
+                let response = handler.request_from_faucet_idempotent(address.to_string(), amount, idempotency_key, tier, pow_solution).await;
+                
+                if response.success {
+                    info!(address = address; "Faucet request successful: sent {} tokens to {}, new balance: {}",
+                         response.amount.unwrap_or(0), address, response.new_balance.unwrap_or(0));
+                } else {
+                    warn!(address = address; "Faucet request failed: {}", response.error.as_ref().unwrap_or(&String::new()));
+                }
+                
+                serde_json::to_string(&response).unwrap_or_default()
+            } else {
+                rpc_error("Missing address parameter")
+            }
+        },
+        _ => {
+            debug!("Unhandled RPC method: {}", method);
+            rpc_error("Method not found")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    const FAUCET: &str = "0x1111111111111111111111111111111111111111";
+    const RECIPIENT: &str = "0x2222222222222222222222222222222222222222";
+
+    fn funded_handler() -> rpc::RpcHandler {
+        let runtime = Runtime::new();
+        runtime.create_account(FAUCET).unwrap();
+        runtime.credit_balance(FAUCET, 1_000_000).unwrap();
+        let mut handler = rpc::RpcHandler::new(runtime);
+        handler.set_node_address(FAUCET.to_string());
+        handler
+    }
+
+    async fn call(handler: &rpc::RpcHandler, method: &str, params: Value) -> Value {
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let peer_addr: SocketAddr = "127.0.0.1:40000".parse().unwrap();
+        let response = handle_rpc_request(handler, &request.to_string(), peer_addr).await;
+        serde_json::from_str(&response).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_faucet_accepts_string_amounts() {
+        let handler = funded_handler();
+
+        let response = call(&handler, "requestFromFaucet", json!([RECIPIENT, "0x14"])).await;
+        assert_eq!(response["success"], json!(true));
+        assert_eq!(response["amount"], json!(20));
+
+        // Huge but valid amounts are accepted and then capped by the faucet
+        let response = call(&handler, "requestFromFaucet", json!([RECIPIENT, "1e19"])).await;
+        assert_eq!(response["amount"], json!(100));
+
+        let response = call(&handler, "requestFromFaucet", json!([RECIPIENT, "0xffffffffffffffff"])).await;
+        assert_eq!(response["amount"], json!(100));
+    }
+
+    #[tokio::test]
+    async fn test_faucet_rejects_invalid_amounts() {
+        let handler = funded_handler();
+
+        for amount in [json!("-5"), json!(2.5), json!(""), json!("1e20")] {
+            let response = call(&handler, "requestFromFaucet", json!([RECIPIENT, amount])).await;
+            let error = response["error"].as_str().unwrap_or_default();
+            assert!(error.starts_with("Invalid amount parameter"), "amount {} gave {}", amount, response);
+        }
+        
+        assert_eq!(handler.runtime.get_balance(RECIPIENT), 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_block_times_stay_stable_under_rpc_saturation() {
+        use std::sync::atomic::AtomicBool;
+        const TEST_BLOCK_TIME_MS: u64 = 100;
+
+        let mut handler = funded_handler();
+        handler.set_concurrency_config(rpc::ConcurrencyConfig {
+            max_in_flight: 4,
+            queue_timeout: Duration::from_millis(0),
+            ..Default::default()
+        });
+        let io = Arc::new(rpc::eth_compat::EthRpcHandler::new(handler.clone(), 2030).io_handler());
+
+        // Saturate the RPC layer with balance queries, which take the accounts lock
+        let stop = Arc::new(AtomicBool::new(false));
+        let clients: Vec<_> = (0..64).map(|_| {
+            let io = io.clone();
+            let stop = stop.clone();
+            tokio::spawn(async move {
+                let request = json!({
+                    "jsonrpc": "2.0", "id": 1, "method": "eth_getBalance", "params": [FAUCET, "latest"]
+                }).to_string();
+                let mut served = 0u64;
+                while !stop.load(Ordering::SeqCst) {
+                    let response = io.handle_request(&request, ()).await.unwrap_or_default();
+                    if response.contains("\"result\"") {
+                        served += 1;
+                    }
+                    tokio::task::yield_now().await;
+                }
+                served
+            })
+        }).collect();
+
+        let (tx_sender, _) = broadcast::channel(100);
+        let (block_sender, mut block_receiver) = mpsc::channel(100);
+        let producer = Arc::new(BlockProducer::new(
+            handler.runtime.clone(),
+            TEST_BLOCK_TIME_MS,
+            "node-test".to_string(),
+            FAUCET.to_string(),
+            tx_sender,
+            block_sender,
+        ));
+        tokio::spawn({
+            let producer = producer.clone();
+            async move { producer.start().await }
+        });
+
+        let mut last_block_at = None;
+        let mut slowest = Duration::ZERO;
+        for _ in 0..10 {
+            time::timeout(Duration::from_secs(5), block_receiver.recv()).await
+                .expect("block production stalled")
+                .unwrap();
+            let now = Instant::now();
+            if let Some(previous) = last_block_at {
+                slowest = slowest.max(now - previous);
+            }
+            last_block_at = Some(now);
+        }
+
+        stop.store(true, Ordering::SeqCst);
+        let mut served = 0;
+        for client in clients {
+            served += client.await.unwrap();
+        }
+
+        assert!(served > 0);
+        assert!(slowest < Duration::from_millis(TEST_BLOCK_TIME_MS * 3),
+                "slowest block took {:?} under load", slowest);
+        assert_eq!(handler.concurrency.metrics().in_flight, 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_keyed_faucet_requests_pay_out_once() {
+        let handler = funded_handler();
+
+        let tasks: Vec<_> = (0..8).map(|_| {
+            let handler = handler.clone();
+            tokio::spawn(async move {
+                call(&handler, "requestFromFaucet", json!([RECIPIENT, 20, "signup-42"])).await
+            })
+        }).collect();
+
+        let mut hashes = std::collections::HashSet::new();
+        let mut duplicates = 0;
+        for task in tasks {
+            let response = task.await.unwrap();
+            assert_eq!(response["success"], json!(true));
+            assert_eq!(response["idempotency_key"], json!("signup-42"));
+            duplicates += response["duplicate"].as_bool().unwrap() as usize;
+            hashes.insert(response["transaction_hash"].as_str().unwrap().to_string());
+        }
+
+        assert_eq!(duplicates, 7);
+        assert_eq!(hashes.len(), 1);
+        assert_eq!(handler.runtime.get_balance(RECIPIENT), 20);
+
+        // Reusing the key for a different request is refused
+        let response = call(&handler, "requestFromFaucet", json!([RECIPIENT, 30, "signup-42"])).await;
+        assert_eq!(response["success"], json!(false));
+        assert_eq!(handler.runtime.get_balance(RECIPIENT), 20);
+    }
+
+    #[tokio::test]
+    async fn test_underfunded_transfer_failure_is_reported() {
+        let handler = funded_handler();
+        handler.runtime.create_account(RECIPIENT).unwrap();
+        handler.runtime.credit_balance(RECIPIENT, 150).unwrap();
+
+        let (tx_sender, _) = broadcast::channel(100);
+        let (block_sender, _block_receiver) = mpsc::channel(100);
+        let producer = Arc::new(BlockProducer::new(
+            handler.runtime.clone(),
+            BLOCK_TIME_MS,
+            "node-test".to_string(),
+            FAUCET.to_string(),
+            tx_sender,
+            block_sender,
+        ));
+        handler.runtime.set_block_producer(producer.clone());
+
+        // Each transfer is affordable on its own, but not both together
+        let transfer = json!([{ "from": RECIPIENT, "to": FAUCET, "amount": 100 }]);
+        call(&handler, "submitTransaction", transfer.clone()).await;
+        let second = call(&handler, "submitTransaction", transfer).await;
+        let second_hash = second["transaction_hash"].as_str().unwrap().to_string();
+
+        let status = call(&handler, "getTransactionStatus", json!([second_hash])).await;
+        assert_eq!(status["state"]["status"], json!("pending"));
+
+        let block = producer.produce_block().await.unwrap();
+        assert_eq!(block.transactions.len(), 2);
+        assert!(!block.transactions[0].failed);
+        assert!(block.transactions[1].failed);
+
+        // Transfers submitted in the same second run in hash order, whichever came first
+        let (first_hash, second_hash) = (block.transactions[0].hash.clone(), block.transactions[1].hash.clone());
+
+        let status = call(&handler, "getTransactionStatus", json!([first_hash])).await;
+        assert_eq!(status["state"], json!({ "status": "included", "block_number": 1 }));
+
+        let status = call(&handler, "getTransactionStatus", json!([second_hash])).await;
+        assert_eq!(status["state"]["status"], json!("failed"));
+        assert_eq!(status["state"]["reason"], json!("insufficient_balance"));
+        assert_eq!(status["state"]["block_number"], json!(1));
+        assert_eq!(status["state"]["retry_count"], json!(0));
+
+        // The Ethereum receipt reports the failure like a reverted transaction
+        let io = rpc::eth_compat::EthRpcHandler::new(handler.clone(), 2030).io_handler();
+        let request = json!({
+            "jsonrpc": "2.0", "id": 1, "method": "eth_getTransactionReceipt", "params": [second_hash]
+        }).to_string();
+        let response: Value = serde_json::from_str(&io.handle_request(&request, ()).await.unwrap()).unwrap();
+        assert_eq!(response["result"]["status"], json!("0x0"));
+        assert_eq!(response["result"]["ubiFailureReason"], json!("insufficient_balance"));
+        assert!(response["result"]["revertReason"].as_str().unwrap().contains("Insufficient balance"));
+    }
+
+    #[tokio::test]
+    async fn test_submissions_before_the_producer_starts_are_pooled() {
+        let handler = funded_handler();
+        handler.runtime.create_account(RECIPIENT).unwrap();
+
+        // Nothing listens for announcements yet, as right after boot
+        let (tx_sender, _) = broadcast::channel(1);
+        let (block_sender, mut block_receiver) = mpsc::channel(100);
+        let producer = Arc::new(BlockProducer::new(
+            handler.runtime.clone(),
+            BLOCK_TIME_MS,
+            "node-test".to_string(),
+            FAUCET.to_string(),
+            tx_sender,
+            block_sender,
+        ));
+        handler.runtime.set_block_producer(producer.clone());
+
+        let early = Transaction {
+            hash: "0xea51".to_string(),
+            from: FAUCET.to_string(),
+            to: RECIPIENT.to_string(),
+            amount: 5,
+            fee: 0,
+            timestamp: 0,
+            failed: false,
+            mode: TransferMode::Send,
+            nonce: None,
+        };
+        producer.submit_transaction(early).await.unwrap();
+        call(&handler, "submitTransaction", json!([{ "from": FAUCET, "to": RECIPIENT, "amount": 7 }])).await;
+        assert_eq!(producer.tx_pool.pending_count(), 2);
+
+        let metrics = producer.channel_metrics();
+        assert_eq!((metrics.transactions_announced, metrics.transactions_unannounced), (0, 1));
+
+        // The producer picks both up once it starts
+        let running = producer.clone();
+        let task = tokio::spawn(async move { running.start().await });
+        let block = time::timeout(Duration::from_secs(5), block_receiver.recv()).await.unwrap().unwrap();
+        task.abort();
+        assert_eq!(block.number, 1);
+        assert_eq!(block.transactions.iter().filter(|tx| !tx.is_system() && !tx.failed).count(), 2);
+        assert_eq!(handler.runtime.get_balance(RECIPIENT), 12);
+        assert_eq!(producer.channel_metrics().blocks_delivered, 1);
+
+        // A consumer that went away gets its blocks dropped and counted, without their reward
+        drop(block_receiver);
+        let balance = handler.runtime.get_balance(FAUCET);
+        producer.produce_block().await.unwrap();
+        assert_eq!(producer.channel_metrics().blocks_dropped, 1);
+        assert_eq!(handler.runtime.get_balance(FAUCET), balance);
+        assert!(producer.get_block_record(2).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_exact_receive_transfer_records_its_mode_and_amounts() {
+        let handler = funded_handler();
+        handler.runtime.create_account(RECIPIENT).unwrap();
+        handler.runtime.credit_balance(RECIPIENT, 1_000).unwrap();
+
+        let (tx_sender, _) = broadcast::channel(100);
+        let (block_sender, _block_receiver) = mpsc::channel(100);
+        let producer = Arc::new(BlockProducer::new(
+            handler.runtime.clone(),
+            BLOCK_TIME_MS,
+            "node-test".to_string(),
+            FAUCET.to_string(),
+            tx_sender,
+            block_sender,
+        ));
+        handler.runtime.set_block_producer(producer.clone());
+
+        let invoice = json!([{ "from": RECIPIENT, "to": FAUCET, "amount": 500, "mode": "exact-receive" }]);
+        let response = call(&handler, "submitTransaction", invoice).await;
+        assert_eq!(response["amounts"], json!({ "mode": "exact_receive", "received": 500, "fee": 5, "debited": 505 }));
+        let hash = response["transaction_hash"].as_str().unwrap().to_string();
+
+        // The payee receives exactly the invoiced amount and the payer pays the fee on top
+        let faucet_before = handler.runtime.get_balance(FAUCET);
+        producer.produce_block().await.unwrap();
+        assert_eq!(handler.runtime.get_balance(FAUCET) - faucet_before, 500 + runtime::params::DEFAULT_BLOCK_REWARD);
+        assert_eq!(handler.runtime.get_balance(RECIPIENT), 495);
+
+        let status = call(&handler, "getTransactionStatus", json!([hash])).await;
+        assert_eq!((status["amount"].clone(), status["fee"].clone(), status["debited"].clone()), (json!(500), json!(5), json!(505)));
+        assert_eq!(status["mode"], json!("exact_receive"));
+        let block = call(&handler, "getBlockByNumber", json!([1])).await;
+        assert_eq!(block["transactions"][0]["mode"], json!("exact_receive"));
+
+        // An invoice the payer cannot cover with the fee is refused up front
+        let response = call(&handler, "submitTransaction", json!([{ "from": RECIPIENT, "to": FAUCET, "amount": 495, "mode": "exact-receive" }])).await;
+        assert_eq!(response["error"], json!("Insufficient balance: 495 < 500 (495 to receive plus a fee of 5)"));
+        let response = call(&handler, "submitTransaction", json!([{ "from": RECIPIENT, "to": FAUCET, "amount": 1, "mode": "gross" }])).await;
+        assert!(response["error"].as_str().unwrap().starts_with("Invalid transfer mode"), "{}", response);
+    }
+
+    #[tokio::test]
+    async fn test_transaction_lifecycle_is_traced() {
+        let handler = funded_handler();
+        handler.runtime.create_account(RECIPIENT).unwrap();
+        handler.runtime.credit_balance(RECIPIENT, 150).unwrap();
+
+        let (tx_sender, _) = broadcast::channel(100);
+        let (block_sender, _block_receiver) = mpsc::channel(100);
+        let producer = Arc::new(BlockProducer::new(
+            handler.runtime.clone(),
+            BLOCK_TIME_MS,
+            "node-test".to_string(),
+            FAUCET.to_string(),
+            tx_sender,
+            block_sender,
+        ));
+        handler.runtime.set_block_producer(producer.clone());
+
+        let transfer = json!([{ "from": RECIPIENT, "to": FAUCET, "amount": 100 }]);
+        call(&handler, "submitTransaction", transfer.clone()).await;
+        call(&handler, "submitTransaction", transfer).await;
+        // Transfers submitted in the same second run in hash order, whichever came first
+        let block = producer.produce_block().await.unwrap();
+        let (first, second) = (&block.transactions[0], &block.transactions[1]);
+
+        let stages = |trace: Value| -> Vec<String> {
+            trace.as_array().unwrap().iter()
+                .map(|event| event["stage"].as_str().unwrap().to_string())
+                .collect()
+        };
+        let trace = call(&handler, "traceTransactionLifecycle", json!([first.hash])).await;
+        assert_eq!(stages(trace.clone()), vec![
+            "received_via_rpc", "validated", "admitted_to_pool", "selected_for_block", "executed", "receipt_written",
+        ]);
+        assert_eq!(trace[4]["block_number"], json!(1));
+
+        let trace = call(&handler, "traceTransactionLifecycle", json!([second.hash])).await;
+        assert_eq!(stages(trace.clone()), vec![
+            "received_via_rpc", "validated", "admitted_to_pool", "selected_for_block", "execution_failed", "receipt_written",
+        ]);
+        assert!(trace[4]["detail"].as_str().unwrap().contains("Insufficient balance"));
+
+        // Transactions gossiped to the pool channel are traced too
+        let gossiped = Transaction {
+            hash: "0xfeed".to_string(),
+            from: FAUCET.to_string(),
+            to: RECIPIENT.to_string(),
+            amount: 1,
+            fee: 0,
+            timestamp: 0,
+            failed: false,
+            mode: TransferMode::Send,
+            nonce: None,
+        };
+        let mut pool_receiver = producer.tx_sender.subscribe();
+        producer.submit_transaction(gossiped).await.unwrap();
+        assert_eq!(pool_receiver.recv().await.unwrap().hash, "0xfeed");
+        assert_eq!(stages(call(&handler, "traceTransactionLifecycle", json!(["0xfeed"])).await), vec!["admitted_to_pool", "gossiped"]);
+
+        assert_eq!(call(&handler, "traceTransactionLifecycle", json!(["0xunknown"])).await, Value::Null);
+    }
+
+    #[tokio::test]
+    async fn test_native_blocks_include_rewards_fees_and_failed_transactions() {
+        let handler = funded_handler();
+        handler.runtime.create_account(RECIPIENT).unwrap();
+        handler.runtime.credit_balance(RECIPIENT, 150).unwrap();
+
+        let (tx_sender, _) = broadcast::channel(100);
+        let (block_sender, _block_receiver) = mpsc::channel(100);
+        let producer = Arc::new(BlockProducer::new(
+            handler.runtime.clone(),
+            BLOCK_TIME_MS,
+            "node-test".to_string(),
+            FAUCET.to_string(),
+            tx_sender,
+            block_sender,
+        ));
+        handler.runtime.set_block_producer(producer.clone());
+
+        let transfer = json!([{ "from": RECIPIENT, "to": FAUCET, "amount": 100 }]);
+        call(&handler, "submitTransaction", transfer.clone()).await;
+        call(&handler, "submitTransaction", transfer).await;
+        producer.produce_block().await.unwrap();
+        handler.runtime.verify_account(RECIPIENT);
+        producer.produce_block().await.unwrap();
+
+        let block = call(&handler, "getBlockByNumber", json!([1])).await;
+        assert_eq!(block["number"], json!(1));
+        assert_eq!(block["reward_paid"], json!(runtime::params::DEFAULT_BLOCK_REWARD));
+        assert_eq!(block["fees_collected"], json!(1));
+        assert_eq!(block["transactions"].as_array().unwrap().len(), 2);
+        assert_eq!(block["transactions"][0]["failed"], json!(false));
+        assert_eq!(block["transactions"][1]["failed"], json!(true));
+
+        let latest = call(&handler, "getLatestBlocks", json!([5])).await;
+        let numbers: Vec<u64> = latest.as_array().unwrap().iter()
+            .map(|block| block["number"].as_u64().unwrap())
+            .collect();
+        assert_eq!(numbers, vec![2, 1]);
+
+        let io = rpc::eth_compat::EthRpcHandler::new(handler.clone(), 2030).io_handler();
+        let request = json!({
+            "jsonrpc": "2.0", "id": 1, "method": "ubi_getBlockByNumber", "params": ["latest"]
+        }).to_string();
+        let response: Value = serde_json::from_str(&io.handle_request(&request, ()).await.unwrap()).unwrap();
+        assert_eq!(response["result"]["number"], json!(2));
+        assert_eq!(response["result"]["fees_collected"], json!(0));
+        
+        // The extras are served apart from the standard Ethereum block
+        let request = json!({
+            "jsonrpc": "2.0", "id": 1, "method": "ubi_getBlockExtras", "params": ["0x1"]
+        }).to_string();
+        let response: Value = serde_json::from_str(&io.handle_request(&request, ()).await.unwrap()).unwrap();
+        assert_eq!(response["result"]["reward_paid"], json!(runtime::params::DEFAULT_BLOCK_REWARD));
+        assert_eq!(response["result"]["ubi_minted_in_block"], json!(0));
+        assert!(response["result"].get("transactions").is_none());
+        let extras = call(&handler, "getBlockExtras", json!(["latest"])).await;
+        assert_eq!(extras["verification_events"], json!([{ "address": RECIPIENT, "change": "verified", "at": extras["verification_events"][0]["at"] }]));
+
+        assert_eq!(call(&handler, "getBlockByNumber", json!([9])).await, Value::Null);
+    }
+
+    #[tokio::test]
+    async fn test_blocks_and_statuses_store_addresses_lowercase() {
+        const SENDER: &str = "0xAbCdEf0123456789aBcDeF0123456789AbCdEf01";
+        let sender = SENDER.to_lowercase();
+        let handler = funded_handler();
+        handler.runtime.create_account(&sender).unwrap();
+        handler.runtime.credit_balance(&sender, 100).unwrap();
+        handler.runtime.create_account(RECIPIENT).unwrap();
+        let (block_sender, _block_receiver) = mpsc::channel(100);
+        let producer = Arc::new(BlockProducer::new(
+            handler.runtime.clone(),
+            BLOCK_TIME_MS,
+            "node-test".to_string(),
+            FAUCET.to_string(),
+            broadcast::channel(100).0,
+            block_sender,
+        ));
+        handler.runtime.set_block_producer(producer.clone());
+
+        // A gossiped transaction keeps the casing its submitter used until it reaches the pool
+        producer.tx_pool.add_transaction(Transaction {
+            hash: "0xBEEF".to_string(),
+            from: SENDER.to_string(),
+            to: RECIPIENT.to_string(),
+            amount: 10,
+            fee: 0,
+            timestamp: 0,
+            failed: false,
+            mode: TransferMode::Send,
+            nonce: None,
+        });
+        let block = producer.produce_block().await.unwrap();
+        assert_eq!((block.transactions[0].hash.as_str(), block.transactions[0].from.as_str()), ("0xbeef", sender.as_str()));
+
+        for hash in ["0xbeef", "0xBEEF"] {
+            let status = call(&handler, "getTransactionStatus", json!([hash])).await;
+            assert_eq!(status["state"], json!({ "status": "included", "block_number": 1 }));
+            assert_eq!(status["from"], json!(sender));
+        }
+        assert_eq!(call(&handler, "getBlockByNumber", json!([1])).await["transactions"][0]["from"], json!(sender));
+
+        // Blocks imported from peers are stored lowercase too
+        let peer_runtime = Runtime::new();
+        peer_runtime.create_account(&sender).unwrap();
+        peer_runtime.credit_balance(&sender, 100).unwrap();
+        let peer = BlockProducer::new(
+            peer_runtime,
+            BLOCK_TIME_MS,
+            "node-peer".to_string(),
+            RECIPIENT.to_string(),
+            broadcast::channel(100).0,
+            mpsc::channel(100).0,
+        );
+        let mut shouted = block.clone();
+        shouted.transactions[0].from = SENDER.to_string();
+        peer.import_block(&shouted).unwrap();
+        assert_eq!(peer.get_block_record(1).unwrap().block.transactions[0].from, sender);
+    }
+
+    #[test]
+    fn test_imported_blocks_breaking_consensus_rules_change_nothing() {
+        let sender = "0x3333333333333333333333333333333333333333";
+        let runtime = Runtime::new();
+        runtime.create_account(sender).unwrap();
+        runtime.credit_balance(sender, 100).unwrap();
+        let peer = BlockProducer::new(
+            runtime.clone(),
+            BLOCK_TIME_MS,
+            "node-peer".to_string(),
+            "0x4444444444444444444444444444444444444444".to_string(),
+            broadcast::channel(100).0,
+            mpsc::channel(100).0,
+        );
+        let transfer = |hash: &str, from: &str, to: &str| Transaction {
+            hash: hash.to_string(),
+            from: from.to_string(),
+            to: to.to_string(),
+            amount: 10,
+            fee: 0,
+            timestamp: 0,
+            failed: false,
+            mode: TransferMode::Send,
+            nonce: None,
+        };
+        let block_with = |transactions| Block {
+            number: 1,
+            hash: "0x1".to_string(),
+            parent_hash: "0x0".to_string(),
+            timestamp: 0,
+            transactions,
+            state_root: "0x0".to_string(),
+            producer: "node-evil".to_string(),
+            producer_address: RECIPIENT.to_string(),
+        };
+
+        // A valid transfer first: nothing of the block may run before the bad one is found
+        let valid = transfer("0xa", sender, RECIPIENT);
+        let burn_sender = transfer("0xb", runtime::DEFAULT_BURN_ADDRESS, RECIPIENT);
+        let malformed = transfer("0xc", sender, "0x12345");
+        for bad in [burn_sender, malformed] {
+            let error = peer.import_block(&block_with(vec![valid.clone(), bad.clone()])).unwrap_err();
+            assert!(error.contains(&bad.hash) && error.contains("consensus"), "{}", error);
+            assert_eq!(peer.current_block(), 0);
+            assert_eq!(runtime.get_balance(sender), 100);
+            assert!(!runtime.get_account_addresses().contains(&RECIPIENT.to_string()));
+        }
+
+        // The same transfer flagged as failed by the producer changes no state, so the block is accepted
+        let mut flagged = transfer("0xc", sender, "0x12345");
+        flagged.failed = true;
+        peer.import_block(&block_with(vec![valid, flagged])).unwrap();
+        assert_eq!(runtime.get_balance(RECIPIENT), 10 + runtime.chain_params().block_reward);
+    }
+
+    #[test]
+    fn test_imported_blocks_failing_part_way_change_nothing() {
+        let sender = "0x3333333333333333333333333333333333333333";
+        let broke = "0x5555555555555555555555555555555555555555";
+        let runtime = Runtime::new();
+        runtime.create_account(sender).unwrap();
+        runtime.credit_balance(sender, 100).unwrap();
+        runtime.create_account(broke).unwrap();
+        let peer = BlockProducer::new(
+            runtime.clone(),
+            BLOCK_TIME_MS,
+            "node-peer".to_string(),
+            "0x4444444444444444444444444444444444444444".to_string(),
+            broadcast::channel(100).0,
+            mpsc::channel(100).0,
+        );
+        let transfer = |hash: &str, from: &str| Transaction {
+            hash: hash.to_string(),
+            from: from.to_string(),
+            to: RECIPIENT.to_string(),
+            amount: 10,
+            fee: 0,
+            timestamp: 0,
+            failed: false,
+            mode: TransferMode::Send,
+            nonce: None,
+        };
+        let block = Block {
+            number: 1,
+            hash: "0x1".to_string(),
+            parent_hash: "0x0".to_string(),
+            timestamp: 0,
+            transactions: vec![transfer("0xa", sender), transfer("0xb", broke)],
+            state_root: "0x0".to_string(),
+            producer: "node-evil".to_string(),
+            producer_address: RECIPIENT.to_string(),
+        };
+        let state_root = runtime.state_root();
+        let total_supply = runtime.get_total_supply();
+
+        // The first transfer is valid, but the second is unfunded, so neither may stick
+        let error = peer.import_block(&block).unwrap_err();
+        assert!(error.contains("0xb"), "{}", error);
+        assert_eq!(peer.current_block(), 0);
+        assert_eq!(runtime.state_root(), state_root);
+        assert_eq!((runtime.get_balance(sender), runtime.get_nonce(sender), runtime.get_nonce(broke)), (100, 0, 0));
+        assert!(!runtime.get_account_addresses().contains(&RECIPIENT.to_string()));
+        assert_eq!((runtime.get_total_supply(), runtime.get_fee_pool()), (total_supply, 0));
+    }
+
+    #[test]
+    fn test_imported_blocks_moving_frozen_tokens_are_rejected() {
+        let sender = "0x3333333333333333333333333333333333333333";
+        let runtime = Runtime::new();
+        runtime.create_account(sender).unwrap();
+        runtime.credit_balance(sender, 100).unwrap();
+        runtime.create_account(RECIPIENT).unwrap();
+        let peer = BlockProducer::new(
+            runtime.clone(),
+            BLOCK_TIME_MS,
+            "node-peer".to_string(),
+            "0x4444444444444444444444444444444444444444".to_string(),
+            broadcast::channel(100).0,
+            mpsc::channel(100).0,
+        );
+        let block = Block {
+            number: 1,
+            hash: "0x1".to_string(),
+            parent_hash: "0x0".to_string(),
+            timestamp: 0,
+            transactions: vec![Transaction {
+                hash: "0xa".to_string(),
+                from: sender.to_string(),
+                to: RECIPIENT.to_string(),
+                amount: 10,
+                fee: 0,
+                timestamp: 0,
+                failed: false,
+                mode: TransferMode::Send,
+                nonce: None,
+            }],
+            state_root: "0x0".to_string(),
+            producer: "node-evil".to_string(),
+            producer_address: RECIPIENT.to_string(),
+        };
+
+        // A frozen sender or recipient breaks the consensus rules, so the block is refused before it runs
+        for frozen in [sender, RECIPIENT] {
+            runtime.freeze_account(frozen, Some("under review".to_string())).unwrap();
+            let error = peer.import_block(&block).unwrap_err();
+            assert!(error.contains("0xa") && error.contains("consensus") && error.contains("frozen"), "{}", error);
+            assert_eq!(peer.current_block(), 0);
+            assert_eq!((runtime.get_balance(sender), runtime.get_balance(RECIPIENT)), (100, 0));
+            assert_eq!(runtime.get_nonce(sender), 0);
+            runtime.unfreeze_account(frozen).unwrap();
+        }
+
+        peer.import_block(&block).unwrap();
+        assert_eq!(runtime.get_balance(RECIPIENT), 10 + runtime.chain_params().block_reward);
+    }
+
+    #[tokio::test]
+    async fn test_rewards_are_paid_for_sent_blocks_and_taken_back_when_discarded() {
+        let handler = funded_handler();
+        let producer_for = |block_sender| BlockProducer::new(
+            handler.runtime.clone(),
+            BLOCK_TIME_MS,
+            "node-test".to_string(),
+            FAUCET.to_string(),
+            broadcast::channel(100).0,
+            block_sender,
+        );
+        let supply = handler.runtime.get_total_supply();
+
+        // With nobody listening the block cannot be sent, so no reward is paid for it
+        let (block_sender, block_receiver) = mpsc::channel(100);
+        drop(block_receiver);
+        let unsent = producer_for(block_sender);
+        unsent.produce_block().await.unwrap();
+        assert_eq!(unsent.get_block_record(1).unwrap().extras.reward_paid, 0);
+        assert_eq!(handler.runtime.get_total_supply(), supply);
+
+        let (block_sender, _block_receiver) = mpsc::channel(100);
+        let producer = producer_for(block_sender);
+        for _ in 0..3 {
+            producer.produce_block().await.unwrap();
+        }
+        assert_eq!(producer.get_block_record(3).unwrap().extras.reward_paid, 100);
+        assert_eq!(handler.runtime.get_total_supply(), supply + 300);
+
+        // A reorg back to block 1 takes back the rewards of the discarded blocks
+        assert_eq!(producer.discard_blocks_above(1), 200);
+        assert!(producer.get_block_record(2).is_none());
+        assert_eq!(handler.runtime.get_balance(FAUCET), 1_000_100);
+        assert_eq!(handler.runtime.get_total_supply(), supply + 100);
+
+        // The replacement block at the same height is paid again
+        assert_eq!(producer.produce_block().await.unwrap().number, 2);
+        assert_eq!(handler.runtime.get_total_supply(), supply + 200);
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_blocks_are_empty_heartbeats() {
+        let handler = funded_handler();
+        handler.runtime.create_account(RECIPIENT).unwrap();
+        handler.runtime.credit_balance(RECIPIENT, 150).unwrap();
+
+        let (tx_sender, _) = broadcast::channel(100);
+        let (block_sender, _block_receiver) = mpsc::channel(100);
+        let producer = Arc::new(BlockProducer::new(
+            handler.runtime.clone(),
+            BLOCK_TIME_MS,
+            "node-test".to_string(),
+            FAUCET.to_string(),
+            tx_sender,
+            block_sender,
+        ));
+        handler.runtime.set_block_producer(producer.clone());
+
+        // A transfer already in the pool waits out the maintenance window
+        let transfer = json!([{ "from": RECIPIENT, "to": FAUCET, "amount": 100 }]);
+        let queued = call(&handler, "submitTransaction", transfer.clone()).await;
+        handler.runtime.set_maintenance(true, Some("migration".to_string()), None);
+        let refused = call(&handler, "submitTransaction", transfer).await;
+        assert_eq!(refused["error"], json!("Node is in maintenance mode: migration"));
+
+        let supply = handler.runtime.get_total_supply();
+        let block = producer.produce_block().await.unwrap();
+        assert!(block.transactions.is_empty());
+        assert_eq!(producer.get_block_record(1).unwrap().extras.reward_paid, 0);
+        assert_eq!(handler.runtime.get_total_supply(), supply);
+        assert_eq!(handler.runtime.get_balance(RECIPIENT), 150);
+
+        handler.runtime.set_maintenance(false, None, None);
+        let block = producer.produce_block().await.unwrap();
+        assert_eq!(block.transactions[0].hash, queued["transaction_hash"].as_str().unwrap());
+        assert!(!block.transactions[0].failed);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_method() {
+        let handler = funded_handler();
+        let response = call(&handler, "noSuchMethod", json!([])).await;
+        assert_eq!(response["error"], json!("Method not found"));
+    }
+
+    /// Production backend failing the reward credits and state checks a test picks
+    struct FailingBackend {
+        runtime: Runtime,
+        reward_credits: AtomicU64,
+        fail_reward: fn(u64) -> bool,
+        poisoned: bool,
+    }
+
+    impl ProductionBackend for FailingBackend {
+        fn check_state(&self) -> Result<(), ProductionError> {
+            if self.poisoned {
+                return Err(ProductionError::State("the accounts lock is poisoned".to_string()));
+            }
+            self.runtime.check_state()
+        }
+
+        fn credit_block_reward(&self, address: &str, amount: u64, block_hash: &str) -> Result<(), AccountError> {
+            if (self.fail_reward)(self.reward_credits.fetch_add(1, Ordering::SeqCst)) {
+                return Err(AccountError::Other("reward ledger unavailable".to_string()));
+            }
+            ProductionBackend::credit_block_reward(&self.runtime, address, amount, block_hash)
+        }
+    }
+
+    /// Creates a producer with a 5 ms block time, and the consumer its blocks are delivered to
+    fn failing_producer(handler: &rpc::RpcHandler, fail_reward: fn(u64) -> bool, poisoned: bool)
+        -> (Arc<BlockProducer>, mpsc::Receiver<Block>) {
+        let (tx_sender, _) = broadcast::channel(100);
+        let (block_sender, block_receiver) = mpsc::channel(100);
+        let backend = FailingBackend { runtime: handler.runtime.clone(), reward_credits: AtomicU64::new(0), fail_reward, poisoned };
+        let producer = Arc::new(BlockProducer::new(
+            handler.runtime.clone(),
+            5,
+            "node-test".to_string(),
+            FAUCET.to_string(),
+            tx_sender,
+            block_sender,
+        ).with_backend(Arc::new(backend)));
+        handler.runtime.set_block_producer(producer.clone());
+        (producer, block_receiver)
+    }
+
+    #[tokio::test]
+    async fn test_repeated_failures_halt_production_and_flag_the_node() {
+        let handler = funded_handler();
+
+        // Four failures in a row are retried, the fifth block succeeds and every block after it fails
+        let (producer, _blocks) = failing_producer(&handler, |credit| credit != 4, false);
+        let halt = time::timeout(Duration::from_secs(10), producer.start()).await.unwrap().unwrap_err();
+        assert_eq!(halt.consecutive_failures, production::MAX_CONSECUTIVE_FAILURES);
+        assert!(halt.error.is_transient());
+        assert!(halt.to_string().contains("reward ledger unavailable"));
+        assert_eq!(producer.current_block(), 10);
+
+        // The node is flagged at once, reported unready and subscribers are told
+        let liveness = producer.liveness();
+        assert!(liveness.stalled);
+        assert_eq!(liveness.halted, Some(halt.to_string()));
+        assert!(!handler.get_readiness().ready);
+        assert!(matches!(producer.check_liveness(), Some(NodeHealthEvent::NodeStalled { last_block: 10, .. })));
+
+        // Configured for maintenance, the node stays up and refuses transactions
+        let task = tokio::spawn(async move { Err(halt) });
+        let watch = production::watch_production(task, producer.clone(), handler.runtime.clone(), HaltAction::Maintenance);
+        assert!(time::timeout(Duration::from_millis(50), watch).await.is_err());
+        let maintenance = handler.runtime.maintenance_status();
+        assert!(maintenance.enabled);
+        assert!(maintenance.reason.unwrap().contains("reward ledger unavailable"));
+    }
+
+    #[tokio::test]
+    async fn test_unusable_state_halts_production_at_once_and_shuts_down() {
+        let handler = funded_handler();
+        let (producer, _blocks) = failing_producer(&handler, |_| false, true);
+
+        let halt = time::timeout(Duration::from_secs(10), producer.start()).await.unwrap().unwrap_err();
+        assert_eq!(halt.consecutive_failures, 1);
+        assert!(matches!(halt.error, ProductionError::State(_)));
+        assert_eq!(producer.current_block(), 0);
+        assert!(producer.liveness().stalled);
+
+        // Maintenance mode needs a usable runtime, so the node shuts down whatever the configuration
+        let task = tokio::spawn(async move { Err(halt) });
+        let watch = production::watch_production(task, producer.clone(), handler.runtime.clone(), HaltAction::Maintenance);
+        time::timeout(Duration::from_secs(1), watch).await.unwrap();
+        assert!(!handler.runtime.maintenance_status().enabled);
+
+        // A producer that is told to stop returns without a halt
+        let (producer, _blocks) = failing_producer(&handler, |_| false, false);
+        producer.stop();
+        assert_eq!(producer.start().await, Ok(()));
+    }
+}
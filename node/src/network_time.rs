@@ -0,0 +1,179 @@
+//! Network Time Estimation
+//!
+//! Each node's wall clock may be skewed. Peers report their current time in the
+//! handshake, and the median offset between those reports and the local clock
+//! estimates how far the local clock is from the rest of the network. The offset
+//! is surfaced to operators so NTP problems get noticed.
+//!
+//! Block timestamps follow two rules:
+//! - Produced blocks use the local clock, but never go below the parent's timestamp
+//! - Received blocks are rejected when their timestamp is more than the allowed
+//!   drift ahead of the local clock
+
+use crate::Clock;
+use log::warn;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Default number of seconds a received block may be ahead of the local clock
+pub const DEFAULT_MAX_CLOCK_DRIFT_SECS: u64 = 15;
+
+/// Maximum number of peers whose offsets are kept
+const MAX_PEER_SAMPLES: usize = 64;
+
+/// Network time estimate built from peer handshakes
+pub struct NetworkTime {
+    /// Local clock
+    clock: Clock,
+    /// Most recent offset (peer time minus local time) reported by each peer
+    peer_offsets: Mutex<HashMap<String, i64>>,
+    /// Seconds a received block may be ahead of the local clock
+    max_drift_secs: u64,
+}
+
+impl NetworkTime {
+    /// Creates an estimate backed by the given local clock
+    pub fn new(clock: Clock) -> Self {
+        NetworkTime {
+            clock,
+            peer_offsets: Mutex::new(HashMap::new()),
+            max_drift_secs: DEFAULT_MAX_CLOCK_DRIFT_SECS,
+        }
+    }
+
+    /// Sets how many seconds a received block may be ahead of the local clock
+    pub fn with_max_drift(mut self, max_drift_secs: u64) -> Self {
+        self.max_drift_secs = max_drift_secs;
+        self
+    }
+
+    /// Gets the number of seconds a received block may be ahead of the local clock
+    pub fn max_drift_secs(&self) -> u64 {
+        self.max_drift_secs
+    }
+
+    /// Gets the local clock time in seconds
+    pub fn local_now(&self) -> u64 {
+        (self.clock)()
+    }
+
+    /// Records the time a peer reported in its handshake
+    ///
+    /// Only the latest report per peer counts, so a chatty peer cannot outvote
+    /// the others. New peers are ignored once `MAX_PEER_SAMPLES` are tracked.
+    ///
+    /// # Arguments
+    /// * `peer_id` - Identifier of the reporting peer
+    /// * `peer_timestamp` - The peer's current UNIX time in seconds
+    pub fn record_peer_time(&self, peer_id: &str, peer_timestamp: u64) {
+        let offset = peer_timestamp as i64 - self.local_now() as i64;
+        let mut peer_offsets = self.peer_offsets.lock().unwrap();
+        if peer_offsets.len() >= MAX_PEER_SAMPLES && !peer_offsets.contains_key(peer_id) {
+            return;
+        }
+        peer_offsets.insert(peer_id.to_string(), offset);
+        drop(peer_offsets);
+
+        let median_offset = self.offset();
+        if median_offset.unsigned_abs() > self.max_drift_secs {
+            warn!(peer_id = peer_id; "Local clock is {}s off the network median; check NTP", median_offset);
+        }
+    }
+
+    /// Gets the median offset of peer clocks from the local clock in seconds
+    ///
+    /// Positive values mean the local clock is behind the network. Returns 0
+    /// until a peer has reported its time.
+    pub fn offset(&self) -> i64 {
+        let mut offsets: Vec<i64> = self.peer_offsets.lock().unwrap().values().copied().collect();
+        if offsets.is_empty() {
+            return 0;
+        }
+
+        offsets.sort_unstable();
+        let middle = offsets.len() / 2;
+        if offsets.len().is_multiple_of(2) {
+            (offsets[middle - 1] + offsets[middle]) / 2
+        } else {
+            offsets[middle]
+        }
+    }
+
+    /// Gets the number of peers contributing to the offset
+    pub fn sample_count(&self) -> usize {
+        self.peer_offsets.lock().unwrap().len()
+    }
+
+    /// Gets the timestamp for a block produced on top of the given parent
+    ///
+    /// # Arguments
+    /// * `parent_timestamp` - Timestamp of the parent block, or 0 for the first block
+    pub fn block_timestamp(&self, parent_timestamp: u64) -> u64 {
+        self.local_now().max(parent_timestamp)
+    }
+
+    /// Checks the timestamp of a block received from a peer
+    ///
+    /// # Arguments
+    /// * `timestamp` - Timestamp of the received block
+    /// * `parent_timestamp` - Timestamp of its parent, or 0 for the first block
+    ///
+    /// # Returns
+    /// An error describing the problem if the timestamp is unacceptable
+    pub fn validate_block_timestamp(&self, timestamp: u64, parent_timestamp: u64) -> Result<(), String> {
+        if timestamp < parent_timestamp {
+            return Err(format!("Block timestamp {} is before its parent's {}", timestamp, parent_timestamp));
+        }
+
+        let latest_allowed = self.local_now().saturating_add(self.max_drift_secs);
+        if timestamp > latest_allowed {
+            return Err(format!(
+                "Block timestamp {} is more than {}s ahead of local time {}",
+                timestamp, self.max_drift_secs, self.local_now()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn fixed_clock(now: u64) -> Clock {
+        Arc::new(move || now)
+    }
+
+    #[test]
+    fn test_offset_is_the_median_of_peers() {
+        let time = NetworkTime::new(fixed_clock(1_000));
+        assert_eq!(time.offset(), 0);
+
+        time.record_peer_time("a", 1_002);
+        time.record_peer_time("b", 998);
+        time.record_peer_time("c", 5_000); // one badly skewed peer does not move the median
+        assert_eq!(time.offset(), 2);
+
+        // A repeated report replaces the peer's previous one
+        time.record_peer_time("c", 1_001);
+        assert_eq!(time.offset(), 1);
+        assert_eq!(time.sample_count(), 3);
+
+        time.record_peer_time("d", 1_003);
+        assert_eq!(time.offset(), 1);
+    }
+
+    #[test]
+    fn test_block_timestamps() {
+        let time = NetworkTime::new(fixed_clock(1_000)).with_max_drift(10);
+
+        assert_eq!(time.block_timestamp(900), 1_000);
+        assert_eq!(time.block_timestamp(1_050), 1_050);
+
+        assert!(time.validate_block_timestamp(1_010, 900).is_ok());
+        assert!(time.validate_block_timestamp(1_011, 900).is_err());
+        assert!(time.validate_block_timestamp(899, 900).is_err());
+    }
+}
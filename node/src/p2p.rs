@@ -1,29 +1,181 @@
-use log::{info, error};
-use std::collections::HashMap;
+use crate::Clock;
+use crate::wire::{Frame, Hello, Message, Session};
+use log::{debug, info, error, warn};
+use runtime::sync_status::{PeerSyncInfo, SyncStatus};
+use std::collections::{HashMap, VecDeque};
 use std::net::SocketAddr;
 use tokio::net::{TcpListener, TcpStream};
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::io::AsyncWriteExt;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 
+/// Seconds of received blocks the throughput estimate covers
+const THROUGHPUT_WINDOW_SECS: u64 = 60;
+
 /// Simple peer-to-peer network implementation
 #[derive(Clone)]
 #[allow(dead_code)]
 pub struct P2PNetwork {
-    peers: Arc<Mutex<HashMap<SocketAddr, PeerInfo>>>,
+    peers: Arc<PeerTracker>,
     listen_addr: SocketAddr,
+    /// Id this node announces to its peers
+    node_id: String,
 }
 
-#[allow(dead_code)]
+/// What a peer has announced and delivered
+#[derive(Default)]
 struct PeerInfo {
-    // Add peer metadata as needed
     connected: bool,
+    /// Highest block the peer announced or delivered
+    best_block: u64,
+    /// Blocks received from the peer
+    blocks_received: u64,
+    /// Received blocks that failed to import
+    invalid_blocks: u64,
+    /// Bytes received from the peer
+    bytes_received: u64,
+    /// Arrival time and size of the blocks received within the throughput window
+    recent_blocks: VecDeque<(u64, u64)>,
+}
+
+impl PeerInfo {
+    /// Drops blocks that fell out of the throughput window
+    fn expire(&mut self, now: u64) {
+        while let Some((received_at, _)) = self.recent_blocks.front() {
+            if now.saturating_sub(*received_at) < THROUGHPUT_WINDOW_SECS {
+                break;
+            }
+            self.recent_blocks.pop_front();
+        }
+    }
+}
+
+/// Sync progress of every peer, fed by the network and the block import path
+pub struct PeerTracker {
+    /// Source of the time used for throughput estimates
+    clock: Clock,
+    /// Peers by id
+    peers: Mutex<HashMap<String, PeerInfo>>,
+    /// Local height when the current sync started
+    sync_start: Mutex<Option<u64>>,
+    /// Local height last recorded, announced in handshakes
+    local_height: AtomicU64,
+}
+
+impl PeerTracker {
+    /// Creates a tracker measuring throughput with the given clock
+    pub fn new(clock: Clock) -> Self {
+        PeerTracker {
+            clock,
+            peers: Mutex::new(HashMap::new()),
+            sync_start: Mutex::new(None),
+            local_height: AtomicU64::new(0),
+        }
+    }
+
+    /// Records whether a peer is connected
+    pub fn set_connected(&self, peer_id: &str, connected: bool) {
+        self.peers.lock().unwrap().entry(peer_id.to_string()).or_default().connected = connected;
+    }
+
+    /// Records the best block a peer announced, e.g. in its handshake
+    pub fn record_peer_head(&self, peer_id: &str, best_block: u64) {
+        let mut peers = self.peers.lock().unwrap();
+        let peer = peers.entry(peer_id.to_string()).or_default();
+        peer.connected = true;
+        peer.best_block = peer.best_block.max(best_block);
+    }
+
+    /// Records bytes received from a peer outside of blocks
+    pub fn record_bytes(&self, peer_id: &str, bytes: u64) {
+        self.peers.lock().unwrap().entry(peer_id.to_string()).or_default().bytes_received += bytes;
+    }
+
+    /// Records a block received from a peer
+    ///
+    /// # Arguments
+    /// * `peer_id` - The peer that sent the block
+    /// * `number` - The block's number
+    /// * `bytes` - Size of the block on the wire
+    /// * `valid` - Whether the block was imported
+    pub fn record_block(&self, peer_id: &str, number: u64, bytes: u64, valid: bool) {
+        let now = (self.clock)();
+        let mut peers = self.peers.lock().unwrap();
+        let peer = peers.entry(peer_id.to_string()).or_default();
+        peer.blocks_received += 1;
+        peer.bytes_received += bytes;
+        if valid {
+            peer.best_block = peer.best_block.max(number);
+        } else {
+            peer.invalid_blocks += 1;
+        }
+        peer.recent_blocks.push_back((now, bytes));
+        peer.expire(now);
+    }
+
+    /// Notes the local height, starting or ending a sync as peers get ahead or are caught up
+    pub fn record_local_height(&self, current_block: u64) {
+        self.local_height.store(current_block, Ordering::SeqCst);
+        let mut sync_start = self.sync_start.lock().unwrap();
+        if current_block < self.highest_peer_block() {
+            sync_start.get_or_insert(current_block);
+        } else {
+            *sync_start = None;
+        }
+    }
+
+    /// Gets the overall sync state and the progress of each peer
+    pub fn status(&self, current_block: u64) -> SyncStatus {
+        let now = (self.clock)();
+        let mut peers = self.peers.lock().unwrap();
+        let mut peer_infos: Vec<PeerSyncInfo> = peers.iter_mut()
+            .map(|(peer_id, peer)| {
+                peer.expire(now);
+                let window_bytes: u64 = peer.recent_blocks.iter().map(|(_, bytes)| bytes).sum();
+                PeerSyncInfo {
+                    peer_id: peer_id.clone(),
+                    connected: peer.connected,
+                    best_block: peer.best_block,
+                    blocks_received: peer.blocks_received,
+                    invalid_blocks: peer.invalid_blocks,
+                    bytes_received: peer.bytes_received,
+                    blocks_per_sec: peer.recent_blocks.len() as f64 / THROUGHPUT_WINDOW_SECS as f64,
+                    bytes_per_sec: window_bytes as f64 / THROUGHPUT_WINDOW_SECS as f64,
+                }
+            })
+            .collect();
+        drop(peers);
+        peer_infos.sort_by(|a, b| a.peer_id.cmp(&b.peer_id));
+
+        let highest_block = peer_infos.iter().map(|peer| peer.best_block).max().unwrap_or(0).max(current_block);
+        let syncing = highest_block > current_block;
+        let starting_block = match *self.sync_start.lock().unwrap() {
+            Some(start) if syncing => start,
+            _ => current_block,
+        };
+
+        SyncStatus {
+            syncing,
+            starting_block,
+            current_block,
+            highest_block,
+            peers: peer_infos,
+        }
+    }
+
+    /// Gets the highest block any peer announced or delivered
+    fn highest_peer_block(&self) -> u64 {
+        self.peers.lock().unwrap().values().map(|peer| peer.best_block).max().unwrap_or(0)
+    }
 }
 
 impl P2PNetwork {
-    pub fn new(listen_addr: SocketAddr) -> Self {
+    /// Creates a network whose peers are tracked in `peers`
+    pub fn new(listen_addr: SocketAddr, node_id: String, peers: Arc<PeerTracker>) -> Self {
         P2PNetwork {
-            peers: Arc::new(Mutex::new(HashMap::new())),
+            peers,
             listen_addr,
+            node_id,
         }
     }
 
@@ -48,41 +200,24 @@ impl P2PNetwork {
     #[allow(dead_code)]
     async fn handle_peer(&self, mut socket: TcpStream, addr: SocketAddr) {
         // Add peer to our list
-        {
-            let mut peers = self.peers.lock().unwrap();
-            peers.insert(addr, PeerInfo { connected: true });
-        }
+        let peer_id = addr.to_string();
+        self.peers.set_connected(&peer_id, true);
 
         // Spawn a task to handle communication with this peer
         let peers_clone = self.peers.clone();
+        let hello = Hello::local(&self.node_id, self.peers.local_height.load(Ordering::SeqCst), (self.peers.clock)());
         tokio::spawn(async move {
-            let mut buffer = [0u8; 1024];
-            
-            loop {
-                match socket.read(&mut buffer).await {
-                    Ok(0) => {
-                        // Connection closed
-                        break;
-                    }
-                    Ok(n) => {
-                        // Process message (just echo for now)
-                        if let Err(e) = socket.write_all(&buffer[..n]).await {
-                            error!("Failed to write to socket: {}", e);
-                            break;
-                        }
-                    }
-                    Err(e) => {
-                        error!("Failed to read from socket: {}", e);
-                        break;
-                    }
-                }
+            match handshake(&mut socket, &hello).await {
+                Ok((theirs, session)) => {
+                    info!(peer_id = peer_id.as_str(); "Peer {} speaks protocol version {}", theirs.node_id, session.version);
+                    peers_clone.record_peer_head(&peer_id, theirs.best_block);
+                    receive_messages(&mut socket, &peer_id, session, &peers_clone).await;
+                },
+                Err(e) => warn!(peer_id = peer_id.as_str(); "Refused peer {}: {}", addr, e),
             }
 
             // Update peer status when disconnected
-            let mut peers = peers_clone.lock().unwrap();
-            if let Some(peer_info) = peers.get_mut(&addr) {
-                peer_info.connected = false;
-            }
+            peers_clone.set_connected(&peer_id, false);
             info!("Peer disconnected: {}", addr);
         });
     }
@@ -102,10 +237,75 @@ impl P2PNetwork {
 
     #[allow(dead_code)]
     pub fn is_peer_connected(&self, addr: &SocketAddr) -> bool {
-        if let Some(peer_info) = self.peers.lock().unwrap().get(addr) {
-            peer_info.connected
-        } else {
-            false
+        self.peers.status(0).peers.iter()
+            .any(|peer| peer.peer_id == addr.to_string() && peer.connected)
+    }
+}
+
+/// Exchanges hellos with a peer and agrees on a protocol version
+async fn handshake(socket: &mut TcpStream, hello: &Hello) -> Result<(Hello, Session), String> {
+    socket.write_all(&hello.to_frame().encode()).await.map_err(|e| e.to_string())?;
+    let frame = Frame::read(socket).await.map_err(|e| e.to_string())?;
+    let theirs = Hello::from_frame(&frame).map_err(|e| e.to_string())?;
+    let session = Session::negotiate(hello, &theirs).map_err(|e| e.to_string())?;
+    Ok((theirs, session))
+}
+
+/// Reads messages from a peer until it disconnects or breaks the negotiated protocol
+async fn receive_messages(socket: &mut TcpStream, peer_id: &str, session: Session, peers: &PeerTracker) {
+    loop {
+        let message = match Frame::read(socket).await {
+            Ok(frame) => {
+                peers.record_bytes(peer_id, (crate::wire::HEADER_BYTES + frame.payload.len()) as u64);
+                session.decode(&frame)
+            },
+            Err(e) => Err(e),
+        };
+        match message {
+            Ok(Message::Ping(nonce)) => debug!(peer_id = peer_id; "Ping {} from {}", nonce, peer_id),
+            Ok(Message::NewBlock(block)) => peers.record_peer_head(peer_id, block.number),
+            Ok(Message::NewTransaction(tx)) => debug!(peer_id = peer_id; "Transaction {} from {}", tx.hash, peer_id),
+            Err(crate::wire::WireError::Truncated) => break,
+            Err(e) => {
+                warn!(peer_id = peer_id; "Closing connection to {}: {}", peer_id, e);
+                break;
+            },
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    #[test]
+    fn test_peer_progress_and_throughput() {
+        let now = Arc::new(AtomicU64::new(1_000));
+        let clock_now = now.clone();
+        let tracker = PeerTracker::new(Arc::new(move || clock_now.load(Ordering::SeqCst)));
+
+        tracker.record_peer_head("a", 10);
+        tracker.record_local_height(4);
+        for number in 5..=7 {
+            tracker.record_block("a", number, 200, true);
+        }
+        tracker.record_block("b", 8, 100, false);
+        tracker.record_local_height(7);
+
+        let status = tracker.status(7);
+        assert!(status.syncing);
+        assert_eq!((status.starting_block, status.highest_block), (4, 10));
+        let a = &status.peers[0];
+        assert_eq!((a.blocks_received, a.invalid_blocks, a.bytes_received), (3, 0, 600));
+        assert_eq!(a.blocks_per_sec, 3.0 / THROUGHPUT_WINDOW_SECS as f64);
+        assert_eq!((status.peers[1].invalid_blocks, status.peers[1].best_block), (1, 0));
+
+        // Throughput decays once the window passes; totals remain
+        now.fetch_add(THROUGHPUT_WINDOW_SECS, Ordering::SeqCst);
+        let status = tracker.status(10);
+        assert!(!status.syncing);
+        assert_eq!(status.starting_block, 10);
+        assert_eq!((status.peers[0].blocks_per_sec, status.peers[0].blocks_received), (0.0, 3));
+    }
+}
@@ -0,0 +1,161 @@
+//! Block Production Errors
+//!
+//! A failed block used to be logged and retried on the next tick forever, so
+//! a systemic failure only showed as log spam while the chain degraded. Each
+//! failure is now classified:
+//! - `Transient`: a step that may succeed next time, such as a refused reward
+//!   credit. Production retries with a growing backoff, and gives up after
+//!   `MAX_CONSECUTIVE_FAILURES` failures in a row
+//! - `State` and `Storage`: the runtime state or the block store can no longer
+//!   be trusted, so production stops at once
+//!
+//! A producer that gives up flags itself as stalled: the watchdog sends
+//! `nodeStalled` and `/ready` turns unready. The node's main task then shuts
+//! the node down or puts it in maintenance mode, as `--on-production-halt` says.
+
+use crate::BlockProducer;
+use log::error;
+use runtime::{AccountError, Runtime};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+
+/// Consecutive transient failures after which block production stops
+pub const MAX_CONSECUTIVE_FAILURES: u32 = 5;
+
+/// Longest wait between retries after a failed block, in block times
+pub const MAX_RETRY_BLOCK_TIMES: u64 = 8;
+
+/// Why a block could not be produced
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProductionError {
+    /// A step failed in a way that may clear by itself
+    Transient(String),
+    /// The runtime state cannot be trusted, for example after a panic poisoned one of its locks
+    State(String),
+    /// The block could not be stored
+    Storage(String),
+}
+
+impl ProductionError {
+    /// Checks whether the next block may succeed
+    pub fn is_transient(&self) -> bool {
+        matches!(self, ProductionError::Transient(_))
+    }
+}
+
+impl fmt::Display for ProductionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProductionError::Transient(e) => write!(f, "{}", e),
+            ProductionError::State(e) => write!(f, "runtime state unusable: {}", e),
+            ProductionError::Storage(e) => write!(f, "block storage failed: {}", e),
+        }
+    }
+}
+
+/// Why block production stopped for good
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProductionHalt {
+    /// Failures in a row, the last one included
+    pub consecutive_failures: u32,
+    /// The failure that stopped production
+    pub error: ProductionError,
+}
+
+impl fmt::Display for ProductionHalt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (after {} consecutive failures)", self.error, self.consecutive_failures)
+    }
+}
+
+/// Runtime operations a block depends on that can fail
+///
+/// The producer goes through this trait rather than the runtime itself, so
+/// tests can make the runtime fail.
+pub trait ProductionBackend: Send + Sync {
+    /// Checks that the runtime state can still be changed safely
+    fn check_state(&self) -> Result<(), ProductionError>;
+
+    /// Credits a block reward to its producer, recorded against the block hash
+    fn credit_block_reward(&self, address: &str, amount: u64, block_hash: &str) -> Result<(), AccountError>;
+}
+
+impl ProductionBackend for Runtime {
+    fn check_state(&self) -> Result<(), ProductionError> {
+        match self.poisoned_lock() {
+            Some(lock) => Err(ProductionError::State(format!("the {} lock is poisoned", lock))),
+            None => Ok(()),
+        }
+    }
+
+    fn credit_block_reward(&self, address: &str, amount: u64, block_hash: &str) -> Result<(), AccountError> {
+        Runtime::credit_block_reward(self, address, amount, block_hash).map(|_| ())
+    }
+}
+
+/// What the node does once block production stopped for good
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HaltAction {
+    /// Enter maintenance mode and keep serving reads
+    #[default]
+    Maintenance,
+    /// Shut the node down gracefully
+    Shutdown,
+}
+
+impl FromStr for HaltAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "maintenance" => Ok(HaltAction::Maintenance),
+            "shutdown" => Ok(HaltAction::Shutdown),
+            _ => Err(format!("Invalid production halt action: {} (expected maintenance or shutdown)", s)),
+        }
+    }
+}
+
+impl fmt::Display for HaltAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HaltAction::Maintenance => write!(f, "maintenance"),
+            HaltAction::Shutdown => write!(f, "shutdown"),
+        }
+    }
+}
+
+/// Waits for the production task to end and carries out the halt action
+///
+/// A panic in the task counts as a halt. Maintenance mode needs a usable
+/// runtime, so a halt caused by its state shuts the node down instead.
+///
+/// # Returns
+/// Once the node should shut down; never, while it stays up in maintenance mode
+pub async fn watch_production(
+    task: JoinHandle<Result<(), ProductionHalt>>,
+    producer: Arc<BlockProducer>,
+    runtime: Runtime,
+    action: HaltAction,
+) {
+    let halt = match task.await {
+        Ok(Ok(())) => return,
+        Ok(Err(halt)) => halt,
+        Err(e) => {
+            let halt = ProductionHalt {
+                consecutive_failures: 1,
+                error: ProductionError::State(format!("block producer panicked: {}", e)),
+            };
+            producer.mark_halted(&halt);
+            halt
+        },
+    };
+    error!(alert = "production_halted"; "Block production stopped: {}", halt);
+
+    if action == HaltAction::Shutdown || matches!(halt.error, ProductionError::State(_)) {
+        return;
+    }
+    runtime.set_maintenance(true, Some(format!("block production stopped: {}", halt)), Some("block producer".to_string()));
+    std::future::pending::<()>().await
+}
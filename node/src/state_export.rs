@@ -0,0 +1,121 @@
+//! State Export and Import
+//!
+//! Implements `ubi-chain-node export-state`, which renders the newest
+//! checkpoint in a directory as a JSON state snapshot, and `import-state`,
+//! which turns such a snapshot into the first checkpoint of a fresh
+//! checkpoint directory. Both go through the checkpoint format, so exporting
+//! an imported snapshot gives back the same bytes.
+
+use runtime::checkpoint;
+use runtime::state_snapshot::StateSnapshot;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Extension of checkpoint files
+const CHECKPOINT_EXTENSION: &str = "dat";
+
+/// Writes the newest checkpoint in a directory as a JSON state snapshot
+///
+/// # Arguments
+/// * `checkpoint_dir` - Directory holding the node's checkpoints
+/// * `out` - File to write the snapshot to
+///
+/// # Returns
+/// The snapshot written, or an error if no checkpoint could be read
+pub fn export_state(checkpoint_dir: &str, out: &str) -> io::Result<StateSnapshot> {
+    let newest = checkpoint_files(checkpoint_dir)?.pop().ok_or_else(|| io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("No checkpoints in {}", checkpoint_dir),
+    ))?;
+
+    let (_, data) = checkpoint::decode_checkpoint(&fs::read(&newest)?)?;
+    let snapshot = StateSnapshot::from_checkpoint(&data);
+    fs::write(out, snapshot.to_json())?;
+    Ok(snapshot)
+}
+
+/// Turns a JSON state snapshot into the first checkpoint of a fresh directory
+///
+/// # Arguments
+/// * `state` - File holding the snapshot
+/// * `checkpoint_dir` - Directory of the node to bootstrap; must hold no checkpoints
+///
+/// # Returns
+/// The path of the checkpoint written, or why the snapshot was refused
+pub fn import_state(state: &str, checkpoint_dir: &str) -> io::Result<PathBuf> {
+    let snapshot = StateSnapshot::from_json(&fs::read_to_string(state)?)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    let data = snapshot.to_checkpoint()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+
+    fs::create_dir_all(checkpoint_dir)?;
+    if !checkpoint_files(checkpoint_dir)?.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already holds checkpoints; import into a fresh directory", checkpoint_dir),
+        ));
+    }
+
+    let path = Path::new(checkpoint_dir).join(format!("checkpoint_{}.{}", data.timestamp, CHECKPOINT_EXTENSION));
+    checkpoint::write_checkpoint_file(&path, &checkpoint::encode_checkpoint(&data))?;
+    Ok(path)
+}
+
+/// Lists the checkpoint files in a directory, oldest first
+fn checkpoint_files(dir: &str) -> io::Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == CHECKPOINT_EXTENSION))
+        .collect();
+    // Names end in the creation time, so name order is age order
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> String {
+        format!("./test_state_export_{}_{}", name, std::process::id())
+    }
+
+    #[test]
+    fn test_export_import_export_is_byte_identical() {
+        let source_dir = test_dir("source");
+        let target_dir = test_dir("target");
+        let _ = fs::remove_dir_all(&source_dir);
+        let _ = fs::remove_dir_all(&target_dir);
+
+        // A live runtime with balances, dividends and a fee ledger
+        let runtime = runtime::Runtime::with_checkpoint_config(10, &source_dir).unwrap();
+        let addresses: Vec<String> = (1..=3).map(|i| format!("0x{:040x}", i)).collect();
+        for address in &addresses {
+            runtime.create_account(address).unwrap();
+            runtime.credit_balance(address, 10_000).unwrap();
+        }
+        runtime.transfer_with_fee(&addresses[2], &addresses[0], 1_000).unwrap();
+        runtime.distribute_fees();
+        runtime.create_checkpoint(true).unwrap();
+
+        let first_json = format!("{}/state.json", source_dir);
+        let exported = export_state(&source_dir, &first_json).unwrap();
+        assert_eq!(exported.accounts.len(), 3);
+        assert!(exported.accounts.windows(2).all(|pair| pair[0].address < pair[1].address));
+
+        import_state(&first_json, &target_dir).unwrap();
+        let second_json = format!("{}/state.json", target_dir);
+        let reexported = export_state(&target_dir, &second_json).unwrap();
+
+        assert_eq!(fs::read(&first_json).unwrap(), fs::read(&second_json).unwrap());
+        assert_eq!(reexported.state_root, exported.state_root);
+
+        // A directory that already has state is not overwritten
+        assert_eq!(import_state(&first_json, &target_dir).unwrap_err().kind(), io::ErrorKind::AlreadyExists);
+
+        let _ = fs::remove_dir_all(&source_dir);
+        let _ = fs::remove_dir_all(&target_dir);
+    }
+}
@@ -0,0 +1,681 @@
+//! Deterministic multi-node simulation harness
+//!
+//! Wires several Runtime + BlockProducer pairs together in one process over an
+//! in-memory transport, so gossip and sync scenarios can be tested without
+//! launching real processes. Time comes from a shared mock clock and every
+//! random value is drawn from a seeded generator; the seed is printed when a
+//! network is created so a failing run can be replayed with `UBI_TESTKIT_SEED`.
+//!
+//! Forks are not resolved: blocks are only synced onto nodes whose chain is a
+//! prefix of the peer's chain. Blocks a node rejects on import are recorded on
+//! the node and not relayed further by it.
+
+use crate::{Block, BlockProducer, Clock, Transaction};
+use runtime::config::RuntimeConfig;
+use runtime::transfer_mode::TransferMode;
+use runtime::Runtime;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::{broadcast, mpsc};
+
+/// Environment variable used to replay a run with a fixed seed
+const SEED_ENV_VAR: &str = "UBI_TESTKIT_SEED";
+
+/// Timestamp the mock clock starts at (2025-01-01T00:00:00Z)
+pub(crate) const GENESIS_TIMESTAMP: u64 = 1_735_689_600;
+
+/// Controllable clock shared by every node in a simulated network
+#[derive(Clone)]
+pub struct MockClock {
+    now: Arc<AtomicU64>,
+}
+
+impl MockClock {
+    /// Creates a clock frozen at the given UNIX timestamp
+    pub fn new(start: u64) -> Self {
+        MockClock {
+            now: Arc::new(AtomicU64::new(start)),
+        }
+    }
+
+    /// Gets the current mock time in seconds
+    pub fn now(&self) -> u64 {
+        self.now.load(Ordering::SeqCst)
+    }
+
+    /// Moves the clock forward by the given number of seconds
+    pub fn advance(&self, secs: u64) {
+        self.now.fetch_add(secs, Ordering::SeqCst);
+    }
+
+    /// Returns a `Clock` reading from this mock clock
+    pub fn as_clock(&self) -> Clock {
+        let now = self.now.clone();
+        Arc::new(move || now.load(Ordering::SeqCst))
+    }
+
+    /// Returns a `Clock` reading from this mock clock shifted by a changeable skew in seconds
+    pub fn as_skewed_clock(&self, skew: Arc<AtomicI64>) -> Clock {
+        let now = self.now.clone();
+        Arc::new(move || now.load(Ordering::SeqCst).saturating_add_signed(skew.load(Ordering::SeqCst)))
+    }
+}
+
+/// Small seeded generator (SplitMix64) so runs are reproducible from the seed
+pub struct DeterministicRng {
+    state: u64,
+}
+
+impl DeterministicRng {
+    /// Creates a generator from a seed
+    pub fn new(seed: u64) -> Self {
+        DeterministicRng { state: seed }
+    }
+
+    /// Returns the next pseudo-random value
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a pseudo-random 0x-prefixed Ethereum address
+    pub fn next_address(&mut self) -> String {
+        format!(
+            "0x{:08x}{:016x}{:016x}",
+            self.next_u64() as u32,
+            self.next_u64(),
+            self.next_u64()
+        )
+    }
+}
+
+/// A single node in a simulated network
+pub struct SimNode {
+    /// The node's runtime
+    pub runtime: Runtime,
+    /// The node's block producer
+    pub producer: BlockProducer,
+    /// Address credited with this node's block rewards
+    pub address: String,
+    /// Blocks this node has produced or imported, in order
+    pub chain: Vec<Block>,
+    /// Blocks this node refused to import, with the reason
+    pub rejected: Vec<(Block, String)>,
+    /// Offset of this node's clock from the shared mock clock in seconds
+    clock_skew: Arc<AtomicI64>,
+    /// Receiving end of the producer's block channel
+    block_receiver: mpsc::Receiver<Block>,
+}
+
+impl SimNode {
+    /// Gets the height of the node's chain
+    pub fn height(&self) -> u64 {
+        self.chain.len() as u64
+    }
+
+    /// Gets the hash of the node's latest block, if any
+    pub fn head_hash(&self) -> Option<String> {
+        self.chain.last().map(|block| block.hash.clone())
+    }
+}
+
+/// A set of nodes connected by an in-memory transport
+pub struct SimNetwork {
+    /// Seed every random value in this network was drawn from
+    pub seed: u64,
+    /// The simulated nodes, addressed by index
+    pub nodes: Vec<SimNode>,
+    /// Clock shared by all nodes
+    pub clock: MockClock,
+    /// Seeded generator for test data
+    rng: DeterministicRng,
+    /// Links that are currently cut, stored as (lower index, higher index)
+    partitions: HashSet<(usize, usize)>,
+}
+
+impl SimNetwork {
+    /// Creates a fully connected network of `node_count` nodes
+    ///
+    /// The seed is taken from `UBI_TESTKIT_SEED` when set, otherwise from the system time.
+    pub fn new(node_count: usize) -> Self {
+        Self::with_config(node_count, RuntimeConfig::default())
+    }
+
+    /// Creates a fully connected network of `node_count` nodes whose runtimes start from the given configuration
+    ///
+    /// The seed is chosen as by `new`.
+    pub fn with_config(node_count: usize, config: RuntimeConfig) -> Self {
+        let seed = std::env::var(SEED_ENV_VAR)
+            .ok()
+            .and_then(|value| value.parse::<u64>().ok())
+            .unwrap_or_else(|| {
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_nanos() as u64
+            });
+        Self::build(node_count, seed, config)
+    }
+
+    /// Creates a fully connected network of `node_count` nodes from a fixed seed
+    pub fn with_seed(node_count: usize, seed: u64) -> Self {
+        Self::build(node_count, seed, RuntimeConfig::default())
+    }
+
+    /// Creates the network from a seed and a runtime configuration
+    fn build(node_count: usize, seed: u64, config: RuntimeConfig) -> Self {
+        println!("testkit seed: {} (set {} to replay)", seed, SEED_ENV_VAR);
+
+        let clock = MockClock::new(GENESIS_TIMESTAMP);
+        let mut rng = DeterministicRng::new(seed);
+        let mut nodes = Vec::with_capacity(node_count);
+
+        for index in 0..node_count {
+            let runtime = Runtime::with_config(config);
+            let address = rng.next_address();
+            let (tx_sender, _) = broadcast::channel(100);
+            let (block_sender, block_receiver) = mpsc::channel(100);
+            let clock_skew = Arc::new(AtomicI64::new(0));
+
+            let producer = BlockProducer::new(
+                runtime.clone(),
+                1000,
+                format!("sim-node-{}", index),
+                address.clone(),
+                tx_sender,
+                block_sender,
+            )
+            .with_clock(clock.as_skewed_clock(clock_skew.clone()));
+
+            nodes.push(SimNode {
+                runtime,
+                producer,
+                address,
+                chain: Vec::new(),
+                rejected: Vec::new(),
+                clock_skew,
+                block_receiver,
+            });
+        }
+
+        let network = SimNetwork {
+            seed,
+            nodes,
+            clock,
+            rng,
+            partitions: HashSet::new(),
+        };
+        network.handshake();
+        network
+    }
+
+    /// Shifts a node's clock away from the shared mock clock
+    pub fn skew_clock(&self, node_idx: usize, skew_secs: i64) {
+        self.nodes[node_idx].clock_skew.store(skew_secs, Ordering::SeqCst);
+    }
+
+    /// Lets every pair of connected nodes exchange their current time and best block, as in a P2P handshake
+    pub fn handshake(&self) {
+        for (index, node) in self.nodes.iter().enumerate() {
+            for (peer_index, peer) in self.nodes.iter().enumerate() {
+                if self.is_connected(index, peer_index) {
+                    let peer_id = format!("sim-node-{}", peer_index);
+                    let peer_time = peer.producer.network_time().local_now();
+                    node.producer.network_time().record_peer_time(&peer_id, peer_time);
+                    node.producer.peer_tracker().record_peer_head(&peer_id, peer.producer.current_block());
+                }
+            }
+        }
+    }
+
+    /// Returns a fresh pseudo-random address drawn from the network seed
+    pub fn random_address(&mut self) -> String {
+        self.rng.next_address()
+    }
+
+    /// Creates an account with the same address on every node
+    pub fn create_account(&self, address: &str) {
+        for node in &self.nodes {
+            let _ = node.runtime.create_account(address);
+        }
+    }
+
+    /// Queues a transfer in the given node's transaction pool
+    pub fn submit_transfer(&mut self, node_idx: usize, from: &str, to: &str, amount: u64) -> String {
+        let hash = format!("0x{:016x}{:016x}", self.rng.next_u64(), self.rng.next_u64());
+        let tx = Transaction {
+            hash: hash.clone(),
+            from: from.to_lowercase(),
+            to: to.to_lowercase(),
+            amount,
+            fee: amount / 100,
+            timestamp: self.clock.now(),
+            failed: false,
+            mode: TransferMode::Send,
+            nonce: None,
+        };
+        self.nodes[node_idx].producer.tx_pool.add_transaction(tx);
+        hash
+    }
+
+    /// Produces a block on the given node and gossips it to every reachable peer
+    pub async fn produce_block(&mut self, node_idx: usize) -> Block {
+        let node = &mut self.nodes[node_idx];
+        let block = node.producer.produce_block().await
+            .unwrap_or_else(|e| panic!("node {} failed to produce a block: {} (seed {})", node_idx, e, self.seed));
+
+        // Drain the broadcast copy so the channel never fills up
+        let _ = node.block_receiver.try_recv();
+        node.chain.push(block.clone());
+
+        self.sync();
+        block
+    }
+
+    /// Cuts the link between two nodes
+    pub fn partition(&mut self, a: usize, b: usize) {
+        self.partitions.insert(Self::link(a, b));
+    }
+
+    /// Restores every cut link and lets the nodes catch up with each other
+    pub fn heal(&mut self) {
+        self.partitions.clear();
+        self.handshake();
+        self.sync();
+    }
+
+    /// Returns true if the two nodes can currently exchange blocks
+    pub fn is_connected(&self, a: usize, b: usize) -> bool {
+        a != b && !self.partitions.contains(&Self::link(a, b))
+    }
+
+    /// Returns true if every node has the same head block
+    pub fn is_converged(&self) -> bool {
+        let head = self.nodes.first().and_then(|node| node.head_hash());
+        self.nodes.iter().all(|node| node.head_hash() == head)
+    }
+
+    /// Copies missing blocks across connected links until no node can make progress
+    ///
+    /// Blocks are relayed, so a node cut off from the producer still receives them
+    /// through any peer it remains connected to.
+    fn sync(&mut self) {
+        loop {
+            let mut progressed = false;
+
+            for source in 0..self.nodes.len() {
+                for target in 0..self.nodes.len() {
+                    if !self.is_connected(source, target) {
+                        continue;
+                    }
+
+                    let target_height = self.nodes[target].chain.len();
+                    if self.nodes[source].chain.len() <= target_height {
+                        continue;
+                    }
+
+                    // Only extend chains that are a prefix of the source chain
+                    let shares_prefix = match self.nodes[target].chain.last() {
+                        Some(head) => self.nodes[source].chain[target_height - 1].hash == head.hash,
+                        None => true,
+                    };
+                    if !shares_prefix {
+                        continue;
+                    }
+
+                    let missing: Vec<Block> = self.nodes[source].chain[target_height..].to_vec();
+                    for block in missing {
+                        let node = &mut self.nodes[target];
+                        if node.rejected.iter().any(|(rejected, _)| rejected.hash == block.hash) {
+                            break;
+                        }
+                        match node.producer.import_block_from(&format!("sim-node-{}", source), &block) {
+                            Ok(()) => {
+                                node.chain.push(block);
+                                progressed = true;
+                            },
+                            Err(e) => {
+                                println!("node {} rejected block #{}: {} (seed {})", target, block.number, e, self.seed);
+                                node.rejected.push((block, e));
+                                break;
+                            },
+                        }
+                    }
+                }
+            }
+
+            if !progressed {
+                break;
+            }
+        }
+    }
+
+    fn link(a: usize, b: usize) -> (usize, usize) {
+        (a.min(b), a.max(b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use runtime::params::{self, ChainParam, PendingParamChange, DEFAULT_BLOCK_REWARD as BLOCK_REWARD};
+
+    #[tokio::test]
+    async fn test_two_node_gossip() {
+        let mut network = SimNetwork::new(2);
+        let recipient = network.random_address();
+        network.create_account(&recipient);
+
+        // Node 0 earns a reward in its first block, then spends part of it
+        network.produce_block(0).await;
+        let sender = network.nodes[0].address.clone();
+        network.submit_transfer(0, &sender, &recipient, 50);
+        let block = network.produce_block(0).await;
+
+        assert_eq!(block.transactions.len(), 1, "seed {}", network.seed);
+        assert!(network.is_converged(), "seed {}", network.seed);
+        assert_eq!(network.nodes[1].height(), 2, "seed {}", network.seed);
+        for node in &network.nodes {
+            assert_eq!(node.runtime.get_balance(&recipient), 50, "seed {}", network.seed);
+            assert_eq!(node.runtime.get_balance(&sender), 2 * BLOCK_REWARD - 50 - params::ChainParams::default().transfer_fee(50),
+                       "seed {}", network.seed);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_arrival_order_does_not_change_block_order() {
+        let mut network = SimNetwork::new(3);
+        for (a, b) in [(0, 1), (0, 2), (1, 2)] {
+            network.partition(a, b);
+        }
+        let sender = network.random_address();
+        let recipient = network.random_address();
+        network.create_account(&sender);
+        network.create_account(&recipient);
+
+        // Only one of the transfers can be funded, so the execution order decides which one fails
+        let transfers: Vec<Transaction> = [60, 50, 200].iter().map(|&amount| Transaction {
+            hash: format!("0x{:016x}", network.rng.next_u64()),
+            from: sender.clone(),
+            to: recipient.clone(),
+            amount,
+            fee: amount / 100,
+            timestamp: network.clock.now(),
+            failed: false,
+            mode: TransferMode::Send,
+            nonce: None,
+        }).collect();
+        for node in &network.nodes {
+            node.runtime.credit_balance(&sender, 100).unwrap();
+        }
+        for (index, node) in network.nodes.iter().take(2).enumerate() {
+            let mut arrivals = transfers.clone();
+            if index == 1 {
+                arrivals.reverse();
+            }
+            for tx in arrivals {
+                node.producer.tx_pool.add_transaction(tx);
+            }
+        }
+
+        let first = network.produce_block(0).await;
+        let second = network.produce_block(1).await;
+        let executed = |block: &Block| -> Vec<(String, bool)> {
+            block.transactions.iter().map(|tx| (tx.hash.clone(), tx.failed)).collect()
+        };
+        assert_eq!(executed(&first), executed(&second), "seed {}", network.seed);
+        assert_eq!(first.transactions[0].amount, 200, "seed {}", network.seed);
+        for node in network.nodes.iter().take(2) {
+            assert_eq!(node.runtime.get_balance(&recipient), network.nodes[0].runtime.get_balance(&recipient),
+                       "seed {}", network.seed);
+        }
+
+        // A block whose transactions are not in canonical order is refused
+        let mut reordered = first.clone();
+        reordered.transactions.reverse();
+        let refused = network.nodes[2].producer.import_block(&reordered).unwrap_err();
+        assert!(refused.contains("canonical order"), "{} (seed {})", refused, network.seed);
+        network.nodes[2].producer.import_block(&first).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_block_timestamps_follow_mock_clock() {
+        let mut network = SimNetwork::new(2);
+
+        let first = network.produce_block(0).await;
+        network.clock.advance(30);
+        let second = network.produce_block(1).await;
+
+        assert_eq!(first.timestamp, GENESIS_TIMESTAMP);
+        assert_eq!(second.timestamp, GENESIS_TIMESTAMP + 30);
+        assert!(network.is_converged(), "seed {}", network.seed);
+    }
+
+    #[tokio::test]
+    async fn test_late_node_syncs_missing_blocks() {
+        let mut network = SimNetwork::new(2);
+        network.partition(0, 1);
+
+        for _ in 0..3 {
+            network.produce_block(0).await;
+        }
+        assert_eq!(network.nodes[1].height(), 0, "seed {}", network.seed);
+
+        network.heal();
+
+        assert!(network.is_converged(), "seed {}", network.seed);
+        assert_eq!(network.nodes[1].producer.current_block(), 3, "seed {}", network.seed);
+        let producer = network.nodes[0].address.clone();
+        assert_eq!(network.nodes[1].runtime.get_balance(&producer), 3 * BLOCK_REWARD,
+                   "seed {}", network.seed);
+
+        // The synced blocks are credited to the peer that delivered them
+        let status = network.nodes[1].producer.peer_tracker().status(3);
+        assert!(!status.syncing, "seed {}", network.seed);
+        assert_eq!(status.peers[0].peer_id, "sim-node-0");
+        assert_eq!((status.peers[0].best_block, status.peers[0].blocks_received), (3, 3), "seed {}", network.seed);
+        assert!(status.peers[0].bytes_received > 0 && status.peers[0].blocks_per_sec > 0.0, "seed {}", network.seed);
+    }
+
+    #[tokio::test]
+    async fn test_stalled_node_is_flagged_until_it_imports_a_block() {
+        use runtime::BlockProducer as _;
+        use runtime::liveness::NodeHealthEvent;
+
+        let mut network = SimNetwork::new(2);
+        network.partition(0, 1);
+        network.produce_block(0).await;
+        network.clock.advance(crate::watchdog::STALL_BLOCK_TIME_MULTIPLE);
+        network.produce_block(0).await;
+
+        // The producing node is live; the cut-off one has made no progress since it started
+        assert_eq!(network.nodes[0].producer.check_liveness(), None, "seed {}", network.seed);
+        let event = network.nodes[1].producer.check_liveness();
+        assert!(matches!(event, Some(NodeHealthEvent::NodeStalled { last_block: 0, .. })), "seed {}", network.seed);
+        assert!(network.nodes[1].producer.liveness().stalled, "seed {}", network.seed);
+
+        // Importing the missed blocks ends the stall
+        network.heal();
+        let event = network.nodes[1].producer.check_liveness();
+        assert!(matches!(event, Some(NodeHealthEvent::NodeRecovered { last_block: 2, .. })), "seed {}", network.seed);
+        let liveness = network.nodes[1].producer.liveness();
+        assert!(!liveness.stalled && liveness.last_imported_at.is_some(), "seed {}", network.seed);
+    }
+
+    #[tokio::test]
+    async fn test_partition_and_heal_converges() {
+        let mut network = SimNetwork::new(3);
+
+        network.produce_block(0).await;
+        assert!(network.is_converged(), "seed {}", network.seed);
+
+        // Isolate node 2 completely; nodes 0 and 1 keep gossiping
+        network.partition(0, 2);
+        network.partition(1, 2);
+        network.produce_block(1).await;
+        network.produce_block(0).await;
+
+        assert_eq!(network.nodes[0].height(), 3, "seed {}", network.seed);
+        assert_eq!(network.nodes[1].height(), 3, "seed {}", network.seed);
+        assert_eq!(network.nodes[2].height(), 1, "seed {}", network.seed);
+        assert!(!network.is_converged(), "seed {}", network.seed);
+
+        network.heal();
+
+        assert!(network.is_converged(), "seed {}", network.seed);
+        for node in &network.nodes {
+            assert_eq!(node.height(), 3, "seed {}", network.seed);
+            assert_eq!(node.runtime.get_balance(&network.nodes[0].address), 2 * BLOCK_REWARD,
+                       "seed {}", network.seed);
+            assert_eq!(node.runtime.get_balance(&network.nodes[1].address), BLOCK_REWARD,
+                       "seed {}", network.seed);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_relay_through_connected_peer() {
+        let mut network = SimNetwork::new(3);
+
+        // Node 2 cannot talk to node 0 directly but still hears about its blocks via node 1
+        network.partition(0, 2);
+        network.produce_block(0).await;
+
+        assert!(network.is_converged(), "seed {}", network.seed);
+    }
+
+    #[tokio::test]
+    async fn test_skewed_producer_blocks_are_rejected() {
+        let mut network = SimNetwork::new(3);
+
+        // Node 0's clock runs two minutes ahead of everyone else
+        network.skew_clock(0, 120);
+        network.handshake();
+        assert_eq!(network.nodes[0].producer.network_time().offset(), -120, "seed {}", network.seed);
+        // Node 1 sees one accurate and one skewed peer, so the median lies halfway
+        assert_eq!(network.nodes[1].producer.network_time().offset(), 60, "seed {}", network.seed);
+
+        let block = network.produce_block(0).await;
+        assert_eq!(block.timestamp, GENESIS_TIMESTAMP + 120);
+        for node in &network.nodes[1..] {
+            assert_eq!(node.height(), 0, "seed {}", network.seed);
+            assert_eq!(node.rejected.len(), 1, "seed {}", network.seed);
+            assert!(node.rejected[0].1.contains("ahead of local time"), "seed {}", network.seed);
+            let status = node.producer.peer_tracker().status(0);
+            let producer = status.peers.iter().find(|peer| peer.peer_id == "sim-node-0").unwrap();
+            assert_eq!(producer.invalid_blocks, 1, "seed {}", network.seed);
+        }
+
+        // Within the drift allowance the block is accepted
+        let mut network = SimNetwork::with_seed(2, network.seed);
+        network.skew_clock(0, 10);
+        network.produce_block(0).await;
+        assert!(network.is_converged(), "seed {}", network.seed);
+    }
+
+    #[tokio::test]
+    async fn test_produced_timestamps_never_go_backwards() {
+        let mut network = SimNetwork::new(2);
+
+        network.skew_clock(0, 10);
+        let first = network.produce_block(0).await;
+        assert!(network.is_converged(), "seed {}", network.seed);
+
+        // Node 1's clock is behind its parent, so it reuses the parent timestamp
+        let second = network.produce_block(1).await;
+        assert_eq!(second.timestamp, first.timestamp, "seed {}", network.seed);
+        assert!(network.is_converged(), "seed {}", network.seed);
+    }
+
+    #[tokio::test]
+    async fn test_block_reward_change_applies_at_activation_block() {
+        let mut network = SimNetwork::new(2);
+        let activation = params::MIN_ACTIVATION_DELAY_BLOCKS + 5;
+
+        // A passed proposal queues the change in every node's runtime
+        for node in &network.nodes {
+            node.runtime.schedule_param_change(PendingParamChange {
+                param: ChainParam::BlockReward,
+                new_value: 40,
+                activates_at_block: activation,
+            }).unwrap();
+        }
+
+        let producer = network.nodes[0].address.clone();
+        for height in 1..activation {
+            network.produce_block(0).await;
+            assert_eq!(network.nodes[1].runtime.get_balance(&producer), height * BLOCK_REWARD,
+                       "reward changed early at block {}, seed {}", height, network.seed);
+        }
+
+        let block = network.produce_block(0).await;
+        assert_eq!(block.number, activation);
+        assert!(network.is_converged(), "seed {}", network.seed);
+        for node in &network.nodes {
+            assert_eq!(node.runtime.get_balance(&producer), (activation - 1) * BLOCK_REWARD + 40,
+                       "seed {}", network.seed);
+            assert_eq!(node.runtime.chain_params().block_reward, 40, "seed {}", network.seed);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fees_are_distributed_on_schedule_to_every_holder() {
+        let config = RuntimeConfig { fee_distribution_interval_blocks: 3, ..RuntimeConfig::default() };
+        let mut network = SimNetwork::with_config(2, config);
+        let (alice, bob) = (network.random_address(), network.random_address());
+        network.create_account(&alice);
+        network.create_account(&bob);
+        for node in &network.nodes {
+            node.runtime.credit_balance(&alice, 30_000).unwrap();
+            node.runtime.credit_balance(&bob, 10_000).unwrap();
+        }
+        let producer = network.nodes[0].address.clone();
+
+        // Blocks 1 and 2 collect fees; block 3 distributes them before its own transfers run
+        network.submit_transfer(0, &alice, &bob, 10_000);
+        network.produce_block(0).await;
+        network.submit_transfer(0, &bob, &alice, 5_000);
+        network.produce_block(0).await;
+        let holdings = |network: &SimNetwork| [&alice, &bob, &producer].map(|address| network.nodes[0].runtime.get_balance(address));
+        let before_first = holdings(&network);
+        network.submit_transfer(0, &alice, &bob, 4_000);
+        network.produce_block(0).await;
+        let before_second = holdings(&network);
+        for _ in 4..=6 {
+            network.produce_block(0).await;
+        }
+        assert!(network.is_converged(), "seed {}", network.seed);
+
+        // Every node paid each holder its share at the balance it held at each distribution
+        let history = network.nodes[0].runtime.get_distribution_history();
+        let fee = |amount| params::ChainParams::default().transfer_fee(amount);
+        assert_eq!(history.iter().map(|event| (event.block_number, event.amount)).collect::<Vec<_>>(),
+                   vec![(3, fee(10_000) + fee(5_000)), (6, fee(4_000))], "seed {}", network.seed);
+        let increases = [history[0].dividend_per_token, history[1].dividend_per_token - history[0].dividend_per_token];
+        let share = |balance: u64, increase: u64| balance * increase / runtime::dividends::DIVIDEND_PRECISION;
+        for node in &network.nodes {
+            assert_eq!(node.runtime.get_distribution_history(), history, "seed {}", network.seed);
+            for (index, address) in [&alice, &bob, &producer].into_iter().enumerate() {
+                assert_eq!(node.runtime.get_unclaimed_dividends(address),
+                           share(before_first[index], increases[0]) + share(before_second[index], increases[1]),
+                           "{} on {}, seed {}", address, node.address, network.seed);
+            }
+            assert_eq!(node.runtime.get_fee_pool(), 0, "seed {}", network.seed);
+        }
+        assert_eq!(before_first, [24_900, 14_950, 2 * BLOCK_REWARD], "seed {}", network.seed);
+    }
+
+    #[test]
+    fn test_same_seed_same_addresses() {
+        let mut a = SimNetwork::with_seed(2, 42);
+        let mut b = SimNetwork::with_seed(2, 42);
+
+        assert_eq!(a.nodes[0].address, b.nodes[0].address);
+        assert_eq!(a.nodes[1].address, b.nodes[1].address);
+        assert_eq!(a.random_address(), b.random_address());
+    }
+}
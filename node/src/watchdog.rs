@@ -0,0 +1,217 @@
+//! Stall Watchdog
+//!
+//! Monitoring cannot tell "no transactions" from "block production wedged" by
+//! looking at transfers. The block producer records when it last produced a
+//! block and when it last applied an imported one; the watchdog checks those
+//! once per block time. When neither happened for `STALL_BLOCK_TIME_MULTIPLE`
+//! block times, the node is flagged as stalled: the flag shows in
+//! `getNetworkStatus` and `/ready`, an error is logged with the last block's
+//! details, and `nodeHealth` subscribers receive `nodeStalled`. The next block
+//! clears the flag and sends `nodeRecovered`.
+//!
+//! A producer that stopped after repeated or unrecoverable failures is flagged
+//! at once, without waiting out the threshold, and stays flagged: a later
+//! imported block does not clear the flag, since production stays stopped.
+
+use crate::BlockProducer;
+use log::{error, info};
+use rpc::eth_pubsub::SubscriptionManager;
+use runtime::liveness::{LivenessStatus, NodeHealthEvent};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Block times without a produced or imported block after which the node counts as stalled
+pub const STALL_BLOCK_TIME_MULTIPLE: u64 = 10;
+
+/// A stall in progress
+#[derive(Debug, Clone, Copy)]
+struct Stall {
+    /// When the stall was detected
+    detected_at: u64,
+    /// When the node last made progress before the stall
+    last_progress_at: u64,
+    /// Whether `nodeStalled` was sent for the stall
+    announced: bool,
+}
+
+/// Tracks whether the node is stalled
+pub struct Watchdog {
+    /// Seconds without progress after which the node is stalled
+    threshold_secs: u64,
+    /// Start of the watch, standing in for the last progress until the first block
+    started_at: u64,
+    /// The stall in progress, if any
+    stall: Mutex<Option<Stall>>,
+    /// Why block production stopped for good, if it did
+    halted: Mutex<Option<String>>,
+}
+
+impl Watchdog {
+    /// Creates a watchdog for the given target block time
+    ///
+    /// # Arguments
+    /// * `block_time_ms` - Target time between blocks
+    /// * `started_at` - Current time; a node that never makes progress stalls relative to it
+    pub fn new(block_time_ms: u64, started_at: u64) -> Self {
+        Watchdog {
+            threshold_secs: (block_time_ms * STALL_BLOCK_TIME_MULTIPLE / 1000).max(1),
+            started_at,
+            stall: Mutex::new(None),
+            halted: Mutex::new(None),
+        }
+    }
+
+    /// Gets when the current stall was detected, if the node is stalled
+    pub fn stalled_since(&self) -> Option<u64> {
+        self.stall.lock().unwrap().map(|stall| stall.detected_at)
+    }
+
+    /// Gets why block production stopped for good, if it did
+    pub fn halted(&self) -> Option<String> {
+        self.halted.lock().unwrap().clone()
+    }
+
+    /// Flags the node as stalled because block production stopped for good
+    ///
+    /// The next check sends `nodeStalled`, unless it was already sent for the current stall.
+    ///
+    /// # Arguments
+    /// * `now` - Current time in seconds
+    /// * `status` - The node's latest block and when it last produced or imported one
+    /// * `reason` - Why production stopped
+    pub fn halt(&self, now: u64, status: &LivenessStatus, reason: String) {
+        *self.halted.lock().unwrap() = Some(reason);
+        let mut stall = self.stall.lock().unwrap();
+        if stall.is_none() {
+            *stall = Some(Stall {
+                detected_at: now,
+                last_progress_at: status.last_progress_at().unwrap_or(self.started_at),
+                announced: false,
+            });
+        }
+    }
+
+    /// Checks the node's progress, returning the event if the node stalled or recovered
+    ///
+    /// # Arguments
+    /// * `now` - Current time in seconds
+    /// * `status` - The node's latest block and when it last produced or imported one
+    pub fn check(&self, now: u64, status: &LivenessStatus) -> Option<NodeHealthEvent> {
+        let last_progress_at = status.last_progress_at().unwrap_or(self.started_at);
+        let idle_secs = now.saturating_sub(last_progress_at);
+        let halted = self.halted.lock().unwrap().is_some();
+        let mut stall = self.stall.lock().unwrap();
+        let stalled = NodeHealthEvent::NodeStalled {
+            last_block: status.last_block,
+            last_progress_at: status.last_progress_at(),
+            stalled_for_secs: idle_secs,
+        };
+
+        match stall.as_mut() {
+            None if idle_secs >= self.threshold_secs => {
+                *stall = Some(Stall { detected_at: now, last_progress_at, announced: true });
+                Some(stalled)
+            },
+            Some(current) if !current.announced => {
+                current.announced = true;
+                Some(stalled)
+            },
+            Some(previous) if !halted && last_progress_at > previous.last_progress_at => {
+                let previous = *previous;
+                *stall = None;
+                Some(NodeHealthEvent::NodeRecovered {
+                    last_block: status.last_block,
+                    stalled_for_secs: last_progress_at - previous.last_progress_at,
+                })
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Checks the producer's progress once per block time, forever
+///
+/// # Arguments
+/// * `producer` - The block producer to watch
+/// * `subscriptions` - Subscriptions that receive `nodeHealth` events
+pub async fn run_watchdog(producer: Arc<BlockProducer>, subscriptions: Arc<SubscriptionManager>) {
+    let interval = Duration::from_millis(producer.block_time_ms().max(1));
+    loop {
+        tokio::time::sleep(interval).await;
+
+        let event = match producer.check_liveness() {
+            Some(event) => event,
+            None => continue,
+        };
+        match &event {
+            NodeHealthEvent::NodeStalled { last_block, last_progress_at, stalled_for_secs } => {
+                let last_block_details = producer.get_block_record(*last_block)
+                    .map(|record| format!("hash {}, timestamp {}, {} transactions",
+                                          record.block.hash, record.block.timestamp, record.block.transactions.len()))
+                    .unwrap_or_else(|| "not recorded".to_string());
+                error!(alert = "node_stalled", block_number = *last_block;
+                       "No block produced or imported for {}s (last progress at {:?}); last block #{}: {}",
+                       stalled_for_secs, last_progress_at, last_block, last_block_details);
+            },
+            NodeHealthEvent::NodeRecovered { last_block, stalled_for_secs } => {
+                info!(block_number = *last_block; "Node recovered at block #{} after a {}s stall", last_block, stalled_for_secs);
+            },
+        }
+        subscriptions.notify_node_health(&event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stall_and_recovery() {
+        let watchdog = Watchdog::new(1000, 100);
+        let threshold = STALL_BLOCK_TIME_MULTIPLE;
+        let mut status = LivenessStatus::default();
+
+        // A node that never produces stalls relative to its start
+        assert_eq!(watchdog.check(100 + threshold - 1, &status), None);
+        status.last_block = 3;
+        status.last_produced_at = Some(105);
+        assert_eq!(watchdog.check(105 + threshold - 1, &status), None);
+        assert_eq!(watchdog.check(105 + threshold, &status), Some(NodeHealthEvent::NodeStalled {
+            last_block: 3,
+            last_progress_at: Some(105),
+            stalled_for_secs: threshold,
+        }));
+        assert_eq!(watchdog.stalled_since(), Some(105 + threshold));
+
+        // Reported once per stall
+        assert_eq!(watchdog.check(200, &status), None);
+
+        // An imported block ends the stall
+        status.last_block = 4;
+        status.last_imported_at = Some(205);
+        assert_eq!(watchdog.check(205, &status), Some(NodeHealthEvent::NodeRecovered { last_block: 4, stalled_for_secs: 100 }));
+        assert_eq!(watchdog.stalled_since(), None);
+    }
+
+    #[test]
+    fn test_halted_production_stalls_at_once_and_stays_stalled() {
+        let watchdog = Watchdog::new(1000, 100);
+        let mut status = LivenessStatus { last_block: 7, last_produced_at: Some(110), ..LivenessStatus::default() };
+
+        watchdog.halt(111, &status, "reward credit failed".to_string());
+        assert_eq!(watchdog.stalled_since(), Some(111));
+        assert_eq!(watchdog.halted().as_deref(), Some("reward credit failed"));
+        assert_eq!(watchdog.check(112, &status), Some(NodeHealthEvent::NodeStalled {
+            last_block: 7,
+            last_progress_at: Some(110),
+            stalled_for_secs: 2,
+        }));
+        assert_eq!(watchdog.check(113, &status), None);
+
+        // Imported blocks do not restart production
+        status.last_block = 8;
+        status.last_imported_at = Some(120);
+        assert_eq!(watchdog.check(120, &status), None);
+        assert_eq!(watchdog.stalled_since(), Some(111));
+    }
+}
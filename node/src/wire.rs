@@ -0,0 +1,425 @@
+//! P2P Wire Format
+//!
+//! Every message between peers travels in a frame that names the protocol
+//! version its payload is encoded in, so a peer speaking a different version
+//! is refused instead of having its messages misparsed:
+//!
+//! ```text
+//! magic "UBIC" | version u16 | kind u8 | payload length u32 | payload
+//! ```
+//!
+//! Integers are big-endian. A connection opens with each side sending a hello
+//! frame, whose layout is fixed across versions: the lowest and highest
+//! protocol versions the node speaks, a capability bitfield, its best block,
+//! its clock and its node id. Later versions may append fields to the hello;
+//! readers ignore bytes they do not know. Both sides then speak the highest
+//! version they share, and refuse the peer if there is none. After the
+//! handshake a frame in any other version, of an unknown kind or of a kind the
+//! negotiated capabilities do not cover is refused, and the connection closed.
+//!
+//! Version 1 encodes blocks and transactions as JSON and pings as a u64.
+//! Connections from nodes predating the framing send bytes without the magic
+//! and are refused the same way.
+
+use crate::{Block, Transaction};
+use std::fmt;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Bytes every frame starts with
+pub const MAGIC: [u8; 4] = *b"UBIC";
+
+/// Highest protocol version this node speaks
+pub const PROTOCOL_VERSION: u16 = 1;
+
+/// Lowest protocol version this node accepts from peers
+pub const MIN_PROTOCOL_VERSION: u16 = 1;
+
+/// Largest payload accepted in a frame
+pub const MAX_PAYLOAD_BYTES: u32 = 4 * 1024 * 1024;
+
+/// Size of a frame header: magic, version, kind and payload length
+pub const HEADER_BYTES: usize = 11;
+
+/// Size of the fixed part of a hello payload, before the node id
+const HELLO_FIXED_BYTES: usize = 30;
+
+/// Optional protocol features, one bit each
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Capabilities(pub u64);
+
+impl Capabilities {
+    /// Announces and accepts new blocks
+    pub const BLOCK_GOSSIP: Capabilities = Capabilities(1 << 0);
+    /// Relays pending transactions
+    pub const TX_GOSSIP: Capabilities = Capabilities(1 << 1);
+    /// Serves state snapshots for fast sync; not implemented yet
+    #[allow(dead_code)]
+    pub const SNAPSHOT_SYNC: Capabilities = Capabilities(1 << 2);
+
+    /// Capabilities of this node
+    pub const LOCAL: Capabilities = Capabilities(Self::BLOCK_GOSSIP.0 | Self::TX_GOSSIP.0);
+
+    /// Checks whether every capability in `other` is present
+    pub fn contains(self, other: Capabilities) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Gets the capabilities present in both
+    pub fn intersection(self, other: Capabilities) -> Capabilities {
+        Capabilities(self.0 & other.0)
+    }
+}
+
+/// Kind of a frame, as sent on the wire
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum FrameKind {
+    Hello = 0,
+    Ping = 1,
+    NewBlock = 2,
+    NewTransaction = 3,
+}
+
+impl FrameKind {
+    /// Reads a kind byte
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(FrameKind::Hello),
+            1 => Some(FrameKind::Ping),
+            2 => Some(FrameKind::NewBlock),
+            3 => Some(FrameKind::NewTransaction),
+            _ => None,
+        }
+    }
+
+    /// Gets the capability a peer needs to receive this kind, if any
+    fn required_capability(self) -> Option<Capabilities> {
+        match self {
+            FrameKind::Hello | FrameKind::Ping => None,
+            FrameKind::NewBlock => Some(Capabilities::BLOCK_GOSSIP),
+            FrameKind::NewTransaction => Some(Capabilities::TX_GOSSIP),
+        }
+    }
+}
+
+/// Reason a frame or a peer was refused
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WireError {
+    /// The bytes do not start with `MAGIC`; the peer predates the framing or is not a node
+    BadMagic,
+    /// Fewer bytes than the frame declares
+    Truncated,
+    /// The declared payload is larger than `MAX_PAYLOAD_BYTES`
+    PayloadTooLarge(u32),
+    /// The peer shares no protocol version with this node
+    Incompatible { ours: (u16, u16), theirs: (u16, u16) },
+    /// A frame in a version other than the negotiated one
+    VersionMismatch { expected: u16, got: u16 },
+    /// A kind byte this version does not define
+    UnknownKind(u8),
+    /// A hello where a message was expected, or a message where a hello was expected
+    UnexpectedKind(u8),
+    /// A message the peer did not negotiate the capability for
+    MissingCapability(u8),
+    /// The payload does not decode as its kind
+    Malformed(String),
+    /// Reading from the connection failed
+    Io(String),
+}
+
+impl fmt::Display for WireError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WireError::BadMagic => write!(f, "Not a UBI Chain frame"),
+            WireError::Truncated => write!(f, "Frame is truncated"),
+            WireError::PayloadTooLarge(len) => write!(f, "Payload of {} bytes exceeds {}", len, MAX_PAYLOAD_BYTES),
+            WireError::Incompatible { ours, theirs } => write!(
+                f, "No shared protocol version: we speak {}-{}, the peer {}-{}", ours.0, ours.1, theirs.0, theirs.1
+            ),
+            WireError::VersionMismatch { expected, got } => write!(f, "Frame in version {}, negotiated {}", got, expected),
+            WireError::UnknownKind(kind) => write!(f, "Unknown frame kind {}", kind),
+            WireError::UnexpectedKind(kind) => write!(f, "Unexpected frame kind {}", kind),
+            WireError::MissingCapability(kind) => write!(f, "Frame kind {} needs a capability the peer lacks", kind),
+            WireError::Malformed(e) => write!(f, "Malformed payload: {}", e),
+            WireError::Io(e) => write!(f, "Connection error: {}", e),
+        }
+    }
+}
+
+/// A frame as read from the wire, before its payload is decoded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub version: u16,
+    pub kind: u8,
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    /// Encodes the frame with its header
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_BYTES + self.payload.len());
+        bytes.extend_from_slice(&MAGIC);
+        bytes.extend_from_slice(&self.version.to_be_bytes());
+        bytes.push(self.kind);
+        bytes.extend_from_slice(&(self.payload.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(&self.payload);
+        bytes
+    }
+
+    /// Decodes one frame from the start of `bytes`
+    ///
+    /// # Returns
+    /// The frame and the number of bytes it took
+    #[allow(dead_code)]
+    pub fn decode(bytes: &[u8]) -> Result<(Frame, usize), WireError> {
+        let header = bytes.get(..HEADER_BYTES).ok_or(WireError::Truncated)?;
+        let (version, kind, len) = parse_header(header)?;
+        let end = HEADER_BYTES + len as usize;
+        let payload = bytes.get(HEADER_BYTES..end).ok_or(WireError::Truncated)?.to_vec();
+        Ok((Frame { version, kind, payload }, end))
+    }
+
+    /// Reads one frame from a connection
+    pub async fn read<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Frame, WireError> {
+        let mut header = [0u8; HEADER_BYTES];
+        reader.read_exact(&mut header).await.map_err(read_error)?;
+        let (version, kind, len) = parse_header(&header)?;
+        let mut payload = vec![0u8; len as usize];
+        reader.read_exact(&mut payload).await.map_err(read_error)?;
+        Ok(Frame { version, kind, payload })
+    }
+}
+
+/// What a node announces when a connection opens
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hello {
+    pub min_version: u16,
+    pub max_version: u16,
+    pub capabilities: Capabilities,
+    pub best_block: u64,
+    /// The node's clock, in seconds since epoch
+    pub time: u64,
+    pub node_id: String,
+}
+
+impl Hello {
+    /// Creates this node's hello
+    pub fn local(node_id: &str, best_block: u64, time: u64) -> Self {
+        Hello {
+            min_version: MIN_PROTOCOL_VERSION,
+            max_version: PROTOCOL_VERSION,
+            capabilities: Capabilities::LOCAL,
+            best_block,
+            time,
+            node_id: node_id.to_string(),
+        }
+    }
+
+    /// Encodes the hello as a frame
+    pub fn to_frame(&self) -> Frame {
+        let mut payload = Vec::with_capacity(HELLO_FIXED_BYTES + self.node_id.len());
+        payload.extend_from_slice(&self.min_version.to_be_bytes());
+        payload.extend_from_slice(&self.max_version.to_be_bytes());
+        payload.extend_from_slice(&self.capabilities.0.to_be_bytes());
+        payload.extend_from_slice(&self.best_block.to_be_bytes());
+        payload.extend_from_slice(&self.time.to_be_bytes());
+        payload.extend_from_slice(&(self.node_id.len() as u16).to_be_bytes());
+        payload.extend_from_slice(self.node_id.as_bytes());
+        Frame { version: self.max_version, kind: FrameKind::Hello as u8, payload }
+    }
+
+    /// Decodes a hello frame of any version, ignoring fields appended by later versions
+    pub fn from_frame(frame: &Frame) -> Result<Self, WireError> {
+        if frame.kind != FrameKind::Hello as u8 {
+            return Err(WireError::UnexpectedKind(frame.kind));
+        }
+        let bytes = &frame.payload;
+        let fixed = bytes.get(..HELLO_FIXED_BYTES).ok_or(WireError::Truncated)?;
+        let u16_at = |at: usize| u16::from_be_bytes([fixed[at], fixed[at + 1]]);
+        let u64_at = |at: usize| u64::from_be_bytes(fixed[at..at + 8].try_into().unwrap());
+        let id_len = u16_at(28) as usize;
+        let node_id = bytes.get(HELLO_FIXED_BYTES..HELLO_FIXED_BYTES + id_len).ok_or(WireError::Truncated)?;
+        Ok(Hello {
+            min_version: u16_at(0),
+            max_version: u16_at(2),
+            capabilities: Capabilities(u64_at(4)),
+            best_block: u64_at(12),
+            time: u64_at(20),
+            node_id: String::from_utf8(node_id.to_vec()).map_err(|e| WireError::Malformed(e.to_string()))?,
+        })
+    }
+}
+
+/// What two peers agreed on in their handshake
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Session {
+    pub version: u16,
+    pub capabilities: Capabilities,
+}
+
+impl Session {
+    /// Agrees on the highest version both hellos speak and the capabilities both have
+    pub fn negotiate(ours: &Hello, theirs: &Hello) -> Result<Self, WireError> {
+        let version = ours.max_version.min(theirs.max_version);
+        if version < ours.min_version.max(theirs.min_version) {
+            return Err(WireError::Incompatible {
+                ours: (ours.min_version, ours.max_version),
+                theirs: (theirs.min_version, theirs.max_version),
+            });
+        }
+        Ok(Session { version, capabilities: ours.capabilities.intersection(theirs.capabilities) })
+    }
+
+    /// Encodes a message in the negotiated version
+    ///
+    /// # Returns
+    /// The frame, or MissingCapability if the peer did not negotiate the message's kind
+    #[allow(dead_code)]
+    pub fn encode(&self, message: &Message) -> Result<Frame, WireError> {
+        let kind = message.kind();
+        self.check_capability(kind)?;
+        let payload = match message {
+            Message::Ping(nonce) => nonce.to_be_bytes().to_vec(),
+            Message::NewBlock(block) => serde_json::to_vec(block).map_err(|e| WireError::Malformed(e.to_string()))?,
+            Message::NewTransaction(tx) => serde_json::to_vec(tx).map_err(|e| WireError::Malformed(e.to_string()))?,
+        };
+        Ok(Frame { version: self.version, kind: kind as u8, payload })
+    }
+
+    /// Decodes a message, refusing frames outside the negotiated version and capabilities
+    pub fn decode(&self, frame: &Frame) -> Result<Message, WireError> {
+        if frame.version != self.version {
+            return Err(WireError::VersionMismatch { expected: self.version, got: frame.version });
+        }
+        let kind = FrameKind::from_byte(frame.kind).ok_or(WireError::UnknownKind(frame.kind))?;
+        self.check_capability(kind)?;
+        let malformed = |e: serde_json::Error| WireError::Malformed(e.to_string());
+        match kind {
+            FrameKind::Hello => Err(WireError::UnexpectedKind(frame.kind)),
+            FrameKind::Ping => {
+                let nonce: [u8; 8] = frame.payload.as_slice().try_into()
+                    .map_err(|_| WireError::Malformed("ping is not 8 bytes".to_string()))?;
+                Ok(Message::Ping(u64::from_be_bytes(nonce)))
+            },
+            FrameKind::NewBlock => serde_json::from_slice(&frame.payload).map(Message::NewBlock).map_err(malformed),
+            FrameKind::NewTransaction => serde_json::from_slice(&frame.payload).map(Message::NewTransaction).map_err(malformed),
+        }
+    }
+
+    /// Checks that the session covers a kind of message
+    fn check_capability(&self, kind: FrameKind) -> Result<(), WireError> {
+        match kind.required_capability() {
+            Some(capability) if !self.capabilities.contains(capability) => Err(WireError::MissingCapability(kind as u8)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A message exchanged after the handshake
+#[derive(Debug, Clone)]
+pub enum Message {
+    Ping(u64),
+    NewBlock(Block),
+    NewTransaction(Transaction),
+}
+
+impl Message {
+    /// Gets the frame kind the message travels as
+    #[allow(dead_code)]
+    fn kind(&self) -> FrameKind {
+        match self {
+            Message::Ping(_) => FrameKind::Ping,
+            Message::NewBlock(_) => FrameKind::NewBlock,
+            Message::NewTransaction(_) => FrameKind::NewTransaction,
+        }
+    }
+}
+
+/// Parses a frame header into its version, kind and payload length
+fn parse_header(header: &[u8]) -> Result<(u16, u8, u32), WireError> {
+    if header[..4] != MAGIC {
+        return Err(WireError::BadMagic);
+    }
+    let version = u16::from_be_bytes([header[4], header[5]]);
+    let len = u32::from_be_bytes([header[7], header[8], header[9], header[10]]);
+    if len > MAX_PAYLOAD_BYTES {
+        return Err(WireError::PayloadTooLarge(len));
+    }
+    Ok((version, header[6], len))
+}
+
+/// Maps a failed read, treating a connection closed mid-frame as truncation
+fn read_error(e: std::io::Error) -> WireError {
+    if e.kind() == std::io::ErrorKind::UnexpectedEof {
+        WireError::Truncated
+    } else {
+        WireError::Io(e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A hello as a node speaking only `min..=max` would send it
+    fn peer_hello(min: u16, max: u16, capabilities: Capabilities) -> Hello {
+        Hello { min_version: min, max_version: max, capabilities, best_block: 7, time: 1_000, node_id: "peer".to_string() }
+    }
+
+    #[test]
+    fn test_version_negotiation_matrix() {
+        let ours = Hello::local("node", 3, 1_000);
+        let hello = Hello::from_frame(&Frame::decode(&ours.to_frame().encode()).unwrap().0).unwrap();
+        assert_eq!(hello, ours);
+
+        // (peer min, peer max) -> negotiated version, or refused
+        let matrix = [
+            ((MIN_PROTOCOL_VERSION, PROTOCOL_VERSION), Some(PROTOCOL_VERSION)),
+            ((0, 0), None),
+            ((PROTOCOL_VERSION, PROTOCOL_VERSION + 2), Some(PROTOCOL_VERSION)),
+            ((PROTOCOL_VERSION + 1, PROTOCOL_VERSION + 2), None),
+        ];
+        for ((min, max), expected) in matrix {
+            let negotiated = Session::negotiate(&ours, &peer_hello(min, max, Capabilities::LOCAL));
+            assert_eq!(negotiated.as_ref().ok().map(|session| session.version), expected, "peer {}-{}", min, max);
+            if expected.is_none() {
+                assert!(matches!(negotiated, Err(WireError::Incompatible { .. })));
+            }
+        }
+
+        // A later version's hello with extra trailing fields still parses
+        let mut frame = peer_hello(1, PROTOCOL_VERSION + 1, Capabilities(u64::MAX)).to_frame();
+        frame.payload.extend_from_slice(b"future fields");
+        let newer = Hello::from_frame(&Frame::decode(&frame.encode()).unwrap().0).unwrap();
+        assert_eq!(Session::negotiate(&ours, &newer).unwrap().capabilities, Capabilities::LOCAL);
+    }
+
+    #[test]
+    fn test_frames_from_older_or_mismatched_peers_are_refused() {
+        let session = Session { version: PROTOCOL_VERSION, capabilities: Capabilities::BLOCK_GOSSIP };
+
+        // Fixtures of what other peers put on the wire
+        let unframed_echo = b"hello from a node without framing".to_vec();
+        let version_zero_ping = Frame { version: 0, kind: FrameKind::Ping as u8, payload: 5u64.to_be_bytes().to_vec() };
+        let unknown_kind = Frame { version: PROTOCOL_VERSION, kind: 42, payload: Vec::new() };
+        let short_ping = Frame { version: PROTOCOL_VERSION, kind: FrameKind::Ping as u8, payload: vec![1, 2] };
+        let garbled_block = Frame { version: PROTOCOL_VERSION, kind: FrameKind::NewBlock as u8, payload: b"{\"number\":".to_vec() };
+        let transaction = Frame { version: PROTOCOL_VERSION, kind: FrameKind::NewTransaction as u8, payload: b"{}".to_vec() };
+
+        assert_eq!(Frame::decode(&unframed_echo), Err(WireError::BadMagic));
+        assert_eq!(Frame::decode(&version_zero_ping.encode()[..HEADER_BYTES + 3]), Err(WireError::Truncated));
+        let mut oversized = version_zero_ping.encode();
+        oversized[7..11].copy_from_slice(&(MAX_PAYLOAD_BYTES + 1).to_be_bytes());
+        assert_eq!(Frame::decode(&oversized), Err(WireError::PayloadTooLarge(MAX_PAYLOAD_BYTES + 1)));
+
+        assert_eq!(session.decode(&version_zero_ping).unwrap_err(), WireError::VersionMismatch { expected: PROTOCOL_VERSION, got: 0 });
+        assert_eq!(session.decode(&unknown_kind).unwrap_err(), WireError::UnknownKind(42));
+        assert!(matches!(session.decode(&short_ping), Err(WireError::Malformed(_))));
+        assert!(matches!(session.decode(&garbled_block), Err(WireError::Malformed(_))));
+        assert_eq!(session.decode(&transaction).unwrap_err(), WireError::MissingCapability(FrameKind::NewTransaction as u8));
+        assert_eq!(session.decode(&Hello::local("node", 0, 0).to_frame()).unwrap_err(), WireError::UnexpectedKind(0));
+
+        let ping = session.encode(&Message::Ping(9)).unwrap();
+        assert!(matches!(session.decode(&Frame::decode(&ping.encode()).unwrap().0), Ok(Message::Ping(9))));
+    }
+}
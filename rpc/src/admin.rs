@@ -0,0 +1,240 @@
+//! Admin Housekeeping Actions
+//!
+//! Operators can force housekeeping over RPC: distribute the fee pool, run the
+//! supply audit, rebuild the state tree to rule out drift in its incremental
+//! updates, switch maintenance mode on and off, change log levels, set and
+//! remove the labels explorers show for addresses, freeze and unfreeze
+//! accounts under review, start a scheduled job, or
+//! export and import the whole state as a JSON snapshot. Each action must be signed by the operations admin
+//! over `signature::admin_action_message`, which covers the action's
+//! parameters and the next admin nonce. The nonce is kept in the runtime's
+//! state store and used up before the action runs, so a captured signature
+//! cannot be replayed, not even after a restart. Every action that runs is
+//! recorded with the signer as an admin event.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use runtime::Runtime;
+
+/// A housekeeping action the operations admin can trigger
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AdminAction {
+    /// Distribute the fee pool to token holders now
+    DistributeFees,
+    /// Report where the total supply is held, with every supply event
+    Audit,
+    /// Rebuild the state tree from the account store and compare roots
+    RecomputeStateRoot,
+    /// Refuse state changes until maintenance mode is disabled
+    EnableMaintenance,
+    /// Resume state changes and flush a checkpoint
+    DisableMaintenance,
+    /// Change the log level of a target
+    SetLogLevel,
+    /// Set or replace the label of an address
+    SetLabel,
+    /// Remove the label of an address
+    RemoveLabel,
+    /// Freeze an account pending review
+    FreezeAccount,
+    /// Lift the freeze on an account
+    UnfreezeAccount,
+    /// Start a scheduled job now
+    RunJob,
+    /// Capture the whole state as a JSON snapshot
+    ExportState,
+    /// Replace the whole state with a JSON snapshot
+    ImportState,
+}
+
+impl fmt::Display for AdminAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AdminAction::DistributeFees => write!(f, "distribute_fees"),
+            AdminAction::Audit => write!(f, "audit"),
+            AdminAction::RecomputeStateRoot => write!(f, "recompute_state_root"),
+            AdminAction::EnableMaintenance => write!(f, "enable_maintenance"),
+            AdminAction::DisableMaintenance => write!(f, "disable_maintenance"),
+            AdminAction::SetLogLevel => write!(f, "set_log_level"),
+            AdminAction::SetLabel => write!(f, "set_label"),
+            AdminAction::RemoveLabel => write!(f, "remove_label"),
+            AdminAction::FreezeAccount => write!(f, "freeze_account"),
+            AdminAction::UnfreezeAccount => write!(f, "unfreeze_account"),
+            AdminAction::RunJob => write!(f, "run_job"),
+            AdminAction::ExportState => write!(f, "export_state"),
+            AdminAction::ImportState => write!(f, "import_state"),
+        }
+    }
+}
+
+/// Record of an admin action that ran
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdminEvent {
+    /// Nonce the action was signed with; numbers continue across restarts
+    pub sequence: u64,
+    /// The action
+    pub action: AdminAction,
+    /// Address that signed the request
+    pub caller: String,
+    /// When the action ran, in seconds since the epoch
+    pub timestamp: u64,
+    /// Short summary of the result
+    pub outcome: String,
+}
+
+/// Counters for monitoring admin actions
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdminMetrics {
+    /// Admin actions run since startup
+    pub actions: u64,
+    /// State root recomputations whose root differed from the incremental one
+    pub state_root_mismatches: u64,
+    /// When the last mismatch was found, in seconds since the epoch
+    pub last_state_root_mismatch: Option<u64>,
+}
+
+/// Log of admin actions, in the order they ran
+pub struct AdminLog {
+    /// Recorded events; the lock is held while an action runs so actions are serialized
+    events: Mutex<Vec<AdminEvent>>,
+    /// Monitoring counters
+    metrics: Mutex<AdminMetrics>,
+    /// Runtime keeping the admin nonce
+    runtime: Runtime,
+}
+
+impl fmt::Debug for AdminLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AdminLog")
+            .field("events", &self.events)
+            .field("metrics", &self.metrics)
+            .field("next_nonce", &self.next_nonce())
+            .finish()
+    }
+}
+
+impl AdminLog {
+    /// Creates an empty log whose nonces are kept by a runtime
+    pub fn new(runtime: Runtime) -> Self {
+        AdminLog {
+            events: Mutex::new(Vec::new()),
+            metrics: Mutex::new(AdminMetrics::default()),
+            runtime,
+        }
+    }
+
+    /// Gets the nonce the next admin request must be signed with
+    pub fn next_nonce(&self) -> u64 {
+        self.runtime.admin_nonce()
+    }
+
+    /// Authorizes an action, runs it and records it
+    ///
+    /// The nonce is used up once the request is authorized, before the
+    /// action runs.
+    ///
+    /// # Arguments
+    /// * `action` - The action requested
+    /// * `authorize` - Checks the request signature against the nonce and
+    ///   returns the caller's address
+    /// * `run` - Performs the action, returning its result and a summary
+    ///
+    /// # Returns
+    /// The result and the recorded event, or why the request was refused
+    pub fn perform<T>(
+        &self,
+        action: AdminAction,
+        authorize: impl FnOnce(u64) -> Result<String, String>,
+        run: impl FnOnce() -> (T, String),
+    ) -> Result<(T, AdminEvent), String> {
+        let mut events = self.events.lock().unwrap();
+        let caller = authorize(self.runtime.admin_nonce())?;
+        let sequence = self.runtime.advance_admin_nonce()
+            .map_err(|e| format!("Failed to store the admin nonce: {}", e))?;
+
+        let (result, outcome) = run();
+        let event = AdminEvent {
+            sequence,
+            action,
+            caller,
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+            outcome,
+        };
+        events.push(event.clone());
+        self.metrics.lock().unwrap().actions += 1;
+
+        Ok((result, event))
+    }
+
+    /// Records that a state root recomputation found drift
+    pub fn record_state_root_mismatch(&self, at: u64) {
+        let mut metrics = self.metrics.lock().unwrap();
+        metrics.state_root_mismatches += 1;
+        metrics.last_state_root_mismatch = Some(at);
+    }
+
+    /// Gets events recorded since startup with a sequence number from `start`, oldest first
+    pub fn events_from(&self, start: u64, limit: usize) -> Vec<AdminEvent> {
+        self.events.lock().unwrap().iter()
+            .filter(|event| event.sequence >= start)
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    /// Gets the monitoring counters
+    pub fn metrics(&self) -> AdminMetrics {
+        self.metrics.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refused_actions_are_not_recorded() {
+        let log = AdminLog::new(Runtime::new());
+        let refused = log.perform(AdminAction::Audit, |_| Err("bad signature".to_string()), || ((), String::new()));
+        assert_eq!(refused.unwrap_err(), "bad signature");
+        assert_eq!(log.next_nonce(), 0);
+
+        let (_, event) = log.perform(
+            AdminAction::DistributeFees,
+            |nonce| { assert_eq!(nonce, 0); Ok("0xadmin".to_string()) },
+            || (5, "distributed 5".to_string()),
+        ).unwrap();
+        assert_eq!((event.sequence, event.caller.as_str()), (0, "0xadmin"));
+        assert_eq!(log.next_nonce(), 1);
+        assert_eq!(log.events_from(0, 10), vec![event]);
+        assert_eq!(log.metrics().actions, 1);
+    }
+
+    #[test]
+    fn test_nonce_survives_a_restart() {
+        let dir = std::env::temp_dir().join(format!("ubi-admin-nonce-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let log = AdminLog::new(Runtime::new().with_storage(&dir).unwrap());
+        log.perform(AdminAction::Audit, |_| Ok("0xadmin".to_string()), || ((), String::new())).unwrap();
+        log.perform(AdminAction::Audit, |_| Ok("0xadmin".to_string()), || ((), String::new())).unwrap();
+        drop(log);
+
+        // A signature over nonce 1 is not accepted again by the restarted node
+        let log = AdminLog::new(Runtime::new().with_storage(&dir).unwrap());
+        assert_eq!(log.next_nonce(), 2);
+        let (_, event) = log.perform(
+            AdminAction::Audit,
+            |nonce| { assert_eq!(nonce, 2); Ok("0xadmin".to_string()) },
+            || ((), String::new()),
+        ).unwrap();
+        assert_eq!(event.sequence, 2);
+        assert_eq!(log.events_from(0, 10), vec![event]);
+        drop(log);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
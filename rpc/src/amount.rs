@@ -0,0 +1,251 @@
+//! Amount Parameter Parsing
+//!
+//! Every RPC method that takes a token amount parses it through `parse_amount`,
+//! so the native RPC, the ubi_ extensions and the faucet accept the same inputs
+//! and reject the same mistakes.
+//!
+//! Accepted forms:
+//! - JSON integers (`100`)
+//! - Integral JSON floats up to 2^53 (`100.0`)
+//! - Decimal strings, optionally in scientific notation (`"100"`, `"1e19"`)
+//! - 0x-prefixed hex strings (`"0x64"`)
+
+use serde_json::Value;
+use std::fmt;
+
+/// Largest integer a JSON float can carry without losing precision
+const MAX_SAFE_FLOAT_INTEGER: f64 = 9_007_199_254_740_992.0; // 2^53
+
+/// Error types for amount parsing
+#[derive(Debug, Clone, PartialEq)]
+pub enum AmountError {
+    /// The amount was an empty string
+    Empty,
+    /// The amount was negative
+    Negative,
+    /// The amount had a fractional part
+    Fractional,
+    /// The amount does not fit in a u64
+    Overflow,
+    /// The amount was a float too large to be represented exactly
+    Imprecise,
+    /// The amount was not a number or numeric string
+    Invalid(String),
+}
+
+impl fmt::Display for AmountError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AmountError::Empty => write!(f, "Amount is empty"),
+            AmountError::Negative => write!(f, "Amount must not be negative"),
+            AmountError::Fractional => write!(f, "Amount must be a whole number of tokens"),
+            AmountError::Overflow => write!(f, "Amount exceeds the maximum of {}", u64::MAX),
+            AmountError::Imprecise => write!(f, "Amount exceeds 2^53 and cannot be sent as a JSON number; pass it as a string"),
+            AmountError::Invalid(value) => write!(f, "Invalid amount: {}", value),
+        }
+    }
+}
+
+impl std::error::Error for AmountError {}
+
+/// Parses an amount parameter
+///
+/// # Arguments
+/// * `value` - The JSON value supplied by the client
+///
+/// # Returns
+/// The amount in UBI tokens, or an error describing why it was rejected
+pub fn parse_amount(value: &Value) -> Result<u64, AmountError> {
+    match value {
+        Value::Number(number) => {
+            if let Some(amount) = number.as_u64() {
+                return Ok(amount);
+            }
+            if number.is_i64() {
+                return Err(AmountError::Negative);
+            }
+            match number.as_f64() {
+                Some(float) => parse_float(float),
+                None => Err(AmountError::Invalid(number.to_string())),
+            }
+        },
+        Value::String(text) => parse_amount_str(text),
+        other => Err(AmountError::Invalid(other.to_string())),
+    }
+}
+
+/// Parses an optional amount parameter, treating a missing or null value as None
+pub fn parse_optional_amount(value: Option<&Value>) -> Result<Option<u64>, AmountError> {
+    match value {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => parse_amount(value).map(Some),
+    }
+}
+
+/// Parses an amount given as a string
+///
+/// # Arguments
+/// * `text` - Decimal, scientific or 0x-prefixed hex representation
+pub fn parse_amount_str(text: &str) -> Result<u64, AmountError> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(AmountError::Empty);
+    }
+    if text.starts_with('-') {
+        return Err(AmountError::Negative);
+    }
+
+    let text = text.strip_prefix('+').unwrap_or(text);
+
+    if let Some(digits) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        return parse_hex(digits, text);
+    }
+
+    parse_decimal(text)
+}
+
+fn parse_float(float: f64) -> Result<u64, AmountError> {
+    if !float.is_finite() {
+        return Err(AmountError::Invalid(float.to_string()));
+    }
+    if float < 0.0 {
+        return Err(AmountError::Negative);
+    }
+    if float.fract() != 0.0 {
+        return Err(AmountError::Fractional);
+    }
+    if float > MAX_SAFE_FLOAT_INTEGER {
+        return Err(AmountError::Imprecise);
+    }
+    Ok(float as u64)
+}
+
+fn parse_hex(digits: &str, original: &str) -> Result<u64, AmountError> {
+    if digits.is_empty() {
+        return Err(AmountError::Empty);
+    }
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(AmountError::Invalid(original.to_string()));
+    }
+
+    let significant = digits.trim_start_matches('0');
+    if significant.len() > 16 {
+        return Err(AmountError::Overflow);
+    }
+    if significant.is_empty() {
+        return Ok(0);
+    }
+
+    u64::from_str_radix(significant, 16).map_err(|_| AmountError::Invalid(original.to_string()))
+}
+
+fn parse_decimal(text: &str) -> Result<u64, AmountError> {
+    // Split off an exponent, if any
+    let (mantissa, exponent) = match text.find(['e', 'E']) {
+        Some(pos) => {
+            let exponent = text[pos + 1..].parse::<i32>()
+                .map_err(|_| AmountError::Invalid(text.to_string()))?;
+            (&text[..pos], exponent)
+        },
+        None => (text, 0),
+    };
+
+    let (integer_part, fraction_part) = match mantissa.find('.') {
+        Some(pos) => (&mantissa[..pos], &mantissa[pos + 1..]),
+        None => (mantissa, ""),
+    };
+
+    if integer_part.is_empty() && fraction_part.is_empty() {
+        return Err(AmountError::Invalid(text.to_string()));
+    }
+    if !integer_part.chars().all(|c| c.is_ascii_digit()) || !fraction_part.chars().all(|c| c.is_ascii_digit()) {
+        return Err(AmountError::Invalid(text.to_string()));
+    }
+
+    // Shift the decimal point by the exponent, working on the digit string so the result is exact
+    let digits = format!("{}{}", integer_part, fraction_part);
+    let point = integer_part.len() as i64 + exponent as i64;
+
+    let (whole, fraction) = if point <= 0 {
+        ("", digits.as_str())
+    } else if point as usize >= digits.len() {
+        (digits.as_str(), "")
+    } else {
+        digits.split_at(point as usize)
+    };
+
+    if fraction.chars().any(|c| c != '0') {
+        return Err(AmountError::Fractional);
+    }
+
+    // Digits beyond the mantissa are implied zeros from a positive exponent
+    let padding = (point.max(0) as usize).saturating_sub(digits.len());
+
+    let mut amount: u64 = 0;
+    for c in whole.chars() {
+        amount = amount.checked_mul(10)
+            .and_then(|value| value.checked_add(c.to_digit(10).unwrap_or(0) as u64))
+            .ok_or(AmountError::Overflow)?;
+    }
+    for _ in 0..padding {
+        if amount == 0 {
+            break;
+        }
+        amount = amount.checked_mul(10).ok_or(AmountError::Overflow)?;
+    }
+
+    Ok(amount)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_integers_and_strings() {
+        assert_eq!(parse_amount(&json!(100)), Ok(100));
+        assert_eq!(parse_amount(&json!("100")), Ok(100));
+        assert_eq!(parse_amount(&json!("0x64")), Ok(100));
+        assert_eq!(parse_amount(&json!(" 42 ")), Ok(42));
+        assert_eq!(parse_amount(&json!(100.0)), Ok(100));
+    }
+
+    #[test]
+    fn test_scientific_notation() {
+        assert_eq!(parse_amount(&json!("1e19")), Ok(10_000_000_000_000_000_000));
+        assert_eq!(parse_amount(&json!("2.5e1")), Ok(25));
+        assert_eq!(parse_amount(&json!("1e20")), Err(AmountError::Overflow));
+        assert_eq!(parse_amount(&json!("2.5e0")), Err(AmountError::Fractional));
+        assert_eq!(parse_amount(&json!("0e400")), Ok(0));
+    }
+
+    #[test]
+    fn test_hex_limits() {
+        assert_eq!(parse_amount(&json!("0xffffffffffffffff")), Ok(u64::MAX));
+        assert_eq!(parse_amount(&json!("0x0000ffffffffffffffff")), Ok(u64::MAX));
+        assert_eq!(parse_amount(&json!("0x10000000000000000")), Err(AmountError::Overflow));
+        assert_eq!(parse_amount(&json!("0x")), Err(AmountError::Empty));
+        assert!(matches!(parse_amount(&json!("0xzz")), Err(AmountError::Invalid(_))));
+    }
+
+    #[test]
+    fn test_rejections() {
+        assert_eq!(parse_amount(&json!("-5")), Err(AmountError::Negative));
+        assert_eq!(parse_amount(&json!(-5)), Err(AmountError::Negative));
+        assert_eq!(parse_amount(&json!(2.5)), Err(AmountError::Fractional));
+        assert_eq!(parse_amount(&json!("2.5")), Err(AmountError::Fractional));
+        assert_eq!(parse_amount(&json!("")), Err(AmountError::Empty));
+        assert_eq!(parse_amount(&json!("18446744073709551616")), Err(AmountError::Overflow));
+        assert_eq!(parse_amount(&json!(1e19)), Err(AmountError::Imprecise));
+        assert!(matches!(parse_amount(&json!(true)), Err(AmountError::Invalid(_))));
+        assert!(matches!(parse_amount(&json!("ten")), Err(AmountError::Invalid(_))));
+    }
+
+    #[test]
+    fn test_optional_amount() {
+        assert_eq!(parse_optional_amount(None), Ok(None));
+        assert_eq!(parse_optional_amount(Some(&Value::Null)), Ok(None));
+        assert_eq!(parse_optional_amount(Some(&json!("7"))), Ok(Some(7)));
+    }
+}
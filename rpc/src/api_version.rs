@@ -0,0 +1,178 @@
+//! API Version and Capabilities
+//!
+//! Clients need to know which response shapes and features a node serves.
+//! `ubi_getApiVersion` reports a semantic API version and what the node
+//! supports. The major version is bumped on every breaking change to a
+//! response shape, such as a renamed field, a list turned into a page or a
+//! field that may now be null. New methods and fields bump the minor version.
+//! Capabilities come from the node's running configuration, not constants:
+//! the methods actually registered, who may run admin actions, and so on.
+//!
+//! A client built against version `MAJOR.MINOR` checks the node's version
+//! once it connects with `ApiVersion::check`. A different major version means
+//! responses may not parse, so it should refuse to go on. An older minor means
+//! some methods are missing, which it can work around by checking
+//! `capabilities.methods`.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Version of the JSON-RPC API this node serves
+pub const API_VERSION: &str = "1.2.0";
+
+/// How list methods split their results
+pub const PAGINATION_STYLE: &str = "cursor";
+
+/// Who may call admin methods
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AdminAuthMode {
+    /// No admin address is configured, so admin methods refuse every call
+    Disabled,
+    /// Admin calls must be signed by a configured admin address
+    Signed,
+}
+
+/// What the node supports
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiCapabilities {
+    /// Method namespaces served, such as "eth" and "ubi"
+    pub method_groups: Vec<String>,
+    /// Every method served over both HTTP and WebSocket, sorted; the
+    /// subscription methods are WebSocket only and not listed
+    pub methods: Vec<String>,
+    /// How list methods are paginated; see the pagination module
+    pub pagination: String,
+    /// Items in a page when a request gives no limit
+    pub default_page_size: usize,
+    /// Most items in a page
+    pub max_page_size: usize,
+    /// Whether state can be read at past blocks rather than only "latest"
+    pub historical_state: bool,
+    /// Subscription types accepted on the WebSocket endpoint
+    pub pubsub: Vec<String>,
+    /// Who may call admin methods
+    pub admin_auth: AdminAuthMode,
+    /// Admin roles with an address configured: "params", "accounts" or "ops"
+    pub admin_roles: Vec<String>,
+}
+
+/// The API version with the node's capabilities
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiVersion {
+    /// Semantic version, MAJOR.MINOR.PATCH
+    pub version: String,
+    /// What the node supports
+    pub capabilities: ApiCapabilities,
+}
+
+/// Whether a client can use a node's API
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Compatibility {
+    /// Every response shape and method the client knows is served
+    Compatible,
+    /// Shapes match, but the node predates some methods the client knows; it
+    /// should check `capabilities.methods` before calling newer ones
+    Degraded {
+        /// The node's version
+        node_version: String,
+    },
+    /// Response shapes differ; the client should not go on
+    Incompatible {
+        /// The node's version
+        node_version: String,
+    },
+}
+
+impl fmt::Display for Compatibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Compatibility::Compatible => write!(f, "compatible"),
+            Compatibility::Degraded { node_version } => write!(f, "node API {} lacks newer methods", node_version),
+            Compatibility::Incompatible { node_version } => write!(f, "node API {} is incompatible", node_version),
+        }
+    }
+}
+
+impl ApiVersion {
+    /// Checks whether a client built against a version can use this API
+    ///
+    /// # Arguments
+    /// * `client_version` - The API version the client was built against
+    ///
+    /// # Returns
+    /// The compatibility, or an error if either version is malformed
+    pub fn check(&self, client_version: &str) -> Result<Compatibility, String> {
+        let (node_major, node_minor) = parse_version(&self.version)?;
+        let (client_major, client_minor) = parse_version(client_version)?;
+        Ok(if node_major != client_major {
+            Compatibility::Incompatible { node_version: self.version.clone() }
+        } else if node_minor < client_minor {
+            Compatibility::Degraded { node_version: self.version.clone() }
+        } else {
+            Compatibility::Compatible
+        })
+    }
+
+    /// Checks whether the node serves a method
+    pub fn supports(&self, method: &str) -> bool {
+        self.capabilities.methods.binary_search_by(|name| name.as_str().cmp(method)).is_ok()
+    }
+}
+
+/// Gets the namespaces of method names, the part before the first underscore, sorted
+pub fn method_groups(methods: &[String]) -> Vec<String> {
+    let mut groups: Vec<String> = methods.iter()
+        .filter_map(|method| method.split_once('_').map(|(group, _)| group.to_string()))
+        .collect();
+    groups.sort();
+    groups.dedup();
+    groups
+}
+
+/// Reads the major and minor numbers of a MAJOR.MINOR.PATCH version
+fn parse_version(version: &str) -> Result<(u64, u64), String> {
+    let mut parts = version.split('.').map(str::parse::<u64>);
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(Ok(major)), Some(Ok(minor)), Some(Ok(_)), None) => Ok((major, minor)),
+        _ => Err(format!("Invalid API version: {} (expected MAJOR.MINOR.PATCH)", version)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn api(version: &str) -> ApiVersion {
+        let methods = vec!["eth_chainId".to_string(), "net_version".to_string(), "ubi_getApiVersion".to_string()];
+        ApiVersion {
+            version: version.to_string(),
+            capabilities: ApiCapabilities {
+                method_groups: method_groups(&methods),
+                methods,
+                pagination: PAGINATION_STYLE.to_string(),
+                default_page_size: 100,
+                max_page_size: 1000,
+                historical_state: false,
+                pubsub: Vec::new(),
+                admin_auth: AdminAuthMode::Disabled,
+                admin_roles: Vec::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_clients_check_the_major_and_minor_version() {
+        let node = api("1.2.3");
+        assert_eq!(node.check("1.0.0"), Ok(Compatibility::Compatible));
+        assert_eq!(node.check("1.2.9"), Ok(Compatibility::Compatible));
+        assert_eq!(node.check("1.3.0"), Ok(Compatibility::Degraded { node_version: "1.2.3".to_string() }));
+        assert_eq!(node.check("2.0.0"), Ok(Compatibility::Incompatible { node_version: "1.2.3".to_string() }));
+        assert!(node.check("1.2").is_err());
+        assert!(node.check("v1.2.3").is_err());
+
+        assert_eq!(node.capabilities.method_groups, vec!["eth", "net", "ubi"]);
+        assert!(node.supports("net_version"));
+        assert!(!node.supports("eth_getProof"));
+    }
+}
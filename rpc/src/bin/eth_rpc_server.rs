@@ -2,8 +2,14 @@
 //!
 //! This binary demonstrates how to start both HTTP and WebSocket
 //! Ethereum-compatible JSON-RPC servers for UBI Chain.
+//!
+//! Usage: `eth_rpc_server [http_addrs] [ws_addrs] [chain_id] [node_address]`,
+//! where each list of addresses is comma-separated. Both servers default to
+//! port 0, so the system picks free ports and the bound addresses are logged.
+//! The node address is funded and serves the faucet; without one the faucet
+//! is disabled.
 
-use ubi_chain_rpc::RpcHandler;
+use ubi_chain_rpc::{logging, RpcHandler};
 use runtime::Runtime;
 use std::env;
 use log::{info, error, LevelFilter};
@@ -13,19 +19,21 @@ use ctrlc;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize logging
-    env_logger::Builder::new()
-        .filter_level(LevelFilter::Info)
-        .init();
+    // Initialize logging, as JSON lines when UBI_LOG_FORMAT=json
+    let log_format = env::var(logging::LOG_FORMAT_ENV_VAR).ok()
+        .and_then(|format| format.parse().ok())
+        .unwrap_or(logging::LogFormat::Text);
+    logging::init_logger(log_format, LevelFilter::Info, None);
 
     // Parse command line arguments
-    let http_addr = env::args().nth(1).unwrap_or_else(|| "127.0.0.1:8545".to_string());
-    let ws_addr = env::args().nth(2).unwrap_or_else(|| "127.0.0.1:8546".to_string());
+    let http_addrs = env::args().nth(1).unwrap_or_else(|| "127.0.0.1:0".to_string());
+    let ws_addrs = env::args().nth(2).unwrap_or_else(|| "127.0.0.1:0".to_string());
     let chain_id = env::args().nth(3).unwrap_or_else(|| "2030".to_string()).parse::<u64>().unwrap_or(2030);
+    let node_address = env::args().nth(4);
 
     info!("Starting Ethereum-compatible JSON-RPC servers");
-    info!("HTTP server address: {}", http_addr);
-    info!("WebSocket server address: {}", ws_addr);
+    info!("HTTP server addresses: {}", http_addrs);
+    info!("WebSocket server addresses: {}", ws_addrs);
     info!("Chain ID: {}", chain_id);
 
     // Initialize the runtime
@@ -34,23 +42,32 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Create the RPC handler
     let mut rpc_handler = RpcHandler::new(runtime);
     
-    // Set a node address for faucet operations
-    let node_address = "0x0000000000000000000000000000000000000001".to_string();
-    rpc_handler.set_node_address(node_address.clone());
+    // Print the seed so deterministic runs can be replayed
+    if let Some(seed) = rpc_handler.random.seed() {
+        info!("Deterministic mode enabled, random seed: {}", seed);
+    }
     
-    // Create the node account if it doesn't exist
-    if rpc_handler.runtime.get_balance(&node_address) == 0 {
-        match rpc_handler.runtime.create_account(&node_address) {
-            Ok(_) => {
-                info!("Created node account: {}", node_address);
-                // Actually fund the account with 1,000,000 UBI tokens
-                match rpc_handler.runtime.credit_balance(&node_address, 1_000_000) {
-                    Ok(balance) => info!("Funded node account with 1,000,000 UBI tokens. New balance: {}", balance),
-                    Err(e) => error!("Failed to fund node account: {:?}", e),
+    // Set the node address for faucet operations, if one was given
+    match node_address {
+        Some(node_address) => {
+            rpc_handler.set_node_address(node_address.clone());
+            
+            // Create the node account if it doesn't exist
+            if rpc_handler.runtime.get_balance(&node_address) == 0 {
+                match rpc_handler.runtime.create_account(&node_address) {
+                    Ok(_) => {
+                        info!("Created node account: {}", node_address);
+                        // Actually fund the account with 1,000,000 UBI tokens
+                        match rpc_handler.runtime.credit_balance(&node_address, 1_000_000) {
+                            Ok(balance) => info!("Funded node account with 1,000,000 UBI tokens. New balance: {}", balance),
+                            Err(e) => error!("Failed to fund node account: {:?}", e),
+                        }
+                    },
+                    Err(e) => error!("Failed to create node account: {}", e),
                 }
-            },
-            Err(e) => error!("Failed to create node account: {:?}", e),
-        }
+            }
+        },
+        None => info!("No node address given, the faucet is disabled"),
     }
     
     // Create a flag for shutdown
@@ -63,29 +80,29 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         r.store(false, Ordering::SeqCst);
     })?;
 
-    // Start HTTP server
-    let http_server = match rpc_handler.start_eth_rpc_server(&http_addr, chain_id) {
-        Ok(server) => {
-            info!("HTTP server started successfully on {}", http_addr);
-            Some(server)
-        },
-        Err(e) => {
-            error!("Failed to start HTTP server: {:?}", e);
-            None
+    // Start an HTTP server on each address
+    let mut http_servers = Vec::new();
+    for http_addr in http_addrs.split(',').map(str::trim).filter(|addr| !addr.is_empty()) {
+        match rpc_handler.start_eth_rpc_server(http_addr, chain_id) {
+            Ok((server, bound_addr)) => {
+                info!("HTTP server started successfully on {}", bound_addr);
+                http_servers.push(server);
+            },
+            Err(e) => error!("Failed to start HTTP server on {}: {:?}", http_addr, e),
         }
-    };
+    }
 
-    // Start WebSocket server
-    let ws_server = match rpc_handler.start_eth_ws_server(&ws_addr, chain_id).await {
-        Ok(server) => {
-            info!("WebSocket server started successfully on {}", ws_addr);
-            Some(server)
-        },
-        Err(e) => {
-            error!("Failed to start WebSocket server: {:?}", e);
-            None
+    // Start a WebSocket server on each address
+    let mut ws_servers = Vec::new();
+    for ws_addr in ws_addrs.split(',').map(str::trim).filter(|addr| !addr.is_empty()) {
+        match rpc_handler.start_eth_ws_server(ws_addr, chain_id).await {
+            Ok((server, bound_addr)) => {
+                info!("WebSocket server started successfully on {}", bound_addr);
+                ws_servers.push(server);
+            },
+            Err(e) => error!("Failed to start WebSocket server on {}: {:?}", ws_addr, e),
         }
-    };
+    }
 
     // Wait for shutdown signal
     while running.load(Ordering::SeqCst) {
@@ -93,14 +110,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     // Clean shutdown
-    if let Some(server) = http_server {
-        info!("Shutting down HTTP server...");
-        drop(server);
+    if !http_servers.is_empty() {
+        info!("Shutting down HTTP servers...");
+        drop(http_servers);
     }
     
-    if let Some(server) = ws_server {
-        info!("Shutting down WebSocket server...");
-        drop(server);
+    if !ws_servers.is_empty() {
+        info!("Shutting down WebSocket servers...");
+        drop(ws_servers);
     }
 
     info!("Servers shut down");
@@ -0,0 +1,393 @@
+//! Tiered Chain Store
+//!
+//! The eth compatibility layer keeps every block and transaction it has seen so
+//! that they can be looked up by hash or number. This module bounds the memory
+//! used for that: the most recent entries stay in memory, older ones are spilled
+//! to an append-only file on disk, and lookups are served transparently from
+//! either tier.
+//!
+//! Entries can be pinned (for example pending transactions) so they are never
+//! spilled; they remain subject to the transaction pool's own limits instead.
+
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use log;
+
+/// Default number of blocks kept in memory
+pub const DEFAULT_MAX_BLOCKS: usize = 10_000;
+
+/// Default number of transactions kept in memory
+pub const DEFAULT_MAX_TRANSACTIONS: usize = 100_000;
+
+/// Default directory for spilled entries
+pub const DEFAULT_SPILL_DIR: &str = "./chain_store";
+
+/// Retention settings for the block and transaction stores
+#[derive(Debug, Clone)]
+pub struct RetentionConfig {
+    /// Number of most recent blocks kept in memory
+    pub max_blocks: usize,
+    /// Number of most recent transactions kept in memory
+    pub max_transactions: usize,
+    /// Directory where older entries are spilled
+    pub spill_dir: String,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        RetentionConfig {
+            max_blocks: DEFAULT_MAX_BLOCKS,
+            max_transactions: DEFAULT_MAX_TRANSACTIONS,
+            spill_dir: DEFAULT_SPILL_DIR.to_string(),
+        }
+    }
+}
+
+/// Append-only file holding spilled entries, indexed by key
+///
+/// The index lives in memory and the file is truncated when opened, so a
+/// spill file is only meaningful to the process that wrote it.
+struct SpillFile {
+    /// Location of the file
+    path: PathBuf,
+    /// Handle used for both appends and lookups
+    file: File,
+    /// Offset and length of the latest record for each key
+    index: HashMap<String, (u64, usize)>,
+    /// Offset where the next record will be written
+    end: u64,
+}
+
+impl SpillFile {
+    fn open(dir: &str, name: &str) -> io::Result<Self> {
+        if !Path::new(dir).exists() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let path = Path::new(dir).join(format!("{}.spill", name));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)?;
+
+        log::info!("Spilling {} to {}", name, path.display());
+
+        Ok(SpillFile {
+            path,
+            file,
+            index: HashMap::new(),
+            end: 0,
+        })
+    }
+
+    fn write<V: Serialize>(&mut self, key: &str, value: &V) -> io::Result<()> {
+        let bytes = serde_json::to_vec(value)?;
+
+        (&self.file).seek(SeekFrom::Start(self.end))?;
+        (&self.file).write_all(&bytes)?;
+
+        self.index.insert(key.to_string(), (self.end, bytes.len()));
+        self.end += bytes.len() as u64;
+        Ok(())
+    }
+
+    fn read<V: DeserializeOwned>(&self, key: &str) -> Option<V> {
+        let (offset, len) = *self.index.get(key)?;
+        let mut bytes = vec![0u8; len];
+
+        let result = (&self.file).seek(SeekFrom::Start(offset))
+            .and_then(|_| (&self.file).read_exact(&mut bytes));
+        if let Err(e) = result {
+            log::error!("Failed to read {} from {}: {}", key, self.path.display(), e);
+            return None;
+        }
+
+        match serde_json::from_slice(&bytes) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                log::error!("Corrupt spill record for {} in {}: {}", key, self.path.display(), e);
+                None
+            }
+        }
+    }
+}
+
+/// Key-value store that keeps the most recent entries in memory and spills the rest to disk
+pub struct TieredStore<V> {
+    /// Name of the store, used for the spill file name
+    name: String,
+    /// Maximum number of unpinned entries kept in memory
+    capacity: usize,
+    /// Directory for the spill file
+    spill_dir: String,
+    /// In-memory tier
+    hot: HashMap<String, V>,
+    /// Keys of the in-memory tier, oldest first
+    order: VecDeque<String>,
+    /// On-disk tier, opened on first spill
+    spill: Option<SpillFile>,
+    /// Returns true for entries that must stay in memory
+    pinned: fn(&V) -> bool,
+}
+
+impl<V: Serialize + DeserializeOwned + Clone> TieredStore<V> {
+    /// Creates a new store
+    ///
+    /// # Arguments
+    /// * `name` - Name of the store, used for the spill file name
+    /// * `capacity` - Maximum number of entries kept in memory
+    /// * `spill_dir` - Directory for entries evicted from memory
+    pub fn new(name: &str, capacity: usize, spill_dir: &str) -> Self {
+        TieredStore {
+            name: name.to_string(),
+            capacity,
+            spill_dir: spill_dir.to_string(),
+            hot: HashMap::new(),
+            order: VecDeque::new(),
+            spill: None,
+            pinned: |_| false,
+        }
+    }
+
+    /// Sets the predicate for entries that must never be spilled
+    pub fn with_pin(mut self, pinned: fn(&V) -> bool) -> Self {
+        self.pinned = pinned;
+        self
+    }
+
+    /// Applies new retention settings, spilling entries if the capacity shrank
+    ///
+    /// The spill directory only takes effect if nothing has been spilled yet.
+    pub fn set_retention(&mut self, capacity: usize, spill_dir: &str) {
+        self.capacity = capacity;
+        if self.spill.is_none() {
+            self.spill_dir = spill_dir.to_string();
+        }
+        self.evict();
+    }
+
+    /// Inserts or replaces an entry
+    pub fn insert(&mut self, key: String, value: V) {
+        if let Some(existing) = self.hot.get_mut(&key) {
+            *existing = value;
+            return;
+        }
+
+        // A newer value supersedes the spilled one
+        if let Some(spill) = self.spill.as_mut() {
+            spill.index.remove(&key);
+        }
+
+        self.hot.insert(key.clone(), value);
+        self.order.push_back(key);
+        self.evict();
+    }
+
+    /// Removes an entry from either tier
+    ///
+    /// # Returns
+    /// The removed value, if there was one
+    pub fn remove(&mut self, key: &str) -> Option<V> {
+        if let Some(value) = self.hot.remove(key) {
+            self.order.retain(|k| k != key);
+            return Some(value);
+        }
+        let spill = self.spill.as_mut()?;
+        let value = spill.read(key);
+        spill.index.remove(key);
+        value
+    }
+
+    /// Looks up an entry in either tier
+    pub fn get(&self, key: &str) -> Option<V> {
+        if let Some(value) = self.hot.get(key) {
+            return Some(value.clone());
+        }
+        self.spill.as_ref().and_then(|spill| spill.read(key))
+    }
+
+    /// Checks whether an entry exists in either tier
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.hot.contains_key(key)
+            || self.spill.as_ref().is_some_and(|spill| spill.index.contains_key(key))
+    }
+
+    /// Gets the total number of entries
+    pub fn len(&self) -> usize {
+        self.hot.len() + self.spilled_len()
+    }
+
+    /// Checks whether the store is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Gets the number of entries held in memory
+    pub fn hot_len(&self) -> usize {
+        self.hot.len()
+    }
+
+    /// Gets the number of entries spilled to disk
+    pub fn spilled_len(&self) -> usize {
+        self.spill.as_ref().map_or(0, |spill| spill.index.len())
+    }
+
+    /// Moves the oldest unpinned entries to disk until the memory tier is within capacity
+    fn evict(&mut self) {
+        // Pinned entries are rotated to the back, so stop after a full pass over them
+        let mut skipped = 0;
+
+        while self.hot.len() > self.capacity && skipped < self.order.len() {
+            let key = match self.order.pop_front() {
+                Some(key) => key,
+                None => break,
+            };
+            let value = match self.hot.remove(&key) {
+                Some(value) => value,
+                None => continue,
+            };
+
+            if (self.pinned)(&value) {
+                self.hot.insert(key.clone(), value);
+                self.order.push_back(key);
+                skipped += 1;
+                continue;
+            }
+
+            if let Err(e) = self.spill_entry(&key, &value) {
+                // Keep the entry in memory rather than lose it
+                log::error!("Failed to spill {} entry {}: {}", self.name, key, e);
+                self.hot.insert(key.clone(), value);
+                self.order.push_front(key);
+                break;
+            }
+        }
+    }
+
+    fn spill_entry(&mut self, key: &str, value: &V) -> io::Result<()> {
+        if self.spill.is_none() {
+            self.spill = Some(SpillFile::open(&self.spill_dir, &self.name)?);
+        }
+
+        match self.spill.as_mut() {
+            Some(spill) => spill.write(key, value),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eth_compat::EthTransaction;
+
+    fn test_dir(name: &str) -> String {
+        format!("./test_chain_store_{}_{}", name, std::process::id())
+    }
+
+    fn transaction(index: u64, mined: bool) -> EthTransaction {
+        let hash = format!("0x{:064x}", index);
+        EthTransaction {
+            hash,
+            nonce: format!("0x{:x}", index),
+            block_hash: mined.then(|| format!("0x{:064x}", index + 1)),
+            block_number: mined.then(|| format!("0x{:x}", index)),
+            transaction_index: mined.then(|| "0x0".to_string()),
+            from: "0x1111111111111111111111111111111111111111".to_string(),
+            to: Some("0x2222222222222222222222222222222222222222".to_string()),
+            value: "0xde0b6b3a7640000".to_string(),
+            gas_price: "0x0".to_string(),
+            gas: "0x5208".to_string(),
+            input: "0x".to_string(),
+            v: "0x0".to_string(),
+            r: "0x0".to_string(),
+            s: "0x0".to_string(),
+        }
+    }
+
+    fn transaction_store(capacity: usize, dir: &str) -> TieredStore<EthTransaction> {
+        TieredStore::new("transactions", capacity, dir).with_pin(|tx| tx.block_hash.is_none())
+    }
+
+    #[test]
+    fn test_spilled_entries_remain_queryable() {
+        let dir = test_dir("spill");
+        let mut store = transaction_store(1_000, &dir);
+
+        for i in 0..100_000 {
+            let tx = transaction(i, true);
+            store.insert(tx.hash.clone(), tx);
+            // Memory stays bounded by the capacity throughout
+            assert!(store.hot_len() <= 1_000);
+        }
+
+        assert_eq!(store.len(), 100_000);
+        assert_eq!(store.hot_len(), 1_000);
+        assert_eq!(store.spilled_len(), 99_000);
+
+        for i in [0, 1, 50_000, 98_999, 99_000, 99_999] {
+            let hash = format!("0x{:064x}", i);
+            let tx = store.get(&hash).expect("transaction should be queryable");
+            assert_eq!(tx.hash, hash);
+            assert_eq!(tx.nonce, format!("0x{:x}", i));
+        }
+        assert!(store.get(&format!("0x{:064x}", 100_000)).is_none());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_pending_entries_are_never_spilled() {
+        let dir = test_dir("pinned");
+        let mut store = transaction_store(2, &dir);
+
+        let pending = transaction(0, false);
+        store.insert(pending.hash.clone(), pending.clone());
+        for i in 1..10 {
+            let tx = transaction(i, true);
+            store.insert(tx.hash.clone(), tx);
+        }
+
+        assert_eq!(store.hot_len(), 2);
+        assert!(store.hot.contains_key(&pending.hash));
+
+        // Once mined, the transaction becomes eligible for eviction
+        let mut mined = pending.clone();
+        mined.block_hash = Some("0xabc".to_string());
+        store.insert(mined.hash.clone(), mined);
+        for i in 10..12 {
+            let tx = transaction(i, true);
+            store.insert(tx.hash.clone(), tx);
+        }
+
+        assert!(!store.hot.contains_key(&pending.hash));
+        assert_eq!(store.get(&pending.hash).unwrap().block_hash, Some("0xabc".to_string()));
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_reinserting_a_spilled_entry_replaces_it() {
+        let dir = test_dir("replace");
+        let mut store = transaction_store(1, &dir);
+
+        let first = transaction(0, true);
+        store.insert(first.hash.clone(), first.clone());
+        store.insert("0x01".to_string(), transaction(1, true));
+        assert_eq!(store.spilled_len(), 1);
+
+        let mut updated = first.clone();
+        updated.value = "0x1".to_string();
+        store.insert(first.hash.clone(), updated);
+
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.get(&first.hash).unwrap().value, "0x1");
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
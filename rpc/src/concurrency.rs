@@ -0,0 +1,417 @@
+//! RPC Concurrency Limits
+//!
+//! Every RPC method reads runtime state under the same locks the block producer
+//! needs, so a burst of requests can starve block production. This module caps
+//! the number of requests handled at once:
+//! - A global cap on in-flight requests across all methods
+//! - Per-method caps for expensive methods
+//!
+//! A request that finds its slots taken waits up to the queue timeout, then is
+//! rejected with a "server busy" error carrying a retry-after hint. The limiter
+//! is shared by the HTTP, WebSocket and native RPC servers.
+
+use jsonrpc_core::futures::future::Either;
+use jsonrpc_core::middleware::Middleware;
+use jsonrpc_core::{BoxFuture, Call, ErrorCode, Metadata, Output, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+
+/// Default cap on requests handled at once across all methods
+pub const DEFAULT_MAX_IN_FLIGHT: usize = 64;
+
+/// Default cap for methods that walk all accounts or checkpoints
+pub const DEFAULT_EXPENSIVE_METHOD_LIMIT: usize = 2;
+
+/// Default time a request may wait for a slot, in milliseconds
+pub const DEFAULT_QUEUE_TIMEOUT_MS: u64 = 1000;
+
+/// JSON-RPC error code for rejected requests ("limit exceeded" in EIP-1474)
+pub const SERVER_BUSY_ERROR_CODE: i64 = -32005;
+
+/// Smallest retry-after hint given to clients, in milliseconds
+const MIN_RETRY_AFTER_MS: u64 = 250;
+
+/// Methods limited to `DEFAULT_EXPENSIVE_METHOD_LIMIT` concurrent calls by default
+const EXPENSIVE_METHODS: &[&str] = &["ubi_listAccounts", "ubi_diffCheckpoints"];
+
+/// Concurrency limits for the RPC servers
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConcurrencyConfig {
+    /// Maximum number of requests handled at once across all methods
+    pub max_in_flight: usize,
+    /// Maximum number of concurrent calls per method, for methods that have one
+    pub method_limits: HashMap<String, usize>,
+    /// How long a request waits for a slot before it is rejected; zero rejects at once
+    pub queue_timeout: Duration,
+}
+
+impl Default for ConcurrencyConfig {
+    fn default() -> Self {
+        ConcurrencyConfig {
+            max_in_flight: DEFAULT_MAX_IN_FLIGHT,
+            method_limits: EXPENSIVE_METHODS.iter()
+                .map(|method| (method.to_string(), DEFAULT_EXPENSIVE_METHOD_LIMIT))
+                .collect(),
+            queue_timeout: Duration::from_millis(DEFAULT_QUEUE_TIMEOUT_MS),
+        }
+    }
+}
+
+/// Parses a per-method limit given as `method=limit`
+///
+/// # Arguments
+/// * `text` - The limit, e.g. `ubi_listAccounts=2`
+///
+/// # Returns
+/// The method name and its limit, or an error message
+pub fn parse_method_limit(text: &str) -> Result<(String, usize), String> {
+    let (method, limit) = text.split_once('=')
+        .ok_or_else(|| format!("Invalid method limit {} (expected method=limit)", text))?;
+
+    let method = method.trim();
+    if method.is_empty() {
+        return Err(format!("Invalid method limit {}: missing method name", text));
+    }
+
+    match limit.trim().parse::<usize>() {
+        Ok(limit) if limit > 0 => Ok((method.to_string(), limit)),
+        _ => Err(format!("Invalid method limit {}: limit must be a positive integer", text)),
+    }
+}
+
+/// A request rejected because no slot freed up in time
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerBusy {
+    /// The rejected method
+    pub method: String,
+    /// Suggested delay before retrying, in milliseconds
+    pub retry_after_ms: u64,
+}
+
+impl fmt::Display for ServerBusy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Server busy: too many concurrent {} requests, retry after {}ms", self.method, self.retry_after_ms)
+    }
+}
+
+impl ServerBusy {
+    /// Converts the rejection into a JSON-RPC error with the retry-after hint in `data`
+    pub fn to_rpc_error(&self) -> jsonrpc_core::Error {
+        jsonrpc_core::Error {
+            code: ErrorCode::ServerError(SERVER_BUSY_ERROR_CODE),
+            message: "server busy".to_string(),
+            data: Some(json!({
+                "method": self.method,
+                "retry_after_ms": self.retry_after_ms,
+            })),
+        }
+    }
+}
+
+/// In-flight counts of one limited method
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MethodMetrics {
+    /// Calls being handled
+    pub in_flight: usize,
+    /// Maximum concurrent calls
+    pub limit: usize,
+}
+
+/// Snapshot of the limiter's state
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConcurrencyMetrics {
+    /// Requests being handled across all methods
+    pub in_flight: usize,
+    /// Maximum requests handled at once
+    pub max_in_flight: usize,
+    /// Requests waiting for a slot
+    pub queued: usize,
+    /// Requests rejected since startup
+    pub rejected: u64,
+    /// Counts of methods with their own limit
+    pub methods: BTreeMap<String, MethodMetrics>,
+}
+
+/// Slots held by a request while it is handled; released on drop
+pub struct ConcurrencyPermit {
+    _method: Option<OwnedSemaphorePermit>,
+    _global: OwnedSemaphorePermit,
+}
+
+/// Counts a call as queued for as long as it lives
+struct QueuedGuard<'a>(&'a AtomicUsize);
+
+impl<'a> QueuedGuard<'a> {
+    fn new(queued: &'a AtomicUsize) -> Self {
+        queued.fetch_add(1, Ordering::SeqCst);
+        QueuedGuard(queued)
+    }
+}
+
+impl Drop for QueuedGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Semaphore and limit of one method
+struct MethodSlots {
+    semaphore: Arc<Semaphore>,
+    limit: usize,
+}
+
+/// Shared state of a limiter
+struct LimiterState {
+    global: Arc<Semaphore>,
+    max_in_flight: usize,
+    methods: HashMap<String, MethodSlots>,
+    queue_timeout: Duration,
+    queued: AtomicUsize,
+    rejected: AtomicU64,
+}
+
+/// Limits the number of RPC requests handled at once
+///
+/// Cloning is cheap and clones share the same slots.
+#[derive(Clone)]
+pub struct ConcurrencyLimiter {
+    state: Arc<LimiterState>,
+}
+
+impl Default for ConcurrencyLimiter {
+    fn default() -> Self {
+        ConcurrencyLimiter::new(ConcurrencyConfig::default())
+    }
+}
+
+impl ConcurrencyLimiter {
+    /// Creates a limiter enforcing the given limits
+    pub fn new(config: ConcurrencyConfig) -> Self {
+        let max_in_flight = config.max_in_flight.max(1);
+        let methods = config.method_limits.into_iter()
+            .map(|(method, limit)| {
+                let limit = limit.max(1);
+                (method, MethodSlots { semaphore: Arc::new(Semaphore::new(limit)), limit })
+            })
+            .collect();
+
+        ConcurrencyLimiter {
+            state: Arc::new(LimiterState {
+                global: Arc::new(Semaphore::new(max_in_flight)),
+                max_in_flight,
+                methods,
+                queue_timeout: config.queue_timeout,
+                queued: AtomicUsize::new(0),
+                rejected: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Waits for the slots needed to handle a call
+    ///
+    /// The method slot is taken first, so calls queued behind a busy expensive
+    /// method do not hold global slots that cheap methods could use.
+    ///
+    /// # Arguments
+    /// * `method` - Name of the called method
+    ///
+    /// # Returns
+    /// A permit to hold while handling the call, or the rejection if no slot
+    /// freed up within the queue timeout
+    pub async fn acquire(&self, method: &str) -> Result<ConcurrencyPermit, ServerBusy> {
+        let deadline = Instant::now() + self.state.queue_timeout;
+
+        let method_permit = match self.state.methods.get(method) {
+            Some(slots) => Some(self.wait_for(&slots.semaphore, deadline).await.ok_or_else(|| self.reject(method))?),
+            None => None,
+        };
+        let global_permit = self.wait_for(&self.state.global, deadline).await.ok_or_else(|| self.reject(method))?;
+
+        Ok(ConcurrencyPermit {
+            _method: method_permit,
+            _global: global_permit,
+        })
+    }
+
+    /// Takes a slot from a semaphore, waiting until the deadline if none is free
+    async fn wait_for(&self, semaphore: &Arc<Semaphore>, deadline: Instant) -> Option<OwnedSemaphorePermit> {
+        if let Ok(permit) = semaphore.clone().try_acquire_owned() {
+            return Some(permit);
+        }
+        if self.state.queue_timeout.is_zero() {
+            return None;
+        }
+
+        // Counted as queued until the wait ends, even if the call is dropped while waiting
+        let _queued = QueuedGuard::new(&self.state.queued);
+        let permit = tokio::time::timeout_at(deadline, semaphore.clone().acquire_owned()).await;
+
+        permit.ok().and_then(|permit| permit.ok())
+    }
+
+    /// Records a rejection
+    fn reject(&self, method: &str) -> ServerBusy {
+        self.state.rejected.fetch_add(1, Ordering::SeqCst);
+        ServerBusy {
+            method: method.to_string(),
+            retry_after_ms: (self.state.queue_timeout.as_millis() as u64).max(MIN_RETRY_AFTER_MS),
+        }
+    }
+
+    /// Gets the current in-flight, queued and rejected counts
+    pub fn metrics(&self) -> ConcurrencyMetrics {
+        ConcurrencyMetrics {
+            in_flight: self.state.max_in_flight - self.state.global.available_permits(),
+            max_in_flight: self.state.max_in_flight,
+            queued: self.state.queued.load(Ordering::SeqCst),
+            rejected: self.state.rejected.load(Ordering::SeqCst),
+            methods: self.state.methods.iter()
+                .map(|(method, slots)| (method.clone(), MethodMetrics {
+                    in_flight: slots.limit - slots.semaphore.available_permits(),
+                    limit: slots.limit,
+                }))
+                .collect(),
+        }
+    }
+}
+
+/// JSON-RPC middleware holding a concurrency slot for the duration of every call
+#[derive(Clone)]
+pub struct ConcurrencyMiddleware {
+    /// The shared limiter
+    limiter: ConcurrencyLimiter,
+}
+
+impl ConcurrencyMiddleware {
+    /// Creates a middleware drawing slots from the given limiter
+    pub fn new(limiter: ConcurrencyLimiter) -> Self {
+        ConcurrencyMiddleware { limiter }
+    }
+}
+
+impl<M: Metadata> Middleware<M> for ConcurrencyMiddleware {
+    type Future = BoxFuture<Option<Response>>;
+    type CallFuture = BoxFuture<Option<Output>>;
+
+    fn on_call<F, X>(&self, call: Call, meta: M, next: F) -> Either<Self::CallFuture, X>
+    where
+        F: Fn(Call, M) -> X + Send + Sync,
+        X: Future<Output = Option<Output>> + Send + 'static,
+    {
+        let (method, id, jsonrpc) = match &call {
+            Call::MethodCall(method_call) => (method_call.method.clone(), Some(method_call.id.clone()), method_call.jsonrpc),
+            Call::Notification(notification) => (notification.method.clone(), None, notification.jsonrpc),
+            Call::Invalid { .. } => return Either::Right(next(call, meta)),
+        };
+
+        // Methods do their work inside the returned future, so it only runs once a slot is held
+        let future = next(call, meta);
+        let limiter = self.limiter.clone();
+
+        Either::Left(Box::pin(async move {
+            let _permit = match limiter.acquire(&method).await {
+                Ok(permit) => permit,
+                Err(busy) => {
+                    log::warn!(method = method.as_str(); "{}", busy);
+                    return id.map(|id| Output::from(Err(busy.to_rpc_error()), id, jsonrpc));
+                }
+            };
+            future.await
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonrpc_core::MetaIoHandler;
+    use serde_json::Value;
+
+    fn config(max_in_flight: usize, method_limits: &[(&str, usize)], queue_timeout_ms: u64) -> ConcurrencyConfig {
+        ConcurrencyConfig {
+            max_in_flight,
+            method_limits: method_limits.iter().map(|(method, limit)| (method.to_string(), *limit)).collect(),
+            queue_timeout: Duration::from_millis(queue_timeout_ms),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_method_and_global_limits() {
+        let limiter = ConcurrencyLimiter::new(config(3, &[("ubi_listAccounts", 1)], 0));
+
+        let expensive = limiter.acquire("ubi_listAccounts").await.unwrap();
+        let busy = limiter.acquire("ubi_listAccounts").await.err().unwrap();
+        assert_eq!(busy.retry_after_ms, MIN_RETRY_AFTER_MS);
+
+        // Other methods still get the remaining global slots
+        let a = limiter.acquire("eth_getBalance").await.unwrap();
+        let _b = limiter.acquire("eth_getBalance").await.unwrap();
+        assert!(limiter.acquire("eth_getBalance").await.is_err());
+
+        let metrics = limiter.metrics();
+        assert_eq!(metrics.in_flight, 3);
+        assert_eq!(metrics.rejected, 2);
+        assert_eq!(metrics.methods["ubi_listAccounts"], MethodMetrics { in_flight: 1, limit: 1 });
+
+        drop(a);
+        drop(expensive);
+        assert!(limiter.acquire("ubi_listAccounts").await.is_ok());
+        assert_eq!(limiter.metrics().in_flight, 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_queued_requests_wait_until_the_timeout() {
+        let limiter = ConcurrencyLimiter::new(config(1, &[], 100));
+        let held = limiter.acquire("eth_getBalance").await.unwrap();
+
+        // A slot freed before the timeout is handed to the waiting request
+        let waiting = tokio::spawn({
+            let limiter = limiter.clone();
+            async move { limiter.acquire("eth_getBalance").await.is_ok() }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(limiter.metrics().queued, 1);
+        drop(held);
+        assert!(waiting.await.unwrap());
+
+        let _held = limiter.acquire("eth_getBalance").await.unwrap();
+        let busy = limiter.acquire("eth_getBalance").await.err().unwrap();
+        assert_eq!(busy.retry_after_ms, MIN_RETRY_AFTER_MS);
+        assert_eq!(limiter.metrics().queued, 0);
+    }
+
+    #[tokio::test]
+    async fn test_middleware_rejects_with_server_busy() {
+        let limiter = ConcurrencyLimiter::new(config(1, &[], 0));
+        let mut io = MetaIoHandler::with_middleware(ConcurrencyMiddleware::new(limiter.clone()));
+        io.add_method("ping", |_params| async { Ok(Value::from("pong")) });
+
+        let response = io.handle_request(r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#, ()).await.unwrap();
+        let response: Value = serde_json::from_str(&response).unwrap();
+        assert_eq!(response["result"], "pong");
+
+        let _held = limiter.acquire("other").await.unwrap();
+        let response = io.handle_request(r#"{"jsonrpc":"2.0","id":2,"method":"ping"}"#, ()).await.unwrap();
+        let response: Value = serde_json::from_str(&response).unwrap();
+        assert_eq!(response["error"]["code"], SERVER_BUSY_ERROR_CODE);
+        assert_eq!(response["error"]["message"], "server busy");
+        assert_eq!(response["error"]["data"]["retry_after_ms"], MIN_RETRY_AFTER_MS);
+        assert_eq!(response["id"], 2);
+    }
+
+    #[test]
+    fn test_parse_method_limit() {
+        assert_eq!(parse_method_limit("ubi_listAccounts=2"), Ok(("ubi_listAccounts".to_string(), 2)));
+        assert!(parse_method_limit("ubi_listAccounts").is_err());
+        assert!(parse_method_limit("=2").is_err());
+        assert!(parse_method_limit("eth_call=0").is_err());
+    }
+}
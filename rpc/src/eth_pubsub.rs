@@ -2,18 +2,47 @@
 //!
 //! This module implements the Ethereum JSON-RPC PubSub API for WebSocket connections,
 //! allowing clients to subscribe to events like new blocks and logs.
+//!
+//! Subscription ids are 0x-prefixed 16-byte hex strings, and notifications are
+//! pushed to the subscribing connection as standard `eth_subscription` messages:
+//!
+//! ```json
+//! {"jsonrpc": "2.0", "method": "eth_subscription", "params": {"subscription": "0x…", "result": …}}
+//! ```
+//!
+//! UBI Chain events are subscribed to with `ubi_subscribe` and delivered the
+//! same way as `ubi_subscription` messages. `nodeHealth` reports when the node
+//! stalls and recovers.
+//!
+//! A connection that does not keep up gets a bounded backlog per subscription
+//! rather than unbounded buffering. When the backlog is full, the
+//! `SlowConsumerPolicy` either drops the oldest queued notification and marks
+//! the subscription lagged, or disconnects the client. Other subscribers are
+//! never held up by a slow one.
 
-use crate::RpcHandler;
+use crate::{RandomSource, RpcHandler};
 use crate::eth_compat::{EthBlock, EthTransaction};
+use runtime::liveness::NodeHealthEvent;
+use futures::channel::mpsc::UnboundedSender;
 use jsonrpc_core::{Error, Result, Value};
-use jsonrpc_pubsub::SubscriptionId;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fmt;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use parking_lot::RwLock;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use log;
-use rand::RngCore;
 use hex;
-use std::sync::Mutex;
+
+/// Method name of subscription notifications
+pub const SUBSCRIPTION_NOTIFICATION_METHOD: &str = "eth_subscription";
+
+/// Method name of UBI Chain subscription notifications
+pub const UBI_SUBSCRIPTION_NOTIFICATION_METHOD: &str = "ubi_subscription";
+
+/// Default number of notifications queued for a subscription whose connection is not keeping up
+pub const DEFAULT_NOTIFICATION_QUEUE: usize = 256;
 
 /// Subscription types supported by the Ethereum PubSub API
 #[derive(Debug, Clone, PartialEq, Copy)]
@@ -24,6 +53,42 @@ pub enum SubscriptionType {
     NewPendingTransactions,
     /// Log events matching a filter
     Logs,
+    /// Node stalls and recoveries, through ubi_subscribe
+    NodeHealth,
+}
+
+impl SubscriptionType {
+    /// Every subscription type, in the order they are advertised
+    pub const ALL: [SubscriptionType; 4] = [
+        SubscriptionType::NewHeads,
+        SubscriptionType::NewPendingTransactions,
+        SubscriptionType::Logs,
+        SubscriptionType::NodeHealth,
+    ];
+
+    /// Gets the name the subscription is requested with
+    pub fn name(&self) -> &'static str {
+        match self {
+            SubscriptionType::NewHeads => "newHeads",
+            SubscriptionType::NewPendingTransactions => "newPendingTransactions",
+            SubscriptionType::Logs => "logs",
+            SubscriptionType::NodeHealth => "nodeHealth",
+        }
+    }
+
+    /// Checks whether the subscription is a UBI Chain one, made with ubi_subscribe
+    pub fn is_ubi(&self) -> bool {
+        matches!(self, SubscriptionType::NodeHealth)
+    }
+
+    /// Gets the method name its notifications are delivered with
+    pub fn notification_method(&self) -> &'static str {
+        if self.is_ubi() {
+            UBI_SUBSCRIPTION_NOTIFICATION_METHOD
+        } else {
+            SUBSCRIPTION_NOTIFICATION_METHOD
+        }
+    }
 }
 
 impl std::str::FromStr for SubscriptionType {
@@ -34,88 +99,425 @@ impl std::str::FromStr for SubscriptionType {
             "newHeads" => Ok(SubscriptionType::NewHeads),
             "newPendingTransactions" => Ok(SubscriptionType::NewPendingTransactions),
             "logs" => Ok(SubscriptionType::Logs),
+            "nodeHealth" => Ok(SubscriptionType::NodeHealth),
             _ => Err(Error::invalid_params(format!("Invalid subscription type: {}", s))),
         }
     }
 }
 
+/// What happens to a subscription whose backlog is full
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SlowConsumerPolicy {
+    /// Drop the oldest queued notification and mark the subscription lagged
+    #[default]
+    DropOldest,
+    /// Close the client's connection and drop its subscriptions
+    Disconnect,
+}
+
+impl std::str::FromStr for SlowConsumerPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "drop-oldest" | "drop_oldest" => Ok(SlowConsumerPolicy::DropOldest),
+            "disconnect" => Ok(SlowConsumerPolicy::Disconnect),
+            _ => Err(format!("Invalid slow consumer policy: {} (expected drop-oldest or disconnect)", s)),
+        }
+    }
+}
+
+impl fmt::Display for SlowConsumerPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SlowConsumerPolicy::DropOldest => write!(f, "drop-oldest"),
+            SlowConsumerPolicy::Disconnect => write!(f, "disconnect"),
+        }
+    }
+}
+
+/// Outcome of handing a notification to a connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    /// The connection took the message
+    Sent,
+    /// The connection cannot take more messages right now
+    Full,
+    /// The connection is gone
+    Closed,
+}
+
+/// Where a connection's notifications are written
+pub trait NotificationSink: Send + Sync {
+    /// Hands a message to the connection without waiting
+    fn try_deliver(&self, message: String) -> Delivery;
+
+    /// Closes the connection, for clients disconnected as slow consumers
+    fn close(&self) {}
+}
+
+impl NotificationSink for UnboundedSender<String> {
+    fn try_deliver(&self, message: String) -> Delivery {
+        match self.unbounded_send(message) {
+            Ok(()) => Delivery::Sent,
+            Err(_) => Delivery::Closed,
+        }
+    }
+
+    fn close(&self) {
+        self.close_channel();
+    }
+}
+
+/// Per-connection metadata for the WebSocket server
+///
+/// Carries the channel used to push notifications back to the connection
+/// that created a subscription.
+#[derive(Clone, Default)]
+pub struct WsSession {
+    /// Channel to the connection, or None for transports that cannot push
+    sender: Option<Arc<dyn NotificationSink>>,
+}
+
+impl WsSession {
+    /// Creates session metadata for a connection
+    ///
+    /// # Arguments
+    /// * `sender` - Channel that writes messages to the connection
+    pub fn new(sender: UnboundedSender<String>) -> Self {
+        WsSession::with_sink(Arc::new(sender))
+    }
+
+    /// Creates session metadata for a connection written through any sink
+    pub fn with_sink(sink: Arc<dyn NotificationSink>) -> Self {
+        WsSession {
+            sender: Some(sink),
+        }
+    }
+}
+
+impl jsonrpc_core::Metadata for WsSession {}
+
+/// A live subscription and the connection it belongs to
+struct Subscriber {
+    /// Kind of events the subscriber wants
+    subscription_type: SubscriptionType,
+    /// The subscribing connection
+    sink: Arc<dyn NotificationSink>,
+    /// Notifications the connection has not taken yet, oldest first
+    backlog: VecDeque<String>,
+    /// Notifications dropped because the backlog was full
+    dropped: u64,
+}
+
+/// What happened to a subscription when it was handed a notification
+enum SubscriberState {
+    /// The subscription is still live
+    Live,
+    /// The connection closed
+    Closed,
+    /// The backlog overflowed under the disconnect policy
+    TooSlow,
+}
+
+impl Subscriber {
+    /// Delivers a notification after anything already queued, queueing it if the connection is full
+    ///
+    /// # Returns
+    /// The subscription's state and the number of notifications handed to the connection
+    fn deliver(&mut self, message: String, capacity: usize, policy: SlowConsumerPolicy) -> (SubscriberState, u64) {
+        let mut sent = 0;
+
+        // Flush the backlog first, so notifications keep their order
+        while let Some(queued) = self.backlog.front() {
+            match self.sink.try_deliver(queued.clone()) {
+                Delivery::Sent => {
+                    self.backlog.pop_front();
+                    sent += 1;
+                },
+                Delivery::Full => break,
+                Delivery::Closed => return (SubscriberState::Closed, sent),
+            }
+        }
+
+        if self.backlog.is_empty() {
+            match self.sink.try_deliver(message.clone()) {
+                Delivery::Sent => return (SubscriberState::Live, sent + 1),
+                Delivery::Full => {},
+                Delivery::Closed => return (SubscriberState::Closed, sent),
+            }
+        }
+
+        if self.backlog.len() >= capacity {
+            match policy {
+                SlowConsumerPolicy::Disconnect => return (SubscriberState::TooSlow, sent),
+                SlowConsumerPolicy::DropOldest => {
+                    self.backlog.pop_front();
+                    self.dropped += 1;
+                },
+            }
+        }
+        if capacity > 0 {
+            self.backlog.push_back(message);
+        } else {
+            self.dropped += 1;
+        }
+        (SubscriberState::Live, sent)
+    }
+}
+
+/// Subscription and notification counters for monitoring
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubscriptionMetrics {
+    /// Live subscriptions by type name
+    pub subscriptions: BTreeMap<String, usize>,
+    /// Notifications handed to connections since startup
+    pub notifications_sent: u64,
+    /// Notifications dropped because a subscription's backlog was full
+    pub notifications_dropped: u64,
+    /// Clients disconnected for not keeping up
+    pub slow_consumers_disconnected: u64,
+    /// Subscriptions with queued notifications or drops
+    pub lagging_subscriptions: usize,
+}
+
+/// Delivery state of one subscription that is behind
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubscriptionLag {
+    /// The subscription id
+    pub id: String,
+    /// Type name of the subscription
+    pub subscription_type: String,
+    /// Notifications waiting for the connection
+    pub queued: usize,
+    /// Notifications dropped so far; non-zero means the subscriber missed events
+    pub dropped: u64,
+}
+
+/// Subscription statistics reported by ubi_getSubscriptionStats
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubscriptionStats {
+    /// Counters, also reported by ubi_getRpcMetrics
+    #[serde(flatten)]
+    pub metrics: SubscriptionMetrics,
+    /// Notifications queued per subscription before the policy applies
+    pub queue_capacity: usize,
+    /// What happens when a backlog is full
+    pub slow_consumer_policy: SlowConsumerPolicy,
+    /// Subscriptions that are behind, most queued first
+    pub lagging: Vec<SubscriptionLag>,
+}
+
 /// Subscription manager for Ethereum PubSub
 pub struct SubscriptionManager {
-    /// Map of subscription IDs to subscription types
-    subscriptions: RwLock<HashMap<SubscriptionId, SubscriptionType>>,
-    /// Reference to the UBI Chain RPC handler
-    #[allow(dead_code)]
-    rpc_handler: RpcHandler,
+    /// Map of subscription IDs to subscribers
+    subscriptions: RwLock<HashMap<String, Subscriber>>,
+    /// Notifications queued per subscription before the policy applies
+    queue_capacity: usize,
+    /// What happens when a backlog is full
+    policy: SlowConsumerPolicy,
+    /// Notifications handed to connections
+    notifications_sent: AtomicU64,
+    /// Notifications dropped from full backlogs, including those of removed subscriptions
+    notifications_dropped: AtomicU64,
+    /// Clients disconnected for not keeping up
+    slow_consumers_disconnected: AtomicU64,
+}
+
+impl Default for SubscriptionManager {
+    fn default() -> Self {
+        SubscriptionManager::with_policy(DEFAULT_NOTIFICATION_QUEUE, SlowConsumerPolicy::default())
+    }
 }
 
 impl SubscriptionManager {
     /// Creates a new subscription manager
-    pub fn new(rpc_handler: RpcHandler) -> Self {
+    pub fn new() -> Self {
+        SubscriptionManager::default()
+    }
+
+    /// Creates a subscription manager with the given backlog bound and slow consumer policy
+    ///
+    /// # Arguments
+    /// * `queue_capacity` - Notifications queued per subscription before the policy applies
+    /// * `policy` - What happens when a backlog is full
+    pub fn with_policy(queue_capacity: usize, policy: SlowConsumerPolicy) -> Self {
         SubscriptionManager {
             subscriptions: RwLock::new(HashMap::new()),
-            rpc_handler,
+            queue_capacity,
+            policy,
+            notifications_sent: AtomicU64::new(0),
+            notifications_dropped: AtomicU64::new(0),
+            slow_consumers_disconnected: AtomicU64::new(0),
         }
     }
 
     /// Adds a new subscription
-    pub fn add_subscription(&self, id: SubscriptionId, subscription_type: SubscriptionType) {
-        self.subscriptions.write().insert(id.clone(), subscription_type);
-        log::info!("Added new subscription: {:?} for type {:?}", id, subscription_type);
+    pub fn add_subscription(&self, id: String, subscription_type: SubscriptionType, sink: Arc<dyn NotificationSink>) {
+        log::info!("Added new subscription: {} for type {:?}", id, subscription_type);
+        self.subscriptions.write().insert(id, Subscriber {
+            subscription_type,
+            sink,
+            backlog: VecDeque::new(),
+            dropped: 0,
+        });
     }
 
     /// Removes a subscription
-    pub fn remove_subscription(&self, id: &SubscriptionId) -> bool {
+    pub fn remove_subscription(&self, id: &str) -> bool {
         let removed = self.subscriptions.write().remove(id).is_some();
         if removed {
-            log::info!("Removed subscription: {:?}", id);
+            log::info!("Removed subscription: {}", id);
         }
         removed
     }
 
-    /// Notifies subscribers of a new block
-    pub fn notify_new_block(&self, sink: &jsonrpc_pubsub::Sink, block: EthBlock) {
-        let block_json = serde_json::to_value(block).unwrap_or(Value::Null);
-        
-        for (id, sub_type) in self.subscriptions.read().iter() {
-            if *sub_type == SubscriptionType::NewHeads {
-                let params = jsonrpc_core::Params::Map(serde_json::Map::from_iter([
-                    ("subscription".to_string(), Value::String(format!("{:?}", id))),
-                    ("result".to_string(), block_json.clone()),
-                ]));
-                let _ = sink.notify(params);
+    /// Gets the number of active subscriptions
+    pub fn subscription_count(&self) -> usize {
+        self.subscriptions.read().len()
+    }
+
+    /// Gets the subscription counters
+    pub fn metrics(&self) -> SubscriptionMetrics {
+        self.stats().metrics
+    }
+
+    /// Gets the subscription counters, the delivery settings and the subscriptions that are behind
+    pub fn stats(&self) -> SubscriptionStats {
+        let subscriptions = self.subscriptions.read();
+        let mut by_type = BTreeMap::new();
+        let mut lagging = Vec::new();
+        for (id, subscriber) in subscriptions.iter() {
+            *by_type.entry(subscriber.subscription_type.name().to_string()).or_insert(0) += 1;
+            if !subscriber.backlog.is_empty() || subscriber.dropped > 0 {
+                lagging.push(SubscriptionLag {
+                    id: id.clone(),
+                    subscription_type: subscriber.subscription_type.name().to_string(),
+                    queued: subscriber.backlog.len(),
+                    dropped: subscriber.dropped,
+                });
             }
         }
+        lagging.sort_by(|a, b| b.queued.cmp(&a.queued).then_with(|| a.id.cmp(&b.id)));
+
+        SubscriptionStats {
+            metrics: SubscriptionMetrics {
+                subscriptions: by_type,
+                notifications_sent: self.notifications_sent.load(Ordering::Relaxed),
+                notifications_dropped: self.notifications_dropped.load(Ordering::Relaxed),
+                slow_consumers_disconnected: self.slow_consumers_disconnected.load(Ordering::Relaxed),
+                lagging_subscriptions: lagging.len(),
+            },
+            queue_capacity: self.queue_capacity,
+            slow_consumer_policy: self.policy,
+            lagging,
+        }
+    }
+
+    /// Notifies subscribers of a new block
+    ///
+    /// Like geth, newHeads delivers only the header: the block without its
+    /// transactions and uncles.
+    pub fn notify_new_block(&self, block: EthBlock) {
+        let mut header = serde_json::to_value(block).unwrap_or(Value::Null);
+        if let Some(fields) = header.as_object_mut() {
+            fields.remove("transactions");
+            fields.remove("uncles");
+        }
+        self.notify(SubscriptionType::NewHeads, header);
     }
 
     /// Notifies subscribers of a new pending transaction
-    pub fn notify_new_transaction(&self, sink: &jsonrpc_pubsub::Sink, tx: EthTransaction) {
-        let tx_hash = tx.hash.clone();
-        
-        for (id, sub_type) in self.subscriptions.read().iter() {
-            if *sub_type == SubscriptionType::NewPendingTransactions {
-                let params = jsonrpc_core::Params::Map(serde_json::Map::from_iter([
-                    ("subscription".to_string(), Value::String(format!("{:?}", id))),
-                    ("result".to_string(), Value::String(tx_hash.clone())),
-                ]));
-                let _ = sink.notify(params);
+    pub fn notify_new_transaction(&self, tx: &EthTransaction) {
+        self.notify(SubscriptionType::NewPendingTransactions, Value::String(tx.hash.clone()));
+    }
+
+    /// Notifies subscribers that the node stalled or recovered
+    pub fn notify_node_health(&self, event: &NodeHealthEvent) {
+        self.notify(SubscriptionType::NodeHealth, serde_json::to_value(event).unwrap_or(Value::Null));
+    }
+
+    /// Sends a notification to every subscriber of the given type
+    ///
+    /// Never waits on a connection. Subscribers whose connection has closed
+    /// are removed, and slow ones are handled by the slow consumer policy.
+    fn notify(&self, subscription_type: SubscriptionType, result: Value) {
+        let mut closed = Vec::new();
+        let mut too_slow = Vec::new();
+
+        let mut subscriptions = self.subscriptions.write();
+        for (id, subscriber) in subscriptions.iter_mut() {
+            if subscriber.subscription_type != subscription_type {
+                continue;
+            }
+
+            let notification = subscription_notification_with_method(subscription_type.notification_method(), id, result.clone());
+            let dropped_before = subscriber.dropped;
+            let (state, sent) = subscriber.deliver(notification, self.queue_capacity, self.policy);
+            self.notifications_sent.fetch_add(sent, Ordering::Relaxed);
+            self.notifications_dropped.fetch_add(subscriber.dropped - dropped_before, Ordering::Relaxed);
+            if subscriber.dropped > dropped_before && dropped_before == 0 {
+                log::warn!("Subscription {} is lagging; dropping its oldest notifications", id);
+            }
+
+            match state {
+                SubscriberState::Live => {},
+                SubscriberState::Closed => closed.push(id.clone()),
+                SubscriberState::TooSlow => too_slow.push(id.clone()),
+            }
+        }
+
+        for id in closed {
+            log::info!("Connection for subscription {} closed", id);
+            subscriptions.remove(&id);
+        }
+        for id in too_slow {
+            if let Some(subscriber) = subscriptions.remove(&id) {
+                log::warn!("Disconnecting slow consumer of subscription {} with {} queued notifications", id, subscriber.backlog.len());
+                // The backlog and the notification that overflowed it are never delivered
+                self.notifications_dropped.fetch_add(subscriber.backlog.len() as u64 + 1, Ordering::Relaxed);
+                self.slow_consumers_disconnected.fetch_add(1, Ordering::Relaxed);
+                subscriber.sink.close();
             }
         }
     }
 }
 
-#[derive(Clone)]
-pub struct Subscription {
-    id: String,
-    subscription_type: String,
+/// Builds the message delivered to a subscriber for one event
+///
+/// # Arguments
+/// * `subscription_id` - The id returned by eth_subscribe
+/// * `result` - The event payload
+///
+/// # Returns
+/// The serialized `eth_subscription` notification
+pub fn subscription_notification(subscription_id: &str, result: Value) -> String {
+    subscription_notification_with_method(SUBSCRIPTION_NOTIFICATION_METHOD, subscription_id, result)
 }
 
-impl Subscription {
-    pub fn new(id: String, subscription_type: String) -> Self {
-        Self {
-            id,
-            subscription_type,
+/// Builds a subscription notification delivered with the given method name
+fn subscription_notification_with_method(method: &str, subscription_id: &str, result: Value) -> String {
+    json!({
+        "jsonrpc": "2.0",
+        "method": method,
+        "params": {
+            "subscription": subscription_id,
+            "result": result,
         }
+    }).to_string()
+}
+
+/// Parses the subscription type, the first subscribe parameter
+fn subscription_type_param(params: jsonrpc_core::Params) -> Result<SubscriptionType> {
+    let params: Vec<Value> = params.parse()?;
+    if params.is_empty() {
+        return Err(Error::invalid_params("Missing subscription type"));
     }
+
+    params[0].as_str()
+        .ok_or_else(|| Error::invalid_params("Invalid subscription type"))?
+        .parse()
 }
 
 /// Ethereum PubSub handler
@@ -125,19 +527,20 @@ pub struct EthPubSubHandler {
     /// Chain ID for EIP-155 compatibility
     #[allow(dead_code)]
     chain_id: u64,
-    /// Active subscriptions
-    subscriptions: Mutex<HashMap<String, Subscription>>,
+    /// Source of randomness for subscription ids
+    random: RandomSource,
 }
 
 impl EthPubSubHandler {
     /// Creates a new Ethereum PubSub handler
     pub fn new(rpc_handler: RpcHandler, chain_id: u64) -> Self {
-        let subscription_manager = Arc::new(SubscriptionManager::new(rpc_handler));
-        
+        let random = rpc_handler.random.clone();
+        let subscription_manager = rpc_handler.eth_subscriptions.clone();
+
         EthPubSubHandler {
             subscription_manager,
             chain_id,
-            subscriptions: Mutex::new(HashMap::new()),
+            random,
         }
     }
 
@@ -147,35 +550,44 @@ impl EthPubSubHandler {
     }
 
     /// Handles eth_subscribe requests
-    pub async fn eth_subscribe(&self, params: jsonrpc_core::Params) -> Result<Value> {
-        let params: Vec<Value> = params.parse()?;
-        if params.is_empty() {
-            return Err(Error::invalid_params("Missing subscription type"));
+    ///
+    /// # Returns
+    /// The subscription id, exactly as it appears in every notification
+    pub async fn eth_subscribe(&self, params: jsonrpc_core::Params, session: WsSession) -> Result<Value> {
+        let subscription_type = subscription_type_param(params)?;
+        if subscription_type == SubscriptionType::Logs || subscription_type.is_ubi() {
+            return Err(Error::invalid_params("Unsupported subscription type"));
+        }
+
+        self.subscribe(subscription_type, session)
+    }
+
+    /// Handles ubi_subscribe requests for UBI Chain events such as nodeHealth
+    ///
+    /// # Returns
+    /// The subscription id; ubi_unsubscribe and eth_unsubscribe both cancel it
+    pub async fn ubi_subscribe(&self, params: jsonrpc_core::Params, session: WsSession) -> Result<Value> {
+        let subscription_type = subscription_type_param(params)?;
+        if !subscription_type.is_ubi() {
+            return Err(Error::invalid_params("Unsupported subscription type"));
         }
 
-        let subscription_type = params[0].as_str()
-            .ok_or_else(|| Error::invalid_params("Invalid subscription type"))?;
+        self.subscribe(subscription_type, session)
+    }
+
+    /// Registers a subscription for the session's connection
+    fn subscribe(&self, subscription_type: SubscriptionType, session: WsSession) -> Result<Value> {
+        let sender = session.sender
+            .ok_or_else(|| Error::invalid_params("Subscriptions require a WebSocket connection"))?;
 
         // Generate a random subscription ID
-        let mut rng = rand::thread_rng();
         let mut id_bytes = [0u8; 16];
-        rng.fill_bytes(&mut id_bytes);
-        let subscription_id = hex::encode(id_bytes);
-
-        match subscription_type {
-            "newHeads" => {
-                let subscription = Subscription::new(
-                    subscription_id.clone(),
-                    subscription_type.to_string(),
-                );
-                
-                let mut subscriptions = self.subscriptions.lock().unwrap();
-                subscriptions.insert(subscription_id.clone(), subscription);
-                
-                Ok(Value::String(subscription_id))
-            },
-            _ => Err(Error::invalid_params("Unsupported subscription type"))
-        }
+        self.random.fill_bytes(&mut id_bytes);
+        let subscription_id = format!("0x{}", hex::encode(id_bytes));
+
+        self.subscription_manager.add_subscription(subscription_id.clone(), subscription_type, sender);
+
+        Ok(Value::String(subscription_id))
     }
 
     /// Handles eth_unsubscribe requests
@@ -188,25 +600,185 @@ impl EthPubSubHandler {
         let subscription_id = params[0].as_str()
             .ok_or_else(|| Error::invalid_params("Invalid subscription ID"))?;
 
-        let mut subscriptions = self.subscriptions.lock().unwrap();
-        let removed = subscriptions.remove(subscription_id).is_some();
+        Ok(Value::Bool(self.subscription_manager.remove_subscription(subscription_id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::channel::mpsc;
+    use jsonrpc_core::Params;
+    use runtime::Runtime;
 
-        Ok(Value::Bool(removed))
+    fn handler() -> EthPubSubHandler {
+        let mut rpc_handler = RpcHandler::new(Runtime::new());
+        rpc_handler.set_random_source(RandomSource::seeded(1));
+        EthPubSubHandler::new(rpc_handler, 2030)
     }
 
-    /// Notifies subscribers of a new block
-    pub async fn notify_new_heads(&self, block_hash: String, block_number: u64) -> Result<()> {
-        let subscriptions = self.subscriptions.lock().unwrap();
-        
-        for subscription in subscriptions.values() {
-            if subscription.subscription_type == "newHeads" {
-                log::info!(
-                    "New block notification for subscription {}: hash={}, number=0x{:x}",
-                    subscription.id, block_hash, block_number
-                );
+    fn subscribe_params(kind: &str) -> Params {
+        Params::Array(vec![json!(kind)])
+    }
+
+    /// Connection that holds at most `capacity` unread messages
+    #[derive(Default)]
+    struct SlowSink {
+        capacity: std::sync::atomic::AtomicUsize,
+        unread: parking_lot::Mutex<Vec<String>>,
+        closed: std::sync::atomic::AtomicBool,
+    }
+
+    impl SlowSink {
+        /// Reads every delivered message and lets the connection hold more
+        fn read(&self, capacity: usize) -> Vec<Value> {
+            self.capacity.store(capacity, Ordering::SeqCst);
+            self.unread.lock().drain(..).map(|message| serde_json::from_str::<Value>(&message).unwrap()["params"]["result"].clone()).collect()
+        }
+    }
+
+    impl NotificationSink for SlowSink {
+        fn try_deliver(&self, message: String) -> Delivery {
+            let mut unread = self.unread.lock();
+            if self.closed.load(Ordering::SeqCst) {
+                Delivery::Closed
+            } else if unread.len() >= self.capacity.load(Ordering::SeqCst) {
+                Delivery::Full
+            } else {
+                unread.push(message);
+                Delivery::Sent
             }
         }
-        
-        Ok(())
+
+        fn close(&self) {
+            self.closed.store(true, Ordering::SeqCst);
+        }
     }
-} 
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_subscription_ids_are_prefixed_hex() {
+        let handler = handler();
+        let (sender, _receiver) = mpsc::unbounded();
+
+        let id = handler.eth_subscribe(subscribe_params("newHeads"), WsSession::new(sender)).await.unwrap();
+        let id = id.as_str().unwrap();
+
+        assert!(id.starts_with("0x"));
+        assert_eq!(id.len(), 34);
+        assert!(id[2..].chars().all(|c| c.is_ascii_hexdigit()));
+    }
+
+    #[tokio::test]
+    async fn test_notification_envelope() {
+        let handler = handler();
+        let (sender, mut receiver) = mpsc::unbounded();
+
+        let id = handler.eth_subscribe(subscribe_params("newPendingTransactions"), WsSession::new(sender)).await.unwrap();
+        handler.subscription_manager().notify(SubscriptionType::NewPendingTransactions, json!("0xabc"));
+
+        let message: Value = serde_json::from_str(&receiver.try_recv().unwrap()).unwrap();
+        assert_eq!(message, json!({
+            "jsonrpc": "2.0",
+            "method": "eth_subscription",
+            "params": { "subscription": id, "result": "0xabc" }
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_unsubscribe_and_closed_connections() {
+        let handler = handler();
+        let (sender, receiver) = mpsc::unbounded();
+
+        let id = handler.eth_subscribe(subscribe_params("newHeads"), WsSession::new(sender.clone())).await.unwrap();
+        handler.eth_subscribe(subscribe_params("newHeads"), WsSession::new(sender)).await.unwrap();
+        assert_eq!(handler.subscription_manager().subscription_count(), 2);
+
+        let removed = handler.eth_unsubscribe(Params::Array(vec![id])).await.unwrap();
+        assert_eq!(removed, Value::Bool(true));
+        assert_eq!(handler.subscription_manager().subscription_count(), 1);
+
+        // The remaining subscription is dropped on the first event after its connection closes
+        drop(receiver);
+        handler.subscription_manager().notify(SubscriptionType::NewHeads, Value::Null);
+        assert_eq!(handler.subscription_manager().subscription_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_rejects_unsupported_requests() {
+        let handler = handler();
+        let (sender, _receiver) = mpsc::unbounded();
+
+        assert!(handler.eth_subscribe(subscribe_params("newHeads"), WsSession::default()).await.is_err());
+        assert!(handler.eth_subscribe(subscribe_params("logs"), WsSession::new(sender.clone())).await.is_err());
+        assert!(handler.eth_subscribe(subscribe_params("syncing"), WsSession::new(sender.clone())).await.is_err());
+        assert!(handler.eth_subscribe(subscribe_params("nodeHealth"), WsSession::new(sender.clone())).await.is_err());
+        assert!(handler.ubi_subscribe(subscribe_params("newHeads"), WsSession::new(sender)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_node_health_notifications() {
+        let handler = handler();
+        let (sender, mut receiver) = mpsc::unbounded();
+
+        let id = handler.ubi_subscribe(subscribe_params("nodeHealth"), WsSession::new(sender)).await.unwrap();
+        handler.subscription_manager().notify_node_health(&NodeHealthEvent::NodeRecovered { last_block: 7, stalled_for_secs: 30 });
+
+        let message: Value = serde_json::from_str(&receiver.try_recv().unwrap()).unwrap();
+        assert_eq!(message, json!({
+            "jsonrpc": "2.0",
+            "method": "ubi_subscription",
+            "params": {
+                "subscription": id,
+                "result": { "event": "nodeRecovered", "last_block": 7, "stalled_for_secs": 30 }
+            }
+        }));
+    }
+
+    #[test]
+    fn test_slow_consumers_do_not_hold_up_others() {
+        // Under drop-oldest the slow subscription keeps only the newest notifications
+        let manager = SubscriptionManager::with_policy(2, SlowConsumerPolicy::DropOldest);
+        let (sender, mut receiver) = mpsc::unbounded();
+        let slow = Arc::new(SlowSink::default());
+        slow.capacity.store(1, Ordering::SeqCst);
+        manager.add_subscription("0xfast".to_string(), SubscriptionType::NewPendingTransactions, Arc::new(sender));
+        manager.add_subscription("0xslow".to_string(), SubscriptionType::NewPendingTransactions, slow.clone());
+        for n in 1..=5 {
+            manager.notify(SubscriptionType::NewPendingTransactions, json!(n));
+        }
+
+        for n in 1..=5 {
+            let message: Value = serde_json::from_str(&receiver.try_recv().unwrap()).unwrap();
+            assert_eq!(message["params"]["result"], json!(n));
+        }
+        let stats = manager.stats();
+        assert_eq!(stats.metrics.subscriptions["newPendingTransactions"], 2);
+        assert_eq!((stats.metrics.notifications_sent, stats.metrics.notifications_dropped), (6, 2));
+        assert_eq!(stats.lagging, vec![SubscriptionLag {
+            id: "0xslow".to_string(),
+            subscription_type: "newPendingTransactions".to_string(),
+            queued: 2,
+            dropped: 2,
+        }]);
+
+        // Once the client reads, the backlog is delivered in order ahead of new notifications
+        assert_eq!(slow.read(10), vec![json!(1)]);
+        manager.notify(SubscriptionType::NewPendingTransactions, json!(6));
+        assert_eq!(slow.read(10), vec![json!(4), json!(5), json!(6)]);
+
+        // Under disconnect the client is closed once its backlog overflows
+        let manager = SubscriptionManager::with_policy(1, SlowConsumerPolicy::Disconnect);
+        let (sender, _receiver) = mpsc::unbounded();
+        let slow = Arc::new(SlowSink::default());
+        manager.add_subscription("0xfast".to_string(), SubscriptionType::NewHeads, Arc::new(sender));
+        manager.add_subscription("0xslow".to_string(), SubscriptionType::NewHeads, slow.clone());
+        manager.notify(SubscriptionType::NewHeads, Value::Null);
+        assert_eq!(manager.subscription_count(), 2);
+        manager.notify(SubscriptionType::NewHeads, Value::Null);
+
+        assert_eq!(manager.subscription_count(), 1);
+        assert!(slow.closed.load(Ordering::SeqCst));
+        let metrics = manager.metrics();
+        assert_eq!((metrics.slow_consumers_disconnected, metrics.notifications_dropped, metrics.notifications_sent), (1, 2, 2));
+    }
+}
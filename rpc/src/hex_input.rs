@@ -0,0 +1,174 @@
+//! Hex Parameter Parsing
+//!
+//! Ethereum clients send byte strings, quantities and addresses as 0x-prefixed
+//! hex. Every eth method parses them through these helpers, so a missing
+//! prefix, odd-length data or a stray character is reported to the client as
+//! invalid params instead of being sliced off, ignored or replaced by a zero
+//! default.
+
+use primitive_types::U256;
+use std::fmt;
+
+/// Most hex digits a quantity may have (256 bits)
+const MAX_QUANTITY_DIGITS: usize = 64;
+
+/// Hex digits of an address (20 bytes)
+const ADDRESS_DIGITS: usize = 40;
+
+/// Error types for hex parsing
+#[derive(Debug, Clone, PartialEq)]
+pub enum HexError {
+    /// The value does not start with 0x
+    MissingPrefix,
+    /// The value has no digits after 0x
+    Empty,
+    /// Byte data has an odd number of digits
+    OddLength(usize),
+    /// The value contains a character that is not a hex digit
+    InvalidCharacter(char),
+    /// The quantity does not fit the target type
+    QuantityTooLarge,
+    /// An address does not have exactly 40 digits
+    WrongAddressLength(usize),
+}
+
+impl fmt::Display for HexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HexError::MissingPrefix => write!(f, "Hex value must start with 0x"),
+            HexError::Empty => write!(f, "Hex value has no digits after 0x"),
+            HexError::OddLength(digits) => write!(f, "Hex data has an odd number of digits ({})", digits),
+            HexError::InvalidCharacter(c) => write!(f, "Invalid hex character {:?}", c),
+            HexError::QuantityTooLarge => write!(f, "Hex quantity is too large"),
+            HexError::WrongAddressLength(digits) => write!(f, "Address must have 40 hex digits, not {}", digits),
+        }
+    }
+}
+
+impl std::error::Error for HexError {}
+
+/// Strips the 0x prefix and checks every remaining character is a hex digit
+fn hex_digits(value: &str) -> Result<&str, HexError> {
+    let digits = value.strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .ok_or(HexError::MissingPrefix)?;
+    match digits.chars().find(|c| !c.is_ascii_hexdigit()) {
+        Some(c) => Err(HexError::InvalidCharacter(c)),
+        None => Ok(digits),
+    }
+}
+
+/// Parses 0x-prefixed byte data, such as a raw transaction
+///
+/// `"0x"` is accepted as empty data.
+///
+/// # Arguments
+/// * `value` - The hex string supplied by the client
+///
+/// # Returns
+/// The bytes, or why the string is not valid data
+pub fn parse_hex_bytes(value: &str) -> Result<Vec<u8>, HexError> {
+    let digits = hex_digits(value)?;
+    if digits.len() % 2 != 0 {
+        return Err(HexError::OddLength(digits.len()));
+    }
+    hex::decode(digits).map_err(|_| HexError::OddLength(digits.len()))
+}
+
+/// Parses a 0x-prefixed quantity, such as a value in wei or a storage slot
+///
+/// # Arguments
+/// * `value` - The hex string supplied by the client
+///
+/// # Returns
+/// The quantity, or why the string is not a valid 256-bit quantity
+pub fn parse_hex_quantity(value: &str) -> Result<U256, HexError> {
+    let digits = hex_digits(value)?;
+    if digits.is_empty() {
+        return Err(HexError::Empty);
+    }
+
+    let significant = digits.trim_start_matches('0');
+    if significant.len() > MAX_QUANTITY_DIGITS {
+        return Err(HexError::QuantityTooLarge);
+    }
+    if significant.is_empty() {
+        return Ok(U256::zero());
+    }
+    U256::from_str_radix(significant, 16).map_err(|_| HexError::QuantityTooLarge)
+}
+
+/// Parses a 0x-prefixed quantity that must fit in a u64, such as a block number
+pub fn parse_hex_u64(value: &str) -> Result<u64, HexError> {
+    let quantity = parse_hex_quantity(value)?;
+    if quantity > U256::from(u64::MAX) {
+        return Err(HexError::QuantityTooLarge);
+    }
+    Ok(quantity.as_u64())
+}
+
+/// Parses a 0x-prefixed 20-byte address
+///
+/// # Arguments
+/// * `value` - The address supplied by the client, in any letter case
+///
+/// # Returns
+/// The address in lowercase, or why the string is not an address
+pub fn parse_address(value: &str) -> Result<String, HexError> {
+    let digits = hex_digits(value)?;
+    if digits.len() != ADDRESS_DIGITS {
+        return Err(HexError::WrongAddressLength(digits.len()));
+    }
+    Ok(format!("0x{}", digits.to_lowercase()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explicit_errors() {
+        assert_eq!(parse_hex_bytes("0x0aff"), Ok(vec![0x0a, 0xff]));
+        assert_eq!(parse_hex_bytes("0x"), Ok(vec![]));
+        assert_eq!(parse_hex_bytes("0aff"), Err(HexError::MissingPrefix));
+        assert_eq!(parse_hex_bytes("0xaff"), Err(HexError::OddLength(3)));
+        assert_eq!(parse_hex_bytes("0xzz"), Err(HexError::InvalidCharacter('z')));
+
+        assert_eq!(parse_hex_quantity("0x0"), Ok(U256::zero()));
+        assert_eq!(parse_hex_quantity("0xde0b6b3a7640000"), Ok(U256::exp10(18)));
+        assert_eq!(parse_hex_quantity("0x"), Err(HexError::Empty));
+        assert_eq!(parse_hex_quantity(&format!("0x1{}", "0".repeat(64))), Err(HexError::QuantityTooLarge));
+        assert_eq!(parse_hex_u64("0x10000000000000000"), Err(HexError::QuantityTooLarge));
+
+        assert_eq!(parse_address("0xABCDEF0000000000000000000000000000000001"),
+                   Ok("0xabcdef0000000000000000000000000000000001".to_string()));
+        assert_eq!(parse_address("0x1234"), Err(HexError::WrongAddressLength(4)));
+    }
+
+    #[test]
+    fn test_arbitrary_strings_never_panic() {
+        const ALPHABET: [&str; 12] = ["0", "x", "X", "a", "F", "9", "g", " ", "é", "\u{1F600}", "\0", "-"];
+        let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        for _ in 0..20_000 {
+            let length = (next() % 80) as usize;
+            let mut input: String = (0..length).map(|_| ALPHABET[(next() % ALPHABET.len() as u64) as usize]).collect();
+            if next() % 2 == 0 {
+                input.insert_str(0, "0x");
+            }
+
+            let _ = parse_hex_bytes(&input);
+            let _ = parse_hex_quantity(&input);
+            let _ = parse_hex_u64(&input);
+            if let Ok(address) = parse_address(&input) {
+                assert_eq!(address.len(), 42);
+            }
+        }
+    }
+}
@@ -0,0 +1,252 @@
+//! Idempotency Keys
+//!
+//! Clients that retry on timeout can attach an idempotency key to faucet
+//! requests and transaction submissions. The first request with a key runs;
+//! requests repeating the key within the TTL get the first request's result
+//! instead of running again, including requests that arrive while the first is
+//! still in progress. Keys are remembered in memory only, so a node restart
+//! forgets them.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::OnceCell;
+use tokio::time::Instant;
+
+/// Default time a key is remembered, in seconds
+pub const DEFAULT_IDEMPOTENCY_TTL_SECS: u64 = 600;
+
+/// Default number of keys remembered per method
+pub const DEFAULT_MAX_IDEMPOTENCY_KEYS: usize = 10_000;
+
+/// Maximum length of a key
+pub const MAX_IDEMPOTENCY_KEY_LENGTH: usize = 128;
+
+/// How long keys are remembered and how many
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IdempotencyConfig {
+    /// Time after the first request during which repeats are absorbed
+    pub ttl: Duration,
+    /// Maximum number of keys remembered; the oldest is forgotten first
+    pub max_entries: usize,
+}
+
+impl Default for IdempotencyConfig {
+    fn default() -> Self {
+        IdempotencyConfig {
+            ttl: Duration::from_secs(DEFAULT_IDEMPOTENCY_TTL_SECS),
+            max_entries: DEFAULT_MAX_IDEMPOTENCY_KEYS,
+        }
+    }
+}
+
+/// Reason a keyed request was refused
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdempotencyError {
+    /// The key is empty, too long or contains control characters
+    InvalidKey,
+    /// The key was already used for a request with different parameters
+    KeyReused,
+}
+
+impl fmt::Display for IdempotencyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IdempotencyError::InvalidKey => write!(
+                f, "Idempotency key must be 1 to {} printable characters", MAX_IDEMPOTENCY_KEY_LENGTH
+            ),
+            IdempotencyError::KeyReused => write!(f, "Idempotency key was already used with different parameters"),
+        }
+    }
+}
+
+/// A remembered key
+struct CacheEntry<T> {
+    /// Parameters of the first request, compared against repeats
+    fingerprint: String,
+    /// Time of the first request
+    created: Instant,
+    /// Result of the first request, set once it completes
+    result: Arc<OnceCell<T>>,
+}
+
+/// Keys and the order they were first seen in
+struct CacheState<T> {
+    entries: HashMap<String, CacheEntry<T>>,
+    order: VecDeque<(String, Instant)>,
+}
+
+/// Results of recent keyed requests
+pub struct IdempotencyCache<T> {
+    config: IdempotencyConfig,
+    state: Mutex<CacheState<T>>,
+}
+
+impl<T: Clone> IdempotencyCache<T> {
+    /// Creates an empty cache
+    pub fn new(config: IdempotencyConfig) -> Self {
+        IdempotencyCache {
+            config,
+            state: Mutex::new(CacheState {
+                entries: HashMap::new(),
+                order: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// Runs a keyed request at most once
+    ///
+    /// # Arguments
+    /// * `key` - The client-supplied idempotency key
+    /// * `fingerprint` - The request parameters; a repeat with other parameters is refused
+    /// * `execute` - Runs the request; only called for the first request with the key
+    ///
+    /// # Returns
+    /// The result, and whether it was cached from an earlier request
+    pub async fn run<F, Fut>(&self, key: &str, fingerprint: &str, execute: F) -> Result<(T, bool), IdempotencyError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = T>,
+    {
+        if !is_valid_key(key) {
+            return Err(IdempotencyError::InvalidKey);
+        }
+
+        let cell = self.claim(key, fingerprint)?;
+
+        let mut executed = false;
+        let result = cell.get_or_init(|| {
+            executed = true;
+            execute()
+        }).await.clone();
+
+        Ok((result, !executed))
+    }
+
+    /// Gets the result slot for a key, remembering the key if it is new
+    fn claim(&self, key: &str, fingerprint: &str) -> Result<Arc<OnceCell<T>>, IdempotencyError> {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        self.evict_expired(&mut state, now);
+
+        if let Some(entry) = state.entries.get(key) {
+            if entry.fingerprint != fingerprint {
+                return Err(IdempotencyError::KeyReused);
+            }
+            return Ok(entry.result.clone());
+        }
+
+        while state.entries.len() >= self.config.max_entries.max(1) {
+            match state.order.pop_front() {
+                Some((oldest, _)) => {
+                    state.entries.remove(&oldest);
+                },
+                None => break,
+            }
+        }
+
+        let result = Arc::new(OnceCell::new());
+        state.entries.insert(key.to_string(), CacheEntry {
+            fingerprint: fingerprint.to_string(),
+            created: now,
+            result: result.clone(),
+        });
+        state.order.push_back((key.to_string(), now));
+
+        Ok(result)
+    }
+
+    /// Forgets keys older than the TTL
+    fn evict_expired(&self, state: &mut CacheState<T>, now: Instant) {
+        while let Some((key, created)) = state.order.front().cloned() {
+            if created + self.config.ttl > now {
+                break;
+            }
+            state.order.pop_front();
+            if state.entries.get(&key).is_some_and(|entry| entry.created == created) {
+                state.entries.remove(&key);
+            }
+        }
+    }
+
+    /// Gets the number of remembered keys
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().entries.len()
+    }
+
+    /// Checks whether no keys are remembered
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Checks that a key is 1 to `MAX_IDEMPOTENCY_KEY_LENGTH` printable characters
+fn is_valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && key.chars().count() <= MAX_IDEMPOTENCY_KEY_LENGTH
+        && !key.chars().any(char::is_control)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn cache(ttl_secs: u64, max_entries: usize) -> Arc<IdempotencyCache<u64>> {
+        Arc::new(IdempotencyCache::new(IdempotencyConfig {
+            ttl: Duration::from_secs(ttl_secs),
+            max_entries,
+        }))
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_requests_run_once() {
+        let cache = cache(60, 10);
+        let runs = Arc::new(AtomicUsize::new(0));
+
+        let tasks: Vec<_> = (0..16).map(|_| {
+            let cache = cache.clone();
+            let runs = runs.clone();
+            tokio::spawn(async move {
+                cache.run("retry-1", "a", || async move {
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    runs.fetch_add(1, Ordering::SeqCst) as u64 + 100
+                }).await.unwrap()
+            })
+        }).collect();
+
+        let mut duplicates = 0;
+        for task in tasks {
+            let (result, duplicate) = task.await.unwrap();
+            assert_eq!(result, 100);
+            duplicates += duplicate as usize;
+        }
+
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        assert_eq!(duplicates, 15);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_keys_expire_and_are_bounded() {
+        let cache = cache(60, 2);
+
+        assert_eq!(cache.run("k", "a", || async { 1 }).await, Ok((1, false)));
+        assert_eq!(cache.run("k", "a", || async { 2 }).await, Ok((1, true)));
+        assert_eq!(cache.run("k", "b", || async { 3 }).await, Err(IdempotencyError::KeyReused));
+
+        // The key is forgotten after the TTL
+        tokio::time::advance(Duration::from_secs(60)).await;
+        assert_eq!(cache.run("k", "b", || async { 4 }).await, Ok((4, false)));
+
+        // The oldest key makes room for new ones
+        cache.run("l", "a", || async { 5 }).await.unwrap();
+        cache.run("m", "a", || async { 6 }).await.unwrap();
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.run("k", "b", || async { 7 }).await, Ok((7, false)));
+
+        assert_eq!(cache.run("", "a", || async { 8 }).await, Err(IdempotencyError::InvalidKey));
+        assert_eq!(cache.run(&"x".repeat(129), "a", || async { 8 }).await, Err(IdempotencyError::InvalidKey));
+    }
+}
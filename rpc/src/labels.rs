@@ -0,0 +1,337 @@
+//! Account Labels
+//!
+//! Explorers show names such as "Faucet", "Treasury" or "Exchange X" next to
+//! well-known addresses. The node operator maintains these names in a label
+//! registry: each address has at most one label, with a category from a fixed
+//! set. Labels are node-local metadata, not consensus state, so they are not
+//! written to checkpoints. They live in a JSON file in the node's data
+//! directory, which the operator may also edit by hand while the node is
+//! stopped:
+//!
+//! ```json
+//! { "0x1111111111111111111111111111111111111111": { "label": "Faucet", "category": "faucet" } }
+//! ```
+//!
+//! Changes made over the admin RPC are saved to the file at once, so they
+//! survive a restart.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+/// Name of the labels file in the node's data directory
+pub const LABELS_FILE: &str = "labels.json";
+
+/// Longest label accepted, in characters
+pub const MAX_LABEL_CHARS: usize = 64;
+
+/// What kind of party an address belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LabelCategory {
+    /// A faucet handing out tokens
+    Faucet,
+    /// A treasury or other reserve
+    Treasury,
+    /// An exchange's deposit or hot wallet
+    Exchange,
+    /// A block producer
+    Validator,
+    /// A reserved or system address, such as the burn address
+    System,
+    /// Anything else
+    Other,
+}
+
+impl FromStr for LabelCategory {
+    type Err = LabelError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "faucet" => Ok(LabelCategory::Faucet),
+            "treasury" => Ok(LabelCategory::Treasury),
+            "exchange" => Ok(LabelCategory::Exchange),
+            "validator" => Ok(LabelCategory::Validator),
+            "system" => Ok(LabelCategory::System),
+            "other" => Ok(LabelCategory::Other),
+            _ => Err(LabelError::InvalidCategory(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for LabelCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LabelCategory::Faucet => write!(f, "faucet"),
+            LabelCategory::Treasury => write!(f, "treasury"),
+            LabelCategory::Exchange => write!(f, "exchange"),
+            LabelCategory::Validator => write!(f, "validator"),
+            LabelCategory::System => write!(f, "system"),
+            LabelCategory::Other => write!(f, "other"),
+        }
+    }
+}
+
+/// Label of one address
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountLabel {
+    /// Name shown next to the address
+    pub label: String,
+    /// What kind of party the address belongs to
+    pub category: LabelCategory,
+}
+
+impl AccountLabel {
+    /// Builds a label, checking its text
+    ///
+    /// # Arguments
+    /// * `label` - Name shown next to the address; surrounding whitespace is trimmed
+    /// * `category` - What kind of party the address belongs to
+    ///
+    /// # Returns
+    /// The label, or why the text was refused
+    pub fn new(label: &str, category: LabelCategory) -> Result<Self, LabelError> {
+        let label = label.trim();
+        if label.is_empty() {
+            return Err(LabelError::EmptyLabel);
+        }
+        let chars = label.chars().count();
+        if chars > MAX_LABEL_CHARS {
+            return Err(LabelError::LabelTooLong(chars));
+        }
+        if label.chars().any(char::is_control) {
+            return Err(LabelError::ControlCharacters);
+        }
+        Ok(AccountLabel { label: label.to_string(), category })
+    }
+}
+
+/// A label set or removed by the operator
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LabelChange {
+    /// The labelled address, lowercase
+    pub address: String,
+    /// The address's label after the change; None if it was removed
+    pub label: Option<AccountLabel>,
+    /// The label it had before
+    pub previous: Option<AccountLabel>,
+}
+
+/// Reason a label was refused or the registry could not be read or saved
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LabelError {
+    /// The address is not a 0x-prefixed 20-byte hex address
+    InvalidAddress(String),
+    /// The label is empty or only whitespace
+    EmptyLabel,
+    /// The label is longer than `MAX_LABEL_CHARS`, with its length
+    LabelTooLong(usize),
+    /// The label contains control characters such as newlines
+    ControlCharacters,
+    /// The category is not one of the known ones
+    InvalidCategory(String),
+    /// The labels file could not be read or written
+    Io(String),
+}
+
+impl fmt::Display for LabelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LabelError::InvalidAddress(address) => write!(f, "Invalid address: {}", address),
+            LabelError::EmptyLabel => write!(f, "Label must not be empty"),
+            LabelError::LabelTooLong(chars) => {
+                write!(f, "Label is {} characters long; at most {} are allowed", chars, MAX_LABEL_CHARS)
+            },
+            LabelError::ControlCharacters => write!(f, "Label must not contain control characters"),
+            LabelError::InvalidCategory(category) => write!(
+                f,
+                "Invalid label category: {} (expected faucet, treasury, exchange, validator, system or other)",
+                category
+            ),
+            LabelError::Io(msg) => write!(f, "Labels file error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for LabelError {}
+
+/// Operator-maintained labels of addresses, optionally saved to a file
+#[derive(Debug, Clone, Default)]
+pub struct LabelRegistry {
+    /// Labels by lowercase address
+    labels: Arc<RwLock<BTreeMap<String, AccountLabel>>>,
+    /// File the labels are saved to after every change, if any
+    path: Option<PathBuf>,
+}
+
+impl LabelRegistry {
+    /// Creates an empty registry that is kept in memory only
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the registry from a labels file, which is created on the first change if missing
+    ///
+    /// # Arguments
+    /// * `path` - The labels file
+    ///
+    /// # Returns
+    /// The registry, or an error if the file cannot be read or holds an invalid label
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, LabelError> {
+        let path = path.as_ref();
+        let mut labels = BTreeMap::new();
+        if path.exists() {
+            let contents = fs::read_to_string(path)
+                .map_err(|e| LabelError::Io(format!("cannot read {}: {}", path.display(), e)))?;
+            let stored: BTreeMap<String, AccountLabel> = serde_json::from_str(&contents)
+                .map_err(|e| LabelError::Io(format!("invalid {}: {}", path.display(), e)))?;
+            // Hand-edited files go through the same checks as admin requests
+            for (address, stored) in stored {
+                let address = normalize_address(&address)?;
+                labels.insert(address, AccountLabel::new(&stored.label, stored.category)?);
+            }
+        }
+
+        Ok(LabelRegistry {
+            labels: Arc::new(RwLock::new(labels)),
+            path: Some(path.to_path_buf()),
+        })
+    }
+
+    /// Gets the file the labels are saved to, if any
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Gets the label of an address
+    pub fn get(&self, address: &str) -> Option<AccountLabel> {
+        self.labels.read().unwrap().get(&address.to_lowercase()).cloned()
+    }
+
+    /// Gets every label, by lowercase address
+    pub fn all(&self) -> BTreeMap<String, AccountLabel> {
+        self.labels.read().unwrap().clone()
+    }
+
+    /// Sets or replaces the label of an address and saves the registry
+    ///
+    /// # Arguments
+    /// * `address` - The labelled address
+    /// * `label` - The validated label
+    ///
+    /// # Returns
+    /// The label it replaced, or an error if the address is invalid or the file cannot be written;
+    /// the change is not kept if it cannot be saved
+    pub fn set(&self, address: &str, label: AccountLabel) -> Result<Option<AccountLabel>, LabelError> {
+        let address = normalize_address(address)?;
+        let mut labels = self.labels.write().unwrap();
+        let previous = labels.insert(address.clone(), label);
+        if let Err(e) = self.save(&labels) {
+            match previous.clone() {
+                Some(previous) => labels.insert(address, previous),
+                None => labels.remove(&address),
+            };
+            return Err(e);
+        }
+        Ok(previous)
+    }
+
+    /// Removes the label of an address and saves the registry
+    ///
+    /// # Returns
+    /// The removed label, if the address had one, or an error if the file cannot be written;
+    /// the label is kept if the removal cannot be saved
+    pub fn remove(&self, address: &str) -> Result<Option<AccountLabel>, LabelError> {
+        let address = normalize_address(address)?;
+        let mut labels = self.labels.write().unwrap();
+        let removed = match labels.remove(&address) {
+            Some(removed) => removed,
+            None => return Ok(None),
+        };
+        if let Err(e) = self.save(&labels) {
+            labels.insert(address, removed);
+            return Err(e);
+        }
+        Ok(Some(removed))
+    }
+
+    /// Writes the labels to the file, replacing it in one step
+    fn save(&self, labels: &BTreeMap<String, AccountLabel>) -> Result<(), LabelError> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(|e| LabelError::Io(format!("cannot create {}: {}", dir.display(), e)))?;
+        }
+
+        let contents = serde_json::to_string_pretty(labels).map_err(|e| LabelError::Io(e.to_string()))?;
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, contents).map_err(|e| LabelError::Io(format!("cannot write {}: {}", tmp.display(), e)))?;
+        fs::rename(&tmp, path).map_err(|e| LabelError::Io(format!("cannot replace {}: {}", path.display(), e)))
+    }
+}
+
+/// Lowercases an address after checking its format
+fn normalize_address(address: &str) -> Result<String, LabelError> {
+    let address = address.to_lowercase();
+    if !crate::is_valid_eth_address(&address) {
+        return Err(LabelError::InvalidAddress(address));
+    }
+    Ok(address)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXCHANGE: &str = "0xAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA";
+    const TREASURY: &str = "0xbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb";
+
+    #[test]
+    fn test_labels_are_validated_and_survive_a_restart() {
+        let dir = std::env::temp_dir().join(format!("ubi-labels-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join(LABELS_FILE);
+
+        let registry = LabelRegistry::load(&path).unwrap();
+        assert!(registry.all().is_empty());
+
+        // Refused labels change nothing
+        assert_eq!(AccountLabel::new("  ", LabelCategory::Other), Err(LabelError::EmptyLabel));
+        assert_eq!(AccountLabel::new(&"x".repeat(MAX_LABEL_CHARS + 1), LabelCategory::Other),
+                   Err(LabelError::LabelTooLong(MAX_LABEL_CHARS + 1)));
+        assert_eq!(AccountLabel::new("Line\nbreak", LabelCategory::Other), Err(LabelError::ControlCharacters));
+        assert_eq!("bank".parse::<LabelCategory>(), Err(LabelError::InvalidCategory("bank".to_string())));
+        let exchange = AccountLabel::new(" Exchange X ", "exchange".parse().unwrap()).unwrap();
+        assert_eq!(registry.set("0x1234", exchange.clone()), Err(LabelError::InvalidAddress("0x1234".to_string())));
+        assert!(!path.exists());
+
+        // Addresses are matched case-insensitively and labels replaced in place
+        assert_eq!(exchange.label, "Exchange X");
+        assert_eq!(registry.set(EXCHANGE, exchange.clone()).unwrap(), None);
+        let treasury = AccountLabel::new("Treasury", LabelCategory::Treasury).unwrap();
+        registry.set(TREASURY, AccountLabel::new("Reserve", LabelCategory::Treasury).unwrap()).unwrap();
+        assert_eq!(registry.set(TREASURY, treasury.clone()).unwrap().unwrap().label, "Reserve");
+        assert_eq!(registry.get(&EXCHANGE.to_lowercase()), Some(exchange.clone()));
+
+        // A restarted node reads back what was saved, removals included
+        let restarted = LabelRegistry::load(&path).unwrap();
+        assert_eq!(restarted.get(EXCHANGE), Some(exchange.clone()));
+        assert_eq!(restarted.get(TREASURY), Some(treasury));
+        assert_eq!(restarted.remove(TREASURY).unwrap().unwrap().category, LabelCategory::Treasury);
+        assert_eq!(restarted.remove(TREASURY).unwrap(), None);
+        let restarted = LabelRegistry::load(&path).unwrap();
+        assert_eq!(restarted.all().into_iter().collect::<Vec<_>>(), vec![(EXCHANGE.to_lowercase(), exchange)]);
+
+        // Hand-edited files are checked like admin requests
+        fs::write(&path, format!(r#"{{ "{}": {{ "label": "", "category": "other" }} }}"#, TREASURY)).unwrap();
+        assert_eq!(LabelRegistry::load(&path).unwrap_err(), LabelError::EmptyLabel);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -9,27 +9,92 @@
 //! - AI resource management
 //! - Network status information
 
-use runtime::{Runtime, AccountError, Transaction};
+use runtime::{Runtime, AccountError, CheckpointHealth, Transaction};
+use runtime::account_export::{AccountExport, SupplyEvent};
+use runtime::batch_transfer::BatchReceipt;
+use runtime::block_extras::BlockExtras;
+use runtime::faucet::{FaucetError, FaucetStats};
+use runtime::limits::{AccountLimitState, AccountLimits};
+use runtime::maintenance::MaintenanceStatus;
+use runtime::payment_stream::{PaymentStream, StreamId};
+use runtime::params::{ChainParam, ChainParams, PendingParamChange};
+use runtime::statement::{Statement, StatementFormat};
+use runtime::state_proof::{AccountProof, ProofCheck};
+use runtime::state_diff::StateDiff;
+use runtime::state_snapshot::StateSnapshot;
+use runtime::tx_status::TransactionStatus;
+use runtime::transfer_mode::{TransferAmounts, TransferMode};
+use runtime::tx_trace::{LifecycleEvent, LifecycleStage};
+use runtime::ubi_mint::ClaimResult;
+use runtime::ubi_stats::{NetworkStats, UbiStats};
 use serde::{Deserialize, Serialize};
-use log::{info, error};
+use log::{info, warn, error};
 
 // Add Ethereum compatibility module
 pub mod eth_compat;
 // Add Ethereum PubSub module
 pub mod eth_pubsub;
+// Add seedable randomness module
+pub mod random;
+// Add amount parameter parsing module
+pub mod amount;
+// Add bounded block and transaction storage module
+pub mod chain_store;
+// Add structured logging and correlation id module
+pub mod logging;
+// Add UBI token pseudo-contract module
+pub mod token_facade;
+// Add signed request verification module
+pub mod signature;
+// Add RPC concurrency limits module
+pub mod concurrency;
+
+// Add RPC response cache module
+pub mod response_cache;
+// Add idempotency key module
+pub mod idempotency;
+// Add cursor pagination module
+pub mod pagination;
+// Add hex parameter parsing module
+pub mod hex_input;
+// Add raw transaction decoding module
+pub mod raw_transaction;
+// Add admin housekeeping module
+pub mod admin;
+// Add account label registry module
+pub mod labels;
+// Add proof-of-work challenge module
+pub mod pow;
+// Add job scheduler module
+pub mod scheduler;
+// Add API version and capabilities module
+pub mod api_version;
+
+pub use random::RandomSource;
+pub use amount::{parse_amount, parse_optional_amount, AmountError};
+pub use chain_store::RetentionConfig;
+pub use concurrency::{ConcurrencyConfig, ConcurrencyLimiter};
+pub use response_cache::ResponseCache;
+pub use idempotency::{IdempotencyCache, IdempotencyConfig};
+pub use pagination::{Page, PageRequest};
 
 // Remove the external crate reference
 // extern crate ubi_chain_node as node;
 // use node::Transaction;
 
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::net::SocketAddr;
 use std::str::FromStr;
-use jsonrpc_core::{IoHandler, Error as JsonRpcError};
+use jsonrpc_core::{MetaIoHandler, Error as JsonRpcError};
 use jsonrpc_http_server::Server as HttpServer;
 use jsonrpc_ws_server::{Server as WsServer, ServerBuilder as WsServerBuilder};
-use rand::Rng;
-use hex;
+
+/// Number of blocks returned by ubi_getLatestBlocks when no count is given
+pub const DEFAULT_LATEST_BLOCKS: usize = 10;
+
+/// Maximum number of blocks returned by one ubi_getLatestBlocks call
+pub const MAX_LATEST_BLOCKS: usize = 100;
 
 /// Account information structure returned by RPC queries
 ///
@@ -57,8 +122,36 @@ pub struct AccountInfo {
     /// Current balance in UBI tokens
     balance: u64,
     
+    /// Tokens of the balance still locked, see `Runtime::lock_balance`
+    #[serde(default)]
+    locked: u64,
+    
+    /// Tokens of the balance that can be spent now
+    #[serde(default)]
+    spendable: u64,
+    
+    /// UBI accrued since the last claim, not yet part of the balance
+    pending_ubi: u64,
+    
     /// Whether the account has passed human verification
     verified: bool,
+    
+    /// Whether the account is frozen pending review
+    #[serde(default)]
+    frozen: bool,
+    
+    /// Reason given for the freeze, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    frozen_reason: Option<String>,
+    
+    /// Whether dividends are claimed into the balance whenever the account
+    /// transfers or claims UBI
+    #[serde(default)]
+    auto_compound: bool,
+    
+    /// Label the node operator gave the address, for display
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    label: Option<labels::AccountLabel>,
 }
 
 /// Response for account creation
@@ -75,7 +168,7 @@ pub struct CreateAccountResponse {
 }
 
 /// Response for faucet requests
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FaucetResponse {
     /// Success status
     pub success: bool,
@@ -89,275 +182,3048 @@ pub struct FaucetResponse {
     /// Transaction hash (if a transaction was created)
     pub transaction_hash: Option<String>,
     
+    /// Faucet source account that paid the grant, if successful
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    
     /// Error message if unsuccessful
     pub error: Option<String>,
+    
+    /// Identifier of the reason the request was refused, if it was: a tier
+    /// refusal, or `faucet_empty` or `account_not_found` for a failed transfer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<String>,
+    
+    /// Idempotency key supplied with the request, if any
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
+    
+    /// Whether this is the cached result of an earlier request with the same key
+    #[serde(default)]
+    pub duplicate: bool,
 }
 
-/// RPC handler for UBI Chain
-///
-/// This struct provides methods for handling RPC requests
-/// to the UBI Chain node.
-#[derive(Clone)]
-pub struct RpcHandler {
-    /// Reference to the blockchain runtime
-    pub runtime: Runtime,
+impl FaucetResponse {
+    fn failure(error: String) -> Self {
+        FaucetResponse {
+            success: false,
+            amount: None,
+            new_balance: None,
+            transaction_hash: None,
+            source: None,
+            error: Some(error),
+            error_code: None,
+            idempotency_key: None,
+            duplicate: false,
+        }
+    }
     
-    /// Node address (used as the faucet address)
-    pub node_address: Option<String>,
+    fn rejected(error: &FaucetError) -> Self {
+        FaucetResponse {
+            error_code: Some(error.code().to_string()),
+            ..Self::failure(error.to_string())
+        }
+    }
 }
 
-/// Combined server structure holding both HTTP and WebSocket servers
-pub struct CombinedServer {
-    /// HTTP server instance
-    pub http_server: HttpServer,
-    /// WebSocket server instance
-    pub ws_server: WsServer,
+/// Tier, access code and challenge signature of a tiered faucet request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FaucetTierRequest {
+    /// Name of the tier
+    pub tier: String,
+    
+    /// Access code, for tiers that require one
+    #[serde(default)]
+    pub code: Option<String>,
+    
+    /// Signature of the address's faucet challenge by the recipient key
+    #[serde(default)]
+    pub signature: Option<String>,
 }
 
-impl RpcHandler {
-    /// Creates a new RPC handler
-    ///
-    /// # Arguments
-    /// * `runtime` - The blockchain runtime
-    ///
-    /// # Returns
-    /// A new RPC handler instance
-    pub fn new(runtime: Runtime) -> Self {
-        RpcHandler {
-            runtime,
-            node_address: None,
-        }
-    }
+/// Seconds a faucet challenge stays valid
+pub const FAUCET_CHALLENGE_TTL_SECS: u64 = 300;
+
+/// Latest grants included in faucet stats
+pub const FAUCET_STATS_RECENT_GRANTS: usize = 20;
+
+/// A nonce the faucet recipient signs to prove control of the address
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FaucetChallenge {
+    /// The recipient address, lowercase
+    pub address: String,
     
-    /// Sets the node address
-    pub fn set_node_address(&mut self, address: String) {
-        self.node_address = Some(address);
-    }
+    /// Random nonce issued by the node
+    pub nonce: String,
     
-    /// Gets the node address
-    pub fn get_node_address(&self) -> Option<String> {
-        self.node_address.clone()
-    }
+    /// The exact message to sign with `personal_sign`
+    pub message: String,
     
-    /// Starts both HTTP and WebSocket Ethereum-compatible JSON-RPC servers
-    ///
-    /// # Arguments
-    /// * `http_addr` - The address to bind the HTTP server to
-    /// * `ws_addr` - The address to bind the WebSocket server to
-    /// * `chain_id` - Chain ID for EIP-155 compatibility
-    ///
-    /// # Returns
-    /// A result containing the combined server instance or an error
-    pub async fn start_combined_server(&self, http_addr: &str, ws_addr: &str, chain_id: u64) -> std::result::Result<CombinedServer, JsonRpcError> {
-        let http_server = self.start_eth_rpc_server(http_addr, chain_id)?;
-        let ws_server = self.start_eth_ws_server(ws_addr, chain_id).await?;
+    /// When the challenge expires, in seconds since epoch
+    pub expires_at: u64,
+}
 
-        Ok(CombinedServer {
-            http_server,
-            ws_server,
-        })
-    }
+/// Response for transaction submissions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubmitTransactionResponse {
+    /// Success status
+    pub success: bool,
+    
+    /// Hash of the submitted transaction
+    pub transaction_hash: Option<String>,
+    
+    /// Amount the recipient receives, the fee and the total debited from the sender
+    #[serde(default)]
+    pub amounts: Option<TransferAmounts>,
+    
+    /// Error message if unsuccessful
+    pub error: Option<String>,
+    
+    /// Idempotency key supplied with the request, if any
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
+    
+    /// Whether this is the cached result of an earlier request with the same key
+    #[serde(default)]
+    pub duplicate: bool,
+}
 
-    /// Starts an Ethereum-compatible JSON-RPC HTTP server
-    ///
-    /// # Arguments
-    /// * `addr` - The address to bind the server to
-    /// * `chain_id` - Chain ID for EIP-155 compatibility
-    ///
-    /// # Returns
-    /// A result containing the server instance or an error
-    ///
-    /// # Important
-    /// The returned server instance must be stored in a variable that lives for the duration
-    /// of the program to prevent the Tokio runtime from being dropped in an asynchronous context.
-    /// 
-    /// # Example
-    /// ```
-    /// let _eth_server = rpc_handler.start_eth_rpc_server("127.0.0.1:8545", 2030)?;
-    /// ```
-    /// Note the use of `_eth_server` to store the server instance.
-    pub fn start_eth_rpc_server(&self, addr: &str, chain_id: u64) -> std::result::Result<HttpServer, JsonRpcError> {
-        let eth_handler = eth_compat::EthRpcHandler::new(self.clone(), chain_id);
-        
-        // Start the server and return it to be managed by the caller
-        eth_handler.start_server(addr).map_err(|_| JsonRpcError::internal_error())
+impl SubmitTransactionResponse {
+    fn failure(error: String) -> Self {
+        SubmitTransactionResponse {
+            success: false,
+            transaction_hash: None,
+            amounts: None,
+            error: Some(error),
+            idempotency_key: None,
+            duplicate: false,
+        }
     }
+}
 
-    /// Retrieves account information for a given address
-    ///
-    /// # Arguments
-    /// * `address` - The account address to query
-    ///
-    /// # Returns
-    /// AccountInfo structure containing the account's current state
-    ///
-    /// # Example
-    /// ```
-    /// let info = rpc_handler.get_account_info("0x123...".to_string());
-    /// println!("Balance: {}", info.balance);
-    /// ```
-    pub fn get_account_info(&self, address: String) -> AccountInfo {
-        // Preserve the original address format for the response
-        let original_address = address.clone();
-        // Normalize the address for lookup
-        let normalized_address = address.to_lowercase();
-        
-        info!("get_account_info called for address: {}", normalized_address);
+/// Node-level configuration shared with the RPC layer
+///
+/// The block producer settings live in the node binary; the node registers
+/// them with the RpcHandler at startup so they can be reported to clients.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeInfo {
+    /// Chain ID for EIP-155 compatibility
+    pub chain_id: u64,
+    
+    /// Hash of the genesis block
+    pub genesis_hash: String,
+    
+    /// Target time between blocks in milliseconds
+    pub block_time_ms: u64,
+    
+    /// Node software version
+    pub version: String,
+}
 
-        // Query the runtime for account information
-        let balance = self.runtime.get_balance(&normalized_address);
-        let verified = self.runtime.is_account_verified(&normalized_address);
+impl Default for NodeInfo {
+    fn default() -> Self {
+        NodeInfo {
+            chain_id: 2030,
+            genesis_hash: "0x0".to_string(),
+            block_time_ms: 1000,
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+}
 
-        info!("Account info retrieved: address={}, balance={}, verified={}", normalized_address, balance, verified);
+/// Fees charged on transfers
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FeeSchedule {
+    /// Fee charged on top of the transfer amount, in whole percent (rounded down)
+    pub transfer_fee_percent: u64,
+    
+    /// Fee charged on top of the transfer amount, in basis points
+    pub transfer_fee_bps: u64,
+    
+    /// Smallest fee charged on a transfer
+    pub minimum_fee: u64,
+    
+    /// Share of each fee paid into the dividend pool, in basis points; the rest is burned
+    pub fee_pool_share_bps: u64,
+}
 
-        // Return the account info with the ORIGINAL address format to maintain case consistency
-        AccountInfo {
-            address: original_address,
-            balance,
-            verified,
+impl From<&ChainParams> for FeeSchedule {
+    fn from(params: &ChainParams) -> Self {
+        FeeSchedule {
+            transfer_fee_percent: params.fee_bps / 100,
+            transfer_fee_bps: params.fee_bps,
+            minimum_fee: params.minimum_fee,
+            fee_pool_share_bps: params.fee_pool_share_bps,
         }
     }
+}
 
-    /// Creates a new account with the given address
-    ///
-    /// # Arguments
-    /// * `address` - The Ethereum-compatible address for the new account
-    ///
-    /// # Returns
-    /// CreateAccountResponse with success status and account info or error message
-    ///
-    /// # Example
-    /// ```
-    /// let response = rpc_handler.create_account("0x1234567890abcdef1234567890abcdef12345678".to_string());
-    /// if response.success {
-    ///     println!("Account created successfully");
-    /// } else {
-    ///     println!("Error: {}", response.error.unwrap());
-    /// }
-    /// ```
-    pub fn create_account(&self, address: String) -> CreateAccountResponse {
-        let normalized_address = address.to_lowercase();
-        match self.runtime.create_account(&normalized_address) {
-            Ok(()) => {
-                // Account created successfully, now get the account info
-                let account_info = self.get_account_info(normalized_address.clone());
-                
-                CreateAccountResponse {
-                    success: true,
-                    account: Some(account_info),
-                    error: None,
-                }
-            },
-            Err(e) => {
-                CreateAccountResponse {
-                    success: false,
-                    account: None,
-                    error: Some(format!("{}", e)),
-                }
-            }
+/// Chain parameters in effect and changes queued for later blocks
+///
+/// # Example Response
+/// ```json
+/// {
+///     "current": { "fee_bps": 100, "minimum_fee": 0, "fee_pool_share_bps": 10000, "ubi_tokens_per_hour": 1, "block_reward": 100 },
+///     "pending": [{ "param": "fee_bps", "new_value": 50, "activates_at_block": 1200 }],
+///     "block_height": 1042,
+///     "min_activation_delay_blocks": 100,
+///     "change_nonce": 3
+/// }
+/// ```
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FeeScheduleInfo {
+    /// Parameters in effect
+    pub current: ChainParams,
+    
+    /// Queued changes in activation order
+    pub pending: Vec<PendingParamChange>,
+    
+    /// Current block height
+    pub block_height: u64,
+    
+    /// Minimum number of blocks between scheduling a change and its activation
+    pub min_activation_delay_blocks: u64,
+    
+    /// Nonce the next signed admin change request must include
+    pub change_nonce: u64,
+}
+
+/// Response for chain parameter change requests
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScheduleParamChangeResponse {
+    /// Success status
+    pub success: bool,
+    
+    /// The queued change
+    pub change: Option<PendingParamChange>,
+    
+    /// Error message if unsuccessful
+    pub error: Option<String>,
+}
+
+impl ScheduleParamChangeResponse {
+    fn failure(error: String) -> Self {
+        ScheduleParamChangeResponse {
+            success: false,
+            change: None,
+            error: Some(error),
         }
     }
+}
 
-    /// Requests tokens from the faucet
-    /// 
-    /// This function:
-    /// 1. Validates the recipient address
-    /// 2. Transfers tokens from the faucet to the recipient
-    /// 3. Returns the updated balance
-    /// 
-    /// # Arguments
-    /// * `address` - The recipient's address
-    /// * `amount` - Optional amount to request (defaults to 10)
-    /// 
-    /// # Returns
-    /// A response indicating success or failure
-    pub async fn request_from_faucet(&self, address: String, amount: Option<u64>) -> FaucetResponse {
-        let normalized_address = address.to_lowercase();
+/// Response for account export requests
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportAccountResponse {
+    /// Success status
+    pub success: bool,
+    
+    /// The exported account, to be imported on the target chain
+    pub export: Option<AccountExport>,
+    
+    /// Error message if unsuccessful
+    pub error: Option<String>,
+}
 
-        if !is_valid_eth_address(&normalized_address) {
-            return FaucetResponse {
-                success: false,
-                amount: None,
-                new_balance: None,
-                transaction_hash: None,
-                error: Some("Invalid Ethereum address".to_string()),
-            };
+impl ExportAccountResponse {
+    fn failure(error: String) -> Self {
+        ExportAccountResponse {
+            success: false,
+            export: None,
+            error: Some(error),
         }
+    }
+}
 
-        let faucet_address = match &self.node_address {
-            Some(addr) => addr.to_lowercase(),
-            None => "0x1111111111111111111111111111111111111111".to_string(),
-        };
+/// Response for account import requests
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportAccountResponse {
+    /// Success status
+    pub success: bool,
+    
+    /// The imported account
+    pub account: Option<AccountInfo>,
+    
+    /// Error message if unsuccessful
+    pub error: Option<String>,
+}
 
-        let tokens_to_send = amount.unwrap_or(10).min(100);
+impl ImportAccountResponse {
+    fn failure(error: String) -> Self {
+        ImportAccountResponse {
+            success: false,
+            account: None,
+            error: Some(error),
+        }
+    }
+}
 
-        let faucet_balance = self.runtime.get_balance(&faucet_address);
+/// Response for voluntary burns
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BurnResponse {
+    /// Success status
+    pub success: bool,
+    
+    /// The supply event recording the burn
+    pub burn: Option<SupplyEvent>,
+    
+    /// Error message if unsuccessful
+    pub error: Option<String>,
+}
 
-        if faucet_balance < tokens_to_send + 1 {
-            return FaucetResponse {
-                success: false,
-                amount: None,
-                new_balance: None,
-                transaction_hash: None,
-                error: Some(format!("Insufficient balance: {} < {}", faucet_balance, tokens_to_send + 1)),
-            };
+impl BurnResponse {
+    fn failure(error: String) -> Self {
+        BurnResponse {
+            success: false,
+            burn: None,
+            error: Some(error),
         }
+    }
+}
+
+/// Response for a batch transfer
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchTransferResponse {
+    /// Success status
+    pub success: bool,
+    
+    /// Fees and totals the batch paid
+    pub receipt: Option<BatchReceipt>,
+    
+    /// Error message if unsuccessful
+    pub error: Option<String>,
+}
+
+impl BatchTransferResponse {
+    fn failure(error: String) -> Self {
+        BatchTransferResponse {
+            success: false,
+            receipt: None,
+            error: Some(error),
+        }
+    }
+}
+
+/// Response for approvals and transfers drawn on an allowance
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AllowanceResponse {
+    /// Success status
+    pub success: bool,
+    
+    /// Tokens the spender may still draw, fee included
+    pub allowance: Option<u64>,
+    
+    /// Fee charged to the owner by a transfer
+    pub fee: Option<u64>,
+    
+    /// Error message if unsuccessful
+    pub error: Option<String>,
+}
+
+impl AllowanceResponse {
+    fn failure(error: String) -> Self {
+        AllowanceResponse {
+            success: false,
+            allowance: None,
+            fee: None,
+            error: Some(error),
+        }
+    }
+}
+
+/// Response for opting in or out of auto-compounding
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AutoCompoundResponse {
+    /// Success status
+    pub success: bool,
+    
+    /// The account after the change
+    pub account: Option<AccountInfo>,
+    
+    /// Error message if unsuccessful
+    pub error: Option<String>,
+}
+
+impl AutoCompoundResponse {
+    fn failure(error: String) -> Self {
+        AutoCompoundResponse {
+            success: false,
+            account: None,
+            error: Some(error),
+        }
+    }
+}
+
+/// Response for opening and closing payment streams
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StreamResponse {
+    /// Success status
+    pub success: bool,
+    
+    /// The stream as opened, or as settled when it was closed
+    pub stream: Option<PaymentStream>,
+    
+    /// Error message if unsuccessful
+    pub error: Option<String>,
+}
+
+impl StreamResponse {
+    fn failure(error: String) -> Self {
+        StreamResponse {
+            success: false,
+            stream: None,
+            error: Some(error),
+        }
+    }
+}
+
+/// Response for UBI claims
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClaimUbiResponse {
+    /// Success status
+    pub success: bool,
+    
+    /// Tokens claimed and the new balance
+    pub claim: Option<ClaimResult>,
+    
+    /// Error message if unsuccessful
+    pub error: Option<String>,
+}
+
+impl ClaimUbiResponse {
+    fn failure(error: String) -> Self {
+        ClaimUbiResponse {
+            success: false,
+            claim: None,
+            error: Some(error),
+        }
+    }
+}
+
+/// Response for admin housekeeping actions
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AdminActionResponse<T> {
+    /// Success status
+    pub success: bool,
+    
+    /// What the action produced
+    pub result: Option<T>,
+    
+    /// The admin event recorded for the action
+    pub event: Option<admin::AdminEvent>,
+    
+    /// Error message if unsuccessful
+    pub error: Option<String>,
+}
+
+impl<T> AdminActionResponse<T> {
+    fn from_outcome(outcome: Result<(T, admin::AdminEvent), String>) -> Self {
+        match outcome {
+            Ok((result, event)) => AdminActionResponse {
+                success: true,
+                result: Some(result),
+                event: Some(event),
+                error: None,
+            },
+            Err(error) => AdminActionResponse {
+                success: false,
+                result: None,
+                event: None,
+                error: Some(error),
+            },
+        }
+    }
+}
+
+/// Result of an on-demand fee distribution
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FeeDistribution {
+    /// Fees distributed by this action
+    pub distributed: u64,
+    
+    /// Fees distributed since genesis, this action included
+    pub total_distributed: u64,
+}
+
+/// Result of a state snapshot import
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StateImport {
+    /// Number of accounts in the imported state
+    pub accounts: usize,
+    
+    /// State root of the imported accounts, hex encoded
+    pub state_root: String,
+}
+
+/// Full supply audit run by the operations admin
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SupplyAuditReport {
+    /// Chain id of this chain
+    pub chain_id: u64,
+    
+    /// Where the total supply is held
+    pub supply: runtime::SupplyReport,
+    
+    /// Every burn and mint caused by exports and imports, oldest first
+    pub events: Vec<SupplyEvent>,
+}
+
+/// Housekeeping actions run by the operations admin
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AdminEventLog {
+    /// Nonce the next signed admin action must include
+    pub next_nonce: u64,
+    
+    /// Recorded actions, oldest first, one page at a time
+    pub events: Page<admin::AdminEvent>,
+}
+
+/// Supply history for auditing account exports and imports
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SupplyAudit {
+    /// Chain id of this chain
+    pub chain_id: u64,
+    
+    /// Total supply of tokens in circulation
+    pub total_supply: u64,
+    
+    /// Nonce the next signed export request must include
+    pub next_export_nonce: u64,
+    
+    /// Burns and mints caused by exports, imports and voluntary burns, oldest first, one page at a time
+    pub events: Page<SupplyEvent>,
+}
+
+/// Lifetime UBI statistics of the network and, if one was asked for, of an account
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UbiStatsReport {
+    /// UBI minted, fees collected and dividends distributed since genesis
+    pub network: NetworkStats,
+    
+    /// The account asked for, lowercase
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    
+    /// UBI the account has received and claims it has made
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account: Option<UbiStats>,
+}
+
+/// An account statement in the requested format
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum StatementExport {
+    /// The statement with its opening and closing balance
+    Json(Statement),
+    /// The entries as CSV text, header first
+    Csv(String),
+}
+
+/// The current state root and the block height it was read at
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StateRootInfo {
+    /// Root of the account state tree, 0x-prefixed hex
+    pub root: String,
+    
+    /// Block height when the root was read
+    pub block_height: u64,
+}
+
+/// Response for chain information requests
+///
+/// # Example Response
+/// ```json
+/// {
+///     "chain_id": 2030,
+///     "genesis_hash": "0x0",
+///     "node_address": "0x0000000000000000000000000000000000007685",
+///     "ubi_tokens_per_hour": 1,
+///     "fee_schedule": { "transfer_fee_percent": 1, "transfer_fee_bps": 100, "minimum_fee": 0, "fee_pool_share_bps": 10000 },
+///     "block_time_ms": 1000,
+///     "block_reward": 100,
+///     "block_height": 42,
+///     "version": "0.1.0"
+/// }
+/// ```
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChainInfo {
+    /// Chain ID for EIP-155 compatibility
+    pub chain_id: u64,
+    
+    /// Hash of the genesis block
+    pub genesis_hash: String,
+    
+    /// Node address, which also serves as the faucet address
+    pub node_address: Option<String>,
+    
+    /// UBI tokens accrued per hour by verified accounts
+    pub ubi_tokens_per_hour: u64,
+    
+    /// Transfer fee schedule
+    pub fee_schedule: FeeSchedule,
+    
+    /// Target time between blocks in milliseconds
+    pub block_time_ms: u64,
+    
+    /// Tokens credited to the producer of each block
+    pub block_reward: u64,
+    
+    /// Current block height
+    pub block_height: u64,
+    
+    /// Node software version
+    pub version: String,
+}
+
+/// Response for network status requests
+///
+/// # Example Response
+/// ```json
+/// {
+///     "block_height": 42,
+///     "clock_offset_secs": -3,
+///     "fee_bps": 100
+/// }
+/// ```
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NetworkStatus {
+    /// Current block height
+    pub block_height: u64,
+    
+    /// Median offset of peer clocks from the local clock in seconds
+    ///
+    /// Positive values mean the local clock is behind the network; large
+    /// values usually point to an NTP problem.
+    pub clock_offset_secs: i64,
+    
+    /// Transfer fee in effect, in basis points of the amount
+    #[serde(default)]
+    pub fee_bps: u64,
+    
+    /// Outcome of recent checkpoint writes
+    pub checkpoints: CheckpointHealth,
+    
+    /// Bootstrap window opened at genesis; while `active`, verification is relaxed
+    pub bootstrap: Option<runtime::genesis::BootstrapStatus>,
+    
+    /// Whether the node is in maintenance mode, why and who enabled it
+    pub maintenance: MaintenanceStatus,
+    
+    /// Whether the node stalled, and when it last produced or imported a block
+    #[serde(flatten)]
+    pub liveness: runtime::liveness::LivenessStatus,
+    
+    /// What happened to the block producer's transaction announcements and block sends
+    pub channels: runtime::channel_metrics::ChannelMetrics,
+}
+
+/// Answer of the `/ready` probe
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Readiness {
+    /// Whether the node should receive traffic
+    pub ready: bool,
+    
+    /// Whether the block producer stalled
+    pub stalled: bool,
+    
+    /// Whether the node is in maintenance mode
+    pub maintenance: bool,
+    
+    /// Current block height
+    pub block_height: u64,
+    
+    /// Scheduled jobs that keep failing; reported without making the node unready
+    pub failing_jobs: Vec<String>,
+}
+
+/// RPC load metrics: concurrency slots and response cache effectiveness
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RpcMetrics {
+    /// In-flight, queued and rejected requests
+    #[serde(flatten)]
+    pub concurrency: concurrency::ConcurrencyMetrics,
+    
+    /// Hits, misses and invalidations of the response cache
+    pub response_cache: response_cache::ResponseCacheMetrics,
+    
+    /// Admin actions run and state root mismatches found
+    pub admin: admin::AdminMetrics,
+    
+    /// WebSocket subscriptions by type and notification delivery counters
+    pub subscriptions: eth_pubsub::SubscriptionMetrics,
+    
+    /// Runs, failures and skipped overlaps of scheduled jobs
+    pub jobs: scheduler::SchedulerMetrics,
+}
+
+/// Limits of an account as reported by RPC queries
+///
+/// # Example Response
+/// ```json
+/// {
+///     "address": "0x123...",
+///     "max_transaction_amount": 500,
+///     "daily_outflow_limit": null,
+///     "pending": { "max_transaction_amount": 1000, "daily_outflow_limit": null, "activates_at": 1735776000 },
+///     "outflow_last_24h": 202,
+///     "change_nonce": 2
+/// }
+/// ```
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccountLimitsInfo {
+    /// The account address
+    pub address: String,
+    
+    /// Maximum amount of a single transfer, if limited
+    pub max_transaction_amount: Option<u64>,
+    
+    /// Maximum outflow including fees over the last 24 hours, if limited
+    pub daily_outflow_limit: Option<u64>,
+    
+    /// Loosening change waiting to take effect
+    pub pending: Option<PendingLimitsInfo>,
+    
+    /// Outflow including fees over the last 24 hours
+    pub outflow_last_24h: u64,
+    
+    /// Nonce the next signed change request must include
+    pub change_nonce: u64,
+}
+
+/// Limits waiting for their activation time
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PendingLimitsInfo {
+    /// Requested maximum transfer amount
+    pub max_transaction_amount: Option<u64>,
+    
+    /// Requested daily outflow limit
+    pub daily_outflow_limit: Option<u64>,
+    
+    /// UNIX time in seconds at which the limits take effect
+    pub activates_at: u64,
+}
+
+impl AccountLimitsInfo {
+    fn new(address: String, state: &AccountLimitState) -> Self {
+        AccountLimitsInfo {
+            address,
+            max_transaction_amount: state.active.max_transaction_amount,
+            daily_outflow_limit: state.active.daily_outflow_limit,
+            pending: state.pending.map(|pending| PendingLimitsInfo {
+                max_transaction_amount: pending.limits.max_transaction_amount,
+                daily_outflow_limit: pending.limits.daily_outflow_limit,
+                activates_at: pending.activates_at,
+            }),
+            outflow_last_24h: state.window_outflow(),
+            change_nonce: state.change_nonce,
+        }
+    }
+}
+
+/// Response for account limit changes
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetAccountLimitsResponse {
+    /// Success status
+    pub success: bool,
+    
+    /// Time at which loosened limits take effect; None if they applied immediately
+    pub activates_at: Option<u64>,
+    
+    /// The account's limits after the change
+    pub limits: Option<AccountLimitsInfo>,
+    
+    /// Error message if unsuccessful
+    pub error: Option<String>,
+}
+
+impl SetAccountLimitsResponse {
+    fn failure(error: String) -> Self {
+        SetAccountLimitsResponse {
+            success: false,
+            activates_at: None,
+            limits: None,
+            error: Some(error),
+        }
+    }
+}
+
+/// RPC handler for UBI Chain
+///
+/// This struct provides methods for handling RPC requests
+/// to the UBI Chain node.
+#[derive(Clone)]
+pub struct RpcHandler {
+    /// Reference to the blockchain runtime
+    pub runtime: Runtime,
+    
+    /// Node address (used as the faucet address)
+    pub node_address: Option<String>,
+    
+    /// Source of randomness for hashes and subscription ids
+    pub random: RandomSource,
+    
+    /// Node-level configuration reported by ubi_getChainInfo
+    pub node_info: NodeInfo,
+    
+    /// Limits on concurrent requests, shared by every server built from this handler
+    pub concurrency: ConcurrencyLimiter,
+    
+    /// Responses of hot polling methods, shared by every server built from this handler
+    pub response_cache: Arc<ResponseCache>,
+    
+    /// Results of recent faucet requests, by idempotency key
+    pub faucet_requests: Arc<IdempotencyCache<FaucetResponse>>,
+    
+    /// Outstanding faucet challenges, by recipient address
+    pub faucet_challenges: Arc<std::sync::Mutex<HashMap<String, FaucetChallenge>>>,
+    
+    /// Results of recent transaction submissions, by idempotency key
+    pub submitted_transactions: Arc<IdempotencyCache<SubmitTransactionResponse>>,
+    
+    /// Address allowed to schedule chain parameter changes while the network bootstraps
+    pub param_admin: Option<String>,
+    
+    /// Address allowed to export and import accounts
+    pub account_admin: Option<String>,
+    
+    /// Address allowed to run housekeeping actions
+    pub ops_admin: Option<String>,
+    
+    /// Housekeeping actions run on this node
+    pub admin_log: Arc<admin::AdminLog>,
+    
+    /// Handle for changing the node's log levels, if it installed a reloadable logger
+    pub log_handle: Option<logging::LogHandle>,
+    
+    /// Labels of well-known addresses, maintained by the node operator
+    pub labels: labels::LabelRegistry,
+    
+    /// Proof-of-work challenges required by public endpoints, if enabled
+    pub pow: Arc<pow::PowGuard>,
+    
+    /// WebSocket subscriptions, shared so blocks produced through any server reach every subscriber
+    pub eth_subscriptions: Arc<eth_pubsub::SubscriptionManager>,
+    
+    /// Recurring node jobs, such as checkpointing, registered by the node
+    pub scheduler: Arc<scheduler::Scheduler>,
+}
+
+/// How long closing a combined server waits for in-flight requests by default
+pub const DEFAULT_DRAIN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Combined server structure holding both HTTP and WebSocket servers
+pub struct CombinedServer {
+    /// HTTP server instance
+    pub http_server: HttpServer,
+    /// WebSocket server instance
+    pub ws_server: WsServer,
+    /// Address the HTTP server is bound to
+    pub http_addr: SocketAddr,
+    /// Address the WebSocket server is bound to
+    pub ws_addr: SocketAddr,
+    /// Limiter whose slots are held by the requests being handled
+    concurrency: ConcurrencyLimiter,
+    /// How long `close` waits for in-flight requests
+    drain_timeout: std::time::Duration,
+}
+
+impl CombinedServer {
+    /// Sets how long `close` waits for in-flight requests
+    pub fn with_drain_timeout(mut self, drain_timeout: std::time::Duration) -> Self {
+        self.drain_timeout = drain_timeout;
+        self
+    }
+
+    /// Blocks until both servers have exited
+    pub fn wait(self) {
+        let CombinedServer { http_server, ws_server, .. } = self;
+        let http_thread = std::thread::spawn(move || http_server.wait());
+        if let Err(e) = ws_server.wait() {
+            warn!("WebSocket server exited with an error: {}", e);
+        }
+        if http_thread.join().is_err() {
+            warn!("HTTP server exited with a panic");
+        }
+    }
+
+    /// Shuts both servers down gracefully
+    ///
+    /// Requests already received keep running until none holds or waits for a
+    /// concurrency slot, or the drain timeout passes. Both servers close then,
+    /// since closing the HTTP server cancels the requests it is still handling.
+    ///
+    /// # Returns
+    /// Whether both servers exited with every in-flight request finished
+    /// within the drain timeout
+    pub fn close(self) -> bool {
+        let deadline = std::time::Instant::now() + self.drain_timeout;
+        let concurrency = self.concurrency.clone();
+        info!("Closing Ethereum-compatible servers on {} and {}", self.http_addr, self.ws_addr);
+
+        let draining = || {
+            let metrics = concurrency.metrics();
+            metrics.in_flight + metrics.queued > 0
+        };
+        while draining() && std::time::Instant::now() < deadline {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        let abandoned = {
+            let metrics = concurrency.metrics();
+            metrics.in_flight + metrics.queued
+        };
+        self.http_server.close_handle().close();
+        self.ws_server.close_handle().close();
+
+        // Waiting happens on its own thread so a connection that never finishes cannot hold shutdown past the deadline
+        let (done_sender, done_receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            self.wait();
+            let _ = done_sender.send(());
+        });
+        let exited = done_receiver.recv_timeout(deadline.saturating_duration_since(std::time::Instant::now())).is_ok();
+
+        if !exited || abandoned > 0 {
+            warn!("Servers closed after the drain timeout with {} requests still in flight", abandoned);
+            return false;
+        }
+        true
+    }
+}
+
+impl RpcHandler {
+    /// Creates a new RPC handler
+    ///
+    /// # Arguments
+    /// * `runtime` - The blockchain runtime
+    ///
+    /// # Returns
+    /// A new RPC handler instance
+    pub fn new(runtime: Runtime) -> Self {
+        RpcHandler {
+            admin_log: Arc::new(admin::AdminLog::new(runtime.clone())),
+            runtime,
+            node_address: None,
+            random: RandomSource::default(),
+            node_info: NodeInfo::default(),
+            concurrency: ConcurrencyLimiter::default(),
+            response_cache: Arc::new(ResponseCache::default()),
+            faucet_requests: Arc::new(IdempotencyCache::new(IdempotencyConfig::default())),
+            faucet_challenges: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            submitted_transactions: Arc::new(IdempotencyCache::new(IdempotencyConfig::default())),
+            param_admin: None,
+            account_admin: None,
+            ops_admin: None,
+            log_handle: None,
+            labels: labels::LabelRegistry::new(),
+            pow: Arc::new(pow::PowGuard::default()),
+            eth_subscriptions: Arc::new(eth_pubsub::SubscriptionManager::new()),
+            scheduler: Arc::new(scheduler::Scheduler::default()),
+        }
+    }
+    
+    /// Sets the source of randomness used by this handler and the eth handlers built from it
+    pub fn set_random_source(&mut self, random: RandomSource) {
+        self.random = random;
+    }
+    
+    /// Sets the node address
+    pub fn set_node_address(&mut self, address: String) {
+        self.node_address = Some(address);
+    }
+    
+    /// Gets the node address
+    pub fn get_node_address(&self) -> Option<String> {
+        self.node_address.clone()
+    }
+    
+    /// Replaces the concurrency limits
+    ///
+    /// Call before starting servers; handlers cloned earlier keep the old limits.
+    pub fn set_concurrency_config(&mut self, config: ConcurrencyConfig) {
+        self.concurrency = ConcurrencyLimiter::new(config);
+    }
+    
+    /// Replaces the response cache with one whose entries expire after `ttl`
+    ///
+    /// Call before starting servers; a zero TTL disables caching.
+    pub fn set_response_cache_ttl(&mut self, ttl: std::time::Duration) {
+        self.response_cache = Arc::new(ResponseCache::new(ttl));
+    }
+    
+    /// Replaces how long and how many idempotency keys are remembered
+    ///
+    /// Call before starting servers; previously seen keys are forgotten.
+    pub fn set_idempotency_config(&mut self, config: IdempotencyConfig) {
+        self.faucet_requests = Arc::new(IdempotencyCache::new(config));
+        self.submitted_transactions = Arc::new(IdempotencyCache::new(config));
+    }
+    
+    /// Bounds the notifications queued for each slow subscriber and sets what happens beyond that
+    ///
+    /// Call before starting servers; existing subscriptions are dropped.
+    pub fn set_subscription_config(&mut self, queue_capacity: usize, policy: eth_pubsub::SlowConsumerPolicy) {
+        self.eth_subscriptions = Arc::new(eth_pubsub::SubscriptionManager::with_policy(queue_capacity, policy));
+    }
+    
+    /// Registers the node-level configuration
+    pub fn set_node_info(&mut self, node_info: NodeInfo) {
+        self.node_info = node_info;
+    }
+    
+    /// Sets the address allowed to schedule chain parameter changes over RPC
+    ///
+    /// Only meant for bootstrapping a network; without an admin, changes can
+    /// only be scheduled through the runtime.
+    pub fn set_param_admin(&mut self, address: String) {
+        self.param_admin = Some(address.to_lowercase());
+    }
+    
+    /// Sets the address allowed to export and import accounts over RPC
+    pub fn set_account_admin(&mut self, address: String) {
+        self.account_admin = Some(address.to_lowercase());
+    }
+    
+    /// Sets the address allowed to run housekeeping actions over RPC
+    pub fn set_ops_admin(&mut self, address: String) {
+        self.ops_admin = Some(address.to_lowercase());
+    }
+    
+    /// Registers the handle of the installed logger, so the operations admin can change log levels
+    pub fn set_log_handle(&mut self, handle: logging::LogHandle) {
+        self.log_handle = Some(handle);
+    }
+    
+    /// Sets the registry of address labels, loaded from the node's labels file
+    pub fn set_label_registry(&mut self, labels: labels::LabelRegistry) {
+        self.labels = labels;
+    }
+    
+    /// Sets which endpoints require proof of work and how hard it is
+    pub fn set_pow_config(&mut self, config: pow::PowConfig) {
+        self.pow = Arc::new(pow::PowGuard::new(config));
+    }
+    
+    /// Gets the chain parameters and current node state
+    ///
+    /// # Returns
+    /// ChainInfo assembled from the chain parameters in effect, the registered
+    /// NodeInfo and the block producer's current height
+    pub fn get_chain_info(&self) -> ChainInfo {
+        let block_height = self.current_block();
+        let params = self.runtime.chain_params();
+        
+        ChainInfo {
+            chain_id: self.node_info.chain_id,
+            genesis_hash: self.node_info.genesis_hash.clone(),
+            node_address: self.node_address.clone(),
+            ubi_tokens_per_hour: params.ubi_tokens_per_hour,
+            fee_schedule: FeeSchedule::from(&params),
+            block_time_ms: self.node_info.block_time_ms,
+            block_reward: params.block_reward,
+            block_height,
+            version: self.node_info.version.clone(),
+        }
+    }
+    
+    /// Gets the API version and the capabilities of this node
+    ///
+    /// # Arguments
+    /// * `methods` - Names of the methods the calling server has registered
+    ///
+    /// # Returns
+    /// The version, with capabilities read from the node's configuration
+    pub fn get_api_version(&self, methods: &[String]) -> api_version::ApiVersion {
+        let mut methods = methods.to_vec();
+        methods.sort();
+        methods.dedup();
+        
+        let admin_roles: Vec<String> = [("params", &self.param_admin), ("accounts", &self.account_admin), ("ops", &self.ops_admin)]
+            .into_iter()
+            .filter(|(_, admin)| admin.is_some())
+            .map(|(role, _)| role.to_string())
+            .collect();
+        let admin_auth = if admin_roles.is_empty() {
+            api_version::AdminAuthMode::Disabled
+        } else {
+            api_version::AdminAuthMode::Signed
+        };
+        
+        api_version::ApiVersion {
+            version: api_version::API_VERSION.to_string(),
+            capabilities: api_version::ApiCapabilities {
+                method_groups: api_version::method_groups(&methods),
+                methods,
+                pagination: api_version::PAGINATION_STYLE.to_string(),
+                default_page_size: pagination::DEFAULT_PAGE_SIZE,
+                max_page_size: pagination::MAX_PAGE_SIZE,
+                historical_state: self.serves_historical_state(),
+                pubsub: eth_pubsub::SubscriptionType::ALL.iter().map(|kind| kind.name().to_string()).collect(),
+                admin_auth,
+                admin_roles,
+            },
+        }
+    }
+    
+    /// Checks whether state can be read at past blocks
+    ///
+    /// The runtime only holds the state at the head: checkpoints are restored,
+    /// not queried, and balance history only covers statements. The Ethereum
+    /// methods refuse past block tags for state reads accordingly.
+    fn serves_historical_state(&self) -> bool {
+        false
+    }
+    
+    /// Gets the block producer's current height, or 0 if none is registered
+    fn current_block(&self) -> u64 {
+        self.runtime.get_block_producer()
+            .map(|producer| producer.current_block())
+            .unwrap_or(0)
+    }
+    
+    /// Gets the chain parameters in effect and the changes queued for later blocks
+    ///
+    /// # Returns
+    /// FeeScheduleInfo with the current values and pending changes
+    pub fn get_fee_schedule(&self) -> FeeScheduleInfo {
+        let schedule = self.runtime.param_schedule();
+        FeeScheduleInfo {
+            current: schedule.current,
+            pending: schedule.pending,
+            block_height: self.current_block(),
+            min_activation_delay_blocks: runtime::params::MIN_ACTIVATION_DELAY_BLOCKS,
+            change_nonce: schedule.change_nonce,
+        }
+    }
+    
+    /// Schedules a chain parameter change on behalf of the parameter admin
+    ///
+    /// `signature` is a personal-message signature by the parameter admin over
+    /// `signature::param_change_message` with the schedule's current change nonce.
+    ///
+    /// # Arguments
+    /// * `param` - Name of the parameter, e.g. "fee_bps"
+    /// * `new_value` - The value the parameter takes at activation
+    /// * `activates_at_block` - Height of the first block using the new value
+    /// * `signature` - 0x-prefixed hex signature
+    ///
+    /// # Returns
+    /// ScheduleParamChangeResponse with the queued change, or an error message
+    pub fn schedule_param_change(&self, param: &str, new_value: u64, activates_at_block: u64, signature: &str) -> ScheduleParamChangeResponse {
+        let admin = match &self.param_admin {
+            Some(admin) => admin,
+            None => return ScheduleParamChangeResponse::failure("No parameter admin is configured on this node".to_string()),
+        };
+        
+        let param = match ChainParam::from_str(param) {
+            Ok(param) => param,
+            Err(e) => return ScheduleParamChangeResponse::failure(e.to_string()),
+        };
+        let change = PendingParamChange { param, new_value, activates_at_block };
+        
+        let nonce = self.runtime.param_schedule().change_nonce;
+        let message = signature::param_change_message(&change, nonce);
+        match signature::recover_signer(&message, signature) {
+            Ok(signer) if &signer == admin => {},
+            Ok(signer) => {
+                return ScheduleParamChangeResponse::failure(format!(
+                    "Signature is from {}, not the parameter admin", signer
+                ));
+            },
+            Err(e) => return ScheduleParamChangeResponse::failure(e.to_string()),
+        }
+        
+        match self.runtime.schedule_param_change(change) {
+            Ok(()) => ScheduleParamChangeResponse {
+                success: true,
+                change: Some(change),
+                error: None,
+            },
+            Err(e) => ScheduleParamChangeResponse::failure(e.to_string()),
+        }
+    }
+    
+    /// Exports an account, removing it from this chain and burning its balance
+    ///
+    /// The request must be signed by the account admin: `signature` is a
+    /// personal-message signature over `signature::account_export_message`
+    /// with this chain's id and next export nonce.
+    ///
+    /// # Arguments
+    /// * `address` - The account to export
+    /// * `signature` - 0x-prefixed hex signature by the account admin
+    ///
+    /// # Returns
+    /// The exported account state, or the reason the export was refused
+    pub fn export_account(&self, address: &str, signature: &str) -> ExportAccountResponse {
+        if let Some(refusal) = self.maintenance_refusal() {
+            return ExportAccountResponse::failure(refusal);
+        }
+        let chain_id = self.node_info.chain_id;
+        let message = signature::account_export_message(address, chain_id, self.runtime.next_export_nonce());
+        if let Err(e) = self.verify_account_admin(&message, signature) {
+            return ExportAccountResponse::failure(e);
+        }
+        
+        match self.runtime.export_account(address, chain_id) {
+            Ok(export) => {
+                info!(address = export.address.as_str();
+                      "Exported account {} with balance {} (nonce {})", export.address, export.balance, export.export_nonce);
+                ExportAccountResponse {
+                    success: true,
+                    export: Some(export),
+                    error: None,
+                }
+            },
+            Err(e) => ExportAccountResponse::failure(e.to_string()),
+        }
+    }
+    
+    /// Imports an account exported from another chain, minting its balance
+    ///
+    /// The request must be signed by this node's account admin over
+    /// `signature::account_import_message` for the export.
+    ///
+    /// # Arguments
+    /// * `export` - The exported account state
+    /// * `signature` - 0x-prefixed hex signature by the account admin
+    ///
+    /// # Returns
+    /// The imported account, or the reason the import was refused
+    pub fn import_account(&self, export: &AccountExport, signature: &str) -> ImportAccountResponse {
+        if let Some(refusal) = self.maintenance_refusal() {
+            return ImportAccountResponse::failure(refusal);
+        }
+        let message = signature::account_import_message(export);
+        if let Err(e) = self.verify_account_admin(&message, signature) {
+            return ImportAccountResponse::failure(e);
+        }
+        
+        match self.runtime.import_account(export) {
+            Ok(()) => {
+                info!(address = export.address.as_str();
+                      "Imported account {} with balance {} from chain {}", export.address, export.balance, export.source_chain_id);
+                ImportAccountResponse {
+                    success: true,
+                    account: Some(self.get_account_info(export.address.clone())),
+                    error: None,
+                }
+            },
+            Err(e) => ImportAccountResponse::failure(e.to_string()),
+        }
+    }
+    
+    /// Burns tokens from an account, removing them from the total supply
+    ///
+    /// The request must be signed by the account holder: `signature` is a
+    /// personal-message signature over `signature::burn_message` with this
+    /// chain's id and the account's burn count.
+    ///
+    /// # Arguments
+    /// * `address` - The account burning its tokens
+    /// * `amount` - Tokens to burn
+    /// * `signature` - 0x-prefixed hex signature by the account holder
+    ///
+    /// # Returns
+    /// The supply event recording the burn, or the reason it was refused
+    pub fn burn(&self, address: &str, amount: u64, signature: &str) -> BurnResponse {
+        if let Some(refusal) = self.maintenance_refusal() {
+            return BurnResponse::failure(refusal);
+        }
+        let normalized_address = address.to_lowercase();
+        if !is_valid_eth_address(&normalized_address) {
+            return BurnResponse::failure("Invalid Ethereum address".to_string());
+        }
+        
+        let nonce = self.runtime.burn_count(&normalized_address);
+        let message = signature::burn_message(&normalized_address, amount, self.node_info.chain_id, nonce);
+        match signature::recover_signer(&message, signature) {
+            Ok(signer) if signer == normalized_address => {},
+            Ok(signer) => return BurnResponse::failure(format!("Signature is from {}, not the account holder", signer)),
+            Err(e) => return BurnResponse::failure(e.to_string()),
+        }
+        
+        match self.runtime.burn(&normalized_address, amount) {
+            Ok(event) => BurnResponse {
+                success: true,
+                burn: Some(event),
+                error: None,
+            },
+            Err(e) => BurnResponse::failure(e.to_string()),
+        }
+    }
+    
+    /// Pays many recipients from one account, all or nothing
+    ///
+    /// The request must be signed by the sender: `signature` is a
+    /// personal-message signature over `signature::batch_transfer_message`
+    /// with this chain's id and the sender's transaction nonce, which the
+    /// batch uses up.
+    ///
+    /// # Arguments
+    /// * `from` - The sender
+    /// * `legs` - Recipient and amount of each transfer
+    /// * `signature` - 0x-prefixed hex signature by the sender
+    ///
+    /// # Returns
+    /// The fees and totals paid, or the reason the batch was refused
+    pub fn batch_transfer(&self, from: &str, legs: Vec<(String, u64)>, signature: &str) -> BatchTransferResponse {
+        if let Some(refusal) = self.maintenance_refusal() {
+            return BatchTransferResponse::failure(refusal);
+        }
+        let from = from.to_lowercase();
+        if !is_valid_eth_address(&from) {
+            return BatchTransferResponse::failure("Invalid Ethereum address".to_string());
+        }
+        
+        let nonce = self.runtime.get_nonce(&from);
+        let message = signature::batch_transfer_message(&from, &legs, self.node_info.chain_id, nonce);
+        match signature::recover_signer(&message, signature) {
+            Ok(signer) if signer == from => {},
+            Ok(signer) => return BatchTransferResponse::failure(format!("Signature is from {}, not the sender", signer)),
+            Err(e) => return BatchTransferResponse::failure(e.to_string()),
+        }
+        
+        match self.runtime.execute_with_nonce(&from, Some(nonce), || self.runtime.batch_transfer(&from, legs)) {
+            Ok((_, receipt)) => {
+                info!("Batch transfer from {}: {} transfers, {} debited", from, receipt.legs.len(), receipt.total_debited);
+                BatchTransferResponse {
+                    success: true,
+                    receipt: Some(receipt),
+                    error: None,
+                }
+            },
+            Err(e) => BatchTransferResponse::failure(e.to_string()),
+        }
+    }
+    
+    /// Sets how many tokens a spender may transfer out of an account
+    ///
+    /// The request must be signed by the owner: `signature` is a
+    /// personal-message signature over `signature::approve_message` with this
+    /// chain's id and the owner's transaction nonce, which the approval uses up.
+    ///
+    /// # Arguments
+    /// * `owner` - The account the tokens are drawn from
+    /// * `spender` - The address allowed to draw them
+    /// * `amount` - Tokens the spender may draw, fee included; zero withdraws the allowance
+    /// * `signature` - 0x-prefixed hex signature by the owner
+    ///
+    /// # Returns
+    /// The new allowance, or the reason it was refused
+    pub fn approve(&self, owner: &str, spender: &str, amount: u64, signature: &str) -> AllowanceResponse {
+        if let Some(refusal) = self.maintenance_refusal() {
+            return AllowanceResponse::failure(refusal);
+        }
+        let (owner, spender) = (owner.to_lowercase(), spender.to_lowercase());
+        if !is_valid_eth_address(&owner) || !is_valid_eth_address(&spender) {
+            return AllowanceResponse::failure("Invalid Ethereum address".to_string());
+        }
+        
+        let nonce = self.runtime.get_nonce(&owner);
+        let message = signature::approve_message(&owner, &spender, amount, self.node_info.chain_id, nonce);
+        match signature::recover_signer(&message, signature) {
+            Ok(signer) if signer == owner => {},
+            Ok(signer) => return AllowanceResponse::failure(format!("Signature is from {}, not the owner", signer)),
+            Err(e) => return AllowanceResponse::failure(e.to_string()),
+        }
+        
+        match self.runtime.execute_with_nonce(&owner, Some(nonce), || self.runtime.approve(&owner, &spender, amount)) {
+            Ok(_) => AllowanceResponse {
+                success: true,
+                allowance: Some(self.runtime.allowance(&owner, &spender)),
+                fee: None,
+                error: None,
+            },
+            Err(e) => AllowanceResponse::failure(e.to_string()),
+        }
+    }
+    
+    /// Opts an account in or out of auto-compounding its dividends
+    ///
+    /// The request must be signed by the account holder: `signature` is a
+    /// personal-message signature over `signature::auto_compound_message`
+    /// with this chain's id and the account's transaction nonce, which the
+    /// change uses up.
+    ///
+    /// # Arguments
+    /// * `address` - The account
+    /// * `enabled` - Whether dividends are claimed whenever the account transfers or claims UBI
+    /// * `signature` - 0x-prefixed hex signature by the account holder
+    ///
+    /// # Returns
+    /// The account after the change, or the reason it was refused
+    pub fn set_auto_compound(&self, address: &str, enabled: bool, signature: &str) -> AutoCompoundResponse {
+        if let Some(refusal) = self.maintenance_refusal() {
+            return AutoCompoundResponse::failure(refusal);
+        }
+        let normalized_address = address.to_lowercase();
+        if !is_valid_eth_address(&normalized_address) {
+            return AutoCompoundResponse::failure("Invalid Ethereum address".to_string());
+        }
+        
+        let nonce = self.runtime.get_nonce(&normalized_address);
+        let message = signature::auto_compound_message(&normalized_address, enabled, self.node_info.chain_id, nonce);
+        match signature::recover_signer(&message, signature) {
+            Ok(signer) if signer == normalized_address => {},
+            Ok(signer) => return AutoCompoundResponse::failure(format!("Signature is from {}, not the account holder", signer)),
+            Err(e) => return AutoCompoundResponse::failure(e.to_string()),
+        }
+        
+        match self.runtime.execute_with_nonce(&normalized_address, Some(nonce), || self.runtime.set_auto_compound(&normalized_address, enabled)) {
+            Ok(_) => AutoCompoundResponse {
+                success: true,
+                account: Some(self.get_account_info(normalized_address)),
+                error: None,
+            },
+            Err(e) => AutoCompoundResponse::failure(e.to_string()),
+        }
+    }
+    
+    /// Transfers tokens out of an owner's account, drawing on the spender's allowance
+    ///
+    /// The request must be signed by the spender: `signature` is a
+    /// personal-message signature over `signature::transfer_from_message`
+    /// with this chain's id and the spender's transaction nonce.
+    ///
+    /// # Arguments
+    /// * `spender` - The address drawing on its allowance
+    /// * `owner` - The account the tokens are drawn from
+    /// * `recipient` - The account receiving them
+    /// * `amount` - The amount the recipient receives
+    /// * `signature` - 0x-prefixed hex signature by the spender
+    ///
+    /// # Returns
+    /// The fee the owner paid and the allowance left, or the reason the transfer was refused
+    pub fn transfer_from(&self, spender: &str, owner: &str, recipient: &str, amount: u64, signature: &str) -> AllowanceResponse {
+        if let Some(refusal) = self.maintenance_refusal() {
+            return AllowanceResponse::failure(refusal);
+        }
+        let (spender, owner, recipient) = (spender.to_lowercase(), owner.to_lowercase(), recipient.to_lowercase());
+        if ![&spender, &owner, &recipient].iter().all(|address| is_valid_eth_address(address)) {
+            return AllowanceResponse::failure("Invalid Ethereum address".to_string());
+        }
+        
+        let nonce = self.runtime.get_nonce(&spender);
+        let message = signature::transfer_from_message(&spender, &owner, &recipient, amount, self.node_info.chain_id, nonce);
+        match signature::recover_signer(&message, signature) {
+            Ok(signer) if signer == spender => {},
+            Ok(signer) => return AllowanceResponse::failure(format!("Signature is from {}, not the spender", signer)),
+            Err(e) => return AllowanceResponse::failure(e.to_string()),
+        }
+        
+        match self.runtime.execute_with_nonce(&spender, Some(nonce), || self.runtime.transfer_from(&spender, &owner, &recipient, amount)) {
+            Ok((_, fee)) => AllowanceResponse {
+                success: true,
+                allowance: Some(self.runtime.allowance(&owner, &spender)),
+                fee: Some(fee),
+                error: None,
+            },
+            Err(e) => AllowanceResponse::failure(e.to_string()),
+        }
+    }
+    
+    /// Opens a payment stream from one account to another
+    ///
+    /// The request must be signed by the sender: `signature` is a
+    /// personal-message signature over `signature::stream_open_message` with
+    /// this chain's id and the runtime's next stream id.
+    ///
+    /// # Arguments
+    /// * `from` - The paying account
+    /// * `to` - The receiving account
+    /// * `rate_per_hour` - Tokens moved per hour
+    /// * `signature` - 0x-prefixed hex signature by the sender
+    ///
+    /// # Returns
+    /// The new stream, or the reason it was refused
+    pub fn open_stream(&self, from: &str, to: &str, rate_per_hour: u64, signature: &str) -> StreamResponse {
+        if let Some(refusal) = self.maintenance_refusal() {
+            return StreamResponse::failure(refusal);
+        }
+        let (from, to) = (from.to_lowercase(), to.to_lowercase());
+        if !is_valid_eth_address(&from) || !is_valid_eth_address(&to) {
+            return StreamResponse::failure("Invalid Ethereum address".to_string());
+        }
+        
+        let nonce = self.runtime.next_stream_id();
+        let message = signature::stream_open_message(&from, &to, rate_per_hour, self.node_info.chain_id, nonce);
+        match signature::recover_signer(&message, signature) {
+            Ok(signer) if signer == from => {},
+            Ok(signer) => return StreamResponse::failure(format!("Signature is from {}, not the sender", signer)),
+            Err(e) => return StreamResponse::failure(e.to_string()),
+        }
+        
+        match self.runtime.open_stream(&from, &to, rate_per_hour) {
+            Ok(id) => StreamResponse {
+                success: true,
+                stream: self.runtime.get_stream(id),
+                error: None,
+            },
+            Err(e) => StreamResponse::failure(e.to_string()),
+        }
+    }
+    
+    /// Closes a payment stream, settling what it owes one last time
+    ///
+    /// The request must be signed by the sender or the recipient:
+    /// `signature` is a personal-message signature over
+    /// `signature::stream_close_message` with this chain's id.
+    ///
+    /// # Arguments
+    /// * `stream_id` - The stream to close
+    /// * `signature` - 0x-prefixed hex signature by either party
+    ///
+    /// # Returns
+    /// The stream as it was closed, or the reason it was refused
+    pub fn close_stream(&self, stream_id: StreamId, signature: &str) -> StreamResponse {
+        if let Some(refusal) = self.maintenance_refusal() {
+            return StreamResponse::failure(refusal);
+        }
+        let Some(stream) = self.runtime.get_stream(stream_id) else {
+            return StreamResponse::failure(format!("No open stream with id {}", stream_id));
+        };
+        
+        let message = signature::stream_close_message(stream_id, self.node_info.chain_id);
+        match signature::recover_signer(&message, signature) {
+            Ok(signer) if stream.involves(&signer) => {},
+            Ok(signer) => return StreamResponse::failure(format!("Signature is from {}, not a party to the stream", signer)),
+            Err(e) => return StreamResponse::failure(e.to_string()),
+        }
+        
+        match self.runtime.close_stream(stream_id) {
+            Ok(stream) => StreamResponse {
+                success: true,
+                stream: Some(stream),
+                error: None,
+            },
+            Err(e) => StreamResponse::failure(e.to_string()),
+        }
+    }
+    
+    /// Gets the payment streams an account pays or receives
+    ///
+    /// They show what settling them now would move, without settling them.
+    ///
+    /// # Arguments
+    /// * `address` - The account address
+    ///
+    /// # Returns
+    /// The open streams, oldest first, or an error if the address is invalid
+    pub fn get_streams(&self, address: &str) -> Result<Vec<PaymentStream>, String> {
+        if !is_valid_eth_address(address) {
+            return Err("Invalid Ethereum address".to_string());
+        }
+        Ok(self.runtime.get_streams(address))
+    }
+    
+    /// Credits the UBI an account has accrued since its last claim
+    ///
+    /// Anyone may trigger a claim: the tokens only ever go to the account
+    /// itself, so no signature is needed.
+    ///
+    /// # Arguments
+    /// * `address` - The account claiming its UBI
+    ///
+    /// # Returns
+    /// The tokens claimed and the new balance, or the reason the claim failed
+    pub fn claim_ubi(&self, address: &str) -> ClaimUbiResponse {
+        if let Some(refusal) = self.maintenance_refusal() {
+            return ClaimUbiResponse::failure(refusal);
+        }
+        let normalized_address = address.to_lowercase();
+        if !is_valid_eth_address(&normalized_address) {
+            return ClaimUbiResponse::failure("Invalid Ethereum address".to_string());
+        }
+        
+        match self.runtime.claim_ubi(&normalized_address) {
+            Ok(claim) => {
+                if claim.claimed > 0 {
+                    info!(address = normalized_address.as_str(); "Claimed {} UBI tokens for {}", claim.claimed, normalized_address);
+                }
+                ClaimUbiResponse {
+                    success: true,
+                    claim: Some(claim),
+                    error: None,
+                }
+            },
+            Err(e) => ClaimUbiResponse::failure(e.to_string()),
+        }
+    }
+    
+    /// Gets lifetime UBI statistics for dashboards
+    ///
+    /// # Arguments
+    /// * `address` - An account to include, if any; one that doesn't exist has received nothing
+    ///
+    /// # Returns
+    /// The network's totals and the account's, or an error if the address is invalid
+    pub fn get_stats(&self, address: Option<&str>) -> Result<UbiStatsReport, String> {
+        let address = address.map(str::to_lowercase);
+        if address.as_deref().is_some_and(|address| !is_valid_eth_address(address)) {
+            return Err("Invalid Ethereum address".to_string());
+        }
+        Ok(UbiStatsReport {
+            network: self.runtime.get_network_stats(),
+            account: address.as_deref().map(|address| self.runtime.get_ubi_stats(address)),
+            address,
+        })
+    }
+    
+    /// Gets the total supply and the burns and mints caused by account exports and imports
+    ///
+    /// # Arguments
+    /// * `page` - Which page of supply events to return
+    ///
+    /// # Returns
+    /// The audit, or an error if the cursor was not returned by this method
+    pub fn get_supply_audit(&self, page: PageRequest) -> Result<SupplyAudit, String> {
+        // The cursor is the index of the last event returned; events are only ever appended
+        let start = match &page.cursor {
+            Some(cursor) => cursor.parse::<usize>()
+                .map(|last| last + 1)
+                .map_err(|_| format!("Invalid cursor: {}", cursor))?,
+            None => 0,
+        };
+        let page_size = page.page_size();
+        let events = self.runtime.supply_events_from(start, page_size + 1);
+        let indexed = events.into_iter().enumerate().map(|(offset, event)| (start + offset, event));
+        let page = pagination::build_page(indexed, page_size, |(index, _)| index.to_string());
+        
+        Ok(SupplyAudit {
+            chain_id: self.node_info.chain_id,
+            total_supply: self.runtime.get_total_supply(),
+            next_export_nonce: self.runtime.next_export_nonce(),
+            events: Page {
+                items: page.items.into_iter().map(|(_, event)| event).collect(),
+                next_cursor: page.next_cursor,
+            },
+        })
+    }
+    
+    /// Gets the fee ledger entries recorded within a block range
+    ///
+    /// # Arguments
+    /// * `page` - Which page of entries to return
+    /// * `from_block` - Lowest block number to include; the first block if None
+    /// * `to_block` - Highest block number to include; the latest if None
+    ///
+    /// # Returns
+    /// A page of entries, oldest first, or an error if the cursor was not returned by this method
+    pub fn get_fee_ledger(&self, page: PageRequest, from_block: Option<u64>, to_block: Option<u64>) -> Result<Page<runtime::fee_ledger::FeeLedgerEntry>, String> {
+        // The cursor is the sequence number of the last entry returned
+        let start = match &page.cursor {
+            Some(cursor) => cursor.parse::<u64>()
+                .map(|last| last + 1)
+                .map_err(|_| format!("Invalid cursor: {}", cursor))?,
+            None => 0,
+        };
+        let page_size = page.page_size();
+        let entries = self.runtime.fee_ledger_entries(
+            start,
+            from_block.unwrap_or(0),
+            to_block.unwrap_or(u64::MAX),
+            page_size + 1,
+        );
+        Ok(pagination::build_page(entries, page_size, |entry| entry.sequence.to_string()))
+    }
+    
+    /// Exports an account's statement: every change to its balance within a time range
+    ///
+    /// # Arguments
+    /// * `address` - The account address
+    /// * `from_timestamp` - Earliest change to include, in seconds since epoch; the first if None
+    /// * `to_timestamp` - Latest change to include, in seconds since epoch; the latest if None
+    /// * `format` - Whether to return the statement as JSON or its entries as CSV
+    ///
+    /// # Returns
+    /// The statement, or an error if the address is invalid or the range is empty
+    pub fn export_statement(&self, address: &str, from_timestamp: Option<u64>, to_timestamp: Option<u64>, format: StatementFormat) -> Result<StatementExport, String> {
+        let normalized_address = address.to_lowercase();
+        if !is_valid_eth_address(&normalized_address) {
+            return Err(format!("Invalid address: {}", address));
+        }
+        let from_timestamp = from_timestamp.unwrap_or(0);
+        let to_timestamp = to_timestamp.unwrap_or(u64::MAX);
+        if from_timestamp > to_timestamp {
+            return Err(format!("Start {} is after end {}", from_timestamp, to_timestamp));
+        }
+        
+        let statement = self.runtime.account_statement(&normalized_address, from_timestamp, to_timestamp);
+        Ok(match format {
+            StatementFormat::Json => StatementExport::Json(statement),
+            StatementFormat::Csv => StatementExport::Csv(statement.to_csv()),
+        })
+    }
+    
+    /// Gets the current state root, for auditors checking account proofs
+    pub fn get_state_root(&self) -> StateRootInfo {
+        let block_height = self.runtime.get_block_producer()
+            .map(|producer| producer.current_block())
+            .unwrap_or(0);
+        StateRootInfo {
+            root: self.runtime.state_root(),
+            block_height,
+        }
+    }
+    
+    /// Proves an account's current state against the current state root
+    ///
+    /// # Returns
+    /// The root, the account's leaf fields and the sibling path, or an error
+    /// if the address is malformed or no such account exists
+    pub fn get_account_proof(&self, address: &str) -> Result<AccountProof, String> {
+        let normalized_address = address.to_lowercase();
+        if !is_valid_eth_address(&normalized_address) {
+            return Err(format!("Invalid address: {}", address));
+        }
+        self.runtime.account_proof(&normalized_address)
+            .ok_or_else(|| format!("Account not found: {}", normalized_address))
+    }
+    
+    /// Checks an account proof obtained elsewhere against the root it claims
+    ///
+    /// Nothing is read from the node's state: the account state and path in the
+    /// proof are hashed up to a root, which is compared with the claimed one.
+    ///
+    /// # Returns
+    /// Whether the proof holds and the root it leads to, or an error if the
+    /// address or a hash is malformed
+    pub fn verify_proof(&self, proof: &AccountProof) -> Result<ProofCheck, String> {
+        if !is_valid_eth_address(&proof.address.to_lowercase()) {
+            return Err(format!("Invalid address: {}", proof.address));
+        }
+        proof.verify()
+    }
+    
+    /// Checks an account proof, as `verify_proof` does, reporting only whether it holds
+    pub fn verify_account_proof(&self, proof: &AccountProof) -> Result<bool, String> {
+        self.verify_proof(proof).map(|check| check.valid)
+    }
+    
+    /// Compares the state held by two of the node's checkpoints
+    ///
+    /// # Arguments
+    /// * `from_timestamp` - Timestamp of the earlier checkpoint
+    /// * `to_timestamp` - Timestamp of the later checkpoint
+    ///
+    /// # Returns
+    /// The accounts created, removed and changed and the change in total
+    /// supply and fee pool, or an error if either checkpoint is unknown or
+    /// cannot be read
+    pub fn diff_checkpoints(&self, from_timestamp: u64, to_timestamp: u64) -> Result<StateDiff, String> {
+        let checkpoints = self.runtime.list_checkpoints();
+        let find = |timestamp: u64| checkpoints.iter()
+            .find(|checkpoint| checkpoint.timestamp == timestamp)
+            .ok_or_else(|| format!("No checkpoint with timestamp {}", timestamp));
+        let (from, to) = (find(from_timestamp)?, find(to_timestamp)?);
+        self.runtime.diff_checkpoints(from, to).map_err(|e| format!("Failed to read checkpoints: {}", e))
+    }
+    
+    /// Gets the UBI credited to an account, as system transactions
+    ///
+    /// # Arguments
+    /// * `address` - The account address
+    /// * `page` - Which page of mints to return
+    ///
+    /// # Returns
+    /// A page of mints, oldest first, or an error if the cursor was not returned by this method
+    pub fn get_ubi_mints(&self, address: &str, page: PageRequest) -> Result<Page<runtime::ubi_mint::UbiMint>, String> {
+        let mints = self.runtime.ubi_mints_for(address);
+        // The cursor is the hash of the last mint returned
+        let start = match &page.cursor {
+            Some(cursor) => mints.iter()
+                .position(|mint| &mint.hash == cursor)
+                .map(|last| last + 1)
+                .ok_or_else(|| format!("Invalid cursor: {}", cursor))?,
+            None => 0,
+        };
+        Ok(pagination::build_page(mints.into_iter().skip(start), page.page_size(), |mint| mint.hash.clone()))
+    }
+    
+    /// Lists accounts, ordered by address
+    ///
+    /// # Arguments
+    /// * `page` - Which page of accounts to return
+    ///
+    /// # Returns
+    /// Up to `page.page_size()` accounts and the cursor of the next page
+    pub fn list_accounts(&self, page: PageRequest) -> Page<AccountInfo> {
+        let after = page.cursor.as_ref().map(|cursor| cursor.to_lowercase());
+        let page_size = page.page_size();
+        let addresses = self.runtime.account_addresses_after(after.as_deref(), page_size + 1);
+        let accounts = addresses.into_iter().map(|address| {
+            let freeze = self.runtime.account_freeze(&address);
+            let balance = self.runtime.get_balance(&address);
+            let locked = self.runtime.locked_balance(&address);
+            AccountInfo {
+                balance,
+                locked,
+                spendable: balance.saturating_sub(locked),
+                pending_ubi: self.runtime.pending_ubi(&address),
+                verified: self.runtime.is_account_verified(&address),
+                frozen: freeze.is_some(),
+                frozen_reason: freeze.flatten(),
+                auto_compound: self.runtime.is_auto_compound(&address),
+                label: self.labels.get(&address),
+                address,
+            }
+        });
+        
+        pagination::build_page(accounts, page_size, |account| account.address.clone())
+    }
+    
+    /// Distributes the fee pool to token holders now, on behalf of the operations admin
+    ///
+    /// # Arguments
+    /// * `signature` - Signature by the operations admin over
+    ///   `signature::admin_action_message` with the node's next admin nonce
+    ///
+    /// # Returns
+    /// The amount distributed and the recorded admin event
+    pub fn distribute_fees_now(&self, signature: &str) -> AdminActionResponse<FeeDistribution> {
+        let outcome = self.admin_log.perform(
+            admin::AdminAction::DistributeFees,
+            |nonce| self.verify_ops_admin(admin::AdminAction::DistributeFees, &[], nonce, signature),
+            || {
+                let distributed = self.runtime.distribute_fees();
+                let distribution = FeeDistribution {
+                    distributed,
+                    total_distributed: self.runtime.get_total_fees_distributed(),
+                };
+                (distribution, format!("distributed {} in fees", distributed))
+            },
+        );
+        AdminActionResponse::from_outcome(outcome)
+    }
+    
+    /// Runs the full supply audit on behalf of the operations admin
+    ///
+    /// # Arguments
+    /// * `signature` - Signature by the operations admin over the admin action message
+    ///
+    /// # Returns
+    /// Where the supply is held, every supply event and the recorded admin event
+    pub fn audit(&self, signature: &str) -> AdminActionResponse<SupplyAuditReport> {
+        let outcome = self.admin_log.perform(
+            admin::AdminAction::Audit,
+            |nonce| self.verify_ops_admin(admin::AdminAction::Audit, &[], nonce, signature),
+            || {
+                let report = SupplyAuditReport {
+                    chain_id: self.node_info.chain_id,
+                    supply: self.runtime.supply_report(),
+                    events: self.runtime.supply_events(),
+                };
+                let outcome = format!("total supply {}, unaccounted {}, fee ledger discrepancy {}",
+                                      report.supply.total_supply, report.supply.unaccounted, report.supply.fee_ledger_discrepancy);
+                if report.supply.fee_ledger_discrepancy != 0 {
+                    error!("Fee pool {} does not match the fee ledger's net {}",
+                           report.supply.fee_pool, report.supply.fee_ledger_net);
+                }
+                (report, outcome)
+            },
+        );
+        AdminActionResponse::from_outcome(outcome)
+    }
+    
+    /// Rebuilds the state tree from the account store on behalf of the operations admin
+    ///
+    /// A root that differs from the incrementally maintained one is logged as
+    /// an error and counted in the admin metrics.
+    ///
+    /// # Arguments
+    /// * `signature` - Signature by the operations admin over the admin action message
+    ///
+    /// # Returns
+    /// Both roots, whether they match and the recorded admin event
+    pub fn recompute_state_root(&self, signature: &str) -> AdminActionResponse<runtime::StateRootCheck> {
+        let outcome = self.admin_log.perform(
+            admin::AdminAction::RecomputeStateRoot,
+            |nonce| self.verify_ops_admin(admin::AdminAction::RecomputeStateRoot, &[], nonce, signature),
+            || {
+                let check = self.runtime.recompute_state_root();
+                if !check.matches {
+                    error!(incremental_root = check.incremental_root.as_str(), recomputed_root = check.recomputed_root.as_str();
+                           "State root drift: incremental root {} differs from recomputed root {}",
+                           check.incremental_root, check.recomputed_root);
+                    self.admin_log.record_state_root_mismatch(current_time_secs());
+                }
+                let outcome = if check.matches { "roots match" } else { "roots differ" };
+                (check, outcome.to_string())
+            },
+        );
+        AdminActionResponse::from_outcome(outcome)
+    }
+    
+    /// Turns maintenance mode on or off on behalf of the operations admin
+    ///
+    /// While it is on, every state change is refused with the reason and the
+    /// block producer stops draining the pool. Turning it off writes a
+    /// checkpoint if checkpoints are enabled, so the state the operator
+    /// worked on is saved before transactions resume.
+    ///
+    /// # Arguments
+    /// * `enabled` - Whether to turn maintenance mode on
+    /// * `reason` - Why, shown to refused callers
+    /// * `signature` - Signature by the operations admin over the admin action
+    ///   message for `enable_maintenance` or `disable_maintenance`, with the reason
+    ///
+    /// # Returns
+    /// The new maintenance status and the recorded admin event
+    pub fn set_maintenance_mode(&self, enabled: bool, reason: Option<String>, signature: &str) -> AdminActionResponse<MaintenanceStatus> {
+        let action = if enabled {
+            admin::AdminAction::EnableMaintenance
+        } else {
+            admin::AdminAction::DisableMaintenance
+        };
+        let signed_reason = reason.clone().unwrap_or_default();
+        let admin = std::cell::RefCell::new(None);
+        let outcome = self.admin_log.perform(
+            action,
+            |nonce| {
+                let signer = self.verify_ops_admin(action, &[("reason", &signed_reason)], nonce, signature)?;
+                *admin.borrow_mut() = Some(signer.clone());
+                Ok(signer)
+            },
+            || {
+                let status = self.runtime.set_maintenance(enabled, reason, admin.borrow().clone());
+                if status.enabled {
+                    warn!("Maintenance mode enabled: {}", status.refusal());
+                    return (status, "maintenance mode enabled".to_string());
+                }
+                
+                info!("Maintenance mode disabled");
+                if !self.runtime.checkpoint_health().enabled {
+                    return (status, "maintenance mode disabled".to_string());
+                }
+                let outcome = match self.runtime.create_checkpoint(true) {
+                    Ok(checkpoint) => format!("maintenance mode disabled, checkpoint {}", checkpoint.file_path),
+                    Err(e) => {
+                        error!("Failed to write checkpoint after maintenance: {}", e);
+                        format!("maintenance mode disabled, checkpoint failed: {}", e)
+                    },
+                };
+                (status, outcome)
+            },
+        );
+        AdminActionResponse::from_outcome(outcome)
+    }
+    
+    /// Changes the log level of a target on behalf of the operations admin
+    ///
+    /// The change applies to the next log call. It is lost on restart unless
+    /// `persist` is set, which saves all levels to the node's log levels file.
+    ///
+    /// # Arguments
+    /// * `target` - Target prefix such as `rpc::eth_compat`, or `*` for the default level
+    /// * `level` - One of off, error, warn, info, debug or trace
+    /// * `persist` - Whether to save the levels for the next start
+    /// * `signature` - Signature by the operations admin over the admin action
+    ///   message for `set_log_level`, with the target, level and persist flag
+    ///
+    /// # Returns
+    /// The levels after the change and the recorded admin event
+    pub fn set_log_level(&self, target: &str, level: &str, persist: bool, signature: &str) -> AdminActionResponse<logging::LogLevels> {
+        let handle = match &self.log_handle {
+            Some(handle) => handle,
+            None => return AdminActionResponse::from_outcome(Err("Log levels cannot be changed on this node".to_string())),
+        };
+        let level_name = level;
+        let level = match logging::parse_level(level) {
+            Ok(level) => level,
+            Err(e) => return AdminActionResponse::from_outcome(Err(e)),
+        };
+        if persist && handle.levels_file().is_none() {
+            return AdminActionResponse::from_outcome(Err(
+                "No log levels file is configured; start the node with --log-levels-file to persist levels".to_string()
+            ));
+        }
+        
+        let outcome = self.admin_log.perform(
+            admin::AdminAction::SetLogLevel,
+            |nonce| self.verify_ops_admin(
+                admin::AdminAction::SetLogLevel,
+                &[("target", target), ("level", level_name), ("persist", if persist { "true" } else { "false" })],
+                nonce,
+                signature,
+            ),
+            || {
+                let levels = handle.set_level(target, level);
+                warn!(log_target = target; "Log level of {} set to {}", target, level);
+                let outcome = match persist.then(|| handle.persist()) {
+                    None => format!("{} set to {}", target, level),
+                    Some(Ok(path)) => format!("{} set to {}, saved to {}", target, level, path.display()),
+                    Some(Err(e)) => {
+                        error!("Failed to persist log levels: {}", e);
+                        format!("{} set to {}, not saved: {}", target, level, e)
+                    },
+                };
+                (levels, outcome)
+            },
+        );
+        AdminActionResponse::from_outcome(outcome)
+    }
+    
+    /// Sets or replaces the label of an address on behalf of the operations admin
+    ///
+    /// The label is saved to the node's labels file before this returns.
+    ///
+    /// # Arguments
+    /// * `address` - The address to label
+    /// * `label` - Name shown next to the address, at most `labels::MAX_LABEL_CHARS` characters
+    /// * `category` - One of faucet, treasury, exchange, validator, system or other
+    /// * `signature` - Signature by the operations admin over the admin action
+    ///   message for `set_label`, with the lowercase address, trimmed label and category
+    ///
+    /// # Returns
+    /// The new and the replaced label and the recorded admin event
+    pub fn set_label(&self, address: &str, label: &str, category: &str, signature: &str) -> AdminActionResponse<labels::LabelChange> {
+        let address = address.to_lowercase();
+        if !is_valid_eth_address(&address) {
+            return AdminActionResponse::from_outcome(Err(labels::LabelError::InvalidAddress(address).to_string()));
+        }
+        let label = match category.parse().and_then(|category| labels::AccountLabel::new(label, category)) {
+            Ok(label) => label,
+            Err(e) => return AdminActionResponse::from_outcome(Err(e.to_string())),
+        };
+        
+        let outcome = self.admin_log.perform(
+            admin::AdminAction::SetLabel,
+            |nonce| self.verify_ops_admin(
+                admin::AdminAction::SetLabel,
+                &[("address", &address), ("label", &label.label), ("category", &label.category.to_string())],
+                nonce,
+                signature,
+            ),
+            || match self.labels.set(&address, label.clone()) {
+                Ok(previous) => {
+                    info!(address = address.as_str(); "Labelled {} as {} ({})", address, label.label, label.category);
+                    let outcome = format!("{} labelled {} ({})", address, label.label, label.category);
+                    (Ok(labels::LabelChange { address: address.clone(), label: Some(label.clone()), previous }), outcome)
+                },
+                Err(e) => {
+                    error!("Failed to save label of {}: {}", address, e);
+                    (Err(e.to_string()), format!("label of {} not saved: {}", address, e))
+                },
+            },
+        );
+        AdminActionResponse::from_outcome(outcome.and_then(|(change, event)| change.map(|change| (change, event))))
+    }
+    
+    /// Removes the label of an address on behalf of the operations admin
+    ///
+    /// # Arguments
+    /// * `address` - The labelled address
+    /// * `signature` - Signature by the operations admin over the admin action
+    ///   message for `remove_label`, with the lowercase address
+    ///
+    /// # Returns
+    /// The removed label, if the address had one, and the recorded admin event
+    pub fn remove_label(&self, address: &str, signature: &str) -> AdminActionResponse<labels::LabelChange> {
+        let address = address.to_lowercase();
+        if !is_valid_eth_address(&address) {
+            return AdminActionResponse::from_outcome(Err(labels::LabelError::InvalidAddress(address).to_string()));
+        }
+        
+        let outcome = self.admin_log.perform(
+            admin::AdminAction::RemoveLabel,
+            |nonce| self.verify_ops_admin(admin::AdminAction::RemoveLabel, &[("address", &address)], nonce, signature),
+            || match self.labels.remove(&address) {
+                Ok(previous) => {
+                    let outcome = match &previous {
+                        Some(previous) => format!("label {} of {} removed", previous.label, address),
+                        None => format!("{} had no label", address),
+                    };
+                    (Ok(labels::LabelChange { address: address.clone(), label: None, previous }), outcome)
+                },
+                Err(e) => {
+                    error!("Failed to save removal of the label of {}: {}", address, e);
+                    (Err(e.to_string()), format!("label of {} not removed: {}", address, e))
+                },
+            },
+        );
+        AdminActionResponse::from_outcome(outcome.and_then(|(change, event)| change.map(|change| (change, event))))
+    }
+    
+    /// Freezes an account on behalf of the operations admin, holding its
+    /// balance while it is under review
+    ///
+    /// # Arguments
+    /// * `address` - The account to freeze
+    /// * `reason` - Why the account is frozen, shown with its account info
+    /// * `signature` - Signature by the operations admin over the admin action
+    ///   message for `freeze_account`, with the lowercase address and the trimmed reason
+    ///
+    /// # Returns
+    /// The frozen account and the recorded admin event
+    pub fn freeze_account(&self, address: &str, reason: Option<&str>, signature: &str) -> AdminActionResponse<AccountInfo> {
+        let address = address.to_lowercase();
+        let reason = reason.map(str::trim).filter(|reason| !reason.is_empty()).map(str::to_string);
+        
+        let outcome = self.admin_log.perform(
+            admin::AdminAction::FreezeAccount,
+            |nonce| self.verify_ops_admin(
+                admin::AdminAction::FreezeAccount,
+                &[("address", &address), ("reason", reason.as_deref().unwrap_or(""))],
+                nonce,
+                signature,
+            ),
+            || match self.runtime.freeze_account(&address, reason.clone()) {
+                Ok(()) => {
+                    let outcome = match &reason {
+                        Some(reason) => format!("{} frozen: {}", address, reason),
+                        None => format!("{} frozen", address),
+                    };
+                    (Ok(self.get_account_info(address.clone())), outcome)
+                },
+                Err(e) => (Err(e.to_string()), format!("{} not frozen: {}", address, e)),
+            },
+        );
+        AdminActionResponse::from_outcome(outcome.and_then(|(account, event)| account.map(|account| (account, event))))
+    }
+    
+    /// Unfreezes an account on behalf of the operations admin
+    ///
+    /// # Arguments
+    /// * `address` - The frozen account
+    /// * `signature` - Signature by the operations admin over the admin action
+    ///   message for `unfreeze_account`, with the lowercase address
+    ///
+    /// # Returns
+    /// The unfrozen account and the recorded admin event
+    pub fn unfreeze_account(&self, address: &str, signature: &str) -> AdminActionResponse<AccountInfo> {
+        let address = address.to_lowercase();
+        
+        let outcome = self.admin_log.perform(
+            admin::AdminAction::UnfreezeAccount,
+            |nonce| self.verify_ops_admin(admin::AdminAction::UnfreezeAccount, &[("address", &address)], nonce, signature),
+            || match self.runtime.unfreeze_account(&address) {
+                Ok(()) => (Ok(self.get_account_info(address.clone())), format!("{} unfrozen", address)),
+                Err(e) => (Err(e.to_string()), format!("{} not unfrozen: {}", address, e)),
+            },
+        );
+        AdminActionResponse::from_outcome(outcome.and_then(|(account, event)| account.map(|account| (account, event))))
+    }
+
+    /// Gets the status of every scheduled job
+    pub fn get_scheduled_jobs(&self) -> Vec<scheduler::JobStatus> {
+        self.scheduler.jobs()
+    }
+
+    /// Starts a scheduled job now on behalf of the operations admin
+    ///
+    /// The run continues in the background; its outcome shows in
+    /// `get_scheduled_jobs` once it finishes.
+    ///
+    /// # Arguments
+    /// * `name` - The job's name
+    /// * `signature` - Signature by the operations admin over the admin action
+    ///   message for `run_job`, with the job's name
+    ///
+    /// # Returns
+    /// The job's status with the run in progress and the recorded admin event
+    pub fn run_job_now(&self, name: &str, signature: &str) -> AdminActionResponse<scheduler::JobStatus> {
+        match self.scheduler.job(name) {
+            None => return AdminActionResponse::from_outcome(Err(scheduler::RunJobError::UnknownJob(name.to_string()).to_string())),
+            Some(status) if status.running => {
+                return AdminActionResponse::from_outcome(Err(scheduler::RunJobError::AlreadyRunning(name.to_string()).to_string()));
+            },
+            Some(_) => {},
+        }
+
+        let outcome = self.admin_log.perform(
+            admin::AdminAction::RunJob,
+            |nonce| self.verify_ops_admin(admin::AdminAction::RunJob, &[("job", name)], nonce, signature),
+            || match self.scheduler.run_now(name) {
+                Ok(status) => (Ok(status), format!("started job {}", name)),
+                Err(e) => (Err(e.to_string()), format!("job {} not started: {}", name, e)),
+            },
+        );
+        AdminActionResponse::from_outcome(outcome.and_then(|(status, event)| status.map(|status| (status, event))))
+    }
+    
+    /// Captures the whole state as a JSON state snapshot on behalf of the operations admin
+    ///
+    /// # Arguments
+    /// * `signature` - Signature by the operations admin over the admin action
+    ///   message for `export_state`
+    ///
+    /// # Returns
+    /// The snapshot and the recorded admin event
+    pub fn export_state(&self, signature: &str) -> AdminActionResponse<StateSnapshot> {
+        let outcome = self.admin_log.perform(
+            admin::AdminAction::ExportState,
+            |nonce| self.verify_ops_admin(admin::AdminAction::ExportState, &[], nonce, signature),
+            || {
+                let snapshot = self.runtime.state_snapshot();
+                let outcome = format!("exported {} accounts with state root {}", snapshot.accounts.len(), snapshot.state_root);
+                (snapshot, outcome)
+            },
+        );
+        AdminActionResponse::from_outcome(outcome)
+    }
+    
+    /// Replaces the whole state with a state snapshot on behalf of the operations admin
+    ///
+    /// A snapshot with a malformed or repeated address, or whose state root
+    /// does not match its accounts, is refused without a record. The
+    /// signature covers the snapshot's state root and content hash, so it
+    /// cannot import any other state.
+    ///
+    /// # Arguments
+    /// * `snapshot` - The state to restore
+    /// * `signature` - Signature by the operations admin over the admin action
+    ///   message for `import_state`, with the snapshot's state root and content hash
+    ///
+    /// # Returns
+    /// The number of accounts and state root imported and the recorded admin event
+    pub fn import_state(&self, snapshot: &StateSnapshot, signature: &str) -> AdminActionResponse<StateImport> {
+        if let Err(e) = snapshot.to_checkpoint() {
+            return AdminActionResponse::from_outcome(Err(e.to_string()));
+        }
+        let content_hash = snapshot.content_hash();
+        
+        let outcome = self.admin_log.perform(
+            admin::AdminAction::ImportState,
+            |nonce| self.verify_ops_admin(
+                admin::AdminAction::ImportState,
+                &[("state_root", &snapshot.state_root), ("snapshot_hash", &content_hash)],
+                nonce,
+                signature,
+            ),
+            || match self.runtime.import_state_snapshot(snapshot) {
+                Ok(()) => {
+                    let import = StateImport { accounts: snapshot.accounts.len(), state_root: snapshot.state_root.clone() };
+                    let outcome = format!("imported {} accounts with state root {}", import.accounts, import.state_root);
+                    (Ok(import), outcome)
+                },
+                Err(e) => {
+                    error!("Failed to import state snapshot: {}", e);
+                    (Err(e.to_string()), format!("state not imported: {}", e))
+                },
+            },
+        );
+        AdminActionResponse::from_outcome(outcome.and_then(|(import, event)| import.map(|import| (import, event))))
+    }
+    
+    /// Gets the log levels in effect
+    ///
+    /// # Returns
+    /// The default level and the levels of configured targets, or an error if
+    /// this node's logger cannot be inspected
+    pub fn get_log_levels(&self) -> Result<logging::LogLevels, String> {
+        self.log_handle.as_ref()
+            .map(|handle| handle.levels())
+            .ok_or_else(|| "Log levels cannot be inspected on this node".to_string())
+    }
+    
+    /// Gets the message state changes are refused with, if the node is in maintenance mode
+    fn maintenance_refusal(&self) -> Option<String> {
+        let status = self.runtime.maintenance_status();
+        status.enabled.then(|| status.refusal())
+    }
+    
+    /// Lists recorded admin actions, oldest first
+    ///
+    /// # Arguments
+    /// * `page` - Which page of events to return
+    ///
+    /// # Returns
+    /// The next admin nonce and a page of events, or an error if the cursor
+    /// was not returned by this method
+    pub fn get_admin_events(&self, page: PageRequest) -> Result<AdminEventLog, String> {
+        // The cursor is the sequence number of the last event returned
+        let start = match &page.cursor {
+            Some(cursor) => cursor.parse::<u64>()
+                .map(|last| last + 1)
+                .map_err(|_| format!("Invalid cursor: {}", cursor))?,
+            None => 0,
+        };
+        let page_size = page.page_size();
+        let next_nonce = self.admin_log.next_nonce();
+        let events = self.admin_log.events_from(start, page_size + 1);
+        Ok(AdminEventLog {
+            next_nonce,
+            events: pagination::build_page(events, page_size, |event| event.sequence.to_string()),
+        })
+    }
+    
+    /// Checks that an admin action was signed by the operations admin
+    ///
+    /// # Arguments
+    /// * `action` - The action requested
+    /// * `params` - The action's parameters, as `signature::admin_action_message` takes them
+    /// * `nonce` - The node's next admin nonce
+    /// * `signature` - The request signature
+    ///
+    /// # Returns
+    /// The admin's address, or why the signature is not accepted
+    fn verify_ops_admin(&self, action: admin::AdminAction, params: &[(&str, &str)], nonce: u64, signature: &str) -> Result<String, String> {
+        let admin = match &self.ops_admin {
+            Some(admin) => admin,
+            None => return Err("No operations admin is configured on this node".to_string()),
+        };
+        
+        let message = signature::admin_action_message(&action.to_string(), params, self.node_info.chain_id, nonce);
+        match signature::recover_signer(&message, signature) {
+            Ok(signer) if &signer == admin => Ok(signer),
+            Ok(signer) => Err(format!("Signature is from {}, not the operations admin", signer)),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+    
+    /// Checks that a message was signed by the account admin
+    fn verify_account_admin(&self, message: &str, signature: &str) -> Result<(), String> {
+        let admin = match &self.account_admin {
+            Some(admin) => admin,
+            None => return Err("No account admin is configured on this node".to_string()),
+        };
+        
+        match signature::recover_signer(message, signature) {
+            Ok(signer) if &signer == admin => Ok(()),
+            Ok(signer) => Err(format!("Signature is from {}, not the account admin", signer)),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+    
+    /// Gets what happened to a transaction submitted to the block producer
+    ///
+    /// # Arguments
+    /// * `hash` - The transaction hash returned on submission
+    ///
+    /// # Returns
+    /// Pending, included with its block, or failed with the reason; None if
+    /// the hash is unknown or no block producer is registered
+    pub fn get_transaction_status(&self, hash: &str) -> Option<TransactionStatus> {
+        self.runtime.get_block_producer()
+            .and_then(|producer| producer.transaction_status(&hash.to_lowercase()))
+    }
+    
+    /// Gets the lifecycle events recorded for a transaction
+    ///
+    /// # Arguments
+    /// * `hash` - The transaction hash returned on submission
+    ///
+    /// # Returns
+    /// The events in the order they happened, from RPC receipt to the stored
+    /// block; None if the hash is unknown or its trace was dropped
+    pub fn trace_transaction_lifecycle(&self, hash: &str) -> Option<Vec<LifecycleEvent>> {
+        self.runtime.transaction_traces().get(hash)
+    }
+    
+    /// Gets a block in the node's native format
+    ///
+    /// # Arguments
+    /// * `number` - The block number
+    ///
+    /// # Returns
+    /// The block with all its transactions, including failed ones, plus its
+    /// `BlockExtras` fields; None if the block is unknown or no block producer
+    /// is registered
+    pub fn get_block_by_number(&self, number: u64) -> Option<serde_json::Value> {
+        self.runtime.get_block_producer()
+            .and_then(|producer| producer.block_record(number))
+    }
+    
+    /// Gets the UBI-specific figures of a block, kept out of Ethereum-shaped blocks
+    ///
+    /// # Arguments
+    /// * `number` - The block number
+    ///
+    /// # Returns
+    /// The reward paid, fees collected and distributed, UBI minted and
+    /// verification changes recorded when the block was sealed; None if the
+    /// block is unknown or no block producer is registered
+    pub fn get_block_extras(&self, number: u64) -> Option<BlockExtras> {
+        self.runtime.get_block_producer()
+            .and_then(|producer| producer.block_extras(number))
+    }
+    
+    /// Gets the most recent blocks in the node's native format
+    ///
+    /// # Arguments
+    /// * `count` - Number of blocks to return, capped at `MAX_LATEST_BLOCKS`
+    ///
+    /// # Returns
+    /// The blocks, newest first
+    pub fn get_latest_blocks(&self, count: usize) -> Vec<serde_json::Value> {
+        let producer = match self.runtime.get_block_producer() {
+            Some(producer) => producer,
+            None => return Vec::new(),
+        };
+        
+        let head = producer.current_block();
+        (0..count.min(MAX_LATEST_BLOCKS) as u64)
+            .take_while(|offset| *offset < head)
+            .filter_map(|offset| producer.block_record(head - offset))
+            .collect()
+    }
+    
+    /// Gets the overall sync state and the progress of each peer
+    ///
+    /// # Returns
+    /// The block producer's sync status; a node without one reports that it is not syncing
+    pub fn get_sync_status(&self) -> runtime::sync_status::SyncStatus {
+        match self.runtime.get_block_producer() {
+            Some(producer) => producer.sync_status(),
+            None => runtime::sync_status::SyncStatus::default(),
+        }
+    }
+    
+    /// Gets the network status
+    ///
+    /// # Returns
+    /// The block height and measured clock offset, from the registered block producer if any,
+    /// the transfer fee rate, whether the producer stalled, and whether the genesis bootstrap
+    /// window is still open
+    pub fn get_network_status(&self) -> NetworkStatus {
+        let producer = self.runtime.get_block_producer();
+        NetworkStatus {
+            block_height: producer.as_ref().map(|producer| producer.current_block()).unwrap_or(0),
+            clock_offset_secs: producer.as_ref().map(|producer| producer.clock_offset()).unwrap_or(0),
+            fee_bps: self.runtime.fee_basis_points(),
+            checkpoints: self.runtime.checkpoint_health(),
+            bootstrap: self.runtime.bootstrap_status(),
+            maintenance: self.runtime.maintenance_status(),
+            liveness: producer.as_ref().map(|producer| producer.liveness()).unwrap_or_default(),
+            channels: producer.as_ref().map(|producer| producer.channel_metrics()).unwrap_or_default(),
+        }
+    }
+    
+    /// Gets the readiness reported by the `/ready` probe
+    ///
+    /// # Returns
+    /// Ready unless the block producer stalled or the node is in maintenance mode;
+    /// failing scheduled jobs are listed but do not make the node unready
+    pub fn get_readiness(&self) -> Readiness {
+        let status = self.get_network_status();
+        Readiness {
+            ready: !status.liveness.stalled && !status.maintenance.enabled,
+            stalled: status.liveness.stalled,
+            maintenance: status.maintenance.enabled,
+            block_height: status.block_height,
+            failing_jobs: self.scheduler.failing_jobs(),
+        }
+    }
+    
+    /// Gets the concurrency and response cache metrics
+    pub fn get_rpc_metrics(&self) -> RpcMetrics {
+        RpcMetrics {
+            concurrency: self.concurrency.metrics(),
+            response_cache: self.response_cache.metrics(),
+            admin: self.admin_log.metrics(),
+            subscriptions: self.eth_subscriptions.metrics(),
+            jobs: self.scheduler.metrics(),
+        }
+    }
+    
+    /// Gets WebSocket subscription counts, notification delivery counters and the subscriptions that are behind
+    pub fn get_subscription_stats(&self) -> eth_pubsub::SubscriptionStats {
+        self.eth_subscriptions.stats()
+    }
+    
+    /// Gets the spending limits of an account
+    ///
+    /// # Arguments
+    /// * `address` - The account address
+    ///
+    /// # Returns
+    /// The active and pending limits, recent outflow and change nonce
+    pub fn get_account_limits(&self, address: String) -> AccountLimitsInfo {
+        let normalized_address = address.to_lowercase();
+        let state = self.runtime.get_account_limits(&normalized_address);
+        AccountLimitsInfo::new(normalized_address, &state)
+    }
+    
+    /// Changes the spending limits of an account
+    ///
+    /// The request must be signed by the account holder: `signature` is a
+    /// personal-message signature over `signature::limits_message` with the
+    /// account's current change nonce. Tighter limits apply immediately, looser
+    /// ones after a delay.
+    ///
+    /// # Arguments
+    /// * `address` - The account address
+    /// * `limits` - The requested limits
+    /// * `signature` - 0x-prefixed hex signature
+    ///
+    /// # Returns
+    /// SetAccountLimitsResponse with the activation time and resulting limits, or an error message
+    pub fn set_account_limits(&self, address: String, limits: AccountLimits, signature: &str) -> SetAccountLimitsResponse {
+        if let Some(refusal) = self.maintenance_refusal() {
+            return SetAccountLimitsResponse::failure(refusal);
+        }
+        let normalized_address = address.to_lowercase();
+        if !is_valid_eth_address(&normalized_address) {
+            return SetAccountLimitsResponse::failure("Invalid Ethereum address".to_string());
+        }
+        
+        let nonce = self.runtime.get_account_limits(&normalized_address).change_nonce;
+        let message = signature::limits_message(&normalized_address, &limits, nonce);
+        match signature::recover_signer(&message, signature) {
+            Ok(signer) if signer == normalized_address => {},
+            Ok(signer) => {
+                return SetAccountLimitsResponse::failure(format!(
+                    "Signature is from {}, not the account holder", signer
+                ));
+            },
+            Err(e) => return SetAccountLimitsResponse::failure(e.to_string()),
+        }
+        
+        match self.runtime.set_account_limits(&normalized_address, limits) {
+            Ok(activates_at) => {
+                info!(address = normalized_address.as_str(); "Account limits changed to {:?}, effective {:?}", limits, activates_at);
+                SetAccountLimitsResponse {
+                    success: true,
+                    activates_at,
+                    limits: Some(self.get_account_limits(normalized_address)),
+                    error: None,
+                }
+            },
+            Err(e) => SetAccountLimitsResponse::failure(e.to_string()),
+        }
+    }
+    
+    /// Starts both HTTP and WebSocket Ethereum-compatible JSON-RPC servers
+    ///
+    /// # Arguments
+    /// * `http_addr` - The address to bind the HTTP server to
+    /// * `ws_addr` - The address to bind the WebSocket server to
+    /// * `chain_id` - Chain ID for EIP-155 compatibility
+    ///
+    /// # Returns
+    /// A result containing the combined server instance or an error
+    pub async fn start_combined_server(&self, http_addr: &str, ws_addr: &str, chain_id: u64) -> std::result::Result<CombinedServer, JsonRpcError> {
+        let (http_server, http_addr) = self.start_eth_rpc_server(http_addr, chain_id)?;
+        let (ws_server, ws_addr) = self.start_eth_ws_server(ws_addr, chain_id).await?;
+
+        Ok(CombinedServer {
+            http_server,
+            ws_server,
+            http_addr,
+            ws_addr,
+            concurrency: self.concurrency.clone(),
+            drain_timeout: DEFAULT_DRAIN_TIMEOUT,
+        })
+    }
+
+    /// Starts an Ethereum-compatible JSON-RPC HTTP server
+    ///
+    /// # Arguments
+    /// * `addr` - The address to bind the server to; port 0 picks a free port
+    /// * `chain_id` - Chain ID for EIP-155 compatibility
+    ///
+    /// # Returns
+    /// A result containing the server instance and the address it is bound to, or an error
+    ///
+    /// # Important
+    /// The returned server instance must be stored in a variable that lives for the duration
+    /// of the program to prevent the Tokio runtime from being dropped in an asynchronous context.
+    /// 
+    /// # Example
+    /// ```no_run
+    /// # fn main() -> Result<(), jsonrpc_core::Error> {
+    /// # let rpc_handler = ubi_chain_rpc::RpcHandler::new(runtime::Runtime::new());
+    /// let (_eth_server, bound_addr) = rpc_handler.start_eth_rpc_server("127.0.0.1:8545", 2030)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    /// Note the use of `_eth_server` to store the server instance.
+    pub fn start_eth_rpc_server(&self, addr: &str, chain_id: u64) -> std::result::Result<(HttpServer, SocketAddr), JsonRpcError> {
+        let eth_handler = eth_compat::EthRpcHandler::new(self.clone(), chain_id);
+        
+        // Start the server and return it to be managed by the caller
+        let server = eth_handler.start_server(addr).map_err(|e| {
+            error!("Failed to bind Ethereum-compatible JSON-RPC server to {}: {}", addr, e.message);
+            JsonRpcError::internal_error()
+        })?;
+        let bound_addr = *server.address();
+        info!("Ethereum-compatible JSON-RPC server listening on {}", bound_addr);
+        Ok((server, bound_addr))
+    }
+
+    /// Retrieves account information for a given address
+    ///
+    /// # Arguments
+    /// * `address` - The account address to query
+    ///
+    /// # Returns
+    /// AccountInfo structure containing the account's current state
+    ///
+    /// # Example
+    /// ```
+    /// # let rpc_handler = ubi_chain_rpc::RpcHandler::new(runtime::Runtime::new());
+    /// let info = rpc_handler.get_account_info("0x123...".to_string());
+    /// println!("{}", serde_json::to_string(&info).unwrap());
+    /// ```
+    pub fn get_account_info(&self, address: String) -> AccountInfo {
+        // Preserve the original address format for the response
+        let original_address = address.clone();
+        // Normalize the address for lookup
+        let normalized_address = address.to_lowercase();
+        
+        info!("get_account_info called for address: {}", normalized_address);
+
+        // Query the runtime for account information
+        let balance = self.runtime.get_balance(&normalized_address);
+        let verified = self.runtime.is_account_verified(&normalized_address);
+
+        info!("Account info retrieved: address={}, balance={}, verified={}", normalized_address, balance, verified);
+
+        let freeze = self.runtime.account_freeze(&normalized_address);
+        let locked = self.runtime.locked_balance(&normalized_address);
+
+        // Return the account info with the ORIGINAL address format to maintain case consistency
+        AccountInfo {
+            address: original_address,
+            balance,
+            locked,
+            spendable: balance.saturating_sub(locked),
+            pending_ubi: self.runtime.pending_ubi(&normalized_address),
+            verified,
+            frozen: freeze.is_some(),
+            frozen_reason: freeze.flatten(),
+            auto_compound: self.runtime.is_auto_compound(&normalized_address),
+            label: self.labels.get(&normalized_address),
+        }
+    }
+
+    /// Creates a new account with the given address
+    ///
+    /// # Arguments
+    /// * `address` - The Ethereum-compatible address for the new account
+    ///
+    /// # Returns
+    /// CreateAccountResponse with success status and account info or error message
+    ///
+    /// # Example
+    /// ```
+    /// # let rpc_handler = ubi_chain_rpc::RpcHandler::new(runtime::Runtime::new());
+    /// let response = rpc_handler.create_account("0x1234567890abcdef1234567890abcdef12345678".to_string());
+    /// println!("{}", serde_json::to_string(&response).unwrap());
+    /// ```
+    pub fn create_account(&self, address: String) -> CreateAccountResponse {
+        let normalized_address = address.to_lowercase();
+        match self.runtime.create_account(&normalized_address) {
+            Ok(()) => {
+                // Account created successfully, now get the account info
+                let account_info = self.get_account_info(normalized_address.clone());
+                
+                CreateAccountResponse {
+                    success: true,
+                    account: Some(account_info),
+                    error: None,
+                }
+            },
+            Err(e) => {
+                CreateAccountResponse {
+                    success: false,
+                    account: None,
+                    error: Some(format!("{}", e)),
+                }
+            }
+        }
+    }
+
+    /// Creates a new account after checking the request's proof of work
+    ///
+    /// # Arguments
+    /// * `address` - The Ethereum-compatible address for the new account
+    /// * `solution` - The solved challenge, required if account creation requires proof of work
+    ///
+    /// # Returns
+    /// CreateAccountResponse with the account information or an error message
+    pub fn create_account_with_pow(&self, address: String, solution: Option<&pow::PowSolution>) -> CreateAccountResponse {
+        if let Err(e) = self.verify_pow(pow::PowEndpoint::CreateAccount, solution) {
+            return CreateAccountResponse {
+                success: false,
+                account: None,
+                error: Some(e.to_string()),
+            };
+        }
+        self.create_account(address)
+    }
+    
+    /// Issues a proof-of-work challenge for an endpoint
+    ///
+    /// # Arguments
+    /// * `endpoint` - "faucet" or "create-account"
+    ///
+    /// # Returns
+    /// The challenge with its difficulty and expiry, or an error if the
+    /// endpoint does not require proof of work
+    pub fn get_pow_challenge(&self, endpoint: &str) -> Result<pow::PowChallenge, String> {
+        let endpoint: pow::PowEndpoint = endpoint.parse()?;
+        self.pow.issue(endpoint, self.random.random_hash(), current_time_secs()).map_err(|e| e.to_string())
+    }
+    
+    /// Checks a request's proof of work, using its challenge up
+    fn verify_pow(&self, endpoint: pow::PowEndpoint, solution: Option<&pow::PowSolution>) -> Result<(), pow::PowError> {
+        self.pow.verify(endpoint, solution, current_time_secs()).inspect_err(|e| {
+            info!(pow_endpoint = endpoint.to_string().as_str(); "Proof of work refused: {}", e);
+        })
+    }
+
+    /// Requests tokens from the faucet
+    /// 
+    /// This function:
+    /// 1. Validates the recipient address
+    /// 2. Transfers tokens from the faucet to the recipient
+    /// 3. Returns the updated balance
+    /// 
+    /// # Arguments
+    /// * `address` - The recipient's address
+    /// * `amount` - Optional amount to request (defaults to 10)
+    /// 
+    /// # Returns
+    /// A response indicating success or failure
+    pub async fn request_from_faucet(&self, address: String, amount: Option<u64>) -> FaucetResponse {
+        let normalized_address = address.to_lowercase();
+
+        if !is_valid_eth_address(&normalized_address) {
+            return FaucetResponse::failure("Invalid Ethereum address".to_string());
+        }
+
+        self.send_from_faucet(&normalized_address, amount.unwrap_or(10).min(100), None)
+    }
+
+    /// Issues a challenge the recipient must sign to use tiers that require one
+    ///
+    /// A new challenge replaces any earlier one for the address. It is valid
+    /// for `FAUCET_CHALLENGE_TTL_SECS` and for a single faucet request.
+    ///
+    /// # Arguments
+    /// * `address` - The recipient's address
+    ///
+    /// # Returns
+    /// The challenge with the exact message to sign, or an error if the address is invalid
+    pub fn get_faucet_challenge(&self, address: &str) -> Result<FaucetChallenge, String> {
+        let address = address.to_lowercase();
+        if !is_valid_eth_address(&address) {
+            return Err("Invalid Ethereum address".to_string());
+        }
+
+        let nonce = self.random.random_hash();
+        let challenge = FaucetChallenge {
+            message: signature::faucet_challenge_message(&address, &nonce),
+            address: address.clone(),
+            nonce,
+            expires_at: current_time_secs() + FAUCET_CHALLENGE_TTL_SECS,
+        };
+        self.faucet_challenges.lock().unwrap().insert(address, challenge.clone());
+        Ok(challenge)
+    }
+
+    /// Requests a grant from a named faucet tier
+    ///
+    /// The tier fixes the amount and how often the address may receive it. A
+    /// signature, if given, must sign the address's current faucet challenge
+    /// with the recipient key; tiers that require a challenge refuse requests
+    /// without one. The challenge is used up either way.
+    ///
+    /// # Arguments
+    /// * `address` - The recipient's address
+    /// * `request` - The tier, access code and challenge signature
+    ///
+    /// # Returns
+    /// A response indicating success or failure; refusals carry an `error_code`
+    /// of `unknown_tier`, `tier_exhausted`, `invalid_code`, `challenge_required`
+    /// or `bad_signature`
+    pub async fn request_from_faucet_tier(&self, address: String, request: FaucetTierRequest) -> FaucetResponse {
+        let normalized_address = address.to_lowercase();
+
+        if !is_valid_eth_address(&normalized_address) {
+            return FaucetResponse::failure("Invalid Ethereum address".to_string());
+        }
+
+        let challenge_verified = match &request.signature {
+            Some(signature) => match self.verify_faucet_challenge(&normalized_address, signature) {
+                Ok(()) => true,
+                Err(e) => return FaucetResponse::rejected(&e),
+            },
+            None => false,
+        };
+
+        let claim = match self.runtime.claim_faucet_tier(&request.tier, &normalized_address, request.code.as_deref(), challenge_verified) {
+            Ok(claim) => claim,
+            Err(e) => {
+                info!(address = normalized_address.as_str(), tier = request.tier.as_str(); "Faucet tier request refused: {}", e);
+                return FaucetResponse::rejected(&e);
+            },
+        };
+
+        let response = self.send_from_faucet(&normalized_address, claim.amount, Some(&request.tier));
+        if !response.success {
+            self.runtime.cancel_faucet_claim(&request.tier, &normalized_address, &claim);
+        }
+        response
+    }
+
+    /// Checks a signature over the address's faucet challenge, using the challenge up
+    fn verify_faucet_challenge(&self, address: &str, signature: &str) -> Result<(), FaucetError> {
+        let challenge = self.faucet_challenges.lock().unwrap().remove(address)
+            .ok_or_else(|| FaucetError::BadSignature("no challenge was issued for this address".to_string()))?;
+        if current_time_secs() > challenge.expires_at {
+            return Err(FaucetError::BadSignature("the challenge has expired".to_string()));
+        }
+
+        let signer = signature::recover_signer(&challenge.message, signature)
+            .map_err(|e| FaucetError::BadSignature(e.to_string()))?;
+        if signer != address {
+            return Err(FaucetError::BadSignature(format!("signed by {}, not the recipient", signer)));
+        }
+        Ok(())
+    }
+
+    /// Gets the faucet source accounts in the order they are tried
+    ///
+    /// These are the sources the operator configured, or else the faucet
+    /// funded at genesis, or else the node's own account.
+    fn faucet_sources(&self) -> Vec<String> {
+        let sources = self.runtime.faucet_sources();
+        if !sources.is_empty() {
+            return sources;
+        }
+        self.node_address.iter().map(|address| address.to_lowercase()).collect()
+    }
 
-        let recipient_exists = self.runtime.get_balance(&normalized_address) > 0;
+    /// Transfers tokens from the first faucet source able to pay, creating the
+    /// recipient account if needed
+    fn send_from_faucet(&self, normalized_address: &str, tokens_to_send: u64, tier: Option<&str>) -> FaucetResponse {
+        if let Some(refusal) = self.maintenance_refusal() {
+            return FaucetResponse::failure(refusal);
+        }
+        
+        let sources = self.faucet_sources();
+        if sources.is_empty() {
+            return FaucetResponse::failure("No faucet account is configured".to_string());
+        }
+
+        let recipient_exists = self.runtime.get_balance(normalized_address) > 0;
         if !recipient_exists {
-            match self.runtime.create_account(&normalized_address) {
+            match self.runtime.create_account(normalized_address) {
                 Ok(_) => {
-                    info!("Created new account for recipient: {}", normalized_address);
+                    info!(address = normalized_address; "Created new account for recipient: {}", normalized_address);
                 },
                 Err(e) => {
                     if let runtime::AccountError::AlreadyExists = e {
                     } else {
-                        return FaucetResponse {
-                            success: false,
-                            amount: None,
-                            new_balance: None,
-                            transaction_hash: None,
-                            error: Some(format!("Failed to create recipient account: {:?}", e)),
-                        };
+                        return FaucetResponse::failure(format!("Failed to create recipient account: {:?}", e));
                     }
                 }
             }
         }
 
-        // Instead of creating a transaction, directly transfer the tokens
-        match self.runtime.transfer_with_fee(&faucet_address, &normalized_address, tokens_to_send) {
-            Ok(_) => {
-                info!("Faucet transfer successful: {} tokens sent to {}", tokens_to_send, normalized_address);
-                
+        // Instead of creating a transaction, directly transfer the tokens; each
+        // source's limits are enforced by its transfer
+        match self.runtime.grant_from_faucet(&sources, normalized_address, tokens_to_send, tier) {
+            Ok(grant) => {
                 // Get the updated balance
-                let new_balance = self.runtime.get_balance(&normalized_address);
+                let new_balance = self.runtime.get_balance(normalized_address);
                 
                 // Generate a transaction hash for compatibility
-                let mut tx_hash_bytes = [0u8; 32];
-                rand::thread_rng().fill(&mut tx_hash_bytes);
-                let tx_hash = format!("0x{}", hex::encode(tx_hash_bytes));
+                let tx_hash = self.random.random_hash();
+                
+                if !grant.skipped.is_empty() {
+                    warn!(address = normalized_address; "Faucet sources passed over: {}", grant.skipped.join("; "));
+                }
+                info!(tx_hash = tx_hash.as_str(), address = normalized_address, source = grant.source.as_str();
+                      "Faucet transfer successful: {} tokens sent to {}", tokens_to_send, normalized_address);
                 
                 FaucetResponse {
                     success: true,
                     amount: Some(tokens_to_send),
                     new_balance: Some(new_balance),
                     transaction_hash: Some(tx_hash),
+                    source: Some(grant.source),
                     error: None,
+                    error_code: None,
+                    idempotency_key: None,
+                    duplicate: false,
                 }
             },
-            Err(e) => {
-                error!("Faucet transfer failed: {:?}", e);
+            Err(runtime::AccountError::InsufficientBalance { required, available }) => {
+                error!(address = normalized_address; "Faucet sources cannot cover {} tokens", required);
                 FaucetResponse {
-                    success: false,
-                    amount: None,
-                    new_balance: None,
-                    transaction_hash: None,
-                    error: Some(format!("Failed to transfer tokens: {:?}", e)),
+                    error_code: Some("faucet_empty".to_string()),
+                    ..FaucetResponse::failure(format!(
+                        "The faucet is out of funds: {} tokens needed, at most {} available", required, available
+                    ))
                 }
+            },
+            Err(runtime::AccountError::AccountNotFound(address)) => FaucetResponse {
+                error_code: Some("account_not_found".to_string()),
+                ..FaucetResponse::failure(format!("Account {} does not exist", address))
+            },
+            Err(e) => {
+                error!(address = normalized_address; "Faucet transfer failed: {}", e);
+                FaucetResponse::failure(format!("Failed to transfer tokens: {}", e))
             }
         }
     }
 
+    /// Gets the faucet sources in the order they are tried, with each one's
+    /// remaining balance and grants, and the latest grants
+    pub fn get_faucet_stats(&self) -> FaucetStats {
+        self.runtime.faucet_stats(&self.faucet_sources(), FAUCET_STATS_RECENT_GRANTS)
+    }
+
+    /// Requests tokens from the faucet at most once per idempotency key
+    ///
+    /// Without a key this is `request_from_faucet`, or `request_from_faucet_tier`
+    /// if a tier is given. With a key, a repeat of the request within the key's
+    /// TTL returns the first request's response with `duplicate` set, even if
+    /// the first request is still in progress. Like any refusal, a missing or
+    /// invalid proof of work is remembered for the key.
+    ///
+    /// # Arguments
+    /// * `address` - The recipient's address
+    /// * `amount` - Optional amount to request (defaults to 10); ignored for tier requests
+    /// * `idempotency_key` - Optional client-supplied key
+    /// * `tier` - Optional tier request
+    /// * `pow_solution` - The solved challenge, required if the faucet requires proof of work
+    ///
+    /// # Returns
+    /// A response indicating success or failure, echoing the key
+    pub async fn request_from_faucet_idempotent(&self, address: String, amount: Option<u64>, idempotency_key: Option<String>, tier: Option<FaucetTierRequest>, pow_solution: Option<pow::PowSolution>) -> FaucetResponse {
+        // The proof of work is checked only when the request runs, so retries with the same key are absorbed
+        let request = move |address: String, tier: Option<FaucetTierRequest>| async move {
+            if let Err(e) = self.verify_pow(pow::PowEndpoint::Faucet, pow_solution.as_ref()) {
+                return FaucetResponse { error_code: Some(e.code().to_string()), ..FaucetResponse::failure(e.to_string()) };
+            }
+            match tier {
+                Some(tier) => self.request_from_faucet_tier(address, tier).await,
+                None => self.request_from_faucet(address, amount).await,
+            }
+        };
+        let key = match idempotency_key {
+            Some(key) => key,
+            None => return request(address, tier).await,
+        };
+        
+        let fingerprint = format!("{}:{:?}:{:?}", address.to_lowercase(), amount, tier.as_ref().map(|tier| &tier.tier));
+        let mut response = match self.faucet_requests.run(&key, &fingerprint, || request(address, tier)).await {
+            Ok((mut response, duplicate)) => {
+                if duplicate {
+                    info!(idempotency_key = key.as_str(); "Absorbed repeated faucet request");
+                }
+                response.duplicate = duplicate;
+                response
+            },
+            Err(e) => FaucetResponse::failure(e.to_string()),
+        };
+        response.idempotency_key = Some(key);
+        response
+    }
+    
+    /// Submits a transfer to the block producer at most once per idempotency key
+    ///
+    /// # Arguments
+    /// * `from_address` - The sender's address
+    /// * `to_address` - The recipient's address
+    /// * `amount` - The amount to transfer, which the recipient receives; the fee is charged on top
+    /// * `mode` - Whether the amount is what the sender sends or what the recipient
+    ///   must receive; recorded with the transaction
+    /// * `idempotency_key` - Optional client-supplied key; repeats within its TTL
+    ///   return the first submission's response with `duplicate` set
+    ///
+    /// # Returns
+    /// SubmitTransactionResponse with the transaction hash and amounts, or an error message
+    pub async fn submit_transaction(&self, from_address: String, to_address: String, amount: u64, mode: TransferMode, idempotency_key: Option<String>) -> SubmitTransactionResponse {
+        let key = match idempotency_key {
+            Some(key) => key,
+            None => return self.submit_transfer(&from_address, &to_address, amount, mode),
+        };
+        
+        let fingerprint = format!("{}:{}:{}:{}", from_address.to_lowercase(), to_address.to_lowercase(), amount, mode);
+        let submit = || std::future::ready(self.submit_transfer(&from_address, &to_address, amount, mode));
+        let mut response = match self.submitted_transactions.run(&key, &fingerprint, submit).await {
+            Ok((mut response, duplicate)) => {
+                if duplicate {
+                    info!(idempotency_key = key.as_str(); "Absorbed repeated transaction submission");
+                }
+                response.duplicate = duplicate;
+                response
+            },
+            Err(e) => SubmitTransactionResponse::failure(e.to_string()),
+        };
+        response.idempotency_key = Some(key);
+        response
+    }
+    
+    /// Validates a transfer and hands it to the block producer
+    fn submit_transfer(&self, from_address: &str, to_address: &str, amount: u64, mode: TransferMode) -> SubmitTransactionResponse {
+        let received = LifecycleEvent::now(LifecycleStage::ReceivedViaRpc);
+        let from = from_address.to_lowercase();
+        let to = to_address.to_lowercase();
+        
+        if let Some(refusal) = self.maintenance_refusal() {
+            return SubmitTransactionResponse::failure(refusal);
+        }
+        if !is_valid_eth_address(&from) || !is_valid_eth_address(&to) {
+            return SubmitTransactionResponse::failure("Invalid Ethereum address".to_string());
+        }
+        if amount == 0 {
+            return SubmitTransactionResponse::failure("Amount must be greater than zero".to_string());
+        }
+        
+        let amounts = match TransferAmounts::with_fee(mode, amount, self.runtime.transfer_fee_for(&from, amount)) {
+            Some(amounts) => amounts,
+            None => return SubmitTransactionResponse::failure("Amount plus fee exceeds the maximum token amount".to_string()),
+        };
+        // Refuse here what every node would refuse in a block, such as transfers to the UBI mint address
+        if let Err(e) = self.runtime.validate_transaction(&from, &to, amount, mode) {
+            return SubmitTransactionResponse::failure(e.to_string());
+        }
+        let balance = self.runtime.get_balance(&from);
+        if balance < amounts.debited {
+            return SubmitTransactionResponse::failure(match mode {
+                TransferMode::Send => format!("Insufficient balance: {} < {}", balance, amounts.debited),
+                TransferMode::ExactReceive => format!("Insufficient balance: {} < {} ({} to receive plus a fee of {})",
+                                                      balance, amounts.debited, amounts.received, amounts.fee),
+            });
+        }
+        
+        let block_producer = match self.runtime.get_block_producer() {
+            Some(block_producer) => block_producer,
+            None => return SubmitTransactionResponse::failure("Block producer not available".to_string()),
+        };
+        
+        let tx_hash = self.random.random_hash();
+        let transaction = Transaction {
+            hash: tx_hash.clone(),
+            from,
+            to,
+            amount,
+            fee: amounts.fee,
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            mode,
+            nonce: None,
+        };
+        
+        // Submissions refused above have no hash to trace
+        let traces = self.runtime.transaction_traces();
+        traces.record_event(&tx_hash, received);
+        traces.record(&tx_hash, LifecycleStage::Validated);
+        
+        match block_producer.submit_transaction(transaction) {
+            Ok(()) => {
+                info!(tx_hash = tx_hash.as_str(); "Transaction submitted: {} -> {}, amount: {}, fee: {}, mode: {}",
+                      from_address, to_address, amount, amounts.fee, mode);
+                SubmitTransactionResponse {
+                    success: true,
+                    transaction_hash: Some(tx_hash),
+                    amounts: Some(amounts),
+                    error: None,
+                    idempotency_key: None,
+                    duplicate: false,
+                }
+            },
+            Err(e) => SubmitTransactionResponse::failure(format!("Failed to submit transaction: {}", e)),
+        }
+    }
+    
     /// Creates a new faucet transaction
     ///
     /// # Arguments
@@ -374,9 +3240,7 @@ impl RpcHandler {
         let normalized_from_address = from_address.to_lowercase();
         let normalized_to_address = to_address.to_lowercase();
 
-        let mut tx_hash_bytes = [0u8; 32];
-        rand::thread_rng().fill(&mut tx_hash_bytes);
-        let tx_hash = format!("0x{}", hex::encode(tx_hash_bytes));
+        let tx_hash = self.random.random_hash();
 
         let timestamp = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
@@ -390,163 +3254,849 @@ impl RpcHandler {
             amount,
             fee: 1,
             timestamp,
+            mode: TransferMode::Send,
+            nonce: None,
         };
+        self.runtime.transaction_traces().record(&tx_hash, LifecycleStage::ReceivedViaRpc);
 
         block_producer.submit_transaction(transaction)
             .map_err(|_| JsonRpcError::internal_error())?;
 
-        Ok(tx_hash)
+        Ok(tx_hash)
+    }
+
+    /// Starts the Ethereum-compatible WebSocket JSON-RPC server with subscription support
+    ///
+    /// # Arguments
+    /// * `addr` - The address to bind the server to; port 0 picks a free port
+    /// * `chain_id` - Chain ID for EIP-155 compatibility
+    ///
+    /// # Returns
+    /// A result containing the server instance and the address it is bound to, or an error
+    pub async fn start_eth_ws_server(&self, addr: &str, chain_id: u64) -> std::result::Result<(WsServer, SocketAddr), JsonRpcError> {
+        let addr = parse_bind_addr(addr).map_err(|e| {
+            error!("Invalid WebSocket server address: {}", e);
+            JsonRpcError::internal_error()
+        })?;
+        
+        // Each connection carries a session so subscriptions can push to it
+        let mut io = MetaIoHandler::<eth_pubsub::WsSession, _>::with_middleware((
+            logging::CorrelationMiddleware::new(self.random.clone()),
+            response_cache::ResponseCacheMiddleware::new(self.response_cache.clone()),
+            concurrency::ConcurrencyMiddleware::new(self.concurrency.clone()),
+        ));
+        
+        // Create the PubSub handler
+        let pubsub_handler = Arc::new(eth_pubsub::EthPubSubHandler::new(self.clone(), chain_id));
+        
+        // Create the Ethereum handler, notifying subscribers of new blocks and transactions
+        let eth_handler = Arc::new(eth_compat::EthRpcHandler::new_with_subscriptions(
+            self.clone(),
+            chain_id,
+            pubsub_handler.subscription_manager(),
+        ));
+        
+        // Add every Ethereum and UBI Chain method, shared with the HTTP server
+        eth_compat::register_methods(&mut io, eth_handler);
+        
+        // Add WebSocket-specific methods
+        io.add_method_with_meta("eth_subscribe", {
+            let handler = pubsub_handler.clone();
+            move |params, session: eth_pubsub::WsSession| {
+                let handler = handler.clone();
+                Box::pin(async move {
+                    handler.eth_subscribe(params, session).await
+                })
+            }
+        });
+        
+        io.add_method("eth_unsubscribe", {
+            let handler = pubsub_handler.clone();
+            move |params| {
+                let handler = handler.clone();
+                Box::pin(async move {
+                    handler.eth_unsubscribe(params).await
+                })
+            }
+        });
+        
+        // UBI Chain events share the subscription registry; ubi_unsubscribe cancels like eth_unsubscribe
+        io.add_method_with_meta("ubi_subscribe", {
+            let handler = pubsub_handler.clone();
+            move |params, session: eth_pubsub::WsSession| {
+                let handler = handler.clone();
+                Box::pin(async move {
+                    handler.ubi_subscribe(params, session).await
+                })
+            }
+        });
+        
+        io.add_method("ubi_unsubscribe", {
+            let handler = pubsub_handler.clone();
+            move |params| {
+                let handler = handler.clone();
+                Box::pin(async move {
+                    handler.eth_unsubscribe(params).await
+                })
+            }
+        });
+        
+        // Start the WebSocket server
+        WsServerBuilder::with_meta_extractor(io, |context: &jsonrpc_ws_server::RequestContext| {
+            eth_pubsub::WsSession::new(context.sender())
+        })
+            .max_connections(100)
+            .start(&addr)
+            .map(|server| {
+                let bound_addr = *server.addr();
+                info!("Ethereum-compatible WebSocket server listening on {}", bound_addr);
+                (server, bound_addr)
+            })
+            .map_err(|e| {
+                error!("Failed to bind Ethereum-compatible WebSocket server to {}: {}", addr, e);
+                JsonRpcError::internal_error()
+            })
+    }
+
+    // TODO: Implement additional RPC methods:
+    // - submit_transaction(): Submit a new transaction
+    // - claim_ubi(): Process UBI claims
+    // - verify_account(): Submit verification proof
+    // - request_ai_resources(): Request AI compute allocation
+    // - get_verification_status(): Check verification progress
+}
+
+/// Parses requested account limits
+///
+/// # Arguments
+/// * `value` - An object with optional `maxTransactionAmount` and `dailyOutflowLimit`
+///   amounts; a missing or null field means unlimited
+///
+/// # Returns
+/// The parsed limits, or an error message
+pub fn parse_account_limits(value: &serde_json::Value) -> std::result::Result<AccountLimits, String> {
+    if !value.is_object() {
+        return Err("Limits must be an object".to_string());
+    }
+    
+    let max_transaction_amount = parse_optional_amount(value.get("maxTransactionAmount"))
+        .map_err(|e| format!("Invalid maxTransactionAmount: {}", e))?;
+    let daily_outflow_limit = parse_optional_amount(value.get("dailyOutflowLimit"))
+        .map_err(|e| format!("Invalid dailyOutflowLimit: {}", e))?;
+    
+    Ok(AccountLimits { max_transaction_amount, daily_outflow_limit })
+}
+
+/// Parses the transfers of a batch
+///
+/// # Arguments
+/// * `value` - An array of objects with a `to` address and an `amount`
+///
+/// # Returns
+/// Recipient and amount of each transfer, or an error message
+pub fn parse_batch_legs(value: &serde_json::Value) -> std::result::Result<Vec<(String, u64)>, String> {
+    let legs = value.as_array().ok_or_else(|| "Transfers must be an array".to_string())?;
+    legs.iter().enumerate().map(|(index, leg)| {
+        let to = leg.get("to").and_then(|to| to.as_str())
+            .ok_or_else(|| format!("Transfer {} has no to address", index))?;
+        let amount = leg.get("amount").ok_or_else(|| format!("Transfer {} has no amount", index))
+            .and_then(|amount| parse_amount(amount).map_err(|e| format!("Invalid amount in transfer {}: {}", index, e)))?;
+        Ok((to.to_string(), amount))
+    }).collect()
+}
+
+/// Parses the solved proof-of-work challenge attached to a request
+///
+/// # Arguments
+/// * `value` - An object with `challenge` and `solution` strings; missing or null means none
+///
+/// # Returns
+/// The solution, if one was given, or an error message
+pub fn parse_pow_solution(value: Option<&serde_json::Value>) -> std::result::Result<Option<pow::PowSolution>, String> {
+    match value {
+        None | Some(serde_json::Value::Null) => Ok(None),
+        Some(value) => serde_json::from_value(value.clone())
+            .map(Some)
+            .map_err(|e| format!("Invalid proof-of-work parameter: {}", e)),
+    }
+}
+
+/// Parses the mode of a submitted transfer
+///
+/// # Arguments
+/// * `value` - The transfer's `mode` field: "send" or "exact-receive"; missing or null means send
+///
+/// # Returns
+/// The transfer mode, or an error message
+pub fn parse_transfer_mode(value: Option<&serde_json::Value>) -> std::result::Result<TransferMode, String> {
+    match value {
+        None | Some(serde_json::Value::Null) => Ok(TransferMode::Send),
+        Some(serde_json::Value::String(mode)) => mode.parse(),
+        Some(_) => Err("Invalid mode parameter".to_string()),
+    }
+}
+
+/// Parses a server bind address
+///
+/// # Arguments
+/// * `addr` - An address such as `0.0.0.0:8545`; a bare `:port` binds the loopback
+///   interface, and port 0 picks a free port when the server starts
+///
+/// # Returns
+/// The socket address, or an error message
+pub fn parse_bind_addr(addr: &str) -> std::result::Result<SocketAddr, String> {
+    let addr = addr.trim();
+    let full_addr = match addr.strip_prefix(':') {
+        Some(port) => format!("127.0.0.1:{}", port),
+        None => addr.to_string(),
+    };
+    SocketAddr::from_str(&full_addr).map_err(|_| format!("Invalid bind address: {}", addr))
+}
+
+/// Validates an Ethereum address
+/// 
+/// # Arguments
+/// * `address` - The address to validate
+/// 
+/// # Returns
+/// `true` if the address is valid, `false` otherwise
+pub fn is_valid_eth_address(address: &str) -> bool {
+    // Check if the address starts with "0x" and has 42 characters total (0x + 40 hex chars)
+    if !address.starts_with("0x") || address.len() != 42 {
+        return false;
+    }
+    
+    // Check if the address contains only hexadecimal characters after "0x"
+    address[2..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Gets the current time in seconds since epoch
+fn current_time_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use runtime::test_utils::TestRuntimeBuilder;
+
+    #[test]
+    fn test_account_info() {
+        let builder = TestRuntimeBuilder::new().initial_balance(10).verify_new_accounts(true);
+        let policy = builder.new_accounts();
+        let handler = RpcHandler::new(builder.build());
+        
+        // For a non-existent account
+        let info = handler.get_account_info("0x1234567890abcdef1234567890abcdef12345678".to_string());
+        assert_eq!(info.balance, 0); // Non-existent accounts have 0 balance
+        assert!(!info.verified); // Non-existent accounts are not verified
+        
+        // Create an account and check its info
+        let valid_address = "0x1234567890abcdef1234567890abcdef12345678";
+        let _ = handler.create_account(valid_address.to_string());
+        
+        let info = handler.get_account_info(valid_address.to_string());
+        assert_eq!(info.balance, policy.balance);
+        assert_eq!(info.verified, policy.verified);
+    }
+    
+    #[test]
+    fn test_set_account_limits_requires_holder_signature() {
+        let handler = RpcHandler::new(Runtime::new());
+        let (address, _) = signature::tests::sign(9, "");
+        handler.runtime.create_account(&address).unwrap();
+        
+        let limits = parse_account_limits(&serde_json::json!({ "maxTransactionAmount": "500" })).unwrap();
+        let message = signature::limits_message(&address, &limits, 0);
+        
+        // Another key cannot change the limits
+        let (_, forged) = signature::tests::sign(10, &message);
+        let response = handler.set_account_limits(address.clone(), limits, &forged);
+        assert!(!response.success);
+        
+        let (_, signed) = signature::tests::sign(9, &message);
+        let response = handler.set_account_limits(address.clone(), limits, &signed);
+        assert!(response.success, "{:?}", response.error);
+        assert_eq!(response.activates_at, None);
+        
+        let info = handler.get_account_limits(address.clone());
+        assert_eq!(info.max_transaction_amount, Some(500));
+        assert_eq!(info.change_nonce, 1);
+        
+        // The same signature cannot be replayed once the nonce moved on
+        assert!(!handler.set_account_limits(address.clone(), limits, &signed).success);
+        
+        // Raising the limit is signed with the new nonce and held back
+        let raised = AccountLimits { max_transaction_amount: None, daily_outflow_limit: None };
+        let (_, signed) = signature::tests::sign(9, &signature::limits_message(&address, &raised, 1));
+        let response = handler.set_account_limits(address.clone(), raised, &signed);
+        assert!(response.activates_at.is_some());
+        assert_eq!(handler.get_account_limits(address).max_transaction_amount, Some(500));
+    }
+
+    #[test]
+    fn test_burn_requires_holder_signature() {
+        let handler = RpcHandler::new(Runtime::new());
+        let (address, _) = signature::tests::sign(13, "");
+        handler.runtime.create_account(&address).unwrap();
+        handler.runtime.credit_balance(&address, 10).unwrap();
+        let supply = handler.runtime.get_total_supply();
+        let message = signature::burn_message(&address, 5, handler.node_info.chain_id, 0);
+        
+        let (_, forged) = signature::tests::sign(14, &message);
+        assert!(!handler.burn(&address, 5, &forged).success);
+        
+        let (_, signed) = signature::tests::sign(13, &message);
+        let response = handler.burn(&address, 5, &signed);
+        assert!(response.success, "{:?}", response.error);
+        assert_eq!(response.burn.unwrap().total_supply_after, supply - 5);
+        
+        // The same signature cannot be replayed once the burn count moved on
+        assert!(!handler.burn(&address, 5, &signed).success);
+        assert_eq!(handler.runtime.get_total_supply(), supply - 5);
+    }
+
+    #[test]
+    fn test_streams_are_opened_by_the_sender_and_closed_by_either_party() {
+        let handler = RpcHandler::new(Runtime::new());
+        let (sender, _) = signature::tests::sign(13, "");
+        let (recipient, _) = signature::tests::sign(14, "");
+        handler.runtime.create_account(&sender).unwrap();
+        handler.runtime.create_account(&recipient).unwrap();
+        handler.runtime.credit_balance(&sender, 1_000).unwrap();
+        let chain_id = handler.node_info.chain_id;
+        let message = signature::stream_open_message(&sender, &recipient, 60, chain_id, 0);
+        
+        // Only the sender can open a stream out of its account
+        let (_, forged) = signature::tests::sign(14, &message);
+        assert!(!handler.open_stream(&sender, &recipient, 60, &forged).success);
+        
+        let (_, signed) = signature::tests::sign(13, &message);
+        let response = handler.open_stream(&sender, &recipient, 60, &signed);
+        assert!(response.success, "{:?}", response.error);
+        let stream = response.stream.unwrap();
+        assert_eq!((stream.id, stream.rate_per_hour), (0, 60));
+        
+        // The next stream id moved on, so the request cannot be replayed
+        assert!(!handler.open_stream(&sender, &recipient, 60, &signed).success);
+        assert_eq!(handler.get_streams(&recipient).unwrap(), vec![stream.clone()]);
+        assert!(handler.get_streams("not-an-address").is_err());
+        
+        // Either party may close it, but no one else
+        let close = signature::stream_close_message(stream.id, chain_id);
+        let (_, outsider) = signature::tests::sign(15, &close);
+        assert!(!handler.close_stream(stream.id, &outsider).success);
+        let (_, by_recipient) = signature::tests::sign(14, &close);
+        let response = handler.close_stream(stream.id, &by_recipient);
+        assert!(response.success, "{:?}", response.error);
+        assert!(handler.get_streams(&sender).unwrap().is_empty());
+        assert!(!handler.close_stream(stream.id, &by_recipient).success);
+    }
+
+    #[test]
+    fn test_batch_transfer_is_signed_by_the_sender() {
+        let handler = RpcHandler::new(Runtime::new());
+        let (treasury, _) = signature::tests::sign(18, "");
+        let recipients = ["0x4444444444444444444444444444444444444444", "0x5555555555555555555555555555555555555555"];
+        handler.runtime.create_account(&treasury).unwrap();
+        for recipient in recipients {
+            handler.runtime.create_account(recipient).unwrap();
+        }
+        handler.runtime.credit_balance(&treasury, 1_000).unwrap();
+        let chain_id = handler.node_info.chain_id;
+        
+        let legs = parse_batch_legs(&serde_json::json!([
+            {"to": recipients[0], "amount": 100},
+            {"to": recipients[1], "amount": "0xc8"},
+        ])).unwrap();
+        assert_eq!(legs[1], (recipients[1].to_string(), 200));
+        assert!(parse_batch_legs(&serde_json::json!([{"to": recipients[0]}])).is_err());
+        
+        let message = signature::batch_transfer_message(&treasury, &legs, chain_id, 0);
+        let (_, forged) = signature::tests::sign(19, &message);
+        assert!(!handler.batch_transfer(&treasury, legs.clone(), &forged).success);
+        
+        let (_, signed) = signature::tests::sign(18, &message);
+        let response = handler.batch_transfer(&treasury, legs.clone(), &signed);
+        assert!(response.success, "{:?}", response.error);
+        assert_eq!(response.receipt.unwrap().total_amount, 300);
+        assert_eq!(handler.runtime.get_balance(recipients[1]), 200);
+        
+        // The batch used the sender's nonce, so it cannot be replayed
+        assert!(!handler.batch_transfer(&treasury, legs, &signed).success);
+        assert_eq!(handler.runtime.get_balance(recipients[1]), 200);
+    }
+
+    #[test]
+    fn test_transfer_from_is_signed_by_the_spender_within_the_allowance() {
+        let handler = RpcHandler::new(Runtime::new());
+        let (owner, _) = signature::tests::sign(16, "");
+        let (spender, _) = signature::tests::sign(17, "");
+        let recipient = "0x3333333333333333333333333333333333333333";
+        handler.runtime.create_account(&owner).unwrap();
+        handler.runtime.create_account(recipient).unwrap();
+        handler.runtime.credit_balance(&owner, 1_000).unwrap();
+        let chain_id = handler.node_info.chain_id;
+        
+        // Only the owner can approve, and the approval uses up its nonce
+        let approve = signature::approve_message(&owner, &spender, 202, chain_id, 0);
+        let (_, forged) = signature::tests::sign(17, &approve);
+        assert!(!handler.approve(&owner, &spender, 202, &forged).success);
+        let (_, signed) = signature::tests::sign(16, &approve);
+        let response = handler.approve(&owner, &spender, 202, &signed);
+        assert!(response.success, "{:?}", response.error);
+        assert_eq!(response.allowance, Some(202));
+        assert!(!handler.approve(&owner, &spender, 202, &signed).success);
+        
+        // A transfer over the allowance is refused and leaves it untouched
+        let transfer = signature::transfer_from_message(&spender, &owner, recipient, 201, chain_id, 0);
+        let (_, signed) = signature::tests::sign(17, &transfer);
+        assert!(!handler.transfer_from(&spender, &owner, recipient, 201, &signed).success);
+        assert_eq!(handler.runtime.allowance(&owner, &spender), 202);
+        
+        // 200 plus the 2 token fee uses the allowance up exactly
+        let transfer = signature::transfer_from_message(&spender, &owner, recipient, 200, chain_id, 0);
+        let (_, signed) = signature::tests::sign(17, &transfer);
+        let response = handler.transfer_from(&spender, &owner, recipient, 200, &signed);
+        assert!(response.success, "{:?}", response.error);
+        assert_eq!((response.fee, response.allowance), (Some(2), Some(0)));
+        assert_eq!(handler.runtime.get_balance(recipient), 200);
+        assert!(!handler.transfer_from(&spender, &owner, recipient, 200, &signed).success);
+    }
+
+    #[test]
+    fn test_stats_report_the_network_and_an_account() {
+        let handler = RpcHandler::new(Runtime::new());
+        let (alice, bob) = ("0x1111111111111111111111111111111111111111", "0x2222222222222222222222222222222222222222");
+        handler.runtime.create_account(alice).unwrap();
+        handler.runtime.create_account(bob).unwrap();
+        handler.runtime.credit_balance(alice, 1_000).unwrap();
+        handler.runtime.transfer_with_fee(alice, bob, 500).unwrap();
+        
+        let report = handler.get_stats(None).unwrap();
+        assert_eq!(report.network.total_fees_collected, handler.runtime.transfer_fee_for(alice, 500));
+        assert!(report.account.is_none());
+        let report = handler.get_stats(Some(alice)).unwrap();
+        assert_eq!((report.address.as_deref(), report.account), (Some(alice), Some(UbiStats::default())));
+        assert!(handler.get_stats(Some("0x123")).is_err());
+    }
+
+    #[test]
+    fn test_auto_compound_is_set_by_the_account_holder() {
+        let handler = RpcHandler::new(Runtime::new());
+        let (address, _) = signature::tests::sign(24, "");
+        handler.runtime.create_account(&address).unwrap();
+        let chain_id = handler.node_info.chain_id;
+        assert!(!handler.get_account_info(address.clone()).auto_compound);
+        
+        let message = signature::auto_compound_message(&address, true, chain_id, 0);
+        let (_, forged) = signature::tests::sign(23, &message);
+        assert!(!handler.set_auto_compound(&address, true, &forged).success);
+        let (_, signed) = signature::tests::sign(24, &message);
+        assert!(!handler.set_auto_compound(&address, false, &signed).success);
+        let response = handler.set_auto_compound(&address, true, &signed);
+        assert!(response.success, "{:?}", response.error);
+        assert!(response.account.unwrap().auto_compound);
+        assert!(handler.runtime.is_auto_compound(&address));
+        
+        // The change used up the nonce, so it cannot be replayed
+        assert_eq!(handler.runtime.get_nonce(&address), 1);
+        assert!(!handler.set_auto_compound(&address, true, &signed).success);
+    }
+
+    #[test]
+    fn test_schedule_param_change_requires_admin_signature() {
+        let mut handler = RpcHandler::new(Runtime::new());
+        let (admin, _) = signature::tests::sign(11, "");
+        let activation = runtime::params::MIN_ACTIVATION_DELAY_BLOCKS;
+        let change = PendingParamChange { param: ChainParam::FeeBps, new_value: 250, activates_at_block: activation };
+        let (_, signed) = signature::tests::sign(11, &signature::param_change_message(&change, 0));
+
+        // Nodes without an admin refuse every request
+        assert!(!handler.schedule_param_change("fee_bps", 250, activation, &signed).success);
+
+        handler.set_param_admin(admin);
+        let (_, forged) = signature::tests::sign(12, &signature::param_change_message(&change, 0));
+        assert!(!handler.schedule_param_change("fee_bps", 250, activation, &forged).success);
+        assert!(!handler.schedule_param_change("fee_bps", 251, activation, &signed).success);
+
+        let response = handler.schedule_param_change("fee_bps", 250, activation, &signed);
+        assert!(response.success, "{:?}", response.error);
+
+        // The fee schedule lists the pending change next to the current value
+        let schedule = handler.get_fee_schedule();
+        assert_eq!(schedule.current.fee_bps, runtime::TRANSFER_FEE_PERCENT * 100);
+        assert_eq!(schedule.pending, vec![change]);
+        assert_eq!(schedule.change_nonce, 1);
+
+        // Replaying the signature fails once the nonce moved on
+        assert!(!handler.schedule_param_change("fee_bps", 250, activation, &signed).success);
+
+        handler.runtime.apply_param_changes(activation);
+        assert_eq!(handler.get_chain_info().fee_schedule.transfer_fee_bps, 250);
+        assert_eq!(handler.get_network_status().fee_bps, 250);
+        assert!(handler.get_fee_schedule().pending.is_empty());
     }
 
-    /// Starts the Ethereum-compatible WebSocket JSON-RPC server with subscription support
-    ///
-    /// # Arguments
-    /// * `addr` - The address to bind the server to
-    /// * `chain_id` - Chain ID for EIP-155 compatibility
-    ///
-    /// # Returns
-    /// A result containing the server instance or an error
-    pub async fn start_eth_ws_server(&self, addr: &str, chain_id: u64) -> std::result::Result<WsServer, JsonRpcError> {
-        let addr = SocketAddr::from_str(addr)
-            .map_err(|_| JsonRpcError::internal_error())?;
-        
-        // Create a standard IoHandler for WebSocket
-        let mut io = IoHandler::new();
-        
-        // Create the PubSub handler
-        let pubsub_handler = Arc::new(eth_pubsub::EthPubSubHandler::new(self.clone(), chain_id));
+    #[test]
+    fn test_account_export_and_import_require_admin_signatures() {
+        let address = "0x6666666666666666666666666666666666666666";
+        let (admin, _) = signature::tests::sign(13, "");
+        let mut source = RpcHandler::new(Runtime::new());
+        let mut target = RpcHandler::new(Runtime::new());
+        source.runtime.create_account(address).unwrap();
+        source.runtime.credit_balance(address, 700).unwrap();
         
-        // Create the Ethereum handler
-        let eth_handler = Arc::new(eth_compat::EthRpcHandler::new(self.clone(), chain_id));
+        let chain_id = source.node_info.chain_id;
+        let (_, export_signed) = signature::tests::sign(13, &signature::account_export_message(address, chain_id, 0));
+        assert!(!source.export_account(address, &export_signed).success);
         
-        // Add standard methods
-        io.add_method("eth_getBalance", {
-            let handler = eth_handler.clone();
-            move |params| handler.eth_get_balance(params)
-        });
+        source.set_account_admin(admin.clone());
+        let (_, forged) = signature::tests::sign(14, &signature::account_export_message(address, chain_id, 0));
+        assert!(!source.export_account(address, &forged).success);
         
-        io.add_method("eth_sendTransaction", {
-            let handler = eth_handler.clone();
-            move |params| handler.eth_send_transaction(params)
-        });
+        let response = source.export_account(address, &export_signed);
+        assert!(response.success, "{:?}", response.error);
+        let export = response.export.unwrap();
+        assert_eq!(export.balance, 700);
+        assert_eq!(source.get_supply_audit(PageRequest::default()).unwrap().total_supply, 0);
+        assert_eq!(source.get_supply_audit(PageRequest::default()).unwrap().next_export_nonce, 1);
         
-        io.add_method("eth_getTransactionCount", {
-            let handler = eth_handler.clone();
-            move |params| handler.eth_get_transaction_count(params)
-        });
+        target.set_account_admin(admin);
+        let (_, import_signed) = signature::tests::sign(13, &signature::account_import_message(&export));
+        let tampered = AccountExport { balance: 7_000, ..export.clone() };
+        assert!(!target.import_account(&tampered, &import_signed).success);
         
-        io.add_method("eth_chainId", {
-            let handler = eth_handler.clone();
-            move |params| handler.eth_chain_id(params)
-        });
+        let response = target.import_account(&export, &import_signed);
+        assert!(response.success, "{:?}", response.error);
+        assert_eq!(response.account.unwrap().balance, 700);
         
-        io.add_method("eth_blockNumber", {
-            let handler = eth_handler.clone();
-            move |params| handler.eth_block_number(params)
-        });
+        // The same export cannot be imported twice
+        assert!(!target.import_account(&export, &import_signed).success);
+        let audit = target.get_supply_audit(PageRequest::default()).unwrap();
+        assert_eq!(audit.total_supply, 700);
+        assert_eq!(audit.events.items.len(), 1);
+    }
+
+    #[test]
+    fn test_admin_actions_are_signed_and_recorded() {
+        let address = "0x7777777777777777777777777777777777777777";
+        let (admin, _) = signature::tests::sign(15, "");
+        let mut handler = RpcHandler::new(Runtime::new());
+        handler.runtime.create_account(address).unwrap();
+        handler.runtime.credit_balance(address, 500).unwrap();
+        let chain_id = handler.node_info.chain_id;
+        let sign = |action: admin::AdminAction, nonce: u64| {
+            signature::tests::sign(15, &signature::admin_action_message(&action.to_string(), &[], chain_id, nonce)).1
+        };
         
-        io.add_method("eth_getBlockByNumber", {
-            let handler = eth_handler.clone();
-            move |params| handler.eth_get_block_by_number(params)
-        });
+        assert!(!handler.audit(&sign(admin::AdminAction::Audit, 0)).success);
+        handler.set_ops_admin(admin.clone());
         
-        io.add_method("eth_getBlockByHash", {
-            let handler = eth_handler.clone();
-            move |params| handler.eth_get_block_by_hash(params)
-        });
+        let response = handler.audit(&sign(admin::AdminAction::Audit, 0));
+        assert!(response.success, "{:?}", response.error);
+        assert_eq!(response.result.unwrap().supply.account_balances, 500);
         
-        io.add_method("eth_accounts", {
-            let handler = eth_handler.clone();
-            move |params| handler.eth_accounts(params)
-        });
+        // The signature is bound to the action and the nonce, so neither can be replayed
+        assert!(!handler.audit(&sign(admin::AdminAction::Audit, 0)).success);
+        assert!(!handler.recompute_state_root(&sign(admin::AdminAction::Audit, 1)).success);
         
-        io.add_method("eth_sendRawTransaction", {
-            let handler = eth_handler.clone();
-            move |params| handler.eth_send_raw_transaction(params)
-        });
+        let response = handler.recompute_state_root(&sign(admin::AdminAction::RecomputeStateRoot, 1));
+        assert!(response.result.unwrap().matches);
+        let response = handler.distribute_fees_now(&sign(admin::AdminAction::DistributeFees, 2));
+        assert_eq!(response.event.unwrap().caller, admin);
         
-        // Add WebSocket-specific methods
-        io.add_method("eth_subscribe", {
-            let handler = pubsub_handler.clone();
-            move |params| {
-                let handler = handler.clone();
-                Box::pin(async move {
-                    handler.eth_subscribe(params).await
-                })
-            }
-        });
+        let log = handler.get_admin_events(PageRequest::default()).unwrap();
+        assert_eq!(log.next_nonce, 3);
+        let actions: Vec<_> = log.events.items.iter().map(|event| event.action).collect();
+        assert_eq!(actions, vec![admin::AdminAction::Audit, admin::AdminAction::RecomputeStateRoot, admin::AdminAction::DistributeFees]);
+        assert_eq!(handler.get_rpc_metrics().admin.state_root_mismatches, 0);
+    }
+
+    #[test]
+    fn test_maintenance_mode_refuses_mutations_until_disabled() {
+        let test_dir = format!("./test_maintenance_{}", std::process::id());
+        let _ = std::fs::remove_dir_all(&test_dir);
+        let (holder, _) = signature::tests::sign(14, "");
+        let (admin, _) = signature::tests::sign(15, "");
+        let mut handler = RpcHandler::new(Runtime::with_checkpoint_config(5, &test_dir).unwrap());
+        handler.set_ops_admin(admin.clone());
+        handler.runtime.create_account(&holder).unwrap();
+        handler.runtime.credit_balance(&holder, 500).unwrap();
+        let chain_id = handler.node_info.chain_id;
+        let sign = |action: admin::AdminAction, reason: &str, nonce: u64| {
+            signature::tests::sign(15, &signature::admin_action_message(&action.to_string(), &[("reason", reason)], chain_id, nonce)).1
+        };
+        let burn_signature = signature::tests::sign(14, &signature::burn_message(&holder, 100, chain_id, 0)).1;
+
+        // The signature covers the reason shown to refused callers
+        assert!(!handler.set_maintenance_mode(true, Some("upgrading".to_string()),
+                                              &sign(admin::AdminAction::EnableMaintenance, "restoring checkpoint", 0)).success);
+        let response = handler.set_maintenance_mode(true, Some("restoring checkpoint".to_string()),
+                                                    &sign(admin::AdminAction::EnableMaintenance, "restoring checkpoint", 0));
+        assert!(response.success, "{:?}", response.error);
+
+        // Mutations are refused with the reason; reads still answer
+        let refused = handler.burn(&holder, 100, &burn_signature);
+        assert_eq!(refused.error.as_deref(), Some("Node is in maintenance mode: restoring checkpoint"));
+        assert!(!handler.create_account("0x7777777777777777777777777777777777777777".to_string()).success);
+        assert_eq!(handler.get_account_info(holder.clone()).balance, 500);
+
+        let status = handler.get_network_status();
+        assert_eq!(status.maintenance.reason.as_deref(), Some("restoring checkpoint"));
+        assert_eq!(status.maintenance.enabled_by.as_deref(), Some(admin.as_str()));
+        assert!(!handler.get_readiness().ready);
+
+        // Disabling resumes mutations and saves the state first
+        let response = handler.set_maintenance_mode(false, None, &sign(admin::AdminAction::DisableMaintenance, "", 1));
+        assert!(response.event.unwrap().outcome.contains("checkpoint"));
+        assert!(handler.runtime.checkpoint_health().last_success.is_some());
+        assert!(handler.get_readiness().ready);
+        assert!(handler.burn(&holder, 100, &burn_signature).success);
+
+        let _ = std::fs::remove_dir_all(&test_dir);
+    }
+
+    #[test]
+    fn test_log_levels_are_changed_by_the_ops_admin() {
+        let (admin, _) = signature::tests::sign(15, "");
+        let mut handler = RpcHandler::new(Runtime::new());
+        handler.set_ops_admin(admin);
+        let chain_id = handler.node_info.chain_id;
+        let signed = |target: &str, level: &str, persist: &str, nonce: u64| signature::tests::sign(
+            15, &signature::admin_action_message(
+                &admin::AdminAction::SetLogLevel.to_string(),
+                &[("target", target), ("level", level), ("persist", persist)],
+                chain_id,
+                nonce,
+            )
+        ).1;
+        assert!(handler.get_log_levels().is_err());
         
-        io.add_method("eth_unsubscribe", {
-            let handler = pubsub_handler.clone();
-            move |params| {
-                let handler = handler.clone();
-                Box::pin(async move {
-                    handler.eth_unsubscribe(params).await
-                })
-            }
-        });
+        handler.set_log_handle(logging::LogHandle::new(logging::LogLevels::new(log::LevelFilter::Info), None));
+        let response = handler.set_log_level("rpc::eth_compat", "trace", false, &signed("rpc::eth_compat", "trace", "false", 0));
+        assert!(response.success, "{:?}", response.error);
+        assert_eq!(handler.get_log_levels().unwrap().level_for("rpc::eth_compat"), log::LevelFilter::Trace);
         
-        // Start the WebSocket server
-        WsServerBuilder::new(io)
-            .max_connections(100)
-            .start(&addr)
-            .map_err(|_| JsonRpcError::internal_error())
+        // Bad levels, persisting without a levels file, replayed signatures and
+        // signatures over other values are refused without a record
+        assert!(!handler.set_log_level("p2p", "loud", false, &signed("p2p", "loud", "false", 1)).success);
+        assert!(!handler.set_log_level("p2p", "off", true, &signed("p2p", "off", "true", 1)).success);
+        assert!(!handler.set_log_level("p2p", "off", false, &signed("p2p", "off", "false", 0)).success);
+        assert!(!handler.set_log_level("p2p", "off", false, &signed("p2p", "debug", "false", 1)).success);
+        assert!(!handler.set_log_level("*", "off", false, &signed("p2p", "off", "false", 1)).success);
+        assert_eq!(handler.admin_log.next_nonce(), 1);
+        assert_eq!(handler.get_log_levels().unwrap().level_for("p2p"), log::LevelFilter::Info);
     }
 
-    // TODO: Implement additional RPC methods:
-    // - submit_transaction(): Submit a new transaction
-    // - claim_ubi(): Process UBI claims
-    // - verify_account(): Submit verification proof
-    // - get_network_status(): Query network state
-    // - request_ai_resources(): Request AI compute allocation
-    // - get_verification_status(): Check verification progress
-}
+    #[test]
+    fn test_labels_are_set_by_the_ops_admin_and_shown_with_accounts() {
+        let exchange = "0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+        let (admin, _) = signature::tests::sign(16, "");
+        let runtime = Runtime::new();
+        runtime.create_account(exchange).unwrap();
+        let mut handler = RpcHandler::new(runtime);
+        handler.set_ops_admin(admin);
+        let chain_id = handler.node_info.chain_id;
+        let signed = |action: admin::AdminAction, params: &[(&str, &str)], nonce: u64| signature::tests::sign(
+            16, &signature::admin_action_message(&action.to_string(), params, chain_id, nonce)
+        ).1;
+        let label_params = [("address", exchange), ("label", "Exchange X"), ("category", "exchange")];
 
-/// Validates an Ethereum address
-/// 
-/// # Arguments
-/// * `address` - The address to validate
-/// 
-/// # Returns
-/// `true` if the address is valid, `false` otherwise
-pub fn is_valid_eth_address(address: &str) -> bool {
-    // Check if the address starts with "0x" and has 42 characters total (0x + 40 hex chars)
-    if !address.starts_with("0x") || address.len() != 42 {
-        return false;
-    }
-    
-    // Check if the address contains only hexadecimal characters after "0x"
-    address[2..].chars().all(|c| c.is_ascii_hexdigit())
-}
+        // Invalid categories, overlong labels and foreign signatures are refused without a record
+        assert!(!handler.set_label(exchange, "Exchange X", "bank", &signed(admin::AdminAction::SetLabel, &label_params, 0)).success);
+        assert!(!handler.set_label(exchange, &"x".repeat(65), "exchange", &signed(admin::AdminAction::SetLabel, &label_params, 0)).success);
+        assert!(!handler.set_label(exchange, "Exchange X", "exchange", &signed(admin::AdminAction::RemoveLabel, &label_params, 0)).success);
+        // A signature for one label cannot set another
+        assert!(!handler.set_label(exchange, "Scam X", "exchange", &signed(admin::AdminAction::SetLabel, &label_params, 0)).success);
+        assert_eq!(handler.admin_log.next_nonce(), 0);
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        let response = handler.set_label(exchange, "Exchange X", "exchange", &signed(admin::AdminAction::SetLabel, &label_params, 0));
+        assert!(response.success, "{:?}", response.error);
+        let label = labels::AccountLabel::new("Exchange X", labels::LabelCategory::Exchange).unwrap();
+        assert_eq!(handler.get_account_info(exchange.to_uppercase().replace("0X", "0x")).label, Some(label.clone()));
+        assert_eq!(handler.list_accounts(PageRequest::default()).items[0].label, Some(label.clone()));
+
+        let response = handler.remove_label(exchange, &signed(admin::AdminAction::RemoveLabel, &[("address", exchange)], 1));
+        assert_eq!(response.result.unwrap().previous, Some(label));
+        let info = serde_json::to_value(handler.get_account_info(exchange.to_string())).unwrap();
+        assert!(info.get("label").is_none(), "{}", info);
+    }
 
     #[test]
-    fn test_account_info() {
+    fn test_account_info_splits_locked_and_spendable_tokens() {
+        let alice = "0x1111111111111111111111111111111111111111";
         let runtime = Runtime::new();
+        runtime.create_account(alice).unwrap();
+        runtime.credit_balance(alice, 1_000).unwrap();
+        runtime.lock_balance(alice, 250, u64::MAX).unwrap();
         let handler = RpcHandler::new(runtime);
+
+        let info = handler.get_account_info(alice.to_string());
+        assert_eq!((info.balance, info.locked, info.spendable), (1_000, 250, 750));
+        let listed = &handler.list_accounts(PageRequest::default()).items[0];
+        assert_eq!((listed.locked, listed.spendable), (250, 750));
+    }
+
+    #[test]
+    fn test_accounts_are_frozen_by_the_ops_admin() {
+        let alice = "0x1111111111111111111111111111111111111111";
+        let bob = "0x2222222222222222222222222222222222222222";
+        let (admin, _) = signature::tests::sign(23, "");
+        let runtime = Runtime::new();
+        runtime.create_account(alice).unwrap();
+        runtime.create_account(bob).unwrap();
+        runtime.credit_balance(alice, 1_000).unwrap();
+        let mut handler = RpcHandler::new(runtime.clone());
+        handler.set_ops_admin(admin);
+        let chain_id = handler.node_info.chain_id;
+        let signed = |action: admin::AdminAction, params: &[(&str, &str)], nonce: u64| signature::tests::sign(
+            23, &signature::admin_action_message(&action.to_string(), params, chain_id, nonce)
+        ).1;
+        let freeze_alice = [("address", alice), ("reason", "sybil review")];
+
+        assert!(!handler.freeze_account(alice, None, &signed(admin::AdminAction::UnfreezeAccount, &[("address", alice)], 0)).success);
+        // A signature to freeze one account, or with another reason, freezes nothing
+        assert!(!handler.freeze_account(bob, Some("sybil review"), &signed(admin::AdminAction::FreezeAccount, &freeze_alice, 0)).success);
+        assert!(!handler.freeze_account(alice, Some("stolen keys"), &signed(admin::AdminAction::FreezeAccount, &freeze_alice, 0)).success);
+        let response = handler.freeze_account(alice, Some(" sybil review "), &signed(admin::AdminAction::FreezeAccount, &freeze_alice, 0));
+        assert!(response.success, "{:?}", response.error);
+        assert_eq!(response.event.unwrap().outcome, format!("{} frozen: sybil review", alice));
+        let info = handler.get_account_info(alice.to_string());
+        assert!(info.frozen);
+        assert_eq!((info.frozen_reason.as_deref(), info.balance), (Some("sybil review"), 1_000));
+        assert!(runtime.transfer_with_fee(alice, bob, 10).is_err());
+
+        assert!(!handler.unfreeze_account(alice, &signed(admin::AdminAction::UnfreezeAccount, &[("address", bob)], 1)).success);
+        let response = handler.unfreeze_account(alice, &signed(admin::AdminAction::UnfreezeAccount, &[("address", alice)], 1));
+        assert!(response.success, "{:?}", response.error);
+        let info = serde_json::to_value(handler.get_account_info(alice.to_string())).unwrap();
+        assert_eq!(info["frozen"], false);
+        assert!(info.get("frozen_reason").is_none(), "{}", info);
+        runtime.transfer_with_fee(alice, bob, 10).unwrap();
+    }
+
+    #[test]
+    fn test_state_is_exported_and_imported_by_the_ops_admin() {
+        let alice = "0x1111111111111111111111111111111111111111";
+        let bob = "0x2222222222222222222222222222222222222222";
+        let (admin, _) = signature::tests::sign(17, "");
+        let source = Runtime::new();
+        source.create_account(alice).unwrap();
+        source.create_account(bob).unwrap();
+        source.credit_balance(alice, 5_000).unwrap();
+        source.transfer_with_fee(alice, bob, 700).unwrap();
+        let mut exporter = RpcHandler::new(source.clone());
+        exporter.set_ops_admin(admin.clone());
+        let mut importer = RpcHandler::new(Runtime::new());
+        importer.set_ops_admin(admin);
+        let chain_id = exporter.node_info.chain_id;
+        let signed = |action: admin::AdminAction, params: &[(&str, &str)], nonce: u64| signature::tests::sign(
+            17, &signature::admin_action_message(&action.to_string(), params, chain_id, nonce)
+        ).1;
+        let signed_import = |snapshot: &StateSnapshot, nonce: u64| signed(
+            admin::AdminAction::ImportState,
+            &[("state_root", &snapshot.state_root), ("snapshot_hash", &snapshot.content_hash())],
+            nonce,
+        );
+
+        assert!(!exporter.export_state(&signed(admin::AdminAction::ImportState, &[], 0)).success);
+        let response = exporter.export_state(&signed(admin::AdminAction::ExportState, &[], 0));
+        assert!(response.success, "{:?}", response.error);
+        // Sent over the wire as JSON
+        let snapshot: StateSnapshot = serde_json::from_value(serde_json::to_value(response.result.unwrap()).unwrap()).unwrap();
+
+        // Snapshots with a bad address or a repeated one are refused before the signature is checked
+        let mut malformed = snapshot.clone();
+        malformed.accounts[0].address = "0xnot-an-address".to_string();
+        assert!(importer.import_state(&malformed, &signed_import(&malformed, 0)).error.unwrap().contains("Invalid account address"));
+        let mut duplicated = snapshot.clone();
+        duplicated.accounts.push(duplicated.accounts[1].clone());
+        assert!(!importer.import_state(&duplicated, &signed_import(&duplicated, 0)).success);
         
-        // For a non-existent account
-        let info = handler.get_account_info("0x1234567890abcdef1234567890abcdef12345678".to_string());
-        assert_eq!(info.balance, 0); // Non-existent accounts have 0 balance
-        assert!(!info.verified); // Non-existent accounts are not verified
-        
-        // Create an account and check its info
-        let valid_address = "0x1234567890abcdef1234567890abcdef12345678";
-        let _ = handler.create_account(valid_address.to_string());
-        
-        let info = handler.get_account_info(valid_address.to_string());
-        assert_eq!(info.balance, 10); // Initial balance is 10 tokens
-        assert!(info.verified); // Accounts are auto-verified
+        // The signature covers the whole snapshot, not only the accounts the state root hashes
+        let mut inflated = snapshot.clone();
+        inflated.fee_pool += 1_000;
+        assert!(inflated.to_checkpoint().is_ok());
+        let refused = importer.import_state(&inflated, &signed_import(&snapshot, 0));
+        assert!(refused.error.unwrap().contains("not the operations admin"));
+        assert!(!importer.import_state(&snapshot, &signed(admin::AdminAction::ImportState, &[], 0)).success);
+        assert_eq!(importer.admin_log.next_nonce(), 0);
+
+        let response = importer.import_state(&snapshot, &signed_import(&snapshot, 0));
+        assert!(response.success, "{:?}", response.error);
+        assert_eq!(response.result.unwrap().accounts, 2);
+        assert_eq!(importer.runtime.state_root(), source.state_root());
+        assert_eq!(importer.runtime.get_balance(bob), source.get_balance(bob));
+        assert_eq!(importer.runtime.get_fee_pool(), source.get_fee_pool());
     }
-    
+
     #[test]
-    fn test_create_account() {
+    fn test_checkpoints_are_diffed_by_timestamp() {
+        let test_dir = format!("./test_diff_checkpoints_{}", std::process::id());
+        let _ = std::fs::remove_dir_all(&test_dir);
+        let handler = RpcHandler::new(Runtime::with_checkpoint_config(5, &test_dir).unwrap());
+        let alice = "0x1111111111111111111111111111111111111111";
+        let bob = "0x2222222222222222222222222222222222222222";
+        handler.runtime.create_account(alice).unwrap();
+        handler.runtime.credit_balance(alice, 1_000).unwrap();
+        let first = handler.runtime.create_checkpoint(true).unwrap();
+
+        // Checkpoint files are named by the second they were taken in
+        std::thread::sleep(std::time::Duration::from_millis(1100));
+        handler.runtime.create_account(bob).unwrap();
+        handler.runtime.credit_balance(bob, 250).unwrap();
+        let second = handler.runtime.create_checkpoint(true).unwrap();
+
+        let diff = handler.diff_checkpoints(first.timestamp, second.timestamp).unwrap();
+        assert_eq!(diff.created.len(), 1);
+        assert_eq!(diff.created[0].address, bob);
+        assert!(diff.removed.is_empty() && diff.changed.is_empty());
+        assert_eq!(diff.total_supply.delta, 250);
+
+        let missing = handler.diff_checkpoints(first.timestamp, 1).unwrap_err();
+        assert_eq!(missing, "No checkpoint with timestamp 1");
+
+        let _ = std::fs::remove_dir_all(&test_dir);
+    }
+
+    #[test]
+    fn test_list_accounts_pages_cover_every_account_once() {
         let runtime = Runtime::new();
+        // Addresses start past the burn address, which cannot hold an account
+        for i in 0..100_000u64 {
+            runtime.create_account(&format!("0x{:040x}", 0x1_0000 + i)).unwrap();
+        }
         let handler = RpcHandler::new(runtime);
         
+        let mut seen = std::collections::HashSet::new();
+        let mut previous: Option<String> = None;
+        let mut cursor = None;
+        loop {
+            let page = handler.list_accounts(PageRequest { cursor, limit: Some(usize::MAX) });
+            assert!(page.items.len() <= pagination::MAX_PAGE_SIZE);
+            for account in page.items {
+                assert!(previous.as_ref().is_none_or(|previous| account.address > *previous));
+                previous = Some(account.address.clone());
+                assert!(seen.insert(account.address));
+            }
+            cursor = match page.next_cursor {
+                Some(next) => Some(next),
+                None => break,
+            };
+        }
+        assert_eq!(seen.len(), 100_000);
+    }
+
+    #[test]
+    fn test_create_account() {
+        let builder = TestRuntimeBuilder::new().initial_balance(10).verify_new_accounts(true);
+        let policy = builder.new_accounts();
+        let handler = RpcHandler::new(builder.build());
+        
         // Test valid address
         let valid_address = "0x1234567890abcdef1234567890abcdef12345678";
         let response = handler.create_account(valid_address.to_string());
@@ -556,8 +4106,8 @@ mod tests {
         
         let account_info = response.account.unwrap();
         assert_eq!(account_info.address, valid_address);
-        assert_eq!(account_info.balance, 10); // Initial balance is 10 tokens
-        assert!(account_info.verified); // Accounts are auto-verified
+        assert_eq!(account_info.balance, policy.balance);
+        assert_eq!(account_info.verified, policy.verified);
         
         // Test duplicate address
         let duplicate_response = handler.create_account(valid_address.to_string());
@@ -577,8 +4127,14 @@ mod tests {
     
     #[tokio::test]
     async fn test_faucet() {
-        let runtime = Runtime::new();
-        let handler = RpcHandler::new(runtime);
+        let faucet = "0x1111111111111111111111111111111111111111";
+        let builder = TestRuntimeBuilder::new()
+            .initial_balance(10)
+            .funded_account(faucet, 1_000);
+        let initial = builder.new_accounts().balance;
+        let params = builder.params();
+        let mut handler = RpcHandler::new(builder.build());
+        handler.set_node_address(faucet.to_string());
         
         // Test requesting tokens for a new account
         let address = "0x1234567890abcdef1234567890abcdef12345678";
@@ -590,16 +4146,16 @@ mod tests {
         assert!(response.transaction_hash.is_some());
         assert!(response.error.is_none());
         
-        // The account should now have 50 tokens (plus the 10 initial tokens)
+        // The recipient gets the full grant on top of its initial balance
         let balance = handler.runtime.get_balance(address);
-        assert_eq!(balance, 60);
+        assert_eq!(balance, initial + 50);
         
         // Test requesting tokens for an existing account
         let response2 = handler.request_from_faucet(address.to_string(), Some(30)).await;
         
         assert!(response2.success);
         assert_eq!(response2.amount, Some(30));
-        assert_eq!(response2.new_balance, Some(90)); // 60 + 30 = 90
+        assert_eq!(response2.new_balance, Some(initial + 80));
         assert!(response2.transaction_hash.is_some());
         assert!(response2.error.is_none());
         
@@ -608,6 +4164,463 @@ mod tests {
         
         assert!(response3.success);
         assert_eq!(response3.amount, Some(100)); // Should be capped at 100
-        assert_eq!(response3.new_balance, Some(190)); // 90 + 100 = 190
+        assert_eq!(response3.new_balance, Some(initial + 180));
+        
+        // The faucet paid the fees on top of the grants
+        let fees: u64 = [50, 30, 100].iter().map(|amount| params.transfer_fee(*amount)).sum();
+        assert_eq!(handler.runtime.get_balance(faucet), initial + 1_000 - 180 - fees);
+    }
+
+    #[tokio::test]
+    async fn test_faucet_fails_over_to_next_source() {
+        let first = "0x1111111111111111111111111111111111111111";
+        let second = "0x2222222222222222222222222222222222222222";
+        let runtime = TestRuntimeBuilder::new()
+            .fee_bps(0)
+            .minimum_fee(0)
+            .funded_account(first, 30)
+            .funded_account(second, 1_000)
+            .build();
+        runtime.set_faucet_sources(vec![first.to_string(), second.to_uppercase().replace("0X", "0x")]).unwrap();
+        let handler = RpcHandler::new(runtime);
+        let address = "0x1234567890abcdef1234567890abcdef12345678";
+
+        let response = handler.request_from_faucet(address.to_string(), Some(20)).await;
+        assert_eq!(response.source.as_deref(), Some(first));
+
+        // The first source is left with 10, too little for the next grant
+        let response = handler.request_from_faucet(address.to_string(), Some(20)).await;
+        assert!(response.success);
+        assert_eq!(response.source.as_deref(), Some(second));
+        assert_eq!(response.new_balance, Some(40));
+
+        let stats = handler.get_faucet_stats();
+        let balances: Vec<(&str, u64, u64)> = stats.sources.iter()
+            .map(|source| (source.address.as_str(), source.balance, source.totals.grants))
+            .collect();
+        assert_eq!(balances, vec![(first, 10, 1), (second, 980, 1)]);
+        assert_eq!(stats.total_balance, 990);
+        assert_eq!(stats.recent_grants[0].source, second);
+        assert_eq!(stats.recent_grants[0].skipped, vec![format!("{}: Insufficient balance: 10 < 20", first)]);
+        assert_eq!(stats.recent_grants[1].source, first);
+
+        // When no source can cover a grant, the response says the faucet is out of funds
+        handler.runtime.transfer_with_fee(second, address, 950).unwrap();
+        let response = handler.request_from_faucet(address.to_string(), Some(50)).await;
+        assert!(!response.success);
+        assert_eq!(response.error_code.as_deref(), Some("faucet_empty"));
+        assert_eq!(response.error.as_deref(), Some("The faucet is out of funds: 50 tokens needed, at most 30 available"));
+    }
+    
+    #[tokio::test]
+    async fn test_faucet_tier_requires_signed_challenge() {
+        let runtime = Runtime::new();
+        let faucet = "0x1111111111111111111111111111111111111111";
+        runtime.create_account(faucet).unwrap();
+        runtime.credit_balance(faucet, 1_000).unwrap();
+        runtime.set_faucet_tiers(vec![runtime::faucet::FaucetTier {
+            name: "proven".to_string(),
+            amount: 20,
+            cooldown_secs: None,
+            requires_code: false,
+            codes: Vec::new(),
+            requires_challenge: true,
+        }]);
+        let mut handler = RpcHandler::new(runtime);
+        handler.set_node_address(faucet.to_string());
+        let (address, _) = signature::tests::sign(21, "");
+        let request = |tier: &str, signature: Option<String>| FaucetTierRequest {
+            tier: tier.to_string(),
+            code: None,
+            signature,
+        };
+
+        let response = handler.request_from_faucet_tier(address.clone(), request("proven", None)).await;
+        assert_eq!(response.error_code.as_deref(), Some("challenge_required"));
+
+        // Signed by another key; the failed attempt uses the challenge up
+        let challenge = handler.get_faucet_challenge(&address).unwrap();
+        let (_, forged) = signature::tests::sign(22, &challenge.message);
+        let response = handler.request_from_faucet_tier(address.clone(), request("proven", Some(forged))).await;
+        assert_eq!(response.error_code.as_deref(), Some("bad_signature"));
+        let (_, signed) = signature::tests::sign(21, &challenge.message);
+        let response = handler.request_from_faucet_tier(address.clone(), request("proven", Some(signed))).await;
+        assert_eq!(response.error_code.as_deref(), Some("bad_signature"));
+
+        let challenge = handler.get_faucet_challenge(&address).unwrap();
+        let (_, signed) = signature::tests::sign(21, &challenge.message);
+        let response = handler.request_from_faucet_tier(address.clone(), request("proven", Some(signed))).await;
+        assert!(response.success);
+        assert_eq!(response.amount, Some(20));
+        assert_eq!(handler.runtime.get_balance(&address), 20);
+
+        // The tier is granted once per address
+        let challenge = handler.get_faucet_challenge(&address).unwrap();
+        let (_, signed) = signature::tests::sign(21, &challenge.message);
+        let response = handler.request_from_faucet_tier(address.clone(), request("proven", Some(signed))).await;
+        assert_eq!(response.error_code.as_deref(), Some("tier_exhausted"));
+
+        let response = handler.request_from_faucet_tier(address.clone(), request("starter", None)).await;
+        assert_eq!(response.error_code.as_deref(), Some("unknown_tier"));
+    }
+
+    #[test]
+    fn test_chain_info() {
+        struct FixedHeight;
+        
+        impl runtime::BlockProducer for FixedHeight {
+            fn submit_transaction(&self, _tx: Transaction) -> Result<(), String> {
+                Ok(())
+            }
+            
+            fn current_block(&self) -> u64 {
+                42
+            }
+        }
+        
+        let runtime = Runtime::new();
+        let mut handler = RpcHandler::new(runtime.clone());
+        
+        // Defaults are reported before the node registers its configuration
+        let info = handler.get_chain_info();
+        assert_eq!(info.block_height, 0);
+        assert!(info.node_address.is_none());
+        
+        runtime.set_block_producer(Arc::new(FixedHeight));
+        handler.set_node_address("0x0000000000000000000000000000000000007685".to_string());
+        handler.set_node_info(NodeInfo {
+            chain_id: 31337,
+            genesis_hash: "0x0".to_string(),
+            block_time_ms: 500,
+            version: "9.9.9".to_string(),
+        });
+        
+        let info = handler.get_chain_info();
+        assert_eq!(info.chain_id, 31337);
+        assert_eq!(info.node_address.as_deref(), Some("0x0000000000000000000000000000000000007685"));
+        assert_eq!(info.ubi_tokens_per_hour, runtime::UBI_TOKENS_PER_HOUR);
+        assert_eq!(info.fee_schedule.transfer_fee_percent, runtime::TRANSFER_FEE_PERCENT);
+        assert_eq!(info.block_time_ms, 500);
+        assert_eq!(info.block_reward, runtime::params::DEFAULT_BLOCK_REWARD);
+        assert_eq!(info.block_height, 42);
+        assert_eq!(info.version, "9.9.9");
+    }
+    
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_keyed_submissions_are_submitted_once() {
+        struct CountingProducer(std::sync::atomic::AtomicUsize);
+        
+        impl runtime::BlockProducer for CountingProducer {
+            fn submit_transaction(&self, _tx: Transaction) -> Result<(), String> {
+                self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                Ok(())
+            }
+            
+            fn current_block(&self) -> u64 {
+                0
+            }
+        }
+        
+        let sender = "0x1111111111111111111111111111111111111111";
+        let recipient = "0x2222222222222222222222222222222222222222";
+        let runtime = Runtime::new();
+        runtime.create_account(sender).unwrap();
+        runtime.credit_balance(sender, 1_000).unwrap();
+        let producer = Arc::new(CountingProducer(std::sync::atomic::AtomicUsize::new(0)));
+        runtime.set_block_producer(producer.clone());
+        let handler = RpcHandler::new(runtime);
+        
+        let tasks: Vec<_> = (0..8).map(|_| {
+            let handler = handler.clone();
+            tokio::spawn(async move {
+                handler.submit_transaction(sender.to_string(), recipient.to_string(), 100, TransferMode::Send, Some("order-7".to_string())).await
+            })
+        }).collect();
+        
+        let mut responses = Vec::new();
+        for task in tasks {
+            responses.push(task.await.unwrap());
+        }
+        
+        assert_eq!(producer.0.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert_eq!(responses.iter().filter(|response| !response.duplicate).count(), 1);
+        assert!(responses.iter().all(|response| response.success
+            && response.transaction_hash == responses[0].transaction_hash
+            && response.idempotency_key.as_deref() == Some("order-7")));
+        
+        // Without a key every call is submitted
+        handler.submit_transaction(sender.to_string(), recipient.to_string(), 100, TransferMode::Send, None).await;
+        assert_eq!(producer.0.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+    
+    #[tokio::test]
+    async fn test_faucet_and_account_creation_require_proof_of_work() {
+        let runtime = Runtime::new();
+        let faucet = "0x1111111111111111111111111111111111111111";
+        runtime.create_account(faucet).unwrap();
+        runtime.credit_balance(faucet, 1_000).unwrap();
+        let mut handler = RpcHandler::new(runtime);
+        handler.set_node_address(faucet.to_string());
+        handler.set_pow_config(pow::PowConfig {
+            endpoints: [pow::PowEndpoint::Faucet, pow::PowEndpoint::CreateAccount].into_iter().collect(),
+            min_difficulty: 8,
+            max_difficulty: 8,
+            ..Default::default()
+        });
+        let address = "0x1234567890abcdef1234567890abcdef12345678";
+
+        let response = handler.request_from_faucet_idempotent(address.to_string(), Some(50), None, None, None).await;
+        assert_eq!(response.error_code.as_deref(), Some("pow_required"));
+        assert!(!handler.create_account_with_pow(address.to_string(), None).success);
+
+        // A challenge is bound to its endpoint
+        let challenge = handler.get_pow_challenge("create-account").unwrap();
+        let solution = pow::tests::solve(&challenge);
+        let response = handler.request_from_faucet_idempotent(address.to_string(), Some(50), None, None, Some(solution)).await;
+        assert_eq!(response.error_code.as_deref(), Some("pow_wrong_endpoint"));
+
+        let challenge = handler.get_pow_challenge("create-account").unwrap();
+        assert!(handler.create_account_with_pow(address.to_string(), Some(&pow::tests::solve(&challenge))).success);
+        let balance = handler.runtime.get_balance(address);
+
+        let challenge = handler.get_pow_challenge("faucet").unwrap();
+        let solution = pow::tests::solve(&challenge);
+        let response = handler.request_from_faucet_idempotent(address.to_string(), Some(50), Some("pow-1".to_string()), None, Some(solution.clone())).await;
+        assert!(response.success, "{:?}", response);
+
+        // A retry with the same key is absorbed, a replay without one is refused
+        let response = handler.request_from_faucet_idempotent(address.to_string(), Some(50), Some("pow-1".to_string()), None, Some(solution.clone())).await;
+        assert!(response.success && response.duplicate);
+        let response = handler.request_from_faucet_idempotent(address.to_string(), Some(50), None, None, Some(solution)).await;
+        assert_eq!(response.error_code.as_deref(), Some("pow_unknown_challenge"));
+        assert_eq!(handler.runtime.get_balance(address), balance + 50);
+    }
+
+    #[tokio::test]
+    async fn test_seeded_faucet_hashes_are_reproducible() {
+        async fn faucet_hash(seed: u64) -> Option<String> {
+            let runtime = Runtime::new();
+            let faucet = "0x1111111111111111111111111111111111111111";
+            runtime.create_account(faucet).unwrap();
+            runtime.credit_balance(faucet, 1_000).unwrap();
+            
+            let mut handler = RpcHandler::new(runtime);
+            handler.set_node_address(faucet.to_string());
+            handler.set_random_source(RandomSource::seeded(seed));
+            handler.request_from_faucet("0x1234567890abcdef1234567890abcdef12345678".to_string(), Some(10))
+                .await
+                .transaction_hash
+        }
+        
+        let first = faucet_hash(42).await;
+        assert!(first.is_some());
+        assert_eq!(first, faucet_hash(42).await);
+        assert_ne!(first, faucet_hash(43).await);
+    }
+    
+    #[test]
+    fn test_eth_servers_report_ephemeral_ports() {
+        let handler = RpcHandler::new(Runtime::new());
+        assert_eq!(parse_bind_addr(":0").unwrap(), "127.0.0.1:0".parse::<SocketAddr>().unwrap());
+        assert!(parse_bind_addr("localhost").is_err());
+        
+        // Two servers asked for port 0 both bind, each to a real port of its own
+        let (first, first_addr) = handler.start_eth_rpc_server("127.0.0.1:0", 2030).unwrap();
+        let (second, second_addr) = handler.start_eth_rpc_server(":0", 2030).unwrap();
+        let (ws, ws_addr) = futures::executor::block_on(handler.start_eth_ws_server("127.0.0.1:0", 2030)).unwrap();
+        assert_ne!(first_addr.port(), 0);
+        assert_ne!(second_addr.port(), 0);
+        assert_ne!(ws_addr.port(), 0);
+        assert_ne!(first_addr, second_addr);
+        assert!(std::net::TcpStream::connect(first_addr).is_ok());
+        assert!(std::net::TcpStream::connect(ws_addr).is_ok());
+        
+        drop((first, second, ws));
+    }
+
+    #[test]
+    fn test_combined_server_close_drains_in_flight_requests() {
+        use std::io::{Read, Write};
+        
+        let mut handler = RpcHandler::new(Runtime::new());
+        handler.concurrency = ConcurrencyLimiter::new(ConcurrencyConfig {
+            max_in_flight: 1,
+            queue_timeout: std::time::Duration::from_secs(5),
+            ..ConcurrencyConfig::default()
+        });
+        
+        // Holding the only slot keeps the request below in flight until it is released
+        let slot = futures::executor::block_on(handler.concurrency.acquire("test")).unwrap();
+        let server = futures::executor::block_on(handler.start_combined_server("127.0.0.1:0", "127.0.0.1:0", 2030)).unwrap();
+        let (http_addr, ws_addr) = (server.http_addr, server.ws_addr);
+        
+        let request = std::thread::spawn(move || {
+            let body = r#"{"jsonrpc":"2.0","id":1,"method":"eth_syncing","params":[]}"#;
+            let mut stream = std::net::TcpStream::connect(http_addr).unwrap();
+            write!(stream, "POST / HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                   http_addr, body.len(), body).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        });
+        while handler.concurrency.metrics().queued == 0 {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(200));
+            drop(slot);
+        });
+        
+        assert!(server.close());
+        let response = request.join().unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        assert!(response.contains(r#""result":false"#), "{}", response);
+        assert!(std::net::TcpStream::connect(http_addr).is_err());
+        assert!(std::net::TcpStream::connect(ws_addr).is_err());
+    }
+
+    #[test]
+    fn test_account_proof_fetched_over_http_verifies_client_side() {
+        use std::io::{Read, Write};
+
+        let runtime = Runtime::new();
+        let accounts: Vec<String> = (1..=5).map(|index| format!("0x{:040x}", index * 65_537)).collect();
+        for account in &accounts {
+            runtime.create_account(account).unwrap();
+        }
+        runtime.credit_balance(&accounts[3], 250).unwrap();
+        let handler = RpcHandler::new(runtime.clone());
+        let (server, http_addr) = handler.start_eth_rpc_server("127.0.0.1:0", 2030).unwrap();
+
+        let call = |method: &str, params: serde_json::Value| -> serde_json::Value {
+            let body = serde_json::json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }).to_string();
+            let mut stream = std::net::TcpStream::connect(http_addr).unwrap();
+            write!(stream, "POST / HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                   http_addr, body.len(), body).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            let (_, body) = response.split_once("\r\n\r\n").unwrap();
+            serde_json::from_str(body).unwrap()
+        };
+
+        // The proof checks out without the node, against the published root
+        let response = call("ubi_getAccountProof", serde_json::json!([accounts[3].to_uppercase().replace("0X", "0x")]));
+        let proof: AccountProof = serde_json::from_value(response["result"].clone()).unwrap();
+        assert_eq!(proof.balance, 250);
+        assert_eq!(proof.root, runtime.state_root());
+        assert!(proof.verify().unwrap().valid);
+        assert_eq!(call("ubi_verifyAccountProof", serde_json::json!([proof]))["result"], serde_json::json!(true));
+
+        let mut forged = proof;
+        forged.balance += 1;
+        assert_eq!(call("ubi_verifyAccountProof", serde_json::json!([forged]))["result"], serde_json::json!(false));
+        assert!(call("ubi_getAccountProof", serde_json::json!(["0x9999999999999999999999999999999999999999"]))["error"].is_object());
+
+        drop(server);
+    }
+
+    #[test]
+    fn test_ubi_methods_available_over_websocket() {
+        use futures::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::Message;
+        
+        let runtime = Runtime::new();
+        let faucet = "0x1111111111111111111111111111111111111111";
+        runtime.create_account(faucet).unwrap();
+        runtime.credit_balance(faucet, 1_000).unwrap();
+        let mut handler = RpcHandler::new(runtime);
+        handler.set_node_address(faucet.to_string());
+        
+        // The server owns its own event loop, so it is started and dropped outside of tokio
+        let (server, bound_addr) = futures::executor::block_on(handler.start_eth_ws_server("127.0.0.1:0", 2030)).unwrap();
+        let url = format!("ws://{}", bound_addr);
+        
+        let response: serde_json::Value = tokio::runtime::Runtime::new().unwrap().block_on(async move {
+            let (mut ws, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+            let request = serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "ubi_requestFromFaucet",
+                "params": ["0x1234567890abcdef1234567890abcdef12345678", 5]
+            });
+            ws.send(Message::Text(request.to_string())).await.unwrap();
+            
+            while let Some(message) = ws.next().await {
+                if let Message::Text(text) = message.unwrap() {
+                    return serde_json::from_str(&text).unwrap();
+                }
+            }
+            panic!("WebSocket closed before a response was received");
+        });
+        
+        assert!(response.get("error").is_none(), "unexpected error: {}", response);
+        assert_eq!(response["result"]["success"], serde_json::json!(true));
+        assert_eq!(response["result"]["amount"], serde_json::json!(5));
+        assert_eq!(handler.runtime.get_balance("0x1234567890abcdef1234567890abcdef12345678"), 5);
+        
+        drop(server);
+    }
+    
+    #[test]
+    fn test_subscription_id_matches_notifications() {
+        use futures::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::Message;
+        
+        let runtime = Runtime::new();
+        let sender = "0x1111111111111111111111111111111111111111";
+        runtime.create_account(sender).unwrap();
+        runtime.credit_balance(sender, 1_000).unwrap();
+        let handler = RpcHandler::new(runtime);
+        
+        let (server, bound_addr) = futures::executor::block_on(handler.start_eth_ws_server("127.0.0.1:0", 2030)).unwrap();
+        let url = format!("ws://{}", bound_addr);
+        
+        let (subscription_id, notification) = tokio::runtime::Runtime::new().unwrap().block_on(async move {
+            let (mut ws, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+            
+            let subscribe = serde_json::json!({
+                "jsonrpc": "2.0", "id": 1, "method": "eth_subscribe", "params": ["newHeads"]
+            });
+            ws.send(Message::Text(subscribe.to_string())).await.unwrap();
+            
+            let mut subscription_id = None;
+            while let Some(message) = ws.next().await {
+                if let Message::Text(text) = message.unwrap() {
+                    let response: serde_json::Value = serde_json::from_str(&text).unwrap();
+                    subscription_id = Some(response["result"].as_str().expect("subscription id").to_string());
+                    break;
+                }
+            }
+            let subscription_id = subscription_id.expect("WebSocket closed before subscribing");
+            
+            // Sending a transaction produces a block, which must reach the subscriber
+            let send = serde_json::json!({
+                "jsonrpc": "2.0", "id": 2, "method": "eth_sendTransaction",
+                "params": [{
+                    "from": "0x1111111111111111111111111111111111111111",
+                    "to": "0x1234567890abcdef1234567890abcdef12345678",
+                    "value": "0xde0b6b3a7640000"
+                }]
+            });
+            ws.send(Message::Text(send.to_string())).await.unwrap();
+            
+            while let Some(message) = ws.next().await {
+                if let Message::Text(text) = message.unwrap() {
+                    let message: serde_json::Value = serde_json::from_str(&text).unwrap();
+                    if message["method"] == "eth_subscription" {
+                        return (subscription_id, message);
+                    }
+                }
+            }
+            panic!("WebSocket closed before a notification was received");
+        });
+        
+        assert!(subscription_id.starts_with("0x"));
+        assert_eq!(subscription_id.len(), 34);
+        assert_eq!(notification["jsonrpc"], "2.0");
+        assert_eq!(notification["params"]["subscription"].as_str(), Some(subscription_id.as_str()));
+        assert!(notification["params"]["result"]["hash"].is_string());
+        
+        drop(server);
     }
-} 
\ No newline at end of file
+}
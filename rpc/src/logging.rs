@@ -0,0 +1,510 @@
+//! Structured Logging and Request Correlation
+//!
+//! This module provides:
+//! - A `LogFormat` switch between the default text output and one JSON object per line
+//! - Per-request correlation ids, held in a task-local while a request is handled
+//!   so every log line emitted for it carries the same id
+//! - A JSON-RPC middleware that assigns the id and echoes it back in the `data`
+//!   field of error responses, so users can quote it in bug reports
+//! - Per-target log levels that can be changed while the process runs through
+//!   the `LogHandle` returned by `init_logger`, and optionally saved to a file
+//!   that is read back on the next start
+//!
+//! Hot-path log calls attach contextual fields with the `log` key-value syntax,
+//! for example `log::info!(tx_hash = hash.as_str(); "Transaction submitted")`.
+//! The JSON format emits them as top-level fields.
+
+use crate::RandomSource;
+use env_logger::fmt::Formatter;
+use jsonrpc_core::futures::future::Either;
+use jsonrpc_core::middleware::Middleware;
+use jsonrpc_core::{BoxFuture, Call, Metadata, Output, Response};
+use log::kv::{self, VisitSource};
+use log::{LevelFilter, Log, Record};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::future::Future;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+/// Field name used for the correlation id in log lines and error data
+pub const CORRELATION_ID_FIELD: &str = "correlation_id";
+
+/// Environment variable selecting the log format for binaries without a --log-format flag
+pub const LOG_FORMAT_ENV_VAR: &str = "UBI_LOG_FORMAT";
+
+/// Target name that stands for the default level in `LogHandle::set_level`
+pub const DEFAULT_LOG_TARGET: &str = "*";
+
+tokio::task_local! {
+    /// Correlation id of the request being handled by the current task
+    static CORRELATION_ID: String;
+}
+
+/// Output format for log lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable env_logger output
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("Invalid log format: {} (expected text or json)", s)),
+        }
+    }
+}
+
+/// Log levels by target, in the `RUST_LOG` sense
+///
+/// A target uses the level of the longest configured prefix of its name, or
+/// the default level when none matches, the same way `env_logger` filters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogLevels {
+    /// Level for targets without a configured prefix
+    pub default: LevelFilter,
+    /// Levels of configured target prefixes, such as `rpc::eth_compat`
+    pub targets: BTreeMap<String, LevelFilter>,
+}
+
+impl LogLevels {
+    /// Creates levels with only a default
+    pub fn new(default: LevelFilter) -> Self {
+        LogLevels {
+            default,
+            targets: BTreeMap::new(),
+        }
+    }
+
+    /// Parses comma-separated `RUST_LOG` directives
+    ///
+    /// Each directive is a level, a target (meaning trace) or `target=level`.
+    /// `env_logger`'s `/regex` message filter is not supported and is ignored.
+    ///
+    /// # Arguments
+    /// * `directives` - The directives, for example `info,rpc::eth_compat=trace`
+    /// * `default` - Default level when no directive sets one
+    ///
+    /// # Returns
+    /// The levels, or the first directive that could not be parsed
+    pub fn parse(directives: &str, default: LevelFilter) -> Result<Self, String> {
+        let mut levels = LogLevels::new(default);
+        let directives = directives.split('/').next().unwrap_or_default();
+
+        for directive in directives.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            match directive.split_once('=') {
+                Some((target, level)) => {
+                    let level = parse_level(level)?;
+                    levels.targets.insert(target.trim().to_string(), level);
+                },
+                None => match LevelFilter::from_str(directive) {
+                    Ok(level) => levels.default = level,
+                    Err(_) => {
+                        levels.targets.insert(directive.to_string(), LevelFilter::Trace);
+                    },
+                },
+            }
+        }
+
+        Ok(levels)
+    }
+
+    /// Gets the level a target logs at
+    pub fn level_for(&self, target: &str) -> LevelFilter {
+        self.targets.iter()
+            .filter(|(prefix, _)| target.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, level)| *level)
+            .unwrap_or(self.default)
+    }
+
+    /// Gets the most verbose level any target logs at
+    pub fn max_level(&self) -> LevelFilter {
+        self.targets.values().copied().fold(self.default, Ord::max)
+    }
+}
+
+impl fmt::Display for LogLevels {
+    /// Formats the levels as `RUST_LOG` directives that parse back to them
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", level_name(self.default))?;
+        for (target, level) in &self.targets {
+            write!(f, ",{}={}", target, level_name(*level))?;
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for LogLevels {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let targets: BTreeMap<&str, &str> = self.targets.iter()
+            .map(|(target, level)| (target.as_str(), level_name(*level)))
+            .collect();
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry("default", level_name(self.default))?;
+        map.serialize_entry("targets", &targets)?;
+        map.end()
+    }
+}
+
+/// Parses a level name such as `debug` or `OFF`
+pub fn parse_level(level: &str) -> Result<LevelFilter, String> {
+    LevelFilter::from_str(level.trim())
+        .map_err(|_| format!("Invalid log level: {} (expected off, error, warn, info, debug or trace)", level.trim()))
+}
+
+/// Gets the lowercase name of a level
+fn level_name(level: LevelFilter) -> &'static str {
+    match level {
+        LevelFilter::Off => "off",
+        LevelFilter::Error => "error",
+        LevelFilter::Warn => "warn",
+        LevelFilter::Info => "info",
+        LevelFilter::Debug => "debug",
+        LevelFilter::Trace => "trace",
+    }
+}
+
+/// Handle for changing the installed logger's levels while the process runs
+#[derive(Clone)]
+pub struct LogHandle {
+    /// Levels consulted by the logger on every call
+    levels: Arc<RwLock<LogLevels>>,
+    /// File the levels are saved to and read from at startup, if any
+    levels_file: Option<PathBuf>,
+}
+
+impl LogHandle {
+    /// Creates a handle over the given levels, not yet installed as the logger
+    pub fn new(levels: LogLevels, levels_file: Option<PathBuf>) -> Self {
+        LogHandle {
+            levels: Arc::new(RwLock::new(levels)),
+            levels_file,
+        }
+    }
+
+    /// Gets the current levels
+    pub fn levels(&self) -> LogLevels {
+        self.levels.read().unwrap().clone()
+    }
+
+    /// Gets the file the levels are saved to, if one is configured
+    pub fn levels_file(&self) -> Option<&Path> {
+        self.levels_file.as_deref()
+    }
+
+    /// Changes the level of a target; later log calls use it straight away
+    ///
+    /// # Arguments
+    /// * `target` - Target prefix such as `rpc::eth_compat`, or
+    ///   `DEFAULT_LOG_TARGET` for the default level
+    /// * `level` - The new level
+    ///
+    /// # Returns
+    /// The levels after the change
+    pub fn set_level(&self, target: &str, level: LevelFilter) -> LogLevels {
+        let mut levels = self.levels.write().unwrap();
+        let target = target.trim();
+        if target.is_empty() || target == DEFAULT_LOG_TARGET {
+            levels.default = level;
+        } else {
+            levels.targets.insert(target.to_string(), level);
+        }
+        log::set_max_level(levels.max_level());
+        levels.clone()
+    }
+
+    /// Saves the current levels to the levels file, so they survive a restart
+    ///
+    /// # Returns
+    /// The file written, or why the levels could not be saved
+    pub fn persist(&self) -> Result<PathBuf, String> {
+        let path = self.levels_file.clone()
+            .ok_or_else(|| "No log levels file is configured on this node".to_string())?;
+        let directives = self.levels().to_string();
+        std::fs::write(&path, format!("{}\n", directives))
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        Ok(path)
+    }
+}
+
+/// Logger filtering records through a `LogHandle`'s levels before formatting them
+struct ReloadableLogger {
+    inner: env_logger::Logger,
+    levels: Arc<RwLock<LogLevels>>,
+}
+
+impl Log for ReloadableLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= self.levels.read().unwrap().level_for(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Initializes the global logger
+///
+/// The levels come from `levels_file` if it exists, otherwise from `RUST_LOG`,
+/// with `default_level` applying to targets neither mentions. They can be
+/// changed afterwards through the returned handle.
+///
+/// # Arguments
+/// * `format` - Text or JSON output
+/// * `default_level` - Level used when neither the file nor `RUST_LOG` sets one
+/// * `levels_file` - File the levels are read from and saved to, if any
+///
+/// # Returns
+/// A handle for changing the levels at runtime
+pub fn init_logger(format: LogFormat, default_level: LevelFilter, levels_file: Option<PathBuf>) -> LogHandle {
+    let saved = levels_file.as_ref()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .map(|directives| (directives, "levels file"));
+    let directives = saved.or_else(|| std::env::var("RUST_LOG").ok().map(|directives| (directives, "RUST_LOG")));
+
+    // The logger is not installed yet, so problems are reported on stderr
+    let levels = match directives {
+        Some((directives, source)) => LogLevels::parse(&directives, default_level).unwrap_or_else(|e| {
+            eprintln!("Ignoring log levels from {}: {}", source, e);
+            LogLevels::new(default_level)
+        }),
+        None => LogLevels::new(default_level),
+    };
+    let handle = LogHandle::new(levels, levels_file);
+
+    // Filtering is left to the handle's levels, so the inner logger lets everything through
+    let mut builder = env_logger::Builder::from_env(env_logger::Env::new().write_style("RUST_LOG_STYLE"));
+    builder.filter_level(LevelFilter::Trace);
+    if format == LogFormat::Json {
+        builder.format(write_json_line);
+    }
+
+    let logger = ReloadableLogger {
+        inner: builder.build(),
+        levels: handle.levels.clone(),
+    };
+    if log::set_boxed_logger(Box::new(logger)).is_ok() {
+        log::set_max_level(handle.levels().max_level());
+    }
+    handle
+}
+
+/// Writes a log record as a single JSON object
+fn write_json_line(buf: &mut Formatter, record: &Record) -> io::Result<()> {
+    let mut entry = Map::new();
+    entry.insert("timestamp".to_string(), json!(buf.timestamp_millis().to_string()));
+    entry.insert("level".to_string(), json!(record.level().to_string()));
+    entry.insert("target".to_string(), json!(record.target()));
+    entry.insert("message".to_string(), json!(record.args().to_string()));
+
+    if let Some(correlation_id) = current_correlation_id() {
+        entry.insert(CORRELATION_ID_FIELD.to_string(), json!(correlation_id));
+    }
+
+    let _ = record.key_values().visit(&mut FieldCollector(&mut entry));
+
+    writeln!(buf, "{}", Value::Object(entry))
+}
+
+/// Copies a record's key-value pairs into a JSON object
+struct FieldCollector<'a>(&'a mut Map<String, Value>);
+
+impl<'kvs> VisitSource<'kvs> for FieldCollector<'_> {
+    fn visit_pair(&mut self, key: kv::Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+        let value = if let Some(number) = value.to_u64() {
+            json!(number)
+        } else if let Some(flag) = value.to_bool() {
+            json!(flag)
+        } else {
+            json!(value.to_string())
+        };
+
+        self.0.insert(key.to_string(), value);
+        Ok(())
+    }
+}
+
+/// Gets the correlation id of the request being handled, if any
+pub fn current_correlation_id() -> Option<String> {
+    CORRELATION_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Generates a new correlation id
+///
+/// # Returns
+/// 16 hex characters drawn from the given random source
+pub fn new_correlation_id(random: &RandomSource) -> String {
+    let mut bytes = [0u8; 8];
+    random.fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Runs a future with the given correlation id attached to its log lines
+pub async fn with_correlation_id<F: Future>(correlation_id: String, future: F) -> F::Output {
+    CORRELATION_ID.scope(correlation_id, future).await
+}
+
+/// Runs a closure with the given correlation id attached to its log lines
+pub fn sync_with_correlation_id<R>(correlation_id: String, f: impl FnOnce() -> R) -> R {
+    CORRELATION_ID.sync_scope(correlation_id, f)
+}
+
+/// Adds the correlation id to the `data` field of an error
+///
+/// Existing object data gains a `correlation_id` key; other existing data is
+/// left untouched so clients relying on it are not broken.
+pub fn attach_correlation_id(error: &mut jsonrpc_core::Error, correlation_id: &str) {
+    match error.data.as_mut() {
+        None => error.data = Some(json!({ (CORRELATION_ID_FIELD): correlation_id })),
+        Some(Value::Object(data)) => {
+            data.insert(CORRELATION_ID_FIELD.to_string(), json!(correlation_id));
+        },
+        Some(_) => {},
+    }
+}
+
+/// JSON-RPC middleware assigning a correlation id to every call
+#[derive(Clone)]
+pub struct CorrelationMiddleware {
+    /// Source of correlation ids
+    random: RandomSource,
+}
+
+impl CorrelationMiddleware {
+    /// Creates a middleware drawing ids from the given random source
+    pub fn new(random: RandomSource) -> Self {
+        CorrelationMiddleware { random }
+    }
+}
+
+impl<M: Metadata> Middleware<M> for CorrelationMiddleware {
+    type Future = BoxFuture<Option<Response>>;
+    type CallFuture = BoxFuture<Option<Output>>;
+
+    fn on_call<F, X>(&self, call: Call, meta: M, next: F) -> Either<Self::CallFuture, X>
+    where
+        F: Fn(Call, M) -> X + Send + Sync,
+        X: Future<Output = Option<Output>> + Send + 'static,
+    {
+        let correlation_id = new_correlation_id(&self.random);
+
+        // Methods may do their work before returning a future, so both parts run in scope
+        let future = sync_with_correlation_id(correlation_id.clone(), || next(call, meta));
+
+        Either::Left(Box::pin(with_correlation_id(correlation_id.clone(), async move {
+            let mut output = future.await;
+            if let Some(Output::Failure(failure)) = output.as_mut() {
+                log::info!("Request failed: {}", failure.error.message);
+                attach_correlation_id(&mut failure.error, &correlation_id);
+            }
+            output
+        })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonrpc_core::MetaIoHandler;
+
+    fn handler() -> MetaIoHandler<(), CorrelationMiddleware> {
+        let mut io = MetaIoHandler::with_middleware(CorrelationMiddleware::new(RandomSource::seeded(3)));
+        io.add_method("correlation_id", |_params| async {
+            Ok(json!(current_correlation_id()))
+        });
+        io.add_method("fail", |_params| async {
+            Err::<Value, _>(jsonrpc_core::Error::invalid_params("bad input"))
+        });
+        io
+    }
+
+    #[tokio::test]
+    async fn test_calls_run_with_a_correlation_id() {
+        let io = handler();
+        let response = io.handle_request(r#"{"jsonrpc":"2.0","id":1,"method":"correlation_id"}"#, ()).await.unwrap();
+        let response: Value = serde_json::from_str(&response).unwrap();
+
+        let id = response["result"].as_str().expect("correlation id should be set while handling");
+        assert_eq!(id.len(), 16);
+        assert!(current_correlation_id().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_failures_echo_the_correlation_id() {
+        let io = handler();
+        let response = io.handle_request(r#"{"jsonrpc":"2.0","id":1,"method":"fail"}"#, ()).await.unwrap();
+        let response: Value = serde_json::from_str(&response).unwrap();
+
+        assert_eq!(response["error"]["message"], "bad input");
+        assert_eq!(response["error"]["data"][CORRELATION_ID_FIELD].as_str().map(str::len), Some(16));
+
+        // Unknown methods fail in the core handler and are covered as well
+        let response = io.handle_request(r#"{"jsonrpc":"2.0","id":2,"method":"missing"}"#, ()).await.unwrap();
+        let response: Value = serde_json::from_str(&response).unwrap();
+        assert!(response["error"]["data"][CORRELATION_ID_FIELD].is_string());
+    }
+
+    #[test]
+    fn test_attach_preserves_existing_data() {
+        let mut error = jsonrpc_core::Error::internal_error();
+        error.data = Some(json!({ "reason": "x" }));
+        attach_correlation_id(&mut error, "abc");
+        assert_eq!(error.data, Some(json!({ "reason": "x", "correlation_id": "abc" })));
+
+        let mut error = jsonrpc_core::Error::internal_error();
+        error.data = Some(json!("opaque"));
+        attach_correlation_id(&mut error, "abc");
+        assert_eq!(error.data, Some(json!("opaque")));
+    }
+
+    #[test]
+    fn test_log_levels_change_and_round_trip() {
+        let levels = LogLevels::parse("warn,rpc=info,rpc::eth_compat", LevelFilter::Info).unwrap();
+        assert_eq!(levels.level_for("p2p::peer"), LevelFilter::Warn);
+        assert_eq!(levels.level_for("rpc::lib"), LevelFilter::Info);
+        assert_eq!(levels.level_for("rpc::eth_compat"), LevelFilter::Trace);
+        assert!(LogLevels::parse("rpc=loud", LevelFilter::Info).is_err());
+
+        // Changes are visible to the logger at once and the directives parse back to the same levels
+        let file = std::env::temp_dir().join(format!("ubi-log-levels-{}", std::process::id()));
+        let handle = LogHandle::new(levels, Some(file.clone()));
+        handle.set_level("p2p", LevelFilter::Off);
+        handle.set_level(DEFAULT_LOG_TARGET, LevelFilter::Debug);
+        assert_eq!(handle.levels().level_for("p2p::peer"), LevelFilter::Off);
+        assert_eq!(handle.levels().level_for("node"), LevelFilter::Debug);
+        assert_eq!(handle.levels().max_level(), LevelFilter::Trace);
+
+        handle.persist().unwrap();
+        let saved = std::fs::read_to_string(&file).unwrap();
+        std::fs::remove_file(&file).unwrap();
+        assert_eq!(saved.trim(), "debug,p2p=off,rpc=info,rpc::eth_compat=trace");
+        assert_eq!(LogLevels::parse(&saved, LevelFilter::Info).unwrap(), handle.levels());
+        assert_eq!(serde_json::to_value(handle.levels()).unwrap()["targets"]["p2p"], "off");
+        assert!(LogHandle::new(LogLevels::new(LevelFilter::Info), None).persist().is_err());
+    }
+
+    #[test]
+    fn test_log_format_parsing() {
+        assert_eq!("json".parse::<LogFormat>(), Ok(LogFormat::Json));
+        assert_eq!("TEXT".parse::<LogFormat>(), Ok(LogFormat::Text));
+        assert!("xml".parse::<LogFormat>().is_err());
+    }
+}
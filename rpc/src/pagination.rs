@@ -0,0 +1,102 @@
+//! Cursor Pagination
+//!
+//! Methods that return lists which grow with the chain (accounts, supply
+//! events) return one page at a time instead of the whole list, so a single
+//! call never builds an unbounded JSON response while holding runtime locks.
+//!
+//! A page has the shape `{ "items": [...], "next_cursor": "..." }`. Clients
+//! pass `next_cursor` back as `cursor` to get the following page and stop when
+//! it is null. Cursors are opaque: they name the last item returned, not an
+//! offset, so items added between calls neither repeat nor shift the pages.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Items returned when a request gives no limit
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+
+/// Most items returned in one page, whatever limit is requested
+pub const MAX_PAGE_SIZE: usize = 1000;
+
+/// One page of a list
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Page<T> {
+    /// The items of this page, in list order
+    pub items: Vec<T>,
+    /// Cursor for the next page; None on the last page
+    pub next_cursor: Option<String>,
+}
+
+/// Which page of a list to return
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageRequest {
+    /// Cursor returned with the previous page; None for the first page
+    #[serde(default)]
+    pub cursor: Option<String>,
+    /// Items wanted, capped at `MAX_PAGE_SIZE`
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+impl PageRequest {
+    /// Reads a page request from an optional RPC parameter
+    ///
+    /// # Arguments
+    /// * `param` - `{ "cursor": ..., "limit": ... }`, or null/absent for the first page
+    ///
+    /// # Returns
+    /// The request, or why the parameter is malformed
+    pub fn from_param(param: Option<&Value>) -> Result<Self, String> {
+        match param {
+            None | Some(Value::Null) => Ok(PageRequest::default()),
+            Some(value) => serde_json::from_value(value.clone())
+                .map_err(|e| format!("Invalid page request: {}", e)),
+        }
+    }
+
+    /// Gets the number of items to return
+    pub fn page_size(&self) -> usize {
+        self.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE)
+    }
+}
+
+/// Builds a page from items following the cursor
+///
+/// # Arguments
+/// * `items` - The items after the cursor in list order; at most one more than
+///   the page size is consumed
+/// * `page_size` - Items in the page
+/// * `cursor_of` - Gets the cursor naming an item
+pub fn build_page<T, I, F>(items: I, page_size: usize, cursor_of: F) -> Page<T>
+where
+    I: IntoIterator<Item = T>,
+    F: Fn(&T) -> String,
+{
+    let mut items: Vec<T> = items.into_iter().take(page_size + 1).collect();
+    let next_cursor = if items.len() > page_size {
+        items.truncate(page_size);
+        items.last().map(cursor_of)
+    } else {
+        None
+    };
+
+    Page { items, next_cursor }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_request_limits() {
+        assert_eq!(PageRequest::from_param(None).unwrap().page_size(), DEFAULT_PAGE_SIZE);
+        let request = PageRequest::from_param(Some(&serde_json::json!({ "limit": 1_000_000 }))).unwrap();
+        assert_eq!(request.page_size(), MAX_PAGE_SIZE);
+        assert!(PageRequest::from_param(Some(&serde_json::json!({ "limit": "ten" }))).is_err());
+
+        let page = build_page(1..=5, 3, |n| n.to_string());
+        assert_eq!(page.items, vec![1, 2, 3]);
+        assert_eq!(page.next_cursor.as_deref(), Some("3"));
+        assert_eq!(build_page(4..=5, 3, |n| n.to_string()).next_cursor, None);
+    }
+}
@@ -0,0 +1,418 @@
+//! Proof-of-Work Challenges
+//!
+//! On fully public deployments bots hold their own keys, so signed challenges
+//! do not slow them down. Operators can additionally require a small proof of
+//! work for the faucet and for account creation. A client fetches a challenge
+//! for the endpoint with `ubi_getPowChallenge` and searches for a solution, any
+//! string of up to `MAX_SOLUTION_LENGTH` characters, such that
+//! `keccak256("<challenge>:<solution>")` starts with at least `difficulty` zero
+//! bits. The request then carries `{"challenge", "solution"}`.
+//!
+//! Challenges are valid for one request and until they expire. The difficulty
+//! of new challenges follows the number issued in the last window: at or below
+//! the target volume it is the minimum, and each doubling above the target adds
+//! one bit, which doubles the expected work, up to the maximum.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+use tiny_keccak::{Hasher, Keccak};
+
+/// Default difficulty in leading zero bits, about 65 thousand hashes on average
+pub const DEFAULT_MIN_POW_DIFFICULTY: u32 = 16;
+
+/// Default highest difficulty the controller raises challenges to
+pub const DEFAULT_MAX_POW_DIFFICULTY: u32 = 24;
+
+/// Default number of challenges per window issued at the minimum difficulty
+pub const DEFAULT_POW_TARGET_REQUESTS: u64 = 60;
+
+/// Default length of the window request volume is measured over, in seconds
+pub const DEFAULT_POW_WINDOW_SECS: u64 = 60;
+
+/// Default time a challenge stays valid, in seconds
+pub const DEFAULT_POW_CHALLENGE_TTL_SECS: u64 = 120;
+
+/// Most unexpired challenges held at once; further requests are refused until some expire or are used
+pub const MAX_OUTSTANDING_CHALLENGES: usize = 100_000;
+
+/// Longest solution accepted, in characters
+pub const MAX_SOLUTION_LENGTH: usize = 128;
+
+/// An endpoint that can require proof of work
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PowEndpoint {
+    /// Faucet requests
+    Faucet,
+    /// Account creation
+    CreateAccount,
+}
+
+impl FromStr for PowEndpoint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "faucet" => Ok(PowEndpoint::Faucet),
+            "create-account" | "create_account" => Ok(PowEndpoint::CreateAccount),
+            _ => Err(format!("Invalid proof-of-work endpoint: {} (expected faucet or create-account)", s)),
+        }
+    }
+}
+
+impl fmt::Display for PowEndpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PowEndpoint::Faucet => write!(f, "faucet"),
+            PowEndpoint::CreateAccount => write!(f, "create-account"),
+        }
+    }
+}
+
+/// Which endpoints require proof of work, and how hard it is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PowConfig {
+    /// Endpoints that require a solved challenge; empty disables proof of work
+    pub endpoints: BTreeSet<PowEndpoint>,
+    /// Difficulty in leading zero bits while volume is at or below the target
+    pub min_difficulty: u32,
+    /// Highest difficulty in leading zero bits
+    pub max_difficulty: u32,
+    /// Challenges per window issued at the minimum difficulty
+    pub target_requests: u64,
+    /// Length of the window volume is measured over, in seconds
+    pub window_secs: u64,
+    /// Time a challenge stays valid, in seconds
+    pub challenge_ttl_secs: u64,
+}
+
+impl Default for PowConfig {
+    fn default() -> Self {
+        PowConfig {
+            endpoints: BTreeSet::new(),
+            min_difficulty: DEFAULT_MIN_POW_DIFFICULTY,
+            max_difficulty: DEFAULT_MAX_POW_DIFFICULTY,
+            target_requests: DEFAULT_POW_TARGET_REQUESTS,
+            window_secs: DEFAULT_POW_WINDOW_SECS,
+            challenge_ttl_secs: DEFAULT_POW_CHALLENGE_TTL_SECS,
+        }
+    }
+}
+
+/// A challenge a client must solve before calling an endpoint
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PowChallenge {
+    /// Random nonce issued by the node
+    pub challenge: String,
+    /// The endpoint the solution may be used for
+    pub endpoint: PowEndpoint,
+    /// Leading zero bits the solution's hash must have
+    pub difficulty: u32,
+    /// When the challenge expires, in seconds since epoch
+    pub expires_at: u64,
+}
+
+/// A solved challenge attached to a request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PowSolution {
+    /// The challenge nonce
+    pub challenge: String,
+    /// The string found by the client
+    pub solution: String,
+}
+
+/// Reason a proof of work was refused
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PowError {
+    /// The endpoint requires proof of work and the request carried none
+    Required(PowEndpoint),
+    /// The endpoint does not require proof of work, so no challenge is issued
+    NotRequired(PowEndpoint),
+    /// The challenge was never issued, has been used already or was pruned after expiring
+    UnknownChallenge,
+    /// The challenge has expired
+    Expired,
+    /// The challenge was issued for another endpoint
+    WrongEndpoint {
+        /// Endpoint the challenge was issued for
+        issued_for: PowEndpoint,
+        /// Endpoint the solution was presented to
+        used_for: PowEndpoint,
+    },
+    /// The solution's hash has too few leading zero bits
+    InsufficientWork {
+        /// Bits required
+        required: u32,
+        /// Bits found
+        found: u32,
+    },
+    /// The solution is longer than `MAX_SOLUTION_LENGTH`
+    SolutionTooLong,
+    /// Too many challenges are outstanding
+    TooManyChallenges,
+}
+
+impl PowError {
+    /// Gets the identifier of the reason, for clients to act on
+    pub fn code(&self) -> &'static str {
+        match self {
+            PowError::Required(_) => "pow_required",
+            PowError::NotRequired(_) => "pow_not_required",
+            PowError::UnknownChallenge => "pow_unknown_challenge",
+            PowError::Expired => "pow_expired",
+            PowError::WrongEndpoint { .. } => "pow_wrong_endpoint",
+            PowError::InsufficientWork { .. } | PowError::SolutionTooLong => "pow_invalid_solution",
+            PowError::TooManyChallenges => "pow_busy",
+        }
+    }
+}
+
+impl fmt::Display for PowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PowError::Required(endpoint) => {
+                write!(f, "The {} endpoint requires a proof of work; get a challenge with ubi_getPowChallenge", endpoint)
+            },
+            PowError::NotRequired(endpoint) => write!(f, "The {} endpoint does not require a proof of work", endpoint),
+            PowError::UnknownChallenge => write!(f, "Unknown or already used proof-of-work challenge"),
+            PowError::Expired => write!(f, "The proof-of-work challenge has expired"),
+            PowError::WrongEndpoint { issued_for, used_for } => {
+                write!(f, "The proof-of-work challenge was issued for {}, not {}", issued_for, used_for)
+            },
+            PowError::InsufficientWork { required, found } => {
+                write!(f, "The proof-of-work hash has {} leading zero bits; {} are required", found, required)
+            },
+            PowError::SolutionTooLong => write!(f, "Proof-of-work solutions are at most {} characters", MAX_SOLUTION_LENGTH),
+            PowError::TooManyChallenges => write!(f, "Too many proof-of-work challenges are outstanding; retry later"),
+        }
+    }
+}
+
+impl std::error::Error for PowError {}
+
+/// Computes the hash a solution is judged by
+pub fn solution_hash(challenge: &str, solution: &str) -> [u8; 32] {
+    let mut output = [0u8; 32];
+    let mut hasher = Keccak::v256();
+    hasher.update(challenge.as_bytes());
+    hasher.update(b":");
+    hasher.update(solution.as_bytes());
+    hasher.finalize(&mut output);
+    output
+}
+
+/// Counts the leading zero bits of a hash
+pub fn leading_zero_bits(hash: &[u8]) -> u32 {
+    let mut bits = 0;
+    for byte in hash {
+        bits += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+    bits
+}
+
+/// Outstanding challenges and recent volume
+#[derive(Debug, Default)]
+struct PowState {
+    /// Unused challenges by nonce
+    challenges: HashMap<String, PowChallenge>,
+    /// When each challenge of the current window was issued, oldest first
+    issued: VecDeque<u64>,
+}
+
+/// Issues and checks proof-of-work challenges
+#[derive(Debug, Default)]
+pub struct PowGuard {
+    config: PowConfig,
+    state: Mutex<PowState>,
+}
+
+impl PowGuard {
+    /// Creates a guard with the given configuration
+    pub fn new(config: PowConfig) -> Self {
+        PowGuard { config, state: Mutex::new(PowState::default()) }
+    }
+
+    /// Gets the configuration
+    pub fn config(&self) -> &PowConfig {
+        &self.config
+    }
+
+    /// Checks whether an endpoint requires proof of work
+    pub fn is_required(&self, endpoint: PowEndpoint) -> bool {
+        self.config.endpoints.contains(&endpoint)
+    }
+
+    /// Gets the difficulty a challenge issued now would have
+    pub fn current_difficulty(&self, now: u64) -> u32 {
+        let mut state = self.state.lock().unwrap();
+        self.prune_window(&mut state, now);
+        self.difficulty_for(state.issued.len() as u64)
+    }
+
+    /// Issues a challenge for an endpoint that requires proof of work
+    ///
+    /// # Arguments
+    /// * `endpoint` - The endpoint the solution will be presented to
+    /// * `nonce` - Random nonce for the challenge
+    /// * `now` - Current time in seconds since epoch
+    ///
+    /// # Returns
+    /// The challenge, or an error if the endpoint does not require proof of
+    /// work or too many challenges are outstanding
+    pub fn issue(&self, endpoint: PowEndpoint, nonce: String, now: u64) -> Result<PowChallenge, PowError> {
+        if !self.is_required(endpoint) {
+            return Err(PowError::NotRequired(endpoint));
+        }
+
+        let mut state = self.state.lock().unwrap();
+        if state.challenges.len() >= MAX_OUTSTANDING_CHALLENGES {
+            state.challenges.retain(|_, challenge| challenge.expires_at >= now);
+            if state.challenges.len() >= MAX_OUTSTANDING_CHALLENGES {
+                return Err(PowError::TooManyChallenges);
+            }
+        }
+
+        // The difficulty counts the challenges issued before this one
+        self.prune_window(&mut state, now);
+        let difficulty = self.difficulty_for(state.issued.len() as u64);
+        state.issued.push_back(now);
+
+        let challenge = PowChallenge {
+            challenge: nonce.clone(),
+            endpoint,
+            difficulty,
+            expires_at: now + self.config.challenge_ttl_secs,
+        };
+        state.challenges.insert(nonce, challenge.clone());
+        Ok(challenge)
+    }
+
+    /// Checks a request's proof of work, using its challenge up
+    ///
+    /// Endpoints that do not require proof of work accept any request.
+    ///
+    /// # Arguments
+    /// * `endpoint` - The endpoint the request was made to
+    /// * `solution` - The solved challenge the request carried, if any
+    /// * `now` - Current time in seconds since epoch
+    ///
+    /// # Returns
+    /// Ok if the request may proceed, or why it was refused
+    pub fn verify(&self, endpoint: PowEndpoint, solution: Option<&PowSolution>, now: u64) -> Result<(), PowError> {
+        if !self.is_required(endpoint) {
+            return Ok(());
+        }
+        let solution = solution.ok_or(PowError::Required(endpoint))?;
+        if solution.solution.chars().count() > MAX_SOLUTION_LENGTH {
+            return Err(PowError::SolutionTooLong);
+        }
+
+        // Whatever the outcome, the challenge cannot be tried again
+        let challenge = self.state.lock().unwrap().challenges.remove(&solution.challenge)
+            .ok_or(PowError::UnknownChallenge)?;
+        if now > challenge.expires_at {
+            return Err(PowError::Expired);
+        }
+        if challenge.endpoint != endpoint {
+            return Err(PowError::WrongEndpoint { issued_for: challenge.endpoint, used_for: endpoint });
+        }
+
+        let found = leading_zero_bits(&solution_hash(&challenge.challenge, &solution.solution));
+        if found < challenge.difficulty {
+            return Err(PowError::InsufficientWork { required: challenge.difficulty, found });
+        }
+        Ok(())
+    }
+
+    /// Forgets issue times that left the window
+    fn prune_window(&self, state: &mut PowState, now: u64) {
+        let start = now.saturating_sub(self.config.window_secs);
+        while state.issued.front().is_some_and(|&issued| issued <= start) {
+            state.issued.pop_front();
+        }
+    }
+
+    /// Maps the volume of the current window to a difficulty
+    fn difficulty_for(&self, recent: u64) -> u32 {
+        let ratio = recent / self.config.target_requests.max(1);
+        let extra = if ratio <= 1 { 0 } else { ratio.ilog2() };
+        (self.config.min_difficulty + extra).min(self.config.max_difficulty.max(self.config.min_difficulty))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_735_689_600;
+
+    fn guard() -> PowGuard {
+        PowGuard::new(PowConfig {
+            endpoints: [PowEndpoint::Faucet].into_iter().collect(),
+            min_difficulty: 8,
+            max_difficulty: 10,
+            target_requests: 4,
+            window_secs: 60,
+            challenge_ttl_secs: 30,
+        })
+    }
+
+    pub(crate) fn solve(challenge: &PowChallenge) -> PowSolution {
+        let solution = (0u64..)
+            .map(|n| n.to_string())
+            .find(|n| leading_zero_bits(&solution_hash(&challenge.challenge, n)) >= challenge.difficulty)
+            .unwrap();
+        PowSolution { challenge: challenge.challenge.clone(), solution }
+    }
+
+    #[test]
+    fn test_solutions_are_single_use_and_expire() {
+        let guard = guard();
+        assert_eq!(guard.issue(PowEndpoint::CreateAccount, "0xa".to_string(), NOW),
+                   Err(PowError::NotRequired(PowEndpoint::CreateAccount)));
+        assert_eq!(guard.verify(PowEndpoint::CreateAccount, None, NOW), Ok(()));
+        assert_eq!(guard.verify(PowEndpoint::Faucet, None, NOW), Err(PowError::Required(PowEndpoint::Faucet)));
+
+        // A solved challenge is accepted once; the replay is refused
+        let challenge = guard.issue(PowEndpoint::Faucet, "0x1".to_string(), NOW).unwrap();
+        let solution = solve(&challenge);
+        assert_eq!(guard.verify(PowEndpoint::Faucet, Some(&solution), NOW + 5), Ok(()));
+        assert_eq!(guard.verify(PowEndpoint::Faucet, Some(&solution), NOW + 5), Err(PowError::UnknownChallenge));
+
+        // A solution presented after expiry is refused, and so is a wrong one
+        let challenge = guard.issue(PowEndpoint::Faucet, "0x2".to_string(), NOW).unwrap();
+        let late = solve(&challenge);
+        assert_eq!(guard.verify(PowEndpoint::Faucet, Some(&late), NOW + 31), Err(PowError::Expired));
+        let challenge = guard.issue(PowEndpoint::Faucet, "0x3".to_string(), NOW).unwrap();
+        let wrong = (0u64..).map(|n| n.to_string())
+            .find(|n| leading_zero_bits(&solution_hash(&challenge.challenge, n)) < challenge.difficulty)
+            .unwrap();
+        let refused = guard.verify(PowEndpoint::Faucet, Some(&PowSolution { challenge: "0x3".to_string(), solution: wrong }), NOW);
+        assert!(matches!(refused, Err(PowError::InsufficientWork { required: 8, .. })), "{:?}", refused);
+    }
+
+    #[test]
+    fn test_difficulty_follows_request_volume() {
+        let guard = guard();
+        assert_eq!(guard.current_difficulty(NOW), 8);
+
+        // Up to twice the target stays at the minimum, then each doubling adds a bit up to the maximum
+        let difficulties: Vec<u32> = (0..40)
+            .map(|i| guard.issue(PowEndpoint::Faucet, format!("0x{:x}", i), NOW).unwrap().difficulty)
+            .collect();
+        assert!(difficulties[..8].iter().all(|&d| d == 8));
+        assert_eq!(difficulties[8], 9);
+        assert_eq!(difficulties[16], 10);
+        assert_eq!(difficulties[39], 10);
+
+        // Once the window has passed the difficulty falls back
+        assert_eq!(guard.current_difficulty(NOW + 59), 10);
+        assert_eq!(guard.current_difficulty(NOW + 60), 8);
+    }
+}
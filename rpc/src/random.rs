@@ -0,0 +1,117 @@
+//! Randomness Source
+//!
+//! This module provides the randomness used for transaction hashes, block hashes
+//! and subscription ids. Production nodes draw from the thread-local RNG, while
+//! tests and deterministic dev mode use a ChaCha generator seeded from config or
+//! the environment so that runs can be replayed exactly.
+
+use parking_lot::Mutex;
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use std::sync::Arc;
+
+/// Environment variable holding the seed for deterministic runs
+pub const RANDOM_SEED_ENV_VAR: &str = "UBI_RANDOM_SEED";
+
+/// Source of random bytes shared by the RPC handlers
+///
+/// Cloning a seeded source shares the underlying generator, so every handler
+/// built from the same `RpcHandler` draws from one reproducible stream.
+#[derive(Clone)]
+pub struct RandomSource {
+    /// Seeded generator, or None to use the thread-local RNG
+    seeded: Option<Arc<Mutex<ChaCha20Rng>>>,
+    /// Seed the generator was created from
+    seed: Option<u64>,
+}
+
+impl RandomSource {
+    /// Creates a source backed by the thread-local RNG
+    pub fn thread() -> Self {
+        RandomSource {
+            seeded: None,
+            seed: None,
+        }
+    }
+
+    /// Creates a deterministic source seeded with the given value
+    pub fn seeded(seed: u64) -> Self {
+        RandomSource {
+            seeded: Some(Arc::new(Mutex::new(ChaCha20Rng::seed_from_u64(seed)))),
+            seed: Some(seed),
+        }
+    }
+
+    /// Creates a seeded source if `UBI_RANDOM_SEED` is set, otherwise a thread-local one
+    pub fn from_env() -> Self {
+        match std::env::var(RANDOM_SEED_ENV_VAR).ok().and_then(|value| value.parse::<u64>().ok()) {
+            Some(seed) => Self::seeded(seed),
+            None => Self::thread(),
+        }
+    }
+
+    /// Gets the seed if this source is deterministic
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// Fills the buffer with random bytes
+    pub fn fill_bytes(&self, dest: &mut [u8]) {
+        match &self.seeded {
+            Some(rng) => rng.lock().fill_bytes(dest),
+            None => rand::thread_rng().fill_bytes(dest),
+        }
+    }
+
+    /// Generates a random 32-byte hash as a 0x-prefixed hex string
+    pub fn random_hash(&self) -> String {
+        let mut bytes = [0u8; 32];
+        self.fill_bytes(&mut bytes);
+        format!("0x{}", hex::encode(bytes))
+    }
+}
+
+impl Default for RandomSource {
+    fn default() -> Self {
+        Self::from_env()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_sources_are_reproducible() {
+        let a = RandomSource::seeded(7);
+        let b = RandomSource::seeded(7);
+
+        assert_eq!(a.random_hash(), b.random_hash());
+        assert_eq!(a.random_hash(), b.random_hash());
+        assert_eq!(a.seed(), Some(7));
+    }
+
+    #[test]
+    fn test_clones_share_the_stream() {
+        let a = RandomSource::seeded(7);
+        let reference = RandomSource::seeded(7);
+        let clone = a.clone();
+
+        let first = a.random_hash();
+        let second = clone.random_hash();
+
+        assert_eq!(first, reference.random_hash());
+        assert_eq!(second, reference.random_hash());
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn test_thread_source_has_no_seed() {
+        let source = RandomSource::thread();
+        let hash = source.random_hash();
+
+        assert!(source.seed().is_none());
+        assert_eq!(hash.len(), 66);
+        assert!(hash.starts_with("0x"));
+    }
+}
@@ -0,0 +1,436 @@
+//! Raw Transaction Decoding
+//!
+//! Wallets submit transfers through eth_sendRawTransaction as RLP-encoded
+//! signed transactions. This module walks the RLP envelope of legacy,
+//! EIP-2930 and EIP-1559 transactions to read the recipient and the value,
+//! and recovers the sender from the secp256k1 signature over the rest of the
+//! fields. Anything it cannot decode with certainty is an error: a transfer is
+//! never sent to a guessed recipient, for a guessed amount or from a guessed
+//! sender.
+
+use crate::signature::{keccak256, recover_prehash_signer};
+use primitive_types::U256;
+use std::fmt;
+
+/// EIP-2930 typed transaction envelope
+const ACCESS_LIST_TX_TYPE: u8 = 0x01;
+
+/// EIP-1559 typed transaction envelope
+const DYNAMIC_FEE_TX_TYPE: u8 = 0x02;
+
+/// Largest raw transaction accepted, in bytes; geth's transaction pool uses the same limit
+pub const MAX_RAW_TRANSACTION_BYTES: usize = 128 * 1024;
+
+/// Error types for raw transaction decoding
+#[derive(Debug, Clone, PartialEq)]
+pub enum RawTransactionError {
+    /// The data ends before the transaction does
+    Truncated,
+    /// The data is not a well-formed RLP transaction
+    Malformed(String),
+    /// The typed envelope is not one of the supported types
+    UnsupportedType(u8),
+    /// The transaction has no recipient, so it would deploy a contract
+    ContractCreation,
+    /// The recipient field is not 20 bytes long
+    InvalidRecipient(usize),
+    /// The value is longer than 32 bytes
+    ValueTooLarge,
+    /// The nonce is longer than 8 bytes
+    NonceTooLarge,
+    /// The transaction is longer than `MAX_RAW_TRANSACTION_BYTES`
+    TooLarge(usize),
+    /// A legacy transaction signed without a chain id, which any chain would accept
+    MissingChainId,
+    /// The transaction is signed for another chain
+    WrongChainId {
+        /// This chain's id
+        expected: u64,
+        /// The chain id the transaction is signed for
+        actual: u64,
+    },
+    /// No valid sender can be recovered from the signature
+    InvalidSignature(String),
+}
+
+impl fmt::Display for RawTransactionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RawTransactionError::Truncated => write!(f, "Raw transaction is truncated"),
+            RawTransactionError::Malformed(reason) => write!(f, "Raw transaction is malformed: {}", reason),
+            RawTransactionError::UnsupportedType(tx_type) => write!(f, "Unsupported transaction type 0x{:02x}", tx_type),
+            RawTransactionError::ContractCreation => write!(f, "Contract creation is not supported; UBI Chain has no EVM"),
+            RawTransactionError::InvalidRecipient(length) => write!(f, "Recipient must be 20 bytes, not {}", length),
+            RawTransactionError::ValueTooLarge => write!(f, "Transaction value is longer than 32 bytes"),
+            RawTransactionError::NonceTooLarge => write!(f, "Transaction nonce is longer than 8 bytes"),
+            RawTransactionError::TooLarge(length) => write!(
+                f, "Raw transaction of {} bytes exceeds the {} byte limit", length, MAX_RAW_TRANSACTION_BYTES
+            ),
+            RawTransactionError::MissingChainId => write!(f, "Transaction is not replay-protected; sign it with a chain id (EIP-155)"),
+            RawTransactionError::WrongChainId { expected, actual } => write!(
+                f, "Transaction is signed for chain id {}, but this chain's id is {}", actual, expected
+            ),
+            RawTransactionError::InvalidSignature(reason) => write!(f, "Invalid transaction signature: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for RawTransactionError {}
+
+/// Transfer fields decoded from a raw transaction
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawTransfer {
+    /// Sender address recovered from the signature, lowercase and 0x-prefixed
+    pub from: String,
+    /// Chain id the transaction is signed for
+    pub chain_id: u64,
+    /// Sender's transaction count when the transaction was signed
+    pub nonce: u64,
+    /// Recipient address, lowercase and 0x-prefixed
+    pub to: String,
+    /// Amount in wei
+    pub value_wei: U256,
+}
+
+/// One decoded RLP item
+struct RlpItem<'a> {
+    /// Whether the item is a list rather than a byte string
+    is_list: bool,
+    /// The item's payload, without its header
+    payload: &'a [u8],
+    /// The item as encoded, header included
+    encoded: &'a [u8],
+}
+
+/// Decodes the RLP item at the start of `data`
+///
+/// # Returns
+/// The item and the number of bytes it occupies, header included
+fn decode_item(data: &[u8]) -> Result<(RlpItem<'_>, usize), RawTransactionError> {
+    let prefix = *data.first().ok_or(RawTransactionError::Truncated)?;
+    let (is_list, header_len, payload_len) = match prefix {
+        0x00..=0x7f => return Ok((RlpItem { is_list: false, payload: &data[..1], encoded: &data[..1] }, 1)),
+        0x80..=0xb7 => (false, 1, (prefix - 0x80) as usize),
+        0xb8..=0xbf => (false, 1 + (prefix - 0xb7) as usize, read_length(data, (prefix - 0xb7) as usize)?),
+        0xc0..=0xf7 => (true, 1, (prefix - 0xc0) as usize),
+        0xf8..=0xff => (true, 1 + (prefix - 0xf7) as usize, read_length(data, (prefix - 0xf7) as usize)?),
+    };
+
+    let end = header_len.checked_add(payload_len).ok_or(RawTransactionError::Truncated)?;
+    let payload = data.get(header_len..end).ok_or(RawTransactionError::Truncated)?;
+    Ok((RlpItem { is_list, payload, encoded: &data[..end] }, end))
+}
+
+/// Reads the big-endian length that follows a long-form RLP prefix
+fn read_length(data: &[u8], length_of_length: usize) -> Result<usize, RawTransactionError> {
+    let bytes = data.get(1..1 + length_of_length).ok_or(RawTransactionError::Truncated)?;
+    if bytes.len() > std::mem::size_of::<usize>() {
+        return Err(RawTransactionError::Truncated);
+    }
+    Ok(bytes.iter().fold(0usize, |length, byte| (length << 8) | *byte as usize))
+}
+
+/// Encodes an RLP header for a payload of the given length
+fn encode_header(short_offset: u8, length: usize) -> Vec<u8> {
+    if length <= 55 {
+        return vec![short_offset + length as u8];
+    }
+    let length_bytes: Vec<u8> = length.to_be_bytes().into_iter().skip_while(|byte| *byte == 0).collect();
+    let mut header = vec![short_offset + 55 + length_bytes.len() as u8];
+    header.extend(length_bytes);
+    header
+}
+
+/// Encodes a byte string as an RLP item
+fn encode_bytes(bytes: &[u8]) -> Vec<u8> {
+    if bytes.len() == 1 && bytes[0] < 0x80 {
+        return bytes.to_vec();
+    }
+    let mut encoded = encode_header(0x80, bytes.len());
+    encoded.extend_from_slice(bytes);
+    encoded
+}
+
+/// Encodes an integer as an RLP item, big-endian without leading zeros
+fn encode_u64(value: u64) -> Vec<u8> {
+    let bytes: Vec<u8> = value.to_be_bytes().into_iter().skip_while(|byte| *byte == 0).collect();
+    encode_bytes(&bytes)
+}
+
+/// Encodes already encoded items as an RLP list
+fn encode_list(items: &[u8]) -> Vec<u8> {
+    let mut encoded = encode_header(0xc0, items.len());
+    encoded.extend_from_slice(items);
+    encoded
+}
+
+/// Reads a byte string item as an integer of at most 8 bytes
+fn read_u64(item: &RlpItem<'_>, field: &str) -> Result<u64, RawTransactionError> {
+    if item.is_list || item.payload.len() > 8 {
+        return Err(RawTransactionError::Malformed(format!("{} must be an integer of at most 8 bytes", field)));
+    }
+    Ok(item.payload.iter().fold(0u64, |value, byte| (value << 8) | *byte as u64))
+}
+
+/// Reads r or s of a signature, left-padded to 32 bytes
+fn read_scalar(item: &RlpItem<'_>) -> Result<[u8; 32], RawTransactionError> {
+    if item.is_list || item.payload.len() > 32 {
+        return Err(RawTransactionError::InvalidSignature("r and s must be at most 32 bytes".to_string()));
+    }
+    let mut scalar = [0u8; 32];
+    scalar[32 - item.payload.len()..].copy_from_slice(item.payload);
+    Ok(scalar)
+}
+
+/// Decodes a signed raw transaction and recovers its sender
+///
+/// # Arguments
+/// * `bytes` - The transaction bytes, hex-decoded from the RPC parameter
+/// * `chain_id` - This chain's id; transactions signed for another chain are refused
+///
+/// # Returns
+/// The transfer, or why the transaction cannot be executed as one
+pub fn decode_transfer(bytes: &[u8], chain_id: u64) -> Result<RawTransfer, RawTransactionError> {
+    if bytes.len() > MAX_RAW_TRANSACTION_BYTES {
+        return Err(RawTransactionError::TooLarge(bytes.len()));
+    }
+    let first = *bytes.first().ok_or(RawTransactionError::Truncated)?;
+
+    // Fields before `to`: nonce, gas price, gas limit (legacy) with chain id and fee fields for typed envelopes.
+    // Every envelope ends with the three signature fields
+    let (tx_type, envelope, field_count, nonce_index, to_index) = match first {
+        0xc0..=0xff => (None, bytes, 9, 0, 3),
+        ACCESS_LIST_TX_TYPE => (Some(first), &bytes[1..], 11, 1, 4),
+        DYNAMIC_FEE_TX_TYPE => (Some(first), &bytes[1..], 12, 1, 5),
+        other => return Err(RawTransactionError::UnsupportedType(other)),
+    };
+
+    let (list, consumed) = decode_item(envelope)?;
+    if !list.is_list {
+        return Err(RawTransactionError::Malformed("transaction is not an RLP list".to_string()));
+    }
+    if consumed != envelope.len() {
+        return Err(RawTransactionError::Malformed("trailing bytes after the transaction".to_string()));
+    }
+
+    let mut fields = Vec::with_capacity(field_count);
+    let mut rest = list.payload;
+    while !rest.is_empty() {
+        let (item, consumed) = decode_item(rest)?;
+        fields.push(item);
+        rest = &rest[consumed..];
+    }
+    if fields.len() < field_count {
+        return Err(RawTransactionError::Truncated);
+    }
+    if fields.len() > field_count {
+        return Err(RawTransactionError::Malformed(format!("expected {} fields, found {}", field_count, fields.len())));
+    }
+
+    let nonce = &fields[nonce_index];
+    let to = &fields[to_index];
+    let value = &fields[to_index + 1];
+    if nonce.is_list || to.is_list || value.is_list {
+        return Err(RawTransactionError::Malformed("nonce, recipient and value must be byte strings".to_string()));
+    }
+    if nonce.payload.len() > 8 {
+        return Err(RawTransactionError::NonceTooLarge);
+    }
+    match to.payload.len() {
+        0 => return Err(RawTransactionError::ContractCreation),
+        20 => {},
+        length => return Err(RawTransactionError::InvalidRecipient(length)),
+    }
+    if value.payload.len() > 32 {
+        return Err(RawTransactionError::ValueTooLarge);
+    }
+
+    // The signature covers every field before it; legacy transactions commit to the chain id through v (EIP-155)
+    let (signed_fields, signature) = fields.split_at(field_count - 3);
+    let unsigned: Vec<u8> = signed_fields.iter().flat_map(|field| field.encoded.iter().copied()).collect();
+    let (signed_chain_id, y_parity, signing_payload) = match tx_type {
+        None => {
+            let v = read_u64(&signature[0], "v")?;
+            if v == 27 || v == 28 {
+                return Err(RawTransactionError::MissingChainId);
+            }
+            if v < 35 {
+                return Err(RawTransactionError::InvalidSignature(format!("invalid v {}", v)));
+            }
+            let signed_chain_id = (v - 35) / 2;
+            let mut items = unsigned;
+            items.extend(encode_u64(signed_chain_id));
+            items.extend([0x80, 0x80]);
+            (signed_chain_id, (v - 35) % 2, encode_list(&items))
+        },
+        Some(tx_type) => {
+            let y_parity = read_u64(&signature[0], "y parity")?;
+            if y_parity > 1 {
+                return Err(RawTransactionError::InvalidSignature(format!("invalid y parity {}", y_parity)));
+            }
+            let mut payload = vec![tx_type];
+            payload.extend(encode_list(&unsigned));
+            (read_u64(&fields[0], "chain id")?, y_parity, payload)
+        },
+    };
+    if signed_chain_id != chain_id {
+        return Err(RawTransactionError::WrongChainId { expected: chain_id, actual: signed_chain_id });
+    }
+
+    let mut r_s = [0u8; 64];
+    r_s[..32].copy_from_slice(&read_scalar(&signature[1])?);
+    r_s[32..].copy_from_slice(&read_scalar(&signature[2])?);
+    let from = recover_prehash_signer(&keccak256(&signing_payload), &r_s, y_parity as u8)
+        .map_err(|e| RawTransactionError::InvalidSignature(e.to_string()))?;
+
+    Ok(RawTransfer {
+        from,
+        chain_id: signed_chain_id,
+        nonce: nonce.payload.iter().fold(0u64, |nonce, byte| (nonce << 8) | *byte as u64),
+        to: format!("0x{}", hex::encode(to.payload)),
+        value_wei: U256::from_big_endian(value.payload),
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::signature::public_key_address;
+    use k256::ecdsa::SigningKey;
+
+    // Legacy transfer of 1 ether to 0x3535…35 from the EIP-155 example, signed for chain id 1
+    const EIP155_TX: &str = "f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83";
+
+    /// Signs a transfer with the key made of `key_byte` repeated, returning the signer address and the transaction
+    ///
+    /// `tx_type` is 0 for an EIP-155 legacy transaction, otherwise the typed envelope to use.
+    pub(crate) fn signed_transfer(key_byte: u8, tx_type: u8, chain_id: u64, nonce: u64, to: &str, value_wei: U256) -> (String, Vec<u8>) {
+        let mut value = [0u8; 32];
+        value_wei.to_big_endian(&mut value);
+        let value: Vec<u8> = value.into_iter().skip_while(|byte| *byte == 0).collect();
+        let transfer = [
+            encode_bytes(&hex::decode(&to[2..]).unwrap()),
+            encode_bytes(&value),
+            encode_bytes(&[]),
+        ].concat();
+        let fees = [encode_u64(1_000_000_000), encode_u64(21_000)].concat();
+        let unsigned = match tx_type {
+            0 => [encode_u64(nonce), fees, transfer].concat(),
+            ACCESS_LIST_TX_TYPE => [encode_u64(chain_id), encode_u64(nonce), fees, transfer, encode_list(&[])].concat(),
+            _ => [encode_u64(chain_id), encode_u64(nonce), encode_u64(1), fees, transfer, encode_list(&[])].concat(),
+        };
+        let signing_payload = match tx_type {
+            0 => encode_list(&[unsigned.clone(), encode_u64(chain_id), vec![0x80, 0x80]].concat()),
+            _ => [vec![tx_type], encode_list(&unsigned)].concat(),
+        };
+
+        let signing_key = SigningKey::from_slice(&[key_byte; 32]).unwrap();
+        let (signature, recovery_id) = signing_key.sign_prehash_recoverable(&keccak256(&signing_payload)).unwrap();
+        let v = match tx_type {
+            0 => chain_id * 2 + 35 + recovery_id.to_byte() as u64,
+            _ => recovery_id.to_byte() as u64,
+        };
+        let r_s = signature.to_bytes();
+        let scalar = |bytes: &[u8]| encode_bytes(&bytes.iter().copied().skip_while(|byte| *byte == 0).collect::<Vec<u8>>());
+        let signed = encode_list(&[unsigned, encode_u64(v), scalar(&r_s[..32]), scalar(&r_s[32..])].concat());
+        let envelope = match tx_type {
+            0 => signed,
+            _ => [vec![tx_type], signed].concat(),
+        };
+        (public_key_address(signing_key.verifying_key()), envelope)
+    }
+
+    #[test]
+    fn test_decode_transfer() {
+        let bytes = hex::decode(EIP155_TX).unwrap();
+        let transfer = decode_transfer(&bytes, 1).unwrap();
+        assert_eq!(transfer.from, "0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f");
+        assert_eq!(transfer.chain_id, 1);
+        assert_eq!(transfer.nonce, 9);
+        assert_eq!(transfer.to, "0x3535353535353535353535353535353535353535");
+        assert_eq!(transfer.value_wei, U256::exp10(18));
+
+        // Every truncation is an error, never a partial transfer
+        for end in 0..bytes.len() {
+            assert!(decode_transfer(&bytes[..end], 1).is_err());
+        }
+
+        // The same transaction with `to` empty deploys a contract
+        let creation = hex::decode(EIP155_TX.replace("f86c", "f858").replace("943535353535353535353535353535353535353535", "80")).unwrap();
+        assert_eq!(decode_transfer(&creation, 1), Err(RawTransactionError::ContractCreation));
+
+        assert_eq!(decode_transfer(&[0x05, 0xc0], 1), Err(RawTransactionError::UnsupportedType(0x05)));
+        assert!(decode_transfer(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff], 1).is_err());
+    }
+
+    #[test]
+    fn test_sender_is_recovered_for_this_chain_only() {
+        let to = "0x3535353535353535353535353535353535353535";
+        for tx_type in [0, ACCESS_LIST_TX_TYPE, DYNAMIC_FEE_TX_TYPE] {
+            let (alice, bytes) = signed_transfer(1, tx_type, 2030, 4, to, U256::exp10(18));
+            let transfer = decode_transfer(&bytes, 2030).unwrap();
+            assert_eq!((transfer.from, transfer.nonce, transfer.value_wei), (alice.clone(), 4, U256::exp10(18)));
+
+            let (bob, bytes) = signed_transfer(2, tx_type, 2030, 4, to, U256::exp10(18));
+            assert_ne!(bob, alice);
+            assert_eq!(decode_transfer(&bytes, 2030).unwrap().from, bob);
+
+            // Replayed on another chain, the transaction is refused
+            assert_eq!(decode_transfer(&bytes, 1), Err(RawTransactionError::WrongChainId { expected: 1, actual: 2030 }));
+        }
+
+        // A tampered value recovers some other address, never the signer
+        let (alice, mut bytes) = signed_transfer(1, 0, 2030, 0, to, U256::exp10(18));
+        let value_at = bytes.windows(8).position(|window| window == U256::exp10(18).low_u64().to_be_bytes()).unwrap();
+        bytes[value_at] ^= 0x01;
+        assert!(decode_transfer(&bytes, 2030).map_or(true, |transfer| transfer.from != alice));
+
+        // Unprotected legacy signatures (v = 27 or 28) could be replayed on any chain
+        let unprotected = hex::decode(EIP155_TX.replace("8025a0", "801ba0")).unwrap();
+        assert_eq!(decode_transfer(&unprotected, 1), Err(RawTransactionError::MissingChainId));
+    }
+
+    #[test]
+    fn test_fuzzed_transactions_decode_or_fail_cleanly() {
+        let legacy = hex::decode(EIP155_TX).unwrap();
+        let (_, dynamic_fee) = signed_transfer(3, DYNAMIC_FEE_TX_TYPE, 1, 1, "0x3535353535353535353535353535353535353535", U256::exp10(18));
+        assert_eq!(decode_transfer(&dynamic_fee, 1).unwrap().value_wei, U256::exp10(18));
+        let seeds = [legacy, dynamic_fee];
+
+        // Xorshift keeps the cases reproducible without a fuzzing dependency
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        for case in 0..50_000 {
+            let mut bytes = seeds[case % seeds.len()].clone();
+            for _ in 0..1 + next() % 4 {
+                let position = next() as usize % bytes.len();
+                match next() % 5 {
+                    0 => bytes[position] ^= 1 << (next() % 8),
+                    1 => bytes[position] = next() as u8,
+                    // Long-form prefixes claiming lengths far beyond the data
+                    2 => bytes[position] = 0xb8 + (next() % 16) as u8,
+                    3 => bytes.insert(position, next() as u8),
+                    _ => bytes.truncate(position.max(1)),
+                }
+            }
+
+            // A decoded transfer always has a well-formed recipient and fits the limits
+            if let Ok(transfer) = decode_transfer(&bytes, 1) {
+                assert_eq!(transfer.to.len(), 42, "case {}", case);
+                assert!(transfer.to.starts_with("0x"));
+                assert_eq!(transfer.from.len(), 42, "case {}", case);
+            }
+        }
+
+        assert_eq!(
+            decode_transfer(&vec![0xc0; MAX_RAW_TRANSACTION_BYTES + 1], 1),
+            Err(RawTransactionError::TooLarge(MAX_RAW_TRANSACTION_BYTES + 1))
+        );
+    }
+}
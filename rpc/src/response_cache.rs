@@ -0,0 +1,248 @@
+//! RPC Response Cache
+//!
+//! Wallets poll a few cheap methods constantly: MetaMask calls `eth_blockNumber`
+//! and `eth_getBlockByNumber("latest")` every few seconds in every connected
+//! tab. Their answers only change when a block is sealed, so the cache keeps
+//! them, keyed by method and parameters, and serves repeats without taking any
+//! state lock.
+//!
+//! Sealing a block invalidates every entry at once. Each entry also expires
+//! after a short TTL as a safety net for changes that do not go through a block.
+//! A response computed while a block was being sealed is never stored, so a
+//! poll after a block event always sees the new block.
+
+use jsonrpc_core::futures::future::Either;
+use jsonrpc_core::middleware::Middleware;
+use jsonrpc_core::{BoxFuture, Call, Metadata, Output, Params};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Default time an entry is served before it is recomputed, in milliseconds
+pub const DEFAULT_RESPONSE_CACHE_TTL_MS: u64 = 1000;
+
+/// Idempotent methods whose responses only change when a block is sealed
+const CACHED_METHODS: &[&str] = &["eth_chainId", "eth_blockNumber", "eth_gasPrice", "eth_getBlockByNumber"];
+
+/// A cached response and when it was computed
+struct CacheEntry {
+    value: Value,
+    stored_at: Instant,
+}
+
+/// Hit, miss and invalidation counts since startup
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResponseCacheMetrics {
+    /// Requests answered from the cache
+    pub hits: u64,
+    /// Requests for cached methods that ran the method
+    pub misses: u64,
+    /// Times the cache was cleared because a block was sealed
+    pub invalidations: u64,
+}
+
+/// Responses of hot methods, cleared whenever a block is sealed
+pub struct ResponseCache {
+    /// How long an entry is served; zero disables the cache
+    ttl: Duration,
+    /// Responses by cache key
+    entries: Mutex<HashMap<String, CacheEntry>>,
+    /// Number of invalidations, checked before storing a response
+    generation: AtomicU64,
+    /// Requests answered from the cache
+    hits: AtomicU64,
+    /// Requests for cached methods that ran the method
+    misses: AtomicU64,
+}
+
+impl Default for ResponseCache {
+    fn default() -> Self {
+        Self::new(Duration::from_millis(DEFAULT_RESPONSE_CACHE_TTL_MS))
+    }
+}
+
+impl ResponseCache {
+    /// Creates an empty cache whose entries expire after `ttl`
+    pub fn new(ttl: Duration) -> Self {
+        ResponseCache {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+            generation: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Builds the cache key of a call, or None if its response must not be cached
+    ///
+    /// Only `eth_getBlockByNumber` calls for the "latest" block are cached;
+    /// other block numbers are rarely repeated.
+    pub fn key(method: &str, params: &Params) -> Option<String> {
+        if !CACHED_METHODS.contains(&method) {
+            return None;
+        }
+
+        let params = match params {
+            Params::None => Value::Array(Vec::new()),
+            Params::Array(values) => Value::Array(values.clone()),
+            Params::Map(map) => Value::Object(map.clone()),
+        };
+        if method == "eth_getBlockByNumber" && params.get(0).and_then(Value::as_str) != Some("latest") {
+            return None;
+        }
+
+        Some(format!("{}:{}", method, params))
+    }
+
+    /// Gets a cached response that has not expired
+    pub fn get(&self, key: &str) -> Option<Value> {
+        let entries = self.entries.lock().unwrap();
+        let value = entries.get(key)
+            .filter(|entry| entry.stored_at.elapsed() < self.ttl)
+            .map(|entry| entry.value.clone());
+
+        match value {
+            Some(_) => self.hits.fetch_add(1, Ordering::Relaxed),
+            None => self.misses.fetch_add(1, Ordering::Relaxed),
+        };
+        value
+    }
+
+    /// Gets the current generation, to pass to `insert` once the response is computed
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    /// Stores a response unless the cache was invalidated since `generation` was read
+    pub fn insert(&self, key: String, value: Value, generation: u64) {
+        if self.ttl.is_zero() {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        // Checked under the lock, so an invalidation cannot slip in before the insert
+        if self.generation.load(Ordering::SeqCst) != generation {
+            return;
+        }
+        entries.insert(key, CacheEntry { value, stored_at: Instant::now() });
+    }
+
+    /// Drops every entry; called whenever a block is sealed
+    pub fn invalidate(&self) {
+        let mut entries = self.entries.lock().unwrap();
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        entries.clear();
+    }
+
+    /// Gets the hit, miss and invalidation counts
+    pub fn metrics(&self) -> ResponseCacheMetrics {
+        ResponseCacheMetrics {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            invalidations: self.generation.load(Ordering::SeqCst),
+        }
+    }
+}
+
+/// Middleware answering hot methods from a shared `ResponseCache`
+///
+/// Placed before the concurrency middleware, so cache hits do not use a slot.
+pub struct ResponseCacheMiddleware {
+    /// The shared cache
+    cache: Arc<ResponseCache>,
+}
+
+impl ResponseCacheMiddleware {
+    /// Creates a middleware serving from the given cache
+    pub fn new(cache: Arc<ResponseCache>) -> Self {
+        ResponseCacheMiddleware { cache }
+    }
+}
+
+impl<M: Metadata> Middleware<M> for ResponseCacheMiddleware {
+    type Future = BoxFuture<Option<jsonrpc_core::Response>>;
+    type CallFuture = BoxFuture<Option<Output>>;
+
+    fn on_call<F, X>(&self, call: Call, meta: M, next: F) -> Either<Self::CallFuture, X>
+    where
+        F: Fn(Call, M) -> X + Send + Sync,
+        X: Future<Output = Option<Output>> + Send + 'static,
+    {
+        let (key, id, jsonrpc) = match &call {
+            Call::MethodCall(method_call) => match ResponseCache::key(&method_call.method, &method_call.params) {
+                Some(key) => (key, method_call.id.clone(), method_call.jsonrpc),
+                None => return Either::Right(next(call, meta)),
+            },
+            _ => return Either::Right(next(call, meta)),
+        };
+
+        if let Some(value) = self.cache.get(&key) {
+            return Either::Left(Box::pin(async move { Some(Output::from(Ok(value), id, jsonrpc)) }));
+        }
+
+        let generation = self.cache.generation();
+        let future = next(call, meta);
+        let cache = self.cache.clone();
+
+        Either::Left(Box::pin(async move {
+            let output = future.await;
+            if let Some(Output::Success(success)) = &output {
+                cache.insert(key, success.result.clone(), generation);
+            }
+            output
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonrpc_core::MetaIoHandler;
+
+    #[tokio::test]
+    async fn test_hits_until_invalidated() {
+        let cache = Arc::new(ResponseCache::new(Duration::from_secs(60)));
+        let calls = Arc::new(AtomicU64::new(0));
+        let mut io = MetaIoHandler::with_middleware(ResponseCacheMiddleware::new(cache.clone()));
+        let counter = calls.clone();
+        io.add_method("eth_blockNumber", move |_params| {
+            let count = counter.fetch_add(1, Ordering::SeqCst);
+            async move { Ok(Value::from(format!("0x{:x}", count))) }
+        });
+
+        let request = r#"{"jsonrpc":"2.0","id":1,"method":"eth_blockNumber","params":[]}"#;
+        for _ in 0..5 {
+            let response: Value = serde_json::from_str(&io.handle_request(request, ()).await.unwrap()).unwrap();
+            assert_eq!(response["result"], "0x0");
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        cache.invalidate();
+        let response: Value = serde_json::from_str(&io.handle_request(request, ()).await.unwrap()).unwrap();
+        assert_eq!(response["result"], "0x1");
+        assert_eq!(cache.metrics(), ResponseCacheMetrics { hits: 4, misses: 2, invalidations: 1 });
+
+        // A response computed before an invalidation is not stored
+        let generation = cache.generation();
+        cache.invalidate();
+        cache.insert("stale".to_string(), Value::from(1), generation);
+        assert!(cache.get("stale").is_none());
+    }
+
+    #[test]
+    fn test_only_hot_calls_are_cached() {
+        let latest = Params::Array(vec![Value::from("latest"), Value::from(false)]);
+        let numbered = Params::Array(vec![Value::from("0x1"), Value::from(false)]);
+        assert!(ResponseCache::key("eth_getBlockByNumber", &latest).is_some());
+        assert!(ResponseCache::key("eth_getBlockByNumber", &numbered).is_none());
+        assert!(ResponseCache::key("eth_getBalance", &Params::None).is_none());
+        assert_ne!(
+            ResponseCache::key("eth_getBlockByNumber", &latest),
+            ResponseCache::key("eth_getBlockByNumber", &Params::Array(vec![Value::from("latest"), Value::from(true)]))
+        );
+    }
+}
@@ -0,0 +1,413 @@
+//! Scheduled Jobs
+//!
+//! Recurring node work, such as checkpointing and fee distribution, runs as
+//! named jobs on one scheduler instead of each owning a tokio task and timer.
+//! A job runs either at a fixed interval or once a day at a set UTC time, and
+//! never overlaps itself: a run that comes due while the previous one is still
+//! going is skipped and counted. Interval jobs that fail are retried with the
+//! delay doubled for each consecutive failure, up to `MAX_BACKOFF_SECS` (or the
+//! interval, if that is longer).
+//!
+//! The scheduler records each job's last run, its duration and its outcome.
+//! `ubi_getScheduledJobs` lists them, the operations admin can start a job at
+//! once with `ubi_runJobNow`, and jobs that failed `FAILURE_ALERT_THRESHOLD`
+//! times in a row are reported by the RPC metrics and the `/ready` probe.
+
+use futures::future::BoxFuture;
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Longest delay between attempts while an interval job keeps failing
+pub const MAX_BACKOFF_SECS: u64 = 3600;
+
+/// Consecutive failures after which a job is reported as failing
+pub const FAILURE_ALERT_THRESHOLD: u32 = 3;
+
+/// Seconds in a day, the period of daily jobs
+const SECS_PER_DAY: u64 = 86_400;
+
+/// When a job runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Schedule {
+    /// Every interval, starting one interval after the scheduler starts
+    Every(Duration),
+    /// Once a day at the given number of seconds after midnight UTC
+    DailyAt(u64),
+}
+
+impl Schedule {
+    /// Creates a daily schedule at the given UTC hour and minute
+    pub fn daily_at(hour: u64, minute: u64) -> Self {
+        Schedule::DailyAt((hour * 3600 + minute * 60) % SECS_PER_DAY)
+    }
+
+    /// Computes the delay before the next run
+    ///
+    /// # Arguments
+    /// * `now` - The current time in seconds since epoch
+    /// * `consecutive_failures` - Failed runs since the last successful one
+    ///
+    /// # Returns
+    /// For interval jobs the interval, backed off after failures; for daily
+    /// jobs the time until the next occurrence
+    pub fn delay(&self, now: u64, consecutive_failures: u32) -> Duration {
+        match *self {
+            Schedule::Every(interval) => backoff(interval, consecutive_failures),
+            Schedule::DailyAt(at) => {
+                let wait = (at + SECS_PER_DAY - now % SECS_PER_DAY) % SECS_PER_DAY;
+                Duration::from_secs(if wait == 0 { SECS_PER_DAY } else { wait })
+            },
+        }
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Schedule::Every(interval) => write!(f, "every {}s", interval.as_secs()),
+            Schedule::DailyAt(at) => write!(f, "daily at {:02}:{:02} UTC", at / 3600, at % 3600 / 60),
+        }
+    }
+}
+
+/// Computes the delay before the next attempt of an interval job
+///
+/// # Arguments
+/// * `interval` - The job's interval
+/// * `consecutive_failures` - Failed runs since the last successful one
+///
+/// # Returns
+/// The interval, doubled for each consecutive failure and capped at
+/// `MAX_BACKOFF_SECS` (or the interval, if that is longer)
+pub fn backoff(interval: Duration, consecutive_failures: u32) -> Duration {
+    let cap = interval.max(Duration::from_secs(MAX_BACKOFF_SECS));
+    interval
+        .checked_mul(1u32.checked_shl(consecutive_failures).unwrap_or(u32::MAX))
+        .unwrap_or(cap)
+        .min(cap)
+}
+
+/// How a job's run ended
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum JobOutcome {
+    /// The run finished, with a short summary
+    Succeeded { message: String },
+    /// The run failed or panicked
+    Failed { error: String },
+}
+
+/// What the scheduler knows about a job
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JobStatus {
+    /// Name the job was registered under
+    pub name: String,
+    /// When the job runs, e.g. "every 300s"
+    pub schedule: String,
+    /// Whether a run is in progress
+    pub running: bool,
+    /// Runs finished since startup
+    pub runs: u64,
+    /// Runs that failed since startup
+    pub failures: u64,
+    /// Failed runs since the last successful one
+    pub consecutive_failures: u32,
+    /// Runs skipped because the previous run was still in progress
+    pub skipped_overlaps: u64,
+    /// When the last run started, in seconds since epoch
+    pub last_started_at: Option<u64>,
+    /// When the last run finished, in seconds since epoch
+    pub last_finished_at: Option<u64>,
+    /// How long the last finished run took, in milliseconds
+    pub last_duration_ms: Option<u64>,
+    /// How the last finished run ended
+    pub last_outcome: Option<JobOutcome>,
+    /// When the next scheduled run is due, in seconds since epoch
+    pub next_run_at: Option<u64>,
+}
+
+/// Counters over all jobs, for the RPC metrics
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchedulerMetrics {
+    /// Registered jobs
+    pub jobs: usize,
+    /// Jobs with a run in progress
+    pub running: usize,
+    /// Runs finished since startup
+    pub runs: u64,
+    /// Runs that failed since startup
+    pub failures: u64,
+    /// Runs skipped because the previous run was still in progress
+    pub skipped_overlaps: u64,
+    /// Jobs that failed at least `FAILURE_ALERT_THRESHOLD` times in a row
+    pub failing_jobs: Vec<String>,
+}
+
+/// Reason a job could not be started on demand
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RunJobError {
+    /// No job is registered under the name
+    UnknownJob(String),
+    /// The job is already running
+    AlreadyRunning(String),
+}
+
+impl fmt::Display for RunJobError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunJobError::UnknownJob(name) => write!(f, "Unknown job: {}", name),
+            RunJobError::AlreadyRunning(name) => write!(f, "Job {} is already running", name),
+        }
+    }
+}
+
+/// Future of one run: a summary on success, the error on failure
+pub type JobFuture = BoxFuture<'static, Result<String, String>>;
+
+/// A registered job
+struct Job {
+    schedule: Schedule,
+    run: Box<dyn Fn() -> JobFuture + Send + Sync>,
+    status: Mutex<JobStatus>,
+}
+
+impl Job {
+    /// Marks the job as running, unless it already is
+    fn claim(&self) -> bool {
+        let mut status = self.status.lock().unwrap();
+        if status.running {
+            status.skipped_overlaps += 1;
+            return false;
+        }
+        status.running = true;
+        status.last_started_at = Some(current_time_secs());
+        true
+    }
+
+    /// Runs a claimed job and records the outcome
+    async fn execute(self: Arc<Self>) {
+        let started = Instant::now();
+        // A panicking job must not leave the job marked as running forever
+        let result = tokio::spawn((self.run)()).await
+            .unwrap_or_else(|e| Err(format!("job panicked: {}", e)));
+
+        let mut status = self.status.lock().unwrap();
+        status.running = false;
+        status.runs += 1;
+        status.last_finished_at = Some(current_time_secs());
+        status.last_duration_ms = Some(started.elapsed().as_millis() as u64);
+        let outcome = match result {
+            Ok(message) => {
+                debug!(job = status.name.as_str(); "Job {} finished: {}", status.name, message);
+                status.consecutive_failures = 0;
+                JobOutcome::Succeeded { message }
+            },
+            Err(error) => {
+                status.failures += 1;
+                status.consecutive_failures += 1;
+                if status.consecutive_failures >= FAILURE_ALERT_THRESHOLD {
+                    error!(alert = "scheduled_job_failing", job = status.name.as_str(), consecutive_failures = status.consecutive_failures;
+                           "Job {} failed {} times in a row: {}", status.name, status.consecutive_failures, error);
+                } else {
+                    warn!(job = status.name.as_str(); "Job {} failed: {}", status.name, error);
+                }
+                JobOutcome::Failed { error }
+            },
+        };
+        status.last_outcome = Some(outcome);
+    }
+
+    /// Runs the job on its schedule forever
+    async fn run_on_schedule(self: Arc<Self>) {
+        loop {
+            let delay = {
+                let mut status = self.status.lock().unwrap();
+                let now = current_time_secs();
+                let delay = self.schedule.delay(now, status.consecutive_failures);
+                status.next_run_at = Some(now + delay.as_secs());
+                delay
+            };
+            tokio::time::sleep(delay).await;
+
+            if self.claim() {
+                self.clone().execute().await;
+            } else {
+                debug!("Skipped a run of a job that was still running");
+            }
+        }
+    }
+}
+
+/// Runs registered jobs on their schedules
+#[derive(Default)]
+pub struct Scheduler {
+    jobs: Mutex<BTreeMap<String, Arc<Job>>>,
+}
+
+impl Scheduler {
+    /// Registers a job; call before `start`
+    ///
+    /// # Arguments
+    /// * `name` - Unique name, used by `ubi_runJobNow`; registering it again replaces the job
+    /// * `schedule` - When the job runs
+    /// * `job` - Starts a run, resolving to a summary or an error
+    pub fn register<F, Fut>(&self, name: &str, schedule: Schedule, job: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<String, String>> + Send + 'static,
+    {
+        let job = Job {
+            schedule,
+            run: Box::new(move || Box::pin(job())),
+            status: Mutex::new(JobStatus {
+                name: name.to_string(),
+                schedule: schedule.to_string(),
+                running: false,
+                runs: 0,
+                failures: 0,
+                consecutive_failures: 0,
+                skipped_overlaps: 0,
+                last_started_at: None,
+                last_finished_at: None,
+                last_duration_ms: None,
+                last_outcome: None,
+                next_run_at: None,
+            }),
+        };
+        info!(job = name; "Registered job {} ({})", name, schedule);
+        self.jobs.lock().unwrap().insert(name.to_string(), Arc::new(job));
+    }
+
+    /// Starts running every registered job on its schedule
+    pub fn start(&self) {
+        for job in self.jobs.lock().unwrap().values() {
+            tokio::spawn(job.clone().run_on_schedule());
+        }
+    }
+
+    /// Starts a run of a job now, in the background
+    ///
+    /// # Arguments
+    /// * `name` - The job's name
+    ///
+    /// # Returns
+    /// The job's status with the run in progress, or why it could not start
+    pub fn run_now(&self, name: &str) -> Result<JobStatus, RunJobError> {
+        let job = self.jobs.lock().unwrap().get(name).cloned()
+            .ok_or_else(|| RunJobError::UnknownJob(name.to_string()))?;
+        if !job.claim() {
+            return Err(RunJobError::AlreadyRunning(name.to_string()));
+        }
+        let status = job.status.lock().unwrap().clone();
+        tokio::spawn(job.execute());
+        Ok(status)
+    }
+
+    /// Gets the status of every job, by name
+    pub fn jobs(&self) -> Vec<JobStatus> {
+        self.jobs.lock().unwrap().values().map(|job| job.status.lock().unwrap().clone()).collect()
+    }
+
+    /// Gets the status of a job
+    pub fn job(&self, name: &str) -> Option<JobStatus> {
+        self.jobs.lock().unwrap().get(name).map(|job| job.status.lock().unwrap().clone())
+    }
+
+    /// Gets the names of the jobs that failed `FAILURE_ALERT_THRESHOLD` times in a row
+    pub fn failing_jobs(&self) -> Vec<String> {
+        self.jobs().into_iter()
+            .filter(|job| job.consecutive_failures >= FAILURE_ALERT_THRESHOLD)
+            .map(|job| job.name)
+            .collect()
+    }
+
+    /// Gets the counters over all jobs
+    pub fn metrics(&self) -> SchedulerMetrics {
+        let jobs = self.jobs();
+        SchedulerMetrics {
+            jobs: jobs.len(),
+            running: jobs.iter().filter(|job| job.running).count(),
+            runs: jobs.iter().map(|job| job.runs).sum(),
+            failures: jobs.iter().map(|job| job.failures).sum(),
+            skipped_overlaps: jobs.iter().map(|job| job.skipped_overlaps).sum(),
+            failing_jobs: self.failing_jobs(),
+        }
+    }
+}
+
+/// Gets the current time in seconds since epoch
+fn current_time_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[test]
+    fn test_delays_back_off_and_follow_the_clock() {
+        let interval = Duration::from_secs(300);
+        assert_eq!(backoff(interval, 0), interval);
+        assert_eq!(backoff(interval, 3), Duration::from_secs(2400));
+        assert_eq!(backoff(interval, 40), Duration::from_secs(MAX_BACKOFF_SECS));
+        // An interval longer than the cap is never shortened
+        let long = Duration::from_secs(2 * MAX_BACKOFF_SECS);
+        assert_eq!(backoff(long, 2), long);
+
+        // 2025-01-01 00:00 UTC; daily jobs ignore failures
+        let midnight = 1_735_689_600;
+        let daily = Schedule::daily_at(3, 30);
+        assert_eq!(daily.to_string(), "daily at 03:30 UTC");
+        assert_eq!(daily.delay(midnight, 5), Duration::from_secs(3 * 3600 + 1800));
+        assert_eq!(daily.delay(midnight + 3 * 3600 + 1800, 0), Duration::from_secs(SECS_PER_DAY));
+        assert_eq!(daily.delay(midnight + 4 * 3600, 0), Duration::from_secs(SECS_PER_DAY - 1800));
+    }
+
+    #[tokio::test]
+    async fn test_jobs_do_not_overlap_and_record_their_outcome() {
+        let scheduler = Scheduler::default();
+        let calls = Arc::new(AtomicU32::new(0));
+        let release = Arc::new(tokio::sync::Semaphore::new(0));
+        scheduler.register("flaky", Schedule::Every(Duration::from_secs(3600)), {
+            let calls = calls.clone();
+            let release = release.clone();
+            move || {
+                let call = calls.fetch_add(1, Ordering::SeqCst);
+                let release = release.clone();
+                async move {
+                    let _ = release.acquire().await;
+                    if call == 0 { Err("disk full".to_string()) } else { Ok("done".to_string()) }
+                }
+            }
+        });
+
+        assert!(scheduler.run_now("flaky").unwrap().running);
+        assert_eq!(scheduler.run_now("flaky"), Err(RunJobError::AlreadyRunning("flaky".to_string())));
+        assert_eq!(scheduler.run_now("other"), Err(RunJobError::UnknownJob("other".to_string())));
+        release.add_permits(1);
+
+        let finished = |scheduler: &Scheduler| scheduler.jobs()[0].clone();
+        while finished(&scheduler).running {
+            tokio::task::yield_now().await;
+        }
+        let status = finished(&scheduler);
+        assert_eq!(status.last_outcome, Some(JobOutcome::Failed { error: "disk full".to_string() }));
+        assert_eq!((status.runs, status.failures, status.consecutive_failures, status.skipped_overlaps), (1, 1, 1, 1));
+
+        scheduler.run_now("flaky").unwrap();
+        while finished(&scheduler).running {
+            tokio::task::yield_now().await;
+        }
+        let status = finished(&scheduler);
+        assert_eq!(status.last_outcome, Some(JobOutcome::Succeeded { message: "done".to_string() }));
+        assert_eq!(status.consecutive_failures, 0);
+        assert_eq!(scheduler.metrics().runs, 2);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,130 @@
+//! UBI Token Facade
+//!
+//! UBI balances are native, but wallets and indexers often look for an ERC-20
+//! contract during token detection. The facade is a pseudo-contract at a fixed
+//! address whose code and storage are synthesized from native state:
+//!
+//! - `eth_getCode` returns a marker bytecode: `INVALID` followed by the ASCII
+//!   tag `UBI-TOKEN-FACADE`, so any attempt to execute it halts immediately
+//! - Storage follows the standard Solidity layout of an ERC-20 `balances`
+//!   mapping at slot 0, so `keccak256(pad32(address) ++ pad32(0))` holds the
+//!   account's balance in wei (18 decimals, matching `eth_getBalance`)
+//! - UBI mints appear in `eth_getLogs` as ERC-20 `Transfer` events emitted by
+//!   the facade, from the UBI mint address to the credited account
+
+use primitive_types::U256;
+use runtime::Runtime;
+use tiny_keccak::{Hasher, Keccak};
+
+/// Address of the UBI token pseudo-contract, reserved so no account can take it
+pub const UBI_TOKEN_ADDRESS: &str = runtime::reserved::TOKEN_FACADE_ADDRESS;
+
+/// Storage slot of the balances mapping
+pub const BALANCES_SLOT: u64 = 0;
+
+/// Topic of the ERC-20 `Transfer(address,address,uint256)` event
+pub const TRANSFER_EVENT_TOPIC: &str = "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+
+/// Code returned for ordinary accounts
+pub const EMPTY_CODE: &str = "0x";
+
+/// Marker tag embedded in the facade bytecode
+const FACADE_TAG: &[u8] = b"UBI-TOKEN-FACADE";
+
+/// INVALID opcode, placed first so the marker is never executed
+const INVALID_OPCODE: u8 = 0xfe;
+
+/// Checks whether an address is the token facade
+pub fn is_token_facade(address: &str) -> bool {
+    address.eq_ignore_ascii_case(UBI_TOKEN_ADDRESS)
+}
+
+/// Gets the bytecode marker of the token facade
+pub fn facade_code() -> String {
+    let mut code = vec![INVALID_OPCODE];
+    code.extend_from_slice(FACADE_TAG);
+    format!("0x{}", hex::encode(code))
+}
+
+/// Pads an address to a 32-byte log topic
+pub fn address_topic(address: &str) -> String {
+    let digits = address.strip_prefix("0x").or_else(|| address.strip_prefix("0X")).unwrap_or(address);
+    format!("0x{:0>64}", digits.to_lowercase())
+}
+
+/// Computes the storage slot holding an account's balance
+///
+/// # Arguments
+/// * `address` - 0x-prefixed account address
+///
+/// # Returns
+/// `keccak256(pad32(address) ++ pad32(BALANCES_SLOT))`, or None if the address is not 20 bytes of hex
+pub fn balance_slot(address: &str) -> Option<[u8; 32]> {
+    let digits = address.strip_prefix("0x").or_else(|| address.strip_prefix("0X"))?;
+    let address_bytes = hex::decode(digits).ok()?;
+    if address_bytes.len() != 20 {
+        return None;
+    }
+
+    let mut preimage = [0u8; 64];
+    preimage[12..32].copy_from_slice(&address_bytes);
+    U256::from(BALANCES_SLOT).to_big_endian(&mut preimage[32..]);
+
+    let mut slot = [0u8; 32];
+    let mut hasher = Keccak::v256();
+    hasher.update(&preimage);
+    hasher.finalize(&mut slot);
+    Some(slot)
+}
+
+/// Finds the account whose balance is stored at the given facade slot
+///
+/// Mapping slots are hashes, so this checks every known account.
+pub fn account_for_slot(runtime: &Runtime, slot: &[u8; 32]) -> Option<String> {
+    runtime.get_account_addresses()
+        .into_iter()
+        .find(|address| balance_slot(address).as_ref() == Some(slot))
+}
+
+/// Formats a 256-bit word as a 0x-prefixed 32-byte hex string
+pub fn format_word(value: U256) -> String {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    format!("0x{}", hex::encode(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_balance_slot_matches_solidity_layout() {
+        // keccak256(abi.encode(address(0), uint256(0)))
+        let slot = balance_slot("0x0000000000000000000000000000000000000000").unwrap();
+        assert_eq!(
+            hex::encode(slot),
+            "ad3228b676f7d3cd4284a5443f17f1962b36e491b30a40b2405849e597ba5fb5"
+        );
+
+        assert_eq!(balance_slot("0x1234"), None);
+        assert_eq!(balance_slot("not an address"), None);
+    }
+
+    #[test]
+    fn test_account_for_slot() {
+        let runtime = Runtime::new();
+        let address = "0x1234567890abcdef1234567890abcdef12345678";
+        runtime.create_account(address).unwrap();
+
+        let slot = balance_slot(address).unwrap();
+        assert_eq!(account_for_slot(&runtime, &slot), Some(address.to_string()));
+        assert_eq!(account_for_slot(&runtime, &[0u8; 32]), None);
+    }
+
+    #[test]
+    fn test_facade_code_is_a_marker() {
+        assert!(is_token_facade("0x0000000000000000000000000000000000001000"));
+        assert!(!is_token_facade("0x0000000000000000000000000000000000001001"));
+        assert_eq!(facade_code(), format!("0xfe{}", hex::encode("UBI-TOKEN-FACADE")));
+    }
+}
@@ -0,0 +1,75 @@
+//! Tooling compatibility suite
+//!
+//! Runs the steps of a Hardhat or Foundry deployment script against a live
+//! node through ethers-rs: chain id, gas price and fee history, nonce
+//! lookup, a signed legacy transfer, its receipt and balance at the receipt
+//! block, and the matching new-heads notification over WebSocket.
+//!
+//! Run with `cargo test -p ubi-chain-rpc --features eth-compat-e2e`.
+#![cfg(feature = "eth-compat-e2e")]
+
+use ethers::prelude::*;
+use runtime::Runtime;
+use std::time::Duration;
+use ubi_chain_rpc::RpcHandler;
+
+/// Chain ID the node is started with
+const CHAIN_ID: u64 = 2030;
+
+/// Test key funded on the node
+const SENDER_KEY: &str = "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+
+#[test]
+fn test_hardhat_style_transfer() {
+    let wallet: LocalWallet = SENDER_KEY.parse::<LocalWallet>().unwrap().with_chain_id(CHAIN_ID);
+    let sender = format!("{:?}", wallet.address());
+    let recipient: Address = "0x3535353535353535353535353535353535353535".parse().unwrap();
+
+    let runtime = Runtime::new();
+    runtime.create_account(&sender).unwrap();
+    runtime.credit_balance(&sender, 100).unwrap();
+    let handler = RpcHandler::new(runtime);
+
+    // The servers run their own event loops, so they are started outside the test runtime,
+    // on ephemeral ports so parallel runs never collide
+    let (_http_server, http_addr) = handler.start_eth_rpc_server("127.0.0.1:0", CHAIN_ID).unwrap();
+    let (_ws_server, ws_addr) = futures::executor::block_on(handler.start_eth_ws_server("127.0.0.1:0", CHAIN_ID)).unwrap();
+
+    let tokio_runtime = tokio::runtime::Builder::new_multi_thread().enable_all().build().unwrap();
+    tokio_runtime.block_on(async move {
+        let provider = Provider::<Http>::try_from(format!("http://{}", http_addr))
+            .unwrap()
+            .interval(Duration::from_millis(50));
+
+        assert_eq!(provider.get_chainid().await.unwrap(), U256::from(CHAIN_ID));
+        let gas_price = provider.get_gas_price().await.unwrap();
+        let history = provider.fee_history(4u64, BlockNumber::Latest, &[50.0]).await.unwrap();
+        assert!(!history.base_fee_per_gas.is_empty());
+        assert_eq!(provider.get_transaction_count(wallet.address(), None).await.unwrap(), U256::zero());
+
+        let ws = Provider::<Ws>::connect(format!("ws://{}", ws_addr)).await.unwrap();
+        let mut heads = ws.subscribe_blocks().await.unwrap();
+
+        let client = SignerMiddleware::new(provider.clone(), wallet.clone());
+        let transfer = TransactionRequest::new()
+            .to(recipient)
+            .value(U256::exp10(18) * 5)
+            .gas(21_000)
+            .gas_price(gas_price);
+        let receipt = client.send_transaction(transfer, None).await.unwrap()
+            .await.unwrap()
+            .expect("transfer was dropped");
+        assert_eq!(receipt.status, Some(U64::one()));
+
+        let block = receipt.block_number.unwrap();
+        let balance = provider.get_balance(recipient, Some(block.into())).await.unwrap();
+        assert_eq!(balance, U256::exp10(18) * 5);
+        assert_eq!(provider.get_transaction_count(wallet.address(), None).await.unwrap(), U256::one());
+
+        let head = tokio::time::timeout(Duration::from_secs(5), heads.next()).await
+            .expect("no new head was published")
+            .unwrap();
+        assert_eq!(head.number, Some(block));
+        assert_eq!(head.hash, receipt.block_hash);
+    });
+}
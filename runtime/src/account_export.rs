@@ -0,0 +1,129 @@
+//! Account Export and Import
+//!
+//! Operators moving an account to another deployment export it from the source
+//! runtime and import it on the target. The export carries the account's state:
+//! balance, verification and when it was granted, last UBI claim and spending
+//! limits. Dividends owed at export time are settled into the balance first.
+//!
+//! Exporting removes the account and burns its balance; importing mints it on
+//! the target. Both are recorded as supply events, so each chain's total supply
+//! can be audited against its history.
+//!
+//! Every export embeds the source chain id and a nonce unique on the source, and
+//! a runtime refuses to import the same export twice.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::limits::AccountLimitState;
+
+/// The state of one account, removed from its source chain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountExport {
+    /// Account address, lowercase
+    pub address: String,
+    /// Balance at export, including settled dividends
+    pub balance: u64,
+    /// Whether the account passed human verification
+    pub verified: bool,
+    /// Last UBI claim as seconds since epoch
+    pub last_ubi_claim_secs: u64,
+    /// When verification was last granted, as seconds since epoch
+    #[serde(default)]
+    pub verified_at_secs: Option<u64>,
+    /// Dividends settled into the balance at export
+    pub settled_dividends: u64,
+    /// Spending limits, if the account opted in
+    pub limits: Option<AccountLimitState>,
+    /// Chain id of the chain the account was exported from
+    pub source_chain_id: u64,
+    /// Nonce of the export on the source chain
+    pub export_nonce: u64,
+    /// When the account was exported, in seconds since epoch
+    pub exported_at: u64,
+}
+
+impl AccountExport {
+    /// Gets the key that identifies this export for replay protection
+    pub fn replay_key(&self) -> String {
+        format!("{}:{}:{}", self.source_chain_id, self.export_nonce, self.address)
+    }
+}
+
+/// Kind of change to the total supply
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SupplyEventKind {
+    /// An account was exported and its balance burned
+    AccountExported,
+    /// An account was imported and its balance minted
+    AccountImported,
+    /// An account holder burned tokens voluntarily
+    Burned,
+    /// A block's producer was paid the block reward
+    BlockReward,
+    /// A block reward was taken back because its block left the chain
+    BlockRewardReverted,
+}
+
+/// An auditable change to the total supply
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SupplyEvent {
+    /// What happened
+    pub kind: SupplyEventKind,
+    /// The account concerned
+    pub address: String,
+    /// Tokens burned or minted
+    pub amount: u64,
+    /// Chain id the account was exported from; zero for burns and rewards
+    pub source_chain_id: u64,
+    /// Nonce of the export on the source chain; zero for burns and rewards
+    pub export_nonce: u64,
+    /// When it happened, in seconds since epoch
+    pub timestamp: u64,
+    /// Total supply after the change
+    pub total_supply_after: u64,
+    /// Hash of the transaction that burned the tokens, for burns sent as transfers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tx_hash: Option<String>,
+    /// Hash of the block a reward was paid or taken back for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_hash: Option<String>,
+}
+
+/// Reason an export or import was refused
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccountTransferError {
+    /// The address is malformed
+    InvalidAddress(String),
+    /// The account to export does not exist
+    UnknownAccount(String),
+    /// The account to export is frozen
+    Frozen(String),
+    /// The account to export holds tokens that are still locked
+    Locked(String),
+    /// The account to import already exists on this chain
+    AlreadyExists(String),
+    /// This export was already imported
+    AlreadyImported {
+        /// Chain id the account was exported from
+        source_chain_id: u64,
+        /// Nonce of the export on the source chain
+        export_nonce: u64,
+    },
+}
+
+impl fmt::Display for AccountTransferError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AccountTransferError::InvalidAddress(address) => write!(f, "Invalid address format: {}", address),
+            AccountTransferError::UnknownAccount(address) => write!(f, "Account {} does not exist", address),
+            AccountTransferError::Frozen(address) => write!(f, "Account {} is frozen and cannot be exported", address),
+            AccountTransferError::Locked(address) => write!(f, "Account {} holds locked tokens and cannot be exported", address),
+            AccountTransferError::AlreadyExists(address) => write!(f, "Account {} already exists", address),
+            AccountTransferError::AlreadyImported { source_chain_id, export_nonce } => {
+                write!(f, "Export {} from chain {} was already imported", export_nonce, source_chain_id)
+            },
+        }
+    }
+}
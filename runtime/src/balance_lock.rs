@@ -0,0 +1,67 @@
+//! Balance Locks
+//!
+//! Part of an account's balance can be locked until a given time, so a grant
+//! or a block reward vests instead of being spendable at once. Locked tokens
+//! stay in the balance and earn dividends, but cannot be transferred, burned
+//! or streamed out before their lock expires. An expired lock no longer
+//! counts, and is dropped the next time the account spends.
+
+use serde::{Deserialize, Serialize};
+
+/// Most unexpired locks one account may hold
+pub const MAX_LOCKS_PER_ACCOUNT: usize = 64;
+
+/// Tokens held back from spending until a given time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BalanceLock {
+    /// Tokens locked
+    pub amount: u64,
+    /// When the tokens become spendable, in seconds since epoch
+    pub unlock_at: u64,
+}
+
+impl BalanceLock {
+    /// Whether the lock still holds its tokens at the given time
+    pub fn is_active(&self, now: u64) -> bool {
+        self.unlock_at > now
+    }
+}
+
+/// Gets the tokens still locked at the given time
+pub fn locked_amount(locks: &[BalanceLock], now: u64) -> u64 {
+    locks.iter()
+        .filter(|lock| lock.is_active(now))
+        .fold(0, |total, lock| total.saturating_add(lock.amount))
+}
+
+/// Drops the locks that expired by the given time
+///
+/// # Returns
+/// The tokens the dropped locks held
+pub fn release_expired(locks: &mut Vec<BalanceLock>, now: u64) -> u64 {
+    let released = locks.iter()
+        .filter(|lock| !lock.is_active(now))
+        .fold(0u64, |total, lock| total.saturating_add(lock.amount));
+    locks.retain(|lock| lock.is_active(now));
+    released
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locks_release_when_they_expire() {
+        let mut locks = vec![
+            BalanceLock { amount: 100, unlock_at: 1_000 },
+            BalanceLock { amount: 40, unlock_at: 2_000 },
+        ];
+        assert_eq!(locked_amount(&locks, 999), 140);
+        assert_eq!(locked_amount(&locks, 1_000), 40);
+        assert_eq!(locked_amount(&locks, 2_000), 0);
+
+        assert_eq!(release_expired(&mut locks, 1_500), 100);
+        assert_eq!(locks, vec![BalanceLock { amount: 40, unlock_at: 2_000 }]);
+        assert_eq!(release_expired(&mut locks, 1_500), 0);
+    }
+}
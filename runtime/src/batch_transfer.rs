@@ -0,0 +1,101 @@
+//! Batch Transfers
+//!
+//! A batch pays many recipients from one sender in a single step, for
+//! example a community treasury distribution. Each leg is charged the fee a
+//! transfer of its amount would pay. The sender must cover every leg and
+//! every fee up front, and the batch is applied completely or not at all.
+
+use serde::{Deserialize, Serialize};
+
+use crate::params::ChainParams;
+use crate::AccountError;
+
+/// Most transfers one batch may hold
+pub const MAX_BATCH_LEGS: usize = 1_000;
+
+/// One payment of a batch
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchLeg {
+    /// Recipient, lowercase
+    pub to: String,
+    /// Amount the recipient receives
+    pub amount: u64,
+    /// Fee charged to the sender on top of the amount
+    pub fee: u64,
+}
+
+/// What a batch paid, leg by leg and in total
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchReceipt {
+    /// Sender, lowercase
+    pub from: String,
+    /// The payments, in the order they were given
+    pub legs: Vec<BatchLeg>,
+    /// Amount the recipients receive together
+    pub total_amount: u64,
+    /// Fees charged together
+    pub total_fee: u64,
+    /// Amount debited from the sender: every amount plus every fee
+    pub total_debited: u64,
+}
+
+impl BatchReceipt {
+    /// Computes the fees and totals of a batch under the given chain parameters
+    ///
+    /// # Arguments
+    /// * `params` - Chain parameters the fees are computed under
+    /// * `from` - The sender
+    /// * `legs` - Recipient and amount of each payment
+    /// * `fee_exempt` - Whether the sender pays no fees
+    ///
+    /// # Returns
+    /// The receipt, or an overflow error if the totals do not fit in a u64
+    pub fn compute(params: &ChainParams, from: &str, legs: &[(String, u64)], fee_exempt: bool) -> Result<Self, AccountError> {
+        let mut receipt = BatchReceipt {
+            from: from.to_lowercase(),
+            legs: Vec::with_capacity(legs.len()),
+            total_amount: 0,
+            total_fee: 0,
+            total_debited: 0,
+        };
+        for (to, amount) in legs {
+            let fee = if fee_exempt { 0 } else { params.transfer_fee(*amount) };
+            receipt.total_amount = receipt.total_amount.checked_add(*amount)
+                .ok_or_else(|| AccountError::Overflow("batch amount".to_string()))?;
+            receipt.total_fee = receipt.total_fee.checked_add(fee)
+                .ok_or_else(|| AccountError::Overflow("batch fee".to_string()))?;
+            receipt.legs.push(BatchLeg { to: to.to_lowercase(), amount: *amount, fee });
+        }
+        receipt.total_debited = receipt.total_amount.checked_add(receipt.total_fee)
+            .ok_or_else(|| AccountError::Overflow("batch amount plus fees".to_string()))?;
+        Ok(receipt)
+    }
+
+    /// Gets the largest single amount, which the sender's per-transaction limit applies to
+    pub fn largest_amount(&self) -> u64 {
+        self.legs.iter().map(|leg| leg.amount).max().unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_each_leg_pays_its_own_fee() {
+        let params = ChainParams::default();
+        let legs = vec![("0xAA".to_string(), 1_000), ("0xbb".to_string(), 50), ("0xcc".to_string(), 250)];
+        let receipt = BatchReceipt::compute(&params, "0xDD", &legs, false).unwrap();
+
+        assert_eq!(receipt.from, "0xdd");
+        assert_eq!(receipt.legs[0], BatchLeg { to: "0xaa".to_string(), amount: 1_000, fee: params.transfer_fee(1_000) });
+        assert_eq!(receipt.total_amount, 1_300);
+        let fees: u64 = [1_000, 50, 250].iter().map(|amount| params.transfer_fee(*amount)).sum();
+        assert_eq!((receipt.total_fee, receipt.total_debited), (fees, 1_300 + fees));
+        assert_eq!(receipt.largest_amount(), 1_000);
+
+        let overflowing = vec![("0xaa".to_string(), u64::MAX / 2), ("0xbb".to_string(), u64::MAX / 2)];
+        assert!(matches!(BatchReceipt::compute(&params, "0xdd", &overflowing, false), Err(AccountError::Overflow(_))));
+        assert_eq!(BatchReceipt::compute(&params, "0xdd", &legs, true).unwrap().total_debited, 1_300);
+    }
+}
@@ -0,0 +1,81 @@
+//! Block Extras
+//!
+//! Ethereum-shaped blocks have no room for the UBI-specific figures explorers
+//! want per block, and generic tools break if extra fields appear in them. The
+//! block producer therefore records these figures when it seals or imports a
+//! block and stores them alongside the block, and `ubi_getBlockExtras` serves
+//! them separately from `eth_getBlockByNumber`.
+//!
+//! Verification changes happen outside blocks, so the runtime queues them as
+//! they happen and the next sealed block takes the queue. At most
+//! `MAX_PENDING_VERIFICATION_EVENTS` wait at once; beyond that the oldest are
+//! dropped, so a node without a block producer does not grow the queue forever.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Most verification events waiting for a block
+pub const MAX_PENDING_VERIFICATION_EVENTS: usize = 10_000;
+
+/// How an account's human verification changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerificationChange {
+    /// The account was verified, including on creation during the bootstrap window
+    Verified,
+    /// The account's verification was revoked
+    Revoked,
+}
+
+/// A change to an account's verification
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerificationEvent {
+    /// The account, lowercase
+    pub address: String,
+    /// Whether it was verified or revoked
+    pub change: VerificationChange,
+    /// When the change happened, in seconds since epoch
+    pub at: u64,
+}
+
+/// Verification events not yet taken by a block, oldest first
+#[derive(Debug, Clone, Default)]
+pub struct VerificationEventLog {
+    pending: VecDeque<VerificationEvent>,
+}
+
+impl VerificationEventLog {
+    /// Queues an event for the next block, dropping the oldest if the queue is full
+    pub fn record(&mut self, address: &str, change: VerificationChange, at: u64) {
+        if self.pending.len() >= MAX_PENDING_VERIFICATION_EVENTS {
+            self.pending.pop_front();
+        }
+        self.pending.push_back(VerificationEvent {
+            address: address.to_lowercase(),
+            change,
+            at,
+        });
+    }
+
+    /// Takes every queued event, oldest first
+    pub fn take(&mut self) -> Vec<VerificationEvent> {
+        self.pending.drain(..).collect()
+    }
+}
+
+/// UBI-specific figures of a block, recorded when it was sealed or imported
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockExtras {
+    /// Tokens credited to the producer; 0 until the block is stored and sent
+    pub reward_paid: u64,
+    /// Fees charged on the block's successful transactions
+    pub fees_collected: u64,
+    /// Fees distributed from the pool to token holders since the previous block
+    pub fees_distributed: u64,
+    /// Tokens minted by the block's UBI mint transactions
+    #[serde(default)]
+    pub ubi_minted_in_block: u64,
+    /// Verification changes since the previous block, oldest first
+    #[serde(default)]
+    pub verification_events: Vec<VerificationEvent>,
+}
@@ -0,0 +1,30 @@
+//! Producer Channel Metrics
+//!
+//! The block producer hands work to the rest of the node over bounded
+//! channels: each submitted transaction is announced to the node's
+//! transaction listeners, and each produced block is sent to the block
+//! consumer. Neither delivery may silently lose data, so the producer counts
+//! what happened to each send:
+//! - transactions go into the pool directly; the announcement is best effort,
+//!   and one made while nobody listens, as during startup, is only counted
+//! - a block send finding the channel full is retried with a growing backoff,
+//!   holding production back for a bounded time; a block the lagging consumer
+//!   still has no room for, or that finds the channel closed, is dropped and
+//!   counted. The block stays stored either way and is served over RPC.
+
+use serde::{Deserialize, Serialize};
+
+/// Counters of the block producer's channel sends since the node started
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChannelMetrics {
+    /// Submitted transactions announced to at least one listener
+    pub transactions_announced: u64,
+    /// Submitted transactions pooled while nobody listened for announcements
+    pub transactions_unannounced: u64,
+    /// Blocks delivered to the block consumer
+    pub blocks_delivered: u64,
+    /// Block sends retried because the consumer's channel was full
+    pub block_send_retries: u64,
+    /// Blocks never delivered, because the consumer lagged past the retries or its channel closed
+    pub blocks_dropped: u64,
+}
@@ -0,0 +1,1416 @@
+//! Checkpoint File Format
+//!
+//! Checkpoints are little-endian binary files. Fifteen versions exist:
+//!
+//! - Version 0 (legacy): the original hand-rolled format with no header. It
+//!   holds the timestamp, root hash, account count, total supply and fee pool,
+//!   then for each account its address, balance, verified flag and last UBI claim.
+//! - Version 1: starts with the `UBICKPT\0` magic and a `u16` version, then the
+//!   version 0 fields plus dividend tracking: the global dividend per token in
+//!   the header, and each account's last dividend point and unclaimed dividends.
+//! - Version 2: version 1 plus each account's spending limits, any pending
+//!   limit change, the change nonce and the outflows in the rolling window.
+//! - Version 3: version 2 plus the time each account was last verified,
+//!   which bounds how far back UBI accrues.
+//! - Version 4: version 3 followed by the fee ledger.
+//! - Version 5: version 4 plus each account's transaction nonce, which the
+//!   state root hashes.
+//! - Version 6: version 5 followed by a SHA-256 digest of every byte before
+//!   it. The digest is checked before anything else is read, so a damaged
+//!   file is refused with a `ChecksumMismatch` instead of being half parsed.
+//! - Version 7: version 6 with a flags byte after the header. When the
+//!   compressed flag is set, the accounts and fee ledger follow as a gzip
+//!   stream preceded by their uncompressed length. The header stays
+//!   uncompressed, so `read_checkpoint_header` can list checkpoints without
+//!   reading their accounts, and the digest covers the file as stored.
+//! - Version 8: version 7 with the next payment stream id and the open
+//!   payment streams after the fee ledger, inside the compressed body.
+//! - Version 9: version 8 followed by the allowances owners granted to
+//!   spenders.
+//! - Version 10: version 9 plus whether each account is frozen and why,
+//!   after its nonce.
+//! - Version 11: version 10 plus each account's balance locks, after the
+//!   freeze.
+//! - Version 12: version 11 plus whether each account compounds its
+//!   dividends, after its locks.
+//! - Version 13: version 12 plus each account's lifetime UBI received and
+//!   claim count, after the auto-compound flag, followed by the network's
+//!   lifetime UBI minted, fees collected and dividends distributed after the
+//!   allowances.
+//! - Version 14 (current): version 13 with the dividend per token value each
+//!   distribution left, after its increase. Reading an older version works
+//!   the values back from the header's dividend per token value.
+//!
+//! The runtime reads every version with a digest, 6 to 14, through
+//! `decode_checkpoint`. Readers for older versions are only reachable through
+//! `decode_any_checkpoint` and `upgrade_checkpoint`, which migration tooling
+//! (`ubi-chain-node migrate-checkpoints` and `Runtime::migrate_checkpoint`) uses.
+//!
+//! Files are written through `write_checkpoint_file`, which writes a temporary
+//! file and renames it into place, so a full disk or a crash mid-write never
+//! leaves a truncated checkpoint under a `.dat` name.
+
+use crate::balance_lock::BalanceLock;
+use crate::fee_ledger::{self, FeeLedgerEntry, FeeLedgerKind};
+use crate::limits::{AccountLimitState, AccountLimits, PendingLimits};
+use crate::payment_stream::{PaymentStream, StreamId};
+use crate::ubi_stats::{NetworkStats, UbiStats};
+use crate::{is_valid_eth_address, AccountState, MerkleTree};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+/// Longest transaction hash a checkpoint may hold, in bytes
+///
+/// Checkpoints may be restored from untrusted backups, so every length read
+/// from a file is capped before anything is allocated for it.
+pub const MAX_CHECKPOINT_STRING_LEN: usize = 256;
+
+/// Longest account address a checkpoint may hold, in bytes
+pub const MAX_CHECKPOINT_ADDRESS_LEN: usize = 64;
+
+/// Most accounts a checkpoint may declare unless the runtime allows more
+pub const DEFAULT_MAX_CHECKPOINT_ACCOUNTS: u64 = 10_000_000;
+
+/// Magic bytes at the start of every versioned checkpoint
+pub const CHECKPOINT_MAGIC: &[u8; 8] = b"UBICKPT\0";
+
+/// Version of the original headerless format
+pub const LEGACY_CHECKPOINT_VERSION: u16 = 0;
+
+/// Version that added dividend tracking
+pub const DIVIDENDS_CHECKPOINT_VERSION: u16 = 1;
+
+/// Version that added spending limits
+pub const LIMITS_CHECKPOINT_VERSION: u16 = 2;
+
+/// Version that added verification times
+pub const VERIFIED_AT_CHECKPOINT_VERSION: u16 = 3;
+
+/// Version that added the fee ledger
+pub const FEE_LEDGER_CHECKPOINT_VERSION: u16 = 4;
+
+/// Version that added nonces
+pub const NONCE_CHECKPOINT_VERSION: u16 = 5;
+
+/// Version that added the digest
+pub const DIGEST_CHECKPOINT_VERSION: u16 = 6;
+
+/// Version that added compression
+pub const COMPRESSION_CHECKPOINT_VERSION: u16 = 7;
+
+/// Version that added payment streams
+pub const STREAMS_CHECKPOINT_VERSION: u16 = 8;
+
+/// Version that added allowances
+pub const ALLOWANCES_CHECKPOINT_VERSION: u16 = 9;
+
+/// Version that added frozen accounts
+pub const FROZEN_CHECKPOINT_VERSION: u16 = 10;
+
+/// Version that added balance locks
+pub const LOCKS_CHECKPOINT_VERSION: u16 = 11;
+
+/// Version that added auto-compounding
+pub const AUTO_COMPOUND_CHECKPOINT_VERSION: u16 = 12;
+
+/// Version that added lifetime UBI statistics
+pub const UBI_STATS_CHECKPOINT_VERSION: u16 = 13;
+
+/// Version written by the runtime, which added the dividend per token value of distributions
+pub const CURRENT_CHECKPOINT_VERSION: u16 = 14;
+
+/// Length of the SHA-256 digest that ends a checkpoint since version 6
+pub const CHECKPOINT_DIGEST_LEN: usize = 32;
+
+/// Flag set when the accounts and fee ledger are gzip compressed
+const COMPRESSED_FLAG: u8 = 1;
+
+/// Largest uncompressed body a compressed checkpoint may declare, in bytes
+///
+/// A small gzip stream can expand to gigabytes, so it is only inflated up
+/// to the length it declares, and that length is capped.
+pub const MAX_UNCOMPRESSED_CHECKPOINT_LEN: u64 = 4 << 30;
+
+/// Length of the header `read_checkpoint_header` reads: magic, version,
+/// timestamp, root hash, four counters and the flags byte
+const CHECKPOINT_HEADER_LEN: u64 = 8 + 2 + 8 + 32 + 8 * 4 + 1;
+
+/// A checkpoint whose contents do not hash to the digest stored with them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChecksumMismatch {
+    /// Digest stored at the end of the file
+    pub expected: [u8; 32],
+    /// Digest of the file's contents
+    pub actual: [u8; 32],
+}
+
+impl fmt::Display for ChecksumMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Checkpoint checksum mismatch: the file records {} but its contents hash to {}",
+               hex(&self.expected), hex(&self.actual))
+    }
+}
+
+fn hex(digest: &[u8; 32]) -> String {
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+impl std::error::Error for ChecksumMismatch {}
+
+/// State of one account in a checkpoint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckpointAccount {
+    /// Lowercase account address
+    pub address: String,
+    /// Balance in UBI tokens
+    pub balance: u64,
+    /// Whether the account passed human verification
+    pub verified: bool,
+    /// Last UBI claim in seconds since the UNIX epoch
+    pub last_ubi_claim_secs: u64,
+    /// Dividend per token value last seen by the account (0 in version 0 files)
+    pub last_dividend_point: u64,
+    /// Dividends owed but not yet claimed (0 in version 0 files)
+    pub unclaimed_dividends: u64,
+    /// Spending limits, if the account opted in (None before version 2)
+    pub limits: Option<AccountLimitState>,
+    /// When verification was last granted in seconds since the UNIX epoch
+    /// (None before version 3)
+    pub verified_at_secs: Option<u64>,
+    /// Number of transactions the account has executed (0 before version 5)
+    pub nonce: u64,
+    /// Whether the account is frozen (false before version 10)
+    pub frozen: bool,
+    /// Why the account was frozen, if a reason was given
+    pub frozen_reason: Option<String>,
+    /// Tokens locked until a later time (empty before version 11)
+    pub locks: Vec<BalanceLock>,
+    /// Whether dividends roll into the balance when the account transacts
+    /// (false before version 12)
+    pub auto_compound: bool,
+    /// Lifetime UBI received and claims made (zero before version 13)
+    pub ubi_stats: UbiStats,
+}
+
+/// Tokens an owner allows a spender to transfer out of its account
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckpointAllowance {
+    /// Lowercase address of the account the tokens are drawn from
+    pub owner: String,
+    /// Lowercase address allowed to draw them
+    pub spender: String,
+    /// Tokens left to draw, fee included
+    pub amount: u64,
+}
+
+/// Contents of a checkpoint file, independent of its version
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckpointData {
+    /// Time the checkpoint was created in seconds since the UNIX epoch
+    pub timestamp: u64,
+    /// Merkle root recorded when the checkpoint was created
+    pub root_hash: [u8; 32],
+    /// Total supply of tokens
+    pub total_supply: u64,
+    /// Undistributed transaction fees
+    pub fee_pool: u64,
+    /// Global dividend per token value (0 in version 0 files)
+    pub dividend_per_token: u64,
+    /// Account states
+    pub accounts: Vec<CheckpointAccount>,
+    /// Every change to the fee pool (empty before version 4)
+    pub fee_ledger: Vec<FeeLedgerEntry>,
+    /// Open payment streams (empty before version 8)
+    pub streams: Vec<PaymentStream>,
+    /// Id the next payment stream opened gets (0 before version 8)
+    pub next_stream_id: StreamId,
+    /// Allowances left to spend (empty before version 9)
+    pub allowances: Vec<CheckpointAllowance>,
+    /// Lifetime network totals (zero before version 13)
+    pub network_stats: NetworkStats,
+}
+
+impl CheckpointData {
+    /// Computes a Merkle root over the checkpoint's accounts
+    ///
+    /// The root recorded in the file depends on when each account was last
+    /// updated, which the file does not store. This root only depends on the
+    /// file contents: accounts are added in address order, and the checkpoint
+    /// timestamp stands in for the update time. It is used to check that a
+    /// rewritten checkpoint holds the same state as the original.
+    pub fn state_root(&self) -> [u8; 32] {
+        let mut accounts: Vec<&CheckpointAccount> = self.accounts.iter().collect();
+        accounts.sort_by(|a, b| a.address.cmp(&b.address));
+
+        let mut tree = MerkleTree::new();
+        for account in accounts {
+            tree.update_account(&account.address, &AccountState {
+                base_balance: account.balance,
+                last_update: self.timestamp,
+                streaming_rate: 0,
+                verified: account.verified,
+                nonce: account.nonce,
+                last_ubi_claim: account.last_ubi_claim_secs,
+            });
+        }
+
+        tree.root_hash().unwrap_or([0; 32])
+    }
+}
+
+/// Encodes a checkpoint in the current format, uncompressed
+pub fn encode_checkpoint(data: &CheckpointData) -> Vec<u8> {
+    encode_checkpoint_with_compression(data, false)
+}
+
+/// Encodes a checkpoint in the current format
+///
+/// # Arguments
+/// * `data` - The checkpoint contents
+/// * `compress` - Whether to gzip the accounts and fee ledger
+pub fn encode_checkpoint_with_compression(data: &CheckpointData, compress: bool) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(CHECKPOINT_MAGIC);
+    bytes.extend_from_slice(&CURRENT_CHECKPOINT_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&data.timestamp.to_le_bytes());
+    bytes.extend_from_slice(&data.root_hash);
+    bytes.extend_from_slice(&(data.accounts.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(&data.total_supply.to_le_bytes());
+    bytes.extend_from_slice(&data.fee_pool.to_le_bytes());
+    bytes.extend_from_slice(&data.dividend_per_token.to_le_bytes());
+
+    let body = encode_body(data);
+    if compress {
+        bytes.push(COMPRESSED_FLAG);
+        bytes.extend_from_slice(&(body.len() as u64).to_le_bytes());
+        let mut encoder = GzEncoder::new(bytes, Compression::default());
+        encoder.write_all(&body).expect("writing to a Vec cannot fail");
+        bytes = encoder.finish().expect("writing to a Vec cannot fail");
+    } else {
+        bytes.push(0);
+        bytes.extend_from_slice(&body);
+    }
+
+    let digest = Sha256::digest(&bytes);
+    bytes.extend_from_slice(&digest);
+    bytes
+}
+
+/// Encodes the accounts, the fee ledger and the payment streams
+fn encode_body(data: &CheckpointData) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for account in &data.accounts {
+        encode_account(&mut bytes, account);
+        bytes.push(account.frozen as u8);
+        encode_optional_string(&mut bytes, account.frozen_reason.as_deref());
+        bytes.extend_from_slice(&(account.locks.len() as u32).to_le_bytes());
+        for lock in &account.locks {
+            bytes.extend_from_slice(&lock.amount.to_le_bytes());
+            bytes.extend_from_slice(&lock.unlock_at.to_le_bytes());
+        }
+        bytes.push(account.auto_compound as u8);
+        bytes.extend_from_slice(&account.ubi_stats.total_ubi_received.to_le_bytes());
+        bytes.extend_from_slice(&account.ubi_stats.ubi_claim_count.to_le_bytes());
+    }
+
+    bytes.extend_from_slice(&(data.fee_ledger.len() as u64).to_le_bytes());
+    for entry in &data.fee_ledger {
+        encode_fee_ledger_entry(&mut bytes, entry);
+    }
+
+    bytes.extend_from_slice(&data.next_stream_id.to_le_bytes());
+    bytes.extend_from_slice(&(data.streams.len() as u64).to_le_bytes());
+    for stream in &data.streams {
+        encode_stream(&mut bytes, stream);
+    }
+
+    bytes.extend_from_slice(&(data.allowances.len() as u64).to_le_bytes());
+    for allowance in &data.allowances {
+        for address in [&allowance.owner, &allowance.spender] {
+            bytes.extend_from_slice(&(address.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(address.as_bytes());
+        }
+        bytes.extend_from_slice(&allowance.amount.to_le_bytes());
+    }
+
+    let stats = &data.network_stats;
+    for total in [stats.total_ubi_minted, stats.total_fees_collected, stats.total_dividends_distributed] {
+        bytes.extend_from_slice(&total.to_le_bytes());
+    }
+    bytes
+}
+
+/// Suffix of a checkpoint file while it is being written
+pub const TEMP_CHECKPOINT_SUFFIX: &str = ".tmp";
+
+/// Writes an encoded checkpoint so that the file either holds all of it or does not exist
+///
+/// The bytes go to a temporary file next to `path`, which is synced and then
+/// renamed over `path`. On any error the temporary file is removed.
+///
+/// # Arguments
+/// * `path` - Final path of the checkpoint file
+/// * `bytes` - The encoded checkpoint
+pub fn write_checkpoint_file(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut temp_name = path.as_os_str().to_owned();
+    temp_name.push(TEMP_CHECKPOINT_SUFFIX);
+    let temp_path = Path::new(&temp_name);
+
+    let result = fs::File::create(temp_path)
+        .and_then(|mut file| {
+            file.write_all(bytes)?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(temp_path, path));
+
+    if result.is_err() {
+        let _ = fs::remove_file(temp_path);
+    }
+    result
+}
+
+/// Appends an account's limit state, preceded by a presence flag
+/// Encodes the account fields every version since 5 holds
+fn encode_account(bytes: &mut Vec<u8>, account: &CheckpointAccount) {
+    bytes.extend_from_slice(&(account.address.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(account.address.as_bytes());
+    bytes.extend_from_slice(&account.balance.to_le_bytes());
+    bytes.push(account.verified as u8);
+    bytes.extend_from_slice(&account.last_ubi_claim_secs.to_le_bytes());
+    bytes.extend_from_slice(&account.last_dividend_point.to_le_bytes());
+    bytes.extend_from_slice(&account.unclaimed_dividends.to_le_bytes());
+    encode_limits(bytes, account.limits.as_ref());
+    encode_optional_u64(bytes, account.verified_at_secs);
+    bytes.extend_from_slice(&account.nonce.to_le_bytes());
+}
+
+fn encode_limits(bytes: &mut Vec<u8>, state: Option<&AccountLimitState>) {
+    let state = match state {
+        Some(state) => state,
+        None => {
+            bytes.push(0);
+            return;
+        }
+    };
+
+    bytes.push(1);
+    encode_account_limits(bytes, &state.active);
+    match &state.pending {
+        Some(pending) => {
+            bytes.push(1);
+            encode_account_limits(bytes, &pending.limits);
+            bytes.extend_from_slice(&pending.activates_at.to_le_bytes());
+        },
+        None => bytes.push(0),
+    }
+    bytes.extend_from_slice(&state.change_nonce.to_le_bytes());
+    bytes.extend_from_slice(&(state.outflows.len() as u32).to_le_bytes());
+    for (timestamp, amount) in &state.outflows {
+        bytes.extend_from_slice(&timestamp.to_le_bytes());
+        bytes.extend_from_slice(&amount.to_le_bytes());
+    }
+}
+
+/// Appends a fee ledger entry: its fields, a kind tag and the kind's data
+fn encode_fee_ledger_entry(bytes: &mut Vec<u8>, entry: &FeeLedgerEntry) {
+    bytes.extend_from_slice(&entry.sequence.to_le_bytes());
+    bytes.extend_from_slice(&entry.timestamp.to_le_bytes());
+    bytes.extend_from_slice(&entry.block_number.to_le_bytes());
+    bytes.extend_from_slice(&entry.amount.to_le_bytes());
+    match &entry.kind {
+        FeeLedgerKind::Collected { tx_hash } => {
+            bytes.push(0);
+            encode_optional_string(bytes, tx_hash.as_deref());
+        },
+        FeeLedgerKind::Distributed { dividend_delta, dividend_per_token } => {
+            bytes.push(1);
+            bytes.extend_from_slice(&dividend_delta.to_le_bytes());
+            bytes.extend_from_slice(&dividend_per_token.to_le_bytes());
+        },
+        FeeLedgerKind::Burned { tx_hash } => {
+            bytes.push(2);
+            encode_optional_string(bytes, tx_hash.as_deref());
+        },
+    }
+}
+
+/// Appends a payment stream: its id, parties, rate and settlement state
+fn encode_stream(bytes: &mut Vec<u8>, stream: &PaymentStream) {
+    bytes.extend_from_slice(&stream.id.to_le_bytes());
+    bytes.extend_from_slice(&(stream.from.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(stream.from.as_bytes());
+    bytes.extend_from_slice(&(stream.to.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(stream.to.as_bytes());
+    bytes.extend_from_slice(&stream.rate_per_hour.to_le_bytes());
+    bytes.extend_from_slice(&stream.opened_at.to_le_bytes());
+    bytes.extend_from_slice(&stream.settled_at.to_le_bytes());
+    bytes.extend_from_slice(&stream.streamed.to_le_bytes());
+    bytes.push(stream.paused as u8);
+}
+
+fn encode_optional_string(bytes: &mut Vec<u8>, value: Option<&str>) {
+    match value {
+        Some(value) => {
+            bytes.push(1);
+            bytes.extend_from_slice(&(value.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(value.as_bytes());
+        },
+        None => bytes.push(0),
+    }
+}
+
+fn encode_account_limits(bytes: &mut Vec<u8>, limits: &AccountLimits) {
+    encode_optional_u64(bytes, limits.max_transaction_amount);
+    encode_optional_u64(bytes, limits.daily_outflow_limit);
+}
+
+fn encode_optional_u64(bytes: &mut Vec<u8>, value: Option<u64>) {
+    match value {
+        Some(value) => {
+            bytes.push(1);
+            bytes.extend_from_slice(&value.to_le_bytes());
+        },
+        None => bytes.push(0),
+    }
+}
+
+/// Decodes a checkpoint written in a format with a digest, version 6 onwards
+///
+/// # Arguments
+/// * `bytes` - Contents of the checkpoint file
+///
+/// # Returns
+/// The version the file was written in and its contents
+pub fn decode_checkpoint(bytes: &[u8]) -> io::Result<(u16, CheckpointData)> {
+    decode_checkpoint_with_limit(bytes, DEFAULT_MAX_CHECKPOINT_ACCOUNTS)
+}
+
+/// Decodes a checkpoint, refusing one that declares more than `max_accounts` accounts
+///
+/// # Arguments
+/// * `bytes` - Contents of the checkpoint file
+/// * `max_accounts` - Most accounts the checkpoint may declare
+///
+/// # Returns
+/// The version the file was written in and its contents
+pub fn decode_checkpoint_with_limit(bytes: &[u8], max_accounts: u64) -> io::Result<(u16, CheckpointData)> {
+    let version = checkpoint_version(bytes)?;
+
+    let data = match version {
+        CURRENT_CHECKPOINT_VERSION | UBI_STATS_CHECKPOINT_VERSION | AUTO_COMPOUND_CHECKPOINT_VERSION | LOCKS_CHECKPOINT_VERSION | FROZEN_CHECKPOINT_VERSION | ALLOWANCES_CHECKPOINT_VERSION
+        | STREAMS_CHECKPOINT_VERSION | COMPRESSION_CHECKPOINT_VERSION | DIGEST_CHECKPOINT_VERSION => {
+            let contents = verify_digest(bytes)?;
+            decode_versioned(&contents[CHECKPOINT_MAGIC.len() + 2..], version, max_accounts)?
+        },
+        NONCE_CHECKPOINT_VERSION | FEE_LEDGER_CHECKPOINT_VERSION | VERIFIED_AT_CHECKPOINT_VERSION
+        | LIMITS_CHECKPOINT_VERSION | DIVIDENDS_CHECKPOINT_VERSION | LEGACY_CHECKPOINT_VERSION => {
+            return Err(invalid_data(
+                "Legacy checkpoint format; convert it with `ubi-chain-node migrate-checkpoints`"
+            ));
+        },
+        other => return Err(invalid_data(&format!("Unsupported checkpoint version {}", other))),
+    };
+
+    Ok((version, data))
+}
+
+/// Decodes a checkpoint written in any known format, including legacy ones
+///
+/// Intended for migration tooling; the runtime uses `decode_checkpoint`.
+pub fn decode_any_checkpoint(bytes: &[u8]) -> io::Result<(u16, CheckpointData)> {
+    match checkpoint_version(bytes)? {
+        LEGACY_CHECKPOINT_VERSION => Ok((LEGACY_CHECKPOINT_VERSION, decode_v0(bytes)?)),
+        version @ (DIVIDENDS_CHECKPOINT_VERSION | LIMITS_CHECKPOINT_VERSION | VERIFIED_AT_CHECKPOINT_VERSION
+                   | FEE_LEDGER_CHECKPOINT_VERSION | NONCE_CHECKPOINT_VERSION) => {
+            let body = bytes.get(CHECKPOINT_MAGIC.len() + 2..).unwrap_or_default();
+            Ok((version, decode_versioned(body, version, DEFAULT_MAX_CHECKPOINT_ACCOUNTS)?))
+        },
+        _ => decode_checkpoint(bytes),
+    }
+}
+
+/// A checkpoint rewritten in the current format
+#[derive(Debug, Clone)]
+pub struct UpgradedCheckpoint {
+    /// Version the original was written in
+    pub from_version: u16,
+    /// Contents of the checkpoint, the same in both formats
+    pub data: CheckpointData,
+    /// The checkpoint encoded in the current format
+    pub bytes: Vec<u8>,
+}
+
+/// Rewrites a checkpoint of any known version in the current format
+///
+/// The rewritten bytes are decoded again and must hold the same contents and
+/// Merkle root as the original, so a reader bug cannot silently lose state.
+///
+/// # Arguments
+/// * `bytes` - Contents of the checkpoint file
+///
+/// # Returns
+/// The original version, the contents and the rewritten bytes
+pub fn upgrade_checkpoint(bytes: &[u8]) -> io::Result<UpgradedCheckpoint> {
+    let (from_version, original) = decode_any_checkpoint(bytes)?;
+    let root_before = original.state_root();
+
+    let encoded = encode_checkpoint(&original);
+    let (_, rewritten) = decode_checkpoint(&encoded)?;
+    let root_after = rewritten.state_root();
+    if rewritten != original || root_after != root_before {
+        return Err(invalid_data(&format!(
+            "Merkle root changed from {} to {}", hex(&root_before), hex(&root_after)
+        )));
+    }
+
+    Ok(UpgradedCheckpoint { from_version, data: rewritten, bytes: encoded })
+}
+
+/// Metadata at the start of a checkpoint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckpointHeader {
+    /// Version the file was written in
+    pub version: u16,
+    /// When the checkpoint was taken, in seconds since the epoch
+    pub timestamp: u64,
+    /// Merkle root recorded when the checkpoint was taken
+    pub root_hash: [u8; 32],
+    /// Number of accounts the checkpoint declares
+    pub account_count: u64,
+    /// Total supply
+    pub total_supply: u64,
+    /// Undistributed fees
+    pub fee_pool: u64,
+    /// Whether the accounts and fee ledger are compressed
+    pub compressed: bool,
+}
+
+/// Reads the header of a checkpoint file without reading its accounts
+///
+/// Only versions `decode_checkpoint` reads are accepted. The digest is not
+/// checked, since that needs the whole file; a file with a damaged body is
+/// refused when it is decoded.
+///
+/// # Arguments
+/// * `path` - Path of the checkpoint file
+/// * `max_accounts` - Most accounts the checkpoint may declare
+pub fn read_checkpoint_header(path: &Path, max_accounts: u64) -> io::Result<CheckpointHeader> {
+    let mut bytes = Vec::new();
+    fs::File::open(path)?.take(CHECKPOINT_HEADER_LEN).read_to_end(&mut bytes)?;
+
+    let version = checkpoint_version(&bytes)?;
+    if !(DIGEST_CHECKPOINT_VERSION..=CURRENT_CHECKPOINT_VERSION).contains(&version) {
+        return Err(invalid_data(&format!("Checkpoint version {} is not loaded by the runtime", version)));
+    }
+
+    let mut reader = &bytes[CHECKPOINT_MAGIC.len() + 2..];
+    let timestamp = read_u64(&mut reader)?;
+    let root_hash = read_hash(&mut reader)?;
+    let account_count = read_u64(&mut reader)?;
+    let total_supply = read_u64(&mut reader)?;
+    let fee_pool = read_u64(&mut reader)?;
+    let _dividend_per_token = read_u64(&mut reader)?;
+    let compressed = version >= COMPRESSION_CHECKPOINT_VERSION && read_u8(&mut reader)? == COMPRESSED_FLAG;
+    if account_count > max_accounts {
+        return Err(invalid_data(&format!(
+            "Checkpoint declares {} accounts, more than the limit of {}", account_count, max_accounts
+        )));
+    }
+
+    Ok(CheckpointHeader { version, timestamp, root_hash, account_count, total_supply, fee_pool, compressed })
+}
+
+/// Checks the digest at the end of a checkpoint
+///
+/// # Returns
+/// The bytes the digest covers, or a `ChecksumMismatch` as invalid data
+fn verify_digest(bytes: &[u8]) -> io::Result<&[u8]> {
+    let split = bytes.len().checked_sub(CHECKPOINT_DIGEST_LEN)
+        .filter(|split| *split >= CHECKPOINT_MAGIC.len() + 2)
+        .ok_or_else(|| invalid_data("Checkpoint is too short to hold its digest"))?;
+    let (contents, stored) = bytes.split_at(split);
+
+    let actual: [u8; 32] = Sha256::digest(contents).into();
+    if stored != actual {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, ChecksumMismatch {
+            expected: stored.try_into().unwrap(),
+            actual,
+        }));
+    }
+    Ok(contents)
+}
+
+/// Reads the format version from the start of a checkpoint
+fn checkpoint_version(bytes: &[u8]) -> io::Result<u16> {
+    if !bytes.starts_with(CHECKPOINT_MAGIC) {
+        return Ok(LEGACY_CHECKPOINT_VERSION);
+    }
+
+    let version_bytes = bytes.get(CHECKPOINT_MAGIC.len()..CHECKPOINT_MAGIC.len() + 2)
+        .ok_or_else(|| invalid_data("Truncated checkpoint header"))?;
+    Ok(u16::from_le_bytes([version_bytes[0], version_bytes[1]]))
+}
+
+/// Reads the original headerless format
+fn decode_v0(mut reader: &[u8]) -> io::Result<CheckpointData> {
+    let timestamp = read_u64(&mut reader)?;
+    let root_hash = read_hash(&mut reader)?;
+    let account_count = read_u64(&mut reader)?;
+    let total_supply = read_u64(&mut reader)?;
+    let fee_pool = read_u64(&mut reader)?;
+    check_account_count(account_count, reader, LEGACY_CHECKPOINT_VERSION, DEFAULT_MAX_CHECKPOINT_ACCOUNTS)?;
+
+    let mut accounts = Vec::new();
+    for _ in 0..account_count {
+        accounts.push(CheckpointAccount {
+            address: read_address(&mut reader)?,
+            balance: read_u64(&mut reader)?,
+            verified: read_u8(&mut reader)? != 0,
+            last_ubi_claim_secs: read_u64(&mut reader)?,
+            last_dividend_point: 0,
+            unclaimed_dividends: 0,
+            limits: None,
+            verified_at_secs: None,
+            nonce: 0,
+            frozen: false,
+            frozen_reason: None,
+            locks: Vec::new(),
+            auto_compound: false,
+            ubi_stats: UbiStats::default(),
+        });
+    }
+
+    Ok(CheckpointData {
+        timestamp,
+        root_hash,
+        total_supply,
+        fee_pool,
+        dividend_per_token: 0,
+        accounts,
+        fee_ledger: Vec::new(),
+        streams: Vec::new(),
+        next_stream_id: 0,
+        allowances: Vec::new(),
+        network_stats: NetworkStats::default(),
+    })
+}
+
+/// Reads versions 1 to 14, starting after the magic and version and ending before any digest
+///
+/// # Arguments
+/// * `input` - The checkpoint body
+/// * `version` - The version the body was written in
+/// * `max_accounts` - Most accounts the body may declare
+fn decode_versioned(input: &[u8], version: u16, max_accounts: u64) -> io::Result<CheckpointData> {
+    let inflated;
+    let mut reader = input;
+    let timestamp = read_u64(&mut reader)?;
+    let root_hash = read_hash(&mut reader)?;
+    let account_count = read_u64(&mut reader)?;
+    let total_supply = read_u64(&mut reader)?;
+    let fee_pool = read_u64(&mut reader)?;
+    let dividend_per_token = read_u64(&mut reader)?;
+    if version >= COMPRESSION_CHECKPOINT_VERSION {
+        match read_u8(&mut reader)? {
+            0 => {},
+            COMPRESSED_FLAG => {
+                inflated = inflate(&mut reader)?;
+                reader = &inflated;
+            },
+            flags => return Err(invalid_data(&format!("Unknown checkpoint flags {:#04x}", flags))),
+        }
+    }
+    check_account_count(account_count, reader, version, max_accounts)?;
+
+    let mut accounts = Vec::new();
+    for _ in 0..account_count {
+        let mut account = CheckpointAccount {
+            address: read_address(&mut reader)?,
+            balance: read_u64(&mut reader)?,
+            verified: read_u8(&mut reader)? != 0,
+            last_ubi_claim_secs: read_u64(&mut reader)?,
+            last_dividend_point: read_u64(&mut reader)?,
+            unclaimed_dividends: read_u64(&mut reader)?,
+            limits: if version >= LIMITS_CHECKPOINT_VERSION { read_limits(&mut reader)? } else { None },
+            verified_at_secs: if version >= VERIFIED_AT_CHECKPOINT_VERSION { read_optional_u64(&mut reader)? } else { None },
+            nonce: if version >= NONCE_CHECKPOINT_VERSION { read_u64(&mut reader)? } else { 0 },
+            frozen: false,
+            frozen_reason: None,
+            locks: Vec::new(),
+            auto_compound: false,
+            ubi_stats: UbiStats::default(),
+        };
+        if version >= FROZEN_CHECKPOINT_VERSION {
+            account.frozen = read_u8(&mut reader)? != 0;
+            account.frozen_reason = read_optional_string(&mut reader)?;
+        }
+        if version >= LOCKS_CHECKPOINT_VERSION {
+            let lock_count = read_u32(&mut reader)?;
+            // Every lock takes its amount and unlock time
+            check_count(lock_count as u64, reader, 2 * 8, "Lock count")?;
+            for _ in 0..lock_count {
+                account.locks.push(BalanceLock { amount: read_u64(&mut reader)?, unlock_at: read_u64(&mut reader)? });
+            }
+        }
+        if version >= AUTO_COMPOUND_CHECKPOINT_VERSION {
+            account.auto_compound = read_u8(&mut reader)? != 0;
+        }
+        if version >= UBI_STATS_CHECKPOINT_VERSION {
+            account.ubi_stats = UbiStats {
+                total_ubi_received: read_u64(&mut reader)?,
+                ubi_claim_count: read_u64(&mut reader)?,
+            };
+        }
+        accounts.push(account);
+    }
+
+    let mut fee_ledger = Vec::new();
+    if version >= FEE_LEDGER_CHECKPOINT_VERSION {
+        let entry_count = read_u64(&mut reader)?;
+        // Every entry takes at least its four numbers, a kind tag and a presence flag
+        check_count(entry_count, reader, 4 * 8 + 2, "Fee ledger length")?;
+        for _ in 0..entry_count {
+            fee_ledger.push(read_fee_ledger_entry(&mut reader, version)?);
+        }
+        if version < CURRENT_CHECKPOINT_VERSION {
+            fee_ledger::fill_dividend_per_token(&mut fee_ledger, dividend_per_token);
+        }
+    }
+
+    let mut streams = Vec::new();
+    let mut next_stream_id = 0;
+    if version >= STREAMS_CHECKPOINT_VERSION {
+        next_stream_id = read_u64(&mut reader)?;
+        let stream_count = read_u64(&mut reader)?;
+        // Every stream takes at least its five numbers, two address lengths and the paused flag
+        check_count(stream_count, reader, 5 * 8 + 2 * 4 + 1, "Stream count")?;
+        for _ in 0..stream_count {
+            streams.push(read_stream(&mut reader)?);
+        }
+    }
+
+    let mut allowances = Vec::new();
+    if version >= ALLOWANCES_CHECKPOINT_VERSION {
+        let allowance_count = read_u64(&mut reader)?;
+        // Every allowance takes at least two address lengths and its amount
+        check_count(allowance_count, reader, 2 * 4 + 8, "Allowance count")?;
+        for _ in 0..allowance_count {
+            allowances.push(CheckpointAllowance {
+                owner: read_address(&mut reader)?,
+                spender: read_address(&mut reader)?,
+                amount: read_u64(&mut reader)?,
+            });
+        }
+    }
+
+    let mut network_stats = NetworkStats::default();
+    if version >= UBI_STATS_CHECKPOINT_VERSION {
+        network_stats = NetworkStats {
+            total_ubi_minted: read_u64(&mut reader)?,
+            total_fees_collected: read_u64(&mut reader)?,
+            total_dividends_distributed: read_u64(&mut reader)?,
+        };
+    }
+
+    Ok(CheckpointData {
+        timestamp,
+        root_hash,
+        total_supply,
+        fee_pool,
+        dividend_per_token,
+        accounts,
+        fee_ledger,
+        streams,
+        next_stream_id,
+        allowances,
+        network_stats,
+    })
+}
+
+/// Inflates a compressed body, which is preceded by its uncompressed length
+fn inflate(reader: &mut &[u8]) -> io::Result<Vec<u8>> {
+    let length = read_u64(reader)?;
+    if length > MAX_UNCOMPRESSED_CHECKPOINT_LEN {
+        return Err(invalid_data(&format!(
+            "Compressed body of {} bytes exceeds the {} byte limit", length, MAX_UNCOMPRESSED_CHECKPOINT_LEN
+        )));
+    }
+
+    // Reading one byte past the declared length catches a stream that is longer
+    let mut body = Vec::new();
+    GzDecoder::new(*reader).take(length + 1).read_to_end(&mut body)
+        .map_err(|e| invalid_data(&format!("Invalid compressed checkpoint body: {}", e)))?;
+    if body.len() as u64 != length {
+        return Err(invalid_data(&format!(
+            "Compressed body holds {} bytes, not the {} it declares", body.len(), length
+        )));
+    }
+    *reader = &[];
+    Ok(body)
+}
+
+fn read_fee_ledger_entry(reader: &mut &[u8], version: u16) -> io::Result<FeeLedgerEntry> {
+    let sequence = read_u64(reader)?;
+    let timestamp = read_u64(reader)?;
+    let block_number = read_u64(reader)?;
+    let amount = read_u64(reader)?;
+    let kind = match read_u8(reader)? {
+        0 => FeeLedgerKind::Collected { tx_hash: read_optional_string(reader)? },
+        1 => FeeLedgerKind::Distributed {
+            dividend_delta: read_u64(reader)?,
+            dividend_per_token: if version >= CURRENT_CHECKPOINT_VERSION { read_u64(reader)? } else { 0 },
+        },
+        2 => FeeLedgerKind::Burned { tx_hash: read_optional_string(reader)? },
+        other => return Err(invalid_data(&format!("Unknown fee ledger entry kind {}", other))),
+    };
+
+    Ok(FeeLedgerEntry { sequence, timestamp, block_number, kind, amount })
+}
+
+fn read_stream(reader: &mut &[u8]) -> io::Result<PaymentStream> {
+    Ok(PaymentStream {
+        id: read_u64(reader)?,
+        from: read_address(reader)?,
+        to: read_address(reader)?,
+        rate_per_hour: read_u64(reader)?,
+        opened_at: read_u64(reader)?,
+        settled_at: read_u64(reader)?,
+        streamed: read_u64(reader)?,
+        paused: read_u8(reader)? != 0,
+    })
+}
+
+fn read_optional_string(reader: &mut &[u8]) -> io::Result<Option<String>> {
+    if read_u8(reader)? == 0 {
+        Ok(None)
+    } else {
+        read_string(reader).map(Some)
+    }
+}
+
+fn read_limits(reader: &mut &[u8]) -> io::Result<Option<AccountLimitState>> {
+    if read_u8(reader)? == 0 {
+        return Ok(None);
+    }
+
+    let active = read_account_limits(reader)?;
+    let pending = if read_u8(reader)? != 0 {
+        Some(PendingLimits {
+            limits: read_account_limits(reader)?,
+            activates_at: read_u64(reader)?,
+        })
+    } else {
+        None
+    };
+    let change_nonce = read_u64(reader)?;
+
+    let mut count_bytes = [0u8; 4];
+    reader.read_exact(&mut count_bytes)?;
+    let count = u32::from_le_bytes(count_bytes) as usize;
+    if count > reader.len() / 16 {
+        return Err(invalid_data("Outflow count exceeds checkpoint size"));
+    }
+
+    let mut outflows = VecDeque::with_capacity(count);
+    for _ in 0..count {
+        outflows.push_back((read_u64(reader)?, read_u64(reader)?));
+    }
+
+    Ok(Some(AccountLimitState { active, pending, change_nonce, outflows }))
+}
+
+fn read_account_limits(reader: &mut &[u8]) -> io::Result<AccountLimits> {
+    Ok(AccountLimits {
+        max_transaction_amount: read_optional_u64(reader)?,
+        daily_outflow_limit: read_optional_u64(reader)?,
+    })
+}
+
+fn read_optional_u64(reader: &mut &[u8]) -> io::Result<Option<u64>> {
+    if read_u8(reader)? == 0 {
+        Ok(None)
+    } else {
+        read_u64(reader).map(Some)
+    }
+}
+
+/// Gets the fewest bytes an account takes in the given version
+fn min_account_len(version: u16) -> usize {
+    // Address length, balance, verified flag and last UBI claim
+    let mut length = 4 + 8 + 1 + 8;
+    if version >= DIVIDENDS_CHECKPOINT_VERSION {
+        length += 8 + 8;
+    }
+    if version >= LIMITS_CHECKPOINT_VERSION {
+        length += 1;
+    }
+    if version >= VERIFIED_AT_CHECKPOINT_VERSION {
+        length += 1;
+    }
+    if version >= NONCE_CHECKPOINT_VERSION {
+        length += 8;
+    }
+    if version >= FROZEN_CHECKPOINT_VERSION {
+        length += 1 + 1;
+    }
+    if version >= LOCKS_CHECKPOINT_VERSION {
+        length += 4;
+    }
+    if version >= AUTO_COMPOUND_CHECKPOINT_VERSION {
+        length += 1;
+    }
+    if version >= UBI_STATS_CHECKPOINT_VERSION {
+        length += 2 * 8;
+    }
+    length
+}
+
+/// Refuses a count of items that cannot fit in the remaining bytes
+fn check_count(count: u64, remaining: &[u8], min_item_len: usize, what: &str) -> io::Result<()> {
+    if count > (remaining.len() / min_item_len) as u64 {
+        return Err(invalid_data(&format!(
+            "{} {} exceeds checkpoint size: needs at least {} bytes but {} remain",
+            what, count, count.saturating_mul(min_item_len as u64), remaining.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Refuses an account count over the limit or larger than the file can hold
+fn check_account_count(count: u64, remaining: &[u8], version: u16, max_accounts: u64) -> io::Result<()> {
+    if count > max_accounts {
+        return Err(invalid_data(&format!(
+            "Checkpoint declares {} accounts, more than the limit of {}", count, max_accounts
+        )));
+    }
+    check_count(count, remaining, min_account_len(version), "Account count")
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn read_u8(reader: &mut &[u8]) -> io::Result<u8> {
+    let mut bytes = [0u8; 1];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes[0])
+}
+
+fn read_u32(reader: &mut &[u8]) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut &[u8]) -> io::Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_hash(reader: &mut &[u8]) -> io::Result<[u8; 32]> {
+    let mut hash = [0u8; 32];
+    reader.read_exact(&mut hash)?;
+    Ok(hash)
+}
+
+/// Reads a length-prefixed account address and checks it is well formed
+fn read_address(reader: &mut &[u8]) -> io::Result<String> {
+    let address = read_bounded_string(reader, MAX_CHECKPOINT_ADDRESS_LEN)?;
+    if !is_valid_eth_address(&address) {
+        return Err(invalid_data(&format!("Invalid account address {:?} in checkpoint", address)));
+    }
+    Ok(address)
+}
+
+/// Reads a length-prefixed transaction hash
+fn read_string(reader: &mut &[u8]) -> io::Result<String> {
+    read_bounded_string(reader, MAX_CHECKPOINT_STRING_LEN)
+}
+
+fn read_bounded_string(reader: &mut &[u8], max_len: usize) -> io::Result<String> {
+    let mut length_bytes = [0u8; 4];
+    reader.read_exact(&mut length_bytes)?;
+    let length = u32::from_le_bytes(length_bytes) as usize;
+    if length > max_len {
+        return Err(invalid_data(&format!("String of {} bytes exceeds the {} byte limit", length, max_len)));
+    }
+    if length > reader.len() {
+        return Err(invalid_data("String length exceeds checkpoint size"));
+    }
+
+    let mut value = vec![0u8; length];
+    reader.read_exact(&mut value)?;
+    String::from_utf8(value).map_err(|_| invalid_data("Invalid UTF-8 in string"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A version 0 checkpoint written by the original `create_checkpoint`
+    const LEGACY_FIXTURE: &[u8] = include_bytes!("../fixtures/checkpoint_v0.dat");
+
+    /// The same checkpoint in version 1, with dividend tracking
+    const V1_FIXTURE: &[u8] = include_bytes!("../fixtures/checkpoint_v1.dat");
+
+    #[test]
+    fn test_decode_legacy_fixture() {
+        // The runtime no longer reads the legacy format directly
+        assert!(decode_checkpoint(LEGACY_FIXTURE).is_err());
+
+        let (version, data) = decode_any_checkpoint(LEGACY_FIXTURE).unwrap();
+
+        assert_eq!(version, LEGACY_CHECKPOINT_VERSION);
+        assert_eq!(data.timestamp, 1_735_689_600);
+        assert_eq!(data.root_hash, [0xab; 32]);
+        assert_eq!(data.total_supply, 600);
+        assert_eq!(data.fee_pool, 3);
+        assert_eq!(data.dividend_per_token, 0);
+        assert_eq!(data.accounts.len(), 2);
+        assert_eq!(data.accounts[0], CheckpointAccount {
+            address: "0x1111111111111111111111111111111111111111".to_string(),
+            balance: 100,
+            verified: true,
+            last_ubi_claim_secs: 1_735_600_000,
+            last_dividend_point: 0,
+            unclaimed_dividends: 0,
+            limits: None,
+            verified_at_secs: None,
+            nonce: 0,
+            frozen: false,
+            frozen_reason: None,
+            locks: Vec::new(),
+            auto_compound: false,
+            ubi_stats: UbiStats::default(),
+        });
+        assert_eq!(data.accounts[1].balance, 500);
+        assert!(!data.accounts[1].verified);
+    }
+
+    #[test]
+    fn test_legacy_round_trip_through_current_format() {
+        let (_, legacy) = decode_any_checkpoint(LEGACY_FIXTURE).unwrap();
+
+        let encoded = encode_checkpoint(&legacy);
+        assert!(encoded.starts_with(CHECKPOINT_MAGIC));
+
+        let (version, current) = decode_checkpoint(&encoded).unwrap();
+        assert_eq!(version, CURRENT_CHECKPOINT_VERSION);
+        assert_eq!(current, legacy);
+        assert_eq!(current.state_root(), legacy.state_root());
+    }
+
+    /// Builds a checkpoint using every optional part of the format
+    fn sample_checkpoint() -> CheckpointData {
+        let (_, mut data) = decode_any_checkpoint(LEGACY_FIXTURE).unwrap();
+        let mut limits = AccountLimitState::default();
+        limits.request_change(AccountLimits { max_transaction_amount: Some(10), daily_outflow_limit: None }, 100);
+        limits.request_change(AccountLimits { max_transaction_amount: Some(20), daily_outflow_limit: Some(50) }, 200);
+        limits.record_outflow(11, 300);
+        data.accounts[0].limits = Some(limits);
+        data.accounts[0].verified_at_secs = Some(1_735_650_000);
+        data.accounts[0].nonce = 4;
+        data.accounts[1].frozen = true;
+        data.accounts[1].frozen_reason = Some("sybil review".to_string());
+        data.accounts[0].locks = vec![
+            BalanceLock { amount: 40, unlock_at: 1_767_225_600 },
+            BalanceLock { amount: 10, unlock_at: 1_798_761_600 },
+        ];
+        data.accounts[1].auto_compound = true;
+        data.accounts[0].ubi_stats = UbiStats { total_ubi_received: 240, ubi_claim_count: 3 };
+        data.network_stats = NetworkStats { total_ubi_minted: 240, total_fees_collected: 12, total_dividends_distributed: 9 };
+        data.dividend_per_token = 5_000_000;
+        data.fee_ledger = vec![
+            FeeLedgerEntry {
+                sequence: 0,
+                timestamp: 1_735_650_100,
+                block_number: 7,
+                kind: FeeLedgerKind::Collected { tx_hash: Some("0xabc".to_string()) },
+                amount: 3,
+            },
+            FeeLedgerEntry {
+                sequence: 1,
+                timestamp: 1_735_650_200,
+                block_number: 8,
+                kind: FeeLedgerKind::Distributed { dividend_delta: 5_000_000, dividend_per_token: 5_000_000 },
+                amount: 3,
+            },
+        ];
+        data.streams = vec![PaymentStream {
+            id: 2,
+            from: data.accounts[1].address.clone(),
+            to: data.accounts[0].address.clone(),
+            rate_per_hour: 12,
+            opened_at: 1_735_650_300,
+            settled_at: 1_735_660_000,
+            streamed: 30,
+            paused: true,
+        }];
+        data.next_stream_id = 3;
+        data.allowances = vec![CheckpointAllowance {
+            owner: data.accounts[0].address.clone(),
+            spender: data.accounts[1].address.clone(),
+            amount: 250,
+        }];
+        data
+    }
+
+    #[test]
+    fn test_decode_and_upgrade_v1_fixture() {
+        assert!(decode_checkpoint(V1_FIXTURE).unwrap_err().to_string().contains("migrate-checkpoints"));
+
+        let (version, data) = decode_any_checkpoint(V1_FIXTURE).unwrap();
+        assert_eq!(version, DIVIDENDS_CHECKPOINT_VERSION);
+        assert_eq!(data.dividend_per_token, 5_000_000);
+        assert_eq!(data.accounts[0].last_dividend_point, 5_000_000);
+        assert_eq!(data.accounts[0].unclaimed_dividends, 7);
+
+        // Apart from dividends it holds what the version 0 file holds
+        let (_, legacy) = decode_any_checkpoint(LEGACY_FIXTURE).unwrap();
+        assert_eq!(data.state_root(), legacy.state_root());
+
+        let upgraded = upgrade_checkpoint(V1_FIXTURE).unwrap();
+        assert_eq!(upgraded.from_version, DIVIDENDS_CHECKPOINT_VERSION);
+        assert_eq!(upgraded.data, data);
+        assert_eq!(decode_checkpoint(&upgraded.bytes).unwrap(), (CURRENT_CHECKPOINT_VERSION, data));
+    }
+
+    #[test]
+    fn test_limits_and_fee_ledger_round_trip() {
+        let data = sample_checkpoint();
+        let (_, decoded) = decode_checkpoint(&encode_checkpoint(&data)).unwrap();
+        assert_eq!(decoded, data);
+    }
+
+    #[test]
+    fn test_compressed_round_trip() {
+        let data = sample_checkpoint();
+        let bytes = encode_checkpoint_with_compression(&data, true);
+        assert_eq!(decode_checkpoint(&bytes).unwrap(), (CURRENT_CHECKPOINT_VERSION, data.clone()));
+
+        // The header stays readable without inflating anything
+        let path = std::env::temp_dir().join(format!("ubi_compressed_checkpoint_{}.dat", std::process::id()));
+        fs::write(&path, &bytes).unwrap();
+        let header = read_checkpoint_header(&path, DEFAULT_MAX_CHECKPOINT_ACCOUNTS).unwrap();
+        assert!(header.compressed);
+        assert_eq!((header.timestamp, header.root_hash, header.account_count), (data.timestamp, data.root_hash, 2));
+        assert!(read_checkpoint_header(&path, 1).unwrap_err().to_string().contains("more than the limit of 1"));
+        fs::remove_file(&path).unwrap();
+
+        // A declared length that is too large or does not match the stream is refused
+        let length_at = CHECKPOINT_MAGIC.len() + 2 + 8 + 32 + 8 * 4 + 1;
+        for length in [MAX_UNCOMPRESSED_CHECKPOINT_LEN + 1, 10] {
+            let mut tampered = bytes.clone();
+            tampered[length_at..length_at + 8].copy_from_slice(&length.to_le_bytes());
+            reseal(&mut tampered);
+            let error = decode_checkpoint(&tampered).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{}", error);
+        }
+    }
+
+    /// Recomputes the digest at the end of a current-format checkpoint
+    fn reseal(bytes: &mut Vec<u8>) {
+        bytes.truncate(bytes.len() - CHECKPOINT_DIGEST_LEN);
+        let digest = Sha256::digest(&bytes);
+        bytes.extend_from_slice(&digest);
+    }
+
+    /// Drops what versions before 8 cannot hold: streams, allowances, freezes, locks, auto-compounding and statistics
+    fn without_streams(data: &CheckpointData) -> CheckpointData {
+        let accounts = data.accounts.iter()
+            .map(|account| CheckpointAccount {
+                frozen: false,
+                frozen_reason: None,
+                locks: Vec::new(),
+                auto_compound: false,
+                ubi_stats: UbiStats::default(),
+                ..account.clone()
+            })
+            .collect();
+        CheckpointData {
+            accounts,
+            streams: Vec::new(),
+            next_stream_id: 0,
+            allowances: Vec::new(),
+            network_stats: NetworkStats::default(),
+            ..data.clone()
+        }
+    }
+
+    /// Encodes a checkpoint as version 5, which carries no flags, streams, allowances, freezes, locks or digest
+    fn encode_without_digest(data: &CheckpointData) -> Vec<u8> {
+        let mut bytes = encode_checkpoint(data);
+        bytes.truncate(CHECKPOINT_HEADER_LEN as usize - 1);
+        let version = CHECKPOINT_MAGIC.len();
+        bytes[version..version + 2].copy_from_slice(&NONCE_CHECKPOINT_VERSION.to_le_bytes());
+        for account in &data.accounts {
+            encode_account(&mut bytes, account);
+        }
+        bytes.extend_from_slice(&(data.fee_ledger.len() as u64).to_le_bytes());
+        for entry in &data.fee_ledger {
+            encode_fee_ledger_entry(&mut bytes, entry);
+            // Distributions only carry the dividend per token value they left since version 14
+            if matches!(entry.kind, FeeLedgerKind::Distributed { .. }) {
+                bytes.truncate(bytes.len() - 8);
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_digest_rejects_corrupted_checkpoints() {
+        let data = sample_checkpoint();
+        let bytes = encode_checkpoint(&data);
+
+        // Any flipped bit, even one inside a balance, is caught
+        for position in [CHECKPOINT_MAGIC.len() + 2, bytes.len() / 2, bytes.len() - 1] {
+            let mut corrupted = bytes.clone();
+            corrupted[position] ^= 0x01;
+            let error = decode_checkpoint(&corrupted).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+            let mismatch = error.get_ref().and_then(|inner| inner.downcast_ref::<ChecksumMismatch>());
+            assert!(mismatch.is_some(), "position {}: {}", position, error);
+        }
+
+        // Version 5 files have no digest and are read by migration tooling, which works back what distributions left
+        let (version, decoded) = decode_any_checkpoint(&encode_without_digest(&data)).unwrap();
+        assert_eq!(version, NONCE_CHECKPOINT_VERSION);
+        assert_eq!(decoded, without_streams(&data));
+    }
+
+    #[test]
+    fn test_fuzzed_checkpoints_decode_or_fail_cleanly() {
+        // The version 5 seed lets mutations reach the decoder instead of stopping at the digest
+        let seeds = [
+            encode_checkpoint(&sample_checkpoint()),
+            encode_without_digest(&sample_checkpoint()),
+            LEGACY_FIXTURE.to_vec(),
+        ];
+
+        // Xorshift keeps the cases reproducible without a fuzzing dependency
+        let mut state = 0x9e37_79b9_7f4a_7c15u64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        for case in 0..20_000 {
+            let mut bytes = seeds[case % seeds.len()].clone();
+            for _ in 0..1 + next() % 4 {
+                let position = next() as usize % bytes.len();
+                match next() % 4 {
+                    0 => bytes[position] ^= 1 << (next() % 8),
+                    1 => bytes[position] = 0xff,
+                    // Lengths and counts claiming far more than the file holds
+                    2 => {
+                        let end = (position + 4).min(bytes.len());
+                        bytes[position..end].fill(0xff);
+                    },
+                    _ => bytes.truncate(position),
+                }
+                if bytes.is_empty() {
+                    break;
+                }
+            }
+
+            // Anything that decodes re-encodes to the same contents
+            for decode in [decode_checkpoint, decode_any_checkpoint] {
+                match decode(&bytes) {
+                    Ok((_, data)) => assert_eq!(decode_checkpoint(&encode_checkpoint(&data)).unwrap().1, data),
+                    Err(e) => assert!(
+                        matches!(e.kind(), io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof),
+                        "case {}: {}", case, e
+                    ),
+                }
+            }
+        }
+
+        // A 4 GB string and 2^64 accounts are refused before anything is allocated
+        let mut bytes = encode_checkpoint(&sample_checkpoint());
+        let header_len = CHECKPOINT_MAGIC.len() + 2 + 8 + 32;
+        let accounts_start = header_len + 8 * 4 + 1;
+        bytes[accounts_start..accounts_start + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        reseal(&mut bytes);
+        assert!(decode_checkpoint(&bytes).unwrap_err().to_string().contains("limit"));
+        bytes[header_len..header_len + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        reseal(&mut bytes);
+        assert!(decode_checkpoint(&bytes).unwrap_err().to_string().contains("accounts"));
+    }
+
+    /// Builds an uncompressed current-format header declaring `account_count` accounts
+    fn header(account_count: u64) -> Vec<u8> {
+        let mut bytes = CHECKPOINT_MAGIC.to_vec();
+        bytes.extend_from_slice(&CURRENT_CHECKPOINT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&1_735_689_600u64.to_le_bytes());
+        bytes.extend_from_slice(&[0u8; 32]);
+        bytes.extend_from_slice(&account_count.to_le_bytes());
+        bytes.extend_from_slice(&[0u8; 8 * 3 + 1]);
+        bytes
+    }
+
+    /// Appends the digest to hand-built checkpoint contents
+    fn sealed(bytes: &[u8]) -> Vec<u8> {
+        let mut sealed = bytes.to_vec();
+        sealed.extend_from_slice(&Sha256::digest(bytes));
+        sealed
+    }
+
+    /// Appends an account with the given address and zeroed fields
+    fn push_account(bytes: &mut Vec<u8>, address: &[u8]) {
+        bytes.extend_from_slice(&(address.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(address);
+        bytes.extend_from_slice(&[0u8; 8 + 1 + 8 * 3 + 2 + 8 + 2 + 4 + 1 + 2 * 8]);
+    }
+
+    #[test]
+    fn test_rejects_malicious_headers() {
+        let error_of = |bytes: &[u8], max_accounts| decode_checkpoint_with_limit(&sealed(bytes), max_accounts).unwrap_err();
+
+        // More accounts than the configured maximum, even if the file could hold them
+        let mut bytes = header(3);
+        for _ in 0..3 {
+            push_account(&mut bytes, b"0x1111111111111111111111111111111111111111");
+        }
+        bytes.extend_from_slice(&[0u8; 8 * 4 + 3 * 8]);
+        assert!(decode_checkpoint_with_limit(&sealed(&bytes), 3).is_ok());
+        let error = error_of(&bytes, 2);
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("more than the limit of 2"), "{}", error);
+
+        // A count the file size cannot back
+        let error = error_of(&header(1_000), DEFAULT_MAX_CHECKPOINT_ACCOUNTS);
+        assert!(error.to_string().contains("Account count 1000 exceeds checkpoint size"), "{}", error);
+
+        // An address longer than any real one
+        let mut bytes = header(1);
+        push_account(&mut bytes, &[b'a'; MAX_CHECKPOINT_ADDRESS_LEN + 1]);
+        let error = error_of(&bytes, DEFAULT_MAX_CHECKPOINT_ACCOUNTS);
+        assert!(error.to_string().contains("byte limit"), "{}", error);
+
+        // A short address that is not an Ethereum address
+        let mut bytes = header(1);
+        push_account(&mut bytes, b"0xnot-an-address");
+        let error = error_of(&bytes, DEFAULT_MAX_CHECKPOINT_ACCOUNTS);
+        assert!(error.to_string().contains("Invalid account address"), "{}", error);
+    }
+
+    #[test]
+    fn test_rejects_truncated_and_unknown_versions() {
+        assert!(decode_any_checkpoint(&LEGACY_FIXTURE[..LEGACY_FIXTURE.len() - 1]).is_err());
+
+        let mut future = CHECKPOINT_MAGIC.to_vec();
+        future.extend_from_slice(&15u16.to_le_bytes());
+        assert!(decode_checkpoint(&future).is_err());
+    }
+}
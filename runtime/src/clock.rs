@@ -0,0 +1,70 @@
+//! Clock
+//!
+//! The runtime reads the time through a clock rather than the system time
+//! directly, so accrual, limits and timestamps can be tested at pinned times.
+//! Nodes always run on the system clock; a mock clock is only available to
+//! tests, through the `test-utils` feature.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Source of the current time, in seconds since epoch
+#[derive(Debug, Clone, Default)]
+pub struct Clock {
+    /// Time set by a mock clock; the system time if None
+    mocked: Option<Arc<AtomicU64>>,
+}
+
+impl Clock {
+    /// Creates a clock reading the system time
+    pub fn system() -> Self {
+        Clock::default()
+    }
+
+    /// Gets the current time, in seconds since epoch
+    pub fn now_secs(&self) -> u64 {
+        match &self.mocked {
+            Some(now) => now.load(Ordering::SeqCst),
+            None => SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+        }
+    }
+}
+
+/// A clock that only moves when told to, shared with every runtime it is given to
+#[cfg(any(test, feature = "test-utils"))]
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    now: Arc<AtomicU64>,
+}
+
+#[cfg(any(test, feature = "test-utils"))]
+impl MockClock {
+    /// Creates a mock clock stopped at the given time
+    pub fn at(secs: u64) -> Self {
+        MockClock { now: Arc::new(AtomicU64::new(secs)) }
+    }
+
+    /// Gets the time the clock is stopped at
+    pub fn now_secs(&self) -> u64 {
+        self.now.load(Ordering::SeqCst)
+    }
+
+    /// Moves the clock to the given time
+    pub fn set(&self, secs: u64) {
+        self.now.store(secs, Ordering::SeqCst);
+    }
+
+    /// Moves the clock forward
+    pub fn advance(&self, secs: u64) {
+        self.now.fetch_add(secs, Ordering::SeqCst);
+    }
+
+    /// Gets a clock reading this mock's time, to hand to a runtime
+    pub fn clock(&self) -> Clock {
+        Clock { mocked: Some(self.now.clone()) }
+    }
+}
@@ -0,0 +1,49 @@
+//! Runtime Configuration
+//!
+//! Settings a runtime starts with, such as a testnet's faster UBI accrual.
+//! Nodes take all of them from the network's genesis file
+//! (`GenesisConfig::runtime_config`), never from operator flags, since every
+//! node of a network must use the same values. They only give the runtime its
+//! starting values: the UBI rate and the transfer fee are chain parameters,
+//! so governance can still schedule changes to them later.
+//!
+//! UBI back pay is capped, so an account left dormant for a year is not paid
+//! a year's worth in one claim, and farming idle accounts does not pay.
+//!
+//! The fee pool is distributed to token holders every so many blocks, at the
+//! same block numbers on every node.
+
+use serde::{Deserialize, Serialize};
+
+use crate::params::BPS_DENOMINATOR;
+use crate::{TRANSFER_FEE_PERCENT, UBI_TOKENS_PER_HOUR};
+
+/// Default most hours of UBI a single claim credits, one week
+pub const DEFAULT_MAX_UBI_ACCRUAL_HOURS: u64 = 168;
+
+/// Default number of blocks between distributions of the fee pool
+pub const DEFAULT_FEE_DISTRIBUTION_INTERVAL_BLOCKS: u64 = 100;
+
+/// Settings a runtime is created with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuntimeConfig {
+    /// UBI tokens accrued per hour by verified accounts; 0 turns accrual off
+    pub ubi_rate_per_hour: u64,
+    /// Most hours of UBI a single claim credits; a longer gap forfeits the rest
+    pub max_ubi_accrual_hours: u64,
+    /// Transfer fee in basis points of the amount; 100 is 1%
+    pub fee_basis_points: u64,
+    /// Blocks between distributions of the fee pool; 0 leaves the pool to manual distributions
+    pub fee_distribution_interval_blocks: u64,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        RuntimeConfig {
+            ubi_rate_per_hour: UBI_TOKENS_PER_HOUR,
+            max_ubi_accrual_hours: DEFAULT_MAX_UBI_ACCRUAL_HOURS,
+            fee_basis_points: TRANSFER_FEE_PERCENT * BPS_DENOMINATOR / 100,
+            fee_distribution_interval_blocks: DEFAULT_FEE_DISTRIBUTION_INTERVAL_BLOCKS,
+        }
+    }
+}
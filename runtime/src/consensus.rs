@@ -0,0 +1,106 @@
+//! Consensus Rules
+//!
+//! A transaction in a block is executed by every node that imports the block,
+//! so the rules it must satisfy have to be the same everywhere and must not
+//! depend on how it reached the producer. These are the consensus rules:
+//! - sender and recipient are well-formed addresses (0x and 40 hex digits, in
+//!   any casing; blocks store them lowercase)
+//! - the amount is not zero, and the amount plus its fee fits in a u64
+//! - the sender is not the burn address or the UBI mint address, which never
+//!   sign transfers
+//! - the recipient is not the UBI mint address, which no account may hold
+//! - neither the sender nor the recipient is frozen; freezes are operator
+//!   actions, never transactions, so they cannot change within a block
+//!
+//! Rate limits, proof of work, idempotency keys and maintenance mode are local
+//! RPC policy instead. They decide what a node accepts from its own clients and
+//! differ between nodes, so they are never applied to imported blocks. Whether
+//! the sender can pay depends on the state the block runs against and is
+//! checked when the transfer executes.
+
+use crate::params::ChainParams;
+use crate::transfer_mode::{TransferAmounts, TransferMode};
+use crate::ubi_mint::UBI_MINT_ADDRESS;
+use std::fmt;
+
+/// A consensus rule broken by a transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConsensusViolation {
+    /// The address is not 0x followed by 40 hex digits
+    MalformedAddress(String),
+    /// The transfer moves no tokens
+    ZeroAmount,
+    /// The amount plus the fee does not fit in a u64
+    AmountOverflow(u64),
+    /// The sender is an address that never signs transfers
+    ReservedSender(String),
+    /// The recipient is an address no account may hold
+    ReservedRecipient(String),
+    /// The sender's account is frozen
+    FrozenSender(String),
+    /// The recipient's account is frozen
+    FrozenRecipient(String),
+}
+
+impl fmt::Display for ConsensusViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConsensusViolation::MalformedAddress(address) => write!(f, "Malformed address: {}", address),
+            ConsensusViolation::ZeroAmount => write!(f, "Amount must be greater than zero"),
+            ConsensusViolation::AmountOverflow(amount) => write!(f, "Amount {} plus fee exceeds the maximum token amount", amount),
+            ConsensusViolation::ReservedSender(address) => write!(f, "{} cannot send transfers", address),
+            ConsensusViolation::ReservedRecipient(address) => write!(f, "{} cannot receive transfers", address),
+            ConsensusViolation::FrozenSender(address) | ConsensusViolation::FrozenRecipient(address) => {
+                write!(f, "Account {} is frozen", address)
+            },
+        }
+    }
+}
+
+/// Checks a transfer against the consensus rules
+///
+/// # Arguments
+/// * `params` - Chain parameters the fee is computed under
+/// * `burn_address` - The node's burn address
+/// * `from` - The sender's address
+/// * `to` - The recipient's address
+/// * `amount` - The amount of the transfer
+/// * `mode` - Which figure of the transfer the caller fixed
+/// * `is_frozen` - Whether the account at an address is frozen
+///
+/// # Returns
+/// The first rule the transfer breaks, if any
+pub fn validate_transaction(
+    params: &ChainParams,
+    burn_address: &str,
+    from: &str,
+    to: &str,
+    amount: u64,
+    mode: TransferMode,
+    is_frozen: impl Fn(&str) -> bool,
+) -> Result<(), ConsensusViolation> {
+    for address in [from, to] {
+        if !crate::is_valid_eth_address(address) {
+            return Err(ConsensusViolation::MalformedAddress(address.to_string()));
+        }
+    }
+    if amount == 0 {
+        return Err(ConsensusViolation::ZeroAmount);
+    }
+    if TransferAmounts::compute(params, mode, amount).is_none() {
+        return Err(ConsensusViolation::AmountOverflow(amount));
+    }
+    if from.eq_ignore_ascii_case(burn_address) || from.eq_ignore_ascii_case(UBI_MINT_ADDRESS) {
+        return Err(ConsensusViolation::ReservedSender(from.to_string()));
+    }
+    if to.eq_ignore_ascii_case(UBI_MINT_ADDRESS) {
+        return Err(ConsensusViolation::ReservedRecipient(to.to_string()));
+    }
+    if is_frozen(from) {
+        return Err(ConsensusViolation::FrozenSender(from.to_string()));
+    }
+    if is_frozen(to) {
+        return Err(ConsensusViolation::FrozenRecipient(to.to_string()));
+    }
+    Ok(())
+}
@@ -0,0 +1,341 @@
+//! Dividend Accounting
+//!
+//! Transfer fees collect in a pool that is periodically distributed to token
+//! holders in proportion to their balances. Distribution is O(1): it only
+//! raises a global dividend-per-token value. Each account remembers the value
+//! it was last settled at, so what it is owed is its balance times the
+//! difference, computed when asked.
+//!
+//! The total supply, the fee pool, the dividend counters and the lifetime UBI
+//! and fee totals live in one struct behind one lock, so a distribution and a
+//! transfer's fee never interleave.
+//! Per-account entries are only written when an account is settled for at
+//! least `DIVIDEND_DUST_THRESHOLD` tokens, claims, or changes balance after a
+//! distribution; smaller entitlements stay implied by the account's settled
+//! point and keep accruing there. A balance change settles everything the old
+//! balance earned, so the new balance only earns from then on. Entries
+//! left behind by removed accounts or emptied claims are dropped by `compact`,
+//! which runs when a checkpoint is written.
+
+use std::collections::HashMap;
+
+/// Precision factor for dividend calculations to avoid rounding errors
+pub const DIVIDEND_PRECISION: u64 = 1_000_000_000;
+
+/// Smallest entitlement, in tokens, that settling an account records
+pub const DIVIDEND_DUST_THRESHOLD: u64 = 10;
+
+/// Supply counters and dividend state, kept under a single lock
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DividendState {
+    /// Total supply of tokens in circulation
+    pub total_supply: u64,
+    /// Collected fees waiting to be distributed
+    pub fee_pool: u64,
+    /// Global dividend per token value, scaled by `DIVIDEND_PRECISION`
+    pub dividend_per_token: u64,
+    /// Total fees ever distributed from the pool
+    pub fees_distributed: u64,
+    /// Total UBI ever minted to accounts
+    pub ubi_minted: u64,
+    /// Total transfer fees ever charged, the pooled and the burned part together
+    pub fees_collected: u64,
+    /// Dividend per token value each account was last settled at; absent is 0
+    points: HashMap<String, u64>,
+    /// Settled dividends not yet claimed; absent is 0
+    unclaimed: HashMap<String, u64>,
+}
+
+/// Counts of stored per-account entries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DividendEntries {
+    /// Accounts with a recorded settlement point
+    pub points: usize,
+    /// Accounts with settled, unclaimed dividends
+    pub unclaimed: usize,
+}
+
+impl DividendState {
+    /// Moves the fee pool into the dividend per token value
+    ///
+    /// # Returns
+    /// The amount distributed and the dividend per token increase, or None if
+    /// there was nothing to distribute, no supply to distribute it over, or
+    /// the totals would overflow; the pool is then left as it is
+    pub fn distribute(&mut self) -> Option<(u64, u64)> {
+        if self.total_supply == 0 || self.fee_pool == 0 {
+            return None;
+        }
+
+        let increase = u64::try_from(self.fee_pool as u128 * DIVIDEND_PRECISION as u128 / self.total_supply as u128).ok()?;
+        let dividend_per_token = self.dividend_per_token.checked_add(increase)?;
+        let fees_distributed = self.fees_distributed.checked_add(self.fee_pool)?;
+        self.dividend_per_token = dividend_per_token;
+        self.fees_distributed = fees_distributed;
+        let distributed = std::mem::take(&mut self.fee_pool);
+        Some((distributed, increase))
+    }
+
+    /// Gets the dividend per token value an account was last settled at
+    pub fn point(&self, address: &str) -> u64 {
+        self.points.get(address).copied().unwrap_or(0)
+    }
+
+    /// Gets the settled, unclaimed dividends of an account
+    pub fn unclaimed(&self, address: &str) -> u64 {
+        self.unclaimed.get(address).copied().unwrap_or(0)
+    }
+
+    /// Gets the dividends an account accrued since it was last settled
+    ///
+    /// The product is taken in u128, and a result beyond u64 saturates.
+    ///
+    /// # Arguments
+    /// * `address` - The account address
+    /// * `balance` - The account's balance
+    pub fn accrued(&self, address: &str, balance: u64) -> u64 {
+        let point_diff = self.dividend_per_token.saturating_sub(self.point(address));
+        u64::try_from(balance as u128 * point_diff as u128 / DIVIDEND_PRECISION as u128).unwrap_or(u64::MAX)
+    }
+
+    /// Gets everything an account is owed: settled and accrued dividends
+    pub fn owed(&self, address: &str, balance: u64) -> u64 {
+        self.unclaimed(address).saturating_add(self.accrued(address, balance))
+    }
+
+    /// Settles an account's accrued dividends into its unclaimed dividends
+    ///
+    /// Accruals below `DIVIDEND_DUST_THRESHOLD` are left where they are, so
+    /// no entry is written and the fraction keeps accruing. So are accruals
+    /// that would overflow the unclaimed dividends.
+    ///
+    /// # Returns
+    /// The amount settled
+    pub fn settle(&mut self, address: &str, balance: u64) -> u64 {
+        let accrued = self.accrued(address, balance);
+        if accrued < DIVIDEND_DUST_THRESHOLD {
+            return 0;
+        }
+        let unclaimed = match self.unclaimed(address).checked_add(accrued) {
+            Some(unclaimed) => unclaimed,
+            None => return 0,
+        };
+        self.points.insert(address.to_string(), self.dividend_per_token);
+        self.unclaimed.insert(address.to_string(), unclaimed);
+        accrued
+    }
+
+    /// Settles an account's accrued dividends before its balance changes
+    ///
+    /// Accrued dividends were earned on the old balance, so they are settled
+    /// in full, dust included, and the account restarts at the current
+    /// dividend per token value. The fraction of a token below the settled
+    /// amount is dropped.
+    ///
+    /// # Returns
+    /// The amount settled
+    pub fn settle_before_change(&mut self, address: &str, balance: u64) -> u64 {
+        if self.point(address) == self.dividend_per_token {
+            return 0;
+        }
+        let accrued = self.accrued(address, balance);
+        if accrued > 0 {
+            let unclaimed = self.unclaimed(address).saturating_add(accrued);
+            self.unclaimed.insert(address.to_string(), unclaimed);
+        }
+        self.points.insert(address.to_string(), self.dividend_per_token);
+        accrued
+    }
+
+    /// Takes everything an account is owed, whatever the amount
+    ///
+    /// # Returns
+    /// The amount to credit to the account
+    pub fn take(&mut self, address: &str, balance: u64) -> u64 {
+        let owed = self.owed(address, balance);
+        if owed > 0 {
+            self.unclaimed.remove(address);
+            self.points.insert(address.to_string(), self.dividend_per_token);
+        }
+        owed
+    }
+
+    /// Starts an account at the current dividend per token value, so it is owed nothing yet
+    pub fn start_account(&mut self, address: &str) {
+        self.points.insert(address.to_string(), self.dividend_per_token);
+    }
+
+    /// Forgets an account's dividend state
+    pub fn remove_account(&mut self, address: &str) {
+        self.points.remove(address);
+        self.unclaimed.remove(address);
+    }
+
+    /// Replaces the per-account state with values read from a checkpoint
+    ///
+    /// # Arguments
+    /// * `accounts` - Address, settled point and unclaimed dividends of each account
+    pub fn restore_accounts<'a>(&mut self, accounts: impl Iterator<Item = (&'a str, u64, u64)>) {
+        self.points.clear();
+        self.unclaimed.clear();
+        for (address, point, unclaimed) in accounts {
+            if point != 0 {
+                self.points.insert(address.to_string(), point);
+            }
+            if unclaimed != 0 {
+                self.unclaimed.insert(address.to_string(), unclaimed);
+            }
+        }
+    }
+
+    /// Drops entries that carry no information
+    ///
+    /// Entries of accounts that no longer exist and zero entries are removed,
+    /// and the maps give back memory they no longer need.
+    ///
+    /// # Arguments
+    /// * `exists` - Whether an account exists
+    ///
+    /// # Returns
+    /// The number of entries removed
+    pub fn compact(&mut self, exists: impl Fn(&str) -> bool) -> usize {
+        let before = self.points.len() + self.unclaimed.len();
+        self.points.retain(|address, point| *point != 0 && exists(address));
+        self.unclaimed.retain(|address, unclaimed| *unclaimed != 0 && exists(address));
+        self.points.shrink_to_fit();
+        self.unclaimed.shrink_to_fit();
+        before - (self.points.len() + self.unclaimed.len())
+    }
+
+    /// Gets how many per-account entries are stored
+    pub fn entries(&self) -> DividendEntries {
+        DividendEntries {
+            points: self.points.len(),
+            unclaimed: self.unclaimed.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_dust_accrues_without_entries_and_compacts() {
+        let mut state = DividendState { total_supply: 1_000_000, ..Default::default() };
+        let (whale, minnow) = ("0x0000000000000000000000000000000000000001", "0x0000000000000000000000000000000000000002");
+
+        // A tiny holder's share of 0.1 tokens per distribution stays below the threshold
+        for _ in 0..99 {
+            state.fee_pool = 1_000;
+            state.distribute().unwrap();
+            assert_eq!(state.settle(minnow, 100), 0);
+        }
+        assert_eq!(state.entries(), DividendEntries::default());
+        assert_eq!(state.owed(minnow, 100), 9);
+
+        // The fractions were kept, not rounded away at each settlement
+        state.fee_pool = 1_000;
+        state.distribute().unwrap();
+        assert_eq!(state.owed(minnow, 100), 10);
+        assert_eq!(state.settle(minnow, 100), 10);
+        assert_eq!(state.take(minnow, 100), 10);
+        assert_eq!(state.owed(minnow, 100), 0);
+
+        assert_eq!(state.settle(whale, 900_000), 90_000);
+        assert_eq!(state.entries(), DividendEntries { points: 2, unclaimed: 1 });
+
+        // Compaction keeps what is owed and drops accounts that are gone
+        assert_eq!(state.compact(|address| address == whale), 1);
+        assert_eq!(state.owed(whale, 900_000), 90_000);
+        assert_eq!(state.entries(), DividendEntries { points: 1, unclaimed: 1 });
+    }
+
+    #[test]
+    fn test_balance_changes_settle_what_the_old_balance_earned() {
+        let mut state = DividendState { total_supply: 1_000, fee_pool: 50, ..Default::default() };
+        let holder = "0x0000000000000000000000000000000000000001";
+        state.distribute().unwrap();
+
+        // Dust is settled too, so a bigger balance does not earn it again
+        assert_eq!(state.settle_before_change(holder, 100), 5);
+        assert_eq!(state.owed(holder, 10_000), 5);
+        assert_eq!(state.settle_before_change(holder, 10_000), 0);
+
+        state.fee_pool = 100;
+        state.distribute().unwrap();
+        assert_eq!(state.owed(holder, 10_000), 5 + 1_000);
+    }
+
+    #[test]
+    fn test_large_balances_neither_wrap_nor_panic() {
+        let holder = "0x0000000000000000000000000000000000000001";
+        let mut state = DividendState { total_supply: u64::MAX, fee_pool: u64::MAX / 2, ..Default::default() };
+
+        // balance * point difference exceeds u64 long before the result does
+        let (_, increase) = state.distribute().unwrap();
+        assert_eq!(increase, DIVIDEND_PRECISION / 2 - 1);
+        assert_eq!(state.owed(holder, u64::MAX), (u64::MAX as u128 * increase as u128 / DIVIDEND_PRECISION as u128) as u64);
+
+        // A distribution that would overflow the counters leaves the pool to wait
+        state.dividend_per_token = u64::MAX;
+        state.fee_pool = u64::MAX;
+        assert_eq!(state.distribute(), None);
+        assert_eq!(state.fee_pool, u64::MAX);
+        assert_eq!(state.owed(holder, u64::MAX), u64::MAX);
+
+        // Unclaimed dividends that would overflow stay accruing instead
+        assert_eq!(state.take(holder, u64::MAX), u64::MAX);
+        state.points.clear();
+        state.unclaimed.insert(holder.to_string(), u64::MAX);
+        assert_eq!(state.settle(holder, 100), 0);
+        assert_eq!(state.unclaimed(holder), u64::MAX);
+    }
+
+    /// Claim latency and stored entries with 100k accounts after 1k distributions
+    ///
+    /// Run with `cargo test --release -p ubi-chain-runtime dividend_benchmark -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn dividend_benchmark() {
+        const ACCOUNTS: u64 = 100_000;
+        const DISTRIBUTIONS: u64 = 1_000;
+        let addresses: Vec<String> = (0..ACCOUNTS).map(|i| format!("0x{:040x}", i)).collect();
+        let balance = |i: usize| 1 + i as u64 % 1_000;
+        let mut state = DividendState {
+            total_supply: (0..addresses.len()).map(balance).sum(),
+            ..Default::default()
+        };
+
+        // Every distribution, a slice of the accounts is settled, as transfers and queries do
+        for round in 0..DISTRIBUTIONS {
+            state.fee_pool = 10_000;
+            state.distribute().unwrap();
+            for i in (round as usize % 100..addresses.len()).step_by(100) {
+                state.settle(&addresses[i], balance(i));
+            }
+        }
+        let settled = state.entries();
+
+        let mut latencies: Vec<Duration> = addresses.iter().enumerate()
+            .map(|(i, address)| {
+                let start = Instant::now();
+                state.take(address, balance(i));
+                start.elapsed()
+            })
+            .collect();
+        latencies.sort();
+        let claimed = state.entries();
+        let removed = state.compact(|_| true);
+
+        // Each entry holds a 42-byte address string and a u64, plus the string and table overhead
+        let entry_bytes = 42 + std::mem::size_of::<String>() + std::mem::size_of::<u64>();
+        println!("{} accounts, {} distributions", ACCOUNTS, DISTRIBUTIONS);
+        println!("claim latency: p50 {:?}, p99 {:?}, max {:?}",
+                 latencies[latencies.len() / 2], latencies[latencies.len() * 99 / 100], latencies[latencies.len() - 1]);
+        println!("entries after settling: {:?} (~{} KiB)", settled, (settled.points + settled.unclaimed) * entry_bytes / 1024);
+        println!("entries after claiming: {:?}, {} removed by compaction", claimed, removed);
+        assert!(latencies[latencies.len() * 99 / 100] < Duration::from_millis(1));
+    }
+}
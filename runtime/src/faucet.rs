@@ -0,0 +1,252 @@
+//! Faucet Tiers
+//!
+//! The public faucet hands out grants from named tiers. Each tier fixes the
+//! amount of a grant and how often an address may receive it: once ever, or
+//! once per cooldown period. A tier can also require an operator-issued access
+//! code, or a challenge proving the requester controls the recipient key.
+//!
+//! The runtime keeps one record per tier and address, so limits hold no matter
+//! which RPC endpoint a request arrives on. Checking the challenge signature is
+//! left to the RPC layer; the runtime only enforces that it was checked.
+//!
+//! Grants are paid from an ordered list of source accounts. The first source
+//! whose transfer goes through pays; a source that does not exist or whose
+//! transfer is refused, for lack of balance or because of its limits, is
+//! passed over. The faucet history records which source paid each grant.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+
+/// Most grants kept in the faucet history; per-source totals cover every grant
+pub const MAX_FAUCET_HISTORY: usize = 1_000;
+
+/// A named kind of faucet grant
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FaucetTier {
+    /// Name requested by clients
+    pub name: String,
+    /// Tokens granted per request
+    pub amount: u64,
+    /// Seconds before an address may receive this tier again; None means once ever
+    #[serde(default)]
+    pub cooldown_secs: Option<u64>,
+    /// Whether each request must carry one of `codes`
+    #[serde(default)]
+    pub requires_code: bool,
+    /// Access codes issued by the operator
+    #[serde(default, skip_serializing)]
+    pub codes: Vec<String>,
+    /// Whether the requester must sign a faucet challenge with the recipient key
+    #[serde(default)]
+    pub requires_challenge: bool,
+}
+
+impl FaucetTier {
+    /// Gets the tiers offered when the operator configures none
+    ///
+    /// "starter" grants 10 tokens once per address. "developer" grants 100
+    /// tokens daily, but has no access codes until the operator configures some.
+    pub fn defaults() -> Vec<FaucetTier> {
+        vec![
+            FaucetTier {
+                name: "starter".to_string(),
+                amount: 10,
+                cooldown_secs: None,
+                requires_code: false,
+                codes: Vec::new(),
+                requires_challenge: false,
+            },
+            FaucetTier {
+                name: "developer".to_string(),
+                amount: 100,
+                cooldown_secs: Some(24 * 60 * 60),
+                requires_code: true,
+                codes: Vec::new(),
+                requires_challenge: false,
+            },
+        ]
+    }
+
+    /// Checks an access code against the tier's codes
+    pub fn accepts_code(&self, code: Option<&str>) -> bool {
+        !self.requires_code || code.is_some_and(|code| self.codes.iter().any(|issued| issued == code))
+    }
+}
+
+/// Grants of one tier to one address
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FaucetRecord {
+    /// Number of grants received
+    pub grants: u64,
+    /// When the last grant was made, in seconds since epoch
+    pub last_grant_secs: u64,
+}
+
+/// A grant reserved for a request, to be cancelled if the transfer fails
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FaucetClaim {
+    /// Tokens to send
+    pub amount: u64,
+    /// The address's record before the claim
+    pub previous: Option<FaucetRecord>,
+}
+
+/// Reason a tiered faucet request was refused
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FaucetError {
+    /// No tier has the requested name
+    UnknownTier(String),
+    /// The address already received every grant the tier allows for now
+    TierExhausted {
+        /// The tier
+        tier: String,
+        /// When the address may request the tier again; None if never
+        retry_after: Option<u64>,
+    },
+    /// The tier needs an access code and none or a wrong one was given
+    InvalidCode(String),
+    /// The tier needs a signed challenge and none was given
+    ChallengeRequired(String),
+    /// The challenge signature is missing, malformed, expired or from another key
+    BadSignature(String),
+}
+
+impl FaucetError {
+    /// Gets a stable identifier for the error, for clients to branch on
+    pub fn code(&self) -> &'static str {
+        match self {
+            FaucetError::UnknownTier(_) => "unknown_tier",
+            FaucetError::TierExhausted { .. } => "tier_exhausted",
+            FaucetError::InvalidCode(_) => "invalid_code",
+            FaucetError::ChallengeRequired(_) => "challenge_required",
+            FaucetError::BadSignature(_) => "bad_signature",
+        }
+    }
+}
+
+impl fmt::Display for FaucetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FaucetError::UnknownTier(tier) => write!(f, "Unknown faucet tier: {}", tier),
+            FaucetError::TierExhausted { tier, retry_after: Some(retry_after) } => {
+                write!(f, "Faucet tier {} exhausted for this address until {}", tier, retry_after)
+            },
+            FaucetError::TierExhausted { tier, retry_after: None } => {
+                write!(f, "Faucet tier {} can only be received once per address", tier)
+            },
+            FaucetError::InvalidCode(tier) => write!(f, "Faucet tier {} requires a valid access code", tier),
+            FaucetError::ChallengeRequired(tier) => write!(f, "Faucet tier {} requires a signed challenge", tier),
+            FaucetError::BadSignature(reason) => write!(f, "Bad challenge signature: {}", reason),
+        }
+    }
+}
+
+/// Checks whether an address may receive a tier now
+///
+/// # Arguments
+/// * `tier` - The tier requested
+/// * `record` - The address's previous grants of the tier, if any
+/// * `now` - Current time in seconds since epoch
+pub fn check_grant(tier: &FaucetTier, record: Option<&FaucetRecord>, now: u64) -> Result<(), FaucetError> {
+    let record = match record {
+        Some(record) if record.grants > 0 => record,
+        _ => return Ok(()),
+    };
+
+    match tier.cooldown_secs {
+        None => Err(FaucetError::TierExhausted { tier: tier.name.clone(), retry_after: None }),
+        Some(cooldown) => {
+            let next = record.last_grant_secs.saturating_add(cooldown);
+            if now < next {
+                Err(FaucetError::TierExhausted { tier: tier.name.clone(), retry_after: Some(next) })
+            } else {
+                Ok(())
+            }
+        },
+    }
+}
+
+/// A grant the faucet paid
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FaucetGrant {
+    /// Account that received the grant
+    pub recipient: String,
+    /// Source account that paid it
+    pub source: String,
+    /// Tokens received
+    pub amount: u64,
+    /// Tier the grant was claimed from, if any
+    pub tier: Option<String>,
+    /// When it was paid, in seconds since epoch
+    pub timestamp: u64,
+    /// Sources passed over before the paying one, with why
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<String>,
+}
+
+/// Grants paid by one source since the node started
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceTotals {
+    /// Grants paid
+    pub grants: u64,
+    /// Tokens granted, fees excluded
+    pub tokens_granted: u64,
+}
+
+/// Recent faucet grants, with totals per source
+#[derive(Debug, Clone, Default)]
+pub struct FaucetHistory {
+    /// Latest grants, oldest first
+    grants: VecDeque<FaucetGrant>,
+    /// Totals by source address
+    totals: HashMap<String, SourceTotals>,
+}
+
+impl FaucetHistory {
+    /// Records a paid grant, forgetting the oldest one past `MAX_FAUCET_HISTORY`
+    pub fn record(&mut self, grant: FaucetGrant) {
+        let totals = self.totals.entry(grant.source.clone()).or_default();
+        totals.grants += 1;
+        totals.tokens_granted += grant.amount;
+        self.grants.push_back(grant);
+        if self.grants.len() > MAX_FAUCET_HISTORY {
+            self.grants.pop_front();
+        }
+    }
+
+    /// Gets the latest grants, newest first
+    pub fn recent(&self, limit: usize) -> Vec<FaucetGrant> {
+        self.grants.iter().rev().take(limit).cloned().collect()
+    }
+
+    /// Gets the totals of a source
+    pub fn totals(&self, source: &str) -> SourceTotals {
+        self.totals.get(source).copied().unwrap_or_default()
+    }
+}
+
+/// State of one faucet source
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FaucetSourceStats {
+    /// Source address, lowercase
+    pub address: String,
+    /// Whether the source has an account; sources without one are passed over
+    pub exists: bool,
+    /// Tokens the source has left
+    pub balance: u64,
+    /// Grants and tokens the source paid
+    #[serde(flatten)]
+    pub totals: SourceTotals,
+}
+
+/// Faucet sources in the order they are tried, and the latest grants
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FaucetStats {
+    /// Each source, first tried first
+    pub sources: Vec<FaucetSourceStats>,
+    /// Tokens left across all sources
+    pub total_balance: u64,
+    /// Latest grants, newest first
+    pub recent_grants: Vec<FaucetGrant>,
+}
@@ -0,0 +1,126 @@
+//! Fee Ledger
+//!
+//! The fee pool is a single balance, which cannot show what went into it or
+//! what came out. Every change to the pool is therefore also appended to the
+//! fee ledger, in the same critical section as the pool mutation:
+//! - `Collected`: the pool's share of a transfer fee
+//! - `Distributed`: the pool paid out as dividends
+//! - `Burned`: the part of a transfer fee that was burned instead; it never
+//!   enters the pool and is recorded so the whole fee can be reconciled
+//!
+//! The ledger is append-only and stored in checkpoints. Its net, collected
+//! minus distributed, must equal the pool balance; the supply audit reports
+//! any difference. The `Distributed` entries double as the history of
+//! distributions: each records the fee pool it distributed, the block it was
+//! distributed at and the dividend per token value it left, so the history
+//! is read back as recorded rather than worked out from the current state.
+
+use serde::{Deserialize, Serialize};
+
+/// What a ledger entry records
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FeeLedgerKind {
+    /// The pool's share of a transfer fee was collected
+    Collected {
+        /// Hash of the transaction that paid the fee, if the transfer had one
+        tx_hash: Option<String>,
+    },
+    /// The pool was distributed as dividends
+    Distributed {
+        /// Increase of the dividend per token value, scaled by the dividend precision
+        dividend_delta: u64,
+        /// Dividend per token value after the distribution, scaled by the
+        /// dividend precision; 0 in entries written before it was recorded
+        /// until `fill_dividend_per_token` fills it in
+        #[serde(default)]
+        dividend_per_token: u64,
+    },
+    /// The burned part of a transfer fee
+    Burned {
+        /// Hash of the transaction that paid the fee, if the transfer had one
+        tx_hash: Option<String>,
+    },
+}
+
+/// One change to the fee pool
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeLedgerEntry {
+    /// Position in the ledger, oldest being 0
+    pub sequence: u64,
+    /// When it happened, in seconds since epoch
+    pub timestamp: u64,
+    /// Height of the latest block when it happened
+    pub block_number: u64,
+    /// What happened
+    #[serde(flatten)]
+    pub kind: FeeLedgerKind,
+    /// Tokens collected, distributed or burned
+    pub amount: u64,
+}
+
+impl FeeLedgerEntry {
+    /// Gets the entry's effect on the pool balance
+    pub fn pool_delta(&self) -> i128 {
+        match self.kind {
+            FeeLedgerKind::Collected { .. } => self.amount as i128,
+            FeeLedgerKind::Distributed { .. } => -(self.amount as i128),
+            FeeLedgerKind::Burned { .. } => 0,
+        }
+    }
+}
+
+/// One distribution of the fee pool to token holders
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DistributionEvent {
+    /// When it happened, in seconds since epoch
+    pub timestamp: u64,
+    /// Height of the latest block when it happened
+    pub block_number: u64,
+    /// Tokens distributed: the whole fee pool at the time
+    pub amount: u64,
+    /// Dividend per token value after the distribution, scaled by the dividend precision
+    pub dividend_per_token: u64,
+}
+
+/// Computes the pool balance the entries account for: collected minus distributed
+pub fn ledger_net<'a>(entries: impl IntoIterator<Item = &'a FeeLedgerEntry>) -> i128 {
+    entries.into_iter().map(FeeLedgerEntry::pool_delta).sum()
+}
+
+/// Lists the distributions recorded in the ledger, oldest first
+pub fn distribution_history(entries: &[FeeLedgerEntry]) -> Vec<DistributionEvent> {
+    entries.iter()
+        .filter_map(|entry| match entry.kind {
+            FeeLedgerKind::Distributed { dividend_per_token, .. } => Some(DistributionEvent {
+                timestamp: entry.timestamp,
+                block_number: entry.block_number,
+                amount: entry.amount,
+                dividend_per_token,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Fills in the dividend per token value of distributions recorded before
+/// entries carried it
+///
+/// Each missing value is worked back from the next recorded one, or from the
+/// current value for the newest entries. This only runs once, when a ledger
+/// written by an older node is loaded; from then on the values are stored.
+///
+/// # Arguments
+/// * `entries` - The whole ledger
+/// * `dividend_per_token` - The dividend per token value the ledger ends at
+pub fn fill_dividend_per_token(entries: &mut [FeeLedgerEntry], dividend_per_token: u64) {
+    let mut after = dividend_per_token;
+    for entry in entries.iter_mut().rev() {
+        if let FeeLedgerKind::Distributed { dividend_delta, dividend_per_token } = &mut entry.kind {
+            if *dividend_per_token == 0 {
+                *dividend_per_token = after;
+            }
+            after = dividend_per_token.saturating_sub(*dividend_delta);
+        }
+    }
+}
@@ -0,0 +1,203 @@
+//! Genesis Configuration
+//!
+//! A brand-new network has no balances and no verified humans, so nobody can
+//! pay fees or earn UBI. The genesis file seeds both:
+//! - `faucet` funds a faucet account, either a fixed address or the node's
+//!   own address, and caps what it pays out per request and per day
+//! - `ubi` sets the UBI rate and how many hours of it one claim may pay, and
+//!   can open a bootstrap window during which new accounts are verified
+//!   automatically
+//! - `fees` sets the transfer fee and how often the fee pool is distributed
+//! - `reserved` reserves address ranges for later system use, so no account
+//!   can be created in them
+//! - `fee_exempt` lists senders whose transfers pay no fee, on top of the
+//!   faucet, which is always exempt
+//!
+//! The window opens at the required `timestamp`, so every node, and a node
+//! after a restart, agrees on when it closes. Once it closes, new accounts
+//! need verification again. Accounts verified during the window keep their
+//! verification.
+//!
+//! The UBI and fee settings decide every balance, so they are chain settings
+//! rather than node settings: every node of a network starts from the same genesis
+//! file, and a node without one uses the defaults.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+use crate::config::RuntimeConfig;
+use crate::reserved::ReservedRange;
+
+/// Seconds in a day, the unit of the bootstrap window
+pub const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Value of `faucet.address` that stands for the node's own address
+pub const NODE_FAUCET_ADDRESS: &str = "node";
+
+/// Contents of a genesis file
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GenesisConfig {
+    /// Start of the network in seconds since the epoch. Required, so that the
+    /// bootstrap window is the same on every node and across restarts
+    pub timestamp: u64,
+    /// Faucet funded at genesis
+    #[serde(default)]
+    pub faucet: Option<GenesisFaucet>,
+    /// UBI rate and bootstrap window
+    #[serde(default)]
+    pub ubi: Option<GenesisUbi>,
+    /// Transfer fee settings
+    #[serde(default)]
+    pub fees: Option<GenesisFees>,
+    /// Address ranges reserved for system use, on top of the built-in system addresses
+    #[serde(default)]
+    pub reserved: Vec<ReservedRange>,
+    /// Senders charged no transfer fee, on top of the faucet
+    #[serde(default)]
+    pub fee_exempt: Vec<String>,
+}
+
+/// Faucet account funded at genesis
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GenesisFaucet {
+    /// Faucet account address, or "node" for the node's own address
+    pub address: String,
+    /// Tokens minted to the faucet at genesis
+    pub initial_balance: u64,
+    /// Maximum tokens paid out by one request
+    #[serde(default)]
+    pub per_request_limit: Option<u64>,
+    /// Maximum tokens, including fees, paid out over a rolling day
+    #[serde(default)]
+    pub daily_limit: Option<u64>,
+}
+
+/// UBI settings at genesis
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GenesisUbi {
+    /// UBI tokens per hour; the default rate if omitted
+    #[serde(default)]
+    pub tokens_per_hour: Option<u64>,
+    /// Most hours of UBI a single claim credits; the default cap if omitted
+    #[serde(default)]
+    pub max_accrual_hours: Option<u64>,
+    /// Whether accounts created during the bootstrap window are verified automatically
+    #[serde(default)]
+    pub auto_verify: bool,
+    /// Length of the bootstrap window in days
+    #[serde(default)]
+    pub bootstrap_window_days: u64,
+}
+
+/// Transfer fee settings at genesis
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GenesisFees {
+    /// Transfer fee in basis points of the amount; the default fee if omitted
+    #[serde(default)]
+    pub fee_bps: Option<u64>,
+    /// Blocks between distributions of the fee pool, 0 for none; the default interval if omitted
+    #[serde(default)]
+    pub distribution_interval_blocks: Option<u64>,
+}
+
+/// Reason a genesis file could not be used
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GenesisError {
+    /// The file could not be read
+    Read(String),
+    /// The file is not a valid genesis configuration
+    Parse(String),
+    /// The faucet address is neither "node" nor a valid account address
+    InvalidFaucetAddress(String),
+    /// A reserved range has an invalid end or ends before it starts
+    InvalidReservedRange(String),
+    /// A fee-exempt address is not a valid account address
+    InvalidFeeExemptAddress(String),
+}
+
+impl fmt::Display for GenesisError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GenesisError::Read(e) => write!(f, "Failed to read genesis file: {}", e),
+            GenesisError::Parse(e) => write!(f, "Invalid genesis file: {}", e),
+            GenesisError::InvalidFaucetAddress(address) => write!(f, "Invalid genesis faucet address: {}", address),
+            GenesisError::InvalidReservedRange(range) => write!(f, "Invalid reserved address range: {}", range),
+            GenesisError::InvalidFeeExemptAddress(address) => write!(f, "Invalid fee-exempt address: {}", address),
+        }
+    }
+}
+
+impl std::error::Error for GenesisError {}
+
+impl GenesisConfig {
+    /// Loads a genesis configuration from a JSON file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, GenesisError> {
+        let contents = std::fs::read_to_string(path.as_ref())
+            .map_err(|e| GenesisError::Read(format!("{}: {}", path.as_ref().display(), e)))?;
+        serde_json::from_str(&contents).map_err(|e| GenesisError::Parse(e.to_string()))
+    }
+
+    /// Gets the settings a runtime of the network is created with, the
+    /// defaults for whatever the file leaves out
+    pub fn runtime_config(&self) -> RuntimeConfig {
+        let defaults = RuntimeConfig::default();
+        let ubi = self.ubi.as_ref();
+        let fees = self.fees.as_ref();
+        RuntimeConfig {
+            ubi_rate_per_hour: ubi.and_then(|ubi| ubi.tokens_per_hour).unwrap_or(defaults.ubi_rate_per_hour),
+            max_ubi_accrual_hours: ubi.and_then(|ubi| ubi.max_accrual_hours).unwrap_or(defaults.max_ubi_accrual_hours),
+            fee_basis_points: fees.and_then(|fees| fees.fee_bps).unwrap_or(defaults.fee_basis_points),
+            fee_distribution_interval_blocks: fees
+                .and_then(|fees| fees.distribution_interval_blocks)
+                .unwrap_or(defaults.fee_distribution_interval_blocks),
+        }
+    }
+}
+
+impl GenesisFaucet {
+    /// Resolves the faucet address, substituting the node's address for "node"
+    pub fn resolve_address(&self, node_address: &str) -> String {
+        if self.address.eq_ignore_ascii_case(NODE_FAUCET_ADDRESS) {
+            node_address.to_lowercase()
+        } else {
+            self.address.to_lowercase()
+        }
+    }
+}
+
+/// Bootstrap window opened by the genesis UBI settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BootstrapWindow {
+    /// Whether accounts created during the window are verified automatically
+    pub auto_verify: bool,
+    /// When the window closes, in seconds since the epoch
+    pub ends_at: u64,
+}
+
+/// Bootstrap state reported to clients
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BootstrapStatus {
+    /// Whether the window is still open, so verification is relaxed
+    pub active: bool,
+    /// Whether new accounts are verified automatically while the window is open
+    pub auto_verify: bool,
+    /// When the window closes, in seconds since the epoch
+    pub ends_at: u64,
+}
+
+impl BootstrapWindow {
+    /// Gets the window's state at the given time
+    pub fn status_at(&self, now: u64) -> BootstrapStatus {
+        BootstrapStatus {
+            active: now < self.ends_at,
+            auto_verify: self.auto_verify,
+            ends_at: self.ends_at,
+        }
+    }
+
+    /// Checks whether accounts created at the given time are verified automatically
+    pub fn auto_verifies_at(&self, now: u64) -> bool {
+        self.auto_verify && now < self.ends_at
+    }
+}
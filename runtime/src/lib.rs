@@ -8,7 +8,7 @@
 //! - Transaction execution
 //! - State transitions
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
@@ -16,30 +16,156 @@ use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use std::fmt;
 
 // Add these imports for checkpoint mechanism
-use std::fs::{self, File};
-use std::io::{self, Read, Write};
+use std::fs;
+use std::io;
 use std::path::Path;
 
 // Add these imports for Merkle tree implementation
 use sha2::{Sha256, Digest};
-use std::collections::VecDeque;
 
 // Add serde imports
 use serde::{Serialize, Deserialize};
 
+// Add checkpoint file format module
+pub mod checkpoint;
+use checkpoint::{CheckpointAccount, CheckpointAllowance, CheckpointData};
+
+// Add account limits module
+pub mod limits;
+use limits::{AccountLimitState, AccountLimits};
+
+// Add chain parameters module
+pub mod params;
+use params::{ChainParam, ChainParams, ParamChangeError, ParamSchedule, PendingParamChange};
+
+// Add transaction status module
+pub mod tx_status;
+use tx_status::TransactionStatus;
+
+// Add transaction lifecycle trace module
+pub mod tx_trace;
+use tx_trace::TransactionTraceStore;
+
+// Add account export and import module
+pub mod account_export;
+use account_export::{AccountExport, AccountTransferError, SupplyEvent, SupplyEventKind};
+
+// Add sync status module
+pub mod sync_status;
+use sync_status::SyncStatus;
+
+// Add fee ledger module
+pub mod fee_ledger;
+use fee_ledger::{FeeLedgerEntry, FeeLedgerKind};
+
+// Add node liveness module
+pub mod liveness;
+use liveness::LivenessStatus;
+
+// Add faucet tier module
+pub mod faucet;
+use faucet::{FaucetClaim, FaucetError, FaucetGrant, FaucetHistory, FaucetRecord, FaucetSourceStats, FaucetStats, FaucetTier};
+
+// Add genesis configuration module
+pub mod genesis;
+use genesis::{BootstrapStatus, BootstrapWindow, GenesisConfig, GenesisError, SECS_PER_DAY};
+
+// Add UBI mint record module
+pub mod ubi_mint;
+use ubi_mint::{ClaimResult, UbiMint, UbiMintLog};
+
+// Add lifetime UBI statistics module
+pub mod ubi_stats;
+use ubi_stats::{NetworkStats, UbiStats};
+
+// Add state snapshot module
+pub mod state_snapshot;
+use state_snapshot::StateSnapshot;
+
+// Add state diff module
+pub mod state_diff;
+use state_diff::StateDiff;
+
+// Add payment stream module
+pub mod payment_stream;
+use payment_stream::{PaymentStream, PaymentStreams, StreamError, StreamId};
+
+// Add batch transfer module
+pub mod batch_transfer;
+use batch_transfer::{BatchReceipt, MAX_BATCH_LEGS};
+
+// Add balance lock module
+pub mod balance_lock;
+use balance_lock::{BalanceLock, MAX_LOCKS_PER_ACCOUNT};
+
+// Add maintenance mode module
+pub mod maintenance;
+use maintenance::MaintenanceStatus;
+
+// Add dividend accounting module
+pub mod dividends;
+use dividends::DividendState;
+
+// Add transfer mode module
+pub mod transfer_mode;
+use transfer_mode::{TransferAmounts, TransferMode};
+
+// Add block extras module
+pub mod block_extras;
+use block_extras::{BlockExtras, VerificationChange, VerificationEvent, VerificationEventLog};
+
+// Add consensus rules module
+pub mod consensus;
+use consensus::ConsensusViolation;
+
+// Add account statement module
+pub mod statement;
+use statement::{BalanceChangeKind, BalanceHistory, Statement};
+
+// Add reserved address module
+pub mod reserved;
+use reserved::{ReservedAddresses, ReservedRange};
+
+// Add clock module
+pub mod clock;
+use clock::Clock;
+
+// Add state proof module
+pub mod state_proof;
+use state_proof::AccountProof;
+
+// Add producer channel metrics module
+pub mod channel_metrics;
+use channel_metrics::ChannelMetrics;
+
+// Add runtime configuration module
+pub mod config;
+use config::RuntimeConfig;
+
+// Add state storage module
+pub mod storage;
+use storage::{StateChanges, StateStore, StoredAccount, StoredState, StoredTotals};
+
+// Add test runtime builder module
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
+
 // Add log crate
 extern crate log;
 
-// Constants for UBI distribution
-const UBI_TOKENS_PER_HOUR: u64 = 1;
+// Constants for UBI distribution (default of the ubi_tokens_per_hour chain parameter)
+pub const UBI_TOKENS_PER_HOUR: u64 = 1;
 
-// Constants for the dividend system
-const DIVIDEND_PRECISION: u64 = 1_000_000_000; // 10^9 precision for dividend calculations
+// Constants for transfer fees (default of the fee_bps chain parameter)
+pub const TRANSFER_FEE_PERCENT: u64 = 1; // Fee charged on top of each transfer, rounded to the nearest token
 
 // Constants for the testnet faucet
 const FAUCET_ADDRESS: &str = "0xFAUCET00000000000000000000000000000000000";
 const FAUCET_MIN_BALANCE: u64 = 1_000_000; // Ensure faucet always has at least 1 million tokens
 
+// Transfers to the burn address are burned instead of credited; no account can hold it
+pub const DEFAULT_BURN_ADDRESS: &str = "0x000000000000000000000000000000000000dEaD";
+
 // Add Transaction type definition
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Transaction {
@@ -60,6 +186,14 @@ pub struct Transaction {
     
     /// Timestamp when the transaction was created
     pub timestamp: u64,
+    
+    /// Whether the amount is what the sender sent or what the recipient must receive
+    #[serde(default, skip_serializing_if = "TransferMode::is_send")]
+    pub mode: TransferMode,
+    
+    /// Position in the sender's transactions; None takes the sender's next nonce when executed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<u64>,
 }
 
 // Add BlockProducer trait definition
@@ -69,12 +203,56 @@ pub trait BlockProducer: Send + Sync {
     
     /// Gets the current block number
     fn current_block(&self) -> u64;
+    
+    /// Gets the estimated offset of the network clock from the local clock in seconds
+    fn clock_offset(&self) -> i64 {
+        0
+    }
+    
+    /// Gets what happened to a submitted transaction, if the producer still remembers it
+    fn transaction_status(&self, _hash: &str) -> Option<TransactionStatus> {
+        None
+    }
+    
+    /// Gets a block in the producer's native format, with its reward and fee data, serialized
+    fn block_record(&self, _number: u64) -> Option<serde_json::Value> {
+        None
+    }
+    
+    /// Gets the UBI-specific figures recorded for a block when it was sealed or imported
+    fn block_extras(&self, _number: u64) -> Option<BlockExtras> {
+        None
+    }
+    
+    /// Gets the overall sync state and the progress of each peer
+    fn sync_status(&self) -> SyncStatus {
+        SyncStatus {
+            starting_block: self.current_block(),
+            current_block: self.current_block(),
+            highest_block: self.current_block(),
+            ..SyncStatus::default()
+        }
+    }
+    
+    /// Gets whether the producer is making progress; producers without a watchdog never report a stall
+    fn liveness(&self) -> LivenessStatus {
+        LivenessStatus {
+            last_block: self.current_block(),
+            ..LivenessStatus::default()
+        }
+    }
+    
+    /// Gets what happened to the producer's transaction announcements and block sends
+    fn channel_metrics(&self) -> ChannelMetrics {
+        ChannelMetrics::default()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::time::Duration;
+    use clock::MockClock;
+    use test_utils::{TestRuntimeBuilder, TEST_START_TIME};
 
     #[test]
     fn it_works() {
@@ -83,17 +261,16 @@ mod tests {
     
     #[test]
     fn test_create_account() {
-        let runtime = Runtime::new();
+        let builder = TestRuntimeBuilder::new().verify_new_accounts(true);
+        let runtime = builder.clone().build();
         
         // Test valid address
         let valid_address = "0x1234567890abcdef1234567890abcdef12345678";
         let result = runtime.create_account(valid_address);
         assert!(result.is_ok());
         
-        let account = result.unwrap();
-        assert_eq!(account.address, valid_address);
-        assert_eq!(account.balance, 0);
-        assert!(account.verified); // Now accounts are auto-verified
+        assert_eq!(runtime.get_balance(valid_address), builder.new_accounts().balance);
+        assert!(runtime.is_account_verified(valid_address));
         
         // Test duplicate address
         let duplicate_result = runtime.create_account(valid_address);
@@ -115,64 +292,595 @@ mod tests {
     
     #[test]
     fn test_get_balance() {
-        let runtime = Runtime::new();
+        let builder = TestRuntimeBuilder::new().initial_balance(10);
+        let runtime = builder.clone().build();
         let address = "0x1234567890abcdef1234567890abcdef12345678";
         
-        // Create account
-        let _ = runtime.create_account(address);
+        // Unknown accounts hold nothing
+        assert_eq!(runtime.get_balance(address), 0);
         
-        // Check initial balance
-        let balance = runtime.get_balance(address);
-        assert_eq!(balance, 0);
+        // New accounts start at the initial balance, which is minted
+        runtime.create_account(address).unwrap();
+        assert_eq!(runtime.get_balance(address), builder.new_accounts().balance);
+        assert_eq!(runtime.get_total_supply(), builder.new_accounts().balance);
     }
     
     #[test]
     fn test_is_account_verified() {
-        let runtime = Runtime::new();
         let address = "0x1234567890abcdef1234567890abcdef12345678";
         
-        // Create account
-        let _ = runtime.create_account(address);
+        // Nodes create accounts unverified
+        let runtime = TestRuntimeBuilder::new().build();
+        runtime.create_account(address).unwrap();
+        assert!(!runtime.is_account_verified(address));
+        assert!(runtime.verify_account(address));
+        assert!(runtime.is_account_verified(address));
         
-        // Check verification status
-        let verified = runtime.is_account_verified(address);
-        assert!(verified);
+        let runtime = TestRuntimeBuilder::new().verify_new_accounts(true).build();
+        runtime.create_account(address).unwrap();
+        assert!(runtime.is_account_verified(address));
     }
     
     #[test]
     fn test_ubi_distribution() {
+        let clock = MockClock::at(TEST_START_TIME);
+        let builder = TestRuntimeBuilder::new().verify_new_accounts(true).clock(&clock);
+        let rate = builder.params().ubi_tokens_per_hour;
+        let runtime = builder.build();
+        let address = "0x1234567890abcdef1234567890abcdef12345678";
+        runtime.create_account(address).unwrap();
+        assert_eq!(runtime.get_balance(address), 0);
+        
+        // Two full hours later, two hours of UBI are credited
+        clock.advance(2 * 3600 + 59);
+        let new_balance = runtime.update_ubi_balance(address).unwrap();
+        assert_eq!(new_balance, 2 * rate);
+        assert_eq!(runtime.get_balance(address), 2 * rate);
+        
+        // The part hour carries over to the next settlement
+        clock.advance(3600 - 59);
+        assert_eq!(runtime.update_ubi_balance(address).unwrap(), rate);
+    }
+    
+    #[test]
+    fn test_ubi_accrues_only_after_verification() {
         let runtime = Runtime::new();
         let address = "0x1234567890abcdef1234567890abcdef12345678";
-        
-        // Create account
-        let _ = runtime.create_account(address);
-        
-        // Manually set the last_ubi_claim to a specific time in the past
-        {
-            let mut accounts = runtime.accounts.lock().unwrap();
-            if let Some(account) = accounts.get_mut(address) {
-                // Set last claim to exactly 2 hours ago
-                let two_hours_ago = SystemTime::now() - Duration::from_secs(7200);
-                account.last_ubi_claim = two_hours_ago;
-                
-                // Verify initial balance is 0
-                assert_eq!(account.balance, 0);
-            }
+        let rate = runtime.chain_params().ubi_tokens_per_hour;
+        let created = current_time_secs();
+        runtime.create_account(address).unwrap();
+
+        // Nothing accrues while unverified
+        assert_eq!(runtime.update_ubi_balance_at(address, created + 5 * 3600).unwrap(), 0);
+
+        // Verified five hours after creation, claimed two and a half hours later
+        assert!(runtime.verify_account_at(address, created + 5 * 3600));
+        assert_eq!(runtime.update_ubi_balance_at(address, created + 7 * 3600 + 1800).unwrap(), 2 * rate);
+        assert_eq!(runtime.get_balance(address), 2 * rate);
+    }
+
+    #[test]
+    fn test_ubi_accrues_at_the_configured_rate() {
+        let address = "0x1234567890abcdef1234567890abcdef12345678";
+        assert_eq!(Runtime::new().chain_params().ubi_tokens_per_hour, UBI_TOKENS_PER_HOUR);
+
+        for (rate, expected) in [(0, 0), (10, 30)] {
+            let runtime = Runtime::with_config(RuntimeConfig { ubi_rate_per_hour: rate, ..Default::default() });
+            let created = current_time_secs();
+            runtime.create_account(address).unwrap();
+            runtime.verify_account_at(address, created);
+
+            // Three hours later, three hours at the configured rate are credited
+            assert_eq!(runtime.update_ubi_balance_at(address, created + 3 * 3600).unwrap(), expected);
+            assert_eq!(runtime.get_balance(address), expected);
+            assert_eq!(runtime.get_total_supply(), expected);
+        }
+    }
+
+    #[test]
+    fn test_ubi_back_pay_is_capped() {
+        let address = "0x1234567890abcdef1234567890abcdef12345678";
+        let runtime = Runtime::with_config(RuntimeConfig { ubi_rate_per_hour: 2, max_ubi_accrual_hours: 168, ..Default::default() });
+        assert_eq!(runtime.max_ubi_accrual_hours(), 168);
+        let created = current_time_secs();
+        runtime.create_account(address).unwrap();
+        runtime.verify_account_at(address, created);
+
+        // A 1000-hour gap credits only the cap, and the forfeited hours do not carry over
+        let claimed = created + 1000 * 3600 + 1800;
+        assert_eq!(runtime.update_ubi_balance_at(address, claimed).unwrap(), 168 * 2);
+        assert_eq!(runtime.get_balance(address), 168 * 2);
+        assert_eq!(runtime.get_total_supply(), 168 * 2);
+        assert_eq!(runtime.update_ubi_balance_at(address, claimed + 1799).unwrap(), 0);
+        assert_eq!(runtime.update_ubi_balance_at(address, claimed + 3600).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_reads_leave_ubi_pending_until_claimed() {
+        let clock = MockClock::at(TEST_START_TIME);
+        let builder = TestRuntimeBuilder::new().verify_new_accounts(true).clock(&clock);
+        let rate = builder.params().ubi_tokens_per_hour;
+        let runtime = builder.build();
+        let address = "0x1234567890abcdef1234567890abcdef12345678";
+        runtime.create_account(address).unwrap();
+        let last_claim = |runtime: &Runtime| runtime.accounts.read().unwrap()[address].last_ubi_claim;
+        let created = last_claim(&runtime);
+        let root = runtime.state_root();
+
+        // Reading the balance reports the accrual without settling it
+        clock.advance(3 * 3600 + 60);
+        for _ in 0..3 {
+            assert_eq!(runtime.get_balance(address), 3 * rate);
+            assert_eq!(runtime.pending_ubi(address), 3 * rate);
+        }
+        assert_eq!(runtime.get_total_supply(), 0);
+        assert_eq!(last_claim(&runtime), created);
+        assert_eq!(runtime.state_root(), root);
+
+        // A claim credits it, and the part token keeps accruing
+        assert_eq!(runtime.claim_ubi(address).unwrap(), ClaimResult { claimed: 3 * rate, new_balance: 3 * rate });
+        assert_eq!(runtime.pending_ubi(address), 0);
+        assert_eq!(runtime.claim_ubi(address).unwrap(), ClaimResult { claimed: 0, new_balance: 3 * rate });
+        assert_eq!(last_claim(&runtime), created + Duration::from_secs(3 * 3600));
+
+        match runtime.claim_ubi("0xabcdef1234567890abcdef1234567890abcdef12") {
+            Err(AccountError::AccountNotFound(_)) => {}
+            other => panic!("Expected AccountNotFound, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_ubi_streams_into_the_balance() {
+        let clock = MockClock::at(TEST_START_TIME);
+        let builder = TestRuntimeBuilder::new().verify_new_accounts(true).ubi_tokens_per_hour(10).clock(&clock);
+        let params = builder.params();
+        let runtime = builder.build();
+        let alice = "0x1111111111111111111111111111111111111111";
+        let bob = "0x2222222222222222222222222222222222222222";
+        runtime.create_account(alice).unwrap();
+        runtime.create_account(bob).unwrap();
+
+        // Half an hour at ten tokens an hour streams five, and the half token
+        // of the next quarter hour shows once it is whole
+        clock.advance(1800);
+        assert_eq!(runtime.get_balance(alice), 5);
+        clock.advance(900);
+        assert_eq!(runtime.get_balance(alice), 7);
+        assert_eq!(runtime.get_total_supply(), 0);
+
+        // A transfer settles the sender's stream first, so streamed tokens can be spent
+        let fee = params.transfer_fee(6);
+        runtime.transfer_with_fee(alice, bob, 6).unwrap();
+        assert_eq!(runtime.ubi_mints_for(alice).iter().map(|mint| mint.amount).sum::<u64>(), 7);
+        assert_eq!(runtime.get_balance(alice), 1 - fee);
+        assert_eq!(runtime.get_balance(bob), 6 + 7);
+
+        // The half token streamed before the settlement is kept
+        clock.advance(180);
+        assert_eq!(runtime.get_balance(alice), 2 - fee);
+        assert_eq!(runtime.pending_ubi(alice), 1);
+
+        // Nothing streams once verification is revoked
+        runtime.revoke_verification(alice);
+        clock.advance(3600);
+        assert_eq!(runtime.get_balance(alice), 2 - fee);
+    }
+
+    #[test]
+    fn test_payment_streams_settle_when_balances_move_but_not_when_read() {
+        let clock = MockClock::at(TEST_START_TIME);
+        let runtime = TestRuntimeBuilder::new().clock(&clock).build();
+        let alice = "0x1111111111111111111111111111111111111111";
+        let bob = "0x2222222222222222222222222222222222222222";
+        let carol = "0x3333333333333333333333333333333333333333";
+        for address in [alice, bob, carol] {
+            runtime.create_account(address).unwrap();
+        }
+        runtime.credit_balance(alice, 100).unwrap();
+        
+        assert_eq!(runtime.open_stream(alice, alice, 6), Err(StreamError::SameAccount));
+        assert_eq!(runtime.open_stream(alice, bob, 0), Err(StreamError::ZeroRate));
+        let stranger = "0x4444444444444444444444444444444444444444";
+        assert_eq!(runtime.open_stream(alice, stranger, 6), Err(AccountError::AccountNotFound(stranger.to_string()).into()));
+        
+        // Six tokens an hour is one every ten minutes; reads show what is due but settle nothing
+        let id = runtime.open_stream(alice, bob, 6).unwrap();
+        clock.advance(15 * 60);
+        let root = runtime.state_root();
+        assert_eq!(runtime.get_balance(bob), 1);
+        assert_eq!(runtime.get_streams(bob)[0].settled_at, TEST_START_TIME + 600);
+        assert_eq!(runtime.get_stream(id).unwrap().streamed, 0);
+        assert_eq!(runtime.get_stream(id).unwrap().settled_at, TEST_START_TIME);
+        assert_eq!(runtime.state_root(), root);
+        
+        // Settling moves what is due, carrying the part token
+        clock.advance(5 * 60);
+        assert_eq!(runtime.get_balance(alice), 98);
+        assert_eq!(runtime.settle_stream(id), Ok(2));
+        assert_eq!(runtime.settle_stream(id), Ok(0));
+        
+        // A transfer by the recipient settles the stream before it is checked
+        clock.advance(10 * 60);
+        let fee = runtime.chain_params().transfer_fee(3);
+        runtime.transfer_with_fee(bob, carol, 3).unwrap();
+        assert_eq!(runtime.get_balance(alice), 97);
+        assert_eq!(runtime.get_balance(bob), 3 - 3 - fee);
+        let kinds: Vec<&str> = runtime.account_statement(bob, 0, u64::MAX).entries.iter()
+            .map(|entry| entry.kind.name())
+            .collect();
+        assert_eq!(kinds, ["stream_in", "stream_in", "transfer_out"]);
+        
+        // Closing settles one last time, and nothing moves after it
+        clock.advance(10 * 60);
+        let closed = runtime.close_stream(id).unwrap();
+        assert_eq!(closed.streamed, 4);
+        clock.advance(3600);
+        assert_eq!(runtime.get_balance(alice), 96);
+        assert!(runtime.get_streams(alice).is_empty());
+        assert_eq!(runtime.settle_stream(id), Err(StreamError::UnknownStream(id)));
+        assert_eq!(runtime.next_stream_id(), id + 1);
+    }
+
+    #[test]
+    fn test_exhausted_sender_pauses_payment_stream() {
+        let clock = MockClock::at(TEST_START_TIME);
+        let runtime = TestRuntimeBuilder::new().clock(&clock).build();
+        let alice = "0x1111111111111111111111111111111111111111";
+        let bob = "0x2222222222222222222222222222222222222222";
+        runtime.create_account(alice).unwrap();
+        runtime.create_account(bob).unwrap();
+        runtime.credit_balance(alice, 5).unwrap();
+        let supply = runtime.get_total_supply();
+        
+        // Ten tokens are due but only five are held: they move and the stream pauses
+        let id = runtime.open_stream(alice, bob, 60).unwrap();
+        clock.advance(10 * 60);
+        assert_eq!(runtime.get_balance(bob), 5);
+        assert!(runtime.get_streams(bob)[0].paused);
+        assert_eq!(runtime.settle_stream(id), Ok(5));
+        assert_eq!(runtime.get_balance(alice), 0);
+        assert!(runtime.get_stream(id).unwrap().paused);
+        
+        // Nothing is owed for the time it is paused
+        clock.advance(3600);
+        assert_eq!(runtime.get_balance(bob), 5);
+        assert_eq!(runtime.settle_stream(id), Ok(0));
+        
+        // Once funded it resumes from the next settlement
+        runtime.credit_balance(alice, 100).unwrap();
+        assert_eq!(runtime.get_balance(alice), 100);
+        assert_eq!(runtime.settle_stream(id), Ok(0));
+        assert!(!runtime.get_stream(id).unwrap().paused);
+        clock.advance(5 * 60);
+        assert_eq!(runtime.get_balance(bob), 10);
+        assert_eq!(runtime.get_balance(alice), 95);
+        assert_eq!(runtime.get_total_supply(), supply + 100);
+    }
+
+    #[test]
+    fn test_frozen_account_stops_accruing_and_moving_tokens() {
+        let clock = MockClock::at(TEST_START_TIME);
+        let runtime = TestRuntimeBuilder::new().verify_new_accounts(true).ubi_tokens_per_hour(10).clock(&clock).build();
+        let alice = "0x1111111111111111111111111111111111111111";
+        let bob = "0x2222222222222222222222222222222222222222";
+        runtime.create_account(alice).unwrap();
+        runtime.create_account(bob).unwrap();
+        runtime.credit_balance(bob, 100).unwrap();
+
+        // Freezing mid-stream settles the UBI streamed so far, and nothing streams after it
+        clock.advance(1800);
+        runtime.freeze_account(alice, Some("sybil review".to_string())).unwrap();
+        assert_eq!(runtime.account_freeze(alice), Some(Some("sybil review".to_string())));
+        assert_eq!(runtime.account_freeze(bob), None);
+        clock.advance(3600);
+        assert_eq!(runtime.get_balance(alice), 5);
+        assert_eq!(runtime.pending_ubi(alice), 0);
+
+        // Transfers are refused in both directions, and so are dividend claims
+        let frozen = Err(AccountError::AccountFrozen(alice.to_string()));
+        assert_eq!(runtime.transfer_with_fee(alice, bob, 1), frozen);
+        assert_eq!(runtime.transfer_with_fee(bob, alice, 1), frozen);
+        assert_eq!(runtime.claim_dividends(alice).map(|_| ()), frozen);
+        assert_eq!((runtime.get_balance(alice), runtime.get_balance(bob)), (5, 100 + 15));
+
+        // Accrual resumes from the unfreeze; the frozen hour is not paid
+        runtime.unfreeze_account(alice).unwrap();
+        assert_eq!(runtime.account_freeze(alice), None);
+        clock.advance(1800);
+        assert_eq!(runtime.get_balance(alice), 10);
+        runtime.transfer_with_fee(bob, alice, 1).unwrap();
+        assert_eq!(runtime.freeze_account("0x3333333333333333333333333333333333333333", None),
+                   Err(AccountError::AccountNotFound("0x3333333333333333333333333333333333333333".to_string())));
+    }
+
+    #[test]
+    fn test_locked_tokens_vest_at_their_unlock_time() {
+        let clock = MockClock::at(TEST_START_TIME);
+        let runtime = TestRuntimeBuilder::new().clock(&clock).build();
+        let alice = "0x1111111111111111111111111111111111111111";
+        let bob = "0x2222222222222222222222222222222222222222";
+        runtime.create_account(alice).unwrap();
+        runtime.create_account(bob).unwrap();
+        runtime.credit_balance(alice, 1_000).unwrap();
+
+        // Only spendable tokens can be locked, and only until a later time
+        let unlock_at = TEST_START_TIME + 3600;
+        assert!(runtime.lock_balance(alice, 100, TEST_START_TIME).is_err());
+        assert_eq!(runtime.lock_balance(alice, 1_001, unlock_at),
+                   Err(AccountError::InsufficientBalance { required: 1_001, available: 1_000 }));
+        runtime.lock_balance(alice, 600, unlock_at).unwrap();
+        assert_eq!(runtime.lock_balance(alice, 401, unlock_at + 60),
+                   Err(AccountError::InsufficientBalance { required: 401, available: 400 }));
+        assert_eq!((runtime.get_balance(alice), runtime.locked_balance(alice)), (1_000, 600));
+
+        // A transfer may spend up to the unlocked part, fee included
+        let fee = runtime.chain_params().transfer_fee(400);
+        assert_eq!(runtime.transfer_with_fee(alice, bob, 400),
+                   Err(AccountError::InsufficientBalance { required: 400 + fee, available: 400 }));
+        assert!(matches!(runtime.burn(alice, 401), Err(AccountError::InsufficientBalance { .. })));
+        assert!(matches!(runtime.export_account(alice, 1), Err(AccountTransferError::Locked(_))));
+        runtime.transfer_with_fee(alice, bob, 300).unwrap();
+
+        // The lock releases at its unlock time and is dropped on the next spend
+        clock.advance(3600);
+        assert_eq!(runtime.locked_balance(alice), 0);
+        let remaining = runtime.get_balance(alice);
+        runtime.transfer_with_fee(alice, bob, 600).unwrap();
+        assert_eq!(runtime.get_balance(alice), remaining - 600 - runtime.chain_params().transfer_fee(600));
+        assert!(runtime.capture_state().accounts.iter().all(|account| account.locks.is_empty()));
+    }
+
+    #[test]
+    fn test_auto_compound_claims_dividends_when_the_account_transacts() {
+        let runtime = TestRuntimeBuilder::new().build();
+        let compounder = "0x1111111111111111111111111111111111111111";
+        let control = "0x2222222222222222222222222222222222222222";
+        let bob = "0x3333333333333333333333333333333333333333";
+        for address in [compounder, control, bob] {
+            runtime.create_account(address).unwrap();
+        }
+        runtime.credit_balance(compounder, 1_000).unwrap();
+        runtime.credit_balance(control, 1_000).unwrap();
+        assert_eq!(runtime.set_auto_compound("0x4444444444444444444444444444444444444444", true),
+                   Err(AccountError::AccountNotFound("0x4444444444444444444444444444444444444444".to_string())));
+        runtime.set_auto_compound(compounder, true).unwrap();
+        assert!(runtime.is_auto_compound(compounder) && !runtime.is_auto_compound(control));
+        
+        // Both earn the same dividends, but only the opted-in account has them claimed on a UBI claim
+        runtime.dividends.write().unwrap().fee_pool = 100;
+        assert_eq!(runtime.distribute_fees(), 100);
+        assert_eq!(runtime.get_unclaimed_dividends(compounder), 50);
+        assert_eq!(runtime.claim_ubi(compounder).unwrap().new_balance, 1_050);
+        assert_eq!(runtime.claim_ubi(control).unwrap().new_balance, 1_000);
+        assert_eq!((runtime.get_unclaimed_dividends(compounder), runtime.get_unclaimed_dividends(control)), (0, 50));
+        
+        // A transfer spends them too: the control account cannot reach its dividends without a claim
+        runtime.dividends.write().unwrap().fee_pool = 100;
+        runtime.distribute_fees();
+        let owed = runtime.get_unclaimed_dividends(compounder);
+        assert!(owed > 0);
+        let fee = runtime.chain_params().transfer_fee(1_000);
+        runtime.transfer_with_fee(compounder, bob, 1_000).unwrap();
+        assert_eq!(runtime.get_balance(compounder), 1_050 + owed - 1_000 - fee);
+        assert_eq!(runtime.get_unclaimed_dividends(compounder), 0);
+        assert_eq!(runtime.transfer_with_fee(control, bob, 1_000),
+                   Err(AccountError::InsufficientBalance { required: 1_000 + fee, available: 1_000 }));
+        assert!(runtime.get_unclaimed_dividends(control) > 50);
+        
+        // Opting out leaves dividends owed again
+        runtime.set_auto_compound(compounder, false).unwrap();
+        runtime.dividends.write().unwrap().fee_pool = 100;
+        runtime.distribute_fees();
+        let owed = runtime.get_unclaimed_dividends(compounder);
+        assert!(owed > 0);
+        runtime.claim_ubi(compounder).unwrap();
+        assert_eq!(runtime.get_unclaimed_dividends(compounder), owed);
+    }
+
+    #[test]
+    fn test_transfer_from_draws_on_the_allowance() {
+        let runtime = TestRuntimeBuilder::new().build();
+        let owner = "0x1111111111111111111111111111111111111111";
+        let recipient = "0x2222222222222222222222222222222222222222";
+        let dapp = "0x3333333333333333333333333333333333333333";
+        runtime.create_account(owner).unwrap();
+        runtime.create_account(recipient).unwrap();
+        runtime.credit_balance(owner, 1_000).unwrap();
+        let fee = runtime.chain_params().transfer_fee(100);
+        
+        // Nothing can be drawn before an approval
+        assert_eq!(runtime.transfer_from(dapp, owner, recipient, 100),
+                   Err(AccountError::InsufficientAllowance { required: 100 + fee, available: 0 }));
+        
+        // The allowance covers the fee, so one more token than approved is refused unchanged
+        runtime.approve(owner, dapp, 100 + fee).unwrap();
+        assert_eq!(runtime.allowance(owner, dapp), 100 + fee);
+        let over = runtime.chain_params().transfer_fee(101);
+        assert_eq!(runtime.transfer_from(dapp, owner, recipient, 101),
+                   Err(AccountError::InsufficientAllowance { required: 101 + over, available: 100 + fee }));
+        assert_eq!(runtime.get_balance(owner), 1_000);
+        
+        // Drawing exactly the allowance uses it up, and the fee goes through the pool
+        let pool = runtime.get_fee_pool();
+        assert_eq!(runtime.transfer_from(dapp, owner, recipient, 100), Ok(fee));
+        assert_eq!(runtime.allowance(owner, dapp), 0);
+        assert_eq!(runtime.get_balance(owner), 1_000 - 100 - fee);
+        assert_eq!(runtime.get_balance(recipient), 100);
+        assert_eq!(runtime.get_fee_pool(), pool + runtime.chain_params().split_fee(fee).0);
+        assert!(runtime.transfer_from(dapp, owner, recipient, 1).is_err());
+        
+        // A new approval replaces the old one, and zero withdraws it
+        runtime.approve(owner, dapp, 50).unwrap();
+        runtime.approve(owner, dapp, 0).unwrap();
+        assert_eq!(runtime.allowance(owner, dapp), 0);
+        assert_eq!(runtime.approve(recipient, "not-an-address", 5), Err(AccountError::InvalidAddress));
+        assert!(runtime.transfer_from(dapp, owner, runtime.burn_address(), 1).is_err());
+    }
+
+    #[test]
+    fn test_batch_transfer_is_all_or_nothing() {
+        let runtime = TestRuntimeBuilder::new().build();
+        let treasury = "0x1111111111111111111111111111111111111111";
+        let recipients: Vec<String> = (2..=4).map(|i| format!("0x{:040x}", i)).collect();
+        runtime.create_account(treasury).unwrap();
+        for recipient in &recipients {
+            runtime.create_account(recipient).unwrap();
+        }
+        runtime.credit_balance(treasury, 1_000).unwrap();
+        let params = runtime.chain_params();
+        let legs = |amounts: [u64; 3]| recipients.iter().cloned().zip(amounts).collect::<Vec<_>>();
+        
+        // The last leg takes the total past the balance, so none of the legs is applied
+        let supply = runtime.get_total_supply();
+        let required = [300, 300, 400].iter().map(|amount| amount + params.transfer_fee(*amount)).sum();
+        assert_eq!(runtime.batch_transfer(treasury, legs([300, 300, 400])),
+                   Err(AccountError::InsufficientBalance { required, available: 1_000 }));
+        assert_eq!(runtime.get_balance(treasury), 1_000);
+        assert!(recipients.iter().all(|recipient| runtime.get_balance(recipient) == 0));
+        assert_eq!(runtime.get_total_supply(), supply);
+        
+        // A batch the sender can cover pays every leg and its fee
+        let pool = runtime.get_fee_pool();
+        let receipt = runtime.batch_transfer(treasury, legs([300, 300, 300])).unwrap();
+        assert_eq!(receipt.total_fee, 3 * params.transfer_fee(300));
+        assert_eq!(runtime.get_balance(treasury), 1_000 - receipt.total_debited);
+        assert!(recipients.iter().all(|recipient| runtime.get_balance(recipient) == 300));
+        assert_eq!(runtime.get_fee_pool(), pool + 3 * params.split_fee(params.transfer_fee(300)).0);
+        
+        // An unknown recipient or an empty batch is refused
+        let stranger = "0x5555555555555555555555555555555555555555".to_string();
+        assert!(runtime.batch_transfer(treasury, vec![(recipients[0].clone(), 1), (stranger, 1)]).is_err());
+        assert!(runtime.batch_transfer(treasury, Vec::new()).is_err());
+        assert!(recipients.iter().all(|recipient| runtime.get_balance(recipient) == 300));
+    }
+
+    #[test]
+    fn test_ubi_accruals_are_recorded_as_mints() {
+        let runtime = Runtime::new();
+        let rate = runtime.chain_params().ubi_tokens_per_hour;
+        let created = current_time_secs();
+        let addresses: Vec<String> = (1..=3).map(|i| format!("0x{:040x}", i)).collect();
+        for address in &addresses {
+            runtime.create_account(address).unwrap();
+            runtime.verify_account_at(address, created);
         }
         
-        // Call update_ubi_balance
-        let new_balance = runtime.update_ubi_balance(address);
-        
-        // With UBI_TOKENS_PER_HOUR = 1, we should get 2 tokens for 2 hours
-        assert_eq!(new_balance, 2, "Expected exactly 2 tokens for 2 hours at 1 token per hour");
+        // Every settlement is a mint matching the balance change and the supply
+        let mut credited = 0;
+        for (i, address) in addresses.iter().enumerate() {
+            credited += runtime.update_ubi_balance_at(address, created + 2 * 3600 + i as u64).unwrap();
+        }
+        credited += runtime.update_ubi_balance_at(&addresses[0], created + 5 * 3600).unwrap();
+        assert_eq!(credited, 9 * rate);
+        assert_eq!(runtime.get_total_supply(), credited);
+        for address in &addresses {
+            let minted: u64 = runtime.ubi_mints_for(address).iter().map(|mint| mint.amount).sum();
+            assert_eq!(minted, runtime.get_balance(address));
+        }
+        let history = runtime.ubi_mints_for(&addresses[0]);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0], UbiMint::new(&addresses[0], 2 * rate, created + 2 * 3600));
+        
+        // Blocks take a capped batch and the rest carries over
+        assert_eq!(runtime.include_ubi_mints(1, "0x1", 3).len(), 3);
+        let carried = runtime.include_ubi_mints(2, "0x2", 3);
+        assert_eq!(carried.len(), 1);
+        assert_eq!((carried[0].block_number, carried[0].amount), (Some(2), 3 * rate));
+        assert!(runtime.include_ubi_mints(3, "0x3", 3).is_empty());
+        assert_eq!(runtime.ubi_mints_between(2, 3), carried);
+    }
+
+    #[test]
+    fn test_lifetime_ubi_stats_count_every_accrual_and_claim() {
+        let clock = MockClock::at(TEST_START_TIME);
+        let runtime = TestRuntimeBuilder::new().clock(&clock).build();
+        let rate = runtime.chain_params().ubi_tokens_per_hour;
+        let claimer = "0x1111111111111111111111111111111111111111";
+        let settler = "0x2222222222222222222222222222222222222222";
+        for address in [claimer, settler] {
+            runtime.create_account(address).unwrap();
+            runtime.verify_account_at(address, TEST_START_TIME);
+        }
         
-        // Verify the balance was updated in storage
-        let final_balance = runtime.get_balance(address);
-        assert_eq!(final_balance, 2);
+        // Explicit claims count; settlements and claims with nothing due only add what they credit
+        for hours in 1..=3 {
+            clock.advance(hours * 3600);
+            assert_eq!(runtime.claim_ubi(claimer).unwrap().claimed, hours * rate);
+            assert_eq!(runtime.claim_ubi(claimer).unwrap().claimed, 0);
+            runtime.update_ubi_balance(settler).unwrap();
+        }
+        assert_eq!(runtime.get_ubi_stats(claimer), UbiStats { total_ubi_received: 6 * rate, ubi_claim_count: 3 });
+        assert_eq!(runtime.get_ubi_stats(settler), UbiStats { total_ubi_received: 6 * rate, ubi_claim_count: 0 });
+        assert_eq!(runtime.get_ubi_stats("0x3333333333333333333333333333333333333333"), UbiStats::default());
+        
+        // Fees count in full, the burned part included, and distributions as dividends
+        runtime.credit_balance(claimer, 10_000).unwrap();
+        runtime.credit_balance(settler, 10_000).unwrap();
+        let fee = runtime.transfer_fee_for(claimer, 1_000);
+        runtime.transfer_with_fee(claimer, settler, 1_000).unwrap();
+        clock.advance(3600);
+        runtime.transfer_with_fee(settler, claimer, 1_000).unwrap();
+        let distributed = runtime.distribute_fees();
+        assert!(distributed > 0);
+        let stats = runtime.get_network_stats();
+        assert_eq!(stats, NetworkStats {
+            total_ubi_minted: 13 * rate,
+            total_fees_collected: 2 * fee,
+            total_dividends_distributed: distributed,
+        });
+        assert_eq!(runtime.get_ubi_stats(settler).total_ubi_received, 7 * rate);
+        
+        // The counters are kept in checkpoints
+        let (_, data) = checkpoint::decode_checkpoint(&checkpoint::encode_checkpoint(&runtime.capture_state())).unwrap();
+        let restored = TestRuntimeBuilder::new().clock(&clock).build();
+        restored.restore_state(data, &mut restored.nonces.lock().unwrap()).unwrap();
+        assert_eq!(restored.get_network_stats(), stats);
+        assert_eq!(restored.get_ubi_stats(claimer), runtime.get_ubi_stats(claimer));
     }
-    
+
+    #[test]
+    fn test_ubi_does_not_accrue_while_revoked() {
+        let runtime = Runtime::new();
+        let address = "0x1234567890abcdef1234567890abcdef12345678";
+        let rate = runtime.chain_params().ubi_tokens_per_hour;
+        let created = current_time_secs();
+        runtime.create_account(address).unwrap();
+
+        runtime.verify_account_at(address, created + 3600);
+        assert_eq!(runtime.update_ubi_balance_at(address, created + 3 * 3600).unwrap(), 2 * rate);
+
+        // Revoking credits the hour earned since the last claim
+        assert!(runtime.revoke_verification_at(address, created + 4 * 3600 + 1800));
+        assert_eq!(runtime.get_balance(address), 3 * rate);
+        assert!(!runtime.is_account_verified(address));
+
+        // Re-verified after a five and a half hour gap, claimed two hours later
+        runtime.verify_account_at(address, created + 10 * 3600);
+        assert_eq!(runtime.update_ubi_balance_at(address, created + 12 * 3600 + 600).unwrap(), 2 * rate);
+        assert_eq!(runtime.get_balance(address), 5 * rate);
+    }
+
+    #[test]
+    fn test_verification_changes_wait_for_the_next_block() {
+        let runtime = Runtime::new();
+        let address = "0x1234567890abcdef1234567890abcdef12345678";
+        let created = current_time_secs();
+        runtime.create_account(address).unwrap();
+
+        runtime.verify_account_at(address, created + 60);
+        runtime.verify_account_at(address, created + 120);
+        runtime.revoke_verification_at(address, created + 180);
+        runtime.revoke_verification_at(address, created + 240);
+
+        // Repeats that change nothing are not events
+        let events = runtime.take_verification_events();
+        let changes: Vec<_> = events.iter().map(|event| (event.change, event.at)).collect();
+        assert_eq!(changes, vec![(VerificationChange::Verified, created + 60), (VerificationChange::Revoked, created + 180)]);
+        assert!(runtime.take_verification_events().is_empty());
+    }
+
     // New tests for the added functionality
-    
+
     #[test]
     fn test_compute_current_balance() {
         // Test with zero streaming rate
@@ -180,6 +888,7 @@ mod tests {
             base_balance: 100,
             last_update: 1000,
             streaming_rate: 0,
+            ..Default::default()
         };
         
         let now = 2000;
@@ -190,7 +899,8 @@ mod tests {
         let account_state = AccountState {
             base_balance: 100,
             last_update: 1000,
-            streaming_rate: 1,
+            streaming_rate: STREAMING_RATE_PRECISION,
+            ..Default::default()
         };
         
         let now = 2000;
@@ -201,12 +911,26 @@ mod tests {
         let account_state = AccountState {
             base_balance: 100,
             last_update: 1000,
-            streaming_rate: 2,
+            streaming_rate: 2 * STREAMING_RATE_PRECISION,
+            ..Default::default()
         };
         
         let now = 101000;
         let balance = compute_current_balance(&account_state, now);
         assert_eq!(balance, 200100); // 100 + 2 * (101000 - 1000)
+        
+        // Only whole tokens count: 30 minutes at one token per hour is half a token
+        let account_state = AccountState {
+            base_balance: 100,
+            last_update: 1000,
+            streaming_rate: streaming_rate_per_hour(1),
+            ..Default::default()
+        };
+        assert_eq!(compute_current_balance(&account_state, 1000 + 1800), 100);
+        assert_eq!(compute_current_balance(&account_state, 1000 + 3600), 101);
+        let account_state = AccountState { streaming_rate: streaming_rate_per_hour(10), ..account_state };
+        assert_eq!(compute_current_balance(&account_state, 1000 + 1800), 105);
+        assert_eq!(compute_current_balance(&account_state, 1000 + 2700), 107);
     }
     
     #[test]
@@ -215,7 +939,8 @@ mod tests {
         let mut account_state = AccountState {
             base_balance: 100,
             last_update: 1000,
-            streaming_rate: 1,
+            streaming_rate: STREAMING_RATE_PRECISION,
+            ..Default::default()
         };
         
         // Update the state
@@ -229,58 +954,307 @@ mod tests {
         assert_eq!(account_state.last_update, now);
         
         // Streaming rate should remain unchanged
-        assert_eq!(account_state.streaming_rate, 1);
+        assert_eq!(account_state.streaming_rate, STREAMING_RATE_PRECISION);
+        
+        // A part token carries over: settling every 30 minutes at one token
+        // per hour streams a token an hour
+        let mut account_state = AccountState { streaming_rate: streaming_rate_per_hour(1), ..account_state };
+        update_account_state(&mut account_state, now + 1800);
+        assert_eq!((account_state.base_balance, account_state.last_update), (1100, now));
+        update_account_state(&mut account_state, now + 3600);
+        assert_eq!((account_state.base_balance, account_state.last_update), (1101, now + 3600));
     }
     
     #[test]
     fn test_transfer_with_fee() {
-        let runtime = Runtime::new();
-        
-        // Create sender and recipient accounts
         let sender = "0x1111111111111111111111111111111111111111";
         let recipient = "0x2222222222222222222222222222222222222222";
+        let builder = TestRuntimeBuilder::new()
+            .funded_account(sender, 1000)
+            .funded_account(recipient, 0);
+        let params = builder.params();
+        let runtime = builder.build();
+        assert_eq!(runtime.get_fee_pool(), 0);
         
-        let _ = runtime.create_account(sender);
-        let _ = runtime.create_account(recipient);
-        
-        // Add some balance to sender
-        {
-            let mut accounts = runtime.accounts.lock().unwrap();
-            if let Some(account) = accounts.get_mut(sender) {
-                account.balance = 1000;
-            }
-        }
-        
-        // Check initial balances
-        let sender_initial = runtime.get_balance(sender);
-        let recipient_initial = runtime.get_balance(recipient);
-        let fee_pool_initial = runtime.get_fee_pool();
-        
-        assert_eq!(sender_initial, 1000);
-        assert_eq!(recipient_initial, 0);
-        assert_eq!(fee_pool_initial, 0);
-        
-        // Perform transfer with fee
         let transfer_amount = 100;
         let result = runtime.transfer_with_fee(sender, recipient, transfer_amount);
         assert!(result.is_ok());
         
-        // Check final balances
-        let sender_final = runtime.get_balance(sender);
-        let recipient_final = runtime.get_balance(recipient);
-        let fee_pool_final = runtime.get_fee_pool();
-        
-        // Sender should have lost the full amount
-        assert_eq!(sender_final, sender_initial - transfer_amount);
+        // The recipient gets the full amount and the sender pays the fee on top
+        let fee = params.transfer_fee(transfer_amount);
+        assert_eq!(runtime.get_balance(sender), 1000 - transfer_amount - fee);
+        assert_eq!(runtime.get_balance(recipient), transfer_amount);
         
-        // Recipient should have received amount minus 1% fee
-        let expected_fee = transfer_amount / 100;
-        assert_eq!(recipient_final, recipient_initial + (transfer_amount - expected_fee));
-        
-        // Fee pool should have received the fee
-        assert_eq!(fee_pool_final, fee_pool_initial + expected_fee);
+        // The pool's share of the fee is collected and the rest burned
+        let (to_pool, burned) = params.split_fee(fee);
+        assert_eq!(runtime.get_fee_pool(), to_pool);
+        assert_eq!(runtime.get_total_supply(), 1000 - burned);
     }
-    
+
+    #[test]
+    fn test_fee_rate_is_configured_and_exempt_senders_pay_none() {
+        let sender = "0x1111111111111111111111111111111111111111";
+        let recipient = "0x2222222222222222222222222222222222222222";
+        for (fee_basis_points, expected_fee) in [(0, 0), (100, 10), (250, 25)] {
+            let runtime = Runtime::with_config(config::RuntimeConfig { fee_basis_points, ..Default::default() });
+            runtime.create_account(sender).unwrap();
+            runtime.create_account(recipient).unwrap();
+            runtime.credit_balance(sender, 10_000).unwrap();
+            assert_eq!(runtime.fee_basis_points(), fee_basis_points);
+            assert_eq!(runtime.transfer_fee_for(sender, 1_000), expected_fee);
+
+            runtime.transfer_with_fee(sender, recipient, 1_000).unwrap();
+            assert_eq!(runtime.get_balance(sender), 10_000 - 1_000 - expected_fee);
+        }
+
+        // An exempt sender's transfers cost nothing until the exemption is lifted
+        let runtime = TestRuntimeBuilder::new()
+            .fee_bps(100)
+            .funded_account(sender, 10_000)
+            .funded_account(recipient, 0)
+            .build();
+        assert!(runtime.is_fee_exempt(FAUCET_ADDRESS));
+        runtime.set_fee_exempt(&sender.to_uppercase().replace("0X", "0x"), true);
+        assert!(runtime.is_fee_exempt(sender));
+        assert_eq!(runtime.simulate_transfer(sender, recipient, 1_000), Ok(0));
+        runtime.transfer_with_fee(sender, recipient, 1_000).unwrap();
+        assert_eq!((runtime.get_balance(sender), runtime.get_fee_pool()), (9_000, 0));
+
+        runtime.set_fee_exempt(sender, false);
+        runtime.transfer_with_fee(sender, recipient, 1_000).unwrap();
+        assert_eq!(runtime.get_balance(sender), 8_000 - 10);
+
+        // Genesis exempts its faucet and the listed senders, and refuses invalid addresses
+        let genesis: GenesisConfig = serde_json::from_str(&format!(r#"{{
+            "timestamp": 1760000000,
+            "faucet": {{ "address": "node", "initial_balance": 1000 }},
+            "fee_exempt": ["{}"]
+        }}"#, recipient)).unwrap();
+        let node = "0xabcdef0123456789abcdef0123456789abcdef01";
+        let runtime = Runtime::from_genesis(&genesis, node).unwrap();
+        assert!(runtime.is_fee_exempt(node) && runtime.is_fee_exempt(recipient) && !runtime.is_fee_exempt(sender));
+        let invalid = GenesisConfig { fee_exempt: vec!["treasury".to_string()], ..GenesisConfig::default() };
+        assert_eq!(Runtime::from_genesis(&invalid, node).err(), Some(GenesisError::InvalidFeeExemptAddress("treasury".to_string())));
+    }
+
+    #[test]
+    fn test_transactions_must_use_the_next_nonce() {
+        let sender = "0x1111111111111111111111111111111111111111";
+        let recipient = "0x2222222222222222222222222222222222222222";
+        let runtime = TestRuntimeBuilder::new()
+            .fee_bps(0)
+            .minimum_fee(0)
+            .funded_account(sender, 100)
+            .funded_account(recipient, 0)
+            .build();
+        let transfer = |hash: &str, amount: u64, nonce: Option<u64>| Transaction {
+            hash: hash.to_string(),
+            from: sender.to_uppercase().replace("0X", "0x"),
+            to: recipient.to_string(),
+            amount,
+            fee: 0,
+            timestamp: TEST_START_TIME,
+            mode: TransferMode::Send,
+            nonce,
+        };
+        assert_eq!(runtime.get_nonce(sender), 0);
+
+        assert_eq!(runtime.execute_transaction(&transfer("0xa", 10, Some(0))).unwrap(), 0);
+        assert_eq!(runtime.get_nonce(sender), 1);
+
+        // A replay and a gap are both refused and change nothing
+        assert!(matches!(
+            runtime.execute_transaction(&transfer("0xa", 10, Some(0))),
+            Err(AccountError::InvalidNonce { expected: 1, actual: 0, .. })
+        ));
+        let gap = runtime.execute_transaction(&transfer("0xb", 10, Some(5))).unwrap_err();
+        assert_eq!(gap.to_string(), format!("Nonce too high for {}: expected 1, got 5", sender));
+        assert_eq!(runtime.get_balance(recipient), 10);
+
+        // A failed transfer leaves the nonce for the next attempt; no nonce takes the next one
+        assert!(runtime.execute_transaction(&transfer("0xc", 1_000, Some(1))).is_err());
+        assert_eq!(runtime.get_nonce(sender), 1);
+        assert_eq!(runtime.execute_transaction(&transfer("0xd", 10, None)).unwrap(), 1);
+        assert_eq!(runtime.get_nonce(&sender.to_uppercase().replace("0X", "0x")), 2);
+        assert_eq!(runtime.get_nonce(recipient), 0);
+    }
+
+    #[test]
+    fn test_failed_debits_report_their_cause() {
+        let sender = "0x1111111111111111111111111111111111111111";
+        let recipient = "0x2222222222222222222222222222222222222222";
+        let missing = "0x3333333333333333333333333333333333333333";
+        let runtime = TestRuntimeBuilder::new()
+            .fee_bps(100)
+            .minimum_fee(1)
+            .funded_account(sender, 100)
+            .funded_account(recipient, 0)
+            .build();
+
+        assert_eq!(
+            runtime.transfer_with_fee(sender, recipient, 100),
+            Err(AccountError::InsufficientBalance { required: 101, available: 100 })
+        );
+        assert_eq!(
+            runtime.burn(sender, 101).unwrap_err(),
+            AccountError::InsufficientBalance { required: 101, available: 100 }
+        );
+        assert_eq!(runtime.transfer_with_fee(missing, recipient, 1), Err(AccountError::AccountNotFound(missing.to_string())));
+        assert_eq!(
+            runtime.transfer_with_fee(sender, &missing.to_uppercase().replace("0X", "0x"), 1),
+            Err(AccountError::AccountNotFound(missing.to_string()))
+        );
+        assert_eq!(runtime.credit_balance(missing, 1), Err(AccountError::AccountNotFound(missing.to_string())));
+        assert_eq!(runtime.get_balance(sender), 100);
+
+        // Faucet sources that are all short of funds report the shortfall of the best funded one
+        let sources = vec![recipient.to_string(), sender.to_string()];
+        assert_eq!(
+            runtime.grant_from_faucet(&sources, recipient, 200, None).map(|grant| grant.amount),
+            Err(AccountError::InsufficientBalance { required: 202, available: 100 })
+        );
+        assert!(matches!(
+            runtime.grant_from_faucet(&[missing.to_string()], recipient, 1, None),
+            Err(AccountError::Other(_))
+        ));
+    }
+
+    #[test]
+    fn test_readers_run_alongside_a_writer() {
+        let runtime = TestRuntimeBuilder::new()
+            .fee_bps(100)
+            .minimum_fee(1)
+            .funded_account("0x00000000000000000000000000000000000a0001", 100_000)
+            .funded_account("0x00000000000000000000000000000000000a0002", 100_000)
+            .build();
+        let addresses = runtime.get_account_addresses();
+        let supply = runtime.get_total_supply();
+
+        // Many readers query balances and dividends while one writer transfers and distributes
+        let reads = std::sync::atomic::AtomicU64::new(0);
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                let (runtime, addresses, reads) = (&runtime, &addresses, &reads);
+                scope.spawn(move || {
+                    for _ in 0..500 {
+                        let held: u64 = addresses.iter().map(|address| runtime.get_balance(address)).sum();
+                        assert!(held <= supply);
+                        for address in addresses {
+                            runtime.get_unclaimed_dividends(address);
+                            runtime.is_account_verified(address);
+                        }
+                        reads.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                    }
+                });
+            }
+            scope.spawn(|| {
+                for i in 0..500 {
+                    runtime.transfer_with_fee(&addresses[i % 2], &addresses[(i + 1) % 2], 10).unwrap();
+                    if i % 50 == 0 {
+                        runtime.distribute_fees();
+                    }
+                }
+            });
+        });
+
+        assert_eq!(reads.into_inner(), 8 * 500);
+        let report = runtime.supply_report();
+        assert_eq!(report.total_supply, supply);
+        // Dividends are rounded down when a balance change settles them, so each of the 10
+        // distributions leaves at most a token per account unassigned, plus the final rounding
+        assert!(report.unaccounted >= 0 && report.unaccounted < 10 * 2 + 2);
+    }
+
+    #[test]
+    fn test_balances_sum_to_the_total_supply() {
+        let runtime = Runtime::new();
+        let created = current_time_secs();
+        let addresses: Vec<String> = (1..=3).map(|i| format!("0x{:040x}", 0xabc000 + i)).collect();
+        for address in &addresses {
+            runtime.create_account(address).unwrap();
+            runtime.verify_account_at(address, created);
+        }
+        // Transfer fees wait in the pool until they are distributed
+        let held = |runtime: &Runtime| -> u64 {
+            let balances: u64 = runtime.get_account_addresses().iter().map(|address| runtime.get_balance(address)).sum();
+            balances + runtime.get_fee_pool()
+        };
+
+        // Credits, UBI, transfers and burns all move the supply with the balances
+        runtime.credit_balance(&addresses[0], 1_000).unwrap();
+        runtime.update_ubi_balance_at(&addresses[1], created + 5 * 3600).unwrap();
+        runtime.transfer_with_fee(&addresses[0], &addresses[2], 400).unwrap();
+        assert_eq!(runtime.burn_balance(&addresses[2], 150), Ok(250));
+        runtime.burn(&addresses[0], 100).unwrap();
+        runtime.transfer_with_fee(&addresses[1], runtime.burn_address(), 2).unwrap();
+        assert_eq!(held(&runtime), runtime.get_total_supply());
+        assert_eq!(runtime.get_total_supply(), 1_000 + 5 * runtime.chain_params().ubi_tokens_per_hour - 252);
+
+        // A failed burn changes neither
+        assert_eq!(
+            runtime.burn_balance(&addresses[2], 251),
+            Err(AccountError::InsufficientBalance { required: 251, available: 250 })
+        );
+        assert_eq!(held(&runtime), runtime.get_total_supply());
+    }
+
+    #[test]
+    fn test_credits_that_would_overflow_leave_state_unchanged() {
+        let rich = "0x7a3f0c91d2e4b5a6c8f9e0d1b2a3c4d5e6f7a8b9";
+        let holder = "0x4c1d9e8f7a6b5c4d3e2f1a0b9c8d7e6f5a4b3c2d";
+        let imported = "0x9e8d7c6b5a4f3e2d1c0b9a8f7e6d5c4b3a2f1e0d";
+        let runtime = TestRuntimeBuilder::new()
+            .fee_bps(100)
+            .minimum_fee(1)
+            .funded_account(holder, 1_000)
+            .funded_account(rich, 0)
+            .build();
+
+        // Credit the rich account up to five tokens short of a full supply
+        let headroom = u64::MAX - runtime.get_total_supply();
+        runtime.credit_balance(rich, headroom - 5).unwrap();
+        let rich_balance = runtime.get_balance(rich);
+        assert_eq!(runtime.get_total_supply(), u64::MAX - 5);
+
+        // Neither a credit nor a block reward can push the supply past u64::MAX
+        assert_eq!(runtime.credit_balance(rich, 6), Err(AccountError::Overflow("total supply".to_string())));
+        assert_eq!(runtime.credit_block_reward(holder, 6, "0xb10c"), Err(AccountError::Overflow("total supply".to_string())));
+        assert_eq!(runtime.get_balance(rich), rich_balance);
+        assert_eq!(runtime.get_balance(holder), 1_000);
+        assert_eq!(runtime.get_total_supply(), u64::MAX - 5);
+        assert!(runtime.supply_events().is_empty());
+
+        // An imported balance is not bounded by this chain's supply, so the balance check catches it
+        runtime.import_account(&AccountExport {
+            address: imported.to_string(),
+            balance: u64::MAX - 10,
+            verified: false,
+            last_ubi_claim_secs: TEST_START_TIME,
+            verified_at_secs: None,
+            settled_dividends: 0,
+            limits: None,
+            source_chain_id: 1,
+            export_nonce: 0,
+            exported_at: TEST_START_TIME,
+        }).unwrap();
+        let fee_pool = runtime.get_fee_pool();
+        assert_eq!(
+            runtime.transfer_with_fee(holder, imported, 11),
+            Err(AccountError::Overflow(format!("balance of {}", imported)))
+        );
+        assert_eq!(
+            runtime.credit_balance(imported, 11),
+            Err(AccountError::Overflow(format!("balance of {}", imported)))
+        );
+        assert_eq!(runtime.get_balance(holder), 1_000);
+        assert_eq!(runtime.get_balance(imported), u64::MAX - 10);
+        assert_eq!(runtime.get_fee_pool(), fee_pool);
+
+        // A transfer that fits still goes through
+        runtime.transfer_with_fee(holder, imported, 5).unwrap();
+        assert_eq!(runtime.get_balance(imported), u64::MAX - 5);
+    }
+
     #[test]
     fn test_fee_distribution() {
         let runtime = Runtime::new();
@@ -298,7 +1272,7 @@ mod tests {
         
         // Set up account balances and total supply
         {
-            let mut accounts_map = runtime.accounts.lock().unwrap();
+            let mut accounts_map = runtime.accounts.write().unwrap();
             
             // Account 1: 500 tokens (50%)
             if let Some(account) = accounts_map.get_mut(accounts[0]) {
@@ -316,21 +1290,29 @@ mod tests {
             }
             
             // Set total supply
-            *runtime.total_supply.lock().unwrap() = 1000;
+            runtime.dividends.write().unwrap().total_supply = 1000;
         }
         
         // Add some fees to the fee pool
         {
-            let mut fee_pool = runtime.fee_pool.lock().unwrap();
-            *fee_pool = 100;
+            runtime.dividends.write().unwrap().fee_pool = 100;
         }
         
+        // Only blocks on the distribution schedule distribute on their own
+        assert_eq!(runtime.distribute_fees_if_due(config::DEFAULT_FEE_DISTRIBUTION_INTERVAL_BLOCKS - 1), 0);
+        assert!(runtime.get_distribution_history().is_empty());
+        
         // Distribute fees
-        let distributed = runtime.distribute_fees();
+        let distributed = runtime.distribute_fees_if_due(config::DEFAULT_FEE_DISTRIBUTION_INTERVAL_BLOCKS);
         assert_eq!(distributed, 100);
+        let history = runtime.get_distribution_history();
+        assert_eq!((history.len(), history[0].amount), (1, 100));
+        assert_eq!(history[0].dividend_per_token, dividends::DIVIDEND_PRECISION / 10);
+        assert_eq!(history[0].block_number, config::DEFAULT_FEE_DISTRIBUTION_INTERVAL_BLOCKS);
         
         // Check that fee pool is now empty
-        assert_eq!(*runtime.fee_pool.lock().unwrap(), 0);
+        assert_eq!(runtime.get_fee_pool(), 0);
+        assert_eq!(runtime.get_total_fees_distributed(), 100);
         
         // Update and check dividends for each account
         for &address in &accounts {
@@ -338,9 +1320,9 @@ mod tests {
         }
         
         // Claim dividends and check balances
-        let account1_dividends = runtime.claim_dividends(accounts[0]);
-        let account2_dividends = runtime.claim_dividends(accounts[1]);
-        let account3_dividends = runtime.claim_dividends(accounts[2]);
+        let account1_dividends = runtime.claim_dividends(accounts[0]).unwrap();
+        let account2_dividends = runtime.claim_dividends(accounts[1]).unwrap();
+        let account3_dividends = runtime.claim_dividends(accounts[2]).unwrap();
         
         // Account 1 should get ~50% of fees
         assert!((49..=51).contains(&account1_dividends));
@@ -366,6 +1348,7 @@ mod tests {
             base_balance: 100,
             last_update: 1000,
             streaming_rate: 1,
+            ..Default::default()
         };
         
         let account2 = "0x2222222222222222222222222222222222222222";
@@ -373,6 +1356,7 @@ mod tests {
             base_balance: 200,
             last_update: 2000,
             streaming_rate: 2,
+            ..Default::default()
         };
         
         // Add accounts to the tree
@@ -388,6 +1372,7 @@ mod tests {
             base_balance: 250, // Changed
             last_update: 2500, // Changed
             streaming_rate: 2,
+            ..Default::default()
         };
         
         tree.update_account(account2, &updated_state);
@@ -396,17 +1381,512 @@ mod tests {
         // Root hash should have changed
         assert_ne!(root_hash, new_root_hash, "Root hash should change after updating an account");
     }
+
+    /// Builds the root the way the tree used to, level by level from scratch
+    fn reference_root(leaves: &[[u8; 32]]) -> [u8; 32] {
+        let mut level = leaves.to_vec();
+        loop {
+            if level.len() % 2 == 1 {
+                level.push(level[level.len() - 1]);
+            }
+            level = level.chunks(2).map(|pair| hash_pair(&pair[0], &pair[1])).collect();
+            if level.len() == 1 {
+                return level[0];
+            }
+        }
+    }
+
+    #[test]
+    fn test_merkle_updates_only_recompute_the_changed_paths() {
+        let state = |balance: u64| AccountState { base_balance: balance, last_update: 1000, ..Default::default() };
+        let addresses: Vec<String> = (0..10_000u64).map(|i| format!("0x{:040x}", i * 7919 % 10_007)).collect();
+        let mut tree = MerkleTree::new();
+        tree.update_accounts(addresses.iter().map(|address| (address.as_str(), state(1))));
+        assert_eq!(tree.root_hash(), Some(reference_root(&tree.leaves)));
+
+        // One changed account recomputes one node per level, ceil(log2(10000)) of them
+        assert_eq!(tree.update_accounts([(addresses[42].as_str(), state(2))]), 14);
+        assert_eq!(tree.root_hash(), Some(reference_root(&tree.leaves)));
+
+        // Unchanged accounts cost nothing, and changed neighbours share their path
+        assert_eq!(tree.update_accounts(addresses.iter().map(|address| (address.as_str(), state(1)))), 14);
+        assert_eq!(tree.update_accounts(addresses.iter().map(|address| (address.as_str(), state(1)))), 0);
+        let mut sorted = addresses.clone();
+        sorted.sort();
+        assert_eq!(tree.update_accounts(sorted[..2].iter().map(|address| (address.as_str(), state(3)))), 14);
+        assert_eq!(tree.root_hash(), Some(reference_root(&tree.leaves)));
+
+        // Any tree size matches the full rebuild, including odd levels
+        for size in 1..=33 {
+            let mut small = MerkleTree::new();
+            small.update_accounts(addresses[..size].iter().map(|address| (address.as_str(), state(1))));
+            small.update_account(&addresses[size / 2], &state(5));
+            assert_eq!(small.root_hash(), Some(reference_root(&small.leaves)), "{} leaves", size);
+        }
+    }
+
+    #[test]
+    fn test_merkle_proofs_verify_for_every_account_at_any_depth() {
+        let state = |index: usize, balance: u64| AccountState { base_balance: balance, last_update: index as u64, ..Default::default() };
+        for size in [3, 4, 5, 17] {
+            let addresses: Vec<String> = (0..size).map(|i| format!("0x{:040x}", (i + 1) * 104_729)).collect();
+            let mut tree = MerkleTree::new();
+            tree.update_accounts(addresses.iter().enumerate().map(|(index, address)| (address.as_str(), state(index, 10))));
+
+            // Proofs hold internal node siblings, before and after an in-place update
+            for balance in [10, 11] {
+                tree.update_account(&addresses[size - 1], &state(size - 1, balance));
+                let root = tree.root_hash().unwrap();
+                for (index, address) in addresses.iter().enumerate() {
+                    let current = state(index, if index == size - 1 { balance } else { 10 });
+                    let proof = tree.generate_proof(address).unwrap();
+                    assert_eq!(proof.len(), size.next_power_of_two().trailing_zeros() as usize);
+                    assert!(MerkleTree::verify_proof(root, address, &current, &proof), "leaf {} of {}", index, size);
+
+                    let tampered = AccountState { base_balance: current.base_balance + 1, ..current };
+                    assert!(!MerkleTree::verify_proof(root, address, &tampered, &proof));
+                }
+            }
+        }
+    }
     
     #[test]
-    fn test_checkpoint_creation_and_loading() {
-        // Use a unique directory for this test to avoid conflicts
-        let test_dir = format!("./test_checkpoints_{}", std::process::id());
-        
+    fn test_state_root_does_not_depend_on_account_creation_order() {
+        let addresses = [
+            "0x3333333333333333333333333333333333333333",
+            "0x1111111111111111111111111111111111111111",
+            "0x2222222222222222222222222222222222222222",
+        ];
+        let clock = MockClock::at(TEST_START_TIME);
+        let forward = TestRuntimeBuilder::new().clock(&clock).build();
+        let backward = TestRuntimeBuilder::new().clock(&clock).build();
+        for address in addresses {
+            forward.create_account(address).unwrap();
+            forward.state_root();
+        }
+        for address in addresses.iter().rev() {
+            backward.create_account(address).unwrap();
+        }
+        assert_eq!(forward.state_root(), backward.state_root());
+
+        let proof = forward.account_proof(addresses[0]).unwrap();
+        assert_eq!(proof.root, forward.state_root());
+        assert!(proof.verify().unwrap().valid);
+        assert!(forward.account_proof("0x4444444444444444444444444444444444444444").is_none());
+    }
+
+    #[test]
+    fn test_state_root_covers_verification_and_nonces() {
+        let test_dir = format!("./test_checkpoints_leaves_{}", std::process::id());
+        let _ = std::fs::remove_dir_all(&test_dir);
+        let runtime = Runtime::with_checkpoint_config(5, &test_dir).unwrap();
+        let sender = "0x1111111111111111111111111111111111111111";
+        let recipient = "0x2222222222222222222222222222222222222222";
+        runtime.create_account(sender).unwrap();
+        runtime.create_account(recipient).unwrap();
+        runtime.credit_balance(sender, 1_000).unwrap();
+
+        // Verification alone changes the root, so the next checkpoint is not skipped
+        let unverified = runtime.create_checkpoint(false).unwrap();
+        assert!(runtime.verify_account(sender));
+        let verified = runtime.create_checkpoint(false).unwrap();
+        assert_ne!(verified.root_hash, unverified.root_hash);
+        assert!(runtime.account_proof(sender).unwrap().verified);
+
+        // Nonces are hashed and checkpointed, so a fresh node restores the same root
+        runtime.execute_transaction(&Transaction {
+            hash: "0xa".to_string(),
+            from: sender.to_string(),
+            to: recipient.to_string(),
+            amount: 100,
+            fee: 0,
+            timestamp: current_time_secs(),
+            mode: TransferMode::Send,
+            nonce: None,
+        }).unwrap();
+        let checkpoint = runtime.create_checkpoint(false).unwrap();
+        assert_eq!(runtime.account_proof(sender).unwrap().nonce, 1);
+        let restored = Runtime::with_checkpoint_config(5, &test_dir).unwrap();
+        restored.load_checkpoint(&checkpoint).unwrap();
+        assert_eq!(restored.get_nonce(sender), 1);
+        assert_eq!(restored.state_root(), runtime.state_root());
+        assert!(restored.recompute_state_root().matches);
+
+        let _ = std::fs::remove_dir_all(&test_dir);
+    }
+    
+    #[test]
+    fn test_move_balance() {
+        let runtime = Runtime::new();
+        let old_address = "0x0000000000000000000000000000000000007685";
+        let new_address = "0xAbCdEf0123456789aBcDeF0123456789AbCdEf01";
+        runtime.create_account(old_address).unwrap();
+        runtime.create_account(new_address).unwrap();
+        runtime.credit_balance(old_address, 500).unwrap();
+        runtime.credit_balance(new_address, 20).unwrap();
+        
+        assert_eq!(runtime.move_balance(old_address, new_address).unwrap(), 500);
+        assert_eq!(runtime.get_balance(old_address), 0);
+        assert_eq!(runtime.get_balance(new_address), 520);
+        assert_eq!(runtime.get_fee_pool(), 0);
+        
+        assert!(runtime.move_balance("0x9999999999999999999999999999999999999999", new_address).is_err());
+        assert!(runtime.move_balance(new_address, "0x9999999999999999999999999999999999999999").is_err());
+        assert_eq!(runtime.get_balance(new_address), 520);
+    }
+    
+    #[test]
+    fn test_transfer_respects_account_limits() {
+        let runtime = Runtime::new();
+        let sender = "0x1111111111111111111111111111111111111111";
+        let recipient = "0x2222222222222222222222222222222222222222";
+        runtime.create_account(sender).unwrap();
+        runtime.create_account(recipient).unwrap();
+        runtime.credit_balance(sender, 10_000).unwrap();
+        
+        let start = 1_735_689_600;
+        let limits = AccountLimits { max_transaction_amount: Some(500), daily_outflow_limit: Some(1_010) };
+        assert_eq!(runtime.set_account_limits_at(sender, limits, start).unwrap(), None);
+        
+        // Per-transaction maximum
+        assert!(runtime.transfer_with_fee_at(sender, recipient, 501, start).is_err());
+        
+        // Daily outflow includes the fee: 2 * (500 + 5) fills the window exactly
+        runtime.transfer_with_fee_at(sender, recipient, 500, start).unwrap();
+        runtime.transfer_with_fee_at(sender, recipient, 500, start + 60).unwrap();
+        assert!(runtime.transfer_with_fee_at(sender, recipient, 1, start + 120).is_err());
+        assert_eq!(runtime.get_balance(sender), 10_000 - 1_010);
+        
+        // The first transfer leaves the window after 24 hours
+        assert!(runtime.transfer_with_fee_at(sender, recipient, 400, start + limits::OUTFLOW_WINDOW_SECS - 1).is_err());
+        runtime.transfer_with_fee_at(sender, recipient, 400, start + limits::OUTFLOW_WINDOW_SECS).unwrap();
+        
+        // Accounts without limits are unaffected
+        runtime.transfer_with_fee_at(recipient, sender, 900, start).unwrap();
+        assert!(runtime.set_account_limits("0x9999999999999999999999999999999999999999", limits).is_err());
+    }
+
+    #[test]
+    fn test_fee_change_applies_at_activation_block() {
+        let runtime = Runtime::new();
+        let sender = "0x1111111111111111111111111111111111111111";
+        let recipient = "0x2222222222222222222222222222222222222222";
+        runtime.create_account(sender).unwrap();
+        runtime.create_account(recipient).unwrap();
+        runtime.credit_balance(sender, 1_000_000).unwrap();
+
+        let activation = params::MIN_ACTIVATION_DELAY_BLOCKS + 1;
+        let change = |param, new_value| PendingParamChange { param, new_value, activates_at_block: activation };
+        runtime.schedule_param_change(change(params::ChainParam::FeeBps, 500)).unwrap();
+        runtime.schedule_param_change(change(params::ChainParam::FeePoolShareBps, 6_000)).unwrap();
+        assert_eq!(runtime.param_schedule().pending.len(), 2);
+
+        // Every block before the activation block still charges 1%
+        for height in 1..activation {
+            assert!(runtime.apply_param_changes(height).is_empty());
+            let pool_before = runtime.get_fee_pool();
+            runtime.transfer_with_fee(sender, recipient, 1_000).unwrap();
+            assert_eq!(runtime.get_fee_pool() - pool_before, 10, "fee changed early at block {}", height);
+        }
+
+        // From the activation block on, 5% is charged and 40% of it burned
+        assert_eq!(runtime.apply_param_changes(activation).len(), 2);
+        let balance_before = runtime.get_balance(sender);
+        let pool_before = runtime.get_fee_pool();
+        let supply_before = runtime.get_total_supply();
+        runtime.transfer_with_fee(sender, recipient, 1_000).unwrap();
+        assert_eq!(balance_before - runtime.get_balance(sender), 1_050);
+        assert_eq!(runtime.get_fee_pool() - pool_before, 30);
+        assert_eq!(runtime.get_total_supply(), supply_before - 20);
+        assert!(runtime.param_schedule().pending.is_empty());
+    }
+
+    #[test]
+    fn test_exact_receive_credits_the_requested_amount_at_fee_boundaries() {
+        let runtime = Runtime::new();
+        let sender = "0x1111111111111111111111111111111111111111";
+        let recipient = "0x2222222222222222222222222222222222222222";
+        runtime.create_account(sender).unwrap();
+        runtime.create_account(recipient).unwrap();
+        runtime.credit_balance(sender, 100_000).unwrap();
+
+        let check = |receive: u64, expected_fee: u64| {
+            let sender_before = runtime.get_balance(sender);
+            let recipient_before = runtime.get_balance(recipient);
+            let amounts = runtime.transfer_exact_receive(sender, recipient, receive).unwrap();
+            assert_eq!(amounts, TransferAmounts {
+                mode: TransferMode::ExactReceive,
+                received: receive,
+                fee: expected_fee,
+                debited: receive + expected_fee,
+            });
+            assert_eq!(runtime.get_balance(recipient) - recipient_before, receive);
+            assert_eq!(sender_before - runtime.get_balance(sender), amounts.debited);
+        };
+
+        // At 1% the fee rounds to the nearest token, so it steps up at 50, 150, 250 and so on
+        for (receive, fee) in [(1, 0), (49, 0), (50, 1), (149, 1), (150, 2), (200, 2)] {
+            check(receive, fee);
+        }
+
+        // At 0.25% the fee steps up 200 past every 400, and below 600 the minimum fee of 1 applies
+        let activation = params::MIN_ACTIVATION_DELAY_BLOCKS;
+        let change = |param, new_value| PendingParamChange { param, new_value, activates_at_block: activation };
+        runtime.schedule_param_change(change(params::ChainParam::FeeBps, 25)).unwrap();
+        runtime.schedule_param_change(change(params::ChainParam::MinimumFee, 1)).unwrap();
+        runtime.apply_param_changes(activation);
+        for (receive, fee) in [(1, 1), (199, 1), (200, 1), (599, 1), (600, 2), (999, 2), (1_000, 3)] {
+            check(receive, fee);
+        }
+
+        // A balance one short of the amount plus the fee is refused and nothing moves
+        let poor = "0x3333333333333333333333333333333333333333";
+        runtime.create_account(poor).unwrap();
+        runtime.credit_balance(poor, 801).unwrap();
+        assert!(runtime.transfer_exact_receive(poor, recipient, 800).is_err());
+        assert_eq!(runtime.get_balance(poor), 801);
+        runtime.credit_balance(poor, 1).unwrap();
+        assert_eq!(runtime.transfer_exact_receive(poor, recipient, 800).unwrap().debited, 802);
+        assert_eq!(runtime.get_balance(poor), 0);
+    }
+
+    #[test]
+    fn test_restore_latest_checkpoint_into_a_new_runtime() {
+        let test_dir = format!("./test_checkpoints_restore_{}", std::process::id());
+        let _ = std::fs::remove_dir_all(&test_dir);
+        
+        let alice = "0x1111111111111111111111111111111111111111";
+        let bob = "0x2222222222222222222222222222222222222222";
+        let runtime = Runtime::with_checkpoint_config(5, &test_dir).unwrap();
+        runtime.create_account(alice).unwrap();
+        runtime.create_account(bob).unwrap();
+        runtime.credit_balance(alice, 1_000).unwrap();
+        runtime.transfer_with_fee(alice, bob, 300).unwrap();
+        let checkpoint = runtime.create_checkpoint(true).unwrap();
+        
+        // A truncated file that sorts as newest, and files that are no checkpoints, are skipped
+        std::fs::write(format!("{}/checkpoint_9999999999.dat", test_dir), b"UBICKPT\0\x05\x00trunc").unwrap();
+        std::fs::write(format!("{}/notes.txt", test_dir), b"not a checkpoint").unwrap();
+        
+        let restarted = Runtime::with_checkpoint_config(5, &test_dir).unwrap();
+        assert_eq!(restarted.get_balance(alice), 0);
+        let restored = restarted.restore_latest().unwrap().unwrap();
+        assert_eq!(restored.timestamp, checkpoint.timestamp);
+        assert_eq!(restored.root_hash, checkpoint.root_hash);
+        assert_eq!(restarted.get_balance(alice), runtime.get_balance(alice));
+        assert_eq!(restarted.get_balance(bob), 300);
+        assert_eq!(restarted.get_total_supply(), runtime.get_total_supply());
+        assert_eq!(restarted.get_fee_pool(), runtime.get_fee_pool());
+        assert_eq!(restarted.state_root(), runtime.state_root());
+        assert_eq!(restarted.list_checkpoints().len(), 1);
+        
+        // Nothing to restore from an empty directory
+        let empty_dir = format!("{}_empty", test_dir);
+        let _ = std::fs::remove_dir_all(&empty_dir);
+        assert!(Runtime::with_checkpoint_config(5, &empty_dir).unwrap().restore_latest().unwrap().is_none());
+        
+        let _ = std::fs::remove_dir_all(&test_dir);
+        let _ = std::fs::remove_dir_all(&empty_dir);
+    }
+
+    #[test]
+    fn test_migrate_v1_checkpoint_in_place() {
+        let test_dir = format!("./test_checkpoints_migrate_{}", std::process::id());
+        let _ = std::fs::remove_dir_all(&test_dir);
+        std::fs::create_dir_all(&test_dir).unwrap();
+        let file_path = format!("{}/checkpoint_1735689600.dat", test_dir);
+        std::fs::write(&file_path, include_bytes!("../fixtures/checkpoint_v1.dat")).unwrap();
+
+        // The runtime reads the file directly only once it is upgraded
+        let runtime = Runtime::with_checkpoint_config(5, &test_dir).unwrap();
+        assert!(runtime.scan_checkpoints().unwrap().is_empty());
+
+        let migrated = runtime.migrate_checkpoint(&file_path).unwrap();
+        assert_eq!(migrated.timestamp, 1_735_689_600);
+        assert_eq!(migrated.account_count, 2);
+        assert_eq!(migrated.total_supply, 600);
+        assert_eq!(runtime.get_balance("0x1111111111111111111111111111111111111111"), 0);
+
+        let (version, _) = checkpoint::decode_checkpoint(&std::fs::read(&file_path).unwrap()).unwrap();
+        assert_eq!(version, checkpoint::CURRENT_CHECKPOINT_VERSION);
+        runtime.load_checkpoint(&migrated).unwrap();
+        // The verified account has streamed UBI since the fixture was written
+        let back_pay = runtime.pending_ubi("0x1111111111111111111111111111111111111111");
+        assert_eq!(runtime.get_balance("0x1111111111111111111111111111111111111111"), 100 + back_pay);
+        assert_eq!(runtime.get_balance("0x2222222222222222222222222222222222222222"), 500);
+        assert_eq!(runtime.get_unclaimed_dividends("0x1111111111111111111111111111111111111111"), 7);
+        assert_eq!(runtime.get_fee_pool(), 3);
+
+        // Migrating again rewrites the same state, and a file that is no checkpoint is left alone
+        assert_eq!(runtime.migrate_checkpoint(&file_path).unwrap().root_hash, migrated.root_hash);
+        let garbage = format!("{}/checkpoint_1.dat", test_dir);
+        std::fs::write(&garbage, b"UBICKPT\0\x0f\x00").unwrap();
+        assert!(runtime.migrate_checkpoint(&garbage).unwrap_err().to_string().contains("Unsupported checkpoint version 15"));
+        assert_eq!(std::fs::read(&garbage).unwrap(), b"UBICKPT\0\x0f\x00");
+
+        let _ = std::fs::remove_dir_all(&test_dir);
+    }
+
+    #[test]
+    fn test_compressed_checkpoint_with_many_accounts() {
+        let test_dir = format!("./test_checkpoints_compressed_{}", std::process::id());
+        let _ = std::fs::remove_dir_all(&test_dir);
+
+        let runtime = Runtime::with_checkpoint_config(5, &test_dir).unwrap().with_checkpoint_compression(true);
+        let addresses: Vec<String> = (0..5_000).map(|i| format!("0x{:040x}", 0x1111_0000_0000u64 + i)).collect();
+        for (i, address) in addresses.iter().enumerate() {
+            runtime.create_account(address).unwrap();
+            runtime.credit_balance(address, 1_000 + i as u64).unwrap();
+            if i % 3 == 0 {
+                runtime.verify_account(address);
+            }
+        }
+        let checkpoint = runtime.create_checkpoint(true).unwrap();
+        assert_eq!(checkpoint.account_count, 5_000);
+
+        let bytes = std::fs::read(&checkpoint.file_path).unwrap();
+        let (_, data) = checkpoint::decode_checkpoint(&bytes).unwrap();
+        assert!(bytes.len() < checkpoint::encode_checkpoint(&data).len() / 2, "{} bytes", bytes.len());
+
+        // A runtime that writes uncompressed checkpoints still reads it
+        let restarted = Runtime::with_checkpoint_config(5, &test_dir).unwrap();
+        assert_eq!(restarted.restore_latest().unwrap().unwrap().root_hash, checkpoint.root_hash);
+        for address in [&addresses[0], &addresses[2_500], &addresses[4_999]] {
+            assert_eq!(restarted.get_balance(address), runtime.get_balance(address));
+            assert_eq!(restarted.is_account_verified(address), runtime.is_account_verified(address));
+        }
+        assert_eq!(restarted.get_total_supply(), runtime.get_total_supply());
+        assert_eq!(restarted.state_root(), runtime.state_root());
+
+        let _ = std::fs::remove_dir_all(&test_dir);
+    }
+
+    #[test]
+    fn test_state_json_round_trip_keeps_the_state_root() {
+        let path = std::env::temp_dir().join(format!("ubi_state_snapshot_{}.json", std::process::id()));
+        let alice = "0x1111111111111111111111111111111111111111";
+        let bob = "0x2222222222222222222222222222222222222222";
+
+        let runtime = Runtime::new();
+        runtime.create_account(alice).unwrap();
+        runtime.create_account(bob).unwrap();
+        runtime.verify_account(alice);
+        runtime.credit_balance(alice, 10_000).unwrap();
+        runtime.transfer_with_fee(alice, bob, 1_000).unwrap();
+        runtime.set_account_limits(alice, AccountLimits { max_transaction_amount: Some(5_000), daily_outflow_limit: None }).unwrap();
+        let exported = runtime.export_state_json(&path).unwrap();
+        assert_eq!(exported.accounts.len(), 2);
+
+        let imported = Runtime::new();
+        assert_eq!(imported.import_state_json(&path).unwrap(), exported);
+        assert_eq!(imported.state_root(), runtime.state_root());
+        assert_eq!(imported.get_balance(bob), runtime.get_balance(bob));
+        assert_eq!(imported.get_total_supply(), runtime.get_total_supply());
+        assert_eq!(imported.get_fee_pool(), runtime.get_fee_pool());
+        assert!(imported.is_account_verified(alice));
+
+        // Exporting the imported state gives back the same document
+        assert_eq!(imported.state_snapshot().to_json(), std::fs::read_to_string(&path).unwrap());
+
+        // A refused snapshot leaves the state untouched
+        let mut duplicated = exported.clone();
+        duplicated.accounts.push(duplicated.accounts[0].clone());
+        let error = imported.import_state_snapshot(&duplicated).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("more than once"), "{}", error);
+        assert_eq!(imported.state_root(), runtime.state_root());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_diff_between_checkpoints() {
+        let test_dir = format!("./test_checkpoints_diff_{}", std::process::id());
+        let _ = std::fs::remove_dir_all(&test_dir);
+
+        let alice = "0x1111111111111111111111111111111111111111";
+        let bob = "0x2222222222222222222222222222222222222222";
+        let carol = "0x3333333333333333333333333333333333333333";
+        let dave = "0x4444444444444444444444444444444444444444";
+        let runtime = Runtime::with_checkpoint_config(5, &test_dir).unwrap();
+        for address in [alice, bob, carol] {
+            runtime.create_account(address).unwrap();
+        }
+        runtime.credit_balance(alice, 1_000).unwrap();
+        runtime.credit_balance(carol, 50).unwrap();
+        let first = runtime.create_checkpoint(true).unwrap();
+
+        // Checkpoint files are named by the second they were written in
+        std::thread::sleep(std::time::Duration::from_millis(1_100));
+        runtime.transfer_with_fee(alice, bob, 300).unwrap();
+        runtime.verify_account(carol);
+        runtime.create_account(dave).unwrap();
+        runtime.credit_balance(dave, 20).unwrap();
+        let second = runtime.create_checkpoint(true).unwrap();
+        let live_root = runtime.state_root();
+
+        let diff = runtime.diff_checkpoints(&first, &second).unwrap();
+        assert_eq!(diff.created, vec![state_diff::DiffAccount { address: dave.to_string(), balance: 20, verified: false }]);
+        assert!(diff.removed.is_empty());
+        let changed: Vec<(&str, i128, bool, bool)> = diff.changed.iter()
+            .map(|change| (change.address.as_str(), change.balance.delta, change.verified_before, change.verified_after))
+            .collect();
+        // Alice also paid the 1% fee, which went to the fee pool
+        assert_eq!(changed, vec![(alice, -303, false, false), (bob, 300, false, false), (carol, 0, false, true)]);
+        assert_eq!((diff.total_supply.before, diff.total_supply.after, diff.total_supply.delta), (1_050, 1_070, 20));
+        assert_eq!((diff.fee_pool.before, diff.fee_pool.delta), (0, runtime.get_fee_pool() as i128));
+
+        // The other direction removes the account again, and nothing live is touched
+        let reverse = runtime.diff_checkpoints(&second, &first).unwrap();
+        assert_eq!(reverse.removed, diff.created);
+        assert!(runtime.diff_checkpoints(&second, &second).unwrap().is_empty());
+        assert_eq!(runtime.state_root(), live_root);
+
+        let _ = std::fs::remove_dir_all(&test_dir);
+    }
+
+    #[test]
+    fn test_corrupted_checkpoint_leaves_state_untouched() {
+        let test_dir = format!("./test_checkpoints_corrupted_{}", std::process::id());
+        let _ = std::fs::remove_dir_all(&test_dir);
+
+        let alice = "0x1111111111111111111111111111111111111111";
+        let runtime = Runtime::with_checkpoint_config(5, &test_dir).unwrap();
+        runtime.create_account(alice).unwrap();
+        runtime.credit_balance(alice, 1_000).unwrap();
+        let checkpoint = runtime.create_checkpoint(true).unwrap();
+
+        // No temporary file is left next to the checkpoint
+        let files: Vec<_> = std::fs::read_dir(&test_dir).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+        assert_eq!(files.len(), 1, "{:?}", files);
+
+        // Flip one bit of a stored amount on disk
+        let mut bytes = std::fs::read(&checkpoint.file_path).unwrap();
+        let balance = bytes.windows(8).position(|window| window == 1_000u64.to_le_bytes()).unwrap();
+        bytes[balance] ^= 0x01;
+        std::fs::write(&checkpoint.file_path, &bytes).unwrap();
+
+        runtime.credit_balance(alice, 500).unwrap();
+        let state_root = runtime.state_root();
+        let error = runtime.load_checkpoint(&checkpoint).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.get_ref().unwrap().is::<checkpoint::ChecksumMismatch>(), "{}", error);
+        assert_eq!(runtime.get_balance(alice), 1_500);
+        assert_eq!(runtime.state_root(), state_root);
+
+        let _ = std::fs::remove_dir_all(&test_dir);
+    }
+
+    #[test]
+    fn test_checkpoint_creation_and_loading() {
+        // Use a unique directory for this test to avoid conflicts
+        let test_dir = format!("./test_checkpoints_{}", std::process::id());
+        
         // Clean up any existing test directory
         let _ = std::fs::remove_dir_all(&test_dir);
         
         // Create a runtime with custom checkpoint config
-        let runtime = Runtime::with_checkpoint_config(5, &test_dir);
+        let runtime = Runtime::with_checkpoint_config(5, &test_dir).unwrap();
         
         // Create some accounts
         let accounts = [
@@ -421,7 +1901,7 @@ mod tests {
         
         // Set up account balances
         {
-            let mut accounts_map = runtime.accounts.lock().unwrap();
+            let mut accounts_map = runtime.accounts.write().unwrap();
             
             if let Some(account) = accounts_map.get_mut(accounts[0]) {
                 account.balance = 100;
@@ -444,7 +1924,7 @@ mod tests {
         
         // Modify the state
         {
-            let mut accounts_map = runtime.accounts.lock().unwrap();
+            let mut accounts_map = runtime.accounts.write().unwrap();
             
             if let Some(account) = accounts_map.get_mut(accounts[0]) {
                 account.balance = 999; // Changed
@@ -466,15 +1946,726 @@ mod tests {
         // Clean up test files
         let _ = std::fs::remove_dir_all(&test_dir);
     }
+
+    #[test]
+    fn test_load_checkpoint_rejects_unrepresentable_times() {
+        let test_dir = format!("./test_checkpoint_times_{}", std::process::id());
+        let _ = std::fs::remove_dir_all(&test_dir);
+        std::fs::create_dir_all(&test_dir).unwrap();
+        let runtime = Runtime::new();
+        let address = "0x1234567890abcdef1234567890abcdef12345678";
+        runtime.create_account(address).unwrap();
+        runtime.credit_balance(address, 10).unwrap();
+        
+        let data = CheckpointData {
+            timestamp: 1_735_689_600,
+            root_hash: [7; 32],
+            total_supply: 5,
+            fee_pool: 0,
+            dividend_per_token: 0,
+            accounts: vec![CheckpointAccount {
+                address: address.to_string(),
+                balance: 5,
+                verified: true,
+                last_ubi_claim_secs: u64::MAX,
+                last_dividend_point: 0,
+                unclaimed_dividends: 0,
+                limits: None,
+                verified_at_secs: None,
+                nonce: 0,
+                frozen: false,
+                frozen_reason: None,
+                locks: Vec::new(),
+                auto_compound: false,
+                ubi_stats: UbiStats::default(),
+            }],
+            fee_ledger: Vec::new(),
+            streams: Vec::new(),
+            next_stream_id: 0,
+            allowances: Vec::new(),
+            network_stats: NetworkStats::default(),
+        };
+        let file_path = format!("{}/checkpoint_1.dat", test_dir);
+        checkpoint::write_checkpoint_file(Path::new(&file_path), &checkpoint::encode_checkpoint(&data)).unwrap();
+        let checkpoint = StateCheckpoint {
+            timestamp: data.timestamp,
+            root_hash: data.root_hash,
+            account_count: 1,
+            total_supply: data.total_supply,
+            fee_pool: 0,
+            file_path,
+        };
+        
+        // Refused without panicking, and without touching the state
+        let error = runtime.load_checkpoint(&checkpoint).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(runtime.get_balance(address), 10);
+        
+        let _ = std::fs::remove_dir_all(&test_dir);
+    }
+
+    #[test]
+    fn test_checkpoint_is_point_in_time_during_transfers() {
+        let test_dir = format!("./test_checkpoints_consistent_{}", std::process::id());
+        let _ = std::fs::remove_dir_all(&test_dir);
+        let runtime = Runtime::with_checkpoint_config(5, &test_dir).unwrap();
+        
+        let addresses: Vec<String> = (1..=8).map(|i| format!("0x{:040x}", i)).collect();
+        for address in &addresses {
+            runtime.create_account(address).unwrap();
+            runtime.credit_balance(address, 1_000_000).unwrap();
+        }
+        
+        // Transfers move balances into the fee pool and burn part of the fee while checkpoints run
+        let stop = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let workers: Vec<_> = (0..4).map(|worker| {
+            let runtime = runtime.clone();
+            let addresses = addresses.clone();
+            let stop = stop.clone();
+            std::thread::spawn(move || {
+                let mut i = worker;
+                while !stop.load(std::sync::atomic::Ordering::Relaxed) {
+                    let from = &addresses[i % addresses.len()];
+                    let to = &addresses[(i + 3) % addresses.len()];
+                    let _ = runtime.transfer_with_fee(from, to, 10);
+                    if i % 7 == 0 {
+                        let _ = runtime.credit_balance(to, 1);
+                    }
+                    i += 1;
+                }
+            })
+        }).collect();
+        
+        for _ in 0..20 {
+            let checkpoint = runtime.create_checkpoint(true).unwrap();
+            let (_, data) = checkpoint::decode_checkpoint(&fs::read(&checkpoint.file_path).unwrap()).unwrap();
+            let balances: u64 = data.accounts.iter().map(|account| account.balance).sum();
+            assert_eq!(balances + data.fee_pool, data.total_supply);
+            assert_eq!(checkpoint.total_supply, data.total_supply);
+        }
+        
+        stop.store(true, std::sync::atomic::Ordering::Relaxed);
+        for worker in workers {
+            worker.join().unwrap();
+        }
+        let _ = std::fs::remove_dir_all(&test_dir);
+    }
+
+    #[test]
+    fn test_supply_report_and_state_root_recomputation() {
+        let runtime = Runtime::new();
+        let addresses: Vec<String> = (1..=3).map(|i| format!("0x{:040x}", i)).collect();
+        for address in &addresses {
+            runtime.create_account(address).unwrap();
+            runtime.credit_balance(address, 10_000).unwrap();
+        }
+        runtime.transfer_with_fee(&addresses[0], &addresses[1], 1_000).unwrap();
+        runtime.distribute_fees();
+        runtime.claim_dividends(&addresses[1]).unwrap();
+        
+        let report = runtime.supply_report();
+        assert_eq!(report.account_count, 3);
+        assert_eq!(report.total_supply, runtime.get_total_supply());
+        assert!((0..=3).contains(&report.unaccounted), "unexpected unaccounted supply: {:?}", report);
+        
+        let check = runtime.recompute_state_root();
+        assert!(check.matches);
+        assert_eq!(check.leaf_count, 3);
+        
+        // Exported accounts keep their leaf in the incremental tree
+        runtime.export_account(&addresses[2], 1).unwrap();
+        let check = runtime.recompute_state_root();
+        assert!(!check.matches);
+        assert_eq!((check.leaf_count, check.account_count), (3, 2));
+    }
+
+    #[test]
+    fn test_fee_ledger_reconciles_with_pool() {
+        let test_dir = format!("./test_fee_ledger_{}", std::process::id());
+        let _ = std::fs::remove_dir_all(&test_dir);
+        let runtime = Runtime::with_checkpoint_config(1000, &test_dir).unwrap();
+        let addresses: Vec<String> = (1..=2).map(|i| format!("0x{:040x}", i)).collect();
+        for address in &addresses {
+            runtime.create_account(address).unwrap();
+            runtime.credit_balance(address, 100_000).unwrap();
+        }
+        // Burn half of every fee
+        runtime.param_schedule.lock().unwrap().current.fee_pool_share_bps = params::BPS_DENOMINATOR / 2;
+        
+        runtime.transfer_transaction("0xabc", &addresses[0], &addresses[1], 10_000).unwrap();
+        runtime.transfer_with_fee(&addresses[1], &addresses[0], 5_000).unwrap();
+        let collected = runtime.get_fee_pool();
+        runtime.distribute_fees();
+        runtime.transfer_with_fee(&addresses[0], &addresses[1], 2_000).unwrap();
+        
+        let entries = runtime.fee_ledger_entries(0, 0, u64::MAX, 100);
+        let kinds: Vec<&FeeLedgerKind> = entries.iter().map(|entry| &entry.kind).collect();
+        assert_eq!(kinds[0], &FeeLedgerKind::Collected { tx_hash: Some("0xabc".to_string()) });
+        assert_eq!(kinds[1], &FeeLedgerKind::Burned { tx_hash: Some("0xabc".to_string()) });
+        assert_eq!(entries[0].amount, entries[1].amount);
+        assert!(entries.iter().any(|entry| {
+            matches!(entry.kind, FeeLedgerKind::Distributed { .. }) && entry.amount == collected
+        }));
+        assert_eq!(fee_ledger::ledger_net(&entries), runtime.get_fee_pool() as i128);
+        assert_eq!(runtime.supply_report().fee_ledger_discrepancy, 0);
+        
+        // The ledger survives a checkpoint round trip
+        let checkpoint = runtime.create_checkpoint(true).unwrap();
+        let restored = Runtime::with_checkpoint_config(1000, &test_dir).unwrap();
+        restored.load_checkpoint(&checkpoint).unwrap();
+        assert_eq!(restored.fee_ledger_entries(0, 0, u64::MAX, 100), entries);
+        assert_eq!(restored.supply_report().fee_ledger_discrepancy, 0);
+        
+        let _ = std::fs::remove_dir_all(&test_dir);
+    }
+
+    #[test]
+    fn test_statement_replays_to_the_current_balance() {
+        let runtime = Runtime::new();
+        let created = current_time_secs();
+        let addresses: Vec<String> = (1..=3).map(|i| format!("0x{:040x}", i)).collect();
+        for address in &addresses {
+            runtime.create_account(address).unwrap();
+        }
+        let account = &addresses[0];
+        runtime.credit_balance(account, 100_000).unwrap();
+        runtime.credit_balance(&addresses[1], 100_000).unwrap();
+        runtime.verify_account_at(account, created);
+        runtime.update_ubi_balance_at(account, created + 3 * 3600).unwrap();
+        runtime.transfer_transaction("0xabc", account, &addresses[1], 10_000).unwrap();
+        runtime.transfer_with_fee(&addresses[1], account, 20_000).unwrap();
+        runtime.distribute_fees();
+        runtime.claim_dividends(account).unwrap();
+        runtime.burn(account, 500).unwrap();
+        runtime.credit_block_reward(account, 50, "0x1").unwrap();
+        runtime.revert_block_reward("0x1").unwrap();
+        
+        // Replaying every row from zero reaches each row's balance and the current one
+        let statement = runtime.account_statement(&account.to_uppercase().replace("0X", "0x"), 0, u64::MAX);
+        let mut balance = 0;
+        for entry in &statement.entries {
+            balance = balance + entry.credit - entry.debit;
+            assert_eq!(balance, entry.balance_after, "{:?}", entry);
+        }
+        assert_eq!(balance, runtime.get_balance(account));
+        assert_eq!(statement.closing_balance, balance);
+        
+        let kinds: Vec<&str> = statement.entries.iter().map(|entry| entry.kind.name()).collect();
+        for kind in ["credit", "ubi", "transfer_out", "transfer_in", "burn", "block_reward", "block_reward_reverted"] {
+            assert!(kinds.contains(&kind), "missing {} in {:?}", kind, kinds);
+        }
+        let sent = statement.entries.iter().find(|entry| entry.kind.name() == "transfer_out").unwrap();
+        assert_eq!(sent.kind, BalanceChangeKind::TransferOut {
+            counterparty: addresses[1].clone(),
+            fee: 100,
+            tx_hash: Some("0xabc".to_string()),
+        });
+        assert_eq!(sent.debit, 10_100);
+        
+        // The balance moves out entirely and the statement closes at zero
+        runtime.move_balance(account, &addresses[2]).unwrap();
+        let statement = runtime.account_statement(account, 0, u64::MAX);
+        assert_eq!(statement.closing_balance, 0);
+        let moved = runtime.account_statement(&addresses[2], 0, u64::MAX);
+        assert_eq!(moved.closing_balance, runtime.get_balance(&addresses[2]));
+        
+        // A range opens at the balance left before it, and the CSV has a row per change
+        let later = runtime.account_statement(account, created + 4 * 3600, u64::MAX);
+        assert!(later.entries.is_empty());
+        assert_eq!((later.opening_balance, later.closing_balance), (0, 0));
+        let csv = statement.to_csv();
+        assert_eq!(csv.lines().count(), statement.entries.len() + 1);
+        assert!(csv.starts_with(statement::CSV_HEADER));
+    }
+
+    #[test]
+    fn test_block_rewards_are_reverted_by_block_hash() {
+        let runtime = Runtime::new();
+        let producer = "0x00000000000000000000000000000000000000aa";
+        runtime.create_account(producer).unwrap();
+        let supply = runtime.get_total_supply();
+        runtime.credit_block_reward(producer, 100, "0x1").unwrap();
+        runtime.credit_block_reward(producer, 100, "0x2").unwrap();
+        assert_eq!(runtime.get_total_supply(), supply + 200);
+        
+        let event = runtime.revert_block_reward("0x2").unwrap();
+        assert_eq!((event.kind, event.amount, event.total_supply_after), (SupplyEventKind::BlockRewardReverted, 100, supply + 100));
+        assert_eq!(runtime.get_balance(producer), 100);
+        assert!(runtime.revert_block_reward("0x2").is_none());
+        assert!(runtime.revert_block_reward("0x9").is_none());
+        
+        // A spent reward is taken back only as far as the balance allows
+        runtime.burn(producer, 60).unwrap();
+        assert_eq!(runtime.revert_block_reward("0x1").unwrap().amount, 40);
+        assert_eq!((runtime.get_balance(producer), runtime.get_total_supply()), (0, supply));
+    }
+
+    #[test]
+    fn test_burns_reduce_supply_and_are_audited() {
+        let runtime = Runtime::new();
+        let addresses: Vec<String> = (1..=2).map(|i| format!("0x{:040x}", i)).collect();
+        for address in &addresses {
+            runtime.create_account(address).unwrap();
+            runtime.credit_balance(address, 10_000).unwrap();
+        }
+        let supply_before = runtime.get_total_supply();
+        
+        let event = runtime.burn(&addresses[0], 1_000).unwrap();
+        assert_eq!(event.kind, SupplyEventKind::Burned);
+        assert_eq!(event.total_supply_after, supply_before - 1_000);
+        assert_eq!(runtime.burn_count(&addresses[0]), 1);
+        
+        // A transfer to the burn address is burned without a fee, not credited
+        let balance_before = runtime.get_balance(&addresses[1]);
+        runtime.transfer_transaction("0xdead", &addresses[1], DEFAULT_BURN_ADDRESS, 500).unwrap();
+        assert_eq!(runtime.get_balance(&addresses[1]), balance_before - 500);
+        assert_eq!(runtime.get_total_supply(), supply_before - 1_500);
+        assert!(runtime.create_account(DEFAULT_BURN_ADDRESS).is_err());
+        assert_eq!(runtime.get_balance(DEFAULT_BURN_ADDRESS), 0);
+        assert_eq!(runtime.supply_events().last().unwrap().tx_hash.as_deref(), Some("0xdead"));
+        
+        // Burning more than the balance changes nothing
+        assert!(runtime.burn(&addresses[0], 1_000_000).is_err());
+        assert!(runtime.simulate_transfer(&addresses[0], DEFAULT_BURN_ADDRESS, 1_000_000).is_err());
+        assert_eq!(runtime.get_total_supply(), supply_before - 1_500);
+        
+        let report = runtime.supply_report();
+        assert_eq!(report.total_burned, 1_500);
+        assert_eq!(report.unaccounted, 0, "unexpected unaccounted supply: {:?}", report);
+    }
+
+    #[test]
+    fn test_maintenance_mode_stops_mutations_mid_stream() {
+        let runtime = Runtime::new();
+        let addresses: Vec<String> = (1..=6).map(|i| format!("0x{:040x}", i)).collect();
+        for address in &addresses {
+            runtime.create_account(address).unwrap();
+            runtime.credit_balance(address, 1_000_000).unwrap();
+        }
+        let balances = |runtime: &Runtime| -> Vec<u64> {
+            addresses.iter().map(|address| runtime.get_balance(address)).collect()
+        };
+
+        let stop = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let workers: Vec<_> = (0..4).map(|worker| {
+            let runtime = runtime.clone();
+            let addresses = addresses.clone();
+            let stop = stop.clone();
+            std::thread::spawn(move || {
+                let mut i = worker;
+                while !stop.load(std::sync::atomic::Ordering::Relaxed) {
+                    let from = &addresses[i % addresses.len()];
+                    let to = &addresses[(i + 1) % addresses.len()];
+                    let _ = runtime.transfer_with_fee(from, to, 10);
+                    let _ = runtime.burn(from, 1);
+                    i += 1;
+                }
+            })
+        }).collect();
+
+        for round in 0..5 {
+            let status = runtime.set_maintenance(true, Some(format!("round {}", round)), Some(addresses[0].clone()));
+            assert!(status.enabled);
+
+            // Nothing moves while the mode is on, however the workers interleave
+            let supply = runtime.get_total_supply();
+            let before = balances(&runtime);
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            assert_eq!(runtime.distribute_fees(), 0);
+            assert_eq!(runtime.get_total_supply(), supply);
+            assert_eq!(balances(&runtime), before);
+            assert!(matches!(runtime.transfer_with_fee(&addresses[0], &addresses[1], 10), Err(AccountError::Maintenance(_))));
+            assert!(matches!(runtime.credit_balance(&addresses[0], 10), Err(AccountError::Maintenance(_))));
+            assert!(runtime.create_account("0x00000000000000000000000000000000000000ff").is_err());
+
+            // Turning it off resumes the stream
+            assert!(!runtime.set_maintenance(false, None, None).enabled);
+            assert!(runtime.transfer_with_fee(&addresses[0], &addresses[1], 10).is_ok());
+        }
+
+        stop.store(true, std::sync::atomic::Ordering::Relaxed);
+        for worker in workers {
+            worker.join().unwrap();
+        }
+        assert_eq!(runtime.supply_report().unaccounted, 0);
+    }
+
+    #[test]
+    fn test_genesis_faucet_and_bootstrap_window() {
+        let test_dir = format!("./test_genesis_{}", std::process::id());
+        let _ = std::fs::remove_dir_all(&test_dir);
+        std::fs::create_dir_all(&test_dir).unwrap();
+        let path = format!("{}/genesis.json", test_dir);
+        let genesis_time = 1_760_000_000;
+        std::fs::write(&path, format!(r#"{{
+            "timestamp": {},
+            "faucet": {{ "address": "node", "initial_balance": 1000000, "per_request_limit": 500, "daily_limit": 2000 }},
+            "ubi": {{ "tokens_per_hour": 3, "max_accrual_hours": 24, "auto_verify": true, "bootstrap_window_days": 7 }},
+            "fees": {{ "fee_bps": 250, "distribution_interval_blocks": 10 }}
+        }}"#, genesis_time)).unwrap();
+        
+        let node = "0xABCDEF0123456789ABCDEF0123456789ABCDEF01";
+        let runtime = Runtime::from_genesis(&GenesisConfig::load(&path).unwrap(), node).unwrap();
+        let faucet = node.to_lowercase();
+        assert_eq!(runtime.genesis_faucet(), Some(faucet.clone()));
+        // Genesis was long ago, so the auto-verified faucet has streamed back pay, capped at genesis
+        assert_eq!(runtime.pending_ubi(&faucet), 3 * 24);
+        assert_eq!(runtime.get_balance(&faucet), 1_000_000 + 3 * 24);
+        assert_eq!(runtime.get_total_supply(), 1_000_000);
+        assert_eq!(runtime.chain_params().ubi_tokens_per_hour, 3);
+        assert_eq!(runtime.chain_params().fee_bps, 250);
+        assert_eq!(runtime.fee_distribution_interval_blocks(), 10);
+        
+        // The faucet pays out right away, within its limits
+        let recipient = "0x1234567890abcdef1234567890abcdef12345678";
+        runtime.create_account_at(recipient, genesis_time + 60).unwrap();
+        runtime.transfer_with_fee_at(&faucet, recipient, 500, genesis_time + 60).unwrap();
+        assert_eq!(runtime.get_balance(recipient), 500 + runtime.pending_ubi(recipient));
+        assert!(runtime.transfer_with_fee_at(&faucet, recipient, 501, genesis_time + 60).is_err());
+        
+        // Accounts are verified on creation until the window closes
+        let ends_at = genesis_time + 7 * genesis::SECS_PER_DAY;
+        assert!(runtime.is_account_verified(recipient));
+        assert_eq!(runtime.bootstrap_status_at(ends_at - 1).map(|status| status.active), Some(true));
+        let late = "0x2234567890abcdef1234567890abcdef12345678";
+        runtime.create_account_at(late, ends_at).unwrap();
+        assert!(!runtime.is_account_verified(late));
+        assert_eq!(runtime.bootstrap_status_at(ends_at).map(|status| status.active), Some(false));
+        
+        // A network without genesis has no window
+        assert_eq!(Runtime::new().bootstrap_status(), None);
+        
+        // Without a timestamp the window would reopen on every restart, so the file is refused
+        std::fs::write(&path, r#"{ "ubi": { "tokens_per_hour": 3, "max_accrual_hours": 24, "auto_verify": true, "bootstrap_window_days": 7 } }"#).unwrap();
+        assert!(matches!(GenesisConfig::load(&path), Err(GenesisError::Parse(_))));
+        
+        let _ = std::fs::remove_dir_all(&test_dir);
+    }
+
+    #[test]
+    fn test_reserved_addresses_cannot_become_accounts() {
+        let genesis: GenesisConfig = serde_json::from_str(r#"{
+            "timestamp": 1760000000,
+            "reserved": [{ "from": "0x00000000000000000000000000000000000a0000", "to": "0x00000000000000000000000000000000000affff", "purpose": "bridges" }]
+        }"#).unwrap();
+        let runtime = Runtime::from_genesis(&genesis, "0x1234567890abcdef1234567890abcdef12345678").unwrap();
+        
+        for address in [reserved::ZERO_ADDRESS, DEFAULT_BURN_ADDRESS, ubi_mint::UBI_MINT_ADDRESS, reserved::TOKEN_FACADE_ADDRESS, "0x00000000000000000000000000000000000A0001"] {
+            match runtime.create_account(address) {
+                Err(AccountError::ReservedAddress { address: reserved, .. }) => assert_eq!(reserved, address.to_lowercase()),
+                other => panic!("Expected ReservedAddress for {}, got {:?}", address, other),
+            }
+        }
+        assert_eq!(runtime.reserved_address_purpose("0x00000000000000000000000000000000000a0001").as_deref(), Some("bridges"));
+        assert!(runtime.create_account("0x00000000000000000000000000000000000b0000").is_ok());
+        assert_eq!(runtime.reserved_addresses().len(), 5);
+        
+        // A genesis faucet may not sit in a reserved range, and ranges must be well formed
+        let faucet_in_range: GenesisConfig = serde_json::from_str(r#"{
+            "timestamp": 1760000000,
+            "faucet": { "address": "0x0000000000000000000000000000000000000000", "initial_balance": 1000 }
+        }"#).unwrap();
+        assert!(matches!(Runtime::from_genesis(&faucet_in_range, "node"), Err(GenesisError::InvalidFaucetAddress(_))));
+        let backwards = GenesisConfig {
+            reserved: vec![ReservedRange {
+                from: "0x0000000000000000000000000000000000000002".to_string(),
+                to: "0x0000000000000000000000000000000000000001".to_string(),
+                purpose: "nothing".to_string(),
+            }],
+            ..GenesisConfig::default()
+        };
+        assert!(matches!(Runtime::from_genesis(&backwards, "node"), Err(GenesisError::InvalidReservedRange(_))));
+    }
+
+    #[test]
+    fn test_faucet_tier_limits() {
+        let runtime = Runtime::new();
+        let address = "0x1234567890abcdef1234567890abcdef12345678";
+        let mut tiers = FaucetTier::defaults();
+        tiers[1].codes = vec!["dev-code".to_string()];
+        tiers.push(FaucetTier {
+            name: "proven".to_string(),
+            amount: 5,
+            cooldown_secs: Some(60),
+            requires_code: false,
+            codes: Vec::new(),
+            requires_challenge: true,
+        });
+        runtime.set_faucet_tiers(tiers);
+        let now = 1_760_000_000;
+
+        // Starter: once ever
+        assert_eq!(runtime.claim_faucet_tier_at("starter", address, None, false, now).unwrap().amount, 10);
+        assert_eq!(
+            runtime.claim_faucet_tier_at("starter", address, None, false, now + 365 * 86_400),
+            Err(FaucetError::TierExhausted { tier: "starter".to_string(), retry_after: None })
+        );
+
+        // Developer: needs a code, then once per day
+        assert_eq!(
+            runtime.claim_faucet_tier_at("developer", address, None, false, now),
+            Err(FaucetError::InvalidCode("developer".to_string()))
+        );
+        assert!(runtime.claim_faucet_tier_at("developer", address, Some("guess"), false, now).is_err());
+        assert_eq!(runtime.claim_faucet_tier_at("developer", address, Some("dev-code"), false, now).unwrap().amount, 100);
+        assert_eq!(
+            runtime.claim_faucet_tier_at("developer", address, Some("dev-code"), false, now + 3600),
+            Err(FaucetError::TierExhausted { tier: "developer".to_string(), retry_after: Some(now + 86_400) })
+        );
+        assert!(runtime.claim_faucet_tier_at("developer", address, Some("dev-code"), false, now + 86_400).is_ok());
+        assert_eq!(runtime.faucet_record("developer", address).unwrap().grants, 2);
+
+        // Challenge tiers need a verified challenge; a cancelled claim frees the grant
+        assert_eq!(
+            runtime.claim_faucet_tier_at("proven", address, None, false, now),
+            Err(FaucetError::ChallengeRequired("proven".to_string()))
+        );
+        let claim = runtime.claim_faucet_tier_at("proven", address, None, true, now).unwrap();
+        runtime.cancel_faucet_claim("proven", address, &claim);
+        assert!(runtime.faucet_record("proven", address).is_none());
+        assert!(runtime.claim_faucet_tier_at("proven", address, None, true, now).is_ok());
+
+        assert_eq!(
+            runtime.claim_faucet_tier_at("premium", address, None, true, now),
+            Err(FaucetError::UnknownTier("premium".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_faucet_fails_over_between_sources_under_concurrent_grants() {
+        let missing = format!("0x{:040x}", 0xf0);
+        let funded = [(format!("0x{:040x}", 0xf1), 100), (format!("0x{:040x}", 0xf2), 50), (format!("0x{:040x}", 0xf3), 35)];
+        let mut builder = TestRuntimeBuilder::new().fee_bps(0).minimum_fee(0);
+        for (address, balance) in &funded {
+            builder = builder.funded_account(address, *balance);
+        }
+        let runtime = builder.build();
+        runtime.set_faucet_sources(std::iter::once(&missing).chain(funded.iter().map(|(address, _)| address)).cloned().collect()).unwrap();
+        let sources = runtime.faucet_sources();
+        let recipients: Vec<String> = (1..=8).map(|i| format!("0x{:040x}", 0x100 + i)).collect();
+        for recipient in &recipients {
+            runtime.create_account(recipient).unwrap();
+        }
+
+        // 32 grants of 10 drain the sources, which only hold 18; stats read meanwhile stay consistent
+        let paid = std::sync::atomic::AtomicU64::new(0);
+        std::thread::scope(|scope| {
+            for recipient in &recipients {
+                let (runtime, sources, paid) = (&runtime, &sources, &paid);
+                scope.spawn(move || {
+                    for _ in 0..4 {
+                        if runtime.grant_from_faucet(sources, recipient, 10, None).is_ok() {
+                            paid.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                        }
+                    }
+                });
+            }
+            scope.spawn(|| {
+                for _ in 0..50 {
+                    let stats = runtime.faucet_stats(&sources, 0);
+                    for (source, (_, initial)) in stats.sources[1..].iter().zip(&funded) {
+                        assert_eq!(source.balance + source.totals.tokens_granted, *initial);
+                    }
+                }
+            });
+        });
+
+        let stats = runtime.faucet_stats(&sources, 100);
+        assert_eq!(paid.into_inner(), 18);
+        assert!(!stats.sources[0].exists);
+        let remaining: Vec<u64> = stats.sources.iter().map(|source| source.balance).collect();
+        assert_eq!(remaining, vec![0, 0, 0, 5]);
+        let grants: Vec<u64> = stats.sources.iter().map(|source| source.totals.grants).collect();
+        assert_eq!(grants, vec![0, 10, 5, 3]);
+        assert_eq!(stats.total_balance, 5);
+        assert_eq!(stats.recent_grants.len(), 18);
+        assert!(stats.recent_grants.iter().all(|grant| grant.skipped[0] == format!("{}: no account", missing)));
+        let received: u64 = recipients.iter().map(|recipient| runtime.get_balance(recipient)).sum();
+        assert_eq!(received, 180);
+
+        let error = runtime.grant_from_faucet(&sources, &recipients[0], 10, None).unwrap_err();
+        assert!(error.to_string().contains("No faucet source can pay 10 tokens"));
+        assert!(runtime.set_faucet_sources(vec!["0x1234".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_account_export_import_round_trip() {
+        let source = Runtime::new();
+        let target = Runtime::new();
+        let address = "0x4444444444444444444444444444444444444444";
+        let other = "0x5555555555555555555555555555555555555555";
+        
+        // Sum of balances plus the fee pool matches the supply while no dividends are outstanding
+        let audit = |runtime: &Runtime| {
+            let balances: u64 = runtime.get_account_addresses().iter()
+                .map(|address| runtime.get_balance(address))
+                .sum();
+            assert_eq!(balances + runtime.get_fee_pool(), runtime.get_total_supply());
+        };
+        
+        source.create_account(address).unwrap();
+        source.create_account(other).unwrap();
+        source.credit_balance(address, 1_000).unwrap();
+        source.credit_balance(other, 500).unwrap();
+        source.verify_account(address);
+        source.set_account_limits(address, AccountLimits {
+            max_transaction_amount: Some(300),
+            daily_outflow_limit: None,
+        }).unwrap();
+        target.create_account(other).unwrap();
+        target.credit_balance(other, 200).unwrap();
+        audit(&source);
+        audit(&target);
+        
+        let export = source.export_account(address, 2030).unwrap();
+        assert_eq!(export.balance, 1_000);
+        assert!(export.verified);
+        assert_eq!(export.export_nonce, 0);
+        assert_eq!(source.next_export_nonce(), 1);
+        assert_eq!(source.get_balance(address), 0);
+        assert!(!source.get_account_addresses().contains(&address.to_string()));
+        assert_eq!(source.get_total_supply(), 500);
+        audit(&source);
+        
+        // The export survives a trip through JSON
+        let export: AccountExport = serde_json::from_str(&serde_json::to_string(&export).unwrap()).unwrap();
+        target.import_account(&export).unwrap();
+        assert_eq!(target.get_balance(address), 1_000);
+        assert!(target.is_account_verified(address));
+        assert_eq!(target.get_account_limits(address).active.max_transaction_amount, Some(300));
+        assert_eq!(target.get_total_supply(), 1_200);
+        audit(&target);
+        
+        assert_eq!(target.import_account(&export), Err(AccountTransferError::AlreadyImported {
+            source_chain_id: 2030,
+            export_nonce: 0,
+        }));
+        assert_eq!(target.get_total_supply(), 1_200);
+        
+        let burned = source.supply_events();
+        let minted = target.supply_events();
+        assert_eq!((burned.len(), minted.len()), (1, 1));
+        assert_eq!(burned[0].kind, SupplyEventKind::AccountExported);
+        assert_eq!(minted[0].kind, SupplyEventKind::AccountImported);
+        assert_eq!(burned[0].amount, minted[0].amount);
+        assert_eq!(minted[0].total_supply_after, 1_200);
+        
+        assert_eq!(source.export_account(address, 2030), Err(AccountTransferError::UnknownAccount(address.to_string())));
+    }
+
+    #[test]
+    #[allow(clippy::permissions_set_readonly_false)]
+    fn test_unwritable_checkpoint_dir_is_an_error() {
+        let test_dir = format!("./test_checkpoints_blocked_{}", std::process::id());
+        let _ = std::fs::remove_dir_all(&test_dir);
+        std::fs::create_dir_all(&test_dir).unwrap();
+        
+        // A file where a parent directory should be cannot be worked around, even by root
+        let blocker = format!("{}/file", test_dir);
+        std::fs::write(&blocker, b"").unwrap();
+        let error = Runtime::with_checkpoint_config(5, &format!("{}/checkpoints", blocker)).err().unwrap();
+        assert!(error.to_string().contains("not writable"), "{}", error);
+        
+        // A read-only directory
+        let read_only = format!("{}/read_only", test_dir);
+        let runtime = Runtime::with_checkpoint_config(5, &read_only).unwrap();
+        runtime.create_account("0x1111111111111111111111111111111111111111").unwrap();
+        let mut permissions = std::fs::metadata(&read_only).unwrap().permissions();
+        permissions.set_readonly(true);
+        std::fs::set_permissions(&read_only, permissions.clone()).unwrap();
+        
+        // Root ignores directory permissions, so only check when they apply
+        if std::fs::write(format!("{}/probe", read_only), b"").is_err() {
+            assert!(Runtime::with_checkpoint_config(5, &read_only).is_err());
+            assert!(runtime.create_checkpoint(true).is_err());
+            assert!(runtime.create_checkpoint(true).is_err());
+            
+            let health = runtime.checkpoint_health();
+            assert!(health.enabled);
+            assert_eq!(health.consecutive_failures, 2);
+            assert!(health.last_error.is_some());
+            assert!(runtime.list_checkpoints().is_empty());
+            assert_eq!(std::fs::read_dir(&read_only).unwrap().count(), 0);
+        }
+        
+        permissions.set_readonly(false);
+        std::fs::set_permissions(&read_only, permissions).unwrap();
+        assert!(runtime.create_checkpoint(true).is_ok());
+        let health = runtime.checkpoint_health();
+        assert_eq!(health.consecutive_failures, 0);
+        assert!(health.last_success.is_some());
+        
+        let _ = std::fs::remove_dir_all(&test_dir);
+    }
+}
+
+/// Creates the checkpoint directory if needed and checks that it is writable
+///
+/// # Arguments
+/// * `dir` - The checkpoint directory
+///
+/// # Returns
+/// An error naming the directory if it cannot be created or written to
+fn prepare_checkpoint_dir(dir: &str) -> io::Result<()> {
+    let describe = |e: io::Error| {
+        io::Error::new(e.kind(), format!("Checkpoint directory {} is not writable: {}", dir, e))
+    };
+    
+    fs::create_dir_all(dir).map_err(describe)?;
+    
+    // Creating a directory that already exists succeeds even when it is read-only
+    let probe = Path::new(dir).join(".write_test");
+    fs::write(&probe, b"").map_err(describe)?;
+    let _ = fs::remove_file(&probe);
+    
+    Ok(())
 }
 
 /// Error types for account operations
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum AccountError {
     /// Account already exists with the given address
     AlreadyExists,
     /// Invalid address format
     InvalidAddress,
+    /// The address is reserved for the system
+    ReservedAddress {
+        /// The reserved address, lowercase
+        address: String,
+        /// What it is reserved for
+        purpose: String,
+    },
+    /// The node is in maintenance mode, with the refusal message
+    Maintenance(String),
+    /// A transaction's nonce is not the sender's next one
+    InvalidNonce {
+        /// The sender, lowercase
+        address: String,
+        /// The nonce the sender's next transaction must use
+        expected: u64,
+        /// The nonce the transaction carried
+        actual: u64,
+    },
+    /// The account cannot cover a debit
+    InsufficientBalance {
+        /// Tokens the debit needs, fee included
+        required: u64,
+        /// The account's balance
+        available: u64,
+    },
+    /// A spender's allowance cannot cover a debit
+    InsufficientAllowance {
+        /// Tokens the debit needs, fee included
+        required: u64,
+        /// The allowance left
+        available: u64,
+    },
+    /// No account exists at the address, given lowercase
+    AccountNotFound(String),
+    /// The account at the address, given lowercase, is frozen
+    AccountFrozen(String),
+    /// A sum would exceed u64::MAX, naming what would overflow
+    Overflow(String),
     /// Other general errors
     Other(String),
 }
@@ -484,6 +2675,17 @@ impl fmt::Display for AccountError {
         match self {
             AccountError::AlreadyExists => write!(f, "Account already exists"),
             AccountError::InvalidAddress => write!(f, "Invalid address format"),
+            AccountError::ReservedAddress { address, purpose } => write!(f, "Address {} is reserved: {}", address, purpose),
+            AccountError::Maintenance(msg) => write!(f, "{}", msg),
+            AccountError::InvalidNonce { address, expected, actual } if actual < expected =>
+                write!(f, "Nonce too low for {}: expected {}, got {}", address, expected, actual),
+            AccountError::InvalidNonce { address, expected, actual } =>
+                write!(f, "Nonce too high for {}: expected {}, got {}", address, expected, actual),
+            AccountError::InsufficientBalance { required, available } => write!(f, "Insufficient balance: {} < {}", available, required),
+            AccountError::InsufficientAllowance { required, available } => write!(f, "Insufficient allowance: {} < {}", available, required),
+            AccountError::AccountNotFound(address) => write!(f, "Account {} does not exist", address),
+            AccountError::AccountFrozen(address) => write!(f, "Account {} is frozen", address),
+            AccountError::Overflow(what) => write!(f, "Arithmetic overflow: {} would exceed {}", what, u64::MAX),
             AccountError::Other(msg) => write!(f, "Error: {}", msg),
         }
     }
@@ -491,21 +2693,82 @@ impl fmt::Display for AccountError {
 
 impl std::error::Error for AccountError {}
 
-/// Account structure representing a user in the UBI Chain system
-///
-/// # Fields
-/// * `address` - The unique identifier/address of the account
-/// * `balance` - The current balance of UBI tokens
-/// * `verified` - Whether the account has passed human verification
-/// * `last_ubi_claim` - Timestamp of the last UBI claim
-///
-/// # Example
-/// ```
-/// let account = Account {
+/// Reason a transfer would be rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransferRejection {
+    /// The sender has no account
+    UnknownSender(String),
+    /// The recipient has no account
+    UnknownRecipient(String),
+    /// The sender cannot cover the amount plus the fee
+    InsufficientFunds {
+        /// The sender's balance, less any tokens still locked
+        have: u64,
+        /// The amount plus the fee
+        want: u64,
+    },
+    /// The transfer breaks the sender's spending limits
+    LimitExceeded(limits::LimitError),
+    /// The sender or the recipient is frozen
+    AccountFrozen(String),
+}
+
+impl fmt::Display for TransferRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransferRejection::UnknownSender(address) => write!(f, "Sender account {} does not exist", address),
+            TransferRejection::UnknownRecipient(address) => write!(f, "Recipient account {} does not exist", address),
+            TransferRejection::InsufficientFunds { have, want } => write!(f, "Insufficient balance: {} < {}", have, want),
+            TransferRejection::LimitExceeded(e) => write!(f, "{}", e),
+            TransferRejection::AccountFrozen(address) => write!(f, "Account {} is frozen", address),
+        }
+    }
+}
+
+impl From<TransferRejection> for AccountError {
+    fn from(rejection: TransferRejection) -> Self {
+        match rejection {
+            TransferRejection::UnknownSender(address) | TransferRejection::UnknownRecipient(address) => {
+                AccountError::AccountNotFound(address.to_lowercase())
+            },
+            TransferRejection::InsufficientFunds { have, want } => AccountError::InsufficientBalance { required: want, available: have },
+            TransferRejection::LimitExceeded(e) => AccountError::Other(e.to_string()),
+            TransferRejection::AccountFrozen(address) => AccountError::AccountFrozen(address.to_lowercase()),
+        }
+    }
+}
+
+/// Account structure representing a user in the UBI Chain system
+///
+/// # Fields
+/// * `address` - The unique identifier/address of the account
+/// * `balance` - The current balance of UBI tokens
+/// * `verified` - Whether the account has passed human verification
+/// * `last_ubi_claim` - Timestamp of the last UBI claim
+/// * `verified_at` - When verification was last granted
+/// * `streaming_rate` - Rate UBI streams in at, scaled by `STREAMING_RATE_PRECISION`
+/// * `frozen` - Whether the account is held under review
+/// * `frozen_reason` - Why it was frozen, if a reason was given
+/// * `locked_balances` - Tokens of the balance locked until a later time
+/// * `auto_compound` - Whether dividends roll into the balance when the account transacts
+/// * `ubi_stats` - Lifetime UBI received and claims made
+///
+/// # Example
+/// ```
+/// # use std::time::SystemTime;
+/// # use ubi_chain_runtime::Account;
+/// let account = Account {
 ///     address: "0x123...".to_string(),
 ///     balance: 1000,
 ///     verified: true,
 ///     last_ubi_claim: SystemTime::now(),
+///     verified_at: Some(SystemTime::now()),
+///     streaming_rate: 0,
+///     frozen: false,
+///     frozen_reason: None,
+///     locked_balances: Vec::new(),
+///     auto_compound: false,
+///     ubi_stats: Default::default(),
 /// };
 /// ```
 #[derive(Debug, Clone)]
@@ -521,9 +2784,61 @@ pub struct Account {
     
     /// Timestamp of the last UBI claim
     pub last_ubi_claim: SystemTime,
+    
+    /// When verification was last granted; UBI never accrues for time before it
+    pub verified_at: Option<SystemTime>,
+    
+    /// Tokens per second streamed to the account as UBI, scaled by
+    /// `STREAMING_RATE_PRECISION`; zero while unverified
+    pub streaming_rate: u64,
+    
+    /// Whether the account is frozen: it can neither send nor receive and
+    /// accrues no UBI until it is unfrozen
+    pub frozen: bool,
+    
+    /// Why the account was frozen, if a reason was given
+    pub frozen_reason: Option<String>,
+    
+    /// Tokens of the balance that cannot be spent before their unlock time
+    pub locked_balances: Vec<BalanceLock>,
+    
+    /// Whether accrued dividends are claimed into the balance whenever the
+    /// account transfers or claims UBI
+    pub auto_compound: bool,
+    
+    /// UBI the account has received and claims it has made since it was created
+    pub ubi_stats: UbiStats,
+}
+
+impl Account {
+    /// Gets the tokens the account can spend at the given time: its balance
+    /// less the tokens still locked
+    pub fn spendable_balance(&self, now: u64) -> u64 {
+        self.balance.saturating_sub(balance_lock::locked_amount(&self.locked_balances, now))
+    }
+    
+    /// Gets the state hashed into the account's Merkle leaf
+    ///
+    /// Only stored fields are used, so the same account always hashes to the
+    /// same leaf and a rebuilt tree matches an incrementally updated one. The
+    /// streaming rate follows the chain's UBI rate and is not stored.
+    ///
+    /// # Arguments
+    /// * `nonce` - The account's transaction nonce, kept outside the account
+    fn merkle_state(&self, nonce: u64) -> AccountState {
+        AccountState {
+            base_balance: self.balance,
+            last_update: system_time_secs(self.last_ubi_claim),
+            streaming_rate: 0,
+            verified: self.verified,
+            nonce,
+            last_ubi_claim: system_time_secs(self.last_ubi_claim),
+        }
+    }
 }
 
 /// Represents the current state of an account with streaming capabilities
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub struct AccountState {
     /// Base balance of the account in tokens
     pub base_balance: u64,
@@ -533,11 +2848,47 @@ pub struct AccountState {
     
     /// Rate at which tokens are streamed (tokens per time unit)
     pub streaming_rate: u64,
+    
+    /// Whether the account passed human verification
+    pub verified: bool,
+    
+    /// Number of transactions the account has executed
+    pub nonce: u64,
+    
+    /// Last UBI claim in seconds since epoch
+    pub last_ubi_claim: u64,
+}
+
+/// Scale of streaming rates: a rate of `STREAMING_RATE_PRECISION` streams one
+/// token per second. It is a multiple of 3600, so a whole number of tokens per
+/// hour converts to an exact rate.
+pub const STREAMING_RATE_PRECISION: u64 = 3_600_000_000;
+
+/// Converts a rate in tokens per hour to a streaming rate
+pub fn streaming_rate_per_hour(tokens_per_hour: u64) -> u64 {
+    tokens_per_hour.saturating_mul(STREAMING_RATE_PRECISION / 3600)
+}
+
+/// Works out the whole tokens streamed since the last update, and the seconds
+/// whose stream has not yet added up to a whole token
+fn streamed_tokens(account: &AccountState, now: u64) -> (u64, u64) {
+    let elapsed = now.saturating_sub(account.last_update);
+    let streamed = account.streaming_rate as u128 * elapsed as u128;
+    let precision = STREAMING_RATE_PRECISION as u128;
+    let tokens = u64::try_from(streamed / precision).unwrap_or(u64::MAX);
+    let carried = match account.streaming_rate {
+        0 => 0,
+        rate => ((streamed % precision) / rate as u128) as u64,
+    };
+    (tokens, carried)
 }
 
 /// Computes the current balance of an account based on its base balance, streaming rate,
 /// and the time elapsed since the last update.
 ///
+/// Only whole tokens are counted; the fraction streamed so far is not lost
+/// but shows once it adds up to a token.
+///
 /// # Arguments
 /// * `account` - Reference to the account state
 /// * `now` - Current timestamp
@@ -545,18 +2896,22 @@ pub struct AccountState {
 /// # Returns
 /// The current balance including streamed tokens
 pub fn compute_current_balance(account: &AccountState, now: u64) -> u64 {
-    account.base_balance + account.streaming_rate * (now - account.last_update)
+    account.base_balance.saturating_add(streamed_tokens(account, now).0)
 }
 
-/// Updates the account state by setting the base balance to the computed current balance
-/// and updating the last update timestamp.
+/// Updates the account state by adding the whole tokens streamed so far to
+/// the base balance and moving the last update timestamp up to them.
+///
+/// The seconds that have not yet streamed a whole token carry over, so
+/// settling often streams as much as settling rarely.
 ///
 /// # Arguments
 /// * `account` - Mutable reference to the account state to update
-/// * `now` - Current timestamp to set as the new last_update
+/// * `now` - Current timestamp
 pub fn update_account_state(account: &mut AccountState, now: u64) {
-    account.base_balance = compute_current_balance(account, now);
-    account.last_update = now;
+    let (tokens, carried) = streamed_tokens(account, now);
+    account.base_balance = account.base_balance.saturating_add(tokens);
+    account.last_update = now.max(account.last_update) - carried;
 }
 
 /// Runtime implementation for UBI Chain
@@ -574,26 +2929,105 @@ pub fn update_account_state(account: &mut AccountState, now: u64) {
 #[derive(Clone)]
 pub struct Runtime {
     /// Thread-safe storage for account states
-    accounts: Arc<std::sync::Mutex<HashMap<String, Account>>>,
+    ///
+    /// A persistent map: cloning it is O(1) and shares structure, and writers
+    /// copy only the nodes they touch, so checkpoints snapshot it without
+    /// duplicating every account. Queries take the read lock, so balance
+    /// lookups from the RPC servers run side by side and only wait on writes.
+    accounts: Arc<std::sync::RwLock<im::HashMap<String, Account>>>,
     
-    /// Global pool for collected transaction fees
-    fee_pool: Arc<std::sync::Mutex<u64>>,
+    /// Held shared by operations that change balances and the supply counters
+    /// together, and exclusively while a checkpoint takes its snapshot, so the
+    /// snapshot never sees half of such an operation
+    state_gate: Arc<std::sync::RwLock<()>>,
     
-    /// Global dividend per token value (scaled by DIVIDEND_PRECISION)
-    dividend_per_token: Arc<std::sync::Mutex<u64>>,
+    /// Total supply, fee pool and dividend state
+    ///
+    /// Taken before the accounts lock by operations that need both; queries
+    /// of the supply, the pool and unclaimed dividends take the read lock.
+    dividends: Arc<std::sync::RwLock<DividendState>>,
     
-    /// Total supply of tokens in circulation
-    total_supply: Arc<std::sync::Mutex<u64>>,
+    /// Every change to the fee pool; appended while the dividends lock is held
+    fee_ledger: Arc<std::sync::Mutex<Vec<FeeLedgerEntry>>>,
     
-    /// Tracks the last dividend per token value seen by each account
-    last_dividend_points: Arc<std::sync::Mutex<HashMap<String, u64>>>,
+    /// Open payment streams
+    ///
+    /// Taken after the state gate and the state store, and before the
+    /// dividends and accounts locks; queries take the read lock.
+    payment_streams: Arc<std::sync::RwLock<PaymentStreams>>,
     
-    /// Unclaimed dividends for each account
-    unclaimed_dividends: Arc<std::sync::Mutex<HashMap<String, u64>>>,
+    /// Every change to an account balance; appended while the accounts lock is held
+    balance_history: Arc<std::sync::Mutex<BalanceHistory>>,
     
     /// Merkle tree for state verification
     state_tree: Arc<std::sync::Mutex<MerkleTree>>,
     
+    /// Opt-in spending limits per account
+    account_limits: Arc<std::sync::Mutex<HashMap<String, AccountLimitState>>>,
+    
+    /// Tokens each spender may still transfer out of an owner's account, by (owner, spender)
+    ///
+    /// Taken after the limits lock, and released before the fee ledger is appended to.
+    allowances: Arc<std::sync::Mutex<HashMap<(String, String), u64>>>,
+    
+    /// Chain parameters in effect and scheduled changes
+    param_schedule: Arc<std::sync::Mutex<ParamSchedule>>,
+    
+    /// Number of accounts exported from this chain, the nonce of the next export
+    export_nonce: Arc<std::sync::Mutex<u64>>,
+    
+    /// Nonce of each sender's next transaction; senders without one start at 0
+    ///
+    /// Held while a transaction executes, so two transactions from one sender
+    /// cannot both take the same nonce. Taken before the state gate.
+    nonces: Arc<std::sync::Mutex<HashMap<String, u64>>>,
+    
+    /// Replay keys of the exports imported into this chain
+    imported_exports: Arc<std::sync::Mutex<HashSet<String>>>,
+    
+    /// Supply changes caused by account exports and imports, and by burns
+    supply_events: Arc<std::sync::Mutex<Vec<SupplyEvent>>>,
+    
+    /// Address whose transfers are burned, lowercase
+    burn_address: String,
+    
+    /// Addresses no account may be created at
+    reserved: Arc<std::sync::Mutex<ReservedAddresses>>,
+    
+    /// Whether balance changes are refused for maintenance
+    maintenance: Arc<std::sync::Mutex<MaintenanceStatus>>,
+    
+    /// Settled UBI accruals, recorded as system transactions
+    ubi_mints: Arc<std::sync::Mutex<UbiMintLog>>,
+    
+    /// Verification changes waiting for the next block
+    verification_events: Arc<std::sync::Mutex<VerificationEventLog>>,
+    
+    /// Grant tiers offered by the faucet
+    faucet_tiers: Arc<std::sync::Mutex<Vec<FaucetTier>>>,
+    
+    /// Faucet grants per tier and address, keyed by "tier:address"
+    faucet_records: Arc<std::sync::Mutex<HashMap<String, FaucetRecord>>>,
+    
+    /// Faucet account funded at genesis, if any
+    genesis_faucet: Arc<std::sync::Mutex<Option<String>>>,
+    
+    /// Senders charged no transfer fee, lowercase
+    fee_exempt: Arc<std::sync::Mutex<HashSet<String>>>,
+    
+    /// Faucet source accounts configured by the operator, in the order they are tried
+    faucet_sources: Arc<std::sync::Mutex<Vec<String>>>,
+    
+    /// Grants the faucet paid
+    ///
+    /// Held while a grant is paid and while faucet stats are read, so stats
+    /// never see a source's balance without the grants that drew it down.
+    /// Taken before the state gate and the accounts lock.
+    faucet_history: Arc<std::sync::Mutex<FaucetHistory>>,
+    
+    /// Bootstrap window opened at genesis, if any
+    bootstrap_window: Arc<std::sync::Mutex<Option<BootstrapWindow>>>,
+    
     /// History of state checkpoints
     checkpoints: Arc<std::sync::Mutex<Vec<StateCheckpoint>>>,
     
@@ -603,8 +3037,65 @@ pub struct Runtime {
     /// Directory to store checkpoint files
     checkpoint_dir: String,
     
+    /// Most accounts a checkpoint may declare before it is refused unread
+    max_checkpoint_accounts: u64,
+    
+    /// Whether checkpoints are written with their accounts compressed
+    compress_checkpoints: bool,
+    
+    /// Most hours of UBI a single claim credits
+    max_ubi_accrual_hours: u64,
+    
+    /// Blocks between distributions of the fee pool; 0 turns them off
+    fee_distribution_interval_blocks: u64,
+    
+    /// Outcome of recent checkpoint writes
+    checkpoint_health: Arc<std::sync::Mutex<CheckpointHealth>>,
+    
+    /// Lifecycle traces of submitted transactions, shared by every component holding the runtime
+    tx_traces: TransactionTraceStore,
+    
+    /// Source of the current time
+    clock: Clock,
+    
+    /// What newly created accounts start with
+    new_accounts: NewAccountPolicy,
+    
     /// Reference to the block producer
     block_producer: Arc<std::sync::RwLock<Option<Arc<dyn BlockProducer>>>>,
+    
+    /// Durable copy of the state, if the runtime was opened on a store
+    ///
+    /// Operations write what they changed through to it once they have
+    /// released the dividends and accounts locks. Taken after the nonce lock
+    /// and the state gate, and before the locks it reads the changes under.
+    state_store: Arc<std::sync::Mutex<Option<AttachedStore>>>,
+    
+    /// Nonce the next operations admin action must be signed with
+    ///
+    /// Kept in the state store, so a signed action cannot be replayed after a
+    /// restart. Taken after the state store lock.
+    admin_nonce: Arc<std::sync::Mutex<u64>>,
+}
+
+/// A state store and how much of the fee ledger it already holds
+#[derive(Debug)]
+struct AttachedStore {
+    store: Box<dyn StateStore>,
+    fee_entries: usize,
+}
+
+/// What a newly created account starts with
+///
+/// Nodes create accounts empty and unverified; only tests pin anything else,
+/// through `test_utils::TestRuntimeBuilder`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NewAccountPolicy {
+    /// Tokens minted to the account on creation
+    pub balance: u64,
+    
+    /// Whether the account is verified on creation
+    pub verified: bool,
 }
 
 /// Represents a checkpoint of the blockchain state
@@ -629,6 +3120,74 @@ pub struct StateCheckpoint {
     pub file_path: String,
 }
 
+/// Where the total supply is held, taken at one point in time
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SupplyReport {
+    /// Total supply of tokens in circulation
+    pub total_supply: u64,
+    
+    /// Sum of all account balances
+    pub account_balances: u64,
+    
+    /// Fees collected but not yet distributed
+    pub fee_pool: u64,
+    
+    /// Distributed fees not yet credited to balances, claimed or not
+    pub dividends_owed: u64,
+    
+    /// Number of accounts
+    pub account_count: usize,
+    
+    /// Total supply minus balances, fee pool and dividends owed; a few tokens
+    /// of rounding from dividend division are expected, anything more is a bug
+    pub unaccounted: i128,
+    
+    /// Fees collected minus fees distributed, according to the fee ledger
+    pub fee_ledger_net: i128,
+    
+    /// Fee pool minus the fee ledger's net; anything but zero means the pool
+    /// changed without a ledger entry
+    pub fee_ledger_discrepancy: i128,
+    
+    /// Tokens burned since genesis: voluntary burns and the burned part of fees
+    pub total_burned: u64,
+}
+
+/// Outcome of rebuilding the state tree from scratch
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateRootCheck {
+    /// Root of the incrementally maintained tree, brought up to date first
+    pub incremental_root: String,
+    
+    /// Root of a tree rebuilt from the account store
+    pub recomputed_root: String,
+    
+    /// Whether the two roots are equal
+    pub matches: bool,
+    
+    /// Leaves in the incrementally maintained tree
+    pub leaf_count: usize,
+    
+    /// Accounts in the account store
+    pub account_count: usize,
+}
+
+/// Outcome of recent checkpoint writes, for monitoring
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckpointHealth {
+    /// Whether the node was configured to write checkpoints
+    pub enabled: bool,
+    
+    /// Failed writes since the last successful one
+    pub consecutive_failures: u32,
+    
+    /// Error of the most recent failed write
+    pub last_error: Option<String>,
+    
+    /// When the last checkpoint was written, in seconds since the epoch
+    pub last_success: Option<u64>,
+}
+
 impl Runtime {
     /// Creates a new Runtime instance with empty state
     ///
@@ -638,6 +3197,36 @@ impl Runtime {
         Self::default()
     }
     
+    /// Creates a new Runtime with empty state and the given configuration
+    ///
+    /// # Arguments
+    /// * `config` - The operator's settings
+    ///
+    /// # Returns
+    /// A new Runtime instance starting from the configured values
+    pub fn with_config(config: RuntimeConfig) -> Self {
+        Runtime::new().with_runtime_config(config)
+    }
+    
+    /// Applies a runtime configuration
+    ///
+    /// # Arguments
+    /// * `config` - The operator's settings
+    ///
+    /// # Returns
+    /// The runtime with the configured values
+    pub fn with_runtime_config(mut self, config: RuntimeConfig) -> Self {
+        {
+            let mut schedule = self.param_schedule.lock().unwrap();
+            schedule.current.ubi_tokens_per_hour = config.ubi_rate_per_hour;
+            schedule.current.fee_bps = config.fee_basis_points;
+        }
+        self.max_ubi_accrual_hours = config.max_ubi_accrual_hours;
+        self.fee_distribution_interval_blocks = config.fee_distribution_interval_blocks;
+        self.refresh_streaming_rates();
+        self
+    }
+    
     /// Creates a new Runtime with custom checkpoint configuration
     ///
     /// # Arguments
@@ -645,342 +3234,2905 @@ impl Runtime {
     /// * `checkpoint_dir` - Directory to store checkpoint files
     ///
     /// # Returns
-    /// A new Runtime instance with the specified configuration
-    pub fn with_checkpoint_config(max_checkpoints: usize, checkpoint_dir: &str) -> Self {
+    /// A new Runtime instance with the specified configuration, or an error if
+    /// the checkpoint directory cannot be created or written to
+    pub fn with_checkpoint_config(max_checkpoints: usize, checkpoint_dir: &str) -> io::Result<Self> {
         let mut runtime = Runtime::new();
         runtime.max_checkpoints = max_checkpoints;
         runtime.checkpoint_dir = checkpoint_dir.to_string();
+        runtime.checkpoint_health.lock().unwrap().enabled = true;
         
-        // Ensure checkpoint directory exists
-        if !Path::new(checkpoint_dir).exists() {
-            fs::create_dir_all(checkpoint_dir).expect("Failed to create checkpoint directory");
-        }
+        // Fail at startup rather than on the first checkpoint
+        prepare_checkpoint_dir(checkpoint_dir)?;
         
-        runtime
+        Ok(runtime)
     }
 
-    /// Gets the balance of an account
+    /// Sets the most accounts a checkpoint may declare when it is loaded
     ///
     /// # Arguments
-    /// * `address` - The account address
+    /// * `max_accounts` - Checkpoints declaring more accounts are refused
     ///
     /// # Returns
-    /// The account balance, or 0 if the account doesn't exist
-    pub fn get_balance(&self, address: &str) -> u64 {
-        // Normalize address to lowercase for consistent lookup
-        let address_lower = address.to_lowercase();
-        
-        // Acquire lock on accounts
-        let accounts_guard = match self.accounts.lock() {
-            Ok(guard) => guard,
-            Err(e) => {
-                log::error!("Failed to acquire lock on accounts: {:?}", e);
-                return 0;
-            }
-        };
-        
-        // Get the account balance
-        accounts_guard.get(&address_lower)
-            .map(|account| account.balance)
-            .unwrap_or(0)
+    /// The runtime with the new limit
+    pub fn with_max_checkpoint_accounts(mut self, max_accounts: u64) -> Self {
+        self.max_checkpoint_accounts = max_accounts;
+        self
     }
 
-    /// Checks if an account is verified
+    /// Sets whether checkpoints are written with their accounts compressed
+    ///
+    /// Compressed and uncompressed checkpoints load alike, so this can be
+    /// changed between restarts.
     ///
     /// # Arguments
-    /// * `address` - The account address
+    /// * `compress` - Whether to gzip the accounts and fee ledger of new checkpoints
     ///
     /// # Returns
-    /// true if the account is verified, false otherwise
-    pub fn is_account_verified(&self, address: &str) -> bool {
-        // Normalize address to lowercase for consistent lookup
-        let address_lower = address.to_lowercase();
-        
-        // Acquire lock on accounts
-        let accounts_guard = match self.accounts.lock() {
-            Ok(guard) => guard,
-            Err(e) => {
-                log::error!("Failed to acquire lock on accounts: {:?}", e);
-                return false;
-            }
-        };
-        
-        // Check if the account is verified
-        accounts_guard.get(&address_lower)
-            .map(|account| account.verified)
-            .unwrap_or(false)
+    /// The runtime with the new setting
+    pub fn with_checkpoint_compression(mut self, compress: bool) -> Self {
+        self.compress_checkpoints = compress;
+        self
     }
 
-    /// Creates a new account with the given address
+    /// Sets the address whose transfers are burned instead of credited
     ///
     /// # Arguments
-    /// * `address` - The account address
+    /// * `address` - The burn address
     ///
     /// # Returns
-    /// Result indicating success or an error
-    pub fn create_account(&self, address: &str) -> Result<(), AccountError> {
-        // Normalize address to lowercase for consistent lookup
-        let address_lower = address.to_lowercase();
-        
-        // Validate the address format
-        if !address_lower.starts_with("0x") || address_lower.len() != 42 {
-            return Err(AccountError::Other(format!("Invalid address format: {}", address)));
-        }
-        
-        // Check if the address contains only valid hex characters
-        if !address_lower[2..].chars().all(|c| c.is_ascii_hexdigit()) {
-            return Err(AccountError::Other(format!("Invalid address format: {}", address)));
-        }
-        
-        // Acquire lock on accounts
-        let mut accounts_guard = match self.accounts.lock() {
-            Ok(guard) => guard,
-            Err(e) => {
-                log::error!("Failed to acquire lock on accounts: {:?}", e);
-                return Err(AccountError::Other(format!("Failed to acquire lock on accounts: {:?}", e)));
-            }
-        };
-        
-        // Check if account already exists
-        if accounts_guard.contains_key(&address_lower) {
-            return Err(AccountError::Other(format!("Account already exists: {}", address)));
-        }
-        
-        // Create the account
-        accounts_guard.insert(address_lower.clone(), Account {
-            address: address_lower,
-            balance: 0,
-            verified: false,
-            last_ubi_claim: SystemTime::now(),
-        });
-        
-        Ok(())
+    /// The runtime with the new burn address
+    pub fn with_burn_address(mut self, address: &str) -> Self {
+        self.burn_address = address.to_lowercase();
+        self.reserved.lock().unwrap().set_burn_address(address);
+        self
     }
-    
-    /// Verifies an account as a human
+
+    /// Keeps the state in a sled database, so it survives a restart
     ///
     /// # Arguments
-    /// * `address` - The account address to verify
+    /// * `path` - Directory of the database, created if it does not exist
     ///
     /// # Returns
-    /// true if verification was successful, false if account doesn't exist
-    pub fn verify_account(&self, address: &str) -> bool {
-        let mut accounts = self.accounts.lock().unwrap();
-        
-        if let Some(account) = accounts.get_mut(address) {
-            account.verified = true;
-            true
-        } else {
-            false
-        }
+    /// The runtime with the state the database holds, or an error if the
+    /// database cannot be opened or read; see `with_state_store`
+    pub fn with_storage(self, path: impl AsRef<Path>) -> io::Result<Self> {
+        self.with_state_store(Box::new(storage::SledStore::open(path)?))
     }
-    
-    /// Updates the UBI balance for an account based on time elapsed since last claim
+
+    /// Keeps the state in a store
+    ///
+    /// A store that holds state replaces the runtime's state with it, the way
+    /// loading a checkpoint does. An empty store is given the runtime's
+    /// current state. From then on every change is written through.
     ///
     /// # Arguments
-    /// * `address` - The account address to update
+    /// * `store` - The store
     ///
     /// # Returns
-    /// The amount of UBI tokens added, or 0 if account doesn't exist or isn't verified
-    pub fn update_ubi_balance(&self, address: &str) -> u64 {
-        let mut accounts = self.accounts.lock().unwrap();
-        
-        if let Some(account) = accounts.get_mut(address) {
-            // Only distribute UBI to verified accounts
-            if account.verified {
-                // Calculate hours since last claim
-                let now = SystemTime::now();
-                let elapsed = now.duration_since(account.last_ubi_claim).unwrap_or(Duration::from_secs(0));
-                let hours = elapsed.as_secs() / 3600;
-                
-                if hours > 0 {
-                    // Calculate UBI tokens to add (1 per hour)
-                    let tokens_to_add = hours * UBI_TOKENS_PER_HOUR;
-                    
-                    // Update account
-                    account.balance += tokens_to_add;
-                    account.last_ubi_claim = now - Duration::from_secs(elapsed.as_secs() % 3600);
-                    
-                    return tokens_to_add;
+    /// The runtime backed by the store, or an error if the store cannot be
+    /// read or written
+    pub fn with_state_store(self, store: Box<dyn StateStore>) -> io::Result<Self> {
+        {
+            let mut nonces = self.nonces.lock().unwrap();
+            let _gate = self.state_gate.write().unwrap();
+            let fee_entries = match store.load()? {
+                Some(state) => {
+                    let fee_entries = state.fee_ledger.len();
+                    *self.admin_nonce.lock().unwrap() = state.admin_nonce;
+                    self.restore_state(state.into_checkpoint_data(self.now()), &mut nonces)?;
+                    fee_entries
                 }
-            }
+                None => {
+                    let state = self.stored_state(&nonces);
+                    store.replace(&state)?;
+                    state.fee_ledger.len()
+                }
+            };
+            *self.state_store.lock().unwrap() = Some(AttachedStore { store, fee_entries });
         }
-        
-        0
+        Ok(self)
     }
 
-    /// Distributes the accumulated fees to all token holders proportionally
-    /// 
-    /// This function:
-    /// 1. Calculates the new dividend per token value
-    /// 2. Updates the global dividend per token
-    /// 3. Resets the fee pool
-    /// 
+    /// Makes sure every change written to the state store survives a crash
+    ///
     /// # Returns
-    /// The amount of fees distributed
-    pub fn distribute_fees(&self) -> u64 {
-        let mut fee_pool = self.fee_pool.lock().unwrap();
-        let total_supply = *self.total_supply.lock().unwrap();
-        
-        // If there are no tokens in circulation or no fees to distribute, return 0
-        if total_supply == 0 || *fee_pool == 0 {
-            return 0;
+    /// Ok, also when the runtime has no store, or the store's error
+    pub fn flush_state_store(&self) -> io::Result<()> {
+        match self.state_store.lock().unwrap().as_ref() {
+            Some(attached) => attached.store.flush(),
+            None => Ok(()),
         }
-        
-        // Calculate the dividend per token increase
-        // Using DIVIDEND_PRECISION to avoid loss of precision in integer division
-        let dividend_increase = (*fee_pool * DIVIDEND_PRECISION) / total_supply;
-        
-        // Update the global dividend per token value
-        let mut dividend_per_token = self.dividend_per_token.lock().unwrap();
-        *dividend_per_token += dividend_increase;
-        
-        // Store the distributed amount and reset the fee pool
-        let distributed_amount = *fee_pool;
-        *fee_pool = 0;
-        
+    }
+
+    /// Writes the given accounts and the supply counters through to the state store
+    ///
+    /// Accounts that no longer exist are removed from the store. Must be
+    /// called without the dividends, accounts or limits locks held. A failed
+    /// write is logged, since the change has already been made in memory.
+    ///
+    /// # Arguments
+    /// * `addresses` - Lowercase addresses of the accounts the operation changed
+    fn persist(&self, addresses: &[&str]) {
+        self.persist_with(addresses, &[], &[]);
+    }
+    
+    /// Writes the given accounts, payment streams and allowances and the supply counters through to the state store
+    ///
+    /// Streams that are no longer open, and allowances with nothing left, are
+    /// removed from the store. Must also be called without the payment
+    /// streams or allowances locks held.
+    ///
+    /// # Arguments
+    /// * `addresses` - Lowercase addresses of the accounts the operation changed
+    /// * `stream_ids` - Ids of the payment streams the operation opened, settled or closed
+    /// * `allowances` - Lowercase owner and spender of the allowances the operation changed
+    fn persist_with(&self, addresses: &[&str], stream_ids: &[StreamId], allowances: &[(&str, &str)]) {
+        let mut guard = self.state_store.lock().unwrap();
+        let Some(attached) = guard.as_mut() else {
+            return;
+        };
+        
+        let (changes, fee_entries) = {
+            let streams = self.payment_streams.read().unwrap();
+            let dividends = self.dividends.read().unwrap();
+            let accounts = self.accounts.read().unwrap();
+            let limits = self.account_limits.lock().unwrap();
+            let allowed = self.allowances.lock().unwrap();
+            let ledger = self.fee_ledger.lock().unwrap();
+            let mut changes = StateChanges {
+                fee_entries: ledger.get(attached.fee_entries..).unwrap_or_default().to_vec(),
+                totals: Some(stored_totals(&dividends)),
+                ..StateChanges::default()
+            };
+            for address in addresses {
+                match accounts.get(*address) {
+                    Some(account) => changes.accounts.push(stored_account(account, &dividends, &limits)),
+                    None => changes.removed.push(address.to_string()),
+                }
+            }
+            for id in stream_ids {
+                match streams.get(*id) {
+                    Some(stream) => changes.streams.push(stream.clone()),
+                    None => changes.closed_streams.push(*id),
+                }
+            }
+            if !stream_ids.is_empty() {
+                changes.next_stream_id = Some(streams.next_id());
+            }
+            for (owner, spender) in allowances {
+                changes.allowances.push(CheckpointAllowance {
+                    owner: owner.to_string(),
+                    spender: spender.to_string(),
+                    amount: allowed.get(&(owner.to_string(), spender.to_string())).copied().unwrap_or(0),
+                });
+            }
+            (changes, ledger.len())
+        };
+        
+        match attached.store.write(&changes) {
+            Ok(()) => attached.fee_entries = fee_entries,
+            Err(e) => log::error!("Failed to write state changes to the state store: {}", e),
+        }
+    }
+
+    /// Writes a sender's nonce through to the state store
+    ///
+    /// Called with the nonce lock held, which `persist` does not take.
+    fn persist_nonce(&self, address: &str, nonce: u64) {
+        if let Some(attached) = self.state_store.lock().unwrap().as_ref() {
+            let changes = StateChanges {
+                nonces: vec![(address.to_string(), nonce)],
+                ..StateChanges::default()
+            };
+            if let Err(e) = attached.store.write(&changes) {
+                log::error!("Failed to write the nonce of {} to the state store: {}", address, e);
+            }
+        }
+    }
+
+    /// Gets the whole state as a store holds it
+    ///
+    /// # Arguments
+    /// * `nonces` - The nonces, whose lock the caller holds
+    fn stored_state(&self, nonces: &HashMap<String, u64>) -> StoredState {
+        let streams = self.payment_streams.read().unwrap();
+        let dividends = self.dividends.read().unwrap();
+        let accounts = self.accounts.read().unwrap();
+        let limits = self.account_limits.lock().unwrap();
+        let mut stored_accounts: Vec<StoredAccount> = accounts.values()
+            .map(|account| stored_account(account, &dividends, &limits))
+            .collect();
+        stored_accounts.sort_by(|a, b| a.address.cmp(&b.address));
+        let allowances = checkpoint_allowances(&self.allowances.lock().unwrap());
+        StoredState {
+            accounts: stored_accounts,
+            nonces: nonces.clone(),
+            fee_ledger: self.fee_ledger.lock().unwrap().clone(),
+            totals: stored_totals(&dividends),
+            streams: streams.all(),
+            next_stream_id: streams.next_id(),
+            allowances,
+            admin_nonce: *self.admin_nonce.lock().unwrap(),
+        }
+    }
+    
+    /// Gets the nonce the next operations admin action must be signed with
+    pub fn admin_nonce(&self) -> u64 {
+        *self.admin_nonce.lock().unwrap()
+    }
+    
+    /// Uses up the current operations admin nonce
+    ///
+    /// The next nonce is written through to the state store before this
+    /// returns, so the action signed with the used one cannot be run again
+    /// after a restart.
+    ///
+    /// # Returns
+    /// The nonce used up, or an error if the next one cannot be stored
+    pub fn advance_admin_nonce(&self) -> io::Result<u64> {
+        let store = self.state_store.lock().unwrap();
+        let mut admin_nonce = self.admin_nonce.lock().unwrap();
+        let used = *admin_nonce;
+        if let Some(attached) = store.as_ref() {
+            attached.store.write(&StateChanges {
+                admin_nonce: Some(used + 1),
+                ..StateChanges::default()
+            })?;
+        }
+        *admin_nonce = used + 1;
+        Ok(used)
+    }
+
+    /// Creates a new Runtime for a brand-new network from a genesis configuration
+    ///
+    /// # Arguments
+    /// * `config` - The genesis configuration
+    /// * `node_address` - Address of this node, used when the faucet address is "node"
+    ///
+    /// # Returns
+    /// A new Runtime with the faucet funded and the UBI settings applied
+    pub fn from_genesis(config: &GenesisConfig, node_address: &str) -> Result<Self, GenesisError> {
+        let runtime = Runtime::with_config(config.runtime_config());
+        runtime.apply_genesis(config, node_address)?;
+        Ok(runtime)
+    }
+    
+    /// Applies a genesis configuration to an empty runtime
+    ///
+    /// Reserves the declared address ranges, funds and limits the faucet
+    /// account and opens the bootstrap window, which starts at the genesis
+    /// timestamp. The UBI settings are not applied here: the runtime is
+    /// created with them, see `GenesisConfig::runtime_config`. A faucet
+    /// account that already exists, such as one restored from the state store
+    /// on restart, is not funded again.
+    /// The faucet and the listed fee-exempt senders pay no transfer fees.
+    ///
+    /// # Arguments
+    /// * `config` - The genesis configuration
+    /// * `node_address` - Address of this node, used when the faucet address is "node"
+    pub fn apply_genesis(&self, config: &GenesisConfig, node_address: &str) -> Result<(), GenesisError> {
+        let genesis_time = config.timestamp;
+        
+        // Reserved first, so the faucet cannot be placed in a reserved range
+        for range in &config.reserved {
+            if !range.is_valid() {
+                return Err(GenesisError::InvalidReservedRange(format!("{}..{}", range.from, range.to)));
+            }
+            self.reserved.lock().unwrap().reserve(range.clone());
+        }
+        
+        if let Some(ubi) = &config.ubi {
+            if ubi.bootstrap_window_days > 0 {
+                *self.bootstrap_window.lock().unwrap() = Some(BootstrapWindow {
+                    auto_verify: ubi.auto_verify,
+                    ends_at: genesis_time.saturating_add(ubi.bootstrap_window_days.saturating_mul(SECS_PER_DAY)),
+                });
+            }
+        }
+        
+        if let Some(faucet) = &config.faucet {
+            let address = faucet.resolve_address(node_address);
+            let invalid = || GenesisError::InvalidFaucetAddress(faucet.address.clone());
+            let exists = self.accounts.read().unwrap().contains_key(&address);
+            if !exists {
+                self.create_account_at(&address, genesis_time).map_err(|_| invalid())?;
+                self.credit_balance(&address, faucet.initial_balance).map_err(|_| invalid())?;
+                self.set_account_limits_at(&address, AccountLimits {
+                    max_transaction_amount: faucet.per_request_limit,
+                    daily_outflow_limit: faucet.daily_limit,
+                }, genesis_time).map_err(|_| invalid())?;
+            }
+            self.set_fee_exempt(&address, true);
+            *self.genesis_faucet.lock().unwrap() = Some(address);
+        }
+        
+        for address in &config.fee_exempt {
+            if !is_valid_eth_address(&address.to_lowercase()) {
+                return Err(GenesisError::InvalidFeeExemptAddress(address.clone()));
+            }
+            self.set_fee_exempt(address, true);
+        }
+        
+        Ok(())
+    }
+    
+    /// Gets what an address is reserved for, or None if an account may be created at it
+    pub fn reserved_address_purpose(&self, address: &str) -> Option<String> {
+        self.reserved.lock().unwrap().purpose(address)
+    }
+    
+    /// Lists the reserved address ranges: the system addresses and those declared at genesis
+    pub fn reserved_addresses(&self) -> Vec<ReservedRange> {
+        self.reserved.lock().unwrap().ranges()
+    }
+    
+    /// Gets the faucet account funded at genesis
+    pub fn genesis_faucet(&self) -> Option<String> {
+        self.genesis_faucet.lock().unwrap().clone()
+    }
+    
+    /// Gets the state of the bootstrap window, or None if genesis opened none
+    pub fn bootstrap_status(&self) -> Option<BootstrapStatus> {
+        self.bootstrap_status_at(self.now())
+    }
+    
+    /// Gets the state of the bootstrap window at the given time
+    fn bootstrap_status_at(&self, now: u64) -> Option<BootstrapStatus> {
+        self.bootstrap_window.lock().unwrap().map(|window| window.status_at(now))
+    }
+
+    /// Gets the balance of an account
+    ///
+    /// A verified account's balance includes the whole tokens of UBI streamed
+    /// since its last settlement, so it grows smoothly rather than hourly.
+    /// The payment streams the account pays or receives count as if settled
+    /// now. Reading it settles neither them nor its UBI; a claim, a transfer
+    /// or an explicit settlement does.
+    ///
+    /// # Arguments
+    /// * `address` - The account address
+    ///
+    /// # Returns
+    /// The account balance, or 0 if the account doesn't exist
+    pub fn get_balance(&self, address: &str) -> u64 {
+        // Normalize address to lowercase for consistent lookup
+        let address_lower = address.to_lowercase();
+        let now = self.now();
+        let (received, paid) = self.unsettled_streams_of(&address_lower, now).iter()
+            .fold((0u64, 0u64), |(received, paid), (stream, moved)| match stream.to == address_lower {
+                true => (received.saturating_add(*moved), paid),
+                false => (received, paid.saturating_add(*moved)),
+            });
+        
+        // Acquire lock on accounts
+        let accounts_guard = match self.accounts.read() {
+            Ok(guard) => guard,
+            Err(e) => {
+                log::error!("Failed to acquire lock on accounts: {:?}", e);
+                return 0;
+            }
+        };
+        
+        // Get the account balance, with the UBI and payments streamed so far
+        accounts_guard.get(&address_lower)
+            .map(|account| {
+                let streamed = self.accrued_ubi(account, now).map_or(0, |(tokens, _)| tokens);
+                account.balance.saturating_add(streamed).saturating_add(received).saturating_sub(paid)
+            })
+            .unwrap_or(0)
+    }
+
+    /// Gets the addresses of all accounts
+    ///
+    /// # Returns
+    /// The lowercase addresses of every account, in no particular order
+    pub fn get_account_addresses(&self) -> Vec<String> {
+        match self.accounts.read() {
+            Ok(accounts_guard) => accounts_guard.keys().cloned().collect(),
+            Err(e) => {
+                log::error!("Failed to acquire lock on accounts: {:?}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Gets account addresses in sorted order, one slice at a time
+    ///
+    /// Only the returned addresses are copied, so paging through every
+    /// account never clones the whole account map.
+    ///
+    /// # Arguments
+    /// * `after` - Return only addresses sorting after this one; None to start at the first
+    /// * `limit` - Maximum number of addresses to return
+    ///
+    /// # Returns
+    /// Up to `limit` addresses, ascending
+    pub fn account_addresses_after(&self, after: Option<&str>, limit: usize) -> Vec<String> {
+        if limit == 0 {
+            return Vec::new();
+        }
+        let accounts = match self.accounts.read() {
+            Ok(accounts_guard) => accounts_guard,
+            Err(e) => {
+                log::error!("Failed to acquire lock on accounts: {:?}", e);
+                return Vec::new();
+            }
+        };
+
+        let mut addresses: Vec<&String> = accounts.keys()
+            .filter(|address| after.is_none_or(|after| address.as_str() > after))
+            .collect();
+        if addresses.len() > limit {
+            addresses.select_nth_unstable(limit - 1);
+            addresses.truncate(limit);
+        }
+        addresses.sort_unstable();
+        addresses.into_iter().cloned().collect()
+    }
+
+    /// Checks if an account is verified
+    ///
+    /// # Arguments
+    /// * `address` - The account address
+    ///
+    /// # Returns
+    /// true if the account is verified, false otherwise
+    pub fn is_account_verified(&self, address: &str) -> bool {
+        // Normalize address to lowercase for consistent lookup
+        let address_lower = address.to_lowercase();
+        
+        // Acquire lock on accounts
+        let accounts_guard = match self.accounts.read() {
+            Ok(guard) => guard,
+            Err(e) => {
+                log::error!("Failed to acquire lock on accounts: {:?}", e);
+                return false;
+            }
+        };
+        
+        // Check if the account is verified
+        accounts_guard.get(&address_lower)
+            .map(|account| account.verified)
+            .unwrap_or(false)
+    }
+
+    /// Checks if an account is frozen
+    ///
+    /// # Arguments
+    /// * `address` - The account address
+    ///
+    /// # Returns
+    /// The reason given for the freeze, if any, when the account is frozen;
+    /// None when it is not frozen or does not exist
+    pub fn account_freeze(&self, address: &str) -> Option<Option<String>> {
+        let accounts_guard = match self.accounts.read() {
+            Ok(guard) => guard,
+            Err(e) => {
+                log::error!("Failed to acquire lock on accounts: {:?}", e);
+                return None;
+            }
+        };
+
+        accounts_guard.get(&address.to_lowercase())
+            .filter(|account| account.frozen)
+            .map(|account| account.frozen_reason.clone())
+    }
+
+    /// Checks if an account claims its dividends whenever it transfers or claims UBI
+    ///
+    /// # Arguments
+    /// * `address` - The account address
+    ///
+    /// # Returns
+    /// Whether the account opted in; false if it does not exist
+    pub fn is_auto_compound(&self, address: &str) -> bool {
+        self.accounts.read().unwrap().get(&address.to_lowercase())
+            .is_some_and(|account| account.auto_compound)
+    }
+
+    /// Gets the current time from the runtime's clock, in seconds since epoch
+    fn now(&self) -> u64 {
+        self.clock.now_secs()
+    }
+    
+    /// Creates a new account with the given address
+    ///
+    /// # Arguments
+    /// * `address` - The account address
+    ///
+    /// # Returns
+    /// Result indicating success or an error
+    pub fn create_account(&self, address: &str) -> Result<(), AccountError> {
+        self.create_account_at(address, self.now())
+    }
+    
+    /// Creates an account at the given time, verifying it if the bootstrap window auto-verifies
+    fn create_account_at(&self, address: &str, now: u64) -> Result<(), AccountError> {
+        // Normalize address to lowercase for consistent lookup
+        let address_lower = address.to_lowercase();
+        
+        // Validate the address format
+        if !is_valid_eth_address(&address_lower) {
+            return Err(AccountError::InvalidAddress);
+        }
+        
+        // System addresses are only used through their own code paths; the
+        // burn address in particular never holds tokens
+        if let Some(purpose) = self.reserved_address_purpose(&address_lower) {
+            return Err(AccountError::ReservedAddress { address: address_lower, purpose });
+        }
+        
+        self.check_maintenance()?;
+        
+        // Acquire lock on accounts
+        let mut accounts_guard = match self.accounts.write() {
+            Ok(guard) => guard,
+            Err(e) => {
+                log::error!("Failed to acquire lock on accounts: {:?}", e);
+                return Err(AccountError::Other(format!("Failed to acquire lock on accounts: {:?}", e)));
+            }
+        };
+        
+        // Check if account already exists
+        if accounts_guard.contains_key(&address_lower) {
+            return Err(AccountError::AlreadyExists);
+        }
+        
+        // During the bootstrap window new accounts may be verified on creation
+        let created_at = UNIX_EPOCH + Duration::from_secs(now);
+        let verified = self.new_accounts.verified || self.bootstrap_window.lock().unwrap()
+            .is_some_and(|window| window.auto_verifies_at(now));
+        
+        if verified {
+            self.verification_events.lock().unwrap().record(&address_lower, VerificationChange::Verified, now);
+        }
+        
+        // Create the account
+        accounts_guard.insert(address_lower.clone(), Account {
+            address: address_lower.clone(),
+            balance: 0,
+            verified,
+            last_ubi_claim: created_at,
+            verified_at: verified.then_some(created_at),
+            streaming_rate: if verified { self.ubi_streaming_rate() } else { 0 },
+            frozen: false,
+            frozen_reason: None,
+            locked_balances: Vec::new(),
+            auto_compound: false,
+            ubi_stats: UbiStats::default(),
+        });
+        drop(accounts_guard);
+        self.persist(&[&address_lower]);
+        
+        if self.new_accounts.balance > 0 {
+            self.credit_balance(address, self.new_accounts.balance)?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Verifies an account as a human
+    ///
+    /// # Arguments
+    /// * `address` - The account address to verify
+    ///
+    /// # Returns
+    /// true if verification was successful, false if account doesn't exist
+    pub fn verify_account(&self, address: &str) -> bool {
+        self.verify_account_at(address, self.now())
+    }
+    
+    /// Verifies an account at the given time, which becomes its verification time
+    ///
+    /// Verifying an account that is already verified keeps its original
+    /// verification time.
+    fn verify_account_at(&self, address: &str, now: u64) -> bool {
+        let mut accounts = self.accounts.write().unwrap();
+        
+        if let Some(account) = accounts.get_mut(address) {
+            if !account.verified {
+                account.verified = true;
+                account.verified_at = Some(UNIX_EPOCH + Duration::from_secs(now));
+                account.streaming_rate = self.ubi_streaming_rate();
+                self.verification_events.lock().unwrap().record(address, VerificationChange::Verified, now);
+                drop(accounts);
+                self.persist(&[address]);
+            }
+            true
+        } else {
+            false
+        }
+    }
+    
+    /// Revokes an account's human verification
+    ///
+    /// UBI earned up to the revocation is credited first. Nothing accrues while
+    /// the account is unverified, including after it is verified again.
+    ///
+    /// # Arguments
+    /// * `address` - The account address
+    ///
+    /// # Returns
+    /// true if the account exists, false otherwise
+    pub fn revoke_verification(&self, address: &str) -> bool {
+        self.revoke_verification_at(address, self.now())
+    }
+    
+    /// Revokes an account's verification at the given time
+    fn revoke_verification_at(&self, address: &str, now: u64) -> bool {
+        if let Err(e) = self.update_ubi_balance_at(address, now) {
+            log::warn!("UBI owed to {} was not paid before revoking its verification: {}", address, e);
+        }
+        
+        let mut accounts = self.accounts.write().unwrap();
+        if let Some(account) = accounts.get_mut(address) {
+            if account.verified {
+                self.verification_events.lock().unwrap().record(address, VerificationChange::Revoked, now);
+            }
+            account.verified = false;
+            account.verified_at = None;
+            account.streaming_rate = 0;
+            drop(accounts);
+            self.persist(&[address]);
+            true
+        } else {
+            false
+        }
+    }
+    
+    /// Freezes an account while it is under review
+    ///
+    /// UBI and payment streams are settled up to the freeze first. A frozen
+    /// account keeps its balance and history and can still be read, but it
+    /// can neither send nor receive, claim dividends or accrue UBI until it
+    /// is unfrozen. Freezing a frozen account replaces the reason.
+    ///
+    /// # Arguments
+    /// * `address` - The account address
+    /// * `reason` - Why the account is frozen, at most `checkpoint::MAX_CHECKPOINT_STRING_LEN` bytes
+    ///
+    /// # Returns
+    /// An error if the account doesn't exist, the reason is too long or the
+    /// node is in maintenance mode
+    pub fn freeze_account(&self, address: &str, reason: Option<String>) -> Result<(), AccountError> {
+        let address_lower = address.to_lowercase();
+        if reason.as_ref().is_some_and(|reason| reason.len() > checkpoint::MAX_CHECKPOINT_STRING_LEN) {
+            return Err(AccountError::Other(format!(
+                "Freeze reason is longer than {} bytes", checkpoint::MAX_CHECKPOINT_STRING_LEN
+            )));
+        }
+        let now = self.now();
+        self.update_ubi_balance_at(&address_lower, now)?;
+        self.settle_streams_of(&address_lower, now);
+        
+        {
+            let _gate = self.state_gate.read().unwrap();
+            self.check_maintenance()?;
+            let mut accounts = self.accounts.write().unwrap();
+            let account = accounts.get_mut(&address_lower)
+                .ok_or_else(|| AccountError::AccountNotFound(address_lower.clone()))?;
+            account.frozen = true;
+            account.frozen_reason = reason;
+        }
+        log::warn!("Froze account {}", address_lower);
+        self.persist(&[&address_lower]);
+        
+        Ok(())
+    }
+    
+    /// Lifts a freeze
+    ///
+    /// UBI starts accruing again from now; nothing is paid for the time the
+    /// account was frozen.
+    ///
+    /// # Arguments
+    /// * `address` - The account address
+    ///
+    /// # Returns
+    /// An error if the account doesn't exist or the node is in maintenance mode
+    pub fn unfreeze_account(&self, address: &str) -> Result<(), AccountError> {
+        let address_lower = address.to_lowercase();
+        {
+            let _gate = self.state_gate.read().unwrap();
+            self.check_maintenance()?;
+            let mut accounts = self.accounts.write().unwrap();
+            let account = accounts.get_mut(&address_lower)
+                .ok_or_else(|| AccountError::AccountNotFound(address_lower.clone()))?;
+            if !account.frozen {
+                return Ok(());
+            }
+            account.frozen = false;
+            account.frozen_reason = None;
+            account.last_ubi_claim = UNIX_EPOCH + Duration::from_secs(self.now());
+        }
+        log::info!("Unfroze account {}", address_lower);
+        self.persist(&[&address_lower]);
+        
+        Ok(())
+    }
+    
+    /// Opts an account in or out of auto-compounding
+    ///
+    /// An opted-in account has its accrued dividends claimed into its balance
+    /// at the start of every transfer it sends and every UBI claim, so they
+    /// can be spent without a separate claim.
+    ///
+    /// # Arguments
+    /// * `address` - The account address
+    /// * `enabled` - Whether dividends are compounded
+    ///
+    /// # Returns
+    /// An error if the account doesn't exist or the node is in maintenance mode
+    pub fn set_auto_compound(&self, address: &str, enabled: bool) -> Result<(), AccountError> {
+        let address_lower = address.to_lowercase();
+        {
+            let _gate = self.state_gate.read().unwrap();
+            self.check_maintenance()?;
+            let mut accounts = self.accounts.write().unwrap();
+            let account = accounts.get_mut(&address_lower)
+                .ok_or_else(|| AccountError::AccountNotFound(address_lower.clone()))?;
+            account.auto_compound = enabled;
+        }
+        log::info!(address = address_lower.as_str(); "Auto-compounding {} for {}", if enabled { "enabled" } else { "disabled" }, address_lower);
+        self.persist(&[&address_lower]);
+        
+        Ok(())
+    }
+    
+    /// Claims an account's dividends into its balance if it opted into auto-compounding
+    ///
+    /// `claim_dividends` settles what accrued since the last settlement as
+    /// well as what was already owed, so no separate update is needed.
+    ///
+    /// # Returns
+    /// The dividends claimed, 0 if the account did not opt in
+    fn compound_dividends(&self, address: &str) -> Result<u64, AccountError> {
+        if !self.is_auto_compound(address) {
+            return Ok(0);
+        }
+        self.claim_dividends(address)
+    }
+    
+    /// Locks part of an account's balance until a given time
+    ///
+    /// Used to vest grants and block rewards: the tokens stay in the balance
+    /// but cannot be spent until `unlock_at`. Only spendable tokens can be
+    /// locked, so a token is never held by two locks.
+    ///
+    /// # Arguments
+    /// * `address` - The account address
+    /// * `amount` - Tokens to lock
+    /// * `unlock_at` - When they become spendable, in seconds since epoch
+    ///
+    /// # Returns
+    /// An error if the account doesn't exist, cannot spare the amount, already
+    /// holds `MAX_LOCKS_PER_ACCOUNT` locks or the node is in maintenance mode
+    pub fn lock_balance(&self, address: &str, amount: u64, unlock_at: u64) -> Result<(), AccountError> {
+        let address_lower = address.to_lowercase();
+        if amount == 0 {
+            return Err(AccountError::Other("Lock amount must be greater than zero".to_string()));
+        }
+        let now = self.now();
+        if unlock_at <= now {
+            return Err(AccountError::Other(format!("Unlock time {} is not in the future", unlock_at)));
+        }
+        self.update_ubi_balance_at(&address_lower, now)?;
+        self.settle_streams_of(&address_lower, now);
+        
+        {
+            let _gate = self.state_gate.read().unwrap();
+            self.check_maintenance()?;
+            let mut accounts = self.accounts.write().unwrap();
+            let account = accounts.get_mut(&address_lower)
+                .ok_or_else(|| AccountError::AccountNotFound(address_lower.clone()))?;
+            balance_lock::release_expired(&mut account.locked_balances, now);
+            let spendable = account.spendable_balance(now);
+            if spendable < amount {
+                return Err(AccountError::InsufficientBalance { required: amount, available: spendable });
+            }
+            if account.locked_balances.len() >= MAX_LOCKS_PER_ACCOUNT {
+                return Err(AccountError::Other(format!(
+                    "Account {} already holds {} locks", address_lower, MAX_LOCKS_PER_ACCOUNT
+                )));
+            }
+            account.locked_balances.push(BalanceLock { amount, unlock_at });
+        }
+        log::info!(address = address_lower.as_str(); "Locked {} tokens of {} until {}", amount, address_lower, unlock_at);
+        self.persist(&[&address_lower]);
+        
+        Ok(())
+    }
+    
+    /// Gets the tokens of an account's balance that are still locked
+    ///
+    /// # Arguments
+    /// * `address` - The account address
+    ///
+    /// # Returns
+    /// The locked tokens, or 0 if the account doesn't exist
+    pub fn locked_balance(&self, address: &str) -> u64 {
+        let now = self.now();
+        self.accounts.read().unwrap().get(&address.to_lowercase())
+            .map_or(0, |account| balance_lock::locked_amount(&account.locked_balances, now))
+    }
+    
+    /// Updates the UBI balance for an account based on time elapsed since last claim
+    ///
+    /// Settles the account's UBI stream: the whole tokens streamed since the
+    /// later of the last claim and the verification time are credited, and
+    /// the fraction of a token streamed so far carries over. At most
+    /// `max_ubi_accrual_hours` are credited; a longer gap credits the cap and
+    /// restarts accrual from now.
+    ///
+    /// # Arguments
+    /// * `address` - The account address to update
+    ///
+    /// # Returns
+    /// The amount of UBI tokens added, or 0 if account doesn't exist or isn't
+    /// verified; an overflow error leaves the balance and the supply unchanged
+    pub fn update_ubi_balance(&self, address: &str) -> Result<u64, AccountError> {
+        self.update_ubi_balance_at(address, self.now())
+    }
+    
+    /// Updates the UBI balance for an account at the given time
+    ///
+    /// The credited tokens are minted and recorded as a UBI mint.
+    fn update_ubi_balance_at(&self, address: &str, now: u64) -> Result<u64, AccountError> {
+        self.credit_ubi_at(address, now, false)
+    }
+    
+    /// Credits the UBI an account has accrued by the given time
+    ///
+    /// The tokens count towards the account's lifetime UBI and the network's
+    /// UBI minted; an explicit claim that credits any also counts as a claim.
+    fn credit_ubi_at(&self, address: &str, now: u64, claimed: bool) -> Result<u64, AccountError> {
+        // The balance and the supply change together; accrual keeps counting during maintenance
+        let _gate = self.state_gate.read().unwrap();
+        if self.check_maintenance().is_err() {
+            return Ok(0);
+        }
+        let mut dividends = self.dividends.write().unwrap();
+        let mut accounts = self.accounts.write().unwrap();
+        
+        let account = match accounts.get_mut(address) {
+            Some(account) => account,
+            None => return Ok(0),
+        };
+        let (tokens_to_add, settled_to) = match self.accrued_ubi(account, now) {
+            Some(accrual) => accrual,
+            None => return Ok(0),
+        };
+        let balance = account.balance.checked_add(tokens_to_add)
+            .ok_or_else(|| AccountError::Overflow(format!("balance of {}", address)))?;
+        let total_supply = dividends.total_supply.checked_add(tokens_to_add)
+            .ok_or_else(|| AccountError::Overflow("total supply".to_string()))?;
+        
+        // Update account
+        dividends.settle_before_change(address, account.balance);
+        account.balance = balance;
+        account.last_ubi_claim = settled_to;
+        account.ubi_stats.record(tokens_to_add, claimed);
+        let settled_at = system_time_secs(settled_to);
+        self.record_balance_change(address, now, BalanceChangeKind::Ubi, tokens_to_add, 0, account.balance);
+        drop(accounts);
+        
+        dividends.total_supply = total_supply;
+        dividends.ubi_minted = dividends.ubi_minted.saturating_add(tokens_to_add);
+        drop(dividends);
+        self.ubi_mints.lock().unwrap().record(UbiMint::new(address, tokens_to_add, settled_at));
+        self.persist(&[address]);
+        
+        Ok(tokens_to_add)
+    }
+    
+    /// Works out the UBI an account has accrued by the given time, without crediting it
+    ///
+    /// Only verified accounts that are not frozen accrue, and never for time
+    /// before their verification. UBI streams in at the account's streaming rate, and back
+    /// pay stops at `max_ubi_accrual_hours`; the time beyond it is forfeited.
+    ///
+    /// # Returns
+    /// The whole tokens due and the time accrual is settled up to, or None if
+    /// not a whole token has streamed
+    fn accrued_ubi(&self, account: &Account, now: u64) -> Option<(u64, SystemTime)> {
+        if !account.verified || account.frozen {
+            return None;
+        }
+        
+        let since = account.verified_at.map_or(account.last_ubi_claim, |verified_at| account.last_ubi_claim.max(verified_at));
+        let since = system_time_secs(since);
+        let cap = self.max_ubi_accrual_hours.saturating_mul(3600);
+        let capped = now.saturating_sub(since) > cap;
+        let mut stream = AccountState {
+            last_update: since.max(now.saturating_sub(cap)),
+            streaming_rate: account.streaming_rate,
+            ..Default::default()
+        };
+        update_account_state(&mut stream, now);
+        if stream.base_balance == 0 {
+            return None;
+        }
+        
+        // The part token carries over, unless the cap forfeits everything up to now
+        let settled_to = if capped { now } else { stream.last_update };
+        Some((stream.base_balance, UNIX_EPOCH + Duration::from_secs(settled_to)))
+    }
+    
+    /// Gets the streaming rate of verified accounts under the current UBI rate
+    fn ubi_streaming_rate(&self) -> u64 {
+        streaming_rate_per_hour(self.chain_params().ubi_tokens_per_hour)
+    }
+    
+    /// Moves every verified account to the current UBI rate
+    ///
+    /// Called when the rate changes; time not yet settled streams at the new rate.
+    fn refresh_streaming_rates(&self) {
+        let rate = self.ubi_streaming_rate();
+        for (_, account) in self.accounts.write().unwrap().iter_mut().filter(|(_, account)| account.verified) {
+            account.streaming_rate = rate;
+        }
+    }
+    
+    /// Gets the UBI an account would be credited if it claimed now, without crediting it
+    ///
+    /// # Arguments
+    /// * `address` - The account address
+    ///
+    /// # Returns
+    /// The pending UBI, or 0 if the account doesn't exist or isn't verified
+    pub fn pending_ubi(&self, address: &str) -> u64 {
+        let accounts = self.accounts.read().unwrap();
+        accounts.get(&address.to_lowercase())
+            .and_then(|account| self.accrued_ubi(account, self.now()))
+            .map_or(0, |(tokens, _)| tokens)
+    }
+    
+    /// Credits the UBI an account has accrued since its last claim
+    ///
+    /// This is the only way UBI reaches a balance outside of revocation and
+    /// export; reading a balance never credits it. An account that opted into
+    /// auto-compounding has its dividends claimed first.
+    ///
+    /// # Arguments
+    /// * `address` - The account address
+    ///
+    /// # Returns
+    /// The tokens claimed, 0 if none are due, with the new balance, or an
+    /// error if the account doesn't exist or the credit would overflow
+    pub fn claim_ubi(&self, address: &str) -> Result<ClaimResult, AccountError> {
+        let address_lower = address.to_lowercase();
+        if !self.accounts.read().unwrap().contains_key(&address_lower) {
+            return Err(AccountError::AccountNotFound(address_lower));
+        }
+        self.compound_dividends(&address_lower)?;
+        let claimed = self.credit_ubi_at(&address_lower, self.now(), true)?;
+        Ok(ClaimResult {
+            claimed,
+            new_balance: self.get_balance(&address_lower),
+        })
+    }
+
+    /// Distributes the accumulated fees to all token holders proportionally
+    /// 
+    /// This function:
+    /// 1. Calculates the new dividend per token value
+    /// 2. Updates the global dividend per token
+    /// 3. Resets the fee pool
+    /// 
+    /// # Returns
+    /// The amount of fees distributed
+    pub fn distribute_fees(&self) -> u64 {
+        let block_number = self.param_schedule.lock().unwrap().height;
+        self.distribute_fees_at(block_number)
+    }
+    
+    /// Distributes the fee pool, recording the distribution at the given block
+    fn distribute_fees_at(&self, block_number: u64) -> u64 {
+        let _gate = self.state_gate.read().unwrap();
+        if self.check_maintenance().is_err() {
+            return 0;
+        }
+        let mut dividends = self.dividends.write().unwrap();
+        
+        // If there are no tokens in circulation or no fees to distribute, return 0
+        let (distributed_amount, dividend_increase) = match dividends.distribute() {
+            Some(distribution) => distribution,
+            None => return 0,
+        };
+        self.append_fee_ledger(self.now(), block_number, FeeLedgerKind::Distributed {
+            dividend_delta: dividend_increase,
+            dividend_per_token: dividends.dividend_per_token,
+        }, distributed_amount);
+        drop(dividends);
+        self.persist(&[]);
+        
         distributed_amount
     }
     
-    /// Updates the dividend accounting for a specific account
-    /// 
-    /// This function:
-    /// 1. Calculates the dividends owed to the account since last update
-    /// 2. Updates the account's last seen dividend point
-    /// 3. Adds the owed dividends to the account's unclaimed dividends
-    /// 
+    /// Distributes the fee pool if the block is due for a distribution
+    ///
+    /// Block producers and importers call this before executing a block, so
+    /// every node distributes the same pool over the same supply. Blocks
+    /// whose number is a multiple of `fee_distribution_interval_blocks` are
+    /// due.
+    ///
+    /// # Arguments
+    /// * `block_number` - The block about to be executed
+    ///
+    /// # Returns
+    /// The amount of fees distributed, 0 if the block is not due
+    pub fn distribute_fees_if_due(&self, block_number: u64) -> u64 {
+        let interval = self.fee_distribution_interval_blocks;
+        if interval == 0 || block_number == 0 || !block_number.is_multiple_of(interval) {
+            return 0;
+        }
+        self.distribute_fees_at(block_number)
+    }
+    
+    /// Lists every distribution of the fee pool, oldest first
+    pub fn get_distribution_history(&self) -> Vec<fee_ledger::DistributionEvent> {
+        fee_ledger::distribution_history(&self.fee_ledger.lock().unwrap())
+    }
+    
+    /// Updates the dividend accounting for a specific account
+    /// 
+    /// Every balance change settles the account in full first, so calling
+    /// this is never needed for correctness; it only moves what accrued so
+    /// far into the unclaimed dividends.
+    /// 
+    /// This function:
+    /// 1. Calculates the dividends owed to the account since last update
+    /// 2. Below `dividends::DIVIDEND_DUST_THRESHOLD`, leaves them to keep accruing
+    /// 3. Otherwise updates the account's last seen dividend point and adds
+    ///    them to the account's unclaimed dividends
+    /// 
+    /// # Arguments
+    /// * `address` - The account address to update
+    /// 
+    /// # Returns
+    /// The amount of new dividends credited to the account
+    pub fn update_account_dividends(&self, address: &str) -> u64 {
+        if !is_valid_eth_address(address) {
+            return 0;
+        }
+        
+        let mut dividends = self.dividends.write().unwrap();
+        let accounts = self.accounts.read().unwrap();
+        let settled = match accounts.get(address) {
+            Some(account) => dividends.settle(address, account.balance),
+            None => 0,
+        };
+        drop(accounts);
+        drop(dividends);
+        if settled > 0 {
+            self.persist(&[address]);
+        }
+        settled
+    }
+    
+    /// Claims the dividends for an account and adds them to the account balance
+    /// 
+    /// # Arguments
+    /// * `address` - The account address to claim dividends for
+    /// 
+    /// # Returns
+    /// The amount of dividends claimed; if the balance would overflow or the
+    /// account is frozen, an error and the dividends stay owed
+    pub fn claim_dividends(&self, address: &str) -> Result<u64, AccountError> {
+        if !is_valid_eth_address(address) {
+            return Ok(0);
+        }
+        
+        // Nothing is paid out during maintenance; the dividends stay owed
+        if self.check_maintenance().is_err() {
+            return Ok(0);
+        }
+        
+        // Take the settled and the accrued dividends in one step
+        let mut dividends = self.dividends.write().unwrap();
+        let mut accounts = self.accounts.write().unwrap();
+        let account = match accounts.get_mut(address) {
+            Some(account) if account.frozen => return Err(AccountError::AccountFrozen(address.to_string())),
+            Some(account) => account,
+            None => return Ok(0),
+        };
+        let owed = dividends.owed(address, account.balance);
+        let balance = account.balance.checked_add(owed)
+            .ok_or_else(|| AccountError::Overflow(format!("balance of {}", address)))?;
+        let to_claim = dividends.take(address, account.balance);
+        account.balance = balance;
+        self.record_balance_change(address, self.now(), BalanceChangeKind::Dividends, to_claim, 0, account.balance);
+        drop(accounts);
+        drop(dividends);
+        self.persist(&[address]);
+        
+        Ok(to_claim)
+    }
+    
+    /// Gets the unclaimed dividends for an account
+    /// 
+    /// # Arguments
+    /// * `address` - The account address to check
+    /// 
+    /// # Returns
+    /// The amount of unclaimed dividends
+    pub fn get_unclaimed_dividends(&self, address: &str) -> u64 {
+        if !is_valid_eth_address(address) {
+            return 0;
+        }
+        
+        // Settled and accrued dividends, without recording anything
+        let dividends = self.dividends.read().unwrap();
+        let balance = self.accounts.read().unwrap().get(address).map_or(0, |account| account.balance);
+        dividends.owed(address, balance)
+    }
+    
+    /// Updates the total supply when tokens are minted or burned
+    /// This should be called whenever the total supply changes
+    /// 
+    /// # Arguments
+    /// * `amount` - The amount to add (positive) or subtract (negative) from total supply
+    /// * `is_addition` - True if adding to supply, false if subtracting
+    pub fn update_total_supply(&self, amount: u64, is_addition: bool) {
+        self.adjust_total_supply(amount, is_addition);
+        self.persist(&[]);
+    }
+    
+    /// Changes the total supply without writing it to the state store, for
+    /// operations that write it together with the accounts they changed
+    fn adjust_total_supply(&self, amount: u64, is_addition: bool) {
+        let mut dividends = self.dividends.write().unwrap();
+        
+        if is_addition {
+            dividends.total_supply = dividends.total_supply.saturating_add(amount);
+        } else {
+            // Ensure we don't underflow
+            dividends.total_supply = dividends.total_supply.saturating_sub(amount);
+        }
+    }
+
+    /// Transfers tokens from one account to another with a fee
+    ///
+    /// # Arguments
+    /// * `from_address` - The sender's address
+    /// * `to_address` - The recipient's address
+    /// * `amount` - The amount to transfer
+    ///
+    /// # Returns
+    /// Result indicating success or an error
+    pub fn transfer_with_fee(&self, from_address: &str, to_address: &str, amount: u64) -> Result<(), AccountError> {
+        self.transfer_with_fee_at(from_address, to_address, amount, self.now())
+    }
+    
+    /// Executes a transaction's transfer, recording its hash with the fee in the fee ledger
+    ///
+    /// # Arguments
+    /// * `tx_hash` - Hash of the transaction
+    /// * `from_address` - The sender's address
+    /// * `to_address` - The recipient's address
+    /// * `amount` - The amount to transfer
+    ///
+    /// # Returns
+    /// Result indicating success or an error
+    pub fn transfer_transaction(&self, tx_hash: &str, from_address: &str, to_address: &str, amount: u64) -> Result<(), AccountError> {
+        self.transfer_with_fee_for(Some(tx_hash), from_address, to_address, amount, self.now()).map(|_| ())
+    }
+    
+    /// Gets the nonce the next transaction from an address must use, which is
+    /// the number of transactions it has executed
+    pub fn get_nonce(&self, address: &str) -> u64 {
+        self.nonces.lock().unwrap().get(&address.to_lowercase()).copied().unwrap_or(0)
+    }
+    
+    /// Executes a transaction's transfer, checking and advancing the sender's nonce
+    ///
+    /// A transaction with a nonce runs only if it is the sender's next one; one
+    /// without takes the next nonce. The nonce advances only if the transfer
+    /// succeeds, so a failed transaction can be sent again with the same nonce.
+    ///
+    /// # Arguments
+    /// * `tx` - The transaction; its hash is recorded with the fee in the fee ledger
+    ///
+    /// # Returns
+    /// The nonce the transaction used, or why it was refused
+    pub fn execute_transaction(&self, tx: &Transaction) -> Result<u64, AccountError> {
+        self.execute_with_nonce(&tx.from, tx.nonce, || {
+            self.transfer_with_fee_for(Some(&tx.hash), &tx.from.to_lowercase(), &tx.to, tx.amount, self.now())
+        })
+        .map(|(nonce, _)| nonce)
+    }
+    
+    /// Runs an operation on behalf of an account, checking and advancing its nonce
+    ///
+    /// Signed requests that are not transfers, such as approvals, use the
+    /// account's transaction nonce the same way, so none can be replayed.
+    /// The operation runs under the nonce lock and must not take it.
+    ///
+    /// # Arguments
+    /// * `address` - The account acting
+    /// * `nonce` - The nonce the request carried, or None to take the next one
+    /// * `operation` - The operation; the nonce advances only if it succeeds
+    ///
+    /// # Returns
+    /// The nonce used and the operation's result, or why it was refused
+    pub fn execute_with_nonce<T>(&self, address: &str, nonce: Option<u64>, operation: impl FnOnce() -> Result<T, AccountError>) -> Result<(u64, T), AccountError> {
+        let sender = address.to_lowercase();
+        let mut nonces = self.nonces.lock().unwrap();
+        let expected = nonces.get(&sender).copied().unwrap_or(0);
+        let nonce = nonce.unwrap_or(expected);
+        if nonce != expected {
+            return Err(AccountError::InvalidNonce { address: sender, expected, actual: nonce });
+        }
+        
+        let result = operation()?;
+        self.persist_nonce(&sender, expected + 1);
+        nonces.insert(sender, expected + 1);
+        Ok((nonce, result))
+    }
+    
+    /// Allows a spender to transfer up to an amount out of an account
+    ///
+    /// The allowance replaces any earlier one, and covers the fee of each
+    /// transfer the spender makes as well as the amount. Zero withdraws it.
+    ///
+    /// # Arguments
+    /// * `owner` - The account the tokens are drawn from
+    /// * `spender` - The address allowed to draw them
+    /// * `amount` - Tokens the spender may draw in total
+    ///
+    /// # Returns
+    /// An error if the owner doesn't exist, the spender address is invalid
+    /// or the node is in maintenance mode
+    pub fn approve(&self, owner: &str, spender: &str, amount: u64) -> Result<(), AccountError> {
+        let owner_lower = owner.to_lowercase();
+        let spender_lower = spender.to_lowercase();
+        if !is_valid_eth_address(&spender_lower) {
+            return Err(AccountError::InvalidAddress);
+        }
+        
+        {
+            let _gate = self.state_gate.read().unwrap();
+            self.check_maintenance()?;
+            if !self.accounts.read().unwrap().contains_key(&owner_lower) {
+                return Err(AccountError::AccountNotFound(owner_lower));
+            }
+            let mut allowances = self.allowances.lock().unwrap();
+            let key = (owner_lower.clone(), spender_lower.clone());
+            match amount {
+                0 => allowances.remove(&key),
+                amount => allowances.insert(key, amount),
+            };
+        }
+        self.persist_with(&[], &[], &[(&owner_lower, &spender_lower)]);
+        
+        Ok(())
+    }
+    
+    /// Gets the tokens a spender may still transfer out of an account, fee included
+    pub fn allowance(&self, owner: &str, spender: &str) -> u64 {
+        let key = (owner.to_lowercase(), spender.to_lowercase());
+        self.allowances.lock().unwrap().get(&key).copied().unwrap_or(0)
+    }
+    
+    /// Transfers tokens out of an account on its owner's behalf, drawing on an allowance
+    ///
+    /// The transfer is charged the usual fee, paid by the owner and routed
+    /// through the fee pool like `transfer_with_fee`. The allowance must
+    /// cover the amount plus the fee, and is reduced by both.
+    ///
+    /// # Arguments
+    /// * `spender` - The address drawing on its allowance
+    /// * `owner` - The account the tokens are drawn from
+    /// * `recipient` - The account receiving the amount
+    /// * `amount` - The amount the recipient receives
+    ///
+    /// # Returns
+    /// The fee charged, or why the transfer was refused
+    pub fn transfer_from(&self, spender: &str, owner: &str, recipient: &str, amount: u64) -> Result<u64, AccountError> {
+        self.transfer_with_fee_by(None, Some(spender), owner, recipient, amount, self.now())
+    }
+    
+    /// Pays many recipients from one account in a single step
+    ///
+    /// Each leg is charged the fee a transfer of its amount would pay, routed
+    /// through the fee pool like `transfer_with_fee`. Every check runs before
+    /// anything changes: the sender must cover the sum of the amounts and
+    /// fees within its limits and every recipient must exist, and no party may
+    /// be frozen, so the batch is applied completely or not at all. Burns and faucet payouts cannot be
+    /// batched.
+    ///
+    /// # Arguments
+    /// * `from_address` - The sender's address
+    /// * `legs` - Recipient and amount of each payment
+    ///
+    /// # Returns
+    /// The fees and totals paid, or why the batch was refused
+    pub fn batch_transfer(&self, from_address: &str, legs: Vec<(String, u64)>) -> Result<BatchReceipt, AccountError> {
+        let now = self.now();
+        let from_lower = from_address.to_lowercase();
+        if legs.is_empty() {
+            return Err(AccountError::Other("A batch needs at least one transfer".to_string()));
+        }
+        if legs.len() > MAX_BATCH_LEGS {
+            return Err(AccountError::Other(format!("A batch holds at most {} transfers", MAX_BATCH_LEGS)));
+        }
+        if from_lower == FAUCET_ADDRESS.to_lowercase() {
+            return Err(AccountError::Other("Faucet payouts cannot be batched".to_string()));
+        }
+        for (to, amount) in &legs {
+            if self.is_burn_address(to) {
+                return Err(AccountError::Other("Burns cannot be batched".to_string()));
+            }
+            self.validate_transaction(&from_lower, to, *amount, TransferMode::Send)
+                .map_err(|e| AccountError::Other(e.to_string()))?;
+        }
+        
+        // Settle the sender's UBI and the payment streams of every party, as a single transfer would
+        self.update_ubi_balance_at(&from_lower, now)?;
+        let mut parties: Vec<String> = legs.iter().map(|(to, _)| to.to_lowercase()).collect();
+        parties.push(from_lower.clone());
+        parties.sort();
+        parties.dedup();
+        for party in &parties {
+            self.settle_streams_of(party, now);
+        }
+        
+        let _gate = self.state_gate.read().unwrap();
+        self.check_maintenance()?;
+        let mut dividends = self.dividends.write().unwrap();
+        let mut accounts_guard = self.accounts.write().unwrap();
+        let (params, block_number) = {
+            let schedule = self.param_schedule.lock().unwrap();
+            (schedule.current, schedule.height)
+        };
+        let receipt = BatchReceipt::compute(&params, &from_lower, &legs, self.is_fee_exempt(&from_lower))?;
+        
+        // Check the whole batch against the sender's balance and limits, and every recipient
+        let (balance, spendable) = match accounts_guard.get(&from_lower) {
+            Some(sender) if sender.frozen => return Err(AccountError::AccountFrozen(from_lower)),
+            Some(sender) => (sender.balance, sender.spendable_balance(now)),
+            None => return Err(TransferRejection::UnknownSender(from_lower).into()),
+        };
+        if spendable < receipt.total_debited {
+            return Err(AccountError::InsufficientBalance { required: receipt.total_debited, available: spendable });
+        }
+        let sender_balance = balance - receipt.total_debited;
+        let mut limits_guard = self.account_limits.lock().unwrap();
+        if let Some(limits) = limits_guard.get_mut(&from_lower) {
+            limits.check_transfer(receipt.largest_amount(), receipt.total_debited, now)
+                .map_err(TransferRejection::LimitExceeded)?;
+        }
+        let mut credits: HashMap<&str, u64> = HashMap::new();
+        for leg in &receipt.legs {
+            match accounts_guard.get(&leg.to) {
+                Some(recipient) if recipient.frozen => return Err(AccountError::AccountFrozen(leg.to.clone())),
+                Some(_) => {},
+                None => return Err(TransferRejection::UnknownRecipient(leg.to.clone()).into()),
+            }
+            *credits.entry(leg.to.as_str()).or_insert(0) += leg.amount;
+        }
+        for (to, credit) in &credits {
+            let before = match *to == from_lower {
+                true => sender_balance,
+                false => accounts_guard[*to].balance,
+            };
+            before.checked_add(*credit)
+                .ok_or_else(|| AccountError::Overflow(format!("balance of {}", to)))?;
+        }
+        let (to_pool, burned) = receipt.legs.iter()
+            .map(|leg| params.split_fee(leg.fee))
+            .fold((0u64, 0u64), |(pooled, burned), (to_pool, to_burn)| (pooled + to_pool, burned + to_burn));
+        let fee_pool = dividends.fee_pool.checked_add(to_pool)
+            .ok_or_else(|| AccountError::Overflow("fee pool".to_string()))?;
+        
+        // Every sum is checked, so the legs below cannot fail part way
+        if let Some(limits) = limits_guard.get_mut(&from_lower) {
+            limits.record_outflow(receipt.total_debited, now);
+        }
+        drop(limits_guard);
+        balance_lock::release_expired(&mut accounts_guard.get_mut(&from_lower).unwrap().locked_balances, now);
+        for leg in &receipt.legs {
+            let sender = accounts_guard.get_mut(&from_lower).unwrap();
+            dividends.settle_before_change(&from_lower, sender.balance);
+            sender.balance -= leg.amount + leg.fee;
+            self.record_balance_change(&from_lower, now, BalanceChangeKind::TransferOut {
+                counterparty: leg.to.clone(),
+                fee: leg.fee,
+                tx_hash: None,
+            }, 0, leg.amount + leg.fee, sender.balance);
+            
+            let recipient = accounts_guard.get_mut(&leg.to).unwrap();
+            dividends.settle_before_change(&leg.to, recipient.balance);
+            recipient.balance += leg.amount;
+            self.record_balance_change(&leg.to, now, BalanceChangeKind::TransferIn {
+                counterparty: from_lower.clone(),
+                tx_hash: None,
+            }, leg.amount, 0, recipient.balance);
+        }
+        drop(accounts_guard);
+        
+        dividends.fee_pool = fee_pool;
+        dividends.total_supply = dividends.total_supply.saturating_sub(burned);
+        dividends.fees_collected = dividends.fees_collected.saturating_add(receipt.total_fee);
+        if to_pool > 0 {
+            self.append_fee_ledger(now, block_number, FeeLedgerKind::Collected { tx_hash: None }, to_pool);
+        }
+        if burned > 0 {
+            self.append_fee_ledger(now, block_number, FeeLedgerKind::Burned { tx_hash: None }, burned);
+        }
+        drop(dividends);
+        let addresses: Vec<&str> = parties.iter().map(String::as_str).collect();
+        self.persist(&addresses);
+        
+        Ok(receipt)
+    }
+    
+    /// Transfers tokens so that the recipient receives exactly the given amount
+    ///
+    /// The fee is computed on the amount received and debited from the sender
+    /// on top of it, so the transfer fails unless the sender's balance covers
+    /// both.
+    ///
+    /// # Arguments
+    /// * `from_address` - The sender's address
+    /// * `to_address` - The recipient's address
+    /// * `receive_amount` - The amount the recipient must receive
+    ///
+    /// # Returns
+    /// The amount received, the fee and the total debited from the sender, or an error
+    pub fn transfer_exact_receive(&self, from_address: &str, to_address: &str, receive_amount: u64) -> Result<TransferAmounts, AccountError> {
+        let fee = self.transfer_with_fee_for(None, from_address, to_address, receive_amount, self.now())?;
+        TransferAmounts::with_fee(TransferMode::ExactReceive, receive_amount, fee)
+            .ok_or_else(|| AccountError::Other("Transfer amount overflows".to_string()))
+    }
+    
+    /// Checks whether a transfer would succeed, without moving any tokens
+    ///
+    /// Runs the same checks as `transfer_with_fee`: both accounts must exist
+    /// and the sender must cover the amount plus the fee within its limits.
+    ///
+    /// # Arguments
+    /// * `from_address` - The sender's address
+    /// * `to_address` - The recipient's address
+    /// * `amount` - Amount of tokens to transfer
+    ///
+    /// # Returns
+    /// The fee the transfer would pay, or why it would be rejected
+    pub fn simulate_transfer(&self, from_address: &str, to_address: &str, amount: u64) -> Result<u64, TransferRejection> {
+        let accounts_guard = self.accounts.read().unwrap();
+        let mut limits_guard = self.account_limits.lock().unwrap();
+        if self.is_burn_address(to_address) {
+            check_burn(&accounts_guard, &mut limits_guard, from_address, amount, self.now())?;
+            return Ok(0);
+        }
+        let fee = self.transfer_fee_for(from_address, amount);
+        check_transfer(&accounts_guard, &mut limits_guard, from_address, to_address, amount, fee, self.now())?;
+        Ok(fee)
+    }
+    
+    /// Transfers tokens with a fee, checking the sender's limits at the given time
+    fn transfer_with_fee_at(&self, from_address: &str, to_address: &str, amount: u64, now: u64) -> Result<(), AccountError> {
+        self.transfer_with_fee_for(None, from_address, to_address, amount, now).map(|_| ())
+    }
+    
+    /// Transfers tokens with a fee at the given time on behalf of an optional transaction
+    ///
+    /// Returns the fee charged; burns are charged none.
+    fn transfer_with_fee_for(&self, tx_hash: Option<&str>, from_address: &str, to_address: &str, amount: u64, now: u64) -> Result<u64, AccountError> {
+        self.transfer_with_fee_by(tx_hash, None, from_address, to_address, amount, now)
+    }
+    
+    /// Transfers tokens with a fee at the given time, optionally drawn by a spender
+    ///
+    /// A spender's allowance from the sender must cover the amount plus the
+    /// fee, and is reduced by both in the same step as the balances. A
+    /// sender that opted into auto-compounding has its dividends claimed
+    /// before its spendable balance is checked.
+    ///
+    /// Returns the fee charged; burns are charged none.
+    fn transfer_with_fee_by(&self, tx_hash: Option<&str>, spender: Option<&str>, from_address: &str, to_address: &str, amount: u64, now: u64) -> Result<u64, AccountError> {
+        self.compound_dividends(&from_address.to_lowercase())?;
+        
+        // Transfers to the burn address destroy the tokens rather than crediting an unspendable account
+        if self.is_burn_address(to_address) {
+            if spender.is_some() {
+                return Err(AccountError::Other("Allowances cannot be spent on burns".to_string()));
+            }
+            return self.burn_for(tx_hash, from_address, amount, now).map(|_| 0);
+        }
+        
+        // Normalize addresses to lowercase for consistent lookup
+        let from_lower = from_address.to_lowercase();
+        let to_lower = to_address.to_lowercase();
+        let allowance_key = spender.map(|spender| (from_lower.clone(), spender.to_lowercase()));
+        
+        // Special case for faucet address - always allow transfers from the faucet
+        let is_faucet_transfer = from_lower == FAUCET_ADDRESS.to_lowercase();
+        
+        // Settle the sender's UBI stream, so the tokens it shows can be spent,
+        // and the payment streams of both parties, so the transfer sees what they moved
+        self.update_ubi_balance_at(&from_lower, now)?;
+        self.settle_streams_of(&from_lower, now);
+        self.settle_streams_of(&to_lower, now);
+        
+        // Balances, the fee pool and the supply change together
+        let _gate = self.state_gate.read().unwrap();
+        self.check_maintenance()?;
+        
+        // The fee pool is locked first, so every sum is checked before anything changes
+        let mut dividends = match self.dividends.write() {
+            Ok(guard) => guard,
+            Err(e) => {
+                log::error!("Failed to acquire lock on fee pool: {:?}", e);
+                return Err(AccountError::Other(format!("Failed to acquire lock on fee pool: {:?}", e)));
+            }
+        };
+        
+        // Acquire lock on accounts
+        let mut accounts_guard = match self.accounts.write() {
+            Ok(guard) => guard,
+            Err(e) => {
+                log::error!("Failed to acquire lock on accounts: {:?}", e);
+                return Err(AccountError::Other(format!("Failed to acquire lock on accounts: {:?}", e)));
+            }
+        };
+        
+        // Calculate fee under the current chain parameters
+        let (params, block_number) = {
+            let schedule = self.param_schedule.lock().unwrap();
+            (schedule.current, schedule.height)
+        };
+        let fee = self.fee_under(&params, &from_lower, amount);
+        let total_deduction = amount.checked_add(fee)
+            .ok_or_else(|| AccountError::Overflow("transfer amount plus fee".to_string()))?;
+        
+        // Check existence, balance and limits, then record the outflow against the limits
+        let mut limits_guard = self.account_limits.lock().unwrap();
+        check_transfer(&accounts_guard, &mut limits_guard, from_address, to_address, amount, fee, now)?;
+        
+        // A spender draws on its allowance, which covers the fee as well
+        let mut allowances_guard = self.allowances.lock().unwrap();
+        let allowance_left = match &allowance_key {
+            Some(key) => {
+                let available = allowances_guard.get(key).copied().unwrap_or(0);
+                Some(available.checked_sub(total_deduction)
+                    .ok_or(AccountError::InsufficientAllowance { required: total_deduction, available })?)
+            },
+            None => None,
+        };
+        
+        // Work out the new balances and pool; a sum that would wrap rejects the transfer unchanged
+        let sender_balance = match is_faucet_transfer {
+            true => None,
+            false => Some(accounts_guard[&from_lower].balance.checked_sub(total_deduction)
+                .ok_or(AccountError::InsufficientBalance { required: total_deduction, available: accounts_guard[&from_lower].balance })?),
+        };
+        let recipient_before = match sender_balance {
+            Some(balance) if from_lower == to_lower => balance,
+            _ => accounts_guard[&to_lower].balance,
+        };
+        let recipient_balance = recipient_before.checked_add(amount)
+            .ok_or_else(|| AccountError::Overflow(format!("balance of {}", to_lower)))?;
+        let (to_pool, burned) = params.split_fee(fee);
+        let fee_pool = dividends.fee_pool.checked_add(to_pool)
+            .ok_or_else(|| AccountError::Overflow("fee pool".to_string()))?;
+        
+        // Nothing is debited from the faucet, so what it pays and pools is minted
+        let total_supply = match sender_balance {
+            Some(_) => dividends.total_supply.saturating_sub(burned),
+            None => amount.checked_add(to_pool)
+                .and_then(|minted| dividends.total_supply.checked_add(minted))
+                .ok_or_else(|| AccountError::Overflow("total supply".to_string()))?,
+        };
+        
+        if let (Some(key), Some(left)) = (&allowance_key, allowance_left) {
+            match left {
+                0 => allowances_guard.remove(key),
+                left => allowances_guard.insert(key.clone(), left),
+            };
+        }
+        drop(allowances_guard);
+        
+        if let Some(balance) = sender_balance {
+            if let Some(limits) = limits_guard.get_mut(&from_lower) {
+                limits.record_outflow(total_deduction, now);
+            }
+            
+            // Deduct from sender, dropping the locks that no longer hold anything back
+            let sender = accounts_guard.get_mut(&from_lower).unwrap();
+            dividends.settle_before_change(&from_lower, sender.balance);
+            sender.balance = balance;
+            balance_lock::release_expired(&mut sender.locked_balances, now);
+            self.record_balance_change(&from_lower, now, BalanceChangeKind::TransferOut {
+                counterparty: to_lower.clone(),
+                fee,
+                tx_hash: tx_hash.map(str::to_string),
+            }, 0, total_deduction, sender.balance);
+        }
+        drop(limits_guard);
+        
+        // Add to recipient
+        let recipient = accounts_guard.get_mut(&to_lower).unwrap();
+        dividends.settle_before_change(&to_lower, recipient.balance);
+        recipient.balance = recipient_balance;
+        self.record_balance_change(&to_lower, now, BalanceChangeKind::TransferIn {
+            counterparty: from_lower.clone(),
+            tx_hash: tx_hash.map(str::to_string),
+        }, amount, 0, recipient.balance);
+        drop(accounts_guard);
+        
+        // Pay the pool's share of the fee and burn the rest, in the same step
+        dividends.fee_pool = fee_pool;
+        dividends.total_supply = total_supply;
+        dividends.fees_collected = dividends.fees_collected.saturating_add(fee);
+        let tx_hash = tx_hash.map(str::to_string);
+        if to_pool > 0 {
+            self.append_fee_ledger(now, block_number, FeeLedgerKind::Collected { tx_hash: tx_hash.clone() }, to_pool);
+        }
+        if burned > 0 {
+            self.append_fee_ledger(now, block_number, FeeLedgerKind::Burned { tx_hash }, burned);
+        }
+        drop(dividends);
+        let allowances: Vec<(&str, &str)> = allowance_key.iter().map(|(owner, spender)| (owner.as_str(), spender.as_str())).collect();
+        self.persist_with(&[&from_lower, &to_lower], &[], &allowances);
+        
+        Ok(fee)
+    }
+    
+    /// Turns maintenance mode on or off
+    ///
+    /// Waits for in-flight balance changes, so once this returns every later
+    /// change is refused until maintenance mode is turned off again.
+    ///
+    /// # Arguments
+    /// * `enabled` - Whether to refuse balance changes
+    /// * `reason` - Why, shown to refused callers
+    /// * `enabled_by` - Address of the admin switching the mode
+    ///
+    /// # Returns
+    /// The new maintenance status
+    pub fn set_maintenance(&self, enabled: bool, reason: Option<String>, enabled_by: Option<String>) -> MaintenanceStatus {
+        let _gate = self.state_gate.write().unwrap();
+        let mut maintenance = self.maintenance.lock().unwrap();
+        *maintenance = if enabled {
+            MaintenanceStatus { enabled, reason, enabled_by, since: Some(self.now()) }
+        } else {
+            MaintenanceStatus::default()
+        };
+        maintenance.clone()
+    }
+    
+    /// Names the first state lock a panic left poisoned, if any
+    ///
+    /// A poisoned lock means a state change was interrupted half way, so the
+    /// state can no longer be trusted and every later change would panic too.
+    pub fn poisoned_lock(&self) -> Option<&'static str> {
+        [
+            ("state gate", self.state_gate.is_poisoned()),
+            ("accounts", self.accounts.is_poisoned()),
+            ("dividends", self.dividends.is_poisoned()),
+            ("state tree", self.state_tree.is_poisoned()),
+            ("supply events", self.supply_events.is_poisoned()),
+        ]
+        .into_iter()
+        .find(|(_, poisoned)| *poisoned)
+        .map(|(name, _)| name)
+    }
+    
+    /// Gets whether the node is in maintenance mode, and why
+    pub fn maintenance_status(&self) -> MaintenanceStatus {
+        self.maintenance.lock().unwrap().clone()
+    }
+    
+    /// Refuses a state change while maintenance mode is on
+    ///
+    /// Callers hold the state gate, which `set_maintenance` takes exclusively.
+    fn check_maintenance(&self) -> Result<(), AccountError> {
+        let maintenance = self.maintenance.lock().unwrap();
+        if maintenance.enabled {
+            return Err(AccountError::Maintenance(maintenance.refusal()));
+        }
+        Ok(())
+    }
+    
+    /// Checks a transfer against the consensus rules every node applies to blocks
+    ///
+    /// Must be called without the accounts lock held, which the frozen rule reads under.
+    ///
+    /// # Arguments
+    /// * `from` - The sender's address
+    /// * `to` - The recipient's address
+    /// * `amount` - The amount of the transfer
+    /// * `mode` - Which figure of the transfer the caller fixed
+    ///
+    /// # Returns
+    /// The first rule the transfer breaks, if any
+    pub fn validate_transaction(&self, from: &str, to: &str, amount: u64, mode: TransferMode) -> Result<(), ConsensusViolation> {
+        consensus::validate_transaction(&self.chain_params(), &self.burn_address, from, to, amount, mode,
+                                        |address| self.account_freeze(address).is_some())
+    }
+    
+    /// Checks whether transfers to an address are burned
+    pub fn is_burn_address(&self, address: &str) -> bool {
+        address.eq_ignore_ascii_case(&self.burn_address)
+    }
+    
+    /// Gets the address whose transfers are burned
+    pub fn burn_address(&self) -> &str {
+        &self.burn_address
+    }
+    
+    /// Burns tokens from an account, removing them from the total supply
+    ///
+    /// Burns pay no fee. The sender's spending limits apply as to any outflow.
+    ///
+    /// # Arguments
+    /// * `from_address` - The account burning its tokens
+    /// * `amount` - The amount to burn
+    ///
+    /// # Returns
+    /// The supply event recording the burn, or why it was rejected
+    pub fn burn(&self, from_address: &str, amount: u64) -> Result<SupplyEvent, AccountError> {
+        self.burn_for(None, from_address, amount, self.now()).map(|(event, _)| event)
+    }
+    
+    /// Debits tokens from an account and removes them from the total supply
+    ///
+    /// The counterpart of `credit_balance`: the debit is a burn, recorded as
+    /// a supply event, and the balance and the supply change in one step.
+    ///
+    /// # Arguments
+    /// * `address` - The account address
+    /// * `amount` - The amount to destroy
+    ///
+    /// # Returns
+    /// Result containing the new balance or an error
+    pub fn burn_balance(&self, address: &str, amount: u64) -> Result<u64, AccountError> {
+        self.burn_for(None, address, amount, self.now()).map(|(_, balance)| balance)
+    }
+    
+    /// Burns tokens at the given time on behalf of an optional transaction
+    ///
+    /// Returns the supply event and the account's new balance.
+    fn burn_for(&self, tx_hash: Option<&str>, from_address: &str, amount: u64, now: u64) -> Result<(SupplyEvent, u64), AccountError> {
+        if amount == 0 {
+            return Err(AccountError::Other("Burn amount must be greater than zero".to_string()));
+        }
+        let from_lower = from_address.to_lowercase();
+        self.update_ubi_balance_at(&from_lower, now)?;
+        self.settle_streams_of(&from_lower, now);
+        
+        // The balance and the supply change together
+        let _gate = self.state_gate.read().unwrap();
+        self.check_maintenance()?;
+        
+        let mut dividends = self.dividends.write().unwrap();
+        let mut accounts_guard = self.accounts.write().unwrap();
+        let mut limits_guard = self.account_limits.lock().unwrap();
+        check_burn(&accounts_guard, &mut limits_guard, from_address, amount, now)?;
+        if let Some(limits) = limits_guard.get_mut(&from_lower) {
+            limits.record_outflow(amount, now);
+        }
+        drop(limits_guard);
+        let account = accounts_guard.get_mut(&from_lower).unwrap();
+        dividends.settle_before_change(&from_lower, account.balance);
+        account.balance -= amount;
+        balance_lock::release_expired(&mut account.locked_balances, now);
+        let balance = account.balance;
+        self.record_balance_change(&from_lower, now, BalanceChangeKind::Burn {
+            tx_hash: tx_hash.map(str::to_string),
+        }, 0, amount, balance);
+        drop(accounts_guard);
+        
+        dividends.total_supply = dividends.total_supply.saturating_sub(amount);
+        let total_supply_after = dividends.total_supply;
+        drop(dividends);
+        self.persist(&[&from_lower]);
+        
+        let event = SupplyEvent {
+            kind: SupplyEventKind::Burned,
+            address: from_lower,
+            amount,
+            source_chain_id: 0,
+            export_nonce: 0,
+            timestamp: now,
+            total_supply_after,
+            tx_hash: tx_hash.map(str::to_string),
+            block_hash: None,
+        };
+        log::info!(address = event.address.as_str(); "Burned {} tokens, total supply now {}", amount, event.total_supply_after);
+        self.supply_events.lock().unwrap().push(event.clone());
+        
+        Ok((event, balance))
+    }
+    
+    /// Gets the number of burns an account has made, the nonce of its next signed burn
+    pub fn burn_count(&self, address: &str) -> u64 {
+        self.supply_events.lock().unwrap().iter()
+            .filter(|event| event.kind == SupplyEventKind::Burned && event.address.eq_ignore_ascii_case(address))
+            .count() as u64
+    }
+    
+    /// Requests new spending limits for an account
+    ///
+    /// Tighter limits apply immediately; looser ones after `limits::LIMIT_CHANGE_DELAY_SECS`.
+    ///
+    /// # Arguments
+    /// * `address` - The account address
+    /// * `limits` - The requested limits
+    ///
+    /// # Returns
+    /// None if the limits took effect immediately, the activation time if they
+    /// are pending, or an error if the account does not exist
+    pub fn set_account_limits(&self, address: &str, limits: AccountLimits) -> Result<Option<u64>, AccountError> {
+        self.set_account_limits_at(address, limits, self.now())
+    }
+    
+    /// Requests new spending limits for an account at the given time
+    fn set_account_limits_at(&self, address: &str, limits: AccountLimits, now: u64) -> Result<Option<u64>, AccountError> {
+        let address_lower = address.to_lowercase();
+        if !self.accounts.read().unwrap().contains_key(&address_lower) {
+            return Err(AccountError::AccountNotFound(address_lower));
+        }
+        
+        let mut limits_guard = self.account_limits.lock().unwrap();
+        let activates_at = limits_guard.entry(address_lower.clone()).or_default().request_change(limits, now);
+        drop(limits_guard);
+        self.persist(&[&address_lower]);
+        Ok(activates_at)
+    }
+    
+    /// Gets the spending limit state of an account
+    ///
+    /// # Arguments
+    /// * `address` - The account address
+    ///
+    /// # Returns
+    /// The limit state, with pending changes applied and expired outflows dropped;
+    /// the default (unlimited) state if the account never set limits
+    pub fn get_account_limits(&self, address: &str) -> AccountLimitState {
+        let mut limits_guard = self.account_limits.lock().unwrap();
+        match limits_guard.get_mut(&address.to_lowercase()) {
+            Some(limits) => {
+                limits.refresh(self.now());
+                limits.clone()
+            },
+            None => AccountLimitState::default(),
+        }
+    }
+    
+    /// Gets the most hours of UBI a single claim credits
+    pub fn max_ubi_accrual_hours(&self) -> u64 {
+        self.max_ubi_accrual_hours
+    }
+    
+    /// Gets the number of blocks between distributions of the fee pool, 0 if they are off
+    pub fn fee_distribution_interval_blocks(&self) -> u64 {
+        self.fee_distribution_interval_blocks
+    }
+    
+    /// Gets the chain parameters in effect
+    pub fn chain_params(&self) -> ChainParams {
+        self.param_schedule.lock().unwrap().current
+    }
+    
+    /// Gets the transfer fee in effect, in basis points of the amount
+    pub fn fee_basis_points(&self) -> u64 {
+        self.chain_params().fee_bps
+    }
+    
+    /// Exempts a sender from transfer fees, or charges it again
+    ///
+    /// The built-in faucet and the genesis faucet are exempt from the start.
+    /// Exemptions change what transfers cost, so every node of a network must
+    /// be given the same ones; they are not stored in checkpoints.
+    ///
+    /// # Arguments
+    /// * `address` - The sender
+    /// * `exempt` - Whether its transfers are free
+    pub fn set_fee_exempt(&self, address: &str, exempt: bool) {
+        let address = address.to_lowercase();
+        let mut fee_exempt = self.fee_exempt.lock().unwrap();
+        match exempt {
+            true => fee_exempt.insert(address),
+            false => fee_exempt.remove(&address),
+        };
+    }
+    
+    /// Checks whether a sender pays no transfer fees
+    pub fn is_fee_exempt(&self, address: &str) -> bool {
+        self.fee_exempt.lock().unwrap().contains(&address.to_lowercase())
+    }
+    
+    /// Computes the fee a sender pays on top of a transfer under the chain parameters in effect
+    ///
+    /// # Arguments
+    /// * `from_address` - The sender
+    /// * `amount` - Amount of tokens to transfer
+    ///
+    /// # Returns
+    /// The fee, 0 for exempt senders
+    pub fn transfer_fee_for(&self, from_address: &str, amount: u64) -> u64 {
+        self.fee_under(&self.chain_params(), from_address, amount)
+    }
+    
+    /// Computes a sender's transfer fee under the given chain parameters
+    fn fee_under(&self, params: &ChainParams, from_address: &str, amount: u64) -> u64 {
+        match self.is_fee_exempt(from_address) {
+            true => 0,
+            false => params.transfer_fee(amount),
+        }
+    }
+    
+    /// Gets the chain parameters in effect and the changes queued for later blocks
+    pub fn param_schedule(&self) -> ParamSchedule {
+        self.param_schedule.lock().unwrap().clone()
+    }
+    
+    /// Queues a chain parameter change
+    ///
+    /// This is the entry point for passed governance proposals and, while the
+    /// network bootstraps, the parameter admin RPC. The change takes effect when
+    /// the block producer reaches its activation block.
+    ///
+    /// # Arguments
+    /// * `change` - The parameter, its new value and the activation block
+    ///
+    /// # Returns
+    /// Ok if the change was queued, or an error if the value is out of range or
+    /// the activation block is closer than `params::MIN_ACTIVATION_DELAY_BLOCKS`
+    pub fn schedule_param_change(&self, change: PendingParamChange) -> Result<(), ParamChangeError> {
+        // Measure the delay from the chain height even if no block applied the schedule yet
+        let current_block = self.get_block_producer()
+            .map(|producer| producer.current_block())
+            .unwrap_or(0);
+        
+        let mut schedule = self.param_schedule.lock().unwrap();
+        schedule.height = schedule.height.max(current_block);
+        schedule.schedule(change)?;
+        log::info!("Scheduled chain parameter change: {} = {} at block {}",
+                   change.param, change.new_value, change.activates_at_block);
+        Ok(())
+    }
+    
+    /// Applies the chain parameter changes due at a block
+    ///
+    /// Called by the block producer before executing a produced or imported
+    /// block, so the block's transfers and reward already use the new values.
+    ///
+    /// # Arguments
+    /// * `height` - Height of the block about to be executed
+    ///
+    /// # Returns
+    /// The changes that took effect
+    pub fn apply_param_changes(&self, height: u64) -> Vec<PendingParamChange> {
+        let applied = self.param_schedule.lock().unwrap().apply(height);
+        for change in &applied {
+            log::info!("Chain parameter {} changed to {} at block {}", change.param, change.new_value, height);
+        }
+        if applied.iter().any(|change| change.param == ChainParam::UbiTokensPerHour) {
+            self.refresh_streaming_rates();
+        }
+        applied
+    }
+    
+    /// Appends an entry to the fee ledger; callers hold the fee pool lock
+    fn append_fee_ledger(&self, timestamp: u64, block_number: u64, kind: FeeLedgerKind, amount: u64) {
+        let mut ledger = self.fee_ledger.lock().unwrap();
+        let sequence = ledger.len() as u64;
+        ledger.push(FeeLedgerEntry { sequence, timestamp, block_number, kind, amount });
+    }
+    
+    /// Appends a change to an account's statement; callers hold the accounts lock
+    fn record_balance_change(&self, address: &str, timestamp: u64, kind: BalanceChangeKind, credit: u64, debit: u64, balance_after: u64) {
+        self.balance_history.lock().unwrap().record(address, timestamp, kind, credit, debit, balance_after);
+    }
+    
+    /// Gets an account's statement: its balance changes within a time range
+    ///
     /// # Arguments
-    /// * `address` - The account address to update
-    /// 
+    /// * `address` - The account address, in any casing
+    /// * `from_timestamp` - Earliest change to include, in seconds since epoch
+    /// * `to_timestamp` - Latest change to include, in seconds since epoch
+    pub fn account_statement(&self, address: &str, from_timestamp: u64, to_timestamp: u64) -> Statement {
+        self.balance_history.lock().unwrap().statement(address, from_timestamp, to_timestamp)
+    }
+    
+    /// Gets fee ledger entries recorded within a block range, oldest first
+    ///
+    /// # Arguments
+    /// * `start` - Sequence number of the first entry to consider
+    /// * `from_block` - Lowest block number to include
+    /// * `to_block` - Highest block number to include
+    /// * `limit` - Maximum number of entries to return
+    pub fn fee_ledger_entries(&self, start: u64, from_block: u64, to_block: u64, limit: usize) -> Vec<FeeLedgerEntry> {
+        self.fee_ledger.lock().unwrap().iter()
+            .skip(start as usize)
+            .filter(|entry| (from_block..=to_block).contains(&entry.block_number))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+    
+    /// Gets the total fees ever distributed from the pool
+    pub fn get_total_fees_distributed(&self) -> u64 {
+        self.dividends.read().unwrap().fees_distributed
+    }
+    
+    /// Gets how much UBI an account has received and how many times it claimed
+    ///
+    /// # Arguments
+    /// * `address` - The account address
+    ///
     /// # Returns
-    /// The amount of new dividends credited to the account
-    pub fn update_account_dividends(&self, address: &str) -> u64 {
-        if !is_valid_eth_address(address) {
-            return 0;
+    /// The account's lifetime statistics, all zero if it doesn't exist
+    pub fn get_ubi_stats(&self, address: &str) -> UbiStats {
+        self.accounts.read().unwrap().get(&address.to_lowercase())
+            .map_or_else(UbiStats::default, |account| account.ubi_stats)
+    }
+    
+    /// Gets the UBI minted, fees collected and dividends distributed since genesis
+    pub fn get_network_stats(&self) -> NetworkStats {
+        network_stats(&self.dividends.read().unwrap())
+    }
+    
+    /// Gets the current total in the fee pool
+    ///
+    /// # Returns
+    /// The current amount in the fee pool
+    pub fn get_fee_pool(&self) -> u64 {
+        self.dividends.read().unwrap().fee_pool
+    }
+
+    /// Creates a checkpoint of the current state
+    ///
+    /// The file is written atomically: a failed write leaves no checkpoint
+    /// file behind and is counted in `checkpoint_health`.
+    ///
+    /// # Arguments
+    /// * `force` - Whether to force checkpoint creation even if no changes since last checkpoint
+    ///
+    /// # Returns
+    /// Result containing the created checkpoint or an error
+    pub fn create_checkpoint(&self, force: bool) -> io::Result<StateCheckpoint> {
+        let result = self.write_checkpoint(force);
+        
+        let mut health = self.checkpoint_health.lock().unwrap();
+        match &result {
+            Ok(_) => {
+                health.consecutive_failures = 0;
+                health.last_error = None;
+                health.last_success = Some(current_time_secs());
+            },
+            Err(e) => {
+                health.consecutive_failures += 1;
+                health.last_error = Some(e.to_string());
+            },
+        }
+        
+        result
+    }
+    
+    /// Gets the outcome of recent checkpoint writes
+    pub fn checkpoint_health(&self) -> CheckpointHealth {
+        self.checkpoint_health.lock().unwrap().clone()
+    }
+    
+    /// Gets the store where components record transaction lifecycle events
+    pub fn transaction_traces(&self) -> &TransactionTraceStore {
+        &self.tx_traces
+    }
+    
+    /// Writes a checkpoint file and records it in the checkpoint list
+    fn write_checkpoint(&self, force: bool) -> io::Result<StateCheckpoint> {
+        // Ensure checkpoint directory exists
+        if !Path::new(&self.checkpoint_dir).exists() {
+            prepare_checkpoint_dir(&self.checkpoint_dir)?;
+        }
+        
+        let mut data = self.capture_state();
+        
+        // Check if we already have a checkpoint with this root hash. The list
+        // stays locked until the new checkpoint is in it, so writes never overlap
+        let mut checkpoints = self.checkpoints.lock().unwrap();
+        if !force {
+            if let Some(last_checkpoint) = checkpoints.last() {
+                if last_checkpoint.root_hash == data.root_hash {
+                    // No changes since last checkpoint
+                    return Ok(last_checkpoint.clone());
+                }
+            }
+        }
+        
+        // Stamp the checkpoint once it holds the list, so names follow write order
+        data.timestamp = current_time_secs();
+        let file_path = format!("{}/checkpoint_{}.dat", self.checkpoint_dir, data.timestamp);
+        
+        // Serialize state to file in the current checkpoint format
+        let bytes = checkpoint::encode_checkpoint_with_compression(&data, self.compress_checkpoints);
+        checkpoint::write_checkpoint_file(Path::new(&file_path), &bytes)?;
+        
+        // Create checkpoint object
+        let checkpoint = StateCheckpoint {
+            timestamp: data.timestamp,
+            root_hash: data.root_hash,
+            account_count: data.accounts.len(),
+            total_supply: data.total_supply,
+            fee_pool: data.fee_pool,
+            file_path,
+        };
+        
+        // Add to checkpoints list, replacing one written earlier in the same second
+        // since it shared the file, and release it before pruning takes the lock again
+        checkpoints.retain(|existing| existing.file_path != checkpoint.file_path);
+        checkpoints.push(checkpoint.clone());
+        drop(checkpoints);
+        
+        // Prune old checkpoints if we have too many
+        self.prune_checkpoints();
+        
+        Ok(checkpoint)
+    }
+    
+    /// Captures the whole state at one point in time, as a checkpoint holds it
+    ///
+    /// The gate waits for in-flight transfers and credits, and the account map
+    /// clone is O(1), so the exclusive section is short and the state can be
+    /// written out without holding any state lock, the state tree included.
+    /// Dividend state is taken first because claim_dividends holds the
+    /// dividends lock while it takes the accounts lock, and is compacted while
+    /// it is held. Nonces come before the gate, as they do for transactions,
+    /// so no transaction is halfway through.
+    ///
+    /// # Returns
+    /// The state, stamped with the current time and the state root
+    fn capture_state(&self) -> CheckpointData {
+        let nonces_guard = self.nonces.lock().unwrap();
+        let gate = self.state_gate.write().unwrap();
+        let (streams, next_stream_id) = {
+            let streams = self.payment_streams.read().unwrap();
+            (streams.all(), streams.next_id())
+        };
+        let mut dividends_guard = self.dividends.write().unwrap();
+        let account_limits = self.account_limits.lock().unwrap().clone();
+        let allowances = checkpoint_allowances(&self.allowances.lock().unwrap());
+        let accounts = self.accounts.read().unwrap().clone();
+        let compacted = dividends_guard.compact(|address| accounts.contains_key(address));
+        let dividends = dividends_guard.clone();
+        drop(dividends_guard);
+        let fee_ledger = self.fee_ledger.lock().unwrap().clone();
+        drop(gate);
+        let nonces = nonces_guard.clone();
+        drop(nonces_guard);
+        if compacted > 0 {
+            log::debug!("Compacted {} dividend entries", compacted);
+        }
+        
+        // Update Merkle tree with current account states and get the root hash
+        let root_hash = {
+            let mut state_tree = self.state_tree.lock().unwrap();
+            refresh_state_tree(&mut state_tree, &accounts, &nonces);
+            state_tree.root_hash().unwrap_or([0; 32])
+        };
+        
+        CheckpointData {
+            timestamp: current_time_secs(),
+            root_hash,
+            total_supply: dividends.total_supply,
+            fee_pool: dividends.fee_pool,
+            dividend_per_token: dividends.dividend_per_token,
+            accounts: accounts.iter()
+                .map(|(address, account)| CheckpointAccount {
+                    address: address.clone(),
+                    balance: account.balance,
+                    verified: account.verified,
+                    // Last UBI claim as seconds since epoch
+                    last_ubi_claim_secs: account.last_ubi_claim
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap_or(Duration::from_secs(0))
+                        .as_secs(),
+                    last_dividend_point: dividends.point(address),
+                    unclaimed_dividends: dividends.unclaimed(address),
+                    limits: account_limits.get(address).cloned(),
+                    verified_at_secs: account.verified_at.map(system_time_secs),
+                    nonce: nonces.get(address).copied().unwrap_or(0),
+                    frozen: account.frozen,
+                    frozen_reason: account.frozen_reason.clone(),
+                    locks: account.locked_balances.clone(),
+                    auto_compound: account.auto_compound,
+                    ubi_stats: account.ubi_stats,
+                })
+                .collect(),
+            fee_ledger,
+            streams,
+            next_stream_id,
+            allowances,
+            network_stats: network_stats(&dividends),
+        }
+    }
+    
+    /// Loads state from a checkpoint
+    ///
+    /// # Arguments
+    /// * `checkpoint` - The checkpoint to load
+    ///
+    /// # Returns
+    /// Result indicating success or failure
+    pub fn load_checkpoint(&self, checkpoint: &StateCheckpoint) -> io::Result<()> {
+        let data = self.read_checkpoint(checkpoint)?;
+        self.replace_state(data)
+    }
+    
+    /// Compares the state held by two checkpoints
+    ///
+    /// Both files are read without touching the running state.
+    ///
+    /// # Arguments
+    /// * `a` - The earlier checkpoint
+    /// * `b` - The later checkpoint
+    ///
+    /// # Returns
+    /// The accounts created, removed and changed from `a` to `b` and the
+    /// change in total supply and fee pool, or an error if either file
+    /// cannot be read
+    pub fn diff_checkpoints(&self, a: &StateCheckpoint, b: &StateCheckpoint) -> io::Result<StateDiff> {
+        Ok(StateDiff::between(&self.read_checkpoint(a)?, &self.read_checkpoint(b)?))
+    }
+    
+    /// Reads a checkpoint file and checks it is the checkpoint described
+    fn read_checkpoint(&self, checkpoint: &StateCheckpoint) -> io::Result<CheckpointData> {
+        let (_, data) = checkpoint::decode_checkpoint_with_limit(
+            &fs::read(&checkpoint.file_path)?,
+            self.max_checkpoint_accounts,
+        )?;
+        
+        // Verify header
+        if data.timestamp != checkpoint.timestamp {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Checkpoint timestamp mismatch"
+            ));
+        }
+        
+        if data.root_hash != checkpoint.root_hash {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Checkpoint root hash mismatch"
+            ));
+        }
+        
+        Ok(data)
+    }
+    
+    /// Replaces the whole state and the state store's contents
+    ///
+    /// # Arguments
+    /// * `data` - The new state
+    ///
+    /// # Returns
+    /// An error if the state cannot be restored or stored; a state that
+    /// cannot be restored leaves the old one untouched
+    fn replace_state(&self, data: CheckpointData) -> io::Result<()> {
+        // Nothing observes a mix of the old and the restored state
+        let mut nonces = self.nonces.lock().unwrap();
+        let _gate = self.state_gate.write().unwrap();
+        self.restore_state(data, &mut nonces)?;
+        
+        // The store holds the restored state, not a mix of it and what it held before
+        let mut store = self.state_store.lock().unwrap();
+        if let Some(attached) = store.as_mut() {
+            let state = self.stored_state(&nonces);
+            attached.store.replace(&state)?;
+            attached.fee_entries = state.fee_ledger.len();
+        }
+        
+        Ok(())
+    }
+    
+    /// Replaces the state with the contents of a checkpoint
+    ///
+    /// The caller holds the nonce lock and the state gate exclusively.
+    ///
+    /// # Arguments
+    /// * `data` - The checkpoint contents
+    /// * `nonces` - The nonces, replaced as well
+    ///
+    /// # Returns
+    /// An error if an account time is out of range; the state is then untouched
+    fn restore_state(&self, data: CheckpointData, nonces: &mut HashMap<String, u64>) -> io::Result<()> {
+        // Times are checked before anything is replaced, so a bad file leaves the state untouched
+        let mut restored = Vec::with_capacity(data.accounts.len());
+        for account in &data.accounts {
+            restored.push(Account {
+                address: account.address.clone(),
+                balance: account.balance,
+                verified: account.verified,
+                last_ubi_claim: checkpoint_time(account.last_ubi_claim_secs)?,
+                verified_at: account.verified_at_secs.map(checkpoint_time).transpose()?,
+                streaming_rate: if account.verified { self.ubi_streaming_rate() } else { 0 },
+                frozen: account.frozen,
+                frozen_reason: account.frozen_reason.clone(),
+                locked_balances: account.locks.clone(),
+                auto_compound: account.auto_compound,
+                ubi_stats: account.ubi_stats,
+            });
+        }
+        
+        *nonces = data.accounts.iter()
+            .filter(|account| account.nonce != 0)
+            .map(|account| (account.address.clone(), account.nonce))
+            .collect();
+        
+        // Ledgers written before distributions recorded their dividend per token value get it filled in
+        let mut ledger = data.fee_ledger;
+        fee_ledger::fill_dividend_per_token(&mut ledger, data.dividend_per_token);
+        *self.fee_ledger.lock().unwrap() = ledger;
+        *self.payment_streams.write().unwrap() = PaymentStreams::restore(data.streams, data.next_stream_id);
+        
+        // Restore the supply and dividend tracking (legacy checkpoints start from zero)
+        // before taking the accounts lock, matching the order claim_dividends uses
+        {
+            let mut dividends = self.dividends.write().unwrap();
+            dividends.total_supply = data.total_supply;
+            dividends.fee_pool = data.fee_pool;
+            dividends.dividend_per_token = data.dividend_per_token;
+            dividends.ubi_minted = data.network_stats.total_ubi_minted;
+            dividends.fees_collected = data.network_stats.total_fees_collected;
+            dividends.fees_distributed = data.network_stats.total_dividends_distributed;
+            dividends.restore_accounts(data.accounts.iter()
+                .map(|account| (account.address.as_str(), account.last_dividend_point, account.unclaimed_dividends)));
+        }
+        *self.account_limits.lock().unwrap() = data.accounts.iter()
+            .filter_map(|account| account.limits.clone().map(|limits| (account.address.clone(), limits)))
+            .collect();
+        *self.allowances.lock().unwrap() = data.allowances.into_iter()
+            .filter(|allowance| allowance.amount > 0)
+            .map(|allowance| ((allowance.owner, allowance.spender), allowance.amount))
+            .collect();
+        
+        // Replace account data
+        let mut accounts = self.accounts.write().unwrap();
+        accounts.clear();
+        for account in restored {
+            accounts.insert(account.address.clone(), account);
+        }
+        
+        // Statements start again at the restored balances
+        self.balance_history.lock().unwrap().restart(
+            accounts.iter().map(|(address, account)| (address.as_str(), account.balance)),
+            data.timestamp,
+        );
+        
+        // Rebuild Merkle tree
+        let mut state_tree = self.state_tree.lock().unwrap();
+        *state_tree = MerkleTree::new();
+        refresh_state_tree(&mut state_tree, &accounts, nonces);
+        
+        Ok(())
+    }
+    
+    /// Reports where the total supply is held
+    ///
+    /// Balances, the fee pool and dividend state are read at one point in
+    /// time, so the report is consistent even while transfers run.
+    pub fn supply_report(&self) -> SupplyReport {
+        let gate = self.state_gate.write().unwrap();
+        let dividends = self.dividends.read().unwrap().clone();
+        let accounts = self.accounts.read().unwrap().clone();
+        let (fee_ledger_net, fees_burned) = {
+            let ledger = self.fee_ledger.lock().unwrap();
+            let burned: u64 = ledger.iter()
+                .filter(|entry| matches!(entry.kind, FeeLedgerKind::Burned { .. }))
+                .map(|entry| entry.amount)
+                .sum();
+            (fee_ledger::ledger_net(ledger.iter()), burned)
+        };
+        let voluntary_burned: u64 = self.supply_events.lock().unwrap().iter()
+            .filter(|event| event.kind == SupplyEventKind::Burned)
+            .map(|event| event.amount)
+            .sum();
+        drop(gate);
+        
+        let (total_supply, fee_pool) = (dividends.total_supply, dividends.fee_pool);
+        let account_balances: u64 = accounts.values().map(|account| account.balance).sum();
+        let dividends_owed: u64 = accounts.values()
+            .map(|account| dividends.owed(&account.address, account.balance))
+            .sum();
+        
+        SupplyReport {
+            total_supply,
+            account_balances,
+            fee_pool,
+            dividends_owed,
+            account_count: accounts.len(),
+            unaccounted: total_supply as i128 - (account_balances as i128 + fee_pool as i128 + dividends_owed as i128),
+            fee_ledger_net,
+            fee_ledger_discrepancy: fee_pool as i128 - fee_ledger_net,
+            total_burned: fees_burned + voluntary_burned,
+        }
+    }
+    
+    /// Copies the account store and the nonces the state tree hashes
+    ///
+    /// Nonces are taken first, as transactions take them, so no transaction
+    /// is between its transfer and its nonce.
+    fn tree_snapshot(&self) -> (im::HashMap<String, Account>, HashMap<String, u64>) {
+        let nonces = self.nonces.lock().unwrap();
+        let accounts = self.accounts.read().unwrap().clone();
+        (accounts, nonces.clone())
+    }
+    
+    /// Gets the current state root as 0x-prefixed hex, all zeros if there are no accounts
+    pub fn state_root(&self) -> String {
+        let (accounts, nonces) = self.tree_snapshot();
+        let mut state_tree = self.state_tree.lock().unwrap();
+        refresh_state_tree(&mut state_tree, &accounts, &nonces);
+        root_hex(state_tree.root_hash())
+    }
+    
+    /// Proves an account's current state against the current state root
+    ///
+    /// # Returns
+    /// The proof, or None if the account does not exist
+    pub fn account_proof(&self, address: &str) -> Option<AccountProof> {
+        let (root, state, path) = self.get_account_proof(address)?;
+        Some(AccountProof::new(root, address, &state, &path))
+    }
+    
+    /// Gets an account's leaf state and Merkle path with the root they lead to
+    ///
+    /// The tree is brought up to date and read under its lock, so the root,
+    /// the leaf and the path always belong to the same state.
+    ///
+    /// # Returns
+    /// The root, the account's leaf state and the sibling path, or None if
+    /// the account does not exist
+    pub fn get_account_proof(&self, address: &str) -> Option<([u8; 32], AccountState, MerklePath)> {
+        let address = address.to_lowercase();
+        let (accounts, nonces) = self.tree_snapshot();
+        let state = accounts.get(&address)?.merkle_state(nonces.get(&address).copied().unwrap_or(0));
+        let mut state_tree = self.state_tree.lock().unwrap();
+        refresh_state_tree(&mut state_tree, &accounts, &nonces);
+        let path = state_tree.generate_proof(&address)?;
+        Some((state_tree.root_hash()?, state, path))
+    }
+    
+    /// Rebuilds the state tree from the account store and compares roots
+    ///
+    /// The incrementally maintained tree is first brought up to date the way
+    /// checkpoints do it, then a fresh tree is built with the accounts in the
+    /// same leaf order. Leaves of removed accounts or leaves that missed an
+    /// update make the roots differ.
+    pub fn recompute_state_root(&self) -> StateRootCheck {
+        let (accounts, nonces) = self.tree_snapshot();
+        let mut state_tree = self.state_tree.lock().unwrap();
+        refresh_state_tree(&mut state_tree, &accounts, &nonces);
+        
+        let mut leaf_order: Vec<(&String, &usize)> = state_tree.address_indices.iter().collect();
+        leaf_order.sort_unstable_by_key(|(_, index)| **index);
+        let mut recomputed = MerkleTree::new();
+        recomputed.update_accounts(leaf_order.into_iter()
+            .filter_map(|(address, _)| accounts.get(address))
+            .map(|account| (account.address.as_str(), account.merkle_state(nonces.get(&account.address).copied().unwrap_or(0)))));
+        
+        let incremental_root = state_tree.root_hash();
+        let recomputed_root = recomputed.root_hash();
+        StateRootCheck {
+            incremental_root: root_hex(incremental_root),
+            recomputed_root: root_hex(recomputed_root),
+            matches: incremental_root == recomputed_root,
+            leaf_count: state_tree.leaves.len(),
+            account_count: accounts.len(),
+        }
+    }
+    
+    /// Prunes old checkpoints to keep storage lean
+    fn prune_checkpoints(&self) {
+        let mut checkpoints = self.checkpoints.lock().unwrap();
+        
+        // If we have more checkpoints than the maximum, remove the oldest ones
+        while checkpoints.len() > self.max_checkpoints {
+            if let Some(oldest) = checkpoints.first().cloned() {
+                // Remove from filesystem
+                let _ = fs::remove_file(&oldest.file_path);
+                
+                // Remove from list
+                checkpoints.remove(0);
+            }
+        }
+    }
+    
+    /// Gets a list of all available checkpoints
+    ///
+    /// # Returns
+    /// Vector of available checkpoints
+    pub fn list_checkpoints(&self) -> Vec<StateCheckpoint> {
+        self.checkpoints.lock().unwrap().clone()
+    }
+    
+    /// Gets the latest checkpoint
+    ///
+    /// # Returns
+    /// Option containing the latest checkpoint, if any
+    pub fn latest_checkpoint(&self) -> Option<StateCheckpoint> {
+        self.checkpoints.lock().unwrap().last().cloned()
+    }
+    
+    /// Reads the checkpoint files in the checkpoint directory into the checkpoint list
+    ///
+    /// Only the uncompressed header of each file is read. Files whose header
+    /// cannot be read or is in a format the runtime does not load are skipped
+    /// with a warning; damage further in is caught when the checkpoint is
+    /// loaded. The scanned list
+    /// replaces the one in memory, so checkpoints written before a restart
+    /// are listed and pruned like new ones.
+    ///
+    /// # Returns
+    /// The readable checkpoints, oldest first, or an error if the directory
+    /// exists but cannot be read
+    pub fn scan_checkpoints(&self) -> io::Result<Vec<StateCheckpoint>> {
+        let entries = match fs::read_dir(&self.checkpoint_dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        
+        let mut scanned = Vec::new();
+        for path in entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+            if !path.is_file() || path.extension().is_none_or(|ext| ext != "dat") {
+                continue;
+            }
+            match checkpoint::read_checkpoint_header(&path, self.max_checkpoint_accounts) {
+                Ok(header) => scanned.push(StateCheckpoint {
+                    timestamp: header.timestamp,
+                    root_hash: header.root_hash,
+                    account_count: header.account_count as usize,
+                    total_supply: header.total_supply,
+                    fee_pool: header.fee_pool,
+                    file_path: path.to_string_lossy().into_owned(),
+                }),
+                Err(e) => log::warn!("Skipping unreadable checkpoint {}: {}", path.display(), e),
+            }
+        }
+        scanned.sort_by(|a, b| (a.timestamp, &a.file_path).cmp(&(b.timestamp, &b.file_path)));
+        
+        *self.checkpoints.lock().unwrap() = scanned.clone();
+        Ok(scanned)
+    }
+    
+    /// Restores the newest checkpoint in the checkpoint directory that loads
+    ///
+    /// Scans the directory first, then tries the checkpoints newest first. A
+    /// checkpoint that fails to load leaves the state untouched and is skipped
+    /// with a warning.
+    ///
+    /// # Returns
+    /// The checkpoint restored, None if there was none that loads, or an
+    /// error if the directory cannot be read
+    pub fn restore_latest(&self) -> io::Result<Option<StateCheckpoint>> {
+        for checkpoint in self.scan_checkpoints()?.into_iter().rev() {
+            match self.load_checkpoint(&checkpoint) {
+                Ok(()) => return Ok(Some(checkpoint)),
+                Err(e) => log::warn!("Skipping checkpoint {} that failed to load: {}", checkpoint.file_path, e),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Upgrades a checkpoint file to the current format in place
+    ///
+    /// The file may be written in any known version. It is rewritten only
+    /// after the upgraded contents were read back and found to hold the same
+    /// state, and the rewrite is atomic. The running state is not changed;
+    /// pass the result to `load_checkpoint` to restore it.
+    ///
+    /// # Arguments
+    /// * `path` - Path of the checkpoint file
+    ///
+    /// # Returns
+    /// The upgraded checkpoint, or an error if the file cannot be read,
+    /// upgraded or written
+    pub fn migrate_checkpoint(&self, path: impl AsRef<Path>) -> io::Result<StateCheckpoint> {
+        let path = path.as_ref();
+        let upgraded = checkpoint::upgrade_checkpoint(&fs::read(path)?)?;
+        if upgraded.data.accounts.len() as u64 > self.max_checkpoint_accounts {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Checkpoint holds more than the limit of {} accounts", self.max_checkpoint_accounts)
+            ));
         }
-        
-        let accounts = self.accounts.lock().unwrap();
-        let account = match accounts.get(address) {
-            Some(acc) => acc,
-            None => return 0,
-        };
-        
-        let balance = account.balance;
-        let current_dividend_per_token = *self.dividend_per_token.lock().unwrap();
-        
-        // Get the last dividend point seen by this account
-        let mut last_points = self.last_dividend_points.lock().unwrap();
-        let last_point = *last_points.get(address).unwrap_or(&0);
-        
-        // Calculate new dividends owed
-        let point_diff = current_dividend_per_token - last_point;
-        let new_dividends = (balance * point_diff) / DIVIDEND_PRECISION;
-        
-        // Update the account's last dividend point
-        last_points.insert(address.to_string(), current_dividend_per_token);
-        drop(last_points); // Release the lock before acquiring a new one
-        
-        // Add to unclaimed dividends
-        if new_dividends > 0 {
-            let mut unclaimed = self.unclaimed_dividends.lock().unwrap();
-            let current_unclaimed = *unclaimed.get(address).unwrap_or(&0);
-            unclaimed.insert(address.to_string(), current_unclaimed + new_dividends);
+        checkpoint::write_checkpoint_file(path, &upgraded.bytes)?;
+        log::info!("Migrated checkpoint {} from version {} to {}",
+                   path.display(), upgraded.from_version, checkpoint::CURRENT_CHECKPOINT_VERSION);
+
+        Ok(StateCheckpoint {
+            timestamp: upgraded.data.timestamp,
+            root_hash: upgraded.data.root_hash,
+            account_count: upgraded.data.accounts.len(),
+            total_supply: upgraded.data.total_supply,
+            fee_pool: upgraded.data.fee_pool,
+            file_path: path.to_string_lossy().into_owned(),
+        })
+    }
+
+    /// Copies the runtime into one that shares no state with it
+    ///
+    /// The copy runs operations the way this runtime would, so a block can be
+    /// tried on it before it is applied here. It has no state store or block
+    /// producer, and starts with empty logs: the fee ledger, balance history,
+    /// supply, UBI mint and verification events, checkpoints and traces.
+    pub fn detached_copy(&self) -> Runtime {
+        fn copied<T: Clone>(shared: &Arc<std::sync::Mutex<T>>) -> Arc<std::sync::Mutex<T>> {
+            Arc::new(std::sync::Mutex::new(shared.lock().unwrap().clone()))
+        }
+        fn copied_rw<T: Clone>(shared: &Arc<std::sync::RwLock<T>>) -> Arc<std::sync::RwLock<T>> {
+            Arc::new(std::sync::RwLock::new(shared.read().unwrap().clone()))
         }
         
-        new_dividends
+        // Nothing changes while the state is copied, so the copy is consistent
+        let nonces = self.nonces.lock().unwrap();
+        let _gate = self.state_gate.write().unwrap();
+        let fresh = Runtime::default();
+        Runtime {
+            accounts: copied_rw(&self.accounts),
+            dividends: copied_rw(&self.dividends),
+            payment_streams: copied_rw(&self.payment_streams),
+            state_tree: copied(&self.state_tree),
+            account_limits: copied(&self.account_limits),
+            allowances: copied(&self.allowances),
+            param_schedule: copied(&self.param_schedule),
+            export_nonce: copied(&self.export_nonce),
+            nonces: Arc::new(std::sync::Mutex::new(nonces.clone())),
+            imported_exports: copied(&self.imported_exports),
+            burn_address: self.burn_address.clone(),
+            reserved: copied(&self.reserved),
+            maintenance: copied(&self.maintenance),
+            faucet_tiers: copied(&self.faucet_tiers),
+            faucet_records: copied(&self.faucet_records),
+            genesis_faucet: copied(&self.genesis_faucet),
+            fee_exempt: copied(&self.fee_exempt),
+            faucet_sources: copied(&self.faucet_sources),
+            bootstrap_window: copied(&self.bootstrap_window),
+            max_checkpoints: self.max_checkpoints,
+            checkpoint_dir: self.checkpoint_dir.clone(),
+            max_checkpoint_accounts: self.max_checkpoint_accounts,
+            compress_checkpoints: self.compress_checkpoints,
+            max_ubi_accrual_hours: self.max_ubi_accrual_hours,
+            fee_distribution_interval_blocks: self.fee_distribution_interval_blocks,
+            clock: self.clock.clone(),
+            new_accounts: self.new_accounts,
+            admin_nonce: copied(&self.admin_nonce),
+            ..fresh
+        }
     }
-    
-    /// Claims the dividends for an account and adds them to the account balance
-    /// 
+
+    /// Captures the whole state as a JSON state snapshot
+    ///
+    /// # Returns
+    /// Every account sorted by address, the supply and fee pool, the fee
+    /// ledger and the state root of the accounts
+    pub fn state_snapshot(&self) -> StateSnapshot {
+        StateSnapshot::from_checkpoint(&self.capture_state())
+    }
+
+    /// Writes the whole state to a file as a JSON state snapshot
+    ///
     /// # Arguments
-    /// * `address` - The account address to claim dividends for
-    /// 
+    /// * `path` - File to write the snapshot to
+    ///
     /// # Returns
-    /// The amount of dividends claimed
-    pub fn claim_dividends(&self, address: &str) -> u64 {
-        if !is_valid_eth_address(address) {
-            return 0;
-        }
-        
-        // First update the account's dividends to ensure all owed dividends are accounted for
-        self.update_account_dividends(address);
+    /// The snapshot written, or an error if the file cannot be written
+    pub fn export_state_json(&self, path: impl AsRef<Path>) -> io::Result<StateSnapshot> {
+        let snapshot = self.state_snapshot();
+        fs::write(path, snapshot.to_json())?;
+        Ok(snapshot)
+    }
+
+    /// Replaces the whole state with a state snapshot
+    ///
+    /// The snapshot is checked before anything changes: every address must be
+    /// a well-formed Ethereum address that appears once, and the state root
+    /// must match the accounts.
+    ///
+    /// # Arguments
+    /// * `snapshot` - The state to restore
+    ///
+    /// # Returns
+    /// An error with `io::ErrorKind::InvalidData` wrapping the
+    /// `StateSnapshotError` if the snapshot is refused, in which case the
+    /// state is untouched
+    pub fn import_state_snapshot(&self, snapshot: &StateSnapshot) -> io::Result<()> {
+        let data = snapshot.to_checkpoint().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        self.replace_state(data)?;
+        log::info!("Imported state snapshot with {} accounts and state root {}", snapshot.accounts.len(), snapshot.state_root);
+        Ok(())
+    }
+
+    /// Replaces the whole state with a JSON state snapshot read from a file
+    ///
+    /// # Arguments
+    /// * `path` - File holding the snapshot
+    ///
+    /// # Returns
+    /// The snapshot imported, or why it was refused; see `import_state_snapshot`
+    pub fn import_state_json(&self, path: impl AsRef<Path>) -> io::Result<StateSnapshot> {
+        let snapshot = StateSnapshot::from_json(&fs::read_to_string(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        self.import_state_snapshot(&snapshot)?;
+        Ok(snapshot)
+    }
+
+    /// Credits tokens to an account
+    ///
+    /// # Arguments
+    /// * `address` - The account address
+    /// * `amount` - The amount to credit
+    ///
+    /// # Returns
+    /// Result containing the new balance or an error
+    pub fn credit_balance(&self, address: &str, amount: u64) -> Result<u64, AccountError> {
+        self.credit_balance_as(address, amount, BalanceChangeKind::Credit)
+    }
+    
+    /// Credits tokens to an account, recording the change on its statement as `kind`
+    fn credit_balance_as(&self, address: &str, amount: u64, kind: BalanceChangeKind) -> Result<u64, AccountError> {
+        // Normalize address to lowercase for consistent lookup
+        let address_lower = address.to_lowercase();
         
-        // Get the unclaimed dividends
-        let mut unclaimed = self.unclaimed_dividends.lock().unwrap();
-        let to_claim = *unclaimed.get(address).unwrap_or(&0);
+        // The balance and the supply change together
+        let _gate = self.state_gate.read().unwrap();
+        self.check_maintenance()?;
         
-        if to_claim == 0 {
-            return 0;
-        }
+        // The supply is locked first, so both sums are checked before anything changes
+        let mut dividends = match self.dividends.write() {
+            Ok(guard) => guard,
+            Err(e) => {
+                log::error!("Failed to acquire lock on total supply: {:?}", e);
+                return Err(AccountError::Other(format!("Failed to acquire lock on total supply: {:?}", e)));
+            }
+        };
         
-        // Reset unclaimed dividends
-        unclaimed.insert(address.to_string(), 0);
+        // Acquire lock on accounts
+        let mut accounts_guard = match self.accounts.write() {
+            Ok(guard) => guard,
+            Err(e) => {
+                log::error!("Failed to acquire lock on accounts: {:?}", e);
+                return Err(AccountError::Other(format!("Failed to acquire lock on accounts: {:?}", e)));
+            }
+        };
         
-        // Add to account balance
-        let mut accounts = self.accounts.lock().unwrap();
-        if let Some(account) = accounts.get_mut(address) {
-            account.balance += to_claim;
-        }
+        // Check if account exists
+        let account = match accounts_guard.get_mut(&address_lower) {
+            Some(account) => account,
+            None => return Err(AccountError::AccountNotFound(address_lower)),
+        };
+        let balance = account.balance.checked_add(amount)
+            .ok_or_else(|| AccountError::Overflow(format!("balance of {}", address_lower)))?;
+        let total_supply = dividends.total_supply.checked_add(amount)
+            .ok_or_else(|| AccountError::Overflow("total supply".to_string()))?;
+        
+        // Credit the account and the supply
+        dividends.settle_before_change(&address_lower, account.balance);
+        account.balance = balance;
+        self.record_balance_change(&address_lower, self.now(), kind, amount, 0, balance);
+        dividends.total_supply = total_supply;
+        drop(accounts_guard);
+        drop(dividends);
+        self.persist(&[&address_lower]);
         
-        to_claim
+        // Return the new balance
+        Ok(balance)
     }
-    
-    /// Gets the unclaimed dividends for an account
-    /// 
+
+    /// Pays a block reward and records it against the block's hash
+    ///
+    /// The supply event lets the reward be taken back with
+    /// `revert_block_reward` if the block later leaves the chain.
+    ///
     /// # Arguments
-    /// * `address` - The account address to check
-    /// 
+    /// * `address` - The producer's account, which must exist
+    /// * `amount` - The block reward
+    /// * `block_hash` - Hash of the block the reward is paid for
+    ///
     /// # Returns
-    /// The amount of unclaimed dividends
-    pub fn get_unclaimed_dividends(&self, address: &str) -> u64 {
-        if !is_valid_eth_address(address) {
-            return 0;
-        }
-        
-        // First update the account's dividends to ensure all owed dividends are accounted for
-        self.update_account_dividends(address);
-        
-        // Return the unclaimed dividends
-        let unclaimed = self.unclaimed_dividends.lock().unwrap();
-        *unclaimed.get(address).unwrap_or(&0)
+    /// The supply event recording the reward, or why it could not be paid
+    pub fn credit_block_reward(&self, address: &str, amount: u64, block_hash: &str) -> Result<SupplyEvent, AccountError> {
+        self.credit_balance_as(address, amount, BalanceChangeKind::BlockReward {
+            block_hash: block_hash.to_string(),
+        })?;
+        
+        let event = SupplyEvent {
+            kind: SupplyEventKind::BlockReward,
+            address: address.to_lowercase(),
+            amount,
+            source_chain_id: 0,
+            export_nonce: 0,
+            timestamp: self.now(),
+            total_supply_after: self.get_total_supply(),
+            tx_hash: None,
+            block_hash: Some(block_hash.to_string()),
+        };
+        self.supply_events.lock().unwrap().push(event.clone());
+        Ok(event)
     }
     
-    /// Updates the total supply when tokens are minted or burned
-    /// This should be called whenever the total supply changes
-    /// 
+    /// Takes back the reward paid for a block that left the chain
+    ///
+    /// Only as much as the producer still holds can be taken back; the event
+    /// records the amount actually removed from the supply. Reorgs run in
+    /// maintenance mode, so unlike other balance changes this is not refused
+    /// while it is on.
+    ///
     /// # Arguments
-    /// * `amount` - The amount to add (positive) or subtract (negative) from total supply
-    /// * `is_addition` - True if adding to supply, false if subtracting
-    pub fn update_total_supply(&self, amount: u64, is_addition: bool) {
-        let mut total_supply = self.total_supply.lock().unwrap();
+    /// * `block_hash` - Hash of the discarded block
+    ///
+    /// # Returns
+    /// The supply event recording the clawback, or None if the block's latest
+    /// reward was never paid or was already taken back
+    pub fn revert_block_reward(&self, block_hash: &str) -> Option<SupplyEvent> {
+        // The latest event for the hash decides, since block hashes repeat across forks
+        let reward = self.supply_events.lock().unwrap().iter().rev()
+            .find(|event| event.block_hash.as_deref() == Some(block_hash))
+            .filter(|event| event.kind == SupplyEventKind::BlockReward)
+            .cloned()?;
+        
+        let _gate = self.state_gate.read().unwrap();
+        let taken = {
+            let mut dividends = self.dividends.write().unwrap();
+            let mut accounts_guard = self.accounts.write().unwrap();
+            let account = accounts_guard.get_mut(&reward.address)?;
+            let taken = account.balance.min(reward.amount);
+            dividends.settle_before_change(&reward.address, account.balance);
+            account.balance -= taken;
+            self.record_balance_change(&reward.address, self.now(), BalanceChangeKind::BlockRewardReverted {
+                block_hash: block_hash.to_string(),
+            }, 0, taken, account.balance);
+            taken
+        };
+        self.adjust_total_supply(taken, false);
+        self.persist(&[&reward.address]);
         
-        if is_addition {
-            *total_supply += amount;
-        } else {
-            // Ensure we don't underflow
-            *total_supply = total_supply.saturating_sub(amount);
+        if taken < reward.amount {
+            log::warn!(address = reward.address.as_str(); "Reverted reward of block {} only partly: {} of {} tokens were still held",
+                       block_hash, taken, reward.amount);
         }
+        let event = SupplyEvent {
+            kind: SupplyEventKind::BlockRewardReverted,
+            amount: taken,
+            timestamp: self.now(),
+            total_supply_after: self.get_total_supply(),
+            ..reward
+        };
+        self.supply_events.lock().unwrap().push(event.clone());
+        Some(event)
     }
 
-    /// Transfers tokens from one account to another with a fee
+    /// Moves an account's entire balance to another account
+    ///
+    /// Used when an account is replaced by a new address, so no transfer fee is
+    /// charged and the total supply is unchanged. Locks move with the balance,
+    /// so tokens still vesting stay locked until the same time.
     ///
     /// # Arguments
-    /// * `from_address` - The sender's address
-    /// * `to_address` - The recipient's address
-    /// * `amount` - The amount to transfer
+    /// * `from_address` - The account being emptied
+    /// * `to_address` - The account receiving the balance
     ///
     /// # Returns
-    /// Result indicating success or an error
-    pub fn transfer_with_fee(&self, from_address: &str, to_address: &str, amount: u64) -> Result<(), AccountError> {
+    /// Result containing the amount moved or an error
+    pub fn move_balance(&self, from_address: &str, to_address: &str) -> Result<u64, AccountError> {
         // Normalize addresses to lowercase for consistent lookup
         let from_lower = from_address.to_lowercase();
         let to_lower = to_address.to_lowercase();
+        self.update_ubi_balance_at(&from_lower, self.now())?;
+        self.settle_streams_of(&from_lower, self.now());
         
-        // Special case for faucet address - always allow transfers from the faucet
-        let is_faucet_transfer = from_lower == FAUCET_ADDRESS.to_lowercase();
+        let _gate = self.state_gate.read().unwrap();
+        self.check_maintenance()?;
         
         // Acquire lock on accounts
-        let mut accounts_guard = match self.accounts.lock() {
+        let mut dividends = self.dividends.write().unwrap();
+        let mut accounts_guard = match self.accounts.write() {
             Ok(guard) => guard,
             Err(e) => {
                 log::error!("Failed to acquire lock on accounts: {:?}", e);
@@ -988,367 +6140,666 @@ impl Runtime {
             }
         };
         
-        // Check if sender account exists
-        if !accounts_guard.contains_key(&from_lower) && !is_faucet_transfer {
-            return Err(AccountError::Other(format!("Sender account {} does not exist", from_address)));
-        }
-        
-        // Check if recipient account exists
-        if !accounts_guard.contains_key(&to_lower) {
-            return Err(AccountError::Other(format!("Recipient account {} does not exist", to_address)));
+        let to_balance = match accounts_guard.get(&to_lower) {
+            Some(recipient) if recipient.frozen => return Err(AccountError::AccountFrozen(to_lower)),
+            Some(recipient) => recipient.balance,
+            None => return Err(AccountError::AccountNotFound(to_lower)),
+        };
+        let from_balance = match accounts_guard.get(&from_lower) {
+            Some(sender) if sender.frozen => return Err(AccountError::AccountFrozen(from_lower)),
+            Some(sender) => sender.balance,
+            None => return Err(AccountError::AccountNotFound(from_lower)),
+        };
+        if from_lower != to_lower && to_balance.checked_add(from_balance).is_none() {
+            return Err(AccountError::Overflow(format!("balance of {}", to_lower)));
         }
         
-        // Calculate fee (1% of transfer amount)
-        let fee = amount / 100;
-        let total_deduction = amount + fee;
-        
-        // Check if sender has sufficient balance (skip for faucet)
-        if !is_faucet_transfer {
-            let sender = accounts_guard.get(&from_lower).unwrap();
-            if sender.balance < total_deduction {
-                return Err(AccountError::Other(format!(
-                    "Insufficient balance: {} < {}", sender.balance, total_deduction
-                )));
-            }
-            
-            // Deduct from sender
-            let sender = accounts_guard.get_mut(&from_lower).unwrap();
-            sender.balance -= total_deduction;
-        }
+        dividends.settle_before_change(&from_lower, from_balance);
+        dividends.settle_before_change(&to_lower, to_balance);
+        drop(dividends);
+        let sender = accounts_guard.get_mut(&from_lower).unwrap();
+        let amount = std::mem::take(&mut sender.balance);
+        let locks = std::mem::take(&mut sender.locked_balances);
+        let now = self.now();
+        self.record_balance_change(&from_lower, now, BalanceChangeKind::MovedOut {
+            counterparty: to_lower.clone(),
+        }, 0, amount, 0);
         
-        // Add to recipient
         let recipient = accounts_guard.get_mut(&to_lower).unwrap();
         recipient.balance += amount;
-        
-        // Drop the accounts lock before acquiring the fee pool lock
-        // This helps avoid potential deadlocks
+        recipient.locked_balances.extend(locks.into_iter().filter(|lock| lock.is_active(now)));
+        self.record_balance_change(&to_lower, now, BalanceChangeKind::MovedIn {
+            counterparty: from_lower.clone(),
+        }, amount, 0, recipient.balance);
         drop(accounts_guard);
+        self.persist(&[&from_lower, &to_lower]);
         
-        // Add fee to pool
-        let mut fee_pool_guard = match self.fee_pool.lock() {
-            Ok(guard) => guard,
-            Err(e) => {
-                log::error!("Failed to acquire lock on fee pool: {:?}", e);
-                // The transfer was successful, so we'll just log the error and continue
-                log::warn!("Fee was not added to the pool, but transfer was successful");
-                return Ok(());
-            }
-        };
-        
-        *fee_pool_guard += fee;
-        
-        Ok(())
-    }
-    
-    /// Gets the current total in the fee pool
-    ///
-    /// # Returns
-    /// The current amount in the fee pool
-    pub fn get_fee_pool(&self) -> u64 {
-        *self.fee_pool.lock().unwrap()
+        Ok(amount)
     }
 
-    /// Creates a checkpoint of the current state
+    /// Opens a payment stream from one account to another
+    ///
+    /// Nothing moves when the stream opens; it is settled lazily, see
+    /// `payment_stream`.
     ///
     /// # Arguments
-    /// * `force` - Whether to force checkpoint creation even if no changes since last checkpoint
+    /// * `from_address` - The paying account
+    /// * `to_address` - The receiving account
+    /// * `rate_per_hour` - Tokens moved per hour
     ///
     /// # Returns
-    /// Result containing the created checkpoint or an error
-    pub fn create_checkpoint(&self, force: bool) -> io::Result<StateCheckpoint> {
-        // Ensure checkpoint directory exists
-        if !Path::new(&self.checkpoint_dir).exists() {
-            fs::create_dir_all(&self.checkpoint_dir)?;
+    /// The new stream's id, or why it could not be opened
+    pub fn open_stream(&self, from_address: &str, to_address: &str, rate_per_hour: u64) -> Result<StreamId, StreamError> {
+        let from_lower = from_address.to_lowercase();
+        let to_lower = to_address.to_lowercase();
+        if rate_per_hour == 0 {
+            return Err(StreamError::ZeroRate);
         }
-        
-        // Get current state
-        let accounts = self.accounts.lock().unwrap();
-        let fee_pool = *self.fee_pool.lock().unwrap();
-        let total_supply = *self.total_supply.lock().unwrap();
-        
-        // Update Merkle tree with current account states
-        let mut state_tree = self.state_tree.lock().unwrap();
-        for (address, account) in accounts.iter() {
-            // Convert Account to AccountState for the Merkle tree
-            let account_state = AccountState {
-                base_balance: account.balance,
-                last_update: SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap()
-                    .as_secs(),
-                streaming_rate: 0, // Default to 0 for now
-            };
-            
-            state_tree.update_account(address, &account_state);
+        if from_lower == to_lower {
+            return Err(StreamError::SameAccount);
         }
         
-        // Get root hash
-        let root_hash = state_tree.root_hash().unwrap_or([0; 32]);
-        
-        // Check if we already have a checkpoint with this root hash
-        let mut checkpoints = self.checkpoints.lock().unwrap();
-        if !force && !checkpoints.is_empty() {
-            if let Some(last_checkpoint) = checkpoints.last() {
-                if last_checkpoint.root_hash == root_hash {
-                    // No changes since last checkpoint
-                    return Ok(last_checkpoint.clone());
+        let id = {
+            let _gate = self.state_gate.read().unwrap();
+            self.check_maintenance()?;
+            let mut streams = self.payment_streams.write().unwrap();
+            let accounts = self.accounts.read().unwrap();
+            for address in [&from_lower, &to_lower] {
+                match accounts.get(address) {
+                    Some(account) if account.frozen => return Err(AccountError::AccountFrozen(address.clone()).into()),
+                    Some(_) => {},
+                    None => return Err(AccountError::AccountNotFound(address.clone()).into()),
                 }
             }
-        }
-        
-        // Create timestamp
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        
-        // Create checkpoint file path
-        let file_path = format!("{}/checkpoint_{}.dat", self.checkpoint_dir, timestamp);
+            drop(accounts);
+            streams.open(&from_lower, &to_lower, rate_per_hour, self.now())
+        };
+        self.persist_with(&[], &[id], &[]);
         
-        // Serialize state to file
-        let mut file = File::create(&file_path)?;
+        Ok(id)
+    }
+    
+    /// Settles a payment stream, moving what it owes from the sender to the recipient
+    ///
+    /// The sender's UBI is settled first, so streamed UBI pays the stream
+    /// too. A sender that cannot pay everything due pays what it holds and
+    /// the stream pauses, as does a stream either of whose parties is
+    /// frozen. A stream whose sender or recipient no longer exists is closed.
+    ///
+    /// # Returns
+    /// The tokens moved, or why the stream could not be settled
+    pub fn settle_stream(&self, id: StreamId) -> Result<u64, StreamError> {
+        self.settle_stream_at(id, self.now())
+    }
+    
+    /// Settles a payment stream at the given time
+    fn settle_stream_at(&self, id: StreamId, now: u64) -> Result<u64, StreamError> {
+        let from = self.payment_streams.read().unwrap().get(id)
+            .map(|stream| stream.from.clone())
+            .ok_or(StreamError::UnknownStream(id))?;
+        self.update_ubi_balance_at(&from, now)?;
+        
+        // Both balances change together
+        let _gate = self.state_gate.read().unwrap();
+        self.check_maintenance()?;
+        let mut streams = self.payment_streams.write().unwrap();
+        let mut dividends = self.dividends.write().unwrap();
+        let mut accounts = self.accounts.write().unwrap();
+        let stream = streams.get(id).cloned().ok_or(StreamError::UnknownStream(id))?;
+        if !accounts.contains_key(&stream.from) || !accounts.contains_key(&stream.to) {
+            streams.remove(id);
+            drop(accounts);
+            drop(dividends);
+            drop(streams);
+            self.persist_with(&[], &[id], &[]);
+            return Ok(0);
+        }
         
-        // Write header information
-        file.write_all(&timestamp.to_le_bytes())?;
-        file.write_all(&root_hash)?;
-        file.write_all(&(accounts.len() as u64).to_le_bytes())?;
-        file.write_all(&total_supply.to_le_bytes())?;
-        file.write_all(&fee_pool.to_le_bytes())?;
+        // Nothing moves to or from a frozen account, which pauses the stream, and locked tokens stay put
+        let available = match accounts[&stream.from].frozen || accounts[&stream.to].frozen {
+            true => 0,
+            false => accounts[&stream.from].spendable_balance(now),
+        };
+        let mut settled = stream.clone();
+        let paid = settled.settle(now, available);
+        let recipient_balance = accounts[&stream.to].balance.checked_add(paid)
+            .ok_or_else(|| AccountError::Overflow(format!("balance of {}", stream.to)))?;
+        if settled == stream {
+            return Ok(0);
+        }
         
-        // Write account data
-        for (address, account) in accounts.iter() {
-            // Write address length and address
-            let address_bytes = address.as_bytes();
-            file.write_all(&(address_bytes.len() as u32).to_le_bytes())?;
-            file.write_all(address_bytes)?;
+        if paid > 0 {
+            let sender = accounts.get_mut(&stream.from).unwrap();
+            dividends.settle_before_change(&stream.from, sender.balance);
+            sender.balance -= paid;
+            self.record_balance_change(&stream.from, now, BalanceChangeKind::StreamOut {
+                stream_id: id,
+                counterparty: stream.to.clone(),
+            }, 0, paid, sender.balance);
             
-            // Write account data
-            file.write_all(&account.balance.to_le_bytes())?;
-            file.write_all(&(account.verified as u8).to_le_bytes())?;
-            
-            // Write last UBI claim as seconds since epoch
-            let last_claim_secs = account.last_ubi_claim
-                .duration_since(UNIX_EPOCH)
-                .unwrap_or(Duration::from_secs(0))
-                .as_secs();
-            file.write_all(&last_claim_secs.to_le_bytes())?;
+            let recipient = accounts.get_mut(&stream.to).unwrap();
+            dividends.settle_before_change(&stream.to, recipient.balance);
+            recipient.balance = recipient_balance;
+            self.record_balance_change(&stream.to, now, BalanceChangeKind::StreamIn {
+                stream_id: id,
+                counterparty: stream.from.clone(),
+            }, paid, 0, recipient.balance);
+        }
+        if let Some(open) = streams.get_mut(id) {
+            *open = settled;
         }
+        drop(accounts);
+        drop(dividends);
+        drop(streams);
+        self.persist_with(&[&stream.from, &stream.to], &[id], &[]);
         
-        // Create checkpoint object
-        let checkpoint = StateCheckpoint {
-            timestamp,
-            root_hash,
-            account_count: accounts.len(),
-            total_supply,
-            fee_pool,
-            file_path,
+        Ok(paid)
+    }
+    
+    /// Settles every payment stream an account pays or receives
+    ///
+    /// Failures are logged rather than returned: the read or transfer that
+    /// settles the streams goes ahead on the balances as they stand.
+    fn settle_streams_of(&self, address: &str, now: u64) {
+        let ids = self.payment_streams.read().unwrap().ids_of(address);
+        for id in ids {
+            match self.settle_stream_at(id, now) {
+                Ok(_) | Err(StreamError::UnknownStream(_)) => {},
+                // Streams keep accruing during maintenance and settle once it ends
+                Err(StreamError::Account(AccountError::Maintenance(_))) => return,
+                Err(e) => log::warn!("Failed to settle payment stream {}: {}", id, e),
+            }
+        }
+    }
+    
+    /// Works out how settling an account's payment streams now would leave
+    /// them, without settling them
+    ///
+    /// The streams are settled oldest first on copies, against copies of the
+    /// balances involved, the way `settle_streams_of` would settle them.
+    ///
+    /// # Returns
+    /// Each stream as it would be settled, with the tokens it would move;
+    /// streams a missing party would close are left out
+    fn unsettled_streams_of(&self, address: &str, now: u64) -> Vec<(PaymentStream, u64)> {
+        let streams: Vec<PaymentStream> = {
+            let streams = self.payment_streams.read().unwrap();
+            streams.ids_of(address).into_iter()
+                .filter_map(|id| streams.get(id).cloned())
+                .collect()
         };
+        if streams.is_empty() {
+            return Vec::new();
+        }
         
-        // Add to checkpoints list
-        checkpoints.push(checkpoint.clone());
-        
-        // Prune old checkpoints if we have too many
-        self.prune_checkpoints();
+        // Settling a stream credits its sender's UBI first, so streamed UBI pays it too
+        let accounts = self.accounts.read().unwrap();
+        let spendable = |account: &Account| account.spendable_balance(now)
+            .saturating_add(self.accrued_ubi(account, now).map_or(0, |(tokens, _)| tokens));
+        let mut available: HashMap<String, u64> = HashMap::new();
+        let mut settled = Vec::with_capacity(streams.len());
+        for mut stream in streams {
+            let (Some(sender), Some(recipient)) = (accounts.get(&stream.from), accounts.get(&stream.to)) else {
+                continue;
+            };
+            let held = *available.entry(stream.from.clone()).or_insert_with(|| spendable(sender));
+            let held = if sender.frozen || recipient.frozen { 0 } else { held };
+            let moved = stream.settle(now, held);
+            if let Some(held) = available.get_mut(&stream.from) {
+                *held -= moved;
+            }
+            let received = available.entry(stream.to.clone()).or_insert_with(|| spendable(recipient));
+            *received = received.saturating_add(moved);
+            settled.push((stream, moved));
+        }
+        settled
+    }
+    
+    /// Closes a payment stream, settling it one last time
+    ///
+    /// # Returns
+    /// The stream as it was closed, or why it could not be closed
+    pub fn close_stream(&self, id: StreamId) -> Result<PaymentStream, StreamError> {
+        self.settle_stream_at(id, self.now())?;
+        
+        let stream = {
+            let _gate = self.state_gate.read().unwrap();
+            self.check_maintenance()?;
+            self.payment_streams.write().unwrap().remove(id).ok_or(StreamError::UnknownStream(id))?
+        };
+        self.persist_with(&[], &[id], &[]);
         
-        Ok(checkpoint)
+        Ok(stream)
     }
     
-    /// Loads state from a checkpoint
+    /// Gets an open payment stream, as last settled
+    pub fn get_stream(&self, id: StreamId) -> Option<PaymentStream> {
+        self.payment_streams.read().unwrap().get(id).cloned()
+    }
+    
+    /// Gets the payment streams an account pays or receives, as settling
+    /// them now would leave them
+    ///
+    /// Nothing is settled; see `get_balance`.
+    ///
+    /// # Returns
+    /// The open streams, oldest first
+    pub fn get_streams(&self, address: &str) -> Vec<PaymentStream> {
+        self.unsettled_streams_of(&address.to_lowercase(), self.now()).into_iter()
+            .map(|(stream, _)| stream)
+            .collect()
+    }
+    
+    /// Gets the id the next payment stream opened gets, the nonce an open request signs
+    pub fn next_stream_id(&self) -> StreamId {
+        self.payment_streams.read().unwrap().next_id()
+    }
+
+    /// Gets the total supply of tokens in circulation
+    pub fn get_total_supply(&self) -> u64 {
+        self.dividends.read().unwrap().total_supply
+    }
+    
+    /// Gets the nonce the next account export will carry
+    pub fn next_export_nonce(&self) -> u64 {
+        *self.export_nonce.lock().unwrap()
+    }
+    
+    /// Exports an account, removing it from this chain and burning its balance
+    ///
+    /// Dividends owed to the account are settled into the balance first. An
+    /// account with tokens still locked cannot be exported, since the export
+    /// does not carry its locks.
     ///
     /// # Arguments
-    /// * `checkpoint` - The checkpoint to load
+    /// * `address` - The account address
+    /// * `source_chain_id` - Chain id of this chain, embedded for replay protection
     ///
     /// # Returns
-    /// Result indicating success or failure
-    pub fn load_checkpoint(&self, checkpoint: &StateCheckpoint) -> io::Result<()> {
-        let file_path = &checkpoint.file_path;
-        let mut file = File::open(file_path)?;
-        
-        // Read and verify header
-        let mut timestamp_bytes = [0u8; 8];
-        file.read_exact(&mut timestamp_bytes)?;
-        let timestamp = u64::from_le_bytes(timestamp_bytes);
-        
-        if timestamp != checkpoint.timestamp {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                "Checkpoint timestamp mismatch"
-            ));
+    /// The exported state, to be imported on the target chain
+    pub fn export_account(&self, address: &str, source_chain_id: u64) -> Result<AccountExport, AccountTransferError> {
+        let address_lower = address.to_lowercase();
+        if !is_valid_eth_address(&address_lower) {
+            return Err(AccountTransferError::InvalidAddress(address.to_string()));
         }
         
-        let mut root_hash = [0u8; 32];
-        file.read_exact(&mut root_hash)?;
-        
-        if root_hash != checkpoint.root_hash {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                "Checkpoint root hash mismatch"
-            ));
+        // The account leaves and the supply shrinks together
+        let _gate = self.state_gate.read().unwrap();
+        match self.accounts.read().unwrap().get(&address_lower) {
+            Some(account) if account.frozen => return Err(AccountTransferError::Frozen(address_lower)),
+            Some(account) if balance_lock::locked_amount(&account.locked_balances, self.now()) > 0 => {
+                return Err(AccountTransferError::Locked(address_lower));
+            },
+            Some(_) => {},
+            None => return Err(AccountTransferError::UnknownAccount(address_lower)),
         }
         
-        let mut account_count_bytes = [0u8; 8];
-        file.read_exact(&mut account_count_bytes)?;
-        let account_count = u64::from_le_bytes(account_count_bytes) as usize;
+        // A balance that cannot take its dividends without overflowing leaves without them
+        let settled_dividends = self.claim_dividends(&address_lower).unwrap_or(0);
+        self.dividends.write().unwrap().remove_account(&address_lower);
+        let limits = self.account_limits.lock().unwrap().remove(&address_lower);
+        
+        let account = {
+            let mut accounts = self.accounts.write().unwrap();
+            let account = accounts.remove(&address_lower)
+                .ok_or_else(|| AccountTransferError::UnknownAccount(address_lower.clone()))?;
+            self.record_balance_change(&address_lower, self.now(), BalanceChangeKind::Exported, 0, account.balance, 0);
+            account
+        };
+        
+        let export_nonce = {
+            let mut nonce = self.export_nonce.lock().unwrap();
+            *nonce += 1;
+            *nonce - 1
+        };
         
-        let mut total_supply_bytes = [0u8; 8];
-        file.read_exact(&mut total_supply_bytes)?;
-        let total_supply = u64::from_le_bytes(total_supply_bytes);
+        let export = AccountExport {
+            address: address_lower,
+            balance: account.balance,
+            verified: account.verified,
+            last_ubi_claim_secs: account.last_ubi_claim
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or(Duration::from_secs(0))
+                .as_secs(),
+            verified_at_secs: account.verified_at.map(system_time_secs),
+            settled_dividends,
+            limits,
+            source_chain_id,
+            export_nonce,
+            exported_at: self.now(),
+        };
         
-        let mut fee_pool_bytes = [0u8; 8];
-        file.read_exact(&mut fee_pool_bytes)?;
-        let fee_pool = u64::from_le_bytes(fee_pool_bytes);
+        self.adjust_total_supply(export.balance, false);
+        self.persist(&[&export.address]);
+        self.record_supply_event(SupplyEventKind::AccountExported, &export);
         
-        // Clear current state
-        let mut accounts = self.accounts.lock().unwrap();
-        accounts.clear();
+        Ok(export)
+    }
+    
+    /// Imports an account exported from another chain, minting its balance
+    ///
+    /// # Arguments
+    /// * `export` - The exported account state
+    ///
+    /// # Returns
+    /// An error if the address is invalid, the account already exists here or
+    /// the export was imported before
+    pub fn import_account(&self, export: &AccountExport) -> Result<(), AccountTransferError> {
+        let address_lower = export.address.to_lowercase();
+        if !is_valid_eth_address(&address_lower) {
+            return Err(AccountTransferError::InvalidAddress(export.address.clone()));
+        }
         
-        *self.fee_pool.lock().unwrap() = fee_pool;
-        *self.total_supply.lock().unwrap() = total_supply;
+        // The account arrives and the supply grows together
+        let _gate = self.state_gate.read().unwrap();
         
-        // Reset dividend tracking
-        *self.dividend_per_token.lock().unwrap() = 0;
-        self.last_dividend_points.lock().unwrap().clear();
-        self.unclaimed_dividends.lock().unwrap().clear();
+        // Hold the replay set until the account is in place so the same export cannot race in twice
+        let mut imported = self.imported_exports.lock().unwrap();
+        if imported.contains(&export.replay_key()) {
+            return Err(AccountTransferError::AlreadyImported {
+                source_chain_id: export.source_chain_id,
+                export_nonce: export.export_nonce,
+            });
+        }
         
-        // Read account data
-        for _ in 0..account_count {
-            // Read address
-            let mut address_len_bytes = [0u8; 4];
-            file.read_exact(&mut address_len_bytes)?;
-            let address_len = u32::from_le_bytes(address_len_bytes) as usize;
-            
-            let mut address_bytes = vec![0u8; address_len];
-            file.read_exact(&mut address_bytes)?;
-            let address = String::from_utf8(address_bytes)
-                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Invalid UTF-8 in address"))?;
-            
-            // Read account data
-            let mut balance_bytes = [0u8; 8];
-            file.read_exact(&mut balance_bytes)?;
-            let balance = u64::from_le_bytes(balance_bytes);
-            
-            let mut verified_bytes = [0u8; 1];
-            file.read_exact(&mut verified_bytes)?;
-            let verified = verified_bytes[0] != 0;
-            
-            let mut last_claim_bytes = [0u8; 8];
-            file.read_exact(&mut last_claim_bytes)?;
-            let last_claim_secs = u64::from_le_bytes(last_claim_bytes);
-            
-            let last_ubi_claim = UNIX_EPOCH + Duration::from_secs(last_claim_secs);
-            
-            // Create account
-            let account = Account {
-                address: address.clone(),
-                balance,
-                verified,
-                last_ubi_claim,
-            };
+        {
+            let mut dividends = self.dividends.write().unwrap();
+            let mut accounts = self.accounts.write().unwrap();
+            if accounts.contains_key(&address_lower) {
+                return Err(AccountTransferError::AlreadyExists(address_lower));
+            }
+            accounts.insert(address_lower.clone(), Account {
+                address: address_lower.clone(),
+                balance: export.balance,
+                verified: export.verified,
+                last_ubi_claim: UNIX_EPOCH + Duration::from_secs(export.last_ubi_claim_secs),
+                verified_at: export.verified_at_secs.map(|secs| UNIX_EPOCH + Duration::from_secs(secs)),
+                streaming_rate: if export.verified { self.ubi_streaming_rate() } else { 0 },
+                frozen: false,
+                frozen_reason: None,
+                locked_balances: Vec::new(),
+                auto_compound: false,
+                ubi_stats: UbiStats::default(),
+            });
+            self.record_balance_change(&address_lower, self.now(), BalanceChangeKind::Imported {
+                source_chain_id: export.source_chain_id,
+            }, export.balance, 0, export.balance);
             
-            // Add to accounts map
-            accounts.insert(address, account);
+            // Dividends distributed on this chain before the import are not owed to the account
+            dividends.start_account(&address_lower);
         }
         
-        // Rebuild Merkle tree
-        let mut state_tree = self.state_tree.lock().unwrap();
-        *state_tree = MerkleTree::new();
-        
-        for (address, account) in accounts.iter() {
-            let account_state = AccountState {
-                base_balance: account.balance,
-                last_update: SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap()
-                    .as_secs(),
-                streaming_rate: 0,
-            };
-            
-            state_tree.update_account(address, &account_state);
+        if let Some(limits) = &export.limits {
+            self.account_limits.lock().unwrap().insert(address_lower.clone(), limits.clone());
         }
+        imported.insert(export.replay_key());
+        drop(imported);
+        
+        self.adjust_total_supply(export.balance, true);
+        self.persist(&[&address_lower]);
+        self.record_supply_event(SupplyEventKind::AccountImported, export);
         
         Ok(())
     }
     
-    /// Prunes old checkpoints to keep storage lean
-    fn prune_checkpoints(&self) {
-        let mut checkpoints = self.checkpoints.lock().unwrap();
-        
-        // If we have more checkpoints than the maximum, remove the oldest ones
-        while checkpoints.len() > self.max_checkpoints {
-            if let Some(oldest) = checkpoints.first().cloned() {
-                // Remove from filesystem
-                let _ = fs::remove_file(&oldest.file_path);
-                
-                // Remove from list
-                checkpoints.remove(0);
-            }
-        }
+    /// Gets the supply changes caused by account exports and imports, oldest first
+    pub fn supply_events(&self) -> Vec<SupplyEvent> {
+        self.supply_events.lock().unwrap().clone()
     }
     
-    /// Gets a list of all available checkpoints
+    /// Gets a slice of the supply events
+    ///
+    /// # Arguments
+    /// * `start` - Index of the first event to return, oldest being 0
+    /// * `limit` - Maximum number of events to return
+    pub fn supply_events_from(&self, start: usize, limit: usize) -> Vec<SupplyEvent> {
+        self.supply_events.lock().unwrap().iter().skip(start).take(limit).cloned().collect()
+    }
+
+    
+    /// Includes pending UBI mints in a block, oldest first
+    ///
+    /// # Arguments
+    /// * `block_number` - The block including them
+    /// * `block_hash` - Hash of that block
+    /// * `limit` - Most mints to include; the rest wait for later blocks
     ///
     /// # Returns
-    /// Vector of available checkpoints
-    pub fn list_checkpoints(&self) -> Vec<StateCheckpoint> {
-        self.checkpoints.lock().unwrap().clone()
+    /// The included mints
+    pub fn include_ubi_mints(&self, block_number: u64, block_hash: &str, limit: usize) -> Vec<UbiMint> {
+        self.ubi_mints.lock().unwrap().include_in_block(block_number, block_hash, limit)
     }
     
-    /// Gets the latest checkpoint
+    /// Takes the verification changes since the previous call, for the block being sealed
     ///
     /// # Returns
-    /// Option containing the latest checkpoint, if any
-    pub fn latest_checkpoint(&self) -> Option<StateCheckpoint> {
-        self.checkpoints.lock().unwrap().last().cloned()
+    /// The changes, oldest first
+    pub fn take_verification_events(&self) -> Vec<VerificationEvent> {
+        self.verification_events.lock().unwrap().take()
     }
-
-    /// Credits tokens to an account
+    
+    /// Gets the UBI mints credited to an account, oldest first
+    pub fn ubi_mints_for(&self, address: &str) -> Vec<UbiMint> {
+        self.ubi_mints.lock().unwrap().for_account(address)
+    }
+    
+    /// Gets the UBI mints included in blocks `from_block` to `to_block`, inclusive
+    pub fn ubi_mints_between(&self, from_block: u64, to_block: u64) -> Vec<UbiMint> {
+        self.ubi_mints.lock().unwrap().included_between(from_block, to_block)
+    }
+    
+    /// Replaces the faucet's grant tiers
+    ///
+    /// Records of grants from earlier tiers with the same names are kept.
+    pub fn set_faucet_tiers(&self, tiers: Vec<FaucetTier>) {
+        *self.faucet_tiers.lock().unwrap() = tiers;
+    }
+    
+    /// Gets the faucet's grant tiers
+    pub fn faucet_tiers(&self) -> Vec<FaucetTier> {
+        self.faucet_tiers.lock().unwrap().clone()
+    }
+    
+    /// Gets the grants of a tier an address has received
+    pub fn faucet_record(&self, tier: &str, address: &str) -> Option<FaucetRecord> {
+        self.faucet_records.lock().unwrap().get(&faucet_record_key(tier, address)).copied()
+    }
+    
+    /// Reserves a faucet grant for an address, recording it against the tier's limits
     ///
     /// # Arguments
-    /// * `address` - The account address
-    /// * `amount` - The amount to credit
+    /// * `tier` - Name of the requested tier
+    /// * `address` - The recipient
+    /// * `code` - Access code supplied with the request, if any
+    /// * `challenge_verified` - Whether the caller checked a challenge signed by the recipient
     ///
     /// # Returns
-    /// Result containing the new balance or an error
-    pub fn credit_balance(&self, address: &str, amount: u64) -> Result<u64, AccountError> {
-        // Normalize address to lowercase for consistent lookup
-        let address_lower = address.to_lowercase();
-        
-        // Acquire lock on accounts
-        let mut accounts_guard = match self.accounts.lock() {
-            Ok(guard) => guard,
-            Err(e) => {
-                log::error!("Failed to acquire lock on accounts: {:?}", e);
-                return Err(AccountError::Other(format!("Failed to acquire lock on accounts: {:?}", e)));
-            }
-        };
-        
-        // Check if account exists
-        if !accounts_guard.contains_key(&address_lower) {
-            return Err(AccountError::Other(format!("Account does not exist: {}", address)));
+    /// The claim, to pass to `cancel_faucet_claim` if the grant cannot be paid,
+    /// or why the request was refused
+    pub fn claim_faucet_tier(&self, tier: &str, address: &str, code: Option<&str>, challenge_verified: bool) -> Result<FaucetClaim, FaucetError> {
+        self.claim_faucet_tier_at(tier, address, code, challenge_verified, self.now())
+    }
+    
+    /// Reserves a faucet grant at the given time
+    fn claim_faucet_tier_at(&self, tier: &str, address: &str, code: Option<&str>, challenge_verified: bool, now: u64) -> Result<FaucetClaim, FaucetError> {
+        let tier = self.faucet_tiers.lock().unwrap().iter()
+            .find(|candidate| candidate.name == tier)
+            .cloned()
+            .ok_or_else(|| FaucetError::UnknownTier(tier.to_string()))?;
+        
+        if !tier.accepts_code(code) {
+            return Err(FaucetError::InvalidCode(tier.name));
+        }
+        if tier.requires_challenge && !challenge_verified {
+            return Err(FaucetError::ChallengeRequired(tier.name));
         }
         
-        // Credit the account
-        let account = accounts_guard.get_mut(&address_lower).unwrap();
-        account.balance += amount;
+        let key = faucet_record_key(&tier.name, address);
+        let mut records = self.faucet_records.lock().unwrap();
+        let previous = records.get(&key).copied();
+        faucet::check_grant(&tier, previous.as_ref(), now)?;
         
-        // Update total supply
-        drop(accounts_guard); // Drop the accounts lock before acquiring the total supply lock
+        let record = records.entry(key).or_default();
+        record.grants += 1;
+        record.last_grant_secs = now;
         
-        match self.total_supply.lock() {
-            Ok(mut total_supply) => {
-                *total_supply += amount;
-            },
-            Err(e) => {
-                log::error!("Failed to acquire lock on total supply: {:?}", e);
-                // The credit was successful, so we'll just log the error and continue
-                log::warn!("Total supply was not updated, but credit was successful");
+        Ok(FaucetClaim { amount: tier.amount, previous })
+    }
+    
+    /// Undoes a claim whose grant could not be paid
+    pub fn cancel_faucet_claim(&self, tier: &str, address: &str, claim: &FaucetClaim) {
+        let key = faucet_record_key(tier, address);
+        let mut records = self.faucet_records.lock().unwrap();
+        match claim.previous {
+            Some(previous) => records.insert(key, previous),
+            None => records.remove(&key),
+        };
+    }
+    
+    /// Sets the faucet source accounts, in the order they are tried
+    ///
+    /// An empty list falls back to the faucet funded at genesis.
+    ///
+    /// # Returns
+    /// An error naming the first invalid address, if any
+    pub fn set_faucet_sources(&self, sources: Vec<String>) -> Result<(), String> {
+        let mut normalized: Vec<String> = Vec::with_capacity(sources.len());
+        for source in sources {
+            let source = source.trim().to_lowercase();
+            if !is_valid_eth_address(&source) {
+                return Err(format!("Invalid faucet source address: {}", source));
+            }
+            if !normalized.contains(&source) {
+                normalized.push(source);
+            }
+        }
+        *self.faucet_sources.lock().unwrap() = normalized;
+        Ok(())
+    }
+    
+    /// Gets the faucet source accounts in the order they are tried: the
+    /// configured sources, or else the faucet funded at genesis
+    pub fn faucet_sources(&self) -> Vec<String> {
+        let sources = self.faucet_sources.lock().unwrap().clone();
+        if !sources.is_empty() {
+            return sources;
+        }
+        self.genesis_faucet().into_iter().collect()
+    }
+    
+    /// Pays a faucet grant from the first source able to
+    ///
+    /// Each source is tried with a single transfer, which checks its balance
+    /// and limits atomically, so concurrent grants never overdraw a source.
+    /// Sources without an account or whose transfer is refused are passed over.
+    ///
+    /// # Arguments
+    /// * `sources` - Source accounts, in the order to try them
+    /// * `recipient` - The recipient, whose account must exist
+    /// * `amount` - Tokens the recipient receives; the source also pays the fee
+    /// * `tier` - Tier the grant was claimed from, if any
+    ///
+    /// # Returns
+    /// The grant, or an error if maintenance mode is on or no source could
+    /// pay; `InsufficientBalance` if every source was short of funds, with
+    /// the largest balance among them
+    pub fn grant_from_faucet(&self, sources: &[String], recipient: &str, amount: u64, tier: Option<&str>) -> Result<FaucetGrant, AccountError> {
+        if sources.is_empty() {
+            return Err(AccountError::Other("No faucet account is configured".to_string()));
+        }
+        
+        let recipient = recipient.to_lowercase();
+        let mut history = self.faucet_history.lock().unwrap();
+        let mut skipped = Vec::new();
+        // The shortfall, while every source so far was short of funds
+        let mut shortfall = Some((0, 0));
+        for source in sources {
+            let source = source.to_lowercase();
+            if !self.accounts.read().unwrap().contains_key(&source) {
+                skipped.push(format!("{}: no account", source));
+                shortfall = None;
+                continue;
+            }
+            match self.transfer_with_fee(&source, &recipient, amount) {
+                Ok(()) => {
+                    let grant = FaucetGrant {
+                        recipient,
+                        source,
+                        amount,
+                        tier: tier.map(str::to_string),
+                        timestamp: self.now(),
+                        skipped,
+                    };
+                    history.record(grant.clone());
+                    return Ok(grant);
+                },
+                Err(AccountError::Maintenance(refusal)) => return Err(AccountError::Maintenance(refusal)),
+                Err(AccountError::InsufficientBalance { required, available }) => {
+                    shortfall = shortfall.map(|(_, most_available): (u64, u64)| (required, most_available.max(available)));
+                    skipped.push(format!("{}: {}", source, AccountError::InsufficientBalance { required, available }));
+                },
+                Err(AccountError::Other(reason)) => {
+                    skipped.push(format!("{}: {}", source, reason));
+                    shortfall = None;
+                },
+                Err(e) => {
+                    skipped.push(format!("{}: {}", source, e));
+                    shortfall = None;
+                },
             }
         }
         
-        // Return the new balance
-        Ok(self.get_balance(&address_lower))
+        match shortfall {
+            Some((required, available)) => Err(AccountError::InsufficientBalance { required, available }),
+            None => Err(AccountError::Other(format!("No faucet source can pay {} tokens ({})", amount, skipped.join("; ")))),
+        }
     }
-
+    
+    /// Gets each faucet source's balance and grants, and the latest grants
+    ///
+    /// # Arguments
+    /// * `sources` - Source accounts, in the order they are tried
+    /// * `recent` - Most recent grants to include
+    pub fn faucet_stats(&self, sources: &[String], recent: usize) -> FaucetStats {
+        let history = self.faucet_history.lock().unwrap();
+        let accounts = self.accounts.read().unwrap();
+        let sources: Vec<FaucetSourceStats> = sources.iter()
+            .map(|source| {
+                let address = source.to_lowercase();
+                let account = accounts.get(&address);
+                FaucetSourceStats {
+                    exists: account.is_some(),
+                    balance: account.map_or(0, |account| account.balance),
+                    totals: history.totals(&address),
+                    address,
+                }
+            })
+            .collect();
+        FaucetStats {
+            total_balance: sources.iter().map(|source| source.balance).sum(),
+            sources,
+            recent_grants: history.recent(recent),
+        }
+    }
+    
+    /// Records a supply change caused by an account export or import
+    fn record_supply_event(&self, kind: SupplyEventKind, export: &AccountExport) {
+        let event = SupplyEvent {
+            kind,
+            address: export.address.to_lowercase(),
+            amount: export.balance,
+            source_chain_id: export.source_chain_id,
+            export_nonce: export.export_nonce,
+            timestamp: self.now(),
+            total_supply_after: self.get_total_supply(),
+            tx_hash: None,
+            block_hash: None,
+        };
+        log::info!("Supply event {:?}: {} tokens for {}, total supply now {}",
+                   event.kind, event.amount, event.address, event.total_supply_after);
+        self.supply_events.lock().unwrap().push(event);
+    }
+    
     /// Sets the block producer reference
     pub fn set_block_producer(&self, producer: Arc<dyn BlockProducer>) {
         let mut block_producer = self.block_producer.write().unwrap();
@@ -1362,6 +6813,176 @@ impl Runtime {
     }
 }
 
+/// Checks that a transfer can be made, without moving any tokens
+///
+/// Transfers from the built-in faucet skip the sender checks.
+///
+/// # Arguments
+/// * `accounts` - The account store
+/// * `account_limits` - Spending limits by address
+/// * `from_address` - The sender's address
+/// * `to_address` - The recipient's address
+/// * `amount` - Amount of tokens to transfer
+/// * `fee` - Fee charged on top of the amount
+/// * `now` - Current UNIX time in seconds
+fn check_transfer(
+    accounts: &im::HashMap<String, Account>,
+    account_limits: &mut HashMap<String, AccountLimitState>,
+    from_address: &str,
+    to_address: &str,
+    amount: u64,
+    fee: u64,
+    now: u64,
+) -> Result<(), TransferRejection> {
+    let from_lower = from_address.to_lowercase();
+    let is_faucet_transfer = from_lower == FAUCET_ADDRESS.to_lowercase();
+    
+    // The sender is checked first, so callers that create missing recipients
+    // still learn whether the sender could pay
+    if !is_faucet_transfer {
+        let balance = match accounts.get(&from_lower) {
+            Some(sender) if sender.frozen => return Err(TransferRejection::AccountFrozen(from_lower)),
+            Some(sender) => sender.spendable_balance(now),
+            None => return Err(TransferRejection::UnknownSender(from_address.to_string())),
+        };
+        let total_deduction = amount.saturating_add(fee);
+        if balance < total_deduction {
+            return Err(TransferRejection::InsufficientFunds { have: balance, want: total_deduction });
+        }
+        
+        // Enforce the sender's spending limits, if it opted in
+        if let Some(limits) = account_limits.get_mut(&from_lower) {
+            limits.check_transfer(amount, total_deduction, now).map_err(TransferRejection::LimitExceeded)?;
+        }
+    }
+    
+    match accounts.get(&to_address.to_lowercase()) {
+        Some(recipient) if recipient.frozen => Err(TransferRejection::AccountFrozen(recipient.address.clone())),
+        Some(_) => Ok(()),
+        None => Err(TransferRejection::UnknownRecipient(to_address.to_string())),
+    }
+}
+
+/// Checks that an account can burn an amount, without changing anything
+///
+/// # Arguments
+/// * `accounts` - The account store
+/// * `account_limits` - Spending limits by address
+/// * `from_address` - The account burning its tokens
+/// * `amount` - Amount of tokens to burn
+/// * `now` - Current UNIX time in seconds
+fn check_burn(
+    accounts: &im::HashMap<String, Account>,
+    account_limits: &mut HashMap<String, AccountLimitState>,
+    from_address: &str,
+    amount: u64,
+    now: u64,
+) -> Result<(), TransferRejection> {
+    let from_lower = from_address.to_lowercase();
+    let balance = match accounts.get(&from_lower) {
+        Some(account) if account.frozen => return Err(TransferRejection::AccountFrozen(from_lower)),
+        Some(account) => account.spendable_balance(now),
+        None => return Err(TransferRejection::UnknownSender(from_address.to_string())),
+    };
+    if balance < amount {
+        return Err(TransferRejection::InsufficientFunds { have: balance, want: amount });
+    }
+    if let Some(limits) = account_limits.get_mut(&from_lower) {
+        limits.check_transfer(amount, amount, now).map_err(TransferRejection::LimitExceeded)?;
+    }
+    Ok(())
+}
+
+/// Gets the current UNIX time in seconds
+fn current_time_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// Builds the key of an address's faucet record for a tier
+fn faucet_record_key(tier: &str, address: &str) -> String {
+    format!("{}:{}", tier, address.to_lowercase())
+}
+
+/// Converts a time read from a checkpoint, refusing times the platform cannot represent
+fn checkpoint_time(secs: u64) -> io::Result<SystemTime> {
+    UNIX_EPOCH.checked_add(Duration::from_secs(secs)).ok_or_else(|| io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Checkpoint time {} is out of range", secs)
+    ))
+}
+
+/// Converts a time to seconds since the UNIX epoch, clamping earlier times to 0
+fn system_time_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).unwrap_or(Duration::from_secs(0)).as_secs()
+}
+
+/// Builds the record a state store keeps for an account
+fn stored_account(account: &Account, dividends: &DividendState, limits: &HashMap<String, AccountLimitState>) -> StoredAccount {
+    StoredAccount {
+        address: account.address.clone(),
+        balance: account.balance,
+        verified: account.verified,
+        last_ubi_claim_secs: system_time_secs(account.last_ubi_claim),
+        verified_at_secs: account.verified_at.map(system_time_secs),
+        last_dividend_point: dividends.point(&account.address),
+        unclaimed_dividends: dividends.unclaimed(&account.address),
+        limits: limits.get(&account.address).cloned(),
+        frozen: account.frozen,
+        frozen_reason: account.frozen_reason.clone(),
+        locked_balances: account.locked_balances.clone(),
+        auto_compound: account.auto_compound,
+        ubi_stats: account.ubi_stats,
+    }
+}
+
+/// Lists allowances the way checkpoints and state stores keep them, by owner and spender
+fn checkpoint_allowances(allowances: &HashMap<(String, String), u64>) -> Vec<CheckpointAllowance> {
+    let mut listed: Vec<CheckpointAllowance> = allowances.iter()
+        .map(|((owner, spender), amount)| CheckpointAllowance { owner: owner.clone(), spender: spender.clone(), amount: *amount })
+        .collect();
+    listed.sort_by(|a, b| (&a.owner, &a.spender).cmp(&(&b.owner, &b.spender)));
+    listed
+}
+
+/// Gets the supply counters a state store keeps
+fn stored_totals(dividends: &DividendState) -> StoredTotals {
+    StoredTotals {
+        total_supply: dividends.total_supply,
+        fee_pool: dividends.fee_pool,
+        dividend_per_token: dividends.dividend_per_token,
+        fees_distributed: dividends.fees_distributed,
+        ubi_minted: dividends.ubi_minted,
+        fees_collected: dividends.fees_collected,
+    }
+}
+
+/// Gets the lifetime network totals kept with the supply counters
+fn network_stats(dividends: &DividendState) -> NetworkStats {
+    NetworkStats {
+        total_ubi_minted: dividends.ubi_minted,
+        total_fees_collected: dividends.fees_collected,
+        total_dividends_distributed: dividends.fees_distributed,
+    }
+}
+
+/// Brings the state tree up to date with the account store
+///
+/// Leaves of known accounts are updated in place; the tree places accounts
+/// it has not seen in address order.
+fn refresh_state_tree(state_tree: &mut MerkleTree, accounts: &im::HashMap<String, Account>, nonces: &HashMap<String, u64>) {
+    state_tree.update_accounts(accounts.values()
+        .map(|account| (account.address.as_str(), account.merkle_state(nonces.get(&account.address).copied().unwrap_or(0)))));
+}
+
+/// Formats a Merkle root as 0x-prefixed hex, all zeros for an empty tree
+fn root_hex(root: Option<[u8; 32]>) -> String {
+    let hex: String = root.unwrap_or([0; 32]).iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("0x{}", hex)
+}
+
 /// Validates if a string is a valid Ethereum address
 ///
 /// # Arguments
@@ -1369,7 +6990,7 @@ impl Runtime {
 ///
 /// # Returns
 /// true if the address is valid, false otherwise
-fn is_valid_eth_address(address: &str) -> bool {
+pub(crate) fn is_valid_eth_address(address: &str) -> bool {
     // Ethereum addresses are 0x followed by 40 hex characters
     if !address.starts_with("0x") || address.len() != 42 {
         return false;
@@ -1390,15 +7011,26 @@ pub struct MerkleNode {
     pub right: Option<Box<MerkleNode>>,
 }
 
+/// Siblings on the path from a leaf to the root, each with whether it is on the right
+pub type MerklePath = Vec<([u8; 32], bool)>;
+
 /// A Merkle tree for efficiently storing and verifying account states
+///
+/// Leaves are kept in address order, so nodes holding the same accounts build
+/// the same tree whatever order the accounts were created in. A level with an
+/// odd number of nodes pairs its last node with itself.
+///
+/// The hashes of every level are kept, so changing a leaf only recomputes the
+/// O(log N) nodes on its path to the root. Adding accounts shifts the leaves
+/// after them and rebuilds the levels once per batch.
 #[derive(Clone, Debug)]
 pub struct MerkleTree {
-    /// Root node of the tree
-    pub root: Option<Box<MerkleNode>>,
     /// Mapping of account addresses to their leaf indices
     pub address_indices: HashMap<String, usize>,
-    /// Leaf nodes for quick access
+    /// Leaf hashes in address order
     pub leaves: Vec<[u8; 32]>,
+    /// Hashes of the levels above the leaves, the root level last
+    levels: Vec<Vec<[u8; 32]>>,
 }
 
 impl Default for MerkleTree {
@@ -1446,9 +7078,9 @@ impl MerkleTree {
     /// Creates a new empty Merkle tree
     pub fn new() -> Self {
         MerkleTree {
-            root: None,
             address_indices: HashMap::new(),
             leaves: Vec::new(),
+            levels: Vec::new(),
         }
     }
     
@@ -1468,123 +7100,175 @@ impl MerkleTree {
         // Add streaming_rate (as 8 bytes)
         result.extend_from_slice(&state.streaming_rate.to_le_bytes());
         
+        // Add verified (as 1 byte), nonce and last_ubi_claim (as 8 bytes each)
+        result.push(state.verified as u8);
+        result.extend_from_slice(&state.nonce.to_le_bytes());
+        result.extend_from_slice(&state.last_ubi_claim.to_le_bytes());
+        
         result
     }
     
     /// Adds or updates an account state in the tree
     pub fn update_account(&mut self, address: &str, state: &AccountState) {
+        self.update_accounts(std::iter::once((address, *state)));
+    }
+    
+    /// Adds or updates many account states
+    ///
+    /// Leaves whose hash is unchanged cost nothing above the leaf. If any
+    /// account is new, the levels are rebuilt once; otherwise only the paths
+    /// of the changed leaves are recomputed, sharing the nodes they have in
+    /// common.
+    ///
+    /// # Returns
+    /// The number of node hashes recomputed above the leaves
+    pub fn update_accounts<'a>(&mut self, states: impl IntoIterator<Item = (&'a str, AccountState)>) -> usize {
+        let mut added = false;
+        let mut changed = Vec::new();
+        for (address, state) in states {
+            match self.set_leaf(address, &state) {
+                LeafChange::Added => added = true,
+                LeafChange::Updated(index) => changed.push(index),
+                LeafChange::Unchanged => {}
+            }
+        }
+        if added {
+            self.sort_leaves();
+            self.rebuild()
+        } else {
+            self.update_paths(changed)
+        }
+    }
+    
+    /// Sets the leaf of an account without touching the levels above it
+    fn set_leaf(&mut self, address: &str, state: &AccountState) -> LeafChange {
         let serialized = Self::serialize_account_state(address, state);
         let leaf_hash = MerkleNode::new_leaf(&serialized).hash;
         
-        if let Some(index) = self.address_indices.get(address) {
-            // Update existing leaf
-            self.leaves[*index] = leaf_hash;
-        } else {
-            // Add new leaf
-            let index = self.leaves.len();
-            self.leaves.push(leaf_hash);
-            self.address_indices.insert(address.to_string(), index);
+        match self.address_indices.get(address) {
+            Some(&index) if self.leaves[index] == leaf_hash => LeafChange::Unchanged,
+            Some(&index) => {
+                self.leaves[index] = leaf_hash;
+                LeafChange::Updated(index)
+            }
+            None => {
+                // New leaves are appended here and put in address order once the batch is in
+                self.address_indices.insert(address.to_string(), self.leaves.len());
+                self.leaves.push(leaf_hash);
+                LeafChange::Added
+            }
         }
-        
-        // Rebuild the tree
-        self.rebuild();
     }
     
-    /// Rebuilds the Merkle tree from the leaves
-    fn rebuild(&mut self) {
-        if self.leaves.is_empty() {
-            self.root = None;
-            return;
-        }
-        
-        // Create leaf nodes
-        let mut nodes: VecDeque<Box<MerkleNode>> = self.leaves
-            .iter()
-            .map(|hash| {
-                Box::new(MerkleNode {
-                    hash: *hash,
-                    left: None,
-                    right: None,
-                })
-            })
+    /// Puts the leaves back in address order after new ones were appended
+    fn sort_leaves(&mut self) {
+        let mut ordered: Vec<(String, [u8; 32])> = self.address_indices.drain()
+            .map(|(address, index)| (address, self.leaves[index]))
             .collect();
-        
-        // If odd number of nodes, duplicate the last one
-        if nodes.len() % 2 == 1 {
-            nodes.push_back(nodes.back().unwrap().clone());
+        ordered.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        self.leaves = ordered.iter().map(|(_, hash)| *hash).collect();
+        self.address_indices = ordered.into_iter()
+            .enumerate()
+            .map(|(index, (address, _))| (address, index))
+            .collect();
+    }
+    
+    /// Gets the hashes of a level, the leaves being level 0
+    fn level(&self, level: usize) -> &[[u8; 32]] {
+        match level {
+            0 => &self.leaves,
+            _ => &self.levels[level - 1],
         }
-        
-        // Build the tree bottom-up
-        while nodes.len() > 1 {
-            let mut new_level = VecDeque::new();
-            
-            while !nodes.is_empty() {
-                let left = nodes.pop_front().unwrap();
-                
-                // If we have an odd number of nodes at this level
-                if nodes.is_empty() {
-                    new_level.push_back(left);
-                    break;
-                }
-                
-                let right = nodes.pop_front().unwrap();
-                let parent = Box::new(MerkleNode::new_internal(left, right));
-                new_level.push_back(parent);
-            }
-            
-            // If odd number of nodes in the new level, duplicate the last one
-            if new_level.len() % 2 == 1 && new_level.len() > 1 {
-                new_level.push_back(new_level.back().unwrap().clone());
+    }
+    
+    /// Hashes a node of a level from its two children in the level below
+    ///
+    /// A last node without a right sibling is paired with itself.
+    fn hash_children(children: &[[u8; 32]], index: usize) -> [u8; 32] {
+        let left = &children[2 * index];
+        let right = children.get(2 * index + 1).unwrap_or(left);
+        hash_pair(left, right)
+    }
+    
+    /// Rebuilds every level above the leaves
+    ///
+    /// The leaves are always hashed into at least one level, so a single
+    /// account's root is its leaf paired with itself.
+    ///
+    /// # Returns
+    /// The number of node hashes computed
+    fn rebuild(&mut self) -> usize {
+        self.levels.clear();
+        let mut hashed = 0;
+        while self.levels.last().map_or(!self.leaves.is_empty(), |level| level.len() > 1) {
+            let children = self.level(self.levels.len());
+            let level: Vec<[u8; 32]> = (0..children.len().div_ceil(2))
+                .map(|index| Self::hash_children(children, index))
+                .collect();
+            hashed += level.len();
+            self.levels.push(level);
+        }
+        hashed
+    }
+    
+    /// Recomputes the nodes on the paths from the given leaves to the root
+    ///
+    /// # Returns
+    /// The number of node hashes recomputed
+    fn update_paths(&mut self, mut indices: Vec<usize>) -> usize {
+        let mut hashed = 0;
+        for level in 1..=self.levels.len() {
+            indices = indices.into_iter().map(|index| index / 2).collect();
+            indices.sort_unstable();
+            indices.dedup();
+            for &index in &indices {
+                let hash = Self::hash_children(self.level(level - 1), index);
+                self.levels[level - 1][index] = hash;
             }
-            
-            nodes = new_level;
+            hashed += indices.len();
         }
-        
-        self.root = if nodes.is_empty() { None } else { Some(nodes.pop_front().unwrap()) };
+        hashed
     }
     
     /// Gets the Merkle root hash
     pub fn root_hash(&self) -> Option<[u8; 32]> {
-        self.root.as_ref().map(|node| node.hash)
+        self.levels.last().map(|root| root[0])
     }
     
     /// Generates a Merkle proof for the given account address
-    pub fn generate_proof(&self, address: &str) -> Option<Vec<([u8; 32], bool)>> {
-        let index = self.address_indices.get(address)?;
+    ///
+    /// # Returns
+    /// The sibling of each node on the path from the account's leaf to the
+    /// root, with whether the sibling is on the right
+    pub fn generate_proof(&self, address: &str) -> Option<MerklePath> {
+        let mut index = *self.address_indices.get(address)?;
         let mut proof = Vec::new();
-        let mut current_index = *index;
-        
-        // Start from the leaf level and work up to the root
-        let mut level_size = self.leaves.len();
-        let mut level_start = 0;
         
-        while level_size > 1 {
-            let sibling_index = if current_index % 2 == 0 {
-                // Current node is left child, sibling is right
-                current_index + 1
-            } else {
-                // Current node is right child, sibling is left
-                current_index - 1
-            };
-            
-            // Ensure sibling index is valid
-            if sibling_index < level_start + level_size {
-                let is_right_sibling = current_index % 2 == 0;
-                
-                if sibling_index < self.leaves.len() {
-                    proof.push((self.leaves[sibling_index], is_right_sibling));
-                }
-            }
-            
-            // Move up to parent level
-            current_index = level_start + (current_index - level_start) / 2;
-            level_size = (level_size + 1) / 2;
-            level_start += level_size;
+        // Every level but the root contributes the sibling of the node on the path
+        for level in 0..self.levels.len() {
+            let nodes = self.level(level);
+            let is_right_sibling = index % 2 == 0;
+            proof.push((nodes.get(index ^ 1).copied().unwrap_or(nodes[index]), is_right_sibling));
+            index /= 2;
         }
         
         Some(proof)
     }
     
+    /// Computes the root a Merkle proof leads to for the given account state
+    pub fn proof_root(address: &str, state: &AccountState, proof: &[([u8; 32], bool)]) -> [u8; 32] {
+        let serialized = Self::serialize_account_state(address, state);
+        let leaf_hash = MerkleNode::new_leaf(&serialized).hash;
+        
+        proof.iter().fold(leaf_hash, |current_hash, &(sibling_hash, is_right)| {
+            if is_right {
+                hash_pair(&current_hash, &sibling_hash)
+            } else {
+                hash_pair(&sibling_hash, &current_hash)
+            }
+        })
+    }
+    
     /// Verifies a Merkle proof for the given account state
     pub fn verify_proof(
         root_hash: [u8; 32],
@@ -1592,45 +7276,72 @@ impl MerkleTree {
         state: &AccountState,
         proof: &[([u8; 32], bool)]
     ) -> bool {
-        let serialized = Self::serialize_account_state(address, state);
-        let mut current_hash = MerkleNode::new_leaf(&serialized).hash;
-        
-        for &(sibling_hash, is_right) in proof {
-            let mut hasher = Sha256::new();
-            
-            if is_right {
-                // Sibling is on the right
-                hasher.update(current_hash);
-                hasher.update(sibling_hash);
-            } else {
-                // Sibling is on the left
-                hasher.update(sibling_hash);
-                hasher.update(current_hash);
-            }
-            
-            let hash = hasher.finalize();
-            current_hash.copy_from_slice(&hash);
-        }
-        
-        current_hash == root_hash
+        Self::proof_root(address, state, proof) == root_hash
     }
 }
 
+/// What setting a leaf did to the tree
+enum LeafChange {
+    /// The account is new and its leaf was appended
+    Added,
+    /// The leaf at this index has a new hash
+    Updated(usize),
+    /// The leaf already had this hash
+    Unchanged,
+}
+
+/// Hashes two sibling nodes into their parent
+fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
 // Add Default implementation for Runtime
 impl Default for Runtime {
     fn default() -> Self {
         Runtime {
-            accounts: Arc::new(std::sync::Mutex::new(HashMap::new())),
-            fee_pool: Arc::new(std::sync::Mutex::new(0)),
-            dividend_per_token: Arc::new(std::sync::Mutex::new(0)),
-            total_supply: Arc::new(std::sync::Mutex::new(0)),
-            last_dividend_points: Arc::new(std::sync::Mutex::new(HashMap::new())),
-            unclaimed_dividends: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            accounts: Arc::new(std::sync::RwLock::new(im::HashMap::new())),
+            state_gate: Arc::new(std::sync::RwLock::new(())),
+            dividends: Arc::new(std::sync::RwLock::new(DividendState::default())),
+            fee_ledger: Arc::new(std::sync::Mutex::new(Vec::new())),
+            payment_streams: Arc::new(std::sync::RwLock::new(PaymentStreams::default())),
+            balance_history: Arc::new(std::sync::Mutex::new(BalanceHistory::default())),
             state_tree: Arc::new(std::sync::Mutex::new(MerkleTree::new())),
+            account_limits: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            allowances: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            param_schedule: Arc::new(std::sync::Mutex::new(ParamSchedule::default())),
+            export_nonce: Arc::new(std::sync::Mutex::new(0)),
+            nonces: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            imported_exports: Arc::new(std::sync::Mutex::new(HashSet::new())),
+            supply_events: Arc::new(std::sync::Mutex::new(Vec::new())),
+            burn_address: DEFAULT_BURN_ADDRESS.to_lowercase(),
+            reserved: Arc::new(std::sync::Mutex::new(ReservedAddresses::new(DEFAULT_BURN_ADDRESS))),
+            maintenance: Arc::new(std::sync::Mutex::new(MaintenanceStatus::default())),
+            ubi_mints: Arc::new(std::sync::Mutex::new(UbiMintLog::default())),
+            verification_events: Arc::new(std::sync::Mutex::new(VerificationEventLog::default())),
+            faucet_tiers: Arc::new(std::sync::Mutex::new(FaucetTier::defaults())),
+            faucet_records: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            genesis_faucet: Arc::new(std::sync::Mutex::new(None)),
+            fee_exempt: Arc::new(std::sync::Mutex::new(HashSet::from([FAUCET_ADDRESS.to_lowercase()]))),
+            faucet_sources: Arc::new(std::sync::Mutex::new(Vec::new())),
+            faucet_history: Arc::new(std::sync::Mutex::new(FaucetHistory::default())),
+            bootstrap_window: Arc::new(std::sync::Mutex::new(None)),
             checkpoints: Arc::new(std::sync::Mutex::new(Vec::new())),
             max_checkpoints: 10, // Default to keeping 10 checkpoints
             checkpoint_dir: "./checkpoints".to_string(),
+            max_checkpoint_accounts: checkpoint::DEFAULT_MAX_CHECKPOINT_ACCOUNTS,
+            compress_checkpoints: false,
+            max_ubi_accrual_hours: config::DEFAULT_MAX_UBI_ACCRUAL_HOURS,
+            fee_distribution_interval_blocks: config::DEFAULT_FEE_DISTRIBUTION_INTERVAL_BLOCKS,
+            checkpoint_health: Arc::new(std::sync::Mutex::new(CheckpointHealth::default())),
+            tx_traces: TransactionTraceStore::default(),
+            clock: Clock::system(),
+            new_accounts: NewAccountPolicy::default(),
             block_producer: Arc::new(std::sync::RwLock::new(None)),
+            state_store: Arc::new(std::sync::Mutex::new(None)),
+            admin_nonce: Arc::new(std::sync::Mutex::new(0)),
         }
     }
 } 
\ No newline at end of file
@@ -0,0 +1,233 @@
+//! Account Spending Limits
+//!
+//! Accounts can opt in to guard rails that bound the damage of a leaked key:
+//! - A maximum amount per transaction
+//! - A cap on the total outflow (amount plus fee) over a rolling 24-hour window
+//!
+//! Changes that only tighten the limits apply immediately. Any change that
+//! raises or removes a limit is held for `LIMIT_CHANGE_DELAY_SECS` before it
+//! takes effect, so an attacker holding the key cannot lift the limits and
+//! drain the account straight away.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+
+/// Length of the rolling outflow window in seconds
+pub const OUTFLOW_WINDOW_SECS: u64 = 24 * 60 * 60;
+
+/// Delay before a change that loosens the limits takes effect, in seconds
+pub const LIMIT_CHANGE_DELAY_SECS: u64 = 24 * 60 * 60;
+
+/// Limits chosen by an account holder; None means unlimited
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountLimits {
+    /// Maximum amount of a single transfer
+    pub max_transaction_amount: Option<u64>,
+    /// Maximum total outflow, including fees, over the rolling window
+    pub daily_outflow_limit: Option<u64>,
+}
+
+impl AccountLimits {
+    /// Checks whether these limits are at least as strict as `current` for every field
+    pub fn is_at_least_as_strict_as(&self, current: &AccountLimits) -> bool {
+        fn stricter(new: Option<u64>, current: Option<u64>) -> bool {
+            match (new, current) {
+                (_, None) => true,
+                (None, Some(_)) => false,
+                (Some(new), Some(current)) => new <= current,
+            }
+        }
+
+        stricter(self.max_transaction_amount, current.max_transaction_amount)
+            && stricter(self.daily_outflow_limit, current.daily_outflow_limit)
+    }
+}
+
+/// A loosening change waiting for its activation time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingLimits {
+    /// The requested limits
+    pub limits: AccountLimits,
+    /// UNIX time in seconds at which the limits take effect
+    pub activates_at: u64,
+}
+
+/// Reason a transfer was refused by an account's limits
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LimitError {
+    /// The transfer amount exceeds the per-transaction maximum
+    TransactionAmountExceeded {
+        /// The attempted amount
+        amount: u64,
+        /// The account's maximum
+        limit: u64,
+    },
+    /// The transfer would push the rolling outflow over the daily cap
+    DailyOutflowExceeded {
+        /// Outflow already recorded in the window
+        spent: u64,
+        /// The attempted outflow, including the fee
+        requested: u64,
+        /// The account's cap
+        limit: u64,
+    },
+}
+
+impl fmt::Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitError::TransactionAmountExceeded { amount, limit } => {
+                write!(f, "Transaction amount {} exceeds the account limit of {}", amount, limit)
+            },
+            LimitError::DailyOutflowExceeded { spent, requested, limit } => {
+                write!(f, "Outflow of {} would exceed the daily limit of {} ({} already spent in the last 24h)",
+                       requested, limit, spent)
+            },
+        }
+    }
+}
+
+/// Limit state of one account
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountLimitState {
+    /// Limits currently enforced
+    pub active: AccountLimits,
+    /// Loosening change waiting to take effect
+    pub pending: Option<PendingLimits>,
+    /// Number of accepted limit changes, included in signed change requests against replay
+    pub change_nonce: u64,
+    /// Outflows in the rolling window as (UNIX time in seconds, amount including fee)
+    pub outflows: VecDeque<(u64, u64)>,
+}
+
+impl AccountLimitState {
+    /// Applies a pending change whose activation time has passed and drops expired outflows
+    pub fn refresh(&mut self, now: u64) {
+        if let Some(pending) = self.pending {
+            if now >= pending.activates_at {
+                self.active = pending.limits;
+                self.pending = None;
+            }
+        }
+
+        while let Some(&(timestamp, _)) = self.outflows.front() {
+            if timestamp.saturating_add(OUTFLOW_WINDOW_SECS) > now {
+                break;
+            }
+            self.outflows.pop_front();
+        }
+    }
+
+    /// Requests new limits
+    ///
+    /// # Arguments
+    /// * `limits` - The requested limits
+    /// * `now` - Current UNIX time in seconds
+    ///
+    /// # Returns
+    /// None if the limits took effect immediately, or the time at which they will
+    pub fn request_change(&mut self, limits: AccountLimits, now: u64) -> Option<u64> {
+        self.refresh(now);
+        self.change_nonce += 1;
+
+        if limits.is_at_least_as_strict_as(&self.active) {
+            // Tightening cancels any pending loosening as well
+            self.active = limits;
+            self.pending = None;
+            None
+        } else {
+            let activates_at = now + LIMIT_CHANGE_DELAY_SECS;
+            self.pending = Some(PendingLimits { limits, activates_at });
+            Some(activates_at)
+        }
+    }
+
+    /// Gets the outflow recorded in the rolling window
+    pub fn window_outflow(&self) -> u64 {
+        self.outflows.iter().map(|&(_, amount)| amount).sum()
+    }
+
+    /// Checks a transfer against the active limits
+    ///
+    /// # Arguments
+    /// * `amount` - The transfer amount
+    /// * `outflow` - The amount leaving the account, including the fee
+    /// * `now` - Current UNIX time in seconds
+    pub fn check_transfer(&mut self, amount: u64, outflow: u64, now: u64) -> Result<(), LimitError> {
+        self.refresh(now);
+
+        if let Some(limit) = self.active.max_transaction_amount {
+            if amount > limit {
+                return Err(LimitError::TransactionAmountExceeded { amount, limit });
+            }
+        }
+
+        if let Some(limit) = self.active.daily_outflow_limit {
+            let spent = self.window_outflow();
+            if spent.saturating_add(outflow) > limit {
+                return Err(LimitError::DailyOutflowExceeded { spent, requested: outflow, limit });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Records a completed outflow
+    pub fn record_outflow(&mut self, outflow: u64, now: u64) {
+        self.outflows.push_back((now, outflow));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START: u64 = 1_735_689_600;
+
+    fn limits(max_transaction_amount: Option<u64>, daily_outflow_limit: Option<u64>) -> AccountLimits {
+        AccountLimits { max_transaction_amount, daily_outflow_limit }
+    }
+
+    #[test]
+    fn test_outflow_window_boundary() {
+        let mut state = AccountLimitState::default();
+        assert_eq!(state.request_change(limits(None, Some(100)), START), None);
+
+        state.check_transfer(60, 60, START).unwrap();
+        state.record_outflow(60, START);
+        state.check_transfer(40, 40, START + 10).unwrap();
+        state.record_outflow(40, START + 10);
+
+        // The cap is reached until the first outflow leaves the window
+        assert!(state.check_transfer(1, 1, START + OUTFLOW_WINDOW_SECS - 1).is_err());
+        assert_eq!(state.check_transfer(60, 60, START + OUTFLOW_WINDOW_SECS), Ok(()));
+        assert_eq!(
+            state.check_transfer(61, 61, START + OUTFLOW_WINDOW_SECS),
+            Err(LimitError::DailyOutflowExceeded { spent: 40, requested: 61, limit: 100 })
+        );
+    }
+
+    #[test]
+    fn test_raised_limit_activates_after_delay() {
+        let mut state = AccountLimitState::default();
+        state.request_change(limits(Some(50), None), START);
+        assert_eq!(state.change_nonce, 1);
+
+        // Raising the limit is delayed
+        assert_eq!(state.request_change(limits(Some(500), None), START), Some(START + LIMIT_CHANGE_DELAY_SECS));
+        assert!(state.check_transfer(100, 101, START + LIMIT_CHANGE_DELAY_SECS - 1).is_err());
+        assert_eq!(state.check_transfer(100, 101, START + LIMIT_CHANGE_DELAY_SECS), Ok(()));
+        assert_eq!(state.active, limits(Some(500), None));
+        assert!(state.pending.is_none());
+
+        // Removing a limit is loosening too, while tightening again is immediate and cancels it
+        assert!(state.request_change(limits(None, None), START + LIMIT_CHANGE_DELAY_SECS).is_some());
+        assert_eq!(state.request_change(limits(Some(10), Some(1_000)), START + LIMIT_CHANGE_DELAY_SECS + 1), None);
+        assert!(state.pending.is_none());
+        assert_eq!(
+            state.check_transfer(11, 12, START + 3 * LIMIT_CHANGE_DELAY_SECS),
+            Err(LimitError::TransactionAmountExceeded { amount: 11, limit: 10 })
+        );
+    }
+}
@@ -0,0 +1,57 @@
+//! Node Liveness
+//!
+//! A quiet chain and a wedged one look the same from the outside: no new
+//! transactions either way. The node therefore records when it last produced
+//! a block and when it last applied an imported one, and a watchdog flags the
+//! node as stalled once neither has happened for several block times. RPC
+//! clients read the result through `BlockProducer::liveness` and are told of
+//! each transition with a `NodeHealthEvent`.
+
+use serde::{Deserialize, Serialize};
+
+/// Whether the node is making progress, and when it last did
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LivenessStatus {
+    /// Whether no block was produced or imported within the stall threshold
+    pub stalled: bool,
+    /// When the node was flagged as stalled, in seconds since the epoch
+    pub stalled_since: Option<u64>,
+    /// Number of the latest produced or imported block
+    pub last_block: u64,
+    /// When the node last produced a block, in seconds since the epoch
+    pub last_produced_at: Option<u64>,
+    /// When the node last applied an imported block, in seconds since the epoch
+    pub last_imported_at: Option<u64>,
+    /// Why block production stopped for good, if it did
+    #[serde(default)]
+    pub halted: Option<String>,
+}
+
+impl LivenessStatus {
+    /// Gets when the node last made progress, by producing or importing a block
+    pub fn last_progress_at(&self) -> Option<u64> {
+        self.last_produced_at.max(self.last_imported_at)
+    }
+}
+
+/// A change in the node's liveness, delivered to `nodeHealth` subscribers
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "camelCase")]
+pub enum NodeHealthEvent {
+    /// No block was produced or imported within the stall threshold
+    NodeStalled {
+        /// Number of the latest block
+        last_block: u64,
+        /// When the node last made progress, in seconds since the epoch
+        last_progress_at: Option<u64>,
+        /// Seconds since the node last made progress
+        stalled_for_secs: u64,
+    },
+    /// A block was produced or imported again after a stall
+    NodeRecovered {
+        /// Number of the block that ended the stall
+        last_block: u64,
+        /// How long the stall lasted, in seconds
+        stalled_for_secs: u64,
+    },
+}
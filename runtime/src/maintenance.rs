@@ -0,0 +1,69 @@
+//! Maintenance Mode
+//!
+//! Risky operations such as a checkpoint restore, a state migration or
+//! recovering from a reorg need the state to hold still. While maintenance
+//! mode is on, the runtime refuses every balance change, the RPC layer
+//! refuses mutating methods with the reason, and the block producer either
+//! seals empty heartbeat blocks or pauses. Reads keep working.
+//!
+//! The mode is switched under the runtime's state gate, so a transfer either
+//! completes before maintenance starts or is refused; none runs halfway.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Whether the node is in maintenance mode, and why
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaintenanceStatus {
+    /// Whether mutations are refused
+    pub enabled: bool,
+    /// Why the operator enabled maintenance mode
+    pub reason: Option<String>,
+    /// Address of the admin who enabled it
+    pub enabled_by: Option<String>,
+    /// When it was enabled, in seconds since epoch
+    pub since: Option<u64>,
+}
+
+impl MaintenanceStatus {
+    /// Gets the message mutating requests are refused with
+    pub fn refusal(&self) -> String {
+        match &self.reason {
+            Some(reason) => format!("Node is in maintenance mode: {}", reason),
+            None => "Node is in maintenance mode".to_string(),
+        }
+    }
+}
+
+/// What the block producer does while maintenance mode is on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaintenanceBlocks {
+    /// Seal empty blocks without a reward, so peers see the node is alive
+    #[default]
+    Heartbeat,
+    /// Produce no blocks until maintenance ends
+    Pause,
+}
+
+impl FromStr for MaintenanceBlocks {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "heartbeat" => Ok(MaintenanceBlocks::Heartbeat),
+            "pause" => Ok(MaintenanceBlocks::Pause),
+            _ => Err(format!("Invalid maintenance block mode: {} (expected heartbeat or pause)", s)),
+        }
+    }
+}
+
+impl fmt::Display for MaintenanceBlocks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MaintenanceBlocks::Heartbeat => write!(f, "heartbeat"),
+            MaintenanceBlocks::Pause => write!(f, "pause"),
+        }
+    }
+}
@@ -0,0 +1,321 @@
+//! Chain Parameters
+//!
+//! The fee rate, minimum fee, fee split, UBI rate and block reward are runtime
+//! parameters rather than constants. They never change on the spot: a change is
+//! queued with an activation height at least `MIN_ACTIVATION_DELAY_BLOCKS`
+//! ahead, and the block producer applies it when it starts that block, so every
+//! transfer and reward in the activation block already uses the new value.
+//!
+//! Changes are scheduled by passed governance proposals or, while the network
+//! bootstraps, by the parameter admin over RPC.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{TRANSFER_FEE_PERCENT, UBI_TOKENS_PER_HOUR};
+
+/// Number of basis points in 100%
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+/// Default number of tokens credited to the producer of each block
+pub const DEFAULT_BLOCK_REWARD: u64 = 100;
+
+/// Minimum number of blocks between scheduling a change and its activation
+pub const MIN_ACTIVATION_DELAY_BLOCKS: u64 = 100;
+
+/// A parameter that can be changed by a scheduled change
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChainParam {
+    /// Transfer fee in basis points of the amount
+    FeeBps,
+    /// Smallest fee charged on a transfer
+    MinimumFee,
+    /// Share of each fee paid into the dividend pool, in basis points; the rest is burned
+    FeePoolShareBps,
+    /// UBI tokens accrued per hour by verified accounts
+    UbiTokensPerHour,
+    /// Tokens credited to the producer of each block
+    BlockReward,
+}
+
+impl ChainParam {
+    /// Gets the name of the parameter as used over RPC
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChainParam::FeeBps => "fee_bps",
+            ChainParam::MinimumFee => "minimum_fee",
+            ChainParam::FeePoolShareBps => "fee_pool_share_bps",
+            ChainParam::UbiTokensPerHour => "ubi_tokens_per_hour",
+            ChainParam::BlockReward => "block_reward",
+        }
+    }
+}
+
+impl fmt::Display for ChainParam {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for ChainParam {
+    type Err = ParamChangeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fee_bps" => Ok(ChainParam::FeeBps),
+            "minimum_fee" => Ok(ChainParam::MinimumFee),
+            "fee_pool_share_bps" => Ok(ChainParam::FeePoolShareBps),
+            "ubi_tokens_per_hour" => Ok(ChainParam::UbiTokensPerHour),
+            "block_reward" => Ok(ChainParam::BlockReward),
+            _ => Err(ParamChangeError::UnknownParam(s.to_string())),
+        }
+    }
+}
+
+/// Values of the chain parameters in effect
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainParams {
+    /// Transfer fee in basis points of the amount, rounded to the nearest token
+    pub fee_bps: u64,
+    /// Smallest fee charged on a transfer
+    pub minimum_fee: u64,
+    /// Share of each fee paid into the dividend pool, in basis points
+    pub fee_pool_share_bps: u64,
+    /// UBI tokens accrued per hour by verified accounts
+    pub ubi_tokens_per_hour: u64,
+    /// Tokens credited to the producer of each block
+    pub block_reward: u64,
+}
+
+impl Default for ChainParams {
+    fn default() -> Self {
+        ChainParams {
+            fee_bps: TRANSFER_FEE_PERCENT * BPS_DENOMINATOR / 100,
+            minimum_fee: 0,
+            fee_pool_share_bps: BPS_DENOMINATOR,
+            ubi_tokens_per_hour: UBI_TOKENS_PER_HOUR,
+            block_reward: DEFAULT_BLOCK_REWARD,
+        }
+    }
+}
+
+impl ChainParams {
+    /// Computes the fee charged on top of a transfer
+    ///
+    /// The fee is rounded to the nearest token, halves up, so small transfers
+    /// are not free: at 1% a transfer of 50 tokens pays one.
+    pub fn transfer_fee(&self, amount: u64) -> u64 {
+        let scaled = amount as u128 * self.fee_bps as u128 + BPS_DENOMINATOR as u128 / 2;
+        let fee = (scaled / BPS_DENOMINATOR as u128).min(u64::MAX as u128) as u64;
+        fee.max(self.minimum_fee)
+    }
+
+    /// Splits a fee into the part paid into the dividend pool and the part burned
+    pub fn split_fee(&self, fee: u64) -> (u64, u64) {
+        let to_pool = (fee as u128 * self.fee_pool_share_bps as u128 / BPS_DENOMINATOR as u128) as u64;
+        (to_pool, fee - to_pool)
+    }
+
+    /// Gets the value of a parameter
+    pub fn get(&self, param: ChainParam) -> u64 {
+        match param {
+            ChainParam::FeeBps => self.fee_bps,
+            ChainParam::MinimumFee => self.minimum_fee,
+            ChainParam::FeePoolShareBps => self.fee_pool_share_bps,
+            ChainParam::UbiTokensPerHour => self.ubi_tokens_per_hour,
+            ChainParam::BlockReward => self.block_reward,
+        }
+    }
+
+    /// Sets the value of a parameter
+    fn set(&mut self, param: ChainParam, value: u64) {
+        match param {
+            ChainParam::FeeBps => self.fee_bps = value,
+            ChainParam::MinimumFee => self.minimum_fee = value,
+            ChainParam::FeePoolShareBps => self.fee_pool_share_bps = value,
+            ChainParam::UbiTokensPerHour => self.ubi_tokens_per_hour = value,
+            ChainParam::BlockReward => self.block_reward = value,
+        }
+    }
+}
+
+/// A parameter change waiting for its activation block
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingParamChange {
+    /// The parameter to change
+    pub param: ChainParam,
+    /// The value it takes at activation
+    pub new_value: u64,
+    /// Height of the first block produced with the new value
+    pub activates_at_block: u64,
+}
+
+/// Reason a parameter change was refused
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParamChangeError {
+    /// The parameter name is not known
+    UnknownParam(String),
+    /// The value is out of range for the parameter
+    InvalidValue {
+        /// The parameter
+        param: ChainParam,
+        /// The refused value
+        value: u64,
+    },
+    /// The activation block is closer than `MIN_ACTIVATION_DELAY_BLOCKS`
+    ActivationTooSoon {
+        /// The requested activation block
+        activates_at_block: u64,
+        /// The earliest allowed activation block
+        earliest: u64,
+    },
+}
+
+impl fmt::Display for ParamChangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParamChangeError::UnknownParam(name) => write!(f, "Unknown chain parameter: {}", name),
+            ParamChangeError::InvalidValue { param, value } => {
+                write!(f, "Invalid value {} for chain parameter {}", value, param)
+            },
+            ParamChangeError::ActivationTooSoon { activates_at_block, earliest } => {
+                write!(f, "Activation block {} is too soon; the earliest allowed is {}", activates_at_block, earliest)
+            },
+        }
+    }
+}
+
+/// Parameters in effect and changes queued for later blocks
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParamSchedule {
+    /// Parameters in effect
+    pub current: ChainParams,
+    /// Queued changes, ordered by activation block and then scheduling order
+    pub pending: Vec<PendingParamChange>,
+    /// Height of the latest block the schedule was applied at
+    pub height: u64,
+    /// Number of changes ever scheduled, included in signed admin requests against replay
+    pub change_nonce: u64,
+}
+
+impl ParamSchedule {
+    /// Queues a parameter change
+    ///
+    /// # Arguments
+    /// * `change` - The change; its activation block must be at least
+    ///   `MIN_ACTIVATION_DELAY_BLOCKS` after the latest applied block
+    pub fn schedule(&mut self, change: PendingParamChange) -> Result<(), ParamChangeError> {
+        let max_value = match change.param {
+            ChainParam::FeeBps | ChainParam::FeePoolShareBps => BPS_DENOMINATOR,
+            _ => u64::MAX,
+        };
+        if change.new_value > max_value {
+            return Err(ParamChangeError::InvalidValue { param: change.param, value: change.new_value });
+        }
+
+        let earliest = self.height.saturating_add(MIN_ACTIVATION_DELAY_BLOCKS);
+        if change.activates_at_block < earliest {
+            return Err(ParamChangeError::ActivationTooSoon {
+                activates_at_block: change.activates_at_block,
+                earliest,
+            });
+        }
+
+        // Keep changes for the same block in the order they were scheduled
+        let position = self.pending.iter()
+            .position(|pending| pending.activates_at_block > change.activates_at_block)
+            .unwrap_or(self.pending.len());
+        self.pending.insert(position, change);
+        self.change_nonce += 1;
+        Ok(())
+    }
+
+    /// Applies every change due at or before the given block
+    ///
+    /// # Arguments
+    /// * `height` - Height of the block about to be produced or imported
+    ///
+    /// # Returns
+    /// The changes that took effect, in the order they were applied
+    pub fn apply(&mut self, height: u64) -> Vec<PendingParamChange> {
+        let due = self.pending.iter()
+            .take_while(|pending| pending.activates_at_block <= height)
+            .count();
+        let applied: Vec<PendingParamChange> = self.pending.drain(..due).collect();
+
+        for change in &applied {
+            self.current.set(change.param, change.new_value);
+        }
+        self.height = self.height.max(height);
+
+        applied
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fee_rules() {
+        let mut params = ChainParams::default();
+        assert_eq!(params.fee_bps, 100);
+        assert_eq!([10_000, 250, 249, 50, 49, 0].map(|amount| params.transfer_fee(amount)), [100, 3, 2, 1, 0, 0]);
+        params.fee_bps = 0;
+        assert_eq!(params.transfer_fee(10_000), 0);
+        params.fee_bps = 250;
+        assert_eq!([10_000, 99, 20, 19].map(|amount| params.transfer_fee(amount)), [250, 2, 1, 0]);
+        assert_eq!(params.transfer_fee(u64::MAX), u64::MAX / 40);
+
+        params.fee_bps = 25;
+        params.minimum_fee = 3;
+        assert_eq!(params.transfer_fee(10_000), 25);
+        assert_eq!(params.transfer_fee(100), 3);
+
+        params.fee_pool_share_bps = 7_500;
+        assert_eq!(params.split_fee(25), (18, 7));
+        assert_eq!(ChainParams::default().split_fee(25), (25, 0));
+    }
+
+    #[test]
+    fn test_changes_apply_at_activation_block() {
+        let mut schedule = ParamSchedule::default();
+        schedule.apply(5);
+
+        let too_soon = PendingParamChange {
+            param: ChainParam::FeeBps,
+            new_value: 50,
+            activates_at_block: 5 + MIN_ACTIVATION_DELAY_BLOCKS - 1,
+        };
+        assert_eq!(schedule.schedule(too_soon), Err(ParamChangeError::ActivationTooSoon {
+            activates_at_block: too_soon.activates_at_block,
+            earliest: 5 + MIN_ACTIVATION_DELAY_BLOCKS,
+        }));
+        assert!(matches!(
+            schedule.schedule(PendingParamChange { new_value: BPS_DENOMINATOR + 1, activates_at_block: 500, ..too_soon }),
+            Err(ParamChangeError::InvalidValue { .. })
+        ));
+
+        let activation = 5 + MIN_ACTIVATION_DELAY_BLOCKS;
+        schedule.schedule(PendingParamChange { param: ChainParam::FeeBps, new_value: 50, activates_at_block: activation + 10 }).unwrap();
+        schedule.schedule(PendingParamChange { param: ChainParam::BlockReward, new_value: 7, activates_at_block: activation }).unwrap();
+        schedule.schedule(PendingParamChange { param: ChainParam::FeeBps, new_value: 75, activates_at_block: activation + 10 }).unwrap();
+        assert_eq!(schedule.change_nonce, 3);
+
+        assert!(schedule.apply(activation - 1).is_empty());
+        assert_eq!(schedule.current.block_reward, DEFAULT_BLOCK_REWARD);
+
+        assert_eq!(schedule.apply(activation).len(), 1);
+        assert_eq!(schedule.current.block_reward, 7);
+
+        // Changes due at the same block apply in scheduling order
+        assert_eq!(schedule.apply(activation + 10).len(), 2);
+        assert_eq!(schedule.current.fee_bps, 75);
+        assert!(schedule.pending.is_empty());
+
+        assert_eq!("ubi_tokens_per_hour".parse::<ChainParam>(), Ok(ChainParam::UbiTokensPerHour));
+        assert!("fee_percent".parse::<ChainParam>().is_err());
+    }
+}
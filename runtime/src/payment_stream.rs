@@ -0,0 +1,266 @@
+//! Payment Streams
+//!
+//! A payment stream moves tokens from one account to another at a fixed rate
+//! per hour until it is closed, for example to pay for a metered resource.
+//! Streams move tokens the way UBI streams in: the rate is spread over each
+//! second, only whole tokens move, and the fraction of a token streamed so far
+//! carries over to the next settlement.
+//!
+//! Streams are settled lazily, when a transfer touches either party, when a
+//! block executes one, or on request. Reading a balance or a stream shows it
+//! as if settled now but settles nothing, so reads never change state and
+//! every node reaches the same state whatever it serves. A sender that cannot pay
+//! what is due pays what it holds and the stream pauses, so no balance ever
+//! goes negative. A paused stream resumes at the first settlement that finds
+//! the sender funded again; nothing is owed for the time it was paused.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::{streaming_rate_per_hour, update_account_state, AccountError, AccountState};
+
+/// Identifier of a payment stream, unique for the life of the chain
+pub type StreamId = u64;
+
+/// A stream of tokens from one account to another
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaymentStream {
+    /// Identifier of the stream
+    pub id: StreamId,
+    /// Paying account, lowercase
+    pub from: String,
+    /// Receiving account, lowercase
+    pub to: String,
+    /// Tokens moved per hour
+    pub rate_per_hour: u64,
+    /// When the stream was opened, in seconds since epoch
+    pub opened_at: u64,
+    /// Time the stream is settled up to, in seconds since epoch
+    pub settled_at: u64,
+    /// Tokens moved so far
+    pub streamed: u64,
+    /// Whether the stream is paused because the sender ran out of tokens
+    pub paused: bool,
+}
+
+impl PaymentStream {
+    /// Works out the whole tokens due by the given time, and the time paying
+    /// them settles the stream up to
+    ///
+    /// A paused stream owes nothing.
+    pub fn due(&self, now: u64) -> (u64, u64) {
+        if self.paused {
+            return (0, now);
+        }
+        let mut state = AccountState {
+            last_update: self.settled_at,
+            streaming_rate: streaming_rate_per_hour(self.rate_per_hour),
+            ..Default::default()
+        };
+        update_account_state(&mut state, now);
+        (state.base_balance, state.last_update)
+    }
+
+    /// Settles the stream given what the sender can pay
+    ///
+    /// # Arguments
+    /// * `now` - Time of the settlement
+    /// * `available` - Tokens the sender holds
+    ///
+    /// # Returns
+    /// The tokens to move from the sender to the recipient
+    pub fn settle(&mut self, now: u64, available: u64) -> u64 {
+        if self.paused {
+            // Resume from now once the sender can pay again
+            if available > 0 {
+                self.paused = false;
+                self.settled_at = now;
+            }
+            return 0;
+        }
+
+        let (due, settled_to) = self.due(now);
+        let paid = due.min(available);
+        if paid < due {
+            self.paused = true;
+            self.settled_at = now;
+        } else {
+            self.settled_at = settled_to;
+        }
+        self.streamed = self.streamed.saturating_add(paid);
+        paid
+    }
+
+    /// Whether the account pays or receives the stream
+    pub fn involves(&self, address: &str) -> bool {
+        self.from == address || self.to == address
+    }
+}
+
+/// Reason a stream could not be opened, settled or closed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StreamError {
+    /// The sender and the recipient are the same account
+    SameAccount,
+    /// The rate is zero
+    ZeroRate,
+    /// No open stream has the id
+    UnknownStream(StreamId),
+    /// A party is missing, a balance would overflow or the node is in maintenance mode
+    Account(AccountError),
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamError::SameAccount => write!(f, "A stream cannot pay the account it comes from"),
+            StreamError::ZeroRate => write!(f, "Stream rate must be greater than zero"),
+            StreamError::UnknownStream(id) => write!(f, "No open stream with id {}", id),
+            StreamError::Account(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<AccountError> for StreamError {
+    fn from(e: AccountError) -> Self {
+        StreamError::Account(e)
+    }
+}
+
+impl std::error::Error for StreamError {}
+
+/// The open payment streams
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PaymentStreams {
+    /// Open streams by id
+    streams: BTreeMap<StreamId, PaymentStream>,
+    /// Id the next stream opened gets
+    next_id: StreamId,
+}
+
+impl PaymentStreams {
+    /// Rebuilds the registry from stored streams
+    ///
+    /// # Arguments
+    /// * `streams` - The open streams
+    /// * `next_id` - Id the next stream opened gets; raised above every stored id
+    pub fn restore(streams: Vec<PaymentStream>, next_id: StreamId) -> Self {
+        let next_id = streams.iter().map(|stream| stream.id + 1).max().unwrap_or(0).max(next_id);
+        PaymentStreams {
+            streams: streams.into_iter().map(|stream| (stream.id, stream)).collect(),
+            next_id,
+        }
+    }
+
+    /// Gets the id the next stream opened gets
+    pub fn next_id(&self) -> StreamId {
+        self.next_id
+    }
+
+    /// Opens a stream, settled up to the time it is opened
+    ///
+    /// # Returns
+    /// The new stream's id
+    pub fn open(&mut self, from: &str, to: &str, rate_per_hour: u64, now: u64) -> StreamId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.streams.insert(id, PaymentStream {
+            id,
+            from: from.to_string(),
+            to: to.to_string(),
+            rate_per_hour,
+            opened_at: now,
+            settled_at: now,
+            streamed: 0,
+            paused: false,
+        });
+        id
+    }
+
+    /// Gets an open stream
+    pub fn get(&self, id: StreamId) -> Option<&PaymentStream> {
+        self.streams.get(&id)
+    }
+
+    /// Gets an open stream to settle it
+    pub fn get_mut(&mut self, id: StreamId) -> Option<&mut PaymentStream> {
+        self.streams.get_mut(&id)
+    }
+
+    /// Removes a stream
+    pub fn remove(&mut self, id: StreamId) -> Option<PaymentStream> {
+        self.streams.remove(&id)
+    }
+
+    /// Gets the ids of the streams an account pays or receives, oldest first
+    pub fn ids_of(&self, address: &str) -> Vec<StreamId> {
+        self.streams.values()
+            .filter(|stream| stream.involves(address))
+            .map(|stream| stream.id)
+            .collect()
+    }
+
+    /// Gets every open stream, oldest first
+    pub fn all(&self) -> Vec<PaymentStream> {
+        self.streams.values().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stream(rate_per_hour: u64) -> PaymentStream {
+        PaymentStream {
+            id: 0,
+            from: "0xaa".to_string(),
+            to: "0xbb".to_string(),
+            rate_per_hour,
+            opened_at: 1_000,
+            settled_at: 1_000,
+            streamed: 0,
+            paused: false,
+        }
+    }
+
+    #[test]
+    fn test_settlement_carries_part_tokens() {
+        // Five tokens an hour is one every 12 minutes
+        let mut stream = stream(5);
+        assert_eq!(stream.settle(1_000 + 17 * 60, 100), 1);
+        assert_eq!(stream.settled_at, 1_000 + 12 * 60);
+        assert_eq!(stream.settle(1_000 + 24 * 60, 100), 1);
+        assert_eq!(stream.settle(1_000 + 3600, 100), 3);
+        assert_eq!(stream.streamed, 5);
+    }
+
+    #[test]
+    fn test_exhausted_sender_pauses_the_stream() {
+        let mut stream = stream(60);
+        assert_eq!(stream.settle(1_000 + 600, 4), 4);
+        assert!(stream.paused);
+        assert_eq!(stream.due(1_000 + 3600), (0, 1_000 + 3600));
+
+        // Nothing is owed for the paused time, and the stream resumes once funded
+        assert_eq!(stream.settle(1_000 + 1200, 0), 0);
+        assert!(stream.paused);
+        assert_eq!(stream.settle(1_000 + 1800, 50), 0);
+        assert!(!stream.paused);
+        assert_eq!(stream.settle(1_000 + 1800 + 300, 50), 5);
+        assert_eq!(stream.streamed, 9);
+    }
+
+    #[test]
+    fn test_ids_are_never_reused() {
+        let mut streams = PaymentStreams::default();
+        let first = streams.open("0xaa", "0xbb", 1, 0);
+        let second = streams.open("0xbb", "0xaa", 1, 0);
+        assert_eq!(streams.ids_of("0xaa"), vec![first, second]);
+        streams.remove(second);
+
+        let restored = PaymentStreams::restore(streams.all(), streams.next_id());
+        assert_eq!(restored.next_id(), 2);
+        assert_eq!(PaymentStreams::restore(streams.all(), 0).next_id(), 1);
+    }
+}
@@ -0,0 +1,139 @@
+//! Reserved Addresses
+//!
+//! Some addresses stand for the system rather than for a holder. No account
+//! can be created at them; each is only used through its own code path:
+//! - the zero address, where mistyped or defaulted transfers would end up
+//! - the burn address: transfers to it are burned
+//! - the UBI mint address: the sender of UBI mint system transactions
+//! - the token facade address: the ERC-20 contract wallets talk to
+//! - ranges the genesis file declares for later system use
+//!
+//! Addresses are compared lowercase, and since they all have the same length
+//! a range check is a plain string comparison.
+
+use serde::{Deserialize, Serialize};
+
+use crate::is_valid_eth_address;
+use crate::ubi_mint::UBI_MINT_ADDRESS;
+
+/// The zero address
+pub const ZERO_ADDRESS: &str = "0x0000000000000000000000000000000000000000";
+
+/// Address of the ERC-20 token facade, the contract wallets call for balances and transfers
+pub const TOKEN_FACADE_ADDRESS: &str = "0x0000000000000000000000000000000000001000";
+
+/// A reserved address range, both ends inclusive
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReservedRange {
+    /// Lowest reserved address
+    pub from: String,
+    /// Highest reserved address
+    pub to: String,
+    /// What the addresses are reserved for
+    pub purpose: String,
+}
+
+impl ReservedRange {
+    /// Reserves a single address
+    pub fn single(address: &str, purpose: &str) -> Self {
+        ReservedRange {
+            from: address.to_lowercase(),
+            to: address.to_lowercase(),
+            purpose: purpose.to_string(),
+        }
+    }
+
+    /// Checks that both ends are valid addresses and the range is not empty
+    pub fn is_valid(&self) -> bool {
+        is_valid_eth_address(&self.from)
+            && is_valid_eth_address(&self.to)
+            && self.from.to_lowercase() <= self.to.to_lowercase()
+    }
+
+    /// Checks whether a lowercase address falls in the range
+    fn contains(&self, address: &str) -> bool {
+        self.from.to_lowercase().as_str() <= address && address <= self.to.to_lowercase().as_str()
+    }
+}
+
+/// Addresses no account may be created at
+#[derive(Debug, Clone)]
+pub struct ReservedAddresses {
+    /// Address whose transfers are burned, lowercase
+    burn_address: String,
+    /// Fixed system addresses and ranges declared at genesis
+    ranges: Vec<ReservedRange>,
+}
+
+impl ReservedAddresses {
+    /// Reserves the system addresses, with the given burn address
+    pub fn new(burn_address: &str) -> Self {
+        ReservedAddresses {
+            burn_address: burn_address.to_lowercase(),
+            ranges: vec![
+                ReservedRange::single(ZERO_ADDRESS, "zero address"),
+                ReservedRange::single(UBI_MINT_ADDRESS, "UBI mint"),
+                ReservedRange::single(TOKEN_FACADE_ADDRESS, "token facade"),
+            ],
+        }
+    }
+
+    /// Changes the burn address
+    pub fn set_burn_address(&mut self, burn_address: &str) {
+        self.burn_address = burn_address.to_lowercase();
+    }
+
+    /// Reserves a range of addresses
+    pub fn reserve(&mut self, range: ReservedRange) {
+        self.ranges.push(range);
+    }
+
+    /// Gets what an address is reserved for, or None if an account may use it
+    pub fn purpose(&self, address: &str) -> Option<String> {
+        let address = address.to_lowercase();
+        if address == self.burn_address {
+            return Some("burn address".to_string());
+        }
+        self.ranges.iter()
+            .find(|range| range.contains(&address))
+            .map(|range| range.purpose.clone())
+    }
+
+    /// Lists every reserved range, the burn address first
+    pub fn ranges(&self) -> Vec<ReservedRange> {
+        std::iter::once(ReservedRange::single(&self.burn_address, "burn address"))
+            .chain(self.ranges.iter().cloned())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_system_addresses_and_ranges_are_reserved() {
+        let mut reserved = ReservedAddresses::new(crate::DEFAULT_BURN_ADDRESS);
+        assert_eq!(reserved.purpose(ZERO_ADDRESS).as_deref(), Some("zero address"));
+        assert_eq!(reserved.purpose(&TOKEN_FACADE_ADDRESS.to_uppercase().replace("0X", "0x")).as_deref(), Some("token facade"));
+        assert_eq!(reserved.purpose(crate::DEFAULT_BURN_ADDRESS).as_deref(), Some("burn address"));
+        assert_eq!(reserved.purpose("0x0000000000000000000000000000000000000001"), None);
+
+        let range = ReservedRange {
+            from: "0x00000000000000000000000000000000000a0000".to_string(),
+            to: "0x00000000000000000000000000000000000AFFFF".to_string(),
+            purpose: "bridges".to_string(),
+        };
+        assert!(range.is_valid());
+        reserved.reserve(range);
+        assert_eq!(reserved.purpose("0x00000000000000000000000000000000000a1234").as_deref(), Some("bridges"));
+        assert_eq!(reserved.purpose("0x00000000000000000000000000000000000b0000"), None);
+
+        let backwards = ReservedRange {
+            from: "0x0000000000000000000000000000000000000002".to_string(),
+            to: "0x0000000000000000000000000000000000000001".to_string(),
+            purpose: "nothing".to_string(),
+        };
+        assert!(!backwards.is_valid());
+    }
+}
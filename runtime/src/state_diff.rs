@@ -0,0 +1,134 @@
+//! State Diffs
+//!
+//! Compares the state held by two checkpoints, to find what changed between
+//! them when chasing a balance discrepancy. Accounts are matched by address:
+//! those only in the later checkpoint were created, those only in the earlier
+//! one were removed, and those in both are listed when their balance or
+//! verification changed. Every list is sorted by address.
+
+use crate::checkpoint::{CheckpointAccount, CheckpointData};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// An amount in the earlier and the later state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AmountChange {
+    /// Amount in the earlier state
+    pub before: u64,
+    /// Amount in the later state
+    pub after: u64,
+    /// `after` minus `before`
+    pub delta: i128,
+}
+
+impl AmountChange {
+    fn new(before: u64, after: u64) -> Self {
+        AmountChange { before, after, delta: after as i128 - before as i128 }
+    }
+}
+
+/// An account that exists in only one of the two states
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiffAccount {
+    /// Account address
+    pub address: String,
+    /// Balance in the state that holds the account
+    pub balance: u64,
+    /// Whether the account was verified in that state
+    pub verified: bool,
+}
+
+impl From<&CheckpointAccount> for DiffAccount {
+    fn from(account: &CheckpointAccount) -> Self {
+        DiffAccount { address: account.address.clone(), balance: account.balance, verified: account.verified }
+    }
+}
+
+/// How an account in both states changed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountChange {
+    /// Account address
+    pub address: String,
+    /// Balance before and after
+    pub balance: AmountChange,
+    /// Whether the account was verified before
+    pub verified_before: bool,
+    /// Whether the account is verified after
+    pub verified_after: bool,
+}
+
+/// What changed between two states
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateDiff {
+    /// Timestamp of the earlier state
+    pub from_timestamp: u64,
+    /// Timestamp of the later state
+    pub to_timestamp: u64,
+    /// Accounts only in the later state
+    pub created: Vec<DiffAccount>,
+    /// Accounts only in the earlier state
+    pub removed: Vec<DiffAccount>,
+    /// Accounts in both whose balance or verification changed
+    pub changed: Vec<AccountChange>,
+    /// Total supply before and after
+    pub total_supply: AmountChange,
+    /// Fee pool before and after
+    pub fee_pool: AmountChange,
+}
+
+impl StateDiff {
+    /// Compares two states
+    ///
+    /// # Arguments
+    /// * `from` - The earlier state
+    /// * `to` - The later state
+    pub fn between(from: &CheckpointData, to: &CheckpointData) -> Self {
+        let before: HashMap<&str, &CheckpointAccount> = from.accounts.iter()
+            .map(|account| (account.address.as_str(), account))
+            .collect();
+        let after: HashMap<&str, &CheckpointAccount> = to.accounts.iter()
+            .map(|account| (account.address.as_str(), account))
+            .collect();
+
+        let mut created = Vec::new();
+        let mut changed = Vec::new();
+        for account in &to.accounts {
+            match before.get(account.address.as_str()) {
+                None => created.push(DiffAccount::from(account)),
+                Some(previous) if previous.balance != account.balance || previous.verified != account.verified => {
+                    changed.push(AccountChange {
+                        address: account.address.clone(),
+                        balance: AmountChange::new(previous.balance, account.balance),
+                        verified_before: previous.verified,
+                        verified_after: account.verified,
+                    });
+                },
+                Some(_) => {},
+            }
+        }
+        let mut removed: Vec<DiffAccount> = from.accounts.iter()
+            .filter(|account| !after.contains_key(account.address.as_str()))
+            .map(DiffAccount::from)
+            .collect();
+
+        created.sort_by(|a, b| a.address.cmp(&b.address));
+        removed.sort_by(|a, b| a.address.cmp(&b.address));
+        changed.sort_by(|a, b| a.address.cmp(&b.address));
+
+        StateDiff {
+            from_timestamp: from.timestamp,
+            to_timestamp: to.timestamp,
+            created,
+            removed,
+            changed,
+            total_supply: AmountChange::new(from.total_supply, to.total_supply),
+            fee_pool: AmountChange::new(from.fee_pool, to.fee_pool),
+        }
+    }
+
+    /// Whether the two states hold the same accounts, balances, verification, supply and fee pool
+    pub fn is_empty(&self) -> bool {
+        self.created.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+            && self.total_supply.delta == 0 && self.fee_pool.delta == 0
+    }
+}
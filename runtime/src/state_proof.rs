@@ -0,0 +1,169 @@
+//! State Proofs
+//!
+//! Each account is a leaf of the state tree, hashing its address, balance,
+//! last update, streaming rate, verification flag, nonce and last UBI claim.
+//! A proof lists the sibling of every node on
+//! the path from the leaf up to the root, so anyone holding the root can check
+//! an account's state without the rest of the tree.
+//!
+//! In JSON the path is an array of `{"hash", "position"}` objects, leaf level
+//! first: `hash` is the sibling's 0x-prefixed hex hash and `position` is
+//! `left` or `right`, the side of the path node the sibling is on.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{root_hex, AccountState, MerkleTree};
+
+/// Side a sibling is on in a proof step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SiblingPosition {
+    /// The sibling is hashed before the path node
+    Left,
+    /// The sibling is hashed after the path node
+    Right,
+}
+
+/// One level of a proof path
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofStep {
+    /// Hash of the sibling, 0x-prefixed hex
+    pub hash: String,
+    /// Side of the path node the sibling is on
+    pub position: SiblingPosition,
+}
+
+/// An account's state with the path proving it against a state root
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountProof {
+    /// State root the proof leads to, 0x-prefixed hex
+    pub root: String,
+    /// Account address, lowercase
+    pub address: String,
+    /// Balance hashed into the leaf
+    pub balance: u64,
+    /// Last update hashed into the leaf, in seconds since epoch
+    pub last_update: u64,
+    /// Streaming rate hashed into the leaf
+    pub streaming_rate: u64,
+    /// Verification flag hashed into the leaf
+    pub verified: bool,
+    /// Transaction nonce hashed into the leaf
+    pub nonce: u64,
+    /// Last UBI claim hashed into the leaf, in seconds since epoch
+    pub last_ubi_claim: u64,
+    /// Siblings from the leaf level up to the root
+    pub path: Vec<ProofStep>,
+}
+
+/// Outcome of checking a proof
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofCheck {
+    /// Whether the path leads from the account state to the claimed root
+    pub valid: bool,
+    /// Root the path actually leads to, 0x-prefixed hex
+    pub computed_root: String,
+}
+
+impl AccountProof {
+    /// Builds a proof from a tree path, as returned by `MerkleTree::generate_proof`
+    pub fn new(root: [u8; 32], address: &str, state: &AccountState, path: &[([u8; 32], bool)]) -> Self {
+        AccountProof {
+            root: root_hex(Some(root)),
+            address: address.to_lowercase(),
+            balance: state.base_balance,
+            last_update: state.last_update,
+            streaming_rate: state.streaming_rate,
+            verified: state.verified,
+            nonce: state.nonce,
+            last_ubi_claim: state.last_ubi_claim,
+            path: path.iter()
+                .map(|(hash, is_right)| ProofStep {
+                    hash: root_hex(Some(*hash)),
+                    position: if *is_right { SiblingPosition::Right } else { SiblingPosition::Left },
+                })
+                .collect(),
+        }
+    }
+
+    /// Checks the proof locally, without asking a node
+    ///
+    /// # Returns
+    /// Whether the proof holds and the root it leads to, or an error if a hash is malformed
+    pub fn verify(&self) -> Result<ProofCheck, String> {
+        let root = parse_hash(&self.root)?;
+        let path = self.path.iter()
+            .map(|step| Ok((parse_hash(&step.hash)?, step.position == SiblingPosition::Right)))
+            .collect::<Result<Vec<_>, String>>()?;
+        let state = AccountState {
+            base_balance: self.balance,
+            last_update: self.last_update,
+            streaming_rate: self.streaming_rate,
+            verified: self.verified,
+            nonce: self.nonce,
+            last_ubi_claim: self.last_ubi_claim,
+        };
+        let computed = MerkleTree::proof_root(&self.address.to_lowercase(), &state, &path);
+        Ok(ProofCheck { valid: computed == root, computed_root: root_hex(Some(computed)) })
+    }
+}
+
+/// Parses a 0x-prefixed 32-byte hex hash
+pub fn parse_hash(text: &str) -> Result<[u8; 32], String> {
+    let invalid = || format!("Invalid hash: {} (expected 0x followed by 64 hex digits)", text);
+    let digits = text.strip_prefix("0x").ok_or_else(invalid)?;
+    if digits.len() != 64 || !digits.is_ascii() {
+        return Err(invalid());
+    }
+    let mut bytes = [0u8; 32];
+    for (index, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&digits[2 * index..2 * index + 2], 16).map_err(|_| invalid())?;
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(index: u64) -> AccountState {
+        AccountState { base_balance: 100 * index, last_update: 1_000 + index, nonce: index, ..Default::default() }
+    }
+
+    #[test]
+    fn test_proofs_round_trip_through_json_for_any_tree_size() {
+        for size in 1..=9u64 {
+            let addresses: Vec<String> = (1..=size).map(|index| format!("0x{:040x}", index * 7919)).collect();
+            let mut tree = MerkleTree::new();
+            tree.update_accounts(addresses.iter().enumerate().map(|(index, address)| (address.as_str(), state(index as u64))));
+            let root = tree.root_hash().unwrap();
+
+            for (index, address) in addresses.iter().enumerate() {
+                let path = tree.generate_proof(address).unwrap();
+                let proof = AccountProof::new(root, address, &state(index as u64), &path);
+                let json = serde_json::to_string(&proof).unwrap();
+                let decoded: AccountProof = serde_json::from_str(&json).unwrap();
+                assert_eq!(decoded.verify().unwrap(), ProofCheck { valid: true, computed_root: proof.root.clone() },
+                           "leaf {} of {}", index, size);
+
+                let mut tampered = decoded;
+                tampered.balance += 1;
+                assert!(!tampered.verify().unwrap().valid);
+            }
+        }
+    }
+
+    #[test]
+    fn test_proof_path_json_encoding() {
+        let proof = AccountProof::new([1; 32], "0xABCDEF0123456789ABCDEF0123456789ABCDEF01", &state(1), &[([2; 32], true), ([3; 32], false)]);
+        let json = serde_json::to_value(&proof).unwrap();
+        assert_eq!(json["address"], "0xabcdef0123456789abcdef0123456789abcdef01");
+        assert_eq!(json["path"][0]["hash"], format!("0x{}", "02".repeat(32)));
+        assert_eq!(json["path"][0]["position"], "right");
+        assert_eq!(json["path"][1]["position"], "left");
+
+        let mut malformed = proof;
+        malformed.path[0].hash = "0x1234".to_string();
+        assert!(malformed.verify().unwrap_err().contains("Invalid hash"));
+    }
+}
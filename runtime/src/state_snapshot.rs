@@ -0,0 +1,339 @@
+//! State Snapshots
+//!
+//! Checkpoints are compact binary files. Auditors and communities forking a
+//! network need the same state in a form they can read and diff, so a
+//! checkpoint can be rendered as a JSON state snapshot and a snapshot turned
+//! back into a checkpoint.
+//!
+//! Snapshots are deterministic: accounts are sorted by address, the fee
+//! ledger is in sequence order and the JSON is pretty-printed with a fixed
+//! field order, so the same state always produces the same bytes. Each
+//! snapshot carries the state root of its contents (see
+//! `CheckpointData::state_root`), which is checked when it is read back.
+
+use crate::balance_lock::BalanceLock;
+use crate::checkpoint::{CheckpointAccount, CheckpointAllowance, CheckpointData};
+use crate::fee_ledger::FeeLedgerEntry;
+use crate::is_valid_eth_address;
+use crate::limits::AccountLimitState;
+use crate::payment_stream::{PaymentStream, StreamId};
+use crate::ubi_stats::{NetworkStats, UbiStats};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fmt;
+
+/// Version of the snapshot format
+///
+/// Version 2 added account nonces, which the state root hashes along with
+/// the verification flag and last UBI claim. Version 3 added payment
+/// streams, version 4 allowances, version 5 frozen accounts, version 6
+/// balance locks, version 7 auto-compounding and version 8 lifetime UBI
+/// statistics; older snapshots are still read, as holding none.
+pub const STATE_SNAPSHOT_VERSION: u32 = 8;
+
+/// Oldest snapshot version that is still read
+const MIN_STATE_SNAPSHOT_VERSION: u32 = 2;
+
+/// One account in a state snapshot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotAccount {
+    /// Lowercase account address
+    pub address: String,
+    /// Balance in UBI tokens
+    pub balance: u64,
+    /// Whether the account passed human verification
+    pub verified: bool,
+    /// When verification was last granted, in seconds since epoch
+    pub verified_at_secs: Option<u64>,
+    /// Last UBI claim, in seconds since epoch
+    pub last_ubi_claim_secs: u64,
+    /// Number of transactions the account has executed
+    pub nonce: u64,
+    /// Dividend per token value last seen by the account
+    pub last_dividend_point: u64,
+    /// Dividends owed but not yet claimed
+    pub unclaimed_dividends: u64,
+    /// Spending limits, if the account opted in
+    pub limits: Option<AccountLimitState>,
+    /// Whether the account is frozen
+    #[serde(default)]
+    pub frozen: bool,
+    /// Why the account was frozen, if a reason was given
+    #[serde(default)]
+    pub frozen_reason: Option<String>,
+    /// Tokens locked until a later time
+    #[serde(default)]
+    pub locked_balances: Vec<BalanceLock>,
+    /// Whether dividends roll into the balance when the account transacts
+    #[serde(default)]
+    pub auto_compound: bool,
+    /// Lifetime UBI received and claims made
+    #[serde(default)]
+    pub ubi_stats: UbiStats,
+}
+
+/// The complete chain state as a readable document
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    /// Snapshot format version
+    pub version: u32,
+    /// Time the state was captured, in seconds since epoch
+    pub timestamp: u64,
+    /// State root of the snapshot's accounts, hex encoded
+    pub state_root: String,
+    /// Merkle root the node recorded with the state, hex encoded
+    pub recorded_root: String,
+    /// Total supply of tokens
+    pub total_supply: u64,
+    /// Undistributed transaction fees
+    pub fee_pool: u64,
+    /// Global dividend per token value
+    pub dividend_per_token: u64,
+    /// Every account, sorted by address
+    pub accounts: Vec<SnapshotAccount>,
+    /// Every change to the fee pool, oldest first
+    pub fee_ledger: Vec<FeeLedgerEntry>,
+    /// Open payment streams, by id
+    #[serde(default)]
+    pub streams: Vec<PaymentStream>,
+    /// Id the next payment stream opened gets
+    #[serde(default)]
+    pub next_stream_id: StreamId,
+    /// Allowances left to spend, by owner and spender
+    #[serde(default)]
+    pub allowances: Vec<CheckpointAllowance>,
+    /// Lifetime network totals
+    #[serde(default)]
+    pub network_stats: NetworkStats,
+}
+
+/// Reason a state snapshot was refused
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateSnapshotError {
+    /// The document is not a valid snapshot
+    Parse(String),
+    /// The snapshot was written in an unknown format version
+    UnsupportedVersion(u32),
+    /// A root is not 32 hex-encoded bytes
+    InvalidRoot(String),
+    /// An address is not a well-formed Ethereum address
+    InvalidAddress(String),
+    /// The same address appears more than once
+    DuplicateAccount(String),
+    /// The state root does not match the accounts
+    RootMismatch {
+        /// Root stated in the snapshot
+        stated: String,
+        /// Root computed from the accounts
+        computed: String,
+    },
+}
+
+impl fmt::Display for StateSnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateSnapshotError::Parse(e) => write!(f, "Invalid state snapshot: {}", e),
+            StateSnapshotError::UnsupportedVersion(version) => write!(f, "Unsupported state snapshot version {}", version),
+            StateSnapshotError::InvalidRoot(root) => write!(f, "Invalid root in state snapshot: {}", root),
+            StateSnapshotError::InvalidAddress(address) => write!(f, "Invalid account address {:?} in state snapshot", address),
+            StateSnapshotError::DuplicateAccount(address) => write!(f, "Account {} appears more than once", address),
+            StateSnapshotError::RootMismatch { stated, computed } => {
+                write!(f, "State root mismatch: snapshot states {}, accounts give {}", stated, computed)
+            },
+        }
+    }
+}
+
+impl std::error::Error for StateSnapshotError {}
+
+impl StateSnapshot {
+    /// Renders checkpoint contents as a snapshot
+    ///
+    /// # Arguments
+    /// * `data` - Contents of a checkpoint
+    pub fn from_checkpoint(data: &CheckpointData) -> Self {
+        let mut accounts: Vec<SnapshotAccount> = data.accounts.iter()
+            .map(|account| SnapshotAccount {
+                address: account.address.to_lowercase(),
+                balance: account.balance,
+                verified: account.verified,
+                verified_at_secs: account.verified_at_secs,
+                last_ubi_claim_secs: account.last_ubi_claim_secs,
+                nonce: account.nonce,
+                last_dividend_point: account.last_dividend_point,
+                unclaimed_dividends: account.unclaimed_dividends,
+                limits: account.limits.clone(),
+                frozen: account.frozen,
+                frozen_reason: account.frozen_reason.clone(),
+                locked_balances: account.locks.clone(),
+                auto_compound: account.auto_compound,
+                ubi_stats: account.ubi_stats,
+            })
+            .collect();
+        accounts.sort_by(|a, b| a.address.cmp(&b.address));
+
+        let mut fee_ledger = data.fee_ledger.clone();
+        fee_ledger.sort_by_key(|entry| entry.sequence);
+        let mut streams = data.streams.clone();
+        streams.sort_by_key(|stream| stream.id);
+        let mut allowances = data.allowances.clone();
+        allowances.sort_by(|a, b| (&a.owner, &a.spender).cmp(&(&b.owner, &b.spender)));
+
+        StateSnapshot {
+            version: STATE_SNAPSHOT_VERSION,
+            timestamp: data.timestamp,
+            state_root: to_hex(&data.state_root()),
+            recorded_root: to_hex(&data.root_hash),
+            total_supply: data.total_supply,
+            fee_pool: data.fee_pool,
+            dividend_per_token: data.dividend_per_token,
+            accounts,
+            fee_ledger,
+            streams,
+            next_stream_id: data.next_stream_id,
+            allowances,
+            network_stats: data.network_stats,
+        }
+    }
+
+    /// Turns the snapshot back into checkpoint contents, checking its addresses and state root
+    ///
+    /// # Returns
+    /// The checkpoint contents, or why the snapshot was refused
+    pub fn to_checkpoint(&self) -> Result<CheckpointData, StateSnapshotError> {
+        if !(MIN_STATE_SNAPSHOT_VERSION..=STATE_SNAPSHOT_VERSION).contains(&self.version) {
+            return Err(StateSnapshotError::UnsupportedVersion(self.version));
+        }
+
+        let mut seen = HashSet::with_capacity(self.accounts.len());
+        let mut accounts: Vec<CheckpointAccount> = Vec::with_capacity(self.accounts.len());
+        for account in &self.accounts {
+            let address = account.address.to_lowercase();
+            if !is_valid_eth_address(&address) {
+                return Err(StateSnapshotError::InvalidAddress(account.address.clone()));
+            }
+            if !seen.insert(address.clone()) {
+                return Err(StateSnapshotError::DuplicateAccount(address));
+            }
+            accounts.push(CheckpointAccount {
+                address,
+                balance: account.balance,
+                verified: account.verified,
+                last_ubi_claim_secs: account.last_ubi_claim_secs,
+                last_dividend_point: account.last_dividend_point,
+                unclaimed_dividends: account.unclaimed_dividends,
+                limits: account.limits.clone(),
+                verified_at_secs: account.verified_at_secs,
+                nonce: account.nonce,
+                frozen: account.frozen,
+                frozen_reason: account.frozen_reason.clone(),
+                locks: account.locked_balances.clone(),
+                auto_compound: account.auto_compound,
+                ubi_stats: account.ubi_stats,
+            });
+        }
+        let parties = self.streams.iter().flat_map(|stream| [&stream.from, &stream.to])
+            .chain(self.allowances.iter().flat_map(|allowance| [&allowance.owner, &allowance.spender]));
+        if let Some(address) = parties.into_iter().find(|address| !is_valid_eth_address(address)) {
+            return Err(StateSnapshotError::InvalidAddress(address.clone()));
+        }
+
+        let data = CheckpointData {
+            timestamp: self.timestamp,
+            root_hash: from_hex(&self.recorded_root)?,
+            total_supply: self.total_supply,
+            fee_pool: self.fee_pool,
+            dividend_per_token: self.dividend_per_token,
+            accounts,
+            fee_ledger: self.fee_ledger.clone(),
+            streams: self.streams.clone(),
+            next_stream_id: self.next_stream_id,
+            allowances: self.allowances.clone(),
+            network_stats: self.network_stats,
+        };
+
+        let stated = from_hex(&self.state_root)?;
+        let computed = data.state_root();
+        if stated != computed {
+            return Err(StateSnapshotError::RootMismatch {
+                stated: self.state_root.clone(),
+                computed: to_hex(&computed),
+            });
+        }
+
+        Ok(data)
+    }
+
+    /// Renders the snapshot as pretty-printed JSON
+    pub fn to_json(&self) -> String {
+        // Every field serializes infallibly: no maps with non-string keys, no floats
+        serde_json::to_string_pretty(self).expect("state snapshot serializes") + "\n"
+    }
+
+    /// Hashes the whole snapshot, hex encoded
+    ///
+    /// The state root covers the accounts only; this covers every field. It
+    /// is the SHA-256 of `to_json`, so it matches the hash of a snapshot file
+    /// written by `export-state`.
+    pub fn content_hash(&self) -> String {
+        to_hex(&Sha256::digest(self.to_json()).into())
+    }
+
+    /// Parses a snapshot from JSON
+    pub fn from_json(json: &str) -> Result<Self, StateSnapshotError> {
+        serde_json::from_str(json).map_err(|e| StateSnapshotError::Parse(e.to_string()))
+    }
+}
+
+fn to_hex(bytes: &[u8; 32]) -> String {
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("0x{}", hex)
+}
+
+fn from_hex(text: &str) -> Result<[u8; 32], StateSnapshotError> {
+    let invalid = || StateSnapshotError::InvalidRoot(text.to_string());
+    let digits = text.strip_prefix("0x").ok_or_else(invalid)?;
+    if digits.len() != 64 || !digits.is_ascii() {
+        return Err(invalid());
+    }
+
+    let mut bytes = [0u8; 32];
+    for (index, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&digits[2 * index..2 * index + 2], 16).map_err(|_| invalid())?;
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::{decode_any_checkpoint, encode_checkpoint};
+
+    #[test]
+    fn test_snapshot_round_trip_is_byte_identical() {
+        let (_, data) = decode_any_checkpoint(include_bytes!("../fixtures/checkpoint_v0.dat")).unwrap();
+        let json = StateSnapshot::from_checkpoint(&data).to_json();
+
+        let restored = StateSnapshot::from_json(&json).unwrap().to_checkpoint().unwrap();
+        assert_eq!(restored.state_root(), data.state_root());
+        assert_eq!(StateSnapshot::from_checkpoint(&restored).to_json(), json);
+
+        // Through the binary format as well
+        let (_, reread) = crate::checkpoint::decode_checkpoint(&encode_checkpoint(&restored)).unwrap();
+        assert_eq!(StateSnapshot::from_checkpoint(&reread).to_json(), json);
+
+        // An edited balance no longer matches the stated root
+        let mut edited = StateSnapshot::from_json(&json).unwrap();
+        edited.accounts[0].balance += 1;
+        assert!(matches!(edited.to_checkpoint(), Err(StateSnapshotError::RootMismatch { .. })));
+
+        // Addresses are checked before the root
+        let mut malformed = StateSnapshot::from_json(&json).unwrap();
+        malformed.accounts[0].address = "0x123".to_string();
+        assert!(matches!(malformed.to_checkpoint(), Err(StateSnapshotError::InvalidAddress(address)) if address == "0x123"));
+        let mut duplicated = StateSnapshot::from_json(&json).unwrap();
+        duplicated.accounts[1].address = duplicated.accounts[0].address.to_uppercase().replace("0X", "0x");
+        assert!(matches!(duplicated.to_checkpoint(), Err(StateSnapshotError::DuplicateAccount(_))));
+    }
+}
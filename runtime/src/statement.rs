@@ -0,0 +1,299 @@
+//! Account Statements
+//!
+//! A balance alone cannot show where its tokens came from. Every change to an
+//! account balance is therefore also appended to the account's statement, while
+//! the accounts lock is held, together with the balance it left:
+//! - `transfer_in` / `transfer_out`: a transfer and its counterparty; the
+//!   sender's entry also carries the fee it paid on top
+//! - `ubi`: settled UBI accrual
+//! - `dividends`: claimed dividends
+//! - `block_reward` / `block_reward_reverted`: a producer's reward, and its
+//!   clawback when the block is reverted
+//! - `burn`: tokens the account burned
+//! - `credit`: tokens minted straight to the account, such as genesis funding
+//! - `moved_in` / `moved_out`: a balance moved to a replacement address
+//! - `stream_in` / `stream_out`: tokens a payment stream moved when it was settled
+//! - `imported` / `exported`: the account moved between chains
+//! - `opening_balance`: the balance a restored checkpoint starts the statement at
+//!
+//! Because each entry carries the balance after it, a statement reconciles on
+//! its own: replaying its credits and debits from zero reaches every entry's
+//! balance. Statements are kept in memory only; restoring a checkpoint starts
+//! them again from an opening balance.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// What caused a balance change
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BalanceChangeKind {
+    /// Tokens received in a transfer
+    TransferIn {
+        /// Sender of the transfer
+        counterparty: String,
+        /// Hash of the transaction, if the transfer had one
+        tx_hash: Option<String>,
+    },
+    /// Tokens sent in a transfer; the debit includes the fee
+    TransferOut {
+        /// Recipient of the transfer
+        counterparty: String,
+        /// Fee paid on top of the amount sent
+        fee: u64,
+        /// Hash of the transaction, if the transfer had one
+        tx_hash: Option<String>,
+    },
+    /// Settled UBI accrual
+    Ubi,
+    /// Claimed dividends
+    Dividends,
+    /// Reward for producing a block
+    BlockReward {
+        /// Hash of the rewarded block
+        block_hash: String,
+    },
+    /// Reward of a reverted block, taken back
+    BlockRewardReverted {
+        /// Hash of the reverted block
+        block_hash: String,
+    },
+    /// Tokens burned by the account
+    Burn {
+        /// Hash of the transaction, if the burn had one
+        tx_hash: Option<String>,
+    },
+    /// Tokens minted straight to the account
+    Credit,
+    /// Balance moved here from a replaced address
+    MovedIn {
+        /// Address the balance was moved from
+        counterparty: String,
+    },
+    /// Balance moved to a replacement address
+    MovedOut {
+        /// Address the balance was moved to
+        counterparty: String,
+    },
+    /// Tokens received from a payment stream
+    StreamIn {
+        /// Id of the stream
+        stream_id: u64,
+        /// Account paying the stream
+        counterparty: String,
+    },
+    /// Tokens paid into a payment stream
+    StreamOut {
+        /// Id of the stream
+        stream_id: u64,
+        /// Account receiving the stream
+        counterparty: String,
+    },
+    /// Account imported from another chain
+    Imported {
+        /// Chain the account was exported from
+        source_chain_id: u64,
+    },
+    /// Account exported to another chain
+    Exported,
+    /// Balance the statement starts at after a checkpoint was restored
+    OpeningBalance,
+}
+
+impl BalanceChangeKind {
+    /// Gets the kind's name, as serialized
+    pub fn name(&self) -> &'static str {
+        match self {
+            BalanceChangeKind::TransferIn { .. } => "transfer_in",
+            BalanceChangeKind::TransferOut { .. } => "transfer_out",
+            BalanceChangeKind::Ubi => "ubi",
+            BalanceChangeKind::Dividends => "dividends",
+            BalanceChangeKind::BlockReward { .. } => "block_reward",
+            BalanceChangeKind::BlockRewardReverted { .. } => "block_reward_reverted",
+            BalanceChangeKind::Burn { .. } => "burn",
+            BalanceChangeKind::Credit => "credit",
+            BalanceChangeKind::MovedIn { .. } => "moved_in",
+            BalanceChangeKind::MovedOut { .. } => "moved_out",
+            BalanceChangeKind::StreamIn { .. } => "stream_in",
+            BalanceChangeKind::StreamOut { .. } => "stream_out",
+            BalanceChangeKind::Imported { .. } => "imported",
+            BalanceChangeKind::Exported => "exported",
+            BalanceChangeKind::OpeningBalance => "opening_balance",
+        }
+    }
+}
+
+/// One change to an account balance
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BalanceChange {
+    /// Position in the account's statement, oldest being 0
+    pub sequence: u64,
+    /// When it happened, in seconds since epoch
+    pub timestamp: u64,
+    /// What happened
+    #[serde(flatten)]
+    pub kind: BalanceChangeKind,
+    /// Tokens added to the balance
+    pub credit: u64,
+    /// Tokens taken from the balance
+    pub debit: u64,
+    /// Balance right after the change
+    pub balance_after: u64,
+}
+
+/// Balance changes of every account, in the order they happened
+#[derive(Debug, Clone, Default)]
+pub struct BalanceHistory {
+    entries: HashMap<String, Vec<BalanceChange>>,
+}
+
+impl BalanceHistory {
+    /// Appends a change to an account's statement
+    ///
+    /// Changes that neither credit nor debit anything are not recorded.
+    pub fn record(&mut self, address: &str, timestamp: u64, kind: BalanceChangeKind, credit: u64, debit: u64, balance_after: u64) {
+        if credit == 0 && debit == 0 {
+            return;
+        }
+        let entries = self.entries.entry(address.to_lowercase()).or_default();
+        entries.push(BalanceChange {
+            sequence: entries.len() as u64,
+            timestamp,
+            kind,
+            credit,
+            debit,
+            balance_after,
+        });
+    }
+
+    /// Forgets every statement and starts each account's again at its balance
+    pub fn restart<'a>(&mut self, balances: impl IntoIterator<Item = (&'a str, u64)>, timestamp: u64) {
+        self.entries.clear();
+        for (address, balance) in balances {
+            self.record(address, timestamp, BalanceChangeKind::OpeningBalance, balance, 0, balance);
+        }
+    }
+
+    /// Builds an account's statement for a time range, both ends inclusive
+    pub fn statement(&self, address: &str, from_timestamp: u64, to_timestamp: u64) -> Statement {
+        let address = address.to_lowercase();
+        let all = self.entries.get(&address).map(Vec::as_slice).unwrap_or_default();
+        let opening_balance = all.iter()
+            .take_while(|entry| entry.timestamp < from_timestamp)
+            .last()
+            .map_or(0, |entry| entry.balance_after);
+        let entries: Vec<BalanceChange> = all.iter()
+            .filter(|entry| entry.timestamp >= from_timestamp && entry.timestamp <= to_timestamp)
+            .cloned()
+            .collect();
+        let closing_balance = entries.last().map_or(opening_balance, |entry| entry.balance_after);
+        Statement {
+            address,
+            from_timestamp,
+            to_timestamp,
+            opening_balance,
+            closing_balance,
+            entries,
+        }
+    }
+}
+
+/// An account's balance changes over a time range
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Statement {
+    /// Account address, lowercase
+    pub address: String,
+    /// Start of the range, in seconds since epoch
+    pub from_timestamp: u64,
+    /// End of the range, in seconds since epoch
+    pub to_timestamp: u64,
+    /// Balance before the first change in the range
+    pub opening_balance: u64,
+    /// Balance after the last change in the range
+    pub closing_balance: u64,
+    /// Changes in the range, oldest first
+    pub entries: Vec<BalanceChange>,
+}
+
+/// Header of a statement rendered as CSV
+pub const CSV_HEADER: &str = "sequence,timestamp,kind,counterparty,tx_hash,block_hash,credit,debit,fee,balance_after";
+
+impl Statement {
+    /// Renders the entries as CSV, one row per change under `CSV_HEADER`
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(CSV_HEADER);
+        csv.push('\n');
+        for entry in &self.entries {
+            let (counterparty, tx_hash, block_hash, fee) = match &entry.kind {
+                BalanceChangeKind::TransferIn { counterparty, tx_hash } => (Some(counterparty), tx_hash.as_ref(), None, 0),
+                BalanceChangeKind::TransferOut { counterparty, fee, tx_hash } => (Some(counterparty), tx_hash.as_ref(), None, *fee),
+                BalanceChangeKind::BlockReward { block_hash }
+                | BalanceChangeKind::BlockRewardReverted { block_hash } => (None, None, Some(block_hash), 0),
+                BalanceChangeKind::Burn { tx_hash } => (None, tx_hash.as_ref(), None, 0),
+                BalanceChangeKind::MovedIn { counterparty }
+                | BalanceChangeKind::MovedOut { counterparty }
+                | BalanceChangeKind::StreamIn { counterparty, .. }
+                | BalanceChangeKind::StreamOut { counterparty, .. } => (Some(counterparty), None, None, 0),
+                _ => (None, None, None, 0),
+            };
+            let row = [
+                entry.sequence.to_string(),
+                entry.timestamp.to_string(),
+                entry.kind.name().to_string(),
+                csv_field(counterparty.map_or("", String::as_str)),
+                csv_field(tx_hash.map_or("", String::as_str)),
+                csv_field(block_hash.map_or("", String::as_str)),
+                entry.credit.to_string(),
+                entry.debit.to_string(),
+                fee.to_string(),
+                entry.balance_after.to_string(),
+            ];
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+/// Quotes a CSV field if it holds a separator, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// How a statement is exported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StatementFormat {
+    /// The statement as a JSON object
+    #[default]
+    Json,
+    /// The entries as CSV text
+    Csv,
+}
+
+impl FromStr for StatementFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(StatementFormat::Json),
+            "csv" => Ok(StatementFormat::Csv),
+            other => Err(format!("Unknown statement format: {} (expected json or csv)", other)),
+        }
+    }
+}
+
+impl fmt::Display for StatementFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StatementFormat::Json => write!(f, "json"),
+            StatementFormat::Csv => write!(f, "csv"),
+        }
+    }
+}
@@ -0,0 +1,544 @@
+//! State Storage
+//!
+//! The runtime serves reads from memory. A `StateStore` keeps a durable copy of
+//! that state, so it survives a restart without waiting for the next
+//! checkpoint. Every operation that changes accounts or the supply counters
+//! writes what it changed through to the store before returning:
+//! - account records: balance, verification, UBI and dividend settlement, limits
+//! - transaction nonces
+//! - the total supply, the fee pool and the dividend per token value
+//! - new fee ledger entries, so the supply audit still reconciles after a restart
+//! - payment streams opened, settled or closed, and the id of the next one
+//! - allowances granted or spent
+//! - the nonce of the next operations admin action, so a signed action cannot
+//!   be replayed after a restart
+//!
+//! A runtime opened on a store starts from what the store holds, the same way
+//! it would start from a checkpoint. `MemoryStore` keeps the copy in memory,
+//! for tests; `SledStore` keeps it in an embedded sled database and is what
+//! nodes use.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::balance_lock::BalanceLock;
+use crate::checkpoint::{CheckpointAccount, CheckpointAllowance, CheckpointData};
+use crate::fee_ledger::FeeLedgerEntry;
+use crate::limits::AccountLimitState;
+use crate::payment_stream::{PaymentStream, StreamId};
+use crate::ubi_stats::{NetworkStats, UbiStats};
+
+/// Key prefix of account records in a sled store
+const ACCOUNT_PREFIX: &str = "account/";
+
+/// Key prefix of transaction nonces in a sled store
+const NONCE_PREFIX: &str = "nonce/";
+
+/// Key prefix of fee ledger entries in a sled store, followed by the big-endian sequence
+const FEE_PREFIX: &str = "fee/";
+
+/// Key of the supply counters in a sled store
+const TOTALS_KEY: &str = "totals";
+
+/// Key prefix of payment streams in a sled store, followed by the big-endian stream id
+const STREAM_PREFIX: &str = "stream/";
+
+/// Key of the next payment stream id in a sled store
+const NEXT_STREAM_KEY: &str = "next_stream_id";
+
+/// Key prefix of allowances in a sled store, followed by the owner, a slash and the spender
+const ALLOWANCE_PREFIX: &str = "allowance/";
+
+/// Key of the next operations admin nonce in a sled store
+const ADMIN_NONCE_KEY: &str = "admin_nonce";
+
+/// How long opening a sled store waits for a lock its previous owner is still releasing
+const LOCK_RETRY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// File in a sled directory that sled locks while the database is open
+const SLED_DATA_FILE: &str = "db";
+
+/// One account as kept in a store; its nonce is kept apart, see `StateChanges`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredAccount {
+    /// Lowercase account address
+    pub address: String,
+    /// Balance in UBI tokens
+    pub balance: u64,
+    /// Whether the account passed human verification
+    pub verified: bool,
+    /// Last UBI claim in seconds since the UNIX epoch
+    pub last_ubi_claim_secs: u64,
+    /// When verification was last granted in seconds since the UNIX epoch
+    pub verified_at_secs: Option<u64>,
+    /// Dividend per token value the account was last settled at
+    pub last_dividend_point: u64,
+    /// Dividends owed but not yet claimed
+    pub unclaimed_dividends: u64,
+    /// Spending limits, if the account opted in
+    pub limits: Option<AccountLimitState>,
+    /// Whether the account is frozen
+    #[serde(default)]
+    pub frozen: bool,
+    /// Why the account was frozen, if a reason was given
+    #[serde(default)]
+    pub frozen_reason: Option<String>,
+    /// Tokens locked until a later time
+    #[serde(default)]
+    pub locked_balances: Vec<BalanceLock>,
+    /// Whether dividends roll into the balance when the account transacts
+    #[serde(default)]
+    pub auto_compound: bool,
+    /// Lifetime UBI received and claims made
+    #[serde(default)]
+    pub ubi_stats: UbiStats,
+}
+
+/// Supply counters and dividend totals
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredTotals {
+    /// Total supply of tokens in circulation
+    pub total_supply: u64,
+    /// Collected fees waiting to be distributed
+    pub fee_pool: u64,
+    /// Global dividend per token value
+    pub dividend_per_token: u64,
+    /// Total fees ever distributed from the pool
+    pub fees_distributed: u64,
+    /// Total UBI ever minted to accounts
+    #[serde(default)]
+    pub ubi_minted: u64,
+    /// Total transfer fees ever charged
+    #[serde(default)]
+    pub fees_collected: u64,
+}
+
+/// Everything a store holds
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StoredState {
+    /// Account records, in address order
+    pub accounts: Vec<StoredAccount>,
+    /// Nonce of each sender's next transaction
+    pub nonces: HashMap<String, u64>,
+    /// Every change to the fee pool, oldest first
+    pub fee_ledger: Vec<FeeLedgerEntry>,
+    /// Supply counters
+    pub totals: StoredTotals,
+    /// Open payment streams, by id
+    pub streams: Vec<PaymentStream>,
+    /// Id the next payment stream opened gets
+    pub next_stream_id: StreamId,
+    /// Allowances left to spend, by owner and spender
+    pub allowances: Vec<CheckpointAllowance>,
+    /// Nonce the next operations admin action must be signed with
+    pub admin_nonce: u64,
+}
+
+impl StoredState {
+    /// Converts the stored state into checkpoint data, so it is restored the way a checkpoint is
+    ///
+    /// # Arguments
+    /// * `timestamp` - Time the state is restored at, in seconds since epoch
+    pub fn into_checkpoint_data(self, timestamp: u64) -> CheckpointData {
+        let nonces = self.nonces;
+        CheckpointData {
+            timestamp,
+            root_hash: [0; 32],
+            total_supply: self.totals.total_supply,
+            fee_pool: self.totals.fee_pool,
+            dividend_per_token: self.totals.dividend_per_token,
+            accounts: self.accounts.into_iter()
+                .map(|account| CheckpointAccount {
+                    nonce: nonces.get(&account.address).copied().unwrap_or(0),
+                    address: account.address,
+                    balance: account.balance,
+                    verified: account.verified,
+                    last_ubi_claim_secs: account.last_ubi_claim_secs,
+                    last_dividend_point: account.last_dividend_point,
+                    unclaimed_dividends: account.unclaimed_dividends,
+                    limits: account.limits,
+                    verified_at_secs: account.verified_at_secs,
+                    frozen: account.frozen,
+                    frozen_reason: account.frozen_reason,
+                    locks: account.locked_balances,
+                    auto_compound: account.auto_compound,
+                    ubi_stats: account.ubi_stats,
+                })
+                .collect(),
+            fee_ledger: self.fee_ledger,
+            streams: self.streams,
+            next_stream_id: self.next_stream_id,
+            allowances: self.allowances,
+            network_stats: NetworkStats {
+                total_ubi_minted: self.totals.ubi_minted,
+                total_fees_collected: self.totals.fees_collected,
+                total_dividends_distributed: self.totals.fees_distributed,
+            },
+        }
+    }
+}
+
+/// Changes written to a store by one operation
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateChanges {
+    /// Account records written, replacing those stored
+    pub accounts: Vec<StoredAccount>,
+    /// Addresses whose records and nonces are removed
+    pub removed: Vec<String>,
+    /// Nonces written
+    ///
+    /// Written apart from the account records: nonces change while a
+    /// transaction holds the nonce lock, which account writes do not take.
+    pub nonces: Vec<(String, u64)>,
+    /// Fee ledger entries appended
+    pub fee_entries: Vec<FeeLedgerEntry>,
+    /// Supply counters, if they are written
+    pub totals: Option<StoredTotals>,
+    /// Payment streams written, replacing those stored
+    pub streams: Vec<PaymentStream>,
+    /// Ids of the payment streams removed
+    pub closed_streams: Vec<StreamId>,
+    /// Next payment stream id, if it is written
+    pub next_stream_id: Option<StreamId>,
+    /// Allowances written; one with no tokens left is removed
+    pub allowances: Vec<CheckpointAllowance>,
+    /// Next operations admin nonce, if it is written
+    pub admin_nonce: Option<u64>,
+}
+
+/// Durable copy of the runtime state
+///
+/// Implementations apply each `StateChanges` atomically: after a crash the
+/// store holds the state before or after an operation, never part of one.
+pub trait StateStore: Send + Sync + fmt::Debug {
+    /// Reads everything stored
+    ///
+    /// # Returns
+    /// The stored state, or None if nothing was ever written
+    fn load(&self) -> io::Result<Option<StoredState>>;
+
+    /// Applies the changes of one operation
+    fn write(&self, changes: &StateChanges) -> io::Result<()>;
+
+    /// Replaces everything stored with the given state
+    fn replace(&self, state: &StoredState) -> io::Result<()>;
+
+    /// Makes sure every write so far survives a crash
+    fn flush(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Store kept in memory, lost with the process
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    /// Stored state, None until the first write
+    state: Mutex<Option<MemoryState>>,
+}
+
+/// Contents of a memory store, kept in maps for cheap updates
+#[derive(Debug, Default)]
+struct MemoryState {
+    accounts: BTreeMap<String, StoredAccount>,
+    nonces: HashMap<String, u64>,
+    fee_ledger: BTreeMap<u64, FeeLedgerEntry>,
+    totals: StoredTotals,
+    streams: BTreeMap<StreamId, PaymentStream>,
+    next_stream_id: StreamId,
+    allowances: BTreeMap<(String, String), u64>,
+    admin_nonce: u64,
+}
+
+impl MemoryStore {
+    /// Creates an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl StateStore for MemoryStore {
+    fn load(&self) -> io::Result<Option<StoredState>> {
+        Ok(self.state.lock().unwrap().as_ref().map(|state| StoredState {
+            accounts: state.accounts.values().cloned().collect(),
+            nonces: state.nonces.clone(),
+            fee_ledger: state.fee_ledger.values().cloned().collect(),
+            totals: state.totals,
+            streams: state.streams.values().cloned().collect(),
+            next_stream_id: state.next_stream_id,
+            allowances: state.allowances.iter()
+                .map(|((owner, spender), amount)| CheckpointAllowance { owner: owner.clone(), spender: spender.clone(), amount: *amount })
+                .collect(),
+            admin_nonce: state.admin_nonce,
+        }))
+    }
+
+    fn write(&self, changes: &StateChanges) -> io::Result<()> {
+        let mut guard = self.state.lock().unwrap();
+        let state = guard.get_or_insert_with(MemoryState::default);
+        for address in &changes.removed {
+            state.accounts.remove(address);
+            state.nonces.remove(address);
+        }
+        for account in &changes.accounts {
+            state.accounts.insert(account.address.clone(), account.clone());
+        }
+        state.nonces.extend(changes.nonces.iter().cloned());
+        for entry in &changes.fee_entries {
+            state.fee_ledger.insert(entry.sequence, entry.clone());
+        }
+        if let Some(totals) = changes.totals {
+            state.totals = totals;
+        }
+        for id in &changes.closed_streams {
+            state.streams.remove(id);
+        }
+        for stream in &changes.streams {
+            state.streams.insert(stream.id, stream.clone());
+        }
+        if let Some(next_stream_id) = changes.next_stream_id {
+            state.next_stream_id = next_stream_id;
+        }
+        for allowance in &changes.allowances {
+            let key = (allowance.owner.clone(), allowance.spender.clone());
+            match allowance.amount {
+                0 => state.allowances.remove(&key),
+                amount => state.allowances.insert(key, amount),
+            };
+        }
+        if let Some(admin_nonce) = changes.admin_nonce {
+            state.admin_nonce = admin_nonce;
+        }
+        Ok(())
+    }
+
+    fn replace(&self, state: &StoredState) -> io::Result<()> {
+        *self.state.lock().unwrap() = Some(MemoryState {
+            accounts: state.accounts.iter().map(|account| (account.address.clone(), account.clone())).collect(),
+            nonces: state.nonces.clone(),
+            fee_ledger: state.fee_ledger.iter().map(|entry| (entry.sequence, entry.clone())).collect(),
+            totals: state.totals,
+            streams: state.streams.iter().map(|stream| (stream.id, stream.clone())).collect(),
+            next_stream_id: state.next_stream_id,
+            allowances: state.allowances.iter()
+                .map(|allowance| ((allowance.owner.clone(), allowance.spender.clone()), allowance.amount))
+                .collect(),
+            admin_nonce: state.admin_nonce,
+        });
+        Ok(())
+    }
+}
+
+/// Store kept in an embedded sled database
+///
+/// Records are JSON values under prefixed keys in one tree, so each change
+/// set is applied as a single atomic batch. Sled syncs to disk in the
+/// background every half second, when flushed and when the store is closed.
+pub struct SledStore {
+    db: sled::Db,
+}
+
+impl fmt::Debug for SledStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SledStore").field("size_on_disk", &self.db.size_on_disk().ok()).finish()
+    }
+}
+
+impl SledStore {
+    /// Opens the database in a directory, creating it if needed
+    ///
+    /// Sled's background threads can hold the database lock for a moment
+    /// after the previous store was dropped, as on a quick restart, so a
+    /// held lock is retried for up to `LOCK_RETRY_TIMEOUT`.
+    ///
+    /// # Arguments
+    /// * `path` - Directory of the database
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let deadline = Instant::now() + LOCK_RETRY_TIMEOUT;
+        loop {
+            match Self::probe_lock(path.as_ref()) {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock && Instant::now() < deadline => {
+                    std::thread::sleep(Duration::from_millis(10));
+                },
+                // Sled reports whatever else is wrong, including a lock still held at the deadline
+                _ => return Ok(SledStore { db: sled::open(path)? }),
+            }
+        }
+    }
+
+    /// Checks the database lock is free by taking and releasing it
+    ///
+    /// Sled reports a held lock as an error of kind `Other`, so the lock is
+    /// tried here first: a held lock fails with `WouldBlock`. A directory
+    /// without a database has no lock to wait for.
+    fn probe_lock(path: &Path) -> io::Result<()> {
+        let file = match File::options().read(true).write(true).open(path.join(SLED_DATA_FILE)) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        file.try_lock().map_err(io::Error::from)
+    }
+
+    /// Reads the values under a key prefix
+    fn scan<T: for<'de> Deserialize<'de>>(&self, prefix: &str) -> io::Result<Vec<T>> {
+        self.db.scan_prefix(prefix)
+            .map(|item| decode(&item?.1))
+            .collect()
+    }
+
+    /// Adds the writes of a change set to a batch
+    fn batch_changes(batch: &mut sled::Batch, changes: &StateChanges) -> io::Result<()> {
+        for address in &changes.removed {
+            batch.remove(account_key(address));
+            batch.remove(nonce_key(address));
+        }
+        for account in &changes.accounts {
+            batch.insert(account_key(&account.address), encode(account)?);
+        }
+        for (address, nonce) in &changes.nonces {
+            batch.insert(nonce_key(address), encode(nonce)?);
+        }
+        for entry in &changes.fee_entries {
+            batch.insert(fee_key(entry.sequence), encode(entry)?);
+        }
+        if let Some(totals) = &changes.totals {
+            batch.insert(TOTALS_KEY, encode(totals)?);
+        }
+        for id in &changes.closed_streams {
+            batch.remove(stream_key(*id));
+        }
+        for stream in &changes.streams {
+            batch.insert(stream_key(stream.id), encode(stream)?);
+        }
+        if let Some(next_stream_id) = &changes.next_stream_id {
+            batch.insert(NEXT_STREAM_KEY, encode(next_stream_id)?);
+        }
+        for allowance in &changes.allowances {
+            let key = allowance_key(&allowance.owner, &allowance.spender);
+            match allowance.amount {
+                0 => batch.remove(key),
+                _ => batch.insert(key, encode(allowance)?),
+            }
+        }
+        if let Some(admin_nonce) = &changes.admin_nonce {
+            batch.insert(ADMIN_NONCE_KEY, encode(admin_nonce)?);
+        }
+        Ok(())
+    }
+}
+
+impl StateStore for SledStore {
+    fn load(&self) -> io::Result<Option<StoredState>> {
+        let totals = match self.db.get(TOTALS_KEY)? {
+            Some(value) => decode(&value)?,
+            None => return Ok(None),
+        };
+
+        let mut nonces = HashMap::new();
+        for item in self.db.scan_prefix(NONCE_PREFIX) {
+            let (key, value) = item?;
+            let address = String::from_utf8_lossy(&key[NONCE_PREFIX.len()..]).into_owned();
+            nonces.insert(address, decode(&value)?);
+        }
+
+        Ok(Some(StoredState {
+            accounts: self.scan(ACCOUNT_PREFIX)?,
+            nonces,
+            fee_ledger: self.scan(FEE_PREFIX)?,
+            totals,
+            streams: self.scan(STREAM_PREFIX)?,
+            next_stream_id: match self.db.get(NEXT_STREAM_KEY)? {
+                Some(value) => decode(&value)?,
+                None => 0,
+            },
+            allowances: self.scan(ALLOWANCE_PREFIX)?,
+            admin_nonce: match self.db.get(ADMIN_NONCE_KEY)? {
+                Some(value) => decode(&value)?,
+                None => 0,
+            },
+        }))
+    }
+
+    fn write(&self, changes: &StateChanges) -> io::Result<()> {
+        let mut batch = sled::Batch::default();
+        Self::batch_changes(&mut batch, changes)?;
+        self.db.apply_batch(batch)?;
+        Ok(())
+    }
+
+    fn replace(&self, state: &StoredState) -> io::Result<()> {
+        let mut batch = sled::Batch::default();
+        for key in self.db.iter().keys() {
+            batch.remove(key?);
+        }
+        Self::batch_changes(&mut batch, &StateChanges {
+            accounts: state.accounts.clone(),
+            removed: Vec::new(),
+            nonces: state.nonces.iter().map(|(address, nonce)| (address.clone(), *nonce)).collect(),
+            fee_entries: state.fee_ledger.clone(),
+            totals: Some(state.totals),
+            streams: state.streams.clone(),
+            closed_streams: Vec::new(),
+            next_stream_id: Some(state.next_stream_id),
+            allowances: state.allowances.clone(),
+            admin_nonce: Some(state.admin_nonce),
+        })?;
+        self.db.apply_batch(batch)?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.db.flush()?;
+        Ok(())
+    }
+}
+
+/// Writes still buffered are flushed when the store closes
+impl Drop for SledStore {
+    fn drop(&mut self) {
+        if let Err(e) = self.db.flush() {
+            log::error!("Failed to flush the state store on close: {}", e);
+        }
+    }
+}
+
+fn account_key(address: &str) -> Vec<u8> {
+    format!("{}{}", ACCOUNT_PREFIX, address).into_bytes()
+}
+
+fn nonce_key(address: &str) -> Vec<u8> {
+    format!("{}{}", NONCE_PREFIX, address).into_bytes()
+}
+
+/// Fee keys end in the big-endian sequence, so a prefix scan reads the ledger in order
+fn fee_key(sequence: u64) -> Vec<u8> {
+    let mut key = FEE_PREFIX.as_bytes().to_vec();
+    key.extend_from_slice(&sequence.to_be_bytes());
+    key
+}
+
+fn allowance_key(owner: &str, spender: &str) -> Vec<u8> {
+    format!("{}{}/{}", ALLOWANCE_PREFIX, owner, spender).into_bytes()
+}
+
+/// Stream keys end in the big-endian id, so a prefix scan reads streams oldest first
+fn stream_key(id: StreamId) -> Vec<u8> {
+    let mut key = STREAM_PREFIX.as_bytes().to_vec();
+    key.extend_from_slice(&id.to_be_bytes());
+    key
+}
+
+fn encode<T: Serialize>(value: &T) -> io::Result<Vec<u8>> {
+    serde_json::to_vec(value).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn decode<T: for<'de> Deserialize<'de>>(bytes: &[u8]) -> io::Result<T> {
+    serde_json::from_slice(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
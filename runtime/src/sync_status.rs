@@ -0,0 +1,44 @@
+//! Sync Status
+//!
+//! The node tracks what each peer has told it and sent it: the peer's best
+//! block, how many blocks it delivered and how many of those were rejected,
+//! and how fast it has been delivering. RPC clients read the aggregate and
+//! the per-peer breakdown through `BlockProducer::sync_status`.
+
+use serde::{Deserialize, Serialize};
+
+/// Sync progress of one peer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeerSyncInfo {
+    /// Identifier of the peer
+    pub peer_id: String,
+    /// Whether the peer is currently connected
+    pub connected: bool,
+    /// Highest block the peer announced or delivered
+    pub best_block: u64,
+    /// Blocks received from the peer
+    pub blocks_received: u64,
+    /// Received blocks that failed to import
+    pub invalid_blocks: u64,
+    /// Bytes received from the peer
+    pub bytes_received: u64,
+    /// Blocks per second received over the recent window
+    pub blocks_per_sec: f64,
+    /// Bytes per second received over the recent window
+    pub bytes_per_sec: f64,
+}
+
+/// Overall sync state and the per-peer breakdown
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SyncStatus {
+    /// Whether a peer is ahead of the local chain
+    pub syncing: bool,
+    /// Local height when the current sync started, or the current height when not syncing
+    pub starting_block: u64,
+    /// Local chain height
+    pub current_block: u64,
+    /// Highest height known locally or announced by a peer
+    pub highest_block: u64,
+    /// Every known peer, ordered by peer id
+    pub peers: Vec<PeerSyncInfo>,
+}
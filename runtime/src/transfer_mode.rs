@@ -0,0 +1,91 @@
+//! Transfer Modes
+//!
+//! The fee of a transfer is charged on top of its amount: the recipient is
+//! credited the amount and the sender is debited the amount plus the fee. A
+//! transfer's mode records which of the figures the caller fixed:
+//! - `Send`: the caller chose an amount to send
+//! - `ExactReceive`: the caller chose the amount the recipient must receive,
+//!   e.g. to settle an invoice, and accepts whatever the fee on it comes to
+//!
+//! Both modes move tokens the same way, so the recipient of an exact-receive
+//! transfer is credited exactly the requested amount whatever the fee rounds
+//! to. The mode travels with the transaction into its block, and receipts show
+//! the amount received, the fee and the total debited side by side.
+
+use crate::params::ChainParams;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Which figure of a transfer the caller fixed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferMode {
+    /// The amount is what the sender chose to send
+    #[default]
+    Send,
+    /// The amount is what the recipient must receive
+    ExactReceive,
+}
+
+impl TransferMode {
+    /// Checks whether this is the default mode, which serialized transactions leave out
+    pub fn is_send(&self) -> bool {
+        *self == TransferMode::Send
+    }
+}
+
+impl FromStr for TransferMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "send" => Ok(TransferMode::Send),
+            "exact-receive" | "exact_receive" => Ok(TransferMode::ExactReceive),
+            _ => Err(format!("Invalid transfer mode: {} (expected send or exact-receive)", s)),
+        }
+    }
+}
+
+impl fmt::Display for TransferMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransferMode::Send => write!(f, "send"),
+            TransferMode::ExactReceive => write!(f, "exact-receive"),
+        }
+    }
+}
+
+/// What a transfer credits, charges and debits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransferAmounts {
+    /// Which figure the caller fixed
+    pub mode: TransferMode,
+    /// Amount credited to the recipient
+    pub received: u64,
+    /// Fee charged on top of the amount
+    pub fee: u64,
+    /// Amount debited from the sender: the amount received plus the fee
+    pub debited: u64,
+}
+
+impl TransferAmounts {
+    /// Computes the amounts of a transfer under the given chain parameters
+    ///
+    /// # Arguments
+    /// * `params` - Chain parameters the fee is computed under
+    /// * `mode` - Which figure the caller fixed
+    /// * `amount` - Amount the recipient receives
+    ///
+    /// # Returns
+    /// The amounts, or None if the amount plus the fee does not fit in a u64
+    pub fn compute(params: &ChainParams, mode: TransferMode, amount: u64) -> Option<Self> {
+        Self::with_fee(mode, amount, params.transfer_fee(amount))
+    }
+
+    /// Builds the amounts of a transfer that was charged the given fee
+    pub fn with_fee(mode: TransferMode, amount: u64, fee: u64) -> Option<Self> {
+        let debited = amount.checked_add(fee)?;
+        Some(TransferAmounts { mode, received: amount, fee, debited })
+    }
+}
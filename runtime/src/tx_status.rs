@@ -0,0 +1,259 @@
+//! Transaction Status
+//!
+//! Transactions submitted to the block producer's pool are executed later,
+//! when a block is produced. The status store remembers what happened to each
+//! of them: still pending, included in a block, or failed with a reason. RPC
+//! clients read it through `BlockProducer::transaction_status`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use crate::transfer_mode::TransferMode;
+use crate::{AccountError, Transaction};
+
+/// Default number of transactions the status store remembers
+pub const DEFAULT_MAX_TRACKED_TRANSACTIONS: usize = 100_000;
+
+/// Why a transaction failed during block production
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureReason {
+    /// The sender could not cover the amount plus the fee
+    InsufficientBalance,
+    /// The sender account does not exist
+    UnknownSender,
+    /// The recipient account does not exist
+    UnknownRecipient,
+    /// The transfer exceeds the sender's spending limits
+    LimitExceeded,
+    /// An address is malformed
+    InvalidAddress,
+    /// The nonce is not the sender's next one
+    InvalidNonce,
+    /// Any other error
+    Other,
+}
+
+impl FailureReason {
+    /// Classifies an error returned by `Runtime::execute_transaction`
+    ///
+    /// # Arguments
+    /// * `error` - The error
+    /// * `tx` - The transaction that failed, to tell a missing sender from a missing recipient
+    pub fn from_account_error(error: &AccountError, tx: &Transaction) -> Self {
+        match error {
+            AccountError::InvalidAddress | AccountError::ReservedAddress { .. } => FailureReason::InvalidAddress,
+            AccountError::InvalidNonce { .. } => FailureReason::InvalidNonce,
+            AccountError::InsufficientBalance { .. } => FailureReason::InsufficientBalance,
+            AccountError::AccountNotFound(address) if address.eq_ignore_ascii_case(&tx.from) => FailureReason::UnknownSender,
+            AccountError::AccountNotFound(_) => FailureReason::UnknownRecipient,
+            AccountError::AlreadyExists | AccountError::Maintenance(_) | AccountError::Overflow(_)
+            | AccountError::InsufficientAllowance { .. } | AccountError::AccountFrozen(_) => FailureReason::Other,
+            AccountError::Other(message) if message.contains("limit") => FailureReason::LimitExceeded,
+            AccountError::Other(_) => FailureReason::Other,
+        }
+    }
+}
+
+/// Details of a failed transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionFailure {
+    /// Classified reason
+    pub reason: FailureReason,
+    /// Error message from the runtime
+    pub message: String,
+    /// Block at which execution was last attempted
+    pub block_number: u64,
+    /// Number of failed attempts after the first
+    pub retry_count: u32,
+}
+
+/// Where a transaction is in its lifecycle
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum TransactionState {
+    /// Waiting in the pool
+    Pending,
+    /// Executed in a block
+    Included {
+        /// The block that executed it
+        block_number: u64,
+    },
+    /// Execution failed
+    Failed(TransactionFailure),
+}
+
+/// Status of one submitted transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionStatus {
+    /// Transaction hash
+    pub hash: String,
+    /// Sender address
+    pub from: String,
+    /// Recipient address
+    pub to: String,
+    /// Amount transferred, which the recipient receives
+    pub amount: u64,
+    /// Fee charged on top of the amount
+    #[serde(default)]
+    pub fee: u64,
+    /// Amount debited from the sender: the amount plus the fee
+    #[serde(default)]
+    pub debited: u64,
+    /// Whether the amount is what the sender sent or what the recipient must receive
+    #[serde(default)]
+    pub mode: TransferMode,
+    /// Current state
+    pub state: TransactionState,
+}
+
+/// Statuses and the order they were first recorded in
+struct StoreState {
+    statuses: HashMap<String, TransactionStatus>,
+    order: VecDeque<String>,
+}
+
+/// Bounded store of transaction statuses, forgetting the oldest first
+#[derive(Clone)]
+pub struct TransactionStatusStore {
+    max_entries: usize,
+    state: Arc<Mutex<StoreState>>,
+}
+
+impl Default for TransactionStatusStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_TRACKED_TRANSACTIONS)
+    }
+}
+
+impl TransactionStatusStore {
+    /// Creates an empty store remembering up to `max_entries` transactions
+    pub fn new(max_entries: usize) -> Self {
+        TransactionStatusStore {
+            max_entries: max_entries.max(1),
+            state: Arc::new(Mutex::new(StoreState {
+                statuses: HashMap::new(),
+                order: VecDeque::new(),
+            })),
+        }
+    }
+
+    /// Records a transaction entering the pool
+    pub fn record_pending(&self, tx: &Transaction) {
+        self.update(tx, |_| TransactionState::Pending);
+    }
+
+    /// Records a transaction executed in a block
+    pub fn record_included(&self, tx: &Transaction, block_number: u64) {
+        self.update(tx, |_| TransactionState::Included { block_number });
+    }
+
+    /// Records a failed execution attempt
+    ///
+    /// # Arguments
+    /// * `tx` - The transaction
+    /// * `error` - The error returned by the runtime
+    /// * `block_number` - The block at which execution was attempted
+    pub fn record_failure(&self, tx: &Transaction, error: &AccountError, block_number: u64) {
+        self.update(tx, |previous| {
+            let retry_count = match previous {
+                Some(TransactionState::Failed(failure)) => failure.retry_count + 1,
+                _ => 0,
+            };
+            TransactionState::Failed(TransactionFailure {
+                reason: FailureReason::from_account_error(error, tx),
+                message: error.to_string(),
+                block_number,
+                retry_count,
+            })
+        });
+    }
+
+    /// Gets the status of a transaction, if it is still remembered
+    pub fn get(&self, hash: &str) -> Option<TransactionStatus> {
+        self.state.lock().unwrap().statuses.get(&hash.to_lowercase()).cloned()
+    }
+
+    /// Sets the state of a transaction from its previous state
+    ///
+    /// The hash and addresses are stored lowercase, whatever casing the transaction carries.
+    fn update(&self, tx: &Transaction, next: impl FnOnce(Option<&TransactionState>) -> TransactionState) {
+        let hash = tx.hash.to_lowercase();
+        let mut state = self.state.lock().unwrap();
+
+        if let Some(status) = state.statuses.get_mut(&hash) {
+            status.state = next(Some(&status.state));
+            return;
+        }
+
+        while state.statuses.len() >= self.max_entries {
+            match state.order.pop_front() {
+                Some(oldest) => {
+                    state.statuses.remove(&oldest);
+                },
+                None => break,
+            }
+        }
+
+        state.statuses.insert(hash.clone(), TransactionStatus {
+            hash: hash.clone(),
+            from: tx.from.to_lowercase(),
+            to: tx.to.to_lowercase(),
+            amount: tx.amount,
+            fee: tx.fee,
+            debited: tx.amount.saturating_add(tx.fee),
+            mode: tx.mode,
+            state: next(None),
+        });
+        state.order.push_back(hash);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transaction(hash: &str) -> Transaction {
+        Transaction {
+            hash: hash.to_string(),
+            from: "0x1111111111111111111111111111111111111111".to_string(),
+            to: "0x2222222222222222222222222222222222222222".to_string(),
+            amount: 100,
+            fee: 1,
+            timestamp: 0,
+            mode: TransferMode::Send,
+            nonce: None,
+        }
+    }
+
+    #[test]
+    fn test_failures_are_classified_and_counted() {
+        let store = TransactionStatusStore::new(2);
+        let tx = transaction("0xa");
+        store.record_pending(&tx);
+        assert_eq!(store.get("0xa").unwrap().state, TransactionState::Pending);
+
+        let error = AccountError::InsufficientBalance { required: 101, available: 50 };
+        store.record_failure(&tx, &error, 3);
+        store.record_failure(&tx, &error, 4);
+        match store.get("0xa").unwrap().state {
+            TransactionState::Failed(failure) => {
+                assert_eq!(failure.reason, FailureReason::InsufficientBalance);
+                assert_eq!(failure.block_number, 4);
+                assert_eq!(failure.retry_count, 1);
+            },
+            state => panic!("unexpected state {:?}", state),
+        }
+
+        // The oldest transaction is forgotten first
+        store.record_included(&transaction("0xb"), 4);
+        store.record_pending(&transaction("0xc"));
+        assert!(store.get("0xa").is_none());
+        assert_eq!(store.get("0xb").unwrap().state, TransactionState::Included { block_number: 4 });
+
+        let missing = |address: &str| FailureReason::from_account_error(&AccountError::AccountNotFound(address.to_string()), &tx);
+        assert_eq!(missing(&tx.from), FailureReason::UnknownSender);
+        assert_eq!(missing(&tx.to), FailureReason::UnknownRecipient);
+    }
+}
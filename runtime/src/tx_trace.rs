@@ -0,0 +1,199 @@
+//! Transaction Lifecycle Traces
+//!
+//! Finding out why a transfer vanished used to mean correlating RPC logs, pool
+//! events, block production and receipts by hand. The trace store keeps, per
+//! transaction hash, the timestamped stages the transaction went through: the
+//! RPC layer, the transaction pool, the block producer and transaction gossip
+//! each record their stage as it happens. The runtime owns the store, so every
+//! component holding a runtime shares it.
+//!
+//! The store is a bounded ring buffer: once `max_transactions` hashes are
+//! traced, the oldest trace is dropped. A transaction that keeps failing stops
+//! recording after `MAX_EVENTS_PER_TRANSACTION` events.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Default number of transactions the trace store remembers
+pub const DEFAULT_MAX_TRACED_TRANSACTIONS: usize = 10_000;
+
+/// Most events kept for one transaction
+pub const MAX_EVENTS_PER_TRANSACTION: usize = 64;
+
+/// A stage of a transaction's lifecycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LifecycleStage {
+    /// Submitted through an RPC method
+    ReceivedViaRpc,
+    /// Passed the RPC layer's address, amount and balance checks
+    Validated,
+    /// Added to the block producer's transaction pool
+    AdmittedToPool,
+    /// Broadcast to the node's transaction subscribers
+    Gossiped,
+    /// Taken from the pool for a block
+    SelectedForBlock,
+    /// Executed successfully in a block
+    Executed,
+    /// Execution in a block failed
+    ExecutionFailed,
+    /// The block holding the transaction was stored, so its receipt can be served
+    ReceiptWritten,
+}
+
+/// One timestamped stage of a transaction's lifecycle
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LifecycleEvent {
+    /// The stage reached
+    pub stage: LifecycleStage,
+    /// When it was reached, in milliseconds since epoch
+    pub timestamp_ms: u64,
+    /// The block concerned, for block stages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_number: Option<u64>,
+    /// Extra information, such as the error of a failed execution
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl LifecycleEvent {
+    /// Creates an event stamped with the current time
+    pub fn now(stage: LifecycleStage) -> Self {
+        LifecycleEvent {
+            stage,
+            timestamp_ms: current_time_millis(),
+            block_number: None,
+            detail: None,
+        }
+    }
+
+    /// Sets the block the event concerns
+    pub fn in_block(mut self, block_number: u64) -> Self {
+        self.block_number = Some(block_number);
+        self
+    }
+
+    /// Sets extra information about the event
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+}
+
+/// Traces and the order they were started in
+#[derive(Debug)]
+struct TraceState {
+    traces: HashMap<String, Vec<LifecycleEvent>>,
+    order: VecDeque<String>,
+}
+
+/// Bounded store of lifecycle traces, forgetting the oldest first
+#[derive(Debug, Clone)]
+pub struct TransactionTraceStore {
+    max_transactions: usize,
+    state: Arc<Mutex<TraceState>>,
+}
+
+impl Default for TransactionTraceStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_TRACED_TRANSACTIONS)
+    }
+}
+
+impl TransactionTraceStore {
+    /// Creates an empty store remembering up to `max_transactions` traces
+    pub fn new(max_transactions: usize) -> Self {
+        TransactionTraceStore {
+            max_transactions: max_transactions.max(1),
+            state: Arc::new(Mutex::new(TraceState {
+                traces: HashMap::new(),
+                order: VecDeque::new(),
+            })),
+        }
+    }
+
+    /// Records a stage reached now
+    pub fn record(&self, hash: &str, stage: LifecycleStage) {
+        self.record_event(hash, LifecycleEvent::now(stage));
+    }
+
+    /// Records an event for a transaction, starting its trace if needed
+    ///
+    /// # Arguments
+    /// * `hash` - The transaction hash
+    /// * `event` - The event to append
+    pub fn record_event(&self, hash: &str, event: LifecycleEvent) {
+        let hash = hash.to_lowercase();
+        let mut state = self.state.lock().unwrap();
+
+        if let Some(events) = state.traces.get_mut(&hash) {
+            if events.len() < MAX_EVENTS_PER_TRANSACTION {
+                events.push(event);
+            }
+            return;
+        }
+
+        while state.traces.len() >= self.max_transactions {
+            match state.order.pop_front() {
+                Some(oldest) => {
+                    state.traces.remove(&oldest);
+                },
+                None => break,
+            }
+        }
+
+        state.traces.insert(hash.clone(), vec![event]);
+        state.order.push_back(hash);
+    }
+
+    /// Gets the events of a transaction in the order they were recorded
+    ///
+    /// # Returns
+    /// The events, or None if the hash is unknown or its trace was dropped
+    pub fn get(&self, hash: &str) -> Option<Vec<LifecycleEvent>> {
+        self.state.lock().unwrap().traces.get(&hash.to_lowercase()).cloned()
+    }
+}
+
+/// Gets the current time in milliseconds since epoch
+fn current_time_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_traces_are_ordered_and_bounded() {
+        let store = TransactionTraceStore::new(2);
+        store.record("0xA", LifecycleStage::ReceivedViaRpc);
+        store.record("0xa", LifecycleStage::Validated);
+        store.record_event("0xa", LifecycleEvent::now(LifecycleStage::SelectedForBlock).in_block(3));
+
+        let stages: Vec<LifecycleStage> = store.get("0xa").unwrap().iter().map(|event| event.stage).collect();
+        assert_eq!(stages, vec![
+            LifecycleStage::ReceivedViaRpc,
+            LifecycleStage::Validated,
+            LifecycleStage::SelectedForBlock,
+        ]);
+        assert_eq!(store.get("0xa").unwrap()[2].block_number, Some(3));
+
+        // A trace stops growing at the per-transaction cap
+        for _ in 0..2 * MAX_EVENTS_PER_TRANSACTION {
+            store.record("0xb", LifecycleStage::ExecutionFailed);
+        }
+        assert_eq!(store.get("0xb").unwrap().len(), MAX_EVENTS_PER_TRANSACTION);
+
+        // The oldest trace is dropped first
+        store.record("0xc", LifecycleStage::ReceivedViaRpc);
+        assert!(store.get("0xa").is_none());
+        assert!(store.get("0xb").is_some());
+    }
+}
@@ -0,0 +1,133 @@
+//! UBI Mint Records
+//!
+//! UBI accrues silently between claims and is credited when the accrual is
+//! settled. So that explorers and transaction histories can explain the
+//! balance change, every settlement is recorded as a system transaction from
+//! the reserved UBI mint address to the account, with no fee. Its hash is
+//! derived from the account, the end of the settled period and the amount, so
+//! the same settlement always has the same hash.
+//!
+//! Settled mints wait until a block includes them. Blocks take at most
+//! `MAX_UBI_MINTS_PER_BLOCK` of them, oldest first; the rest carry over to the
+//! following blocks.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+
+/// Reserved address UBI mints are sent from; no account can hold it
+pub const UBI_MINT_ADDRESS: &str = "0x0000000000000000000000000000000000001001";
+
+/// Most UBI mints included in a single block
+pub const MAX_UBI_MINTS_PER_BLOCK: usize = 100;
+
+/// Checks whether an address is the UBI mint pseudo-address
+pub fn is_ubi_mint_address(address: &str) -> bool {
+    address.eq_ignore_ascii_case(UBI_MINT_ADDRESS)
+}
+
+/// Outcome of an explicit UBI claim
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClaimResult {
+    /// Tokens credited by the claim; 0 if not a full hour had accrued
+    pub claimed: u64,
+    /// The account's balance after the claim
+    pub new_balance: u64,
+}
+
+/// One settled UBI accrual, as a system transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UbiMint {
+    /// Deterministic transaction hash
+    pub hash: String,
+    /// Account credited
+    pub to: String,
+    /// Tokens credited
+    pub amount: u64,
+    /// End of the settled accrual period, in seconds since epoch
+    pub settled_at: u64,
+    /// Block that included the mint, once one has
+    pub block_number: Option<u64>,
+    /// Hash of the block that included the mint, once one has
+    pub block_hash: Option<String>,
+}
+
+impl UbiMint {
+    /// Creates a mint that no block has included yet
+    ///
+    /// # Arguments
+    /// * `to` - The account credited
+    /// * `amount` - Tokens credited
+    /// * `settled_at` - End of the settled accrual period
+    pub fn new(to: &str, amount: u64, settled_at: u64) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(b"ubi-mint");
+        hasher.update(to.to_lowercase().as_bytes());
+        hasher.update(settled_at.to_be_bytes());
+        hasher.update(amount.to_be_bytes());
+
+        let hash: String = hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect();
+
+        UbiMint {
+            hash: format!("0x{}", hash),
+            to: to.to_lowercase(),
+            amount,
+            settled_at,
+            block_number: None,
+            block_hash: None,
+        }
+    }
+}
+
+/// Every UBI mint, and which of them still wait for a block
+#[derive(Debug, Clone, Default)]
+pub struct UbiMintLog {
+    /// Mints in settlement order
+    mints: Vec<UbiMint>,
+    /// Positions in `mints` not yet included in a block, oldest first
+    pending: VecDeque<usize>,
+}
+
+impl UbiMintLog {
+    /// Records a settled mint, to be included in a coming block
+    pub fn record(&mut self, mint: UbiMint) {
+        self.pending.push_back(self.mints.len());
+        self.mints.push(mint);
+    }
+
+    /// Includes up to `limit` pending mints in a block, oldest first
+    ///
+    /// # Returns
+    /// The included mints, stamped with the block
+    pub fn include_in_block(&mut self, block_number: u64, block_hash: &str, limit: usize) -> Vec<UbiMint> {
+        let count = limit.min(self.pending.len());
+        self.pending.drain(..count)
+            .map(|index| {
+                let mint = &mut self.mints[index];
+                mint.block_number = Some(block_number);
+                mint.block_hash = Some(block_hash.to_string());
+                mint.clone()
+            })
+            .collect()
+    }
+
+    /// Gets how many mints wait for a block
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Gets the mints credited to an account, oldest first
+    pub fn for_account(&self, address: &str) -> Vec<UbiMint> {
+        self.mints.iter().filter(|mint| mint.to.eq_ignore_ascii_case(address)).cloned().collect()
+    }
+
+    /// Gets the mints included in blocks `from_block` to `to_block`, inclusive
+    pub fn included_between(&self, from_block: u64, to_block: u64) -> Vec<UbiMint> {
+        let mut included: Vec<UbiMint> = self.mints.iter()
+            .filter(|mint| mint.block_number.is_some_and(|number| (from_block..=to_block).contains(&number)))
+            .cloned()
+            .collect();
+        included.sort_by_key(|mint| mint.block_number);
+        included
+    }
+}
@@ -0,0 +1,60 @@
+//! Lifetime UBI Statistics
+//!
+//! Counters for dashboards: how much UBI each account has received and how
+//! many times it claimed, and how much UBI, fees and dividends the whole
+//! network has moved since genesis. The counters only grow. They are kept in
+//! checkpoints, the state store and state snapshots, so they survive restarts
+//! and are the same on every node.
+
+use serde::{Deserialize, Serialize};
+
+/// Lifetime UBI of one account
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UbiStats {
+    /// UBI tokens ever credited to the account, by claims and by the
+    /// settlements transfers and other balance changes make
+    pub total_ubi_received: u64,
+    /// Explicit UBI claims that credited tokens
+    pub ubi_claim_count: u64,
+}
+
+impl UbiStats {
+    /// Counts a UBI credit
+    ///
+    /// # Arguments
+    /// * `amount` - Tokens credited
+    /// * `claimed` - Whether the credit was an explicit claim
+    pub fn record(&mut self, amount: u64, claimed: bool) {
+        self.total_ubi_received = self.total_ubi_received.saturating_add(amount);
+        if claimed {
+            self.ubi_claim_count = self.ubi_claim_count.saturating_add(1);
+        }
+    }
+}
+
+/// Lifetime totals of the whole network
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkStats {
+    /// UBI tokens ever minted to accounts
+    pub total_ubi_minted: u64,
+    /// Transfer fees ever charged, the pooled and the burned part together
+    pub total_fees_collected: u64,
+    /// Fees ever distributed from the pool to token holders as dividends
+    pub total_dividends_distributed: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_explicit_claims_are_counted() {
+        let mut stats = UbiStats::default();
+        stats.record(5, true);
+        stats.record(3, false);
+        assert_eq!(stats, UbiStats { total_ubi_received: 8, ubi_claim_count: 1 });
+
+        stats.record(u64::MAX, true);
+        assert_eq!(stats, UbiStats { total_ubi_received: u64::MAX, ubi_claim_count: 2 });
+    }
+}
@@ -0,0 +1,185 @@
+//! State store persistence
+//!
+//! Opens a runtime on a sled store, changes its state, drops it and opens
+//! the same store again, as a node restarting on its data directory does.
+
+use std::path::PathBuf;
+
+use ubi_chain_runtime::genesis::{GenesisConfig, GenesisFaucet};
+use ubi_chain_runtime::{Runtime, Transaction};
+
+const ALICE: &str = "0x1111111111111111111111111111111111111111";
+const BOB: &str = "0x2222222222222222222222222222222222222222";
+const CAROL: &str = "0x3333333333333333333333333333333333333333";
+
+/// Gets a fresh store directory for a test, removed when dropped
+struct StoreDir(PathBuf);
+
+impl StoreDir {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("ubi_state_store_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        StoreDir(path)
+    }
+}
+
+impl Drop for StoreDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+#[test]
+fn test_balances_survive_reopening_the_store() {
+    let dir = StoreDir::new("reopen");
+
+    let runtime = Runtime::new().with_storage(&dir.0).unwrap();
+    for address in [ALICE, BOB, CAROL] {
+        runtime.create_account(address).unwrap();
+    }
+    runtime.verify_account(ALICE);
+    runtime.credit_balance(ALICE, 10_000).unwrap();
+    runtime.credit_balance(CAROL, 5_000).unwrap();
+    runtime.transfer_with_fee(ALICE, BOB, 1_000).unwrap();
+    runtime.execute_transaction(&Transaction {
+        hash: "0xabc".to_string(),
+        from: ALICE.to_string(),
+        to: CAROL.to_string(),
+        amount: 2_000,
+        fee: 0,
+        timestamp: 0,
+        mode: Default::default(),
+        nonce: None,
+    }).unwrap();
+    runtime.distribute_fees();
+    runtime.transfer_with_fee(CAROL, BOB, 500).unwrap();
+
+    let balances: Vec<u64> = [ALICE, BOB, CAROL].iter().map(|address| runtime.get_balance(address)).collect();
+    let unclaimed: Vec<u64> = [ALICE, BOB, CAROL].iter().map(|address| runtime.get_unclaimed_dividends(address)).collect();
+    let report = runtime.supply_report();
+    let state_root = runtime.state_root();
+    let (network_stats, ubi_stats) = (runtime.get_network_stats(), runtime.get_ubi_stats(ALICE));
+    assert!(network_stats.total_fees_collected > 0 && network_stats.total_dividends_distributed > 0);
+    drop(runtime);
+
+    let reopened = Runtime::new().with_storage(&dir.0).unwrap();
+    let reopened_balances: Vec<u64> = [ALICE, BOB, CAROL].iter().map(|address| reopened.get_balance(address)).collect();
+    let reopened_unclaimed: Vec<u64> = [ALICE, BOB, CAROL].iter().map(|address| reopened.get_unclaimed_dividends(address)).collect();
+    assert_eq!(reopened_balances, balances);
+    assert_eq!(reopened_unclaimed, unclaimed);
+    assert_eq!(reopened.supply_report(), report);
+    assert_eq!(reopened.get_nonce(ALICE), 1);
+    assert!(reopened.is_account_verified(ALICE));
+    assert_eq!(reopened.state_root(), state_root);
+    assert_eq!((reopened.get_network_stats(), reopened.get_ubi_stats(ALICE)), (network_stats, ubi_stats));
+
+    // Changes after the restart are written through as well
+    reopened.transfer_with_fee(BOB, ALICE, 100).unwrap();
+    let bob_balance = reopened.get_balance(BOB);
+    drop(reopened);
+    assert_eq!(Runtime::new().with_storage(&dir.0).unwrap().get_balance(BOB), bob_balance);
+}
+
+#[test]
+fn test_genesis_faucet_is_funded_once_across_restarts() {
+    let dir = StoreDir::new("genesis");
+    let genesis = GenesisConfig {
+        faucet: Some(GenesisFaucet {
+            address: ALICE.to_string(),
+            initial_balance: 1_000_000,
+            per_request_limit: None,
+            daily_limit: None,
+        }),
+        ..GenesisConfig::default()
+    };
+
+    let runtime = Runtime::new().with_storage(&dir.0).unwrap();
+    runtime.apply_genesis(&genesis, BOB).unwrap();
+    runtime.create_account(BOB).unwrap();
+    runtime.transfer_with_fee(ALICE, BOB, 1_000).unwrap();
+    let faucet_balance = runtime.get_balance(ALICE);
+    let total_supply = runtime.get_total_supply();
+    drop(runtime);
+
+    let restarted = Runtime::new().with_storage(&dir.0).unwrap();
+    restarted.apply_genesis(&genesis, BOB).unwrap();
+    assert_eq!(restarted.get_balance(ALICE), faucet_balance);
+    assert_eq!(restarted.get_total_supply(), total_supply);
+}
+
+#[test]
+fn test_payment_streams_survive_reopening_the_store() {
+    let dir = StoreDir::new("streams");
+
+    let runtime = Runtime::new().with_storage(&dir.0).unwrap();
+    for address in [ALICE, BOB, CAROL] {
+        runtime.create_account(address).unwrap();
+    }
+    runtime.credit_balance(ALICE, 1_000).unwrap();
+    let kept = runtime.open_stream(ALICE, BOB, 60).unwrap();
+    let closed = runtime.open_stream(ALICE, CAROL, 60).unwrap();
+    runtime.close_stream(closed).unwrap();
+    let stream = runtime.get_stream(kept).unwrap();
+    drop(runtime);
+
+    let reopened = Runtime::new().with_storage(&dir.0).unwrap();
+    assert_eq!(reopened.get_stream(kept), Some(stream));
+    assert_eq!(reopened.get_stream(closed), None);
+    assert_eq!(reopened.next_stream_id(), closed + 1);
+}
+
+#[test]
+fn test_allowances_survive_reopening_the_store() {
+    let dir = StoreDir::new("allowances");
+
+    let runtime = Runtime::new().with_storage(&dir.0).unwrap();
+    for address in [ALICE, BOB] {
+        runtime.create_account(address).unwrap();
+    }
+    runtime.credit_balance(ALICE, 1_000).unwrap();
+    runtime.approve(ALICE, CAROL, 500).unwrap();
+    runtime.approve(ALICE, BOB, 20).unwrap();
+    let fee = runtime.transfer_from(CAROL, ALICE, BOB, 100).unwrap();
+    runtime.approve(ALICE, BOB, 0).unwrap();
+    drop(runtime);
+
+    let reopened = Runtime::new().with_storage(&dir.0).unwrap();
+    assert_eq!(reopened.allowance(ALICE, CAROL), 500 - 100 - fee);
+    assert_eq!(reopened.allowance(ALICE, BOB), 0);
+}
+
+#[test]
+fn test_freezes_survive_reopening_the_store() {
+    let dir = StoreDir::new("freezes");
+
+    let runtime = Runtime::new().with_storage(&dir.0).unwrap();
+    for address in [ALICE, BOB] {
+        runtime.create_account(address).unwrap();
+    }
+    runtime.freeze_account(ALICE, Some("chargeback dispute".to_string())).unwrap();
+    runtime.freeze_account(BOB, None).unwrap();
+    runtime.unfreeze_account(BOB).unwrap();
+    drop(runtime);
+
+    let reopened = Runtime::new().with_storage(&dir.0).unwrap();
+    assert_eq!(reopened.account_freeze(ALICE), Some(Some("chargeback dispute".to_string())));
+    assert_eq!(reopened.account_freeze(BOB), None);
+}
+
+#[test]
+fn test_auto_compounding_survives_reopening_the_store() {
+    let dir = StoreDir::new("auto_compound");
+
+    let runtime = Runtime::new().with_storage(&dir.0).unwrap();
+    for address in [ALICE, BOB] {
+        runtime.create_account(address).unwrap();
+    }
+    runtime.set_auto_compound(ALICE, true).unwrap();
+    runtime.set_auto_compound(BOB, true).unwrap();
+    runtime.set_auto_compound(BOB, false).unwrap();
+    drop(runtime);
+
+    let reopened = Runtime::new().with_storage(&dir.0).unwrap();
+    assert!(reopened.is_auto_compound(ALICE));
+    assert!(!reopened.is_auto_compound(BOB));
+}